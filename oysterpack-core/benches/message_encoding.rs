@@ -24,7 +24,12 @@ extern crate serde;
 use criterion::Criterion;
 use oysterpack_core::message::*;
 
-criterion_group!(benches, encoding_benchmarks, encoding_decoding_benchmarks);
+criterion_group!(
+    benches,
+    encoding_benchmarks,
+    encoding_decoding_benchmarks,
+    dictionary_benchmarks
+);
 
 criterion_main!(benches);
 
@@ -65,26 +70,53 @@ fn encoding_decoding_benchmark(c: &mut Criterion, encoding: Encoding) {
 // - fastest combination was bincode + snappy
 //   - message pack came in a close 2nd place
 fn encoding_benchmarks(c: &mut Criterion) {
-    encoding_benchmark(c, Encoding::Bincode(None));
-    encoding_benchmark(c, Encoding::Bincode(Some(Compression::Deflate)));
-    encoding_benchmark(c, Encoding::Bincode(Some(Compression::Gzip)));
-    encoding_benchmark(c, Encoding::Bincode(Some(Compression::Zlib)));
-    encoding_benchmark(c, Encoding::Bincode(Some(Compression::Snappy)));
-    encoding_benchmark(c, Encoding::Bincode(Some(Compression::Lz4)));
+    encoding_benchmark(c, Encoding::Bincode(None, BincodeOptions::default()));
+    encoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Deflate(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
+    encoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Gzip(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
+    encoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Zlib(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
+    encoding_benchmark(
+        c,
+        Encoding::Bincode(Some(Compression::Snappy), BincodeOptions::default()),
+    );
+    encoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Lz4(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
 
     encoding_benchmark(c, Encoding::CBOR(None));
-    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Deflate)));
-    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Gzip)));
-    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Zlib)));
+    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Deflate(CompressionLevel::Fast))));
+    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Gzip(CompressionLevel::Fast))));
+    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Zlib(CompressionLevel::Fast))));
     encoding_benchmark(c, Encoding::CBOR(Some(Compression::Snappy)));
-    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Lz4)));
+    encoding_benchmark(c, Encoding::CBOR(Some(Compression::Lz4(CompressionLevel::Fast))));
 
     encoding_benchmark(c, Encoding::JSON(None));
-    encoding_benchmark(c, Encoding::JSON(Some(Compression::Deflate)));
-    encoding_benchmark(c, Encoding::JSON(Some(Compression::Gzip)));
-    encoding_benchmark(c, Encoding::JSON(Some(Compression::Zlib)));
+    encoding_benchmark(c, Encoding::JSON(Some(Compression::Deflate(CompressionLevel::Fast))));
+    encoding_benchmark(c, Encoding::JSON(Some(Compression::Gzip(CompressionLevel::Fast))));
+    encoding_benchmark(c, Encoding::JSON(Some(Compression::Zlib(CompressionLevel::Fast))));
     encoding_benchmark(c, Encoding::JSON(Some(Compression::Snappy)));
-    encoding_benchmark(c, Encoding::JSON(Some(Compression::Lz4)));
+    encoding_benchmark(c, Encoding::JSON(Some(Compression::Lz4(CompressionLevel::Fast))));
 }
 
 // - Snappy compression is the fastest
@@ -93,24 +125,81 @@ fn encoding_benchmarks(c: &mut Criterion) {
 // - fastest combination was bincode + snappy
 //   - message pack came in a close 2nd place
 fn encoding_decoding_benchmarks(c: &mut Criterion) {
-    encoding_decoding_benchmark(c, Encoding::Bincode(None));
-    encoding_decoding_benchmark(c, Encoding::Bincode(Some(Compression::Deflate)));
-    encoding_decoding_benchmark(c, Encoding::Bincode(Some(Compression::Gzip)));
-    encoding_decoding_benchmark(c, Encoding::Bincode(Some(Compression::Zlib)));
-    encoding_decoding_benchmark(c, Encoding::Bincode(Some(Compression::Snappy)));
-    encoding_decoding_benchmark(c, Encoding::Bincode(Some(Compression::Lz4)));
+    encoding_decoding_benchmark(c, Encoding::Bincode(None, BincodeOptions::default()));
+    encoding_decoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Deflate(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
+    encoding_decoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Gzip(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
+    encoding_decoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Zlib(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
+    encoding_decoding_benchmark(
+        c,
+        Encoding::Bincode(Some(Compression::Snappy), BincodeOptions::default()),
+    );
+    encoding_decoding_benchmark(
+        c,
+        Encoding::Bincode(
+            Some(Compression::Lz4(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        ),
+    );
 
     encoding_decoding_benchmark(c, Encoding::CBOR(None));
-    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Deflate)));
-    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Gzip)));
-    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Zlib)));
+    encoding_decoding_benchmark(
+        c,
+        Encoding::CBOR(Some(Compression::Deflate(CompressionLevel::Fast))),
+    );
+    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Gzip(CompressionLevel::Fast))));
+    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Zlib(CompressionLevel::Fast))));
     encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Snappy)));
-    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Lz4)));
+    encoding_decoding_benchmark(c, Encoding::CBOR(Some(Compression::Lz4(CompressionLevel::Fast))));
 
     encoding_decoding_benchmark(c, Encoding::JSON(None));
-    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Deflate)));
-    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Gzip)));
-    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Zlib)));
+    encoding_decoding_benchmark(
+        c,
+        Encoding::JSON(Some(Compression::Deflate(CompressionLevel::Fast))),
+    );
+    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Gzip(CompressionLevel::Fast))));
+    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Zlib(CompressionLevel::Fast))));
     encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Snappy)));
-    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Lz4)));
+    encoding_decoding_benchmark(c, Encoding::JSON(Some(Compression::Lz4(CompressionLevel::Fast))));
+}
+
+// measures the per message dictionary cost
+// - DeflateDictionary loads the preset dictionary into the deflate window for every message
+// - ZstdDictionary uses the dictionary that was digested when it was registered
+fn dictionary_benchmarks(c: &mut Criterion) {
+    /// `01D8B2TFGSS7JQ0GM9B4MXDCSV`
+    const DICTIONARY_ID: DictionaryId = DictionaryId(1880061260287448262642946785420948283);
+    compression_dictionary_registry()
+        .register(DICTIONARY_ID, br#"hello 1867384532653698871582487715619812439 "#)
+        .unwrap();
+
+    encoding_decoding_benchmark(
+        c,
+        Encoding::JSON(Some(Compression::Deflate(CompressionLevel::Fast))),
+    );
+    encoding_decoding_benchmark(
+        c,
+        Encoding::JSON(Some(Compression::DeflateDictionary(DICTIONARY_ID))),
+    );
+    encoding_decoding_benchmark(
+        c,
+        Encoding::JSON(Some(Compression::ZstdDictionary(DICTIONARY_ID))),
+    );
 }
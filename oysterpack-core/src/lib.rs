@@ -63,6 +63,10 @@ mod macros;
 pub mod actor;
 pub mod message;
 
+// used by the message_type_id! macro to parse ULID literals at compile time
+#[doc(hidden)]
+pub use oysterpack_uid::const_ulid_u128;
+
 #[cfg(test)]
 op_tests_mod!();
 
@@ -326,3 +326,54 @@ macro_rules! op_actor_service {
         }
     };
 }
+
+/// Constructs a [MessageTypeId](message/struct.MessageTypeId.html) from a ULID string literal,
+/// which is parsed and validated at compile time.
+/// - a malformed ULID string fails to compile, i.e., it must be 26 Crockford base32 characters
+/// - a zero id is rejected because it is reserved to mean "unset"
+///
+/// ```rust
+/// # #[macro_use] extern crate oysterpack_core;
+/// use oysterpack_core::message::MessageTypeId;
+///
+/// const MESSAGE_TYPE_ID: MessageTypeId = message_type_id!("01D882HDAHNQSE2MXX05WVM06G");
+/// # fn main() { assert_ne!(MESSAGE_TYPE_ID.0, 0); }
+/// ```
+///
+/// Invalid ULID strings fail const evaluation (E0080):
+///
+/// ```rust,compile_fail,E0080
+/// # #[macro_use] extern crate oysterpack_core;
+/// use oysterpack_core::message::MessageTypeId;
+///
+/// // 'U' is not a Crockford base32 character
+/// const MESSAGE_TYPE_ID: MessageTypeId = message_type_id!("01D882HDAHNQSE2MXX05WVM06U");
+/// # fn main() { assert_ne!(MESSAGE_TYPE_ID.0, 0); }
+/// ```
+///
+/// ```rust,compile_fail,E0080
+/// # #[macro_use] extern crate oysterpack_core;
+/// use oysterpack_core::message::MessageTypeId;
+///
+/// // a ULID is 26 characters
+/// const MESSAGE_TYPE_ID: MessageTypeId = message_type_id!("01D882HDAHNQSE2MXX05WVM06");
+/// # fn main() { assert_ne!(MESSAGE_TYPE_ID.0, 0); }
+/// ```
+///
+/// ```rust,compile_fail,E0080
+/// # #[macro_use] extern crate oysterpack_core;
+/// use oysterpack_core::message::MessageTypeId;
+///
+/// const MESSAGE_TYPE_ID: MessageTypeId = message_type_id!("00000000000000000000000000");
+/// # fn main() { assert_ne!(MESSAGE_TYPE_ID.0, 0); }
+/// ```
+#[macro_export]
+macro_rules! message_type_id {
+    ( $ulid:literal ) => {
+        $crate::message::MessageTypeId({
+            const ID: u128 = $crate::const_ulid_u128($ulid);
+            // indexing the single element array with 1 fails const evaluation when the id is zero
+            [ID][(ID == 0) as usize]
+        })
+    };
+}
@@ -0,0 +1,334 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! [proptest](https://crates.io/crates/proptest) support for property testing messages.
+//!
+//! - [Arbitrary](https://docs.rs/proptest/latest/proptest/arbitrary/trait.Arbitrary.html) is
+//!   implemented for [Metadata](../struct.Metadata.html), [Encoding](../enum.Encoding.html),
+//!   [Compression](../enum.Compression.html), [Deadline](../enum.Deadline.html),
+//!   [Sequence](../enum.Sequence.html), and [Message&lt;T&gt;](../struct.Message.html) for any
+//!   arbitrary message data type
+//! - [Payload](struct.Payload.html) is a ready made message data type, which is supported by all
+//!   encodings
+//! - [encodings()](fn.encodings.html) lists every Encoding x Compression combination
+//! - [assert_message_roundtrip()](fn.assert_message_roundtrip.html) asserts that encoding and then
+//!   decoding a message is the identity
+//!
+//! The module is compiled for tests, and is made available to other crates via the `proptest`
+//! feature.
+//!
+//! ```ignore
+//! use oysterpack_core::message::{arbitrary::*, Message};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn message_roundtrip(msg in any::<Message<Payload>>()) {
+//!         for encoding in encodings() {
+//!             assert_message_roundtrip(msg.clone(), encoding);
+//!         }
+//!     }
+//! }
+//! ```
+
+use super::{
+    compression_dictionary_registry, BincodeOptions, Compression, CompressionLevel, Deadline,
+    DictionaryId, Encoding, InstanceId, Message, MessageBytes, MessageTypeId, Metadata, Sequence,
+    SessionId,
+};
+use oysterpack_events::AttributeId;
+use oysterpack_uid::ULID;
+use proptest::{collection, prelude::*};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, sync::Once};
+
+/// DictionaryId that is used by the arbitrary [Compression::DeflateDictionary](../enum.Compression.html#variant.DeflateDictionary)
+/// and [Compression::ZstdDictionary](../enum.Compression.html#variant.ZstdDictionary):
+/// `01D8ABD5JFR5R1GMDY1WEWVHST`
+/// - the dictionary is registered with the global registry the first time a Compression strategy
+///   is created
+pub const ARBITRARY_DICTIONARY_ID: DictionaryId =
+    DictionaryId(1880031577026428328516197453874972474);
+
+const ARBITRARY_DICTIONARY: &[u8] =
+    br#"{"id":,"value":,"name":"","bytes":[],"tags":[""],"flag":truefalsenull}"#;
+
+/// Registers the dictionary that is referenced by arbitrary Compression values
+pub fn register_arbitrary_dictionary() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        compression_dictionary_registry()
+            .register(ARBITRARY_DICTIONARY_ID, ARBITRARY_DICTIONARY)
+            .unwrap();
+    });
+}
+
+/// Returns all of the compression options, including no compression
+pub fn compressions() -> Vec<Option<Compression>> {
+    register_arbitrary_dictionary();
+    vec![
+        None,
+        Some(Compression::Deflate(CompressionLevel::Fast)),
+        Some(Compression::Zlib(CompressionLevel::Fast)),
+        Some(Compression::Gzip(CompressionLevel::Fast)),
+        Some(Compression::Snappy),
+        Some(Compression::Lz4(CompressionLevel::Fast)),
+        Some(Compression::DeflateDictionary(ARBITRARY_DICTIONARY_ID)),
+        Some(Compression::ZstdDictionary(ARBITRARY_DICTIONARY_ID)),
+    ]
+}
+
+/// Returns every Encoding x Compression combination
+pub fn encodings() -> Vec<Encoding> {
+    compressions()
+        .into_iter()
+        .flat_map(|compression| {
+            vec![
+                Encoding::Bincode(compression, BincodeOptions::default()),
+                Encoding::CBOR(compression),
+                Encoding::JSON(compression),
+            ]
+        })
+        .collect()
+}
+
+/// Asserts that the message round trips using the specified encoding, i.e., the message is encoded,
+/// sent over the wire, and then decoded back into the same message.
+/// - the message metadata encoding is replaced with the specified encoding
+///
+/// ## Panics
+/// If encoding or decoding fails, or the decoded message does not match.
+pub fn assert_message_roundtrip<T>(mut msg: Message<T>, encoding: Encoding)
+where
+    T: fmt::Debug + Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    msg.metadata.encoding = encoding;
+    let encoded = msg
+        .clone()
+        .encode()
+        .unwrap_or_else(|err| panic!("{:?}: failed to encode message: {}", encoding, err));
+    let bytes = bincode::serialize(&encoded)
+        .unwrap_or_else(|err| panic!("{:?}: failed to serialize message: {}", encoding, err));
+    let encoded: Message<MessageBytes> = bincode::deserialize(&bytes)
+        .unwrap_or_else(|err| panic!("{:?}: failed to deserialize message: {}", encoding, err));
+    let decoded = encoded
+        .decode::<T>()
+        .unwrap_or_else(|err| panic!("{:?}: failed to decode message: {}", encoding, err));
+    assert_eq!(decoded.metadata(), msg.metadata(), "{:?}", encoding);
+    assert_eq!(decoded.data(), msg.data(), "{:?}", encoding);
+}
+
+/// Message data that is supported by all encodings, i.e., there is no need to hand write a message
+/// data type for round trip tests
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Payload {
+    /// id
+    pub id: u64,
+    /// signed value
+    pub value: i32,
+    /// UTF-8 text
+    pub name: String,
+    /// binary data
+    pub bytes: Vec<u8>,
+    /// list of strings
+    pub tags: Vec<String>,
+    /// optional value
+    pub flag: Option<bool>,
+}
+
+impl Arbitrary for Payload {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<u64>(),
+            any::<i32>(),
+            any::<String>(),
+            collection::vec(any::<u8>(), 0..256),
+            collection::vec(any::<String>(), 0..4),
+            any::<Option<bool>>(),
+        )
+            .prop_map(|(id, value, name, bytes, tags, flag)| Payload {
+                id,
+                value,
+                name,
+                bytes,
+                tags,
+                flag,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for CompressionLevel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(CompressionLevel::Fast),
+            Just(CompressionLevel::Default),
+            Just(CompressionLevel::Best),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Compression {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        register_arbitrary_dictionary();
+        prop_oneof![
+            any::<CompressionLevel>().prop_map(Compression::Deflate),
+            any::<CompressionLevel>().prop_map(Compression::Zlib),
+            any::<CompressionLevel>().prop_map(Compression::Gzip),
+            Just(Compression::Snappy),
+            any::<CompressionLevel>().prop_map(Compression::Lz4),
+            Just(Compression::DeflateDictionary(ARBITRARY_DICTIONARY_ID)),
+            Just(Compression::ZstdDictionary(ARBITRARY_DICTIONARY_ID)),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Encoding {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<Option<Compression>>().prop_map(Encoding::Bincode),
+            any::<Option<Compression>>().prop_map(Encoding::CBOR),
+            any::<Option<Compression>>().prop_map(Encoding::JSON),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Deadline {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<u64>().prop_map(Deadline::ProcessingTimeoutMillis),
+            any::<u64>().prop_map(Deadline::MessageTimeoutMillis),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Sequence {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<u64>().prop_map(Sequence::Strict),
+            any::<u64>().prop_map(Sequence::Loose),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Metadata {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// the instance id is generated, i.e., the message timestamp is the current time
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<u128>(),
+            any::<Encoding>(),
+            any::<Option<Deadline>>(),
+            any::<Option<u128>>(),
+            any::<u128>(),
+            any::<Option<Sequence>>(),
+            collection::vec(any::<(u128, u128)>(), 0..=Metadata::MAX_ATTRIBUTES),
+        )
+            .prop_map(
+                |(msg_type, encoding, deadline, correlation_id, session_id, sequence, attributes)| {
+                    let mut metadata =
+                        Metadata::new(MessageTypeId(msg_type).message_type(), encoding, deadline)
+                            .set_session_id(SessionId(ULID::from(session_id)));
+                    if let Some(correlation_id) = correlation_id {
+                        metadata = metadata.correlate(InstanceId(ULID::from(correlation_id)));
+                    }
+                    if let Some(sequence) = sequence {
+                        metadata = metadata.set_sequence(sequence);
+                    }
+                    // duplicate attribute ids are skipped
+                    attributes.into_iter().fold(metadata, |metadata, (id, value)| {
+                        metadata
+                            .with_attribute(AttributeId(id), ULID::from(value))
+                            .unwrap_or(metadata)
+                    })
+                },
+            )
+            .boxed()
+    }
+}
+
+impl<T> Arbitrary for Message<T>
+where
+    T: Arbitrary + fmt::Debug + Clone + Serialize + 'static,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<Metadata>(), any::<T>())
+            .prop_map(|(metadata, data)| Message::new(metadata, data))
+            .boxed()
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn message_roundtrip(msg in any::<Message<Payload>>()) {
+            for encoding in encodings() {
+                assert_message_roundtrip(msg.clone(), encoding);
+            }
+        }
+
+        #[test]
+        fn arbitrary_encoding_roundtrip(
+            msg in any::<Message<Payload>>(),
+            encoding in any::<Encoding>()
+        ) {
+            assert_message_roundtrip(msg, encoding);
+        }
+    }
+
+    #[test]
+    fn every_encoding_compression_pair() {
+        // Bincode, CBOR, JSON
+        const FORMATS: usize = 3;
+        let encodings = encodings();
+        // every format is paired with every compression, including no compression
+        assert_eq!(encodings.len(), FORMATS * compressions().len());
+        let distinct: std::collections::HashSet<Encoding> = encodings.iter().cloned().collect();
+        assert_eq!(distinct.len(), encodings.len());
+    }
+}
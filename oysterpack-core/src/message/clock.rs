@@ -0,0 +1,74 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides a pluggable clock, which enables time dependent logic, e.g., deadlines, to be tested
+//! deterministically.
+//! - [SystemClock](struct.SystemClock.html) is backed by the system wall clock and is used in production
+//! - [MockClock](struct.MockClock.html) is manually controlled and is meant to be used for testing
+
+use chrono::{DateTime, Duration, Utc};
+use std::{fmt, sync::Mutex};
+
+/// Clock abstraction
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// returns the current time according to the clock
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock that is backed by the system wall clock
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock whose time only changes when it is explicitly set or advanced
+#[derive(Debug)]
+pub struct MockClock(Mutex<DateTime<Utc>>);
+
+impl MockClock {
+    /// constructor
+    pub fn new(now: DateTime<Utc>) -> MockClock {
+        MockClock(Mutex::new(now))
+    }
+
+    /// sets the clock time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.0.lock().unwrap() = now;
+    }
+
+    /// moves the clock time forward by the specified duration
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.0.lock().unwrap();
+        *now = *now + duration;
+    }
+}
+
+impl Default for MockClock {
+    /// the clock is initialized to the current system time
+    fn default() -> MockClock {
+        MockClock::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
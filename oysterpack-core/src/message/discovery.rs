@@ -0,0 +1,182 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Service discovery messages.
+//!
+//! When a peer comes online, it advertises the services it provides. Clients use the advertisements
+//! to choose servers.
+
+use super::{IsMessage, MessageTypeId};
+use std::time::Duration;
+
+/// Service advertisement, which is published by a peer to advertise its service capacity and
+/// quality of service.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct ServiceAdvertisement {
+    capacity: usize,
+    load: u8,
+    qos_tier: QosTier,
+    latency_sla: Duration,
+    max_msg_size: usize,
+}
+
+impl IsMessage for ServiceAdvertisement {
+    /// `01D87KR6RJAEXJ55V4NY75NEQY`
+    const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1879920461001664570825826261479897854);
+}
+
+impl ServiceAdvertisement {
+    /// constructor
+    /// - load is initialized to 0
+    /// - max_msg_size is initialized to [MAX_MSG_SIZE](../constant.MAX_MSG_SIZE.html)
+    pub fn new(capacity: usize, qos_tier: QosTier, latency_sla: Duration) -> ServiceAdvertisement {
+        ServiceAdvertisement {
+            capacity,
+            load: 0,
+            qos_tier,
+            latency_sla,
+            max_msg_size: super::MAX_MSG_SIZE,
+        }
+    }
+
+    /// Constructs an advertisement using the server's current connection metrics to compute the load.
+    pub fn from_server_metrics(
+        metrics: ServerMetricsSnapshot,
+        capacity: usize,
+        qos_tier: QosTier,
+        latency_sla: Duration,
+    ) -> ServiceAdvertisement {
+        ServiceAdvertisement::new(capacity, qos_tier, latency_sla).set_load(metrics.load(capacity))
+    }
+
+    /// Max number of concurrent connections that the server supports
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Current load as a percentage of capacity, i.e., 0-100
+    pub fn load(&self) -> u8 {
+        self.load
+    }
+
+    /// Quality of service tier
+    pub fn qos_tier(&self) -> QosTier {
+        self.qos_tier
+    }
+
+    /// The latency that the server commits to for processing requests
+    pub fn latency_sla(&self) -> Duration {
+        self.latency_sla
+    }
+
+    /// The max message size that the server will accept
+    pub fn max_msg_size(&self) -> usize {
+        self.max_msg_size
+    }
+
+    /// Sets the load percentage - values greater than 100 are capped at 100
+    pub fn set_load(mut self, load: u8) -> ServiceAdvertisement {
+        self.load = std::cmp::min(load, 100);
+        self
+    }
+
+    /// Sets the max message size
+    pub fn set_max_msg_size(mut self, max_msg_size: usize) -> ServiceAdvertisement {
+        self.max_msg_size = max_msg_size;
+        self
+    }
+}
+
+/// Quality of service tier
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum QosTier {
+    /// dedicated resources
+    Premium,
+    /// shared resources
+    Standard,
+    /// requests are processed when resources are available
+    BestEffort,
+}
+
+/// Server connection metrics snapshot, i.e., the values reported by the nng server's `ServerMetrics`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash, Default)]
+pub struct ServerMetricsSnapshot {
+    /// Active number of socket connections
+    pub active_conn_count: usize,
+    /// Total number of socket connections since the server was started
+    pub tot_conn_count: usize,
+}
+
+impl ServerMetricsSnapshot {
+    /// Load as a percentage of the specified capacity, i.e., active connections relative to capacity.
+    /// - if capacity is zero, then the server is considered fully loaded
+    pub fn load(&self, capacity: usize) -> u8 {
+        if capacity == 0 {
+            return 100;
+        }
+        std::cmp::min(self.active_conn_count * 100 / capacity, 100) as u8
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{BincodeOptions, Encoding};
+    use crate::tests::run_test;
+
+    #[test]
+    fn service_advertisement_from_server_metrics() {
+        run_test("service_advertisement_from_server_metrics", || {
+            // GIVEN: a ServerMetrics snapshot
+            let metrics = ServerMetricsSnapshot {
+                active_conn_count: 25,
+                tot_conn_count: 1000,
+            };
+            // WHEN: an advertisement is constructed from the metrics
+            let advertisement = ServiceAdvertisement::from_server_metrics(
+                metrics,
+                100,
+                QosTier::Standard,
+                Duration::from_millis(50),
+            )
+            .set_max_msg_size(1024);
+            // THEN: the load is computed from active connections relative to capacity
+            assert_eq!(advertisement.load(), 25);
+            assert_eq!(advertisement.capacity(), 100);
+            assert_eq!(advertisement.max_msg_size(), 1024);
+
+            // THEN: the advertisement round trips through each Encoding
+            for encoding in &[
+                Encoding::Bincode(None, BincodeOptions::default()),
+                Encoding::CBOR(None),
+                Encoding::JSON(None),
+            ] {
+                let bytes = encoding.encode(&advertisement).unwrap();
+                let decoded: ServiceAdvertisement = encoding.decode(&bytes).unwrap();
+                assert_eq!(decoded, advertisement);
+            }
+
+            // THEN: load is capped when active connections exceed capacity
+            let metrics = ServerMetricsSnapshot {
+                active_conn_count: 200,
+                tot_conn_count: 1000,
+            };
+            assert_eq!(metrics.load(100), 100);
+            assert_eq!(metrics.load(0), 100);
+        });
+    }
+}
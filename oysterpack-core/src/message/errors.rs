@@ -16,7 +16,7 @@
 
 //! message errors
 
-use super::{Address, Encoding, SessionId};
+use super::{Address, Deadline, Encoding, SessionId};
 use sodiumoxide::crypto::{box_, sign};
 use oysterpack_errors::{ErrorMessage, Id, IsError, Level};
 use std::fmt;
@@ -46,9 +46,10 @@ impl fmt::Display for SealedEnvelopeOpenFailed<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Failed to open SealedEnvelope: {} -> {}, nonce: {}, msg.len: {}",
+            "Failed to open SealedEnvelope: {} -> {}, scheme: {}, nonce: {}, msg.len: {}",
             self.0.sender(),
             self.0.recipient(),
+            self.0.scheme(),
             crate::message::base58::encode(&self.0.nonce().0),
             self.0.msg().len()
         )
@@ -113,6 +114,48 @@ pub enum MessageError<'a> {
     MessageDataDeserializationFailed(&'a Address, ErrorInfo),
     /// The EncodedMessage serialization failed
     EncodedMessageSerializationFailed(&'a Address, ErrorInfo),
+    /// The message data deserialization exceeded the size limit, e.g., a length prefix within the
+    /// message data declared more bytes than are allowed
+    MessageDataTooLarge {
+        /// sender address
+        from: &'a Address,
+        /// max number of bytes that may be read
+        limit: usize,
+    },
+    /// The message deadline has expired
+    MessageExpired {
+        /// sender address
+        from: &'a Address,
+        /// message deadline
+        deadline: Deadline,
+    },
+    /// The sender and recipient addresses are the same
+    SelfAddressed(&'a Address),
+    /// The message size exceeds the max allowed message size
+    MessageTooLarge {
+        /// sender address
+        from: &'a Address,
+        /// message size
+        size: usize,
+        /// max message size
+        max: usize,
+    },
+    /// The encoded MessageBatch size exceeds the max allowed message size
+    MessageBatchTooLarge {
+        /// encoded batch size
+        size: usize,
+        /// max message size
+        max: usize,
+    },
+    /// The MessageBatch frame count exceeds the max number of frames allowed per batch
+    MessageBatchTooManyFrames {
+        /// number of frames
+        count: usize,
+        /// max number of frames
+        max: usize,
+    },
+    /// The client and server do not support a common message Encoding
+    NoMutualEncoding(&'a Address),
 }
 
 impl IsError for MessageError<'_> {
@@ -139,6 +182,15 @@ impl IsError for MessageError<'_> {
             MessageError::EncodedMessageSerializationFailed(_, _) => {
                 Id(1867382411073195824459596594818407224)
             } // 01CYJGZAP68TF3H847NCYE2PSR
+            MessageError::MessageDataTooLarge { .. } => Id(1880023019353529687772031976617742685), // 01D8A4N4QTMMRW769TN508E4AX
+            MessageError::MessageExpired { .. } => Id(1879894146921508120059690952532042825), // 01D86YZYDHC9Q0VM7XHMNNM929
+            MessageError::SelfAddressed(_) => Id(1879925842602548001037180534321620629), // 01D87R01ZP7R563RTPPBEB19MN
+            MessageError::MessageTooLarge { .. } => Id(1879930555393737905819142363682454478), // 01D87VQ0YFQVHABPVNKR9YGEYE
+            MessageError::MessageBatchTooLarge { .. } => Id(1880059476132149057802172578693115621), // 01D8B1DE9F3P4FFH1R2F6Z7VQ5
+            MessageError::MessageBatchTooManyFrames { .. } => {
+                Id(1880044694707560035661079023317276522)
+            } // 01D8ANR9YK8KW8PYPAB3TFBNVA
+            MessageError::NoMutualEncoding(_) => Id(1880061007612837277057969733610685857), // 01D8B2M3DA7Z4N0FVQFZFYNHD1
         }
     }
 
@@ -162,6 +214,13 @@ impl IsError for MessageError<'_> {
             MessageError::InvalidSessionId { .. } => Level::Error,
             MessageError::MessageDataDeserializationFailed(_, _) => Level::Error,
             MessageError::EncodedMessageSerializationFailed(_, _) => Level::Error,
+            MessageError::MessageDataTooLarge { .. } => Level::Error,
+            MessageError::MessageExpired { .. } => Level::Error,
+            MessageError::SelfAddressed(_) => Level::Error,
+            MessageError::MessageTooLarge { .. } => Level::Error,
+            MessageError::MessageBatchTooLarge { .. } => Level::Error,
+            MessageError::MessageBatchTooManyFrames { .. } => Level::Error,
+            MessageError::NoMutualEncoding(_) => Level::Error,
         }
     }
 }
@@ -225,6 +284,39 @@ impl fmt::Display for MessageError<'_> {
                 "Failed to serialize encoded message: {} : {}",
                 address, err_info
             ),
+            MessageError::MessageExpired { from, deadline } => {
+                write!(f, "Message has expired: {:?} - from: {}", deadline, from)
+            }
+            MessageError::SelfAddressed(address) => write!(
+                f,
+                "The sender and recipient addresses must be different: {}",
+                address
+            ),
+            MessageError::MessageDataTooLarge { from, limit } => write!(
+                f,
+                "Message data exceeds the deserialization size limit ({}) - from: {}",
+                limit, from
+            ),
+            MessageError::MessageTooLarge { from, size, max } => write!(
+                f,
+                "Message size ({}) exceeds the max message size ({}) - from: {}",
+                size, max, from
+            ),
+            MessageError::MessageBatchTooLarge { size, max } => write!(
+                f,
+                "MessageBatch size ({}) exceeds the max message size ({})",
+                size, max
+            ),
+            MessageError::MessageBatchTooManyFrames { count, max } => write!(
+                f,
+                "MessageBatch frame count ({}) exceeds the max frame count ({})",
+                count, max
+            ),
+            MessageError::NoMutualEncoding(address) => write!(
+                f,
+                "There is no mutually supported encoding - from: {}",
+                address
+            ),
         }
     }
 }
@@ -236,6 +328,8 @@ pub enum DecodingError {
     InvalidSealedEnvelope(ErrorMessage),
     /// SealedSignedMessage failed to be decoded
     InvalidSealedSignedMessage(ErrorMessage),
+    /// MessageBatch failed to be decoded
+    InvalidMessageBatch(ErrorMessage),
 }
 
 impl fmt::Display for DecodingError {
@@ -253,6 +347,18 @@ pub enum EncodingError {
     InvalidSealedEnvelope(ErrorMessage),
     /// SealedSignedMessage failed to be encoded
     InvalidSealedSignedMessage(ErrorMessage),
+    /// MessageBatch failed to be encoded
+    InvalidMessageBatch(ErrorMessage),
+    /// The encoding task was canceled before it completed, e.g., the thread pool was shutdown
+    TaskCanceled,
+    /// The message data cannot be transcoded without knowing its type, i.e., Bincode is not
+    /// self-describing
+    UntypedTranscodeNotSupported {
+        /// the message data encoding
+        from: Encoding,
+        /// the target encoding
+        to: Encoding,
+    },
 }
 
 impl fmt::Display for EncodingError {
@@ -347,6 +453,103 @@ impl fmt::Display for DeserializationError {
     }
 }
 
+/// DecompressionError
+#[derive(Debug)]
+pub struct DecompressionError {
+    encoding: Encoding,
+    err_msg: String,
+}
+
+impl DecompressionError {
+    /// Error Id(01D8ASCTBD01A4ND2K0EN9VN9M)
+    pub const ERROR_ID: Id = Id(1880049310243361926746329312621614388);
+    /// Level::Error
+    pub const ERROR_LEVEL: Level = Level::Error;
+
+    /// constructor
+    pub fn new<Msg: fmt::Display>(encoding: Encoding, err_msg: Msg) -> DecompressionError {
+        DecompressionError {
+            encoding,
+            err_msg: err_msg.to_string(),
+        }
+    }
+}
+
+impl IsError for DecompressionError {
+    fn error_id(&self) -> Id {
+        Self::ERROR_ID
+    }
+
+    fn error_level(&self) -> Level {
+        Self::ERROR_LEVEL
+    }
+}
+
+impl fmt::Display for DecompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} decompression failed: {}", self.encoding, self.err_msg)
+    }
+}
+
+/// The max number of Metadata attributes has been reached
+#[derive(Debug)]
+pub struct TooManyMetadataAttributes(pub usize);
+
+impl TooManyMetadataAttributes {
+    /// Error Id(01D8719CW5GYWW3AMQJQ5Z2DNG)
+    pub const ERROR_ID: Id = Id(1879897056646553848308213888905721520);
+    /// Level::Error
+    pub const ERROR_LEVEL: Level = Level::Error;
+}
+
+impl IsError for TooManyMetadataAttributes {
+    fn error_id(&self) -> Id {
+        Self::ERROR_ID
+    }
+
+    fn error_level(&self) -> Level {
+        Self::ERROR_LEVEL
+    }
+}
+
+impl fmt::Display for TooManyMetadataAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Max number of Metadata attributes ({}) has been reached", self.0)
+    }
+}
+
+/// The decoded Address has the wrong number of bytes
+#[derive(Debug)]
+pub struct InvalidAddressLength(pub usize);
+
+impl InvalidAddressLength {
+    /// Error Id(01D8B7VXCE02EA8XKTFQ9H2C2A)
+    pub const ERROR_ID: Id = Id(1880067655316699983477254533072105546);
+    /// Level::Error
+    pub const ERROR_LEVEL: Level = Level::Error;
+}
+
+impl IsError for InvalidAddressLength {
+    fn error_id(&self) -> Id {
+        Self::ERROR_ID
+    }
+
+    fn error_level(&self) -> Level {
+        Self::ERROR_LEVEL
+    }
+}
+
+impl fmt::Display for InvalidAddressLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid Address length: expected {} bytes, but was {} bytes",
+            Address::LEN,
+            self.0
+        )
+    }
+}
+
 /// nng:Message related error
 #[derive(Debug)]
 pub struct NngMessageError(ErrorMessage);
@@ -0,0 +1,129 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Length prefixed framing, which is shared by all framed formats, e.g., [SealedEnvelope](../struct.SealedEnvelope.html),
+//! [MessageBatch](../struct.MessageBatch.html), and message journals.
+//!
+//! A frame is: `| u32 BE length | bytes |`
+//! - frame lengths are not trusted for pre-allocation, i.e., the frame buffer grows as data is read
+//! - a truncated frame is reported as an `io::ErrorKind::UnexpectedEof` error
+
+use std::io::{self, Read, Write};
+
+/// size of the frame length prefix
+pub const LEN_PREFIX_SIZE: usize = 4;
+
+/// Writes the bytes as a length prefixed frame: `| u32 BE length | bytes |`
+/// - the frame is written with a single write to minimize the chance of interleaved partial frames
+/// - fails with `io::ErrorKind::InvalidInput` if the bytes do not fit into a frame
+pub fn write_framed<W: Write + ?Sized>(wr: &mut W, bytes: &[u8]) -> io::Result<()> {
+    if bytes.len() > u32::max_value() as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame is too large: {} bytes", bytes.len()),
+        ));
+    }
+    let mut frame = Vec::with_capacity(LEN_PREFIX_SIZE + bytes.len());
+    frame.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    frame.extend_from_slice(bytes);
+    wr.write_all(&frame)
+}
+
+/// Reads the next length prefixed frame
+/// - returns None if the stream is at the end, i.e., there are no more frames
+/// - returns an `UnexpectedEof` error if the frame is truncated
+pub fn read_framed<R: Read + ?Sized>(read: &mut R) -> io::Result<Option<Vec<u8>>> {
+    match read_frame_len(read)? {
+        Some(len) => read_frame(read, len).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads the next frame length prefix, which enables the frame length to be checked before the
+/// frame is read via [read_frame()](fn.read_frame.html)
+/// - returns None if the stream is at the end
+/// - returns an `UnexpectedEof` error if the length prefix is truncated
+pub fn read_frame_len<R: Read + ?Sized>(read: &mut R) -> io::Result<Option<usize>> {
+    let mut len = [0_u8; LEN_PREFIX_SIZE];
+    let mut header_len = 0;
+    while header_len < len.len() {
+        match read.read(&mut len[header_len..]) {
+            Ok(0) if header_len == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated frame header",
+                ))
+            }
+            Ok(n) => header_len += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(Some(u32::from_be_bytes(len) as usize))
+}
+
+/// Reads the frame bytes, i.e., the frame that follows the length prefix
+/// - returns an `UnexpectedEof` error if less than `len` bytes could be read
+pub fn read_frame<R: Read + ?Sized>(read: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut frame = Vec::new();
+    read.take(len as u64).read_to_end(&mut frame)?;
+    if frame.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "incomplete frame: expected {} bytes, but read {} bytes",
+                len,
+                frame.len()
+            ),
+        ));
+    }
+    Ok(frame)
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framed_round_trip() {
+        let mut bytes = Vec::new();
+        write_framed(&mut bytes, b"foo").unwrap();
+        write_framed(&mut bytes, b"").unwrap();
+        write_framed(&mut bytes, b"bar").unwrap();
+        assert_eq!(&bytes[..LEN_PREFIX_SIZE], &3_u32.to_be_bytes());
+
+        let mut read = &bytes[..];
+        assert_eq!(read_framed(&mut read).unwrap(), Some(b"foo".to_vec()));
+        assert_eq!(read_framed(&mut read).unwrap(), Some(Vec::new()));
+        assert_eq!(read_framed(&mut read).unwrap(), Some(b"bar".to_vec()));
+        assert_eq!(read_framed(&mut read).unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_frames() {
+        let mut bytes = Vec::new();
+        write_framed(&mut bytes, b"foo").unwrap();
+
+        // truncated frame
+        let err = read_framed(&mut &bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        // truncated length prefix
+        let err = read_framed(&mut &bytes[..2]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
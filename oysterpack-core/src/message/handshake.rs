@@ -0,0 +1,308 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Connection handshake messages.
+//!
+//! Before issuing a [ConnectAccepted](struct.ConnectAccepted.html) reply, the server verifies that the
+//! client actually controls the private signing key that it claims, i.e., the client must prove its
+//! identity and not just present a public key. This prevents address spoofing.
+//!
+//! <pre>
+//! client                                               server
+//!   | -- Connect(signing public key, encodings) -----------> |  ServerHandshake::new()
+//!   | <-------------------------- ConnectChallenge(nonce) -- |
+//!   | -- ConnectChallengeResponse(signed nonce) -----------> |  ServerHandshake::verify()
+//!   | <-- ConnectAccepted(SessionId, encodings, encoding) -- |
+//! </pre>
+//!
+//! - the `Connect` message is sent within a [SealedEnvelope](../struct.SealedEnvelope.html), which
+//!   binds the signing public key to the sender's [Address](../struct.Address.html)
+//! - the challenge nonce is randomly generated per handshake, thus responses cannot be replayed
+//! - the client signs the nonce using [SignedHash::sign()](../struct.SignedHash.html#method.sign),
+//!   and the server verifies it using [SignedHash::verify()](../struct.SignedHash.html#method.verify)
+//!
+//! ## Encoding Negotiation
+//! The client and server each list the [Encoding(s)](../enum.Encoding.html) they support, in
+//! priority order. The session uses the client's most preferred encoding that the server also
+//! supports - see [Encoding::negotiate()](../enum.Encoding.html#method.negotiate). If there is
+//! none, then the `Connect` request is rejected.
+
+use super::{
+    errors::MessageError, Address, Encoding, IsMessage, MessageTypeId, SessionId, SignedHash,
+};
+use oysterpack_errors::Error;
+use sodiumoxide::{
+    crypto::{hash, sign},
+    randombytes,
+};
+
+/// Initiates the connection handshake
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Connect {
+    signing_key: sign::PublicKey,
+    encodings: Vec<Encoding>,
+}
+
+impl IsMessage for Connect {
+    /// `01D89WAZ91W1EJ4YCCPWZX7K7M`
+    const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1880012475246672601179549994138717428);
+}
+
+impl Connect {
+    /// constructor
+    /// - signing_key is the public key the client will use to sign the ConnectChallenge nonce
+    /// - encodings are the encodings the client supports, in priority order
+    pub fn new(signing_key: sign::PublicKey, encodings: Vec<Encoding>) -> Connect {
+        Connect {
+            signing_key,
+            encodings,
+        }
+    }
+
+    /// the client's claimed signing public key
+    pub fn signing_key(&self) -> &sign::PublicKey {
+        &self.signing_key
+    }
+
+    /// the client's encoding preferences, in priority order
+    pub fn encodings(&self) -> &[Encoding] {
+        &self.encodings
+    }
+}
+
+/// The server challenges the client to sign a random nonce
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ConnectChallenge {
+    nonce: Vec<u8>,
+}
+
+impl IsMessage for ConnectChallenge {
+    /// `01D89Y8SD1B27TR3E1022MFC8Z`
+    const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1880014924046171502981545861749125407);
+}
+
+impl ConnectChallenge {
+    /// Challenge nonce length in bytes
+    pub const NONCE_LEN: usize = 32;
+
+    /// constructor - generates a random nonce
+    pub fn generate() -> ConnectChallenge {
+        ConnectChallenge {
+            nonce: randombytes::randombytes(ConnectChallenge::NONCE_LEN),
+        }
+    }
+
+    /// challenge nonce
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    /// Signs the challenge nonce using the client's private signing key
+    pub fn respond(&self, key: &sign::SecretKey) -> ConnectChallengeResponse {
+        ConnectChallengeResponse {
+            signed_hash: SignedHash::sign(&hash::hash(&self.nonce), key),
+        }
+    }
+}
+
+/// The client's signed challenge nonce
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ConnectChallengeResponse {
+    signed_hash: SignedHash,
+}
+
+impl IsMessage for ConnectChallengeResponse {
+    /// `01D89YFZT071VKN870A163R0W4`
+    const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1880015209273933187136616127128142724);
+}
+
+impl ConnectChallengeResponse {
+    /// signed hash of the challenge nonce
+    pub fn signed_hash(&self) -> &SignedHash {
+        &self.signed_hash
+    }
+}
+
+/// The server accepted the connection
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ConnectAccepted {
+    session_id: SessionId,
+    encodings: Vec<Encoding>,
+    encoding: Encoding,
+}
+
+impl IsMessage for ConnectAccepted {
+    /// `01D8A095YT1DR56MK4ZNQYFDDN`
+    const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1880017474890167021763583642379466165);
+}
+
+impl ConnectAccepted {
+    /// the SessionId that was assigned to the connection
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    /// the server's encoding preferences, in priority order
+    pub fn encodings(&self) -> &[Encoding] {
+        &self.encodings
+    }
+
+    /// the negotiated encoding, which is used to encode the messages for the session
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+}
+
+/// Server side handshake state, which is held while waiting for the client's challenge response
+#[derive(Debug, Clone)]
+pub struct ServerHandshake {
+    client: Address,
+    signing_key: sign::PublicKey,
+    challenge: ConnectChallenge,
+    encodings: Vec<Encoding>,
+    encoding: Encoding,
+    session_id: SessionId,
+}
+
+impl ServerHandshake {
+    /// Starts the handshake for the client's Connect request
+    /// - client is the sender Address of the SealedEnvelope that the Connect request was received in
+    /// - encodings are the encodings the server supports, in priority order
+    /// - returns the ConnectChallenge, which must be sent to the client
+    ///
+    /// ## Errors
+    /// - [MessageError::NoMutualEncoding](../errors/enum.MessageError.html#variant.NoMutualEncoding)
+    ///   if the client and server do not support a common encoding
+    pub fn new(
+        client: Address,
+        connect: &Connect,
+        encodings: &[Encoding],
+    ) -> Result<(ServerHandshake, ConnectChallenge), Error> {
+        let encoding = Encoding::negotiate(&connect.encodings, encodings)
+            .ok_or_else(|| op_error!(MessageError::NoMutualEncoding(&client)))?;
+        let challenge = ConnectChallenge::generate();
+        Ok((
+            ServerHandshake {
+                client,
+                signing_key: connect.signing_key,
+                challenge: challenge.clone(),
+                encodings: encodings.to_vec(),
+                encoding,
+                session_id: SessionId::generate(),
+            },
+            challenge,
+        ))
+    }
+
+    /// Binds the handshake to an existing SessionId, e.g., the SessionId that the server assigned
+    /// to the connection. By default, a new SessionId is generated.
+    pub fn set_session_id(mut self, session_id: SessionId) -> ServerHandshake {
+        self.session_id = session_id;
+        self
+    }
+
+    /// the client Address
+    pub fn client(&self) -> &Address {
+        &self.client
+    }
+
+    /// the challenge that was issued to the client
+    pub fn challenge(&self) -> &ConnectChallenge {
+        &self.challenge
+    }
+
+    /// the negotiated encoding
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Verifies the client signed the challenge nonce with the private key for its claimed signing
+    /// public key. If verified, then the connection is accepted, and is assigned the handshake
+    /// SessionId and the negotiated encoding.
+    ///
+    /// ## Errors
+    /// - [MessageError::InvalidSignature](../errors/enum.MessageError.html#variant.InvalidSignature)
+    ///   if the nonce was not signed with the client's claimed signing key
+    /// - [MessageError::ChecksumFailed](../errors/enum.MessageError.html#variant.ChecksumFailed)
+    ///   if a different nonce was signed
+    pub fn verify(self, response: &ConnectChallengeResponse) -> Result<ConnectAccepted, Error> {
+        response
+            .signed_hash
+            .verify(&self.challenge.nonce, &self.signing_key)?;
+        Ok(ConnectAccepted {
+            session_id: self.session_id,
+            encodings: self.encodings,
+            encoding: self.encoding,
+        })
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{errors::MessageError, BincodeOptions};
+    use crate::tests::run_test;
+    use oysterpack_errors::IsError;
+    use sodiumoxide::crypto::box_;
+
+    #[test]
+    fn server_handshake() {
+        run_test("server_handshake", || {
+            let (client_public_key, _) = box_::gen_keypair();
+            let client_address: Address = client_public_key.into();
+            let (signing_public_key, signing_private_key) = sign::gen_keypair();
+
+            // GIVEN: the server received a Connect request
+            let connect = Connect::new(signing_public_key, vec![Encoding::CBOR(None)]);
+            let server_encodings = [
+                Encoding::Bincode(None, BincodeOptions::default()),
+                Encoding::CBOR(None),
+            ];
+            let (handshake, challenge) =
+                ServerHandshake::new(client_address, &connect, &server_encodings).unwrap();
+            assert_eq!(challenge.nonce().len(), ConnectChallenge::NONCE_LEN);
+            assert_eq!(*handshake.client(), client_address);
+
+            // WHEN: the client signs the challenge with the wrong key
+            let (_, wrong_private_key) = sign::gen_keypair();
+            let response = challenge.respond(&wrong_private_key);
+            // THEN: the client is rejected
+            let err = handshake.clone().verify(&response).unwrap_err();
+            assert_eq!(err.id(), MessageError::InvalidSignature(&signing_public_key).error_id());
+
+            // WHEN: the client signs a different challenge with the correct key
+            let response = ConnectChallenge::generate().respond(&signing_private_key);
+            // THEN: the client is rejected
+            let err = handshake.clone().verify(&response).unwrap_err();
+            assert_eq!(err.id(), MessageError::ChecksumFailed(&signing_public_key).error_id());
+
+            // WHEN: the client signs the challenge with the correct key
+            let response = challenge.respond(&signing_private_key);
+            // THEN: the connection is accepted
+            let connect_accepted = handshake.verify(&response).unwrap();
+            info!("{:?}", connect_accepted);
+            assert_eq!(connect_accepted.encoding(), Encoding::CBOR(None));
+
+            // WHEN: the client and server do not support a common encoding
+            let connect = Connect::new(signing_public_key, vec![Encoding::JSON(None)]);
+            // THEN: the Connect request is rejected
+            let err =
+                ServerHandshake::new(client_address, &connect, &server_encodings).unwrap_err();
+            assert_eq!(err.id(), MessageError::NoMutualEncoding(&client_address).error_id());
+        });
+    }
+}
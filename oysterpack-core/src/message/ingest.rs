@@ -0,0 +1,529 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Hardened ingress for envelopes that are received from untrusted peers.
+//!
+//! [ingest_untrusted()](fn.ingest_untrusted.html) is the single entry point that takes raw bytes
+//! off the wire and returns an [EncodedMessage](../struct.EncodedMessage.html). Every step is
+//! validated against [IngestLimits](struct.IngestLimits.html) before any work is done that is
+//! proportional to what the peer claims, e.g., allocations are bounded by the actual input size and
+//! compressed payloads are inflated through a bounded stream.
+//!
+//! All failures are reported via [IngestError](enum.IngestError.html), i.e., the function is
+//! designed to never panic, which makes it suitable as a fuzz target.
+
+use super::{
+    key_exchange::KeyExchangeScheme, BincodeOptions, Compression, EncodedMessage, Encoding,
+    Message, MessageBytes, SealedEnvelope, MAX_MSG_SIZE,
+};
+use flate2::bufread;
+use oysterpack_errors::{Id, IsError, Level};
+use sodiumoxide::crypto::box_;
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+/// Limits that are enforced by [ingest_untrusted()](fn.ingest_untrusted.html)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IngestLimits {
+    max_envelope_size: usize,
+    max_msg_size: usize,
+    max_decompressed_size: usize,
+}
+
+impl IngestLimits {
+    /// The envelope framing overhead, i.e., the addresses, nonce, and lengths prefixes
+    const ENVELOPE_OVERHEAD: usize = 256;
+
+    /// Max size of the wire bytes, i.e., the encoded SealedEnvelope
+    pub fn max_envelope_size(&self) -> usize {
+        self.max_envelope_size
+    }
+
+    /// Max size of the decrypted message
+    pub fn max_msg_size(&self) -> usize {
+        self.max_msg_size
+    }
+
+    /// Max size of the message data once it is decompressed
+    pub fn max_decompressed_size(&self) -> usize {
+        self.max_decompressed_size
+    }
+
+    /// Sets the max envelope size
+    pub fn set_max_envelope_size(mut self, max_envelope_size: usize) -> IngestLimits {
+        self.max_envelope_size = max_envelope_size;
+        self
+    }
+
+    /// Sets the max message size
+    pub fn set_max_msg_size(mut self, max_msg_size: usize) -> IngestLimits {
+        self.max_msg_size = max_msg_size;
+        self
+    }
+
+    /// Sets the max decompressed message data size
+    pub fn set_max_decompressed_size(mut self, max_decompressed_size: usize) -> IngestLimits {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+}
+
+impl Default for IngestLimits {
+    /// - max_msg_size = [MAX_MSG_SIZE](../constant.MAX_MSG_SIZE.html)
+    /// - max_envelope_size = max_msg_size + envelope overhead
+    /// - max_decompressed_size = 4 * max_msg_size
+    fn default() -> IngestLimits {
+        IngestLimits {
+            max_envelope_size: MAX_MSG_SIZE + IngestLimits::ENVELOPE_OVERHEAD,
+            max_msg_size: MAX_MSG_SIZE,
+            max_decompressed_size: MAX_MSG_SIZE * 4,
+        }
+    }
+}
+
+/// Ingest errors
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IngestError {
+    /// The envelope size exceeds the max envelope size
+    EnvelopeTooLarge {
+        /// envelope size
+        size: usize,
+        /// max envelope size
+        max: usize,
+    },
+    /// The frame header is invalid, e.g., truncated or the frame length does not match
+    InvalidFrame(String),
+    /// The frame version is not supported
+    UnsupportedFrameVersion(u8),
+    /// The envelope failed to be deserialized
+    MalformedEnvelope(String),
+    /// The sender and recipient addresses are the same
+    SelfAddressed,
+    /// The envelope failed to be opened, i.e., it was not sealed by the sender for the recipient
+    AuthenticationFailed,
+    /// The decrypted message size exceeds the max message size
+    MessageTooLarge {
+        /// message size
+        size: usize,
+        /// max message size
+        max: usize,
+    },
+    /// The decrypted message failed to be deserialized
+    MalformedMessage(String),
+    /// The message data failed to be decompressed
+    DecompressionFailed(String),
+    /// The decompressed message data exceeds the max decompressed size
+    DecompressedSizeExceeded {
+        /// max decompressed size
+        max: usize,
+    },
+}
+
+impl IsError for IngestError {
+    fn error_id(&self) -> Id {
+        match self {
+            IngestError::EnvelopeTooLarge { .. } => Id(1879959890081783954997422737402268353), // 01D88JVHACZ3Z378GA7QMCN2P1
+            IngestError::InvalidFrame(_) => Id(1879963007122641051347842039905243007), // 01D88NA780QKVV5RX472J0QKVZ
+            IngestError::UnsupportedFrameVersion(_) => Id(1879966165527647977805191162968555840), // 01D88QSYJW41GA60WCJEQGMGA0
+            IngestError::MalformedEnvelope(_) => Id(1879968697581420208354720520653637552), // 01D88SSVYXXASW5F6MA9RDM1XG
+            IngestError::SelfAddressed => Id(1879971244596702455650408208568221753), // 01D88VT5DQJG116PQ5WY43201S
+            IngestError::AuthenticationFailed => Id(1879975085207632266847268073416646828), // 01D88YV3V6FB5BG84EX9GPYR5C
+            IngestError::MessageTooLarge { .. } => Id(1879979083342949450838174289401559543), // 01D89201GJG0CX1047SVF8TEFQ
+            IngestError::MalformedMessage(_) => Id(1879981958374899838875404795435247321), // 01D8948KYD62S9S7WE3DEWYSPS
+            IngestError::DecompressionFailed(_) => Id(1879984598795873604124152200740285512), // 01D896B8VP7A63V4B2DKCWAB28
+            IngestError::DecompressedSizeExceeded { .. } => {
+                Id(1879986085736831037499553775978171764)
+            } // 01D897GT06YQR9CTWGT1V3CKBM
+        }
+    }
+
+    /// anything that looks like tampering or a resource exhaustion attempt triggers an Alert
+    fn error_level(&self) -> Level {
+        match self {
+            IngestError::EnvelopeTooLarge { .. } => Level::Alert,
+            IngestError::InvalidFrame(_) => Level::Error,
+            IngestError::UnsupportedFrameVersion(_) => Level::Error,
+            IngestError::MalformedEnvelope(_) => Level::Error,
+            IngestError::SelfAddressed => Level::Error,
+            IngestError::AuthenticationFailed => Level::Alert,
+            IngestError::MessageTooLarge { .. } => Level::Alert,
+            IngestError::MalformedMessage(_) => Level::Error,
+            IngestError::DecompressionFailed(_) => Level::Error,
+            IngestError::DecompressedSizeExceeded { .. } => Level::Alert,
+        }
+    }
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IngestError::EnvelopeTooLarge { size, max } => write!(
+                f,
+                "Envelope size ({}) exceeds the max envelope size ({})",
+                size, max
+            ),
+            IngestError::InvalidFrame(err) => write!(f, "Invalid frame: {}", err),
+            IngestError::UnsupportedFrameVersion(version) => {
+                write!(f, "Unsupported frame version: {}", version)
+            }
+            IngestError::MalformedEnvelope(err) => write!(f, "Malformed envelope: {}", err),
+            IngestError::SelfAddressed => {
+                f.write_str("The sender and recipient addresses must be different")
+            }
+            IngestError::AuthenticationFailed => f.write_str("Failed to open the envelope"),
+            IngestError::MessageTooLarge { size, max } => write!(
+                f,
+                "Message size ({}) exceeds the max message size ({})",
+                size, max
+            ),
+            IngestError::MalformedMessage(err) => write!(f, "Malformed message: {}", err),
+            IngestError::DecompressionFailed(err) => write!(f, "Decompression failed: {}", err),
+            IngestError::DecompressedSizeExceeded { max } => write!(
+                f,
+                "Decompressed message data exceeds the max decompressed size ({})",
+                max
+            ),
+        }
+    }
+}
+
+/// Decodes, opens, and validates an envelope that was received from an untrusted peer.
+///
+/// The bytes must be a complete message that was received over a message oriented transport, e.g.,
+/// an nng message body - see [SealedEnvelope::decode_transport_message()](../struct.SealedEnvelope.html#method.decode_transport_message).
+///
+/// The following checks are applied in order:
+/// 1. the envelope size is checked against the max envelope size
+/// 2. the frame header is validated, i.e., magic bytes, version, and the frame length must match
+///    the input length exactly. Unframed input is supported for backward compatibility.
+/// 3. the SealedEnvelope is deserialized with a size limit, which prevents length prefixes from
+///    triggering large allocations
+/// 4. self addressed envelopes are rejected
+/// 5. the envelope is opened, which authenticates the sender
+/// 6. the decrypted message size is checked against the max message size
+/// 7. the message is deserialized with a size limit
+/// 8. if the message data is compressed, then it is inflated through a bounded stream to verify
+///    that it decompresses within the max decompressed size
+///
+/// ## Notes
+/// - the message data is not decoded, i.e., the message type is unknown at this point
+/// - the decompression guard inflates the data into a sink, i.e., the decompressed data is not
+///   retained. The data is decompressed again when the message is decoded.
+pub fn ingest_untrusted(
+    bytes: &[u8],
+    open_key: &box_::PrecomputedKey,
+    limits: &IngestLimits,
+) -> Result<EncodedMessage, IngestError> {
+    if bytes.len() > limits.max_envelope_size {
+        return Err(IngestError::EnvelopeTooLarge {
+            size: bytes.len(),
+            max: limits.max_envelope_size,
+        });
+    }
+
+    let (scheme, bytes) = unframe(bytes)?;
+    let mut envelope: SealedEnvelope = BincodeOptions::default()
+        .set_limit(bytes.len() as u64)
+        .deserialize(bytes)
+        .map_err(|err| IngestError::MalformedEnvelope(err.to_string()))?;
+    envelope.scheme = scheme;
+    if envelope.sender == envelope.recipient {
+        return Err(IngestError::SelfAddressed);
+    }
+
+    let open_envelope = envelope
+        .open(open_key)
+        .map_err(|_| IngestError::AuthenticationFailed)?;
+    if open_envelope.msg().len() > limits.max_msg_size {
+        return Err(IngestError::MessageTooLarge {
+            size: open_envelope.msg().len(),
+            max: limits.max_msg_size,
+        });
+    }
+
+    let msg: Message<MessageBytes> = BincodeOptions::default()
+        .set_limit(open_envelope.msg().len() as u64)
+        .deserialize(open_envelope.msg())
+        .map_err(|err| IngestError::MalformedMessage(err.to_string()))?;
+    if let Some(compression) = msg.metadata().encoding().compression() {
+        check_decompressed_size(compression, msg.data().data(), limits.max_decompressed_size)?;
+    }
+
+    Ok(EncodedMessage {
+        sender: open_envelope.sender,
+        recipient: open_envelope.recipient,
+        msg,
+    })
+}
+
+/// Returns the key exchange scheme and the envelope bytes after validating the frame header.
+/// - the bytes must be a complete message that was received over a message oriented transport,
+///   i.e., the frame magic is authoritative and the frame must span the whole message
+/// - if the bytes are not framed, then they are returned as is, i.e., frame version 0
+/// - version 0 and 1 envelopes use the [SodiumBox](../key_exchange/struct.SodiumBox.html) scheme
+pub(crate) fn unframe(bytes: &[u8]) -> Result<(KeyExchangeScheme, &[u8]), IngestError> {
+    if !bytes.starts_with(&SealedEnvelope::FRAME_MAGIC) {
+        return Ok((KeyExchangeScheme::SODIUM_BOX, bytes));
+    }
+    let version = bytes.get(3).cloned().unwrap_or_default();
+    // version 2 frame headers carry the scheme after the version
+    let header_len = if version == SealedEnvelope::FRAME_VERSION {
+        9
+    } else {
+        8
+    };
+    if bytes.len() < header_len {
+        return Err(IngestError::InvalidFrame(format!(
+            "truncated frame header: {} bytes",
+            bytes.len()
+        )));
+    }
+    let scheme = SealedEnvelope::frame_scheme(version, bytes[4])
+        .ok_or_else(|| IngestError::UnsupportedFrameVersion(version))?;
+    let mut len = [0_u8; 4];
+    len.copy_from_slice(&bytes[header_len - 4..header_len]);
+    let len = u32::from_be_bytes(len) as usize;
+    let frame = &bytes[header_len..];
+    if frame.len() != len {
+        return Err(IngestError::InvalidFrame(format!(
+            "frame length is {} bytes, but {} bytes were received",
+            len,
+            frame.len()
+        )));
+    }
+    Ok((scheme, frame))
+}
+
+/// Inflates the data into a sink, failing as soon as the max size is exceeded
+fn check_decompressed_size(
+    compression: Compression,
+    data: &[u8],
+    max: usize,
+) -> Result<(), IngestError> {
+    fn drain<R: Read>(read: R, max: usize) -> io::Result<u64> {
+        io::copy(&mut read.take(max as u64 + 1), &mut io::sink())
+    }
+
+    let decompressed_size = match compression {
+        Compression::Deflate(_) => drain(bufread::DeflateDecoder::new(data), max),
+        Compression::Zlib(_) => drain(bufread::ZlibDecoder::new(data), max),
+        Compression::Gzip(_) => drain(bufread::GzDecoder::new(data), max),
+        Compression::Lz4(_) => lz4::Decoder::new(data).and_then(|decoder| drain(decoder, max)),
+        Compression::DeflateDictionary(id) => {
+            super::deflate_dictionary_decoder(id, data).and_then(|decoder| drain(decoder, max))
+        }
+        Compression::ZstdDictionary(id) => {
+            super::with_zstd_dictionary_decoder(id, data, |decoder| drain(decoder, max))
+        }
+        // the snappy raw format is prefixed with the uncompressed length, which is checked before
+        // the data is decompressed
+        Compression::Snappy => super::snappy_decompressed_len(data).and_then(|len| {
+            if len > max as u64 {
+                Ok(len)
+            } else {
+                Compression::Snappy
+                    .decompress(data)
+                    .map(|data| data.len() as u64)
+            }
+        }),
+    }
+    .map_err(|err| IngestError::DecompressionFailed(err.to_string()))?;
+
+    if decompressed_size > max as u64 {
+        return Err(IngestError::DecompressedSizeExceeded { max });
+    }
+    Ok(())
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Address, CompressionLevel, Metadata, MessageTypeId, OpenEnvelope};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+    struct Foo(String);
+
+    const MESSAGE_TYPE: MessageTypeId = MessageTypeId(1879989413775719489230998204805009826);
+
+    struct Peers {
+        client: Address,
+        server: Address,
+        sealing_key: box_::PrecomputedKey,
+        opening_key: box_::PrecomputedKey,
+    }
+
+    fn peers() -> Peers {
+        let (client_pub_key, client_priv_key) = box_::gen_keypair();
+        let (server_pub_key, server_priv_key) = box_::gen_keypair();
+        let (client, server) = (Address::from(client_pub_key), Address::from(server_pub_key));
+        Peers {
+            sealing_key: server.precompute_sealing_key(&client_priv_key),
+            opening_key: client.precompute_opening_key(&server_priv_key),
+            client,
+            server,
+        }
+    }
+
+    fn sealed_bytes<T>(peers: &Peers, encoding: Encoding, data: &T) -> Vec<u8>
+    where
+        T: fmt::Debug + Clone + serde::Serialize,
+    {
+        let metadata = Metadata::new(MESSAGE_TYPE.message_type(), encoding, None);
+        let sealed_envelope = Message::new(metadata, data.clone())
+            .encoded_message(peers.client, peers.server)
+            .unwrap()
+            .open_envelope()
+            .unwrap()
+            .seal(&peers.sealing_key);
+        let mut bytes = Vec::new();
+        sealed_envelope.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn ingest_valid_envelope() {
+        let peers = peers();
+        for encoding in vec![
+            Encoding::Bincode(None, BincodeOptions::default()),
+            Encoding::Bincode(
+                Some(Compression::Deflate(CompressionLevel::Fast)),
+                BincodeOptions::default(),
+            ),
+            Encoding::CBOR(Some(Compression::Snappy)),
+            Encoding::JSON(Some(Compression::Lz4(CompressionLevel::Fast))),
+        ] {
+            let bytes = sealed_bytes(&peers, encoding, &Foo("FOO".to_string()));
+            let encoded_message =
+                ingest_untrusted(&bytes, &peers.opening_key, &IngestLimits::default()).unwrap();
+            let (_, msg) = encoded_message.decode::<Foo>().unwrap();
+            assert_eq!(*msg.data(), Foo("FOO".to_string()));
+        }
+    }
+
+    #[test]
+    fn ingest_malformed_inputs() {
+        let peers = peers();
+        let limits = IngestLimits::default();
+        let valid = sealed_bytes(
+            &peers,
+            Encoding::Bincode(None, BincodeOptions::default()),
+            &Foo("FOO".to_string()),
+        );
+
+        let mut bad_version = valid.clone();
+        bad_version[3] = 3;
+        let mut bad_frame_len = valid.clone();
+        bad_frame_len[8] = bad_frame_len[8].wrapping_add(1);
+        let mut huge_frame_len = valid.clone();
+        huge_frame_len[5..9].copy_from_slice(&u32::max_value().to_be_bytes());
+        let mut flipped_ciphertext = valid.clone();
+        let last = flipped_ciphertext.len() - 1;
+        flipped_ciphertext[last] ^= 0x01;
+        // a frame whose body starts with a length prefix that claims to be u64::MAX bytes
+        let mut huge_len_prefix = vec![0xFF, b'O', b'P', 1, 0, 0, 0, 96];
+        huge_len_prefix.extend_from_slice(&[0xFF; 96]);
+
+        let corpus: Vec<(&str, Vec<u8>)> = vec![
+            ("empty", vec![]),
+            ("single byte", vec![0]),
+            ("magic only", vec![0xFF, b'O', b'P']),
+            ("truncated header", vec![0xFF, b'O', b'P', 1, 0]),
+            ("unsupported version", bad_version),
+            ("frame length mismatch", bad_frame_len),
+            ("huge frame length", huge_frame_len),
+            ("huge length prefix", huge_len_prefix),
+            ("garbage", (0..200).map(|i| (i * 31 % 251) as u8).collect()),
+            ("unframed garbage", vec![0x42; 120]),
+            ("flipped ciphertext", flipped_ciphertext),
+            ("too large", vec![0; limits.max_envelope_size() + 1]),
+        ];
+        for (name, bytes) in corpus {
+            let result = ingest_untrusted(&bytes, &peers.opening_key, &limits);
+            assert!(result.is_err(), "{} should have failed", name);
+            let err = result.unwrap_err();
+            println!("{}: {}", name, err);
+            let expected = match name {
+                "unsupported version" => err == IngestError::UnsupportedFrameVersion(3),
+                "too large" => match err {
+                    IngestError::EnvelopeTooLarge { .. } => true,
+                    _ => false,
+                },
+                "flipped ciphertext" => err == IngestError::AuthenticationFailed,
+                "truncated header" | "frame length mismatch" | "huge frame length" => match err {
+                    IngestError::InvalidFrame(_) => true,
+                    _ => false,
+                },
+                _ => true,
+            };
+            assert!(expected, "{}: unexpected error: {:?}", name, err);
+        }
+
+        // every truncation of a valid envelope fails with a typed error
+        for len in 0..valid.len() {
+            assert!(ingest_untrusted(&valid[..len], &peers.opening_key, &limits).is_err());
+        }
+
+        // the envelope must have been sealed for the recipient
+        let other_peers = self::peers();
+        assert_eq!(
+            ingest_untrusted(&valid, &other_peers.opening_key, &limits).unwrap_err(),
+            IngestError::AuthenticationFailed
+        );
+    }
+
+    #[test]
+    fn ingest_message_size_limits() {
+        let peers = peers();
+        let data = Foo("0".repeat(10 * 1000));
+
+        // GIVEN: a max message size that is smaller than the message
+        let bytes = sealed_bytes(
+            &peers,
+            Encoding::Bincode(None, BincodeOptions::default()),
+            &data,
+        );
+        let limits = IngestLimits::default().set_max_msg_size(1000);
+        // THEN: the message is rejected
+        match ingest_untrusted(&bytes, &peers.opening_key, &limits) {
+            Err(IngestError::MessageTooLarge { max, .. }) => assert_eq!(max, 1000),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // GIVEN: a compressed message that decompresses to more than the max decompressed size
+        for compression in vec![
+            Compression::Deflate(CompressionLevel::Fast),
+            Compression::Zlib(CompressionLevel::Fast),
+            Compression::Gzip(CompressionLevel::Fast),
+            Compression::Snappy,
+            Compression::Lz4(CompressionLevel::Fast),
+        ] {
+            let bytes = sealed_bytes(
+                &peers,
+                Encoding::Bincode(Some(compression), BincodeOptions::default()),
+                &data,
+            );
+            let limits = IngestLimits::default().set_max_decompressed_size(1000);
+            // THEN: the message is rejected
+            match ingest_untrusted(&bytes, &peers.opening_key, &limits) {
+                Err(IngestError::DecompressedSizeExceeded { max }) => assert_eq!(max, 1000),
+                other => panic!("{:?}: unexpected result: {:?}", compression, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Key exchange schemes, i.e., the public-key authenticated encryption that is used to seal and open
+//! envelopes.
+//!
+//! [KeyExchange](trait.KeyExchange.html) abstracts the scheme behind `precompute`, `seal`, and `open`.
+//! [SodiumBox](struct.SodiumBox.html), i.e., libsodium's `crypto_box` (Curve25519, XSalsa20, Poly1305),
+//! is the default scheme.
+//! - each scheme is identified by a [KeyExchangeScheme](struct.KeyExchangeScheme.html) tag, which is
+//!   carried by the [SealedEnvelope](../struct.SealedEnvelope.html). An envelope can only be opened
+//!   using the scheme that sealed it.
+//! - [OpenEnvelope::seal()](../struct.OpenEnvelope.html#method.seal) and
+//!   [SealedEnvelope::open()](../struct.SealedEnvelope.html#method.open) use the default scheme.
+//!   Other schemes are plugged in via [seal_with()](../struct.OpenEnvelope.html#method.seal_with) and
+//!   [open_with()](../struct.SealedEnvelope.html#method.open_with).
+//! - the scheme is selected statically via a type parameter, thus there is no dynamic dispatch
+//!   on the default path
+//! - all schemes share the envelope's [Address](../struct.Address.html), i.e., a 32 byte public-key,
+//!   and its 24 byte random nonce
+
+use super::Address;
+use sodiumoxide::crypto::box_;
+use std::fmt;
+
+/// Public-key authenticated encryption scheme that is used to seal and open envelopes
+pub trait KeyExchange {
+    /// tag that identifies the scheme within a SealedEnvelope
+    const SCHEME: KeyExchangeScheme;
+
+    /// private-key that is paired with the [Address](../struct.Address.html) public-key
+    type SecretKey;
+
+    /// shared key that is precomputed from the peer's public-key and the local private-key
+    type PrecomputedKey;
+
+    /// Precomputes the shared key
+    /// - the sender precomputes the key using the recipient's address and its own private-key
+    /// - the recipient precomputes the key using the sender's address and its own private-key
+    fn precompute(address: &Address, secret_key: &Self::SecretKey) -> Self::PrecomputedKey;
+
+    /// Encrypts and authenticates the message
+    fn seal(msg: &[u8], nonce: &box_::Nonce, key: &Self::PrecomputedKey) -> Vec<u8>;
+
+    /// Verifies and decrypts the message
+    /// - returns None if the message fails to be verified
+    fn open(msg: &[u8], nonce: &box_::Nonce, key: &Self::PrecomputedKey) -> Option<Vec<u8>>;
+}
+
+/// Identifies a [KeyExchange](trait.KeyExchange.html) scheme
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct KeyExchangeScheme(pub u8);
+
+impl KeyExchangeScheme {
+    /// [SodiumBox](struct.SodiumBox.html) scheme
+    pub const SODIUM_BOX: KeyExchangeScheme = KeyExchangeScheme(0);
+}
+
+impl Default for KeyExchangeScheme {
+    fn default() -> Self {
+        KeyExchangeScheme::SODIUM_BOX
+    }
+}
+
+impl fmt::Display for KeyExchangeScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "KeyExchangeScheme({})", self.0)
+    }
+}
+
+/// libsodium `crypto_box`, i.e., Curve25519 key exchange with XSalsa20 encryption and Poly1305
+/// authentication
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SodiumBox;
+
+impl KeyExchange for SodiumBox {
+    const SCHEME: KeyExchangeScheme = KeyExchangeScheme::SODIUM_BOX;
+
+    type SecretKey = box_::SecretKey;
+
+    type PrecomputedKey = box_::PrecomputedKey;
+
+    #[inline]
+    fn precompute(address: &Address, secret_key: &box_::SecretKey) -> box_::PrecomputedKey {
+        box_::precompute(address.public_key(), secret_key)
+    }
+
+    #[inline]
+    fn seal(msg: &[u8], nonce: &box_::Nonce, key: &box_::PrecomputedKey) -> Vec<u8> {
+        box_::seal_precomputed(msg, nonce, key)
+    }
+
+    #[inline]
+    fn open(msg: &[u8], nonce: &box_::Nonce, key: &box_::PrecomputedKey) -> Option<Vec<u8>> {
+        box_::open_precomputed(msg, nonce, key).ok()
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{OpenEnvelope, SealedEnvelope};
+    use crate::tests::run_test;
+    use sodiumoxide::crypto::hash;
+
+    /// Mock scheme - the secret key is the shared key, the message is XOR'd with the key, and is
+    /// authenticated with a keyed hash
+    struct XorMock;
+
+    impl XorMock {
+        const TAG_LEN: usize = 16;
+
+        fn tag(msg: &[u8], nonce: &box_::Nonce, key: &[u8; 32]) -> Vec<u8> {
+            let mut data = Vec::with_capacity(key.len() + nonce.0.len() + msg.len());
+            data.extend_from_slice(key);
+            data.extend_from_slice(&nonce.0);
+            data.extend_from_slice(msg);
+            hash::hash(&data).0[..XorMock::TAG_LEN].to_vec()
+        }
+
+        fn xor(msg: &[u8], key: &[u8; 32]) -> Vec<u8> {
+            msg.iter()
+                .zip(key.iter().cycle())
+                .map(|(b, k)| b ^ k)
+                .collect()
+        }
+    }
+
+    impl KeyExchange for XorMock {
+        const SCHEME: KeyExchangeScheme = KeyExchangeScheme(0xFF);
+
+        type SecretKey = [u8; 32];
+
+        type PrecomputedKey = [u8; 32];
+
+        fn precompute(_address: &Address, secret_key: &[u8; 32]) -> [u8; 32] {
+            *secret_key
+        }
+
+        fn seal(msg: &[u8], nonce: &box_::Nonce, key: &[u8; 32]) -> Vec<u8> {
+            let mut sealed = XorMock::xor(msg, key);
+            sealed.extend(XorMock::tag(msg, nonce, key));
+            sealed
+        }
+
+        fn open(msg: &[u8], nonce: &box_::Nonce, key: &[u8; 32]) -> Option<Vec<u8>> {
+            if msg.len() < XorMock::TAG_LEN {
+                return None;
+            }
+            let (msg, tag) = msg.split_at(msg.len() - XorMock::TAG_LEN);
+            let msg = XorMock::xor(msg, key);
+            if XorMock::tag(&msg, nonce, key)[..] == tag[..] {
+                Some(msg)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn key_exchange_schemes() {
+        run_test("key_exchange_schemes", || {
+            let (client_pub_key, client_priv_key) = box_::gen_keypair();
+            let (server_pub_key, server_priv_key) = box_::gen_keypair();
+            let (client_addr, server_addr) = (Address::from(client_pub_key), server_pub_key.into());
+            let msg = b"key exchange";
+
+            // GIVEN: an envelope that is sealed using the default scheme
+            let sealed_envelope = OpenEnvelope::new(client_addr, server_addr, msg)
+                .seal(&server_addr.precompute_sealing_key(&client_priv_key));
+            // THEN: the envelope is tagged with the default scheme
+            assert_eq!(sealed_envelope.scheme(), KeyExchangeScheme::SODIUM_BOX);
+            assert_eq!(sealed_envelope.scheme(), SodiumBox::SCHEME);
+            // AND: the envelope round trips
+            let open_key = client_addr.precompute_key::<SodiumBox>(&server_priv_key);
+            let open_envelope = sealed_envelope.clone().open(&open_key).unwrap();
+            assert_eq!(open_envelope.msg(), msg);
+            let open_envelope = sealed_envelope.open_with::<SodiumBox>(&open_key).unwrap();
+            assert_eq!(open_envelope.msg(), msg);
+
+            // GIVEN: an envelope that is sealed using an alternate scheme
+            let shared_key = [7_u8; 32];
+            let sealed_envelope = OpenEnvelope::new(client_addr, server_addr, msg)
+                .seal_with::<XorMock>(&server_addr.precompute_key::<XorMock>(&shared_key));
+            assert_eq!(sealed_envelope.scheme(), XorMock::SCHEME);
+            // WHEN: the envelope is sent over the wire
+            let mut bytes = Vec::new();
+            sealed_envelope.encode(&mut bytes).unwrap();
+            let sealed_envelope = SealedEnvelope::decode(&bytes[..]).unwrap();
+            // THEN: the scheme tag is preserved
+            assert_eq!(sealed_envelope.scheme(), XorMock::SCHEME);
+            // AND: the envelope cannot be opened using a different scheme
+            assert!(sealed_envelope.clone().open(&open_key).is_err());
+            // AND: the envelope cannot be opened using the wrong key
+            assert!(sealed_envelope
+                .clone()
+                .open_with::<XorMock>(&[8_u8; 32])
+                .is_err());
+            // AND: the envelope is opened using the same scheme
+            let open_key = client_addr.precompute_key::<XorMock>(&shared_key);
+            let open_envelope = sealed_envelope.open_with::<XorMock>(&open_key).unwrap();
+            assert_eq!(open_envelope.msg(), msg);
+        });
+    }
+}
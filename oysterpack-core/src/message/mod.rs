@@ -41,6 +41,9 @@
 //!       - a flat rate per unit of time for the connection
 //!       - a flat rate per message byte
 //!       - a flat rate for each message type
+//!   - the server verifies that the client controls the private signing key it claims via a
+//!     challenge / response - see [handshake](handshake/index.html)
+//!   - the client and server negotiate the message encoding for the session during the handshake
 //!   - if the server successfully authenticates the client, then the server will reply with a
 //!     `ConnectAccepted` reply
 //!     - the message contains a shared secret cipher, which will be used to encrypt all future messages
@@ -52,9 +55,12 @@
 //!     - the hash is digitally signed by the server
 //!     - the message is encrypted using the client's private-key
 //!
+//! - envelopes are sealed using a pluggable key exchange scheme, which is tagged on the envelope -
+//!   see [key_exchange](key_exchange/index.html)
+//!
 //! - when a peer comes online they register themselves with the services they provide
 //!   - this enables clients to discover peers that offer services that the client is interested in
-//!   - peers can advertise service metadata
+//!   - peers can advertise service metadata - see [ServiceAdvertisement](discovery/struct.ServiceAdvertisement.html)
 //!     - service price
 //!     - quality of service
 //!     - capacity
@@ -69,49 +75,151 @@
 //!   - clients can bid for a service at a lower price, sellers may choose to take the lower price
 //!   - clients can bid higher, if service supply is low, in order to get higher priority
 //!
+//! ### Identifiers
+//! [MessageType](struct.MessageType.html), [InstanceId](struct.InstanceId.html), and
+//! [SessionId](struct.SessionId.html) are all backed by a ULID, but they belong to different id
+//! domains and must not be mixed. The conversion boundaries are:
+//! - each id exposes its ULID via `ulid()` - converting an id into a ULID is always safe
+//! - ids are never implicitly created from a ULID, i.e., there are no `From<ULID>` conversions
+//!   - MessageType is created from a [MessageTypeId](struct.MessageTypeId.html)
+//!   - InstanceId and SessionId are generated
+//!   - [SessionId::from_ulid_unchecked()](struct.SessionId.html#method.from_ulid_unchecked) is the
+//!     explicit escape hatch for ULIDs that are known to be SessionId(s), e.g., ULIDs read from storage
+//!
+//! ### Property Testing
+//! The [arbitrary](arbitrary/index.html) module provides proptest strategies for messages, which is
+//! enabled via the `proptest` feature.
+//!
 //! ### Notes
 //! - rmp_serde does not support Serde #[serde(skip_serializing_if="Option::is_none")] - it fails
 //!   on deserialization - [https://github.com/3Hren/msgpack-rust/issues/86]
 //!   - take away lesson is don't use the Serde #[serde(skip_serializing_if="Option::is_none")] feature
 //!
 
+use bincode::Options;
 use chrono::{DateTime, Duration, Utc};
 use sodiumoxide::crypto::{box_, hash, secretbox, sign};
 use flate2::bufread;
 use oysterpack_errors::{Error, ErrorMessage, Id as ErrorId, IsError, Level as ErrorLevel};
-use oysterpack_events::event::ModuleSource;
+use oysterpack_events::{event::ModuleSource, AttributeId};
 use oysterpack_uid::{Domain, DomainULID, ULID};
 use std::{
-    cmp, error, fmt,
+    cmp,
+    convert::TryFrom,
+    error, fmt,
     io::{self, Read, Write},
-    time,
+    str, time,
 };
 
+#[cfg(any(test, feature = "proptest"))]
+pub mod arbitrary;
 pub mod base58;
+pub mod clock;
+pub mod discovery;
 pub mod errors;
+pub mod framing;
+pub mod handshake;
+pub mod ingest;
+pub mod key_exchange;
 pub mod service;
 
 /// Max message size - 256 KB
 pub const MAX_MSG_SIZE: usize = 1000 * 256;
 
+/// Default max number of frames, i.e., messages, per [MessageBatch](struct.MessageBatch.html)
+pub const DEFAULT_MAX_BATCH_FRAMES: usize = 1000;
+
 /// Min message size for SealedEnvelope using MessagePack encoding
 pub const SEALED_ENVELOPE_MIN_SIZE: usize = 90;
 
 /// A sealed envelope is secured via public-key authenticated encryption. It contains a private message
 /// that is encrypted using the recipient's public-key and the sender's private-key. If the recipient
 /// is able to decrypt the message, then the recipient knows it was sealed by the sender.
+///
+/// The key exchange scheme is not part of the serialized envelope, i.e., the envelope layout is the
+/// same as before schemes were introduced. The scheme is carried in the frame header - see
+/// [FRAME_VERSION](#associatedconstant.FRAME_VERSION).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SealedEnvelope {
     sender: Address,
     recipient: Address,
+    #[serde(skip)]
+    scheme: key_exchange::KeyExchangeScheme,
     nonce: box_::Nonce,
     msg: EncryptedMessageBytes,
 }
 
 impl SealedEnvelope {
+    /// Frame header magic bytes, which are followed by the frame version
+    const FRAME_MAGIC: [u8; 3] = [0xFF, b'O', b'P'];
+
+    /// Current frame version
+    /// - version 2 frames carry the key exchange scheme: `| 0xFF 'O' 'P' 0x02 | scheme u8 | u32 BE length | bincode SealedEnvelope |`
+    /// - version 1 frames are length prefixed: `| 0xFF 'O' 'P' 0x01 | u32 BE length | bincode SealedEnvelope |`
+    /// - unframed data, i.e., the raw bincode encoding used before framing was introduced, is treated as version 0
+    ///
+    /// The bincode SealedEnvelope layout is the same for all versions. Version 0 and 1 envelopes
+    /// are decoded using the [SodiumBox](key_exchange/struct.SodiumBox.html) scheme.
+    pub const FRAME_VERSION: u8 = 2;
+
+    /// Returns the key exchange scheme for the specified frame version.
+    /// - version 2 frames carry the scheme in the byte that follows the frame version
+    /// - returns None if the frame version is not supported
+    pub(crate) fn frame_scheme(
+        version: u8,
+        scheme: u8,
+    ) -> Option<key_exchange::KeyExchangeScheme> {
+        match version {
+            1 => Some(key_exchange::KeyExchangeScheme::SODIUM_BOX),
+            SealedEnvelope::FRAME_VERSION => Some(key_exchange::KeyExchangeScheme(scheme)),
+            _ => None,
+        }
+    }
+
     /// decodes the io stream to construct a new SealedEnvelope
     /// - the stream must use the [bincode](https://crates.io/crates/bincode) encoding
+    /// - framed streams are read fully before they are deserialized, i.e., partial reads are
+    ///   retried until the whole frame has been read.
+    ///   See [FRAME_VERSION](#associatedconstant.FRAME_VERSION)
+    /// - if the stream does not start with the frame magic, then it is decoded as an unframed,
+    ///   i.e., version 0, SealedEnvelope via [decode_unframed()](#method.decode_unframed).
+    ///   Unframed envelopes start with the bincode length prefix of the sender's public key, which
+    ///   never matches the frame magic.
     pub fn decode<R>(read: R) -> Result<SealedEnvelope, Error>
+    where
+        R: io::Read,
+    {
+        fn decoding_error<E: fmt::Display>(err: E) -> Error {
+            op_error!(errors::MessageError::DecodingError(
+                errors::DecodingError::InvalidSealedEnvelope(ErrorMessage(err.to_string()))
+            ))
+        }
+
+        let mut read = read;
+        let mut header = [0_u8; 4];
+        read.read_exact(&mut header).map_err(decoding_error)?;
+        if header[..3] != SealedEnvelope::FRAME_MAGIC {
+            return SealedEnvelope::decode_unframed((&header[..]).chain(read));
+        }
+        let mut scheme = [0_u8; 1];
+        if header[3] == SealedEnvelope::FRAME_VERSION {
+            read.read_exact(&mut scheme).map_err(decoding_error)?;
+        }
+        let scheme = SealedEnvelope::frame_scheme(header[3], scheme[0]).ok_or_else(|| {
+            decoding_error(format!("unsupported frame version: {}", header[3]))
+        })?;
+        let frame = framing::read_framed(&mut read)
+            .map_err(decoding_error)?
+            .ok_or_else(|| decoding_error("missing frame"))?;
+        let mut envelope: SealedEnvelope = bincode::deserialize(&frame).map_err(decoding_error)?;
+        envelope.scheme = scheme;
+        Ok(envelope)
+    }
+
+    /// decodes an unframed, i.e., version 0, SealedEnvelope, which is the raw bincode encoding that
+    /// was used before framing was introduced
+    /// - the envelope is decoded using the [SodiumBox](key_exchange/struct.SodiumBox.html) scheme
+    pub fn decode_unframed<R>(read: R) -> Result<SealedEnvelope, Error>
     where
         R: io::Read,
     {
@@ -122,19 +230,74 @@ impl SealedEnvelope {
         })
     }
 
+    /// decodes a SealedEnvelope from a complete message that was received over a message oriented
+    /// transport, e.g., an nng message body
+    /// - the transport delimits the message, which makes the frame magic authoritative, i.e., if
+    ///   the bytes start with the frame magic, then the frame must span the whole message.
+    ///   Otherwise, the bytes are decoded as an unframed, i.e., version 0, SealedEnvelope.
+    /// - streams must be decoded via [decode()](#method.decode)
+    pub fn decode_transport_message(bytes: &[u8]) -> Result<SealedEnvelope, Error> {
+        fn decoding_error<E: fmt::Display>(err: E) -> Error {
+            op_error!(errors::MessageError::DecodingError(
+                errors::DecodingError::InvalidSealedEnvelope(ErrorMessage(err.to_string()))
+            ))
+        }
+
+        let (scheme, bytes) = ingest::unframe(bytes).map_err(decoding_error)?;
+        let mut envelope: SealedEnvelope = bincode::deserialize(bytes).map_err(decoding_error)?;
+        envelope.scheme = scheme;
+        Ok(envelope)
+    }
+
     /// encode the SealedEnvelope and write it to the io stream using [bincode](https://crates.io/crates/bincode) encoding
+    /// - the encoded bytes are framed using a length prefix - see [FRAME_VERSION](#associatedconstant.FRAME_VERSION)
     pub fn encode<W: ?Sized>(&self, wr: &mut W) -> Result<(), Error>
     where
         W: io::Write,
     {
-        bincode::serialize_into(wr, self).map_err(|err| {
+        fn encoding_error<E: fmt::Display>(err: E) -> Error {
             op_error!(errors::MessageError::EncodingError(
                 errors::EncodingError::InvalidSealedEnvelope(ErrorMessage(err.to_string()))
             ))
-        })
+        }
+
+        let bytes = bincode::serialize(self).map_err(encoding_error)?;
+        wr.write_all(&SealedEnvelope::FRAME_MAGIC)
+            .and_then(|_| wr.write_all(&[SealedEnvelope::FRAME_VERSION, self.scheme.0]))
+            .and_then(|_| framing::write_framed(wr, &bytes))
+            .map_err(encoding_error)
+    }
+
+    /// returns the number of bytes that [encode()](#method.encode) writes, i.e., the framed size
+    pub fn encoded_len(&self) -> Result<usize, Error> {
+        const HEADER_LEN: u64 = 9;
+
+        bincode::serialized_size(self)
+            .map(|size| (HEADER_LEN + size) as usize)
+            .map_err(|err| {
+                op_error!(errors::MessageError::EncodingError(
+                    errors::EncodingError::InvalidSealedEnvelope(ErrorMessage(err.to_string()))
+                ))
+            })
+    }
+
+    /// encode the SealedEnvelope and write it to the io stream using [bincode](https://crates.io/crates/bincode) encoding.
+    /// The encoded bytes are hashed incrementally as they are written to the stream.
+    /// - returns the hash of the encoded bytes
+    pub fn encode_hashed<W: ?Sized>(&self, wr: &mut W) -> Result<hash::Digest, Error>
+    where
+        W: io::Write,
+    {
+        let mut hashing_writer = HashingWriter {
+            writer: wr,
+            hasher: MessageHasher::new(),
+        };
+        self.encode(&mut hashing_writer)?;
+        Ok(hashing_writer.hasher.finalize())
     }
 
     /// constructor
+    /// - the envelope is tagged with the default [SodiumBox](key_exchange/struct.SodiumBox.html) scheme
     pub fn new(
         sender: Address,
         recipient: Address,
@@ -144,51 +307,46 @@ impl SealedEnvelope {
         SealedEnvelope {
             sender,
             recipient,
+            scheme: key_exchange::KeyExchangeScheme::SODIUM_BOX,
             nonce,
             msg: EncryptedMessageBytes(msg.into()),
         }
     }
 
-    // TODO: implement TryFrom when it bocomes stable
     /// Converts an nng:Message into a SealedEnvelope.
+    /// - delegates to `TryFrom<nng::Message>`
     pub fn try_from_nng_message(msg: nng::Message) -> Result<SealedEnvelope, Error> {
-        SealedEnvelope::decode(&**msg).map_err(|err| {
-            op_error!(errors::NngMessageError::from(ErrorMessage::from(
-                "Failed to decode SealedEnvelope"
-            )))
-            .with_cause(err)
-        })
+        SealedEnvelope::try_from(msg)
     }
 
-    // TODO: implement TryInto when it becomes stable
     /// Converts itself into an nng:Message
+    /// - delegates to `TryFrom<SealedEnvelope> for nng::Message`
     pub fn try_into_nng_message(self) -> Result<nng::Message, Error> {
-        let bytes = bincode::serialize(&self).map_err(|err| {
-            op_error!(errors::MessageError::EncodingError(
-                errors::EncodingError::InvalidSealedEnvelope(ErrorMessage(err.to_string()))
-            ))
-        })?;
-        let mut msg = nng::Message::with_capacity(bytes.len()).map_err(|err| {
-            op_error!(errors::NngMessageError::from(ErrorMessage(format!("Failed to create an empty message with a pre-allocated body buffer (capacity = {}): {}", bytes.len(), err))))
-        })?;
-        msg.push_back(&bytes).map_err(|err| {
-            op_error!(errors::NngMessageError::from(ErrorMessage(format!(
-                "Failed to append data to the back of the message body: {}",
-                err
-            ))))
-        })?;
-        Ok(msg)
+        nng::Message::try_from(self)
     }
 
     /// open the envelope using the specified precomputed key
+    /// - the envelope must be sealed using the default [SodiumBox](key_exchange/struct.SodiumBox.html) scheme
     pub fn open(self, key: &box_::PrecomputedKey) -> Result<OpenEnvelope, Error> {
-        match box_::open_precomputed(&self.msg.0, &self.nonce, key) {
-            Ok(msg) => Ok(OpenEnvelope {
+        self.open_with::<key_exchange::SodiumBox>(key)
+    }
+
+    /// open the envelope using the specified key exchange scheme
+    /// - fails if the envelope was sealed using a different scheme
+    pub fn open_with<K: key_exchange::KeyExchange>(
+        self,
+        key: &K::PrecomputedKey,
+    ) -> Result<OpenEnvelope, Error> {
+        if self.scheme != K::SCHEME {
+            return Err(op_error!(errors::SealedEnvelopeOpenFailed(&self)));
+        }
+        match K::open(&self.msg.0, &self.nonce, key) {
+            Some(msg) => Ok(OpenEnvelope {
                 sender: self.sender,
                 recipient: self.recipient,
                 msg: MessageBytes(msg),
             }),
-            Err(_) => Err(op_error!(errors::SealedEnvelopeOpenFailed(&self))),
+            None => Err(op_error!(errors::SealedEnvelopeOpenFailed(&self))),
         }
     }
 
@@ -211,6 +369,78 @@ impl SealedEnvelope {
     pub fn nonce(&self) -> &box_::Nonce {
         &self.nonce
     }
+
+    /// returns the key exchange scheme that was used to seal the envelope
+    pub fn scheme(&self) -> key_exchange::KeyExchangeScheme {
+        self.scheme
+    }
+
+    /// Opens the envelope, verifies the sender's signature, checks the message deadline, and then
+    /// decodes the message.
+    ///
+    /// The envelope is expected to contain [SignedMessageBytes](struct.SignedMessageBytes.html), i.e.,
+    /// the envelope was created via [EncodedMessage::signed_open_envelope()](struct.EncodedMessage.html#method.signed_open_envelope).
+    ///
+    /// If the message has a [Deadline::MessageTimeoutMillis](enum.Deadline.html) deadline that has
+    /// expired, then a [MessageError::MessageExpired](errors/enum.MessageError.html) error is returned.
+    pub fn open_verified_decode<T>(
+        self,
+        open_key: &box_::PrecomputedKey,
+        sign_pubkey: &sign::PublicKey,
+    ) -> Result<(Addresses, Message<T>), Error>
+    where
+        T: fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+    {
+        let encoded_message = self
+            .open(open_key)?
+            .verified_encoded_message(sign_pubkey)?;
+        let msg = &encoded_message.msg;
+        if let Some(deadline @ Deadline::MessageTimeoutMillis(_)) = msg.metadata.deadline {
+            if deadline.duration(msg.metadata.timestamp()) == Duration::zero() {
+                return Err(op_error!(errors::MessageError::MessageExpired {
+                    from: &encoded_message.sender,
+                    deadline
+                }));
+            }
+        }
+        encoded_message.decode()
+    }
+}
+
+/// Converts an nng:Message into a SealedEnvelope.
+/// - see [decode_transport_message()](struct.SealedEnvelope.html#method.decode_transport_message)
+impl TryFrom<nng::Message> for SealedEnvelope {
+    type Error = Error;
+
+    fn try_from(msg: nng::Message) -> Result<SealedEnvelope, Error> {
+        SealedEnvelope::decode_transport_message(&msg).map_err(|err| {
+            op_error!(errors::NngMessageError::from(ErrorMessage::from(
+                "Failed to decode SealedEnvelope"
+            )))
+            .with_cause(err)
+        })
+    }
+}
+
+/// Converts the SealedEnvelope into an nng:Message
+/// - the message body is framed - see [encode()](struct.SealedEnvelope.html#method.encode)
+impl TryFrom<SealedEnvelope> for nng::Message {
+    type Error = Error;
+
+    fn try_from(sealed_envelope: SealedEnvelope) -> Result<nng::Message, Error> {
+        let mut bytes = Vec::with_capacity(sealed_envelope.encoded_len()?);
+        sealed_envelope.encode(&mut bytes)?;
+        let mut msg = nng::Message::with_capacity(bytes.len()).map_err(|err| {
+            op_error!(errors::NngMessageError::from(ErrorMessage(format!("Failed to create an empty message with a pre-allocated body buffer (capacity = {}): {}", bytes.len(), err))))
+        })?;
+        msg.push_back(&bytes).map_err(|err| {
+            op_error!(errors::NngMessageError::from(ErrorMessage(format!(
+                "Failed to append data to the back of the message body: {}",
+                err
+            ))))
+        })?;
+        Ok(msg)
+    }
 }
 
 impl fmt::Display for SealedEnvelope {
@@ -236,6 +466,7 @@ pub struct OpenEnvelope {
 
 impl OpenEnvelope {
     /// constructor
+    /// - no validation is performed - see [try_new()](#method.try_new)
     pub fn new(sender: Address, recipient: Address, msg: &[u8]) -> OpenEnvelope {
         OpenEnvelope {
             sender,
@@ -244,15 +475,74 @@ impl OpenEnvelope {
         }
     }
 
-    /// seals the envelope
+    /// validating constructor
+    ///
+    /// ## Errors
+    /// - [MessageError::SelfAddressed](errors/enum.MessageError.html#variant.SelfAddressed) if the sender and recipient are the same
+    /// - [MessageError::MessageTooLarge](errors/enum.MessageError.html#variant.MessageTooLarge) if the msg size exceeds [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html)
+    pub fn try_new(sender: Address, recipient: Address, msg: &[u8]) -> Result<OpenEnvelope, Error> {
+        if sender == recipient {
+            return Err(op_error!(errors::MessageError::SelfAddressed(&sender)));
+        }
+        if msg.len() > MAX_MSG_SIZE {
+            return Err(op_error!(errors::MessageError::MessageTooLarge {
+                from: &sender,
+                size: msg.len(),
+                max: MAX_MSG_SIZE
+            }));
+        }
+        Ok(OpenEnvelope::new(sender, recipient, msg))
+    }
+
+    /// seals the envelope using the default [SodiumBox](key_exchange/struct.SodiumBox.html) scheme
     pub fn seal(self, key: &box_::PrecomputedKey) -> SealedEnvelope {
+        self.seal_with::<key_exchange::SodiumBox>(key)
+    }
+
+    /// seals the envelope using the specified key exchange scheme
+    pub fn seal_with<K: key_exchange::KeyExchange>(
+        self,
+        key: &K::PrecomputedKey,
+    ) -> SealedEnvelope {
         let nonce = box_::gen_nonce();
         SealedEnvelope {
             sender: self.sender,
             recipient: self.recipient,
+            scheme: K::SCHEME,
             nonce,
-            msg: EncryptedMessageBytes(box_::seal_precomputed(&self.msg.0, &nonce, key)),
+            msg: EncryptedMessageBytes(K::seal(&self.msg.0, &nonce, key)),
+        }
+    }
+
+    /// seals the envelope using the default [SodiumBox](key_exchange/struct.SodiumBox.html) scheme,
+    /// checking that the sealed envelope does not exceed [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html)
+    /// - see [try_seal_with()](#method.try_seal_with)
+    pub fn try_seal(self, key: &box_::PrecomputedKey) -> Result<SealedEnvelope, Error> {
+        self.try_seal_with::<key_exchange::SodiumBox>(key)
+    }
+
+    /// seals the envelope using the specified key exchange scheme, checking that the sealed
+    /// envelope does not exceed [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html)
+    /// - the size check is applied to the encoded SealedEnvelope, i.e., the bytes that are sent,
+    ///   which include the encryption and framing overhead. Thus, a msg that is just under
+    ///   MAX_MSG_SIZE will be rejected.
+    ///
+    /// ## Errors
+    /// - [MessageError::MessageTooLarge](errors/enum.MessageError.html#variant.MessageTooLarge) if the sealed envelope size exceeds [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html)
+    pub fn try_seal_with<K: key_exchange::KeyExchange>(
+        self,
+        key: &K::PrecomputedKey,
+    ) -> Result<SealedEnvelope, Error> {
+        let sealed_envelope = self.seal_with::<K>(key);
+        let size = sealed_envelope.encoded_len()?;
+        if size > MAX_MSG_SIZE {
+            return Err(op_error!(errors::MessageError::MessageTooLarge {
+                from: &sealed_envelope.sender,
+                size,
+                max: MAX_MSG_SIZE
+            }));
         }
+        Ok(sealed_envelope)
     }
 
     /// msg bytes
@@ -271,13 +561,53 @@ impl OpenEnvelope {
     }
 
     /// parses the message data into an encoded message
+    /// - deserialization is bounded by [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html) - see
+    ///   [encoded_message_with_limit()](#method.encoded_message_with_limit)
     pub fn encoded_message(self) -> Result<EncodedMessage, Error> {
-        let msg: Message<MessageBytes> = bincode::deserialize(self.msg()).map_err(|err| {
-            op_error!(errors::MessageError::MessageDataDeserializationFailed(
-                &self.sender,
-                errors::ErrorInfo(err.to_string())
+        self.encoded_message_with_limit(MAX_MSG_SIZE)
+    }
+
+    /// parses the message data into an encoded message
+    /// - at most `limit` bytes are read, i.e., length prefixes within the message data that declare
+    ///   more bytes than the limit allows are rejected before any allocation is made
+    ///
+    /// ## Errors
+    /// - [MessageError::MessageDataTooLarge](errors/enum.MessageError.html#variant.MessageDataTooLarge) if the limit is exceeded
+    /// - [MessageError::MessageDataDeserializationFailed](errors/enum.MessageError.html#variant.MessageDataDeserializationFailed)
+    pub fn encoded_message_with_limit(self, limit: usize) -> Result<EncodedMessage, Error> {
+        let msg = deserialize_message_data(&self.sender, self.msg(), limit)?;
+        Ok(EncodedMessage {
+            sender: self.sender,
+            recipient: self.recipient,
+            msg,
+        })
+    }
+
+    /// returns true if the message data is [SignedMessageBytes](struct.SignedMessageBytes.html),
+    /// i.e., the envelope was created via [EncodedMessage::signed_open_envelope()](struct.EncodedMessage.html#method.signed_open_envelope)
+    /// - the signature is not verified - see [verified_encoded_message()](#method.verified_encoded_message)
+    pub fn is_signed(&self) -> bool {
+        BincodeOptions::default()
+            .set_limit(self.msg.0.len() as u64)
+            .deserialize::<SignedMessageBytes>(self.msg())
+            .is_ok()
+    }
+
+    /// verifies the sender's signature, and then parses the signed message data into an encoded message
+    /// - the message data is expected to be [SignedMessageBytes](struct.SignedMessageBytes.html)
+    pub fn verified_encoded_message(
+        self,
+        sign_pubkey: &sign::PublicKey,
+    ) -> Result<EncodedMessage, Error> {
+        let signed_msg: SignedMessageBytes = bincode::deserialize(self.msg()).map_err(|err| {
+            op_error!(errors::MessageError::DecodingError(
+                errors::DecodingError::InvalidSealedSignedMessage(ErrorMessage(err.to_string()))
             ))
         })?;
+        signed_msg
+            .signed_hash
+            .verify(signed_msg.msg.data(), sign_pubkey)?;
+        let msg = deserialize_message_data(&self.sender, signed_msg.msg.data(), MAX_MSG_SIZE)?;
         Ok(EncodedMessage {
             sender: self.sender,
             recipient: self.recipient,
@@ -286,6 +616,50 @@ impl OpenEnvelope {
     }
 }
 
+/// bincode deserializes the message data, reading at most `limit` bytes
+fn deserialize_message_data(
+    sender: &Address,
+    bytes: &[u8],
+    limit: usize,
+) -> Result<Message<MessageBytes>, Error> {
+    BincodeOptions::default()
+        .set_limit(limit as u64)
+        .deserialize(bytes)
+        .map_err(|err| match *err {
+            bincode::ErrorKind::SizeLimit => {
+                op_error!(errors::MessageError::MessageDataTooLarge {
+                    from: sender,
+                    limit
+                })
+            }
+            _ => op_error!(errors::MessageError::MessageDataDeserializationFailed(
+                sender,
+                errors::ErrorInfo(err.to_string())
+            )),
+        })
+}
+
+/// Message bytes that are digitally signed by the sender.
+/// - the signed hash is computed over the bincode serialized [Message](struct.Message.html), i.e., it
+///   covers both the message metadata and data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedMessageBytes {
+    msg: MessageBytes,
+    signed_hash: SignedHash,
+}
+
+impl SignedMessageBytes {
+    /// signed message bytes
+    pub fn msg(&self) -> &MessageBytes {
+        &self.msg
+    }
+
+    /// signed hash of the message bytes
+    pub fn signed_hash(&self) -> &SignedHash {
+        &self.signed_hash
+    }
+}
+
 impl fmt::Display for OpenEnvelope {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -303,11 +677,34 @@ impl fmt::Display for OpenEnvelope {
 pub struct Address(box_::PublicKey);
 
 impl Address {
+    /// Address length in bytes
+    pub const LEN: usize = box_::PUBLICKEYBYTES;
+
     /// returns the underlying public-key
     pub fn public_key(&self) -> &box_::PublicKey {
         &self.0
     }
 
+    /// returns the raw public-key bytes
+    /// - this is meant to be used to embed the address in fixed-layout binary frames
+    pub fn as_bytes(&self) -> [u8; Address::LEN] {
+        (self.0).0
+    }
+
+    /// constructs an Address from the raw public-key bytes
+    pub fn from_bytes(bytes: [u8; Address::LEN]) -> Address {
+        Address(box_::PublicKey(bytes))
+    }
+
+    /// Deterministically derives an Address and its private-key from the specified seed.
+    /// - the same seed always produces the same keypair, which makes it useful for test fixtures
+    ///   that require stable identities
+    /// - the seed must be kept secret because the private-key can be derived from it
+    pub fn from_seed(seed: &[u8; box_::SEEDBYTES]) -> (Address, box_::SecretKey) {
+        let (public_key, private_key) = box_::keypair_from_seed(&box_::Seed(*seed));
+        (Address(public_key), private_key)
+    }
+
     /// precompute the key that can be used to seal the envelope by the sender
     pub fn precompute_sealing_key(
         &self,
@@ -323,6 +720,16 @@ impl Address {
     ) -> box_::PrecomputedKey {
         box_::precompute(&self.0, recipient_private_key)
     }
+
+    /// precompute the key using the specified key exchange scheme
+    /// - the sender precomputes the sealing key using the recipient's address
+    /// - the recipient precomputes the opening key using the sender's address
+    pub fn precompute_key<K: key_exchange::KeyExchange>(
+        &self,
+        private_key: &K::SecretKey,
+    ) -> K::PrecomputedKey {
+        K::precompute(self, private_key)
+    }
 }
 
 impl From<box_::PublicKey> for Address {
@@ -337,6 +744,21 @@ impl fmt::Display for Address {
     }
 }
 
+impl str::FromStr for Address {
+    type Err = Error;
+
+    /// parses the base58 encoded public-key, i.e., the Address Display format
+    /// - [base58::DecodeError](base58/struct.DecodeError.html) if the input is not valid base58
+    /// - [InvalidAddressLength](errors/struct.InvalidAddressLength.html) if the input does not
+    ///   decode to [Address::LEN](struct.Address.html#associatedconstant.LEN) bytes
+    fn from_str(s: &str) -> Result<Address, Error> {
+        let bytes = base58::decode(s)?;
+        box_::PublicKey::from_slice(&bytes)
+            .map(Address)
+            .ok_or_else(|| op_error!(errors::InvalidAddressLength(bytes.len())))
+    }
+}
+
 /// message data bytes that is encrypted
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct EncryptedMessageBytes(Vec<u8>);
@@ -385,6 +807,71 @@ impl MessageBytes {
     }
 }
 
+/// Computes a message hash incrementally, i.e., large messages can be hashed as the bytes arrive
+/// without needing to buffer the entire message.
+/// - the resulting digest is the same as [MessageBytes::hash()](struct.MessageBytes.html#method.hash)
+///   for the concatenated bytes
+/// - implements `io::Write`, which enables the hasher to be used as a stream sink
+pub struct MessageHasher(hash::State);
+
+impl MessageHasher {
+    /// constructor
+    pub fn new() -> MessageHasher {
+        MessageHasher(hash::State::new())
+    }
+
+    /// feeds the next chunk of bytes into the hash
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+
+    /// computes the hash for all bytes that have been fed into the hasher
+    pub fn finalize(self) -> hash::Digest {
+        self.0.finalize()
+    }
+}
+
+impl Default for MessageHasher {
+    fn default() -> MessageHasher {
+        MessageHasher::new()
+    }
+}
+
+impl fmt::Debug for MessageHasher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("MessageHasher")
+    }
+}
+
+impl Write for MessageHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer that hashes the bytes that are written through to the underlying writer
+struct HashingWriter<'a, W: ?Sized + io::Write> {
+    writer: &'a mut W,
+    hasher: MessageHasher,
+}
+
+impl<W: ?Sized + io::Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
 impl From<&[u8]> for MessageBytes {
     fn from(bytes: &[u8]) -> MessageBytes {
         MessageBytes(Vec::from(bytes))
@@ -407,6 +894,13 @@ impl std::iter::FromIterator<u8> for MessageBytes {
 }
 
 /// Message metadata
+///
+/// ## Attributes
+/// Custom attributes can be attached to the metadata, e.g., tenant ID, trace ID. Attributes are keyed
+/// by AttributeId and the attribute value is a ULID.
+/// - in order to keep Metadata `Copy`, the attributes are stored in a fixed size array. Thus, at most
+///   [Metadata::MAX_ATTRIBUTES](struct.Metadata.html#associatedconstant.MAX_ATTRIBUTES) attributes
+///   can be attached.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct Metadata {
     msg_type: MessageType,
@@ -416,9 +910,13 @@ pub struct Metadata {
     correlation_id: Option<InstanceId>,
     session_id: SessionId,
     sequence: Option<Sequence>,
+    attributes: [Option<(AttributeId, ULID)>; Metadata::MAX_ATTRIBUTES],
 }
 
 impl Metadata {
+    /// Max number of attributes that can be attached to the metadata
+    pub const MAX_ATTRIBUTES: usize = 4;
+
     /// constructor
     pub fn new(msg_type: MessageType, encoding: Encoding, deadline: Option<Deadline>) -> Metadata {
         Metadata {
@@ -429,9 +927,49 @@ impl Metadata {
             correlation_id: None,
             session_id: SessionId::generate(),
             sequence: None,
+            attributes: [None; Metadata::MAX_ATTRIBUTES],
+        }
+    }
+
+    /// Attaches an attribute. If an attribute with the same AttributeId is already attached, then its
+    /// value is replaced.
+    /// - an error is returned if [Metadata::MAX_ATTRIBUTES](struct.Metadata.html#associatedconstant.MAX_ATTRIBUTES)
+    ///   attributes are already attached
+    pub fn with_attribute(self, id: AttributeId, value: ULID) -> Result<Metadata, Error> {
+        let mut md = self;
+        let slot = match md
+            .attributes
+            .iter()
+            .position(|attr| attr.map_or(false, |(attr_id, _)| attr_id == id))
+        {
+            Some(index) => Some(index),
+            None => md.attributes.iter().position(Option::is_none),
+        };
+        match slot {
+            Some(index) => {
+                md.attributes[index] = Some((id, value));
+                Ok(md)
+            }
+            None => Err(op_error!(errors::TooManyMetadataAttributes(
+                Metadata::MAX_ATTRIBUTES
+            ))),
         }
     }
 
+    /// Returns the attribute value for the specified AttributeId
+    pub fn attribute(&self, id: AttributeId) -> Option<ULID> {
+        self.attributes
+            .iter()
+            .filter_map(|attr| *attr)
+            .find(|(attr_id, _)| *attr_id == id)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the attached attributes
+    pub fn attributes(&self) -> impl Iterator<Item = (AttributeId, ULID)> + '_ {
+        self.attributes.iter().filter_map(|attr| *attr)
+    }
+
     /// sets the session id
     pub fn set_session_id(self, session_id: SessionId) -> Metadata {
         let mut md = self;
@@ -539,46 +1077,100 @@ pub trait IsMessage {
 }
 
 /// Compression mode
+/// - the compression level only applies when compressing, i.e., decompression does not depend on
+///   the level that was used to compress the data
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Compression {
     /// deflate
-    Deflate,
+    Deflate(CompressionLevel),
     /// zlib
-    Zlib,
+    Zlib(CompressionLevel),
     /// gzip
-    Gzip,
+    Gzip(CompressionLevel),
     /// snappy
     Snappy,
     /// LZ4
-    Lz4,
+    Lz4(CompressionLevel),
+    /// raw deflate, using a shared pre-trained dictionary as the deflate preset dictionary
+    /// - the dictionary must be registered with the [CompressionDictionaryRegistry](struct.CompressionDictionaryRegistry.html)
+    ///   on both the sending and receiving side
+    /// - the DictionaryId is carried in the message Encoding, which is how the receiver looks up
+    ///   the matching dictionary
+    /// - cost: zlib loads the preset dictionary into the deflate window for every message, which
+    ///   is linear in the dictionary size, i.e., the dictionary is hashed but it is never
+    ///   compressed or inflated. Only the last 32 KB of the dictionary are used, which is the
+    ///   deflate window size.
+    DeflateDictionary(DictionaryId),
+    /// zstd, using a shared pre-trained dictionary
+    /// - the dictionary must be registered with the [CompressionDictionaryRegistry](struct.CompressionDictionaryRegistry.html)
+    ///   on both the sending and receiving side
+    /// - cost: the dictionary is digested once, when it is registered, i.e., there is no per
+    ///   message dictionary cost. Data is compressed at the zstd default level.
+    /// - zstd dictionaries are best trained on sample messages via `zstd --train`
+    ZstdDictionary(DictionaryId),
+}
+
+/// Compression level, which trades CPU for size
+/// - [Deflate](enum.Compression.html#variant.Deflate), [Zlib](enum.Compression.html#variant.Zlib),
+///   and [Gzip](enum.Compression.html#variant.Gzip) map to the corresponding flate2 level
+/// - [Lz4](enum.Compression.html#variant.Lz4): Fast uses the LZ4 fast mode, Default and Best use
+///   the LZ4 high compression mode at levels 9 and 16 respectively
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CompressionLevel {
+    /// optimize for speed
+    Fast,
+    /// balance speed and size
+    Default,
+    /// optimize for size
+    Best,
+}
+
+impl CompressionLevel {
+    fn flate2(self) -> flate2::Compression {
+        match self {
+            CompressionLevel::Fast => flate2::Compression::fast(),
+            CompressionLevel::Default => flate2::Compression::default(),
+            CompressionLevel::Best => flate2::Compression::best(),
+        }
+    }
+
+    fn lz4(self) -> u32 {
+        match self {
+            CompressionLevel::Fast => 0,
+            CompressionLevel::Default => 9,
+            CompressionLevel::Best => 16,
+        }
+    }
 }
 
 impl Compression {
     /// compress the data
     pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
         match self {
-            Compression::Deflate => {
-                let mut deflater = bufread::DeflateEncoder::new(data, flate2::Compression::fast());
+            Compression::Deflate(level) => {
+                let mut deflater = bufread::DeflateEncoder::new(data, level.flate2());
                 let mut buffer = Vec::new();
                 deflater.read_to_end(&mut buffer)?;
                 Ok(buffer)
             }
-            Compression::Zlib => {
-                let mut deflater = bufread::ZlibEncoder::new(data, flate2::Compression::fast());
+            Compression::Zlib(level) => {
+                let mut deflater = bufread::ZlibEncoder::new(data, level.flate2());
                 let mut buffer = Vec::new();
                 deflater.read_to_end(&mut buffer)?;
                 Ok(buffer)
             }
-            Compression::Gzip => {
-                let mut deflater = bufread::GzEncoder::new(data, flate2::Compression::fast());
+            Compression::Gzip(level) => {
+                let mut deflater = bufread::GzEncoder::new(data, level.flate2());
                 let mut buffer = Vec::new();
                 deflater.read_to_end(&mut buffer)?;
                 Ok(buffer)
             }
             Compression::Snappy => Ok(parity_snappy::compress(data)),
-            Compression::Lz4 => {
+            Compression::Lz4(level) => {
                 let mut buf = Vec::with_capacity(data.len() / 2);
-                let mut encoder = lz4::EncoderBuilder::new().build(&mut buf)?;
+                let mut encoder = lz4::EncoderBuilder::new()
+                    .level(level.lz4())
+                    .build(&mut buf)?;
                 encoder.write_all(data)?;
                 let (_, result) = encoder.finish();
                 match result {
@@ -586,25 +1178,53 @@ impl Compression {
                     Err(err) => Err(err),
                 }
             }
+            Compression::DeflateDictionary(id) => {
+                let dictionary = compression_dictionary_registry().dictionary(id)?;
+                let mut deflater = flate2::Compress::new(flate2::Compression::fast(), false);
+                deflater
+                    .set_dictionary(&dictionary.data)
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                let mut buf = Vec::with_capacity(data.len() / 2 + 64);
+                loop {
+                    let consumed = deflater.total_in() as usize;
+                    let status = deflater
+                        .compress_vec(&data[consumed..], &mut buf, flate2::FlushCompress::Finish)
+                        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                    if status == flate2::Status::StreamEnd {
+                        return Ok(buf);
+                    }
+                    // the output buffer is full
+                    buf.reserve(cmp::max(buf.capacity(), 64));
+                }
+            }
+            Compression::ZstdDictionary(id) => {
+                let dictionary = compression_dictionary_registry().dictionary(id)?;
+                let mut encoder = zstd::stream::Encoder::with_prepared_dictionary(
+                    Vec::with_capacity(data.len() / 2),
+                    &dictionary.zstd_encoder,
+                )?;
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
         }
     }
 
     /// compress the data
     pub fn decompress(self, data: &[u8]) -> io::Result<Vec<u8>> {
         match self {
-            Compression::Deflate => {
+            Compression::Deflate(_) => {
                 let mut inflater = bufread::DeflateDecoder::new(data);
                 let mut buffer = Vec::new();
                 inflater.read_to_end(&mut buffer)?;
                 Ok(buffer)
             }
-            Compression::Zlib => {
+            Compression::Zlib(_) => {
                 let mut inflater = bufread::ZlibDecoder::new(data);
                 let mut buffer = Vec::new();
                 inflater.read_to_end(&mut buffer)?;
                 Ok(buffer)
             }
-            Compression::Gzip => {
+            Compression::Gzip(_) => {
                 let mut inflater = bufread::GzDecoder::new(data);
                 let mut buffer = Vec::new();
                 inflater.read_to_end(&mut buffer)?;
@@ -612,46 +1232,322 @@ impl Compression {
             }
             Compression::Snappy => parity_snappy::decompress(data)
                 .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
-            Compression::Lz4 => {
+            Compression::Lz4(_) => {
                 let mut buf = Vec::with_capacity(data.len() / 2);
                 let mut decoder = lz4::Decoder::new(data)?;
                 io::copy(&mut decoder, &mut buf)?;
                 Ok(buf)
             }
+            Compression::DeflateDictionary(id) => {
+                let mut buffer = Vec::new();
+                deflate_dictionary_decoder(id, data)?.read_to_end(&mut buffer)?;
+                Ok(buffer)
+            }
+            Compression::ZstdDictionary(id) => with_zstd_dictionary_decoder(id, data, |decoder| {
+                let mut buffer = Vec::new();
+                decoder.read_to_end(&mut buffer)?;
+                Ok(buffer)
+            }),
         }
     }
-}
 
-/// Message encoding format
-///
-/// ## Performance (based on simple benchmark tests)
-/// - bincode tends to be the smallest
-/// - based on some simple benchmarks, bincode+snappy are the fastest
-///
-/// You will want to benchmark to see what works best for your use case.
-///
-/// ## Notes
-/// - [MessagePack](https://crates.io/crates/rmp-serde) was dropped because it was found to be too buggy
-///   - there are issues deserializing Option(s), which is a show stopper
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
-pub enum Encoding {
-    /// [Bincode](https://github.com/TyOverby/bincode)
-    Bincode(Option<Compression>),
-    /// [CBOR](http://cbor.io/)
-    CBOR(Option<Compression>),
-    /// [JSON](https://www.json.org/)
-    JSON(Option<Compression>),
+    /// Decompresses the data, guarding against decompression bombs, i.e., inputs that expand to far
+    /// more data than their compressed size would suggest.
+    /// - max_ratio - the max decompressed size to compressed size ratio
+    /// - max_bytes - the max decompressed size
+    ///
+    /// The output is streamed from the decoder, and decompression is aborted as soon as either limit
+    /// is crossed. If a limit is exceeded, then an `io::ErrorKind::InvalidData` error is returned.
+    pub fn decompress_guarded(
+        self,
+        data: &[u8],
+        max_ratio: usize,
+        max_bytes: usize,
+    ) -> io::Result<Vec<u8>> {
+        fn read_guarded<R: Read>(read: R, limit: usize) -> io::Result<Vec<u8>> {
+            let mut buffer = Vec::new();
+            read.take(limit as u64 + 1).read_to_end(&mut buffer)?;
+            Ok(buffer)
+        }
+
+        let ratio_limit = data.len().saturating_mul(max_ratio);
+        let limit = cmp::min(ratio_limit, max_bytes);
+        let limit_exceeded = || {
+            let msg = if ratio_limit < max_bytes {
+                format!(
+                    "decompressed size exceeds the max ratio: {} x {} compressed bytes",
+                    max_ratio,
+                    data.len()
+                )
+            } else {
+                format!("decompressed size exceeds the max size: {} bytes", max_bytes)
+            };
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        };
+
+        let buffer = match self {
+            Compression::Deflate(_) => read_guarded(bufread::DeflateDecoder::new(data), limit)?,
+            Compression::Zlib(_) => read_guarded(bufread::ZlibDecoder::new(data), limit)?,
+            Compression::Gzip(_) => read_guarded(bufread::GzDecoder::new(data), limit)?,
+            Compression::Lz4(_) => read_guarded(lz4::Decoder::new(data)?, limit)?,
+            Compression::DeflateDictionary(id) => {
+                read_guarded(deflate_dictionary_decoder(id, data)?, limit)?
+            }
+            Compression::ZstdDictionary(id) => {
+                with_zstd_dictionary_decoder(id, data, |decoder| read_guarded(decoder, limit))?
+            }
+            // the snappy raw format is prefixed with the decompressed length, which is checked
+            // before any data is decompressed
+            Compression::Snappy => {
+                if snappy_decompressed_len(data)? > limit as u64 {
+                    return Err(limit_exceeded());
+                }
+                self.decompress(data)?
+            }
+        };
+        if buffer.len() > limit {
+            return Err(limit_exceeded());
+        }
+        Ok(buffer)
+    }
 }
 
-impl Encoding {
+/// Parses the varint uncompressed length header of the snappy raw format
+fn snappy_decompressed_len(data: &[u8]) -> io::Result<u64> {
+    let mut len = 0_u64;
+    for (i, byte) in data.iter().take(10).enumerate() {
+        len |= u64::from(byte & 0x7F) << (7 * i as u64);
+        if byte & 0x80 == 0 {
+            return Ok(len);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "invalid snappy length header",
+    ))
+}
+
+/// Returns a decoder for data that was compressed via [Compression::DeflateDictionary](enum.Compression.html#variant.DeflateDictionary)
+/// - the dictionary is set as the inflate preset dictionary, i.e., it is not inflated
+fn deflate_dictionary_decoder(id: DictionaryId, data: &[u8]) -> io::Result<impl Read + '_> {
+    let dictionary = compression_dictionary_registry().dictionary(id)?;
+    let mut inflater = flate2::Decompress::new(false);
+    inflater
+        .set_dictionary(&dictionary.data)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(DeflateDictionaryDecoder { data, inflater })
+}
+
+/// Inflates raw deflate data that was compressed using a preset dictionary
+struct DeflateDictionaryDecoder<'a> {
+    data: &'a [u8],
+    inflater: flate2::Decompress,
+}
+
+impl Read for DeflateDictionaryDecoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let (total_in, total_out) = (self.inflater.total_in(), self.inflater.total_out());
+            let status = self
+                .inflater
+                .decompress(self.data, buf, flate2::FlushDecompress::None)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            let consumed = (self.inflater.total_in() - total_in) as usize;
+            let read = (self.inflater.total_out() - total_out) as usize;
+            self.data = &self.data[consumed..];
+            if read > 0 || buf.is_empty() || status == flate2::Status::StreamEnd {
+                return Ok(read);
+            }
+            if self.data.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated deflate stream",
+                ));
+            }
+            if consumed == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid deflate stream",
+                ));
+            }
+        }
+    }
+}
+
+/// Runs the function with a decoder for data that was compressed via [Compression::ZstdDictionary](enum.Compression.html#variant.ZstdDictionary)
+/// - the decoder borrows the registered digested dictionary, which is why it is scoped to the
+///   function
+fn with_zstd_dictionary_decoder<T, F>(id: DictionaryId, data: &[u8], f: F) -> io::Result<T>
+where
+    F: FnOnce(&mut dyn Read) -> io::Result<T>,
+{
+    let dictionary = compression_dictionary_registry().dictionary(id)?;
+    let mut decoder =
+        zstd::stream::Decoder::with_prepared_dictionary(data, &dictionary.zstd_decoder)?;
+    f(&mut decoder)
+}
+
+/// Compression dictionary identifier
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct DictionaryId(pub u128);
+
+lazy_static! {
+    static ref COMPRESSION_DICTIONARY_REGISTRY: CompressionDictionaryRegistry =
+        CompressionDictionaryRegistry::default();
+}
+
+/// Returns the global CompressionDictionaryRegistry
+pub fn compression_dictionary_registry() -> &'static CompressionDictionaryRegistry {
+    &COMPRESSION_DICTIONARY_REGISTRY
+}
+
+/// Shared pre-trained compression dictionaries, which are used to compress small messages that share
+/// a lot of structure, e.g., repeated JSON field names.
+/// - a dictionary should contain the byte sequences that are most likely to occur in the messages
+/// - dictionaries are immutable once they are in use, i.e., changing the dictionary requires a new
+///   DictionaryId, otherwise messages compressed with the old dictionary will fail to decompress
+#[derive(Debug, Default)]
+pub struct CompressionDictionaryRegistry {
+    dictionaries:
+        std::sync::RwLock<std::collections::BTreeMap<DictionaryId, std::sync::Arc<Dictionary>>>,
+}
+
+struct Dictionary {
+    data: Vec<u8>,
+    // the zstd dictionaries are digested once, when the dictionary is registered
+    zstd_encoder: zstd::dict::EncoderDictionary<'static>,
+    zstd_decoder: zstd::dict::DecoderDictionary<'static>,
+}
+
+impl fmt::Debug for Dictionary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dictionary")
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+impl CompressionDictionaryRegistry {
+    /// Registers the dictionary
+    /// - if a dictionary is already registered for the id, then it is replaced
+    /// - the dictionary is used by both [DeflateDictionary](enum.Compression.html#variant.DeflateDictionary)
+    ///   and [ZstdDictionary](enum.Compression.html#variant.ZstdDictionary)
+    pub fn register(&self, id: DictionaryId, dictionary: &[u8]) -> io::Result<()> {
+        let dictionary = Dictionary {
+            data: dictionary.to_vec(),
+            zstd_encoder: zstd::dict::EncoderDictionary::copy(
+                dictionary,
+                zstd::DEFAULT_COMPRESSION_LEVEL,
+            ),
+            zstd_decoder: zstd::dict::DecoderDictionary::copy(dictionary),
+        };
+        let mut dictionaries = self.dictionaries.write().unwrap();
+        dictionaries.insert(id, std::sync::Arc::new(dictionary));
+        Ok(())
+    }
+
+    /// Unregisters the dictionary, returning true if it was registered
+    pub fn unregister(&self, id: DictionaryId) -> bool {
+        let mut dictionaries = self.dictionaries.write().unwrap();
+        dictionaries.remove(&id).is_some()
+    }
+
+    /// Returns true if the dictionary is registered
+    pub fn is_registered(&self, id: DictionaryId) -> bool {
+        self.dictionaries.read().unwrap().contains_key(&id)
+    }
+
+    fn dictionary(&self, id: DictionaryId) -> io::Result<std::sync::Arc<Dictionary>> {
+        self.dictionaries
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("compression dictionary is not registered: {:?}", id),
+                )
+            })
+    }
+}
+
+/// Message encoding format
+///
+/// ## Performance (based on simple benchmark tests)
+/// - bincode tends to be the smallest
+/// - based on some simple benchmarks, bincode+snappy are the fastest
+///
+/// You will want to benchmark to see what works best for your use case.
+///
+/// ## Notes
+/// - [MessagePack](https://crates.io/crates/rmp-serde) was dropped because it was found to be too buggy
+///   - there are issues deserializing Option(s), which is a show stopper
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum Encoding {
+    /// [Bincode](https://github.com/TyOverby/bincode), which is configured via [BincodeOptions](struct.BincodeOptions.html)
+    Bincode(Option<Compression>, BincodeOptions),
+    /// [CBOR](http://cbor.io/)
+    CBOR(Option<Compression>),
+    /// [JSON](https://www.json.org/)
+    JSON(Option<Compression>),
+}
+
+impl Encoding {
+    /// Negotiates the encoding to use between a client and server, given each peer's encoding
+    /// preferences in priority order.
+    /// - returns the client's most preferred encoding that the server also supports
+    /// - returns None if there is no mutually supported encoding
+    pub fn negotiate(
+        client_preferences: &[Encoding],
+        server_preferences: &[Encoding],
+    ) -> Option<Encoding> {
+        client_preferences
+            .iter()
+            .find(|encoding| server_preferences.contains(encoding))
+            .cloned()
+    }
+
+    /// returns the compression that is applied to the serialized data
+    pub fn compression(self) -> Option<Compression> {
+        match self {
+            Encoding::Bincode(compression, _) => compression,
+            Encoding::CBOR(compression) => compression,
+            Encoding::JSON(compression) => compression,
+        }
+    }
+
+    /// returns the same serialization format using the specified compression
+    pub fn with_compression(self, compression: Option<Compression>) -> Encoding {
+        match self {
+            Encoding::Bincode(_, options) => Encoding::Bincode(compression, options),
+            Encoding::CBOR(_) => Encoding::CBOR(compression),
+            Encoding::JSON(_) => Encoding::JSON(compression),
+        }
+    }
+
+    /// returns true if the data is serialized using the same format, ignoring compression
+    /// - the bincode byte limit does not change the format
+    fn same_format(self, other: Encoding) -> bool {
+        match (self, other) {
+            (Encoding::Bincode(_, a), Encoding::Bincode(_, b)) => {
+                a.int_encoding == b.int_encoding && a.endian == b.endian
+            }
+            (Encoding::CBOR(_), Encoding::CBOR(_)) => true,
+            (Encoding::JSON(_), Encoding::JSON(_)) => true,
+            _ => false,
+        }
+    }
+
     /// encode the data
     pub fn encode<T>(self, data: T) -> Result<Vec<u8>, Error>
     where
         T: serde::Serialize,
     {
         let (data, compression) = match self {
-            Encoding::Bincode(compression) => {
-                let data = bincode::serialize(&data)
+            Encoding::Bincode(compression, options) => {
+                let data = options
+                    .serialize(&data)
                     .map_err(|err| op_error!(errors::SerializationError::new(self, err)))?;
                 (data, compression)
             }
@@ -677,22 +1573,30 @@ impl Encoding {
     }
 
     /// decodes the data
+    ///
+    /// ## Errors
+    /// - [DecompressionError](errors/struct.DecompressionError.html) if the data fails to be decompressed
+    /// - [DeserializationError](errors/struct.DeserializationError.html) if the data fails to be deserialized
     pub fn decode<T>(self, data: &[u8]) -> Result<T, Error>
     where
         T: serde::de::DeserializeOwned,
     {
         match self {
-            Encoding::Bincode(compression) => {
+            Encoding::Bincode(compression, options) => {
                 if let Some(compression) = compression {
                     compression
                         .decompress(data)
+                        .map_err(|err| op_error!(errors::DecompressionError::new(self, err)))
                         .and_then(|data| {
-                            bincode::deserialize(&data)
-                                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                            options
+                                .deserialize(&data)
+                                .map_err(|err| {
+                                    op_error!(errors::DeserializationError::new(self, err))
+                                })
                         })
-                        .map_err(|err| op_error!(errors::DeserializationError::new(self, err)))
                 } else {
-                    bincode::deserialize(data)
+                    options
+                        .deserialize(data)
                         .map_err(|err| op_error!(errors::DeserializationError::new(self, err)))
                 }
             }
@@ -700,11 +1604,13 @@ impl Encoding {
                 if let Some(compression) = compression {
                     compression
                         .decompress(data)
+                        .map_err(|err| op_error!(errors::DecompressionError::new(self, err)))
                         .and_then(|data| {
                             serde_cbor::from_slice(&data)
-                                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                                .map_err(|err| {
+                                    op_error!(errors::DeserializationError::new(self, err))
+                                })
                         })
-                        .map_err(|err| op_error!(errors::DeserializationError::new(self, err)))
                 } else {
                     serde_cbor::from_slice(data)
                         .map_err(|err| op_error!(errors::DeserializationError::new(self, err)))
@@ -714,11 +1620,13 @@ impl Encoding {
                 if let Some(compression) = compression {
                     compression
                         .decompress(data)
+                        .map_err(|err| op_error!(errors::DecompressionError::new(self, err)))
                         .and_then(|data| {
                             serde_json::from_slice(&data)
-                                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                                .map_err(|err| {
+                                    op_error!(errors::DeserializationError::new(self, err))
+                                })
                         })
-                        .map_err(|err| op_error!(errors::DeserializationError::new(self, err)))
                 } else {
                     serde_json::from_slice(data)
                         .map_err(|err| op_error!(errors::DeserializationError::new(self, err)))
@@ -726,18 +1634,352 @@ impl Encoding {
             }
         }
     }
+
+    /// encode the data, streaming it through the serializer and the compression codec into the
+    /// writer
+    /// - the output is the same as [encode()](#method.encode), but no intermediate buffer is used
+    ///   for the uncompressed data
+    /// - Snappy and DeflateDictionary compression are buffered, i.e., the snappy raw format and the
+    ///   deflate preset dictionary compression require the whole message
+    pub fn encode_into<T, W>(self, data: T, writer: W) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+        W: io::Write,
+    {
+        let serialization_error = |err| op_error!(errors::SerializationError::new(self, err));
+        let mut writer = writer;
+        match self.compression() {
+            None => self.serialize_into(&data, &mut writer),
+            Some(Compression::Deflate(level)) => {
+                let mut encoder = flate2::write::DeflateEncoder::new(writer, level.flate2());
+                self.serialize_into(&data, &mut encoder)?;
+                encoder.finish().map(|_| ()).map_err(serialization_error)
+            }
+            Some(Compression::Zlib(level)) => {
+                let mut encoder = flate2::write::ZlibEncoder::new(writer, level.flate2());
+                self.serialize_into(&data, &mut encoder)?;
+                encoder.finish().map(|_| ()).map_err(serialization_error)
+            }
+            Some(Compression::Gzip(level)) => {
+                let mut encoder = flate2::write::GzEncoder::new(writer, level.flate2());
+                self.serialize_into(&data, &mut encoder)?;
+                encoder.finish().map(|_| ()).map_err(serialization_error)
+            }
+            Some(Compression::Lz4(level)) => {
+                let mut encoder = lz4::EncoderBuilder::new()
+                    .level(level.lz4())
+                    .build(writer)
+                    .map_err(serialization_error)?;
+                self.serialize_into(&data, &mut encoder)?;
+                let (_, result) = encoder.finish();
+                result.map_err(serialization_error)
+            }
+            Some(Compression::ZstdDictionary(id)) => {
+                let dictionary = compression_dictionary_registry()
+                    .dictionary(id)
+                    .map_err(serialization_error)?;
+                let mut encoder = zstd::stream::Encoder::with_prepared_dictionary(
+                    writer,
+                    &dictionary.zstd_encoder,
+                )
+                .map_err(serialization_error)?;
+                self.serialize_into(&data, &mut encoder)?;
+                encoder.finish().map(|_| ()).map_err(serialization_error)
+            }
+            Some(Compression::Snappy) | Some(Compression::DeflateDictionary(_)) => {
+                let data = self.encode(data)?;
+                writer.write_all(&data).map_err(serialization_error)
+            }
+        }
+    }
+
+    fn serialize_into<T, W>(self, data: &T, writer: W) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+        W: io::Write,
+    {
+        let mut writer = writer;
+        match self {
+            Encoding::Bincode(_, options) => options
+                .serialize_into(&mut writer, data)
+                .map_err(|err| op_error!(errors::SerializationError::new(self, err))),
+            Encoding::CBOR(_) => serde_cbor::to_writer(&mut writer, data)
+                .map_err(|err| op_error!(errors::SerializationError::new(self, err))),
+            Encoding::JSON(_) => serde_json::to_writer(&mut writer, data)
+                .map_err(|err| op_error!(errors::SerializationError::new(self, err))),
+        }
+    }
+
+    /// decodes the data, streaming it from the reader through the compression codec and the
+    /// deserializer
+    /// - the data must have been encoded via [encode()](#method.encode) or
+    ///   [encode_into()](#method.encode_into)
+    /// - Snappy and DeflateDictionary compressed data is read fully before it is decompressed
+    /// - IO errors that occur while compressed data is read are reported as a
+    ///   [DecompressionError](errors/struct.DecompressionError.html)
+    pub fn decode_from<T, R>(self, reader: R) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+        R: io::Read,
+    {
+        let decompression_error = |err| op_error!(errors::DecompressionError::new(self, err));
+        match self.compression() {
+            None => self.deserialize_from(reader),
+            Some(Compression::Deflate(_)) => {
+                self.deserialize_decompressed(flate2::read::DeflateDecoder::new(reader))
+            }
+            Some(Compression::Zlib(_)) => {
+                self.deserialize_decompressed(flate2::read::ZlibDecoder::new(reader))
+            }
+            Some(Compression::Gzip(_)) => {
+                self.deserialize_decompressed(flate2::read::GzDecoder::new(reader))
+            }
+            Some(Compression::Lz4(_)) => {
+                let decoder = lz4::Decoder::new(reader).map_err(decompression_error)?;
+                self.deserialize_decompressed(decoder)
+            }
+            Some(Compression::ZstdDictionary(id)) => {
+                let dictionary = compression_dictionary_registry()
+                    .dictionary(id)
+                    .map_err(decompression_error)?;
+                let decoder = zstd::stream::Decoder::with_prepared_dictionary(
+                    io::BufReader::new(reader),
+                    &dictionary.zstd_decoder,
+                )
+                .map_err(decompression_error)?;
+                self.deserialize_decompressed(decoder)
+            }
+            Some(Compression::Snappy) | Some(Compression::DeflateDictionary(_)) => {
+                let mut reader = reader;
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data).map_err(decompression_error)?;
+                self.decode(&data)
+            }
+        }
+    }
+
+    /// decoder read errors are tracked in order to distinguish corrupt compressed data from data that
+    /// fails to deserialize
+    fn deserialize_decompressed<T, R>(self, decoder: R) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+        R: io::Read,
+    {
+        let mut decoder = ReadErrorTracker {
+            read: decoder,
+            err: None,
+        };
+        self.deserialize_from(&mut decoder)
+            .map_err(|err| match decoder.err.take() {
+                Some(read_err) => op_error!(errors::DecompressionError::new(self, read_err)),
+                None => err,
+            })
+    }
+
+    fn deserialize_from<T, R>(self, reader: R) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+        R: io::Read,
+    {
+        match self {
+            Encoding::Bincode(_, options) => options
+                .deserialize_from(reader)
+                .map_err(|err| op_error!(errors::DeserializationError::new(self, err))),
+            Encoding::CBOR(_) => serde_cbor::from_reader(reader)
+                .map_err(|err| op_error!(errors::DeserializationError::new(self, err))),
+            Encoding::JSON(_) => serde_json::from_reader(reader)
+                .map_err(|err| op_error!(errors::DeserializationError::new(self, err))),
+        }
+    }
+}
+
+/// Remembers the first read error
+struct ReadErrorTracker<R> {
+    read: R,
+    err: Option<String>,
+}
+
+impl<R: io::Read> io::Read for ReadErrorTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read.read(buf).map_err(|err| {
+            if self.err.is_none() {
+                self.err = Some(err.to_string());
+            }
+            err
+        })
+    }
 }
 
 impl fmt::Display for Encoding {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Encoding::Bincode(compression) => write!(f, "Bincode({:?})", compression),
+            Encoding::Bincode(compression, options) => {
+                write!(f, "Bincode({:?}, {:?})", compression, options)
+            }
             Encoding::CBOR(compression) => write!(f, "CBOR({:?})", compression),
             Encoding::JSON(compression) => write!(f, "JSON({:?})", compression),
         }
     }
 }
 
+/// Bincode serialization options, which are carried by [Encoding::Bincode](enum.Encoding.html#variant.Bincode)
+/// - the default options match bincode's default config, i.e., no byte limit, fixed width int
+///   encoding, and little endian
+/// - a byte limit should be configured when decoding untrusted input - decoding fails if it requires
+///   reading or allocating more bytes than the limit
+/// - the options are part of the Encoding, which is carried in the message [Metadata](struct.Metadata.html).
+///   Thus, the receiver decodes the message data using the sender's options.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct BincodeOptions {
+    limit: Option<u64>,
+    int_encoding: IntEncoding,
+    endian: Endian,
+}
+
+/// Evaluates the body using the bincode Options that match the BincodeOptions, which are bound to
+/// the specified identifier. Each bincode option changes the Options type, thus each combination
+/// is spelled out.
+macro_rules! with_bincode_options {
+    ($options:expr, $config:ident => $body:expr) => {{
+        let options: &BincodeOptions = $options;
+        let config = bincode::DefaultOptions::new().allow_trailing_bytes();
+        match (options.int_encoding, options.endian) {
+            (IntEncoding::Fixint, Endian::Little) => with_bincode_limit!(
+                options.limit,
+                config.with_fixint_encoding().with_little_endian(),
+                $config => $body
+            ),
+            (IntEncoding::Fixint, Endian::Big) => with_bincode_limit!(
+                options.limit,
+                config.with_fixint_encoding().with_big_endian(),
+                $config => $body
+            ),
+            (IntEncoding::Varint, Endian::Little) => with_bincode_limit!(
+                options.limit,
+                config.with_varint_encoding().with_little_endian(),
+                $config => $body
+            ),
+            (IntEncoding::Varint, Endian::Big) => with_bincode_limit!(
+                options.limit,
+                config.with_varint_encoding().with_big_endian(),
+                $config => $body
+            ),
+        }
+    }};
+}
+
+macro_rules! with_bincode_limit {
+    ($limit:expr, $options:expr, $config:ident => $body:expr) => {
+        match $limit {
+            Some(limit) => {
+                let $config = $options.with_limit(limit);
+                $body
+            }
+            None => {
+                let $config = $options.with_no_limit();
+                $body
+            }
+        }
+    };
+}
+
+impl BincodeOptions {
+    /// Sets the max number of bytes that can be serialized or deserialized
+    pub fn set_limit(mut self, limit: u64) -> BincodeOptions {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets how integers are encoded
+    pub fn set_int_encoding(mut self, int_encoding: IntEncoding) -> BincodeOptions {
+        self.int_encoding = int_encoding;
+        self
+    }
+
+    /// Sets the byte order
+    pub fn set_endian(mut self, endian: Endian) -> BincodeOptions {
+        self.endian = endian;
+        self
+    }
+
+    /// Max number of bytes that can be serialized or deserialized
+    /// - None means there is no limit
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// Integer encoding
+    pub fn int_encoding(&self) -> IntEncoding {
+        self.int_encoding
+    }
+
+    /// Byte order
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    fn serialize<T>(&self, data: &T) -> bincode::Result<Vec<u8>>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        with_bincode_options!(self, config => config.serialize(data))
+    }
+
+    fn serialize_into<T, W>(&self, writer: W, data: &T) -> bincode::Result<()>
+    where
+        T: serde::Serialize + ?Sized,
+        W: io::Write,
+    {
+        with_bincode_options!(self, config => config.serialize_into(writer, data))
+    }
+
+    fn deserialize<T>(&self, data: &[u8]) -> bincode::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        with_bincode_options!(self, config => config.deserialize(data))
+    }
+
+    fn deserialize_from<T, R>(&self, reader: R) -> bincode::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        R: io::Read,
+    {
+        with_bincode_options!(self, config => config.deserialize_from(reader))
+    }
+}
+
+/// Bincode integer encoding
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum IntEncoding {
+    /// integers are encoded using their fixed width, e.g., a u64 is always encoded as 8 bytes
+    Fixint,
+    /// integers are encoded using a variable number of bytes, i.e., small values are encoded using
+    /// fewer bytes
+    Varint,
+}
+
+impl Default for IntEncoding {
+    fn default() -> IntEncoding {
+        IntEncoding::Fixint
+    }
+}
+
+/// Byte order
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum Endian {
+    /// little endian
+    Little,
+    /// big endian, i.e., network byte order
+    Big,
+}
+
+impl Default for Endian {
+    fn default() -> Endian {
+        Endian::Little
+    }
+}
+
 /// Deadline
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Deadline {
@@ -752,13 +1994,24 @@ impl Deadline {
     /// - the starting_time is used when the deadline is Deadline::MessageTimeoutMillis. The timeout
     ///   is taken relative to the specified starting time. If the deadline time has passed, then
     ///   a zero duration is returned.
+    /// - the current time is read from the [SystemClock](clock/struct.SystemClock.html)
     pub fn duration(&self, starting_time: chrono::DateTime<Utc>) -> chrono::Duration {
+        self.duration_with_clock(starting_time, &clock::SystemClock)
+    }
+
+    /// converts the deadline into a timeout duration, using the specified clock to read the current time
+    /// - see [duration()](enum.Deadline.html#method.duration)
+    pub fn duration_with_clock<C: clock::Clock>(
+        &self,
+        starting_time: chrono::DateTime<Utc>,
+        clock: &C,
+    ) -> chrono::Duration {
         match self {
             Deadline::ProcessingTimeoutMillis(millis) => {
                 chrono::Duration::milliseconds(*millis as i64)
             }
             Deadline::MessageTimeoutMillis(millis) => {
-                let now = Utc::now();
+                let now = clock.now();
                 if starting_time >= now {
                     return chrono::Duration::zero();
                 }
@@ -777,11 +2030,50 @@ impl Deadline {
             }
         }
     }
+
+    /// returns true if the deadline has expired, i.e., there is no time remaining
+    /// - Deadline::ProcessingTimeoutMillis is relative to the processing start time, which means it
+    ///   never expires based on the wall clock
+    /// - Deadline::MessageTimeoutMillis expires once the wall clock passes the deadline
+    /// - see [duration()](enum.Deadline.html#method.duration)
+    pub fn is_expired(&self, starting_time: chrono::DateTime<Utc>) -> bool {
+        self.is_expired_with_clock(starting_time, &clock::SystemClock)
+    }
+
+    /// returns true if the deadline has expired according to the specified clock, i.e., there is no
+    /// time remaining
+    pub fn is_expired_with_clock<C: clock::Clock>(
+        &self,
+        starting_time: chrono::DateTime<Utc>,
+        clock: &C,
+    ) -> bool {
+        self.duration_with_clock(starting_time, clock) == chrono::Duration::zero()
+    }
+
+    /// returns the time remaining until the deadline in millis
+    /// - 0 means the deadline has expired
+    /// - see [duration()](enum.Deadline.html#method.duration)
+    pub fn remaining_millis(&self, starting_time: chrono::DateTime<Utc>) -> u64 {
+        self.remaining_millis_with_clock(starting_time, &clock::SystemClock)
+    }
+
+    /// returns the time remaining until the deadline in millis, using the specified clock to read
+    /// the current time
+    pub fn remaining_millis_with_clock<C: clock::Clock>(
+        &self,
+        starting_time: chrono::DateTime<Utc>,
+        clock: &C,
+    ) -> u64 {
+        // the duration is never negative
+        self.duration_with_clock(starting_time, clock).num_milliseconds() as u64
+    }
 }
 
 #[oysterpack_uid::macros::ulid]
 /// Unique message type identifier
 /// - MessageTypeId enables MessageType(s) to be defined as constants
+/// - use the [message_type_id!](../macro.message_type_id.html) macro to define constants, which
+///   rejects a zero id at compile time
 pub struct MessageTypeId(pub u128);
 
 impl MessageTypeId {
@@ -792,6 +2084,13 @@ impl MessageTypeId {
 }
 
 /// Identifies the message type, which tells us how to decode the bytes message data.
+///
+/// A MessageType can only be created from a [MessageTypeId](struct.MessageTypeId.html):
+/// ```compile_fail
+/// use oysterpack_core::message::{MessageType, SessionId};
+///
+/// let msg_type = MessageType::from(SessionId::generate().ulid());
+/// ```
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct MessageType(ULID);
 
@@ -824,16 +2123,128 @@ impl fmt::Display for MessageType {
     }
 }
 
+lazy_static! {
+    static ref MESSAGE_TYPE_REGISTRY: MessageTypeRegistry = MessageTypeRegistry::default();
+}
+
+/// Returns the global MessageTypeRegistry
+pub fn message_type_registry() -> &'static MessageTypeRegistry {
+    &MESSAGE_TYPE_REGISTRY
+}
+
+/// Tracks the message types that are handled, which enables runtime introspection.
+/// - services register the MessageTypeId(s) that they handle, along with an optional name
+#[derive(Debug, Default)]
+pub struct MessageTypeRegistry {
+    types: std::sync::RwLock<std::collections::BTreeMap<MessageType, Option<String>>>,
+}
+
+impl MessageTypeRegistry {
+    /// Registers the message type
+    /// - if the message type is already registered, then its name is replaced
+    pub fn register(&self, type_id: MessageTypeId, name: Option<&str>) {
+        let mut types = self.types.write().unwrap();
+        types.insert(type_id.message_type(), name.map(str::to_string));
+    }
+
+    /// Unregisters the message type, returning true if it was registered
+    pub fn unregister(&self, type_id: MessageTypeId) -> bool {
+        let mut types = self.types.write().unwrap();
+        types.remove(&type_id.message_type()).is_some()
+    }
+
+    /// Returns true if the message type is registered
+    pub fn is_registered(&self, type_id: MessageTypeId) -> bool {
+        self.types
+            .read()
+            .unwrap()
+            .contains_key(&type_id.message_type())
+    }
+
+    /// Returns the registered message types, ordered by MessageType
+    pub fn registered_types(&self) -> Vec<(MessageType, Option<String>)> {
+        self.types
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(msg_type, name)| (*msg_type, name.clone()))
+            .collect()
+    }
+}
+
+lazy_static! {
+    static ref LAST_MONOTONIC_ULID: std::sync::Mutex<Option<ULID>> = std::sync::Mutex::new(None);
+}
+
+/// Generates a ULID that is greater than all ULIDs previously generated via this function within
+/// the process.
+/// - ULID timestamps have millisecond granularity. Within the same millisecond, the random
+///   component of the last ULID is incremented.
+/// - if the clock moves backwards, then the last ULID continues to be incremented until the clock
+///   catches up
+/// - if the random component overflows, which is highly unlikely, then this blocks until the clock
+///   moves past the last ULID's timestamp
+fn generate_monotonic_ulid() -> ULID {
+    let mut last_ulid = LAST_MONOTONIC_ULID
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    let ulid = match *last_ulid {
+        None => ULID::generate(),
+        Some(last) => {
+            let ulid = ULID::generate();
+            if ulid.datetime() > last.datetime() {
+                ulid
+            } else {
+                let ulid = last.increment();
+                if ulid > last {
+                    ulid
+                } else {
+                    loop {
+                        std::thread::yield_now();
+                        let ulid = ULID::generate();
+                        if ulid > last {
+                            break ulid;
+                        }
+                    }
+                }
+            }
+        }
+    };
+    *last_ulid = Some(ulid);
+    ulid
+}
+
 /// Message instance unique identifier.
+///
+/// An InstanceId cannot be created from a ULID, i.e., instance ids are always generated:
+/// ```compile_fail
+/// use oysterpack_core::message::InstanceId;
+/// use oysterpack_uid::ULID;
+///
+/// let instance_id: InstanceId = ULID::generate().into();
+/// ```
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct InstanceId(ULID);
 
 impl InstanceId {
     /// generates a new MessageInstance
+    /// - instance ids that are generated within the same millisecond are randomly ordered - see
+    ///   [generate_monotonic()](#method.generate_monotonic)
     pub fn generate() -> InstanceId {
         InstanceId(ULID::generate())
     }
 
+    /// generates a new MessageInstance that is greater than all instance ids that were previously
+    /// generated via this method within the process, i.e., the instance ids sort in creation order
+    /// even when they are generated within the same millisecond
+    /// - this is meant to be used when message ordering matters, e.g., see
+    ///   [Sequence](enum.Sequence.html)
+    /// - the monotonic state is shared process wide with [SessionId::generate_monotonic()](struct.SessionId.html#method.generate_monotonic),
+    ///   which means generation is serialized across threads
+    pub fn generate_monotonic() -> InstanceId {
+        InstanceId(generate_monotonic_ulid())
+    }
+
     /// ULID getter
     pub fn ulid(&self) -> ULID {
         self.0
@@ -843,6 +2254,22 @@ impl InstanceId {
     pub fn domain_ulid(&self) -> DomainULID {
         DomainULID::from_ulid(MESSAGE_INSTANCE_ID_DOMAIN, self.0)
     }
+
+    /// Strict deserialization, which rejects instance ids whose ULID timestamp is implausibly far
+    /// from the current time - see [ULID::deserialize_strict()](../../oysterpack_uid/ulid/struct.ULID.html#method.deserialize_strict)
+    /// - the message timestamp is derived from the InstanceId
+    /// - opt in via `#[serde(deserialize_with = "InstanceId::deserialize_strict")]`
+    pub fn deserialize_strict<'de, D>(deserializer: D) -> Result<InstanceId, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let instance_id = <InstanceId as serde::Deserialize>::deserialize(deserializer)?;
+        instance_id
+            .0
+            .validate_timestamp(oysterpack_uid::DEFAULT_TIMESTAMP_TOLERANCE)
+            .map_err(serde::de::Error::custom)?;
+        Ok(instance_id)
+    }
 }
 
 impl fmt::Display for InstanceId {
@@ -891,6 +2318,32 @@ where
         }
     }
 
+    /// Encodes the message data on the specified thread pool.
+    ///
+    /// Serialization and compression are CPU bound. Encoding large payloads inline would block the
+    /// task that is running on an async executor thread. Instead, the work is offloaded to the
+    /// thread pool and the encoded message is returned async.
+    pub fn encode_on(
+        self,
+        executor: &tokio_threadpool::ThreadPool,
+    ) -> impl futures::Future<Item = Message<MessageBytes>, Error = Error>
+    where
+        T: Send + 'static,
+    {
+        let (tx, rx) = futures::sync::oneshot::channel();
+        executor.spawn(futures::future::lazy(move || {
+            // if the receiver was dropped, then the result is no longer needed
+            let _ = tx.send(self.encode());
+            Ok(())
+        }));
+        futures::Future::then(rx, |result| match result {
+            Ok(result) => result,
+            Err(_) => Err(op_error!(errors::MessageError::EncodingError(
+                errors::EncodingError::TaskCanceled
+            ))),
+        })
+    }
+
     /// converts itself into an EncodedMessage
     pub fn encoded_message(
         self,
@@ -931,10 +2384,67 @@ impl Message<MessageBytes> {
             Err(err) => Err(err),
         }
     }
-}
 
-/// Encoded message data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+    /// Transcodes the message data to the specified encoding without knowing the data type, and
+    /// updates the metadata encoding.
+    /// - if only the compression differs, then the data is decompressed and recompressed, i.e., it
+    ///   is not deserialized
+    /// - CBOR and JSON are self-describing, thus the data is transcoded between them via a generic
+    ///   intermediate value
+    /// - Bincode is not self-describing, thus transcoding between Bincode and another format fails
+    ///   with [EncodingError::UntypedTranscodeNotSupported](errors/enum.EncodingError.html#variant.UntypedTranscodeNotSupported) -
+    ///   use [transcode_as()](#method.transcode_as) instead
+    pub fn transcode(self, to: Encoding) -> Result<Message<MessageBytes>, Error> {
+        let from = self.metadata.encoding;
+        let data = if from.same_format(to) {
+            let data = match from.compression() {
+                Some(compression) => compression
+                    .decompress(self.data.data())
+                    .map_err(|err| op_error!(errors::DeserializationError::new(from, err)))?,
+                None => self.data.0,
+            };
+            match to.compression() {
+                Some(compression) => compression
+                    .compress(&data)
+                    .map_err(|err| op_error!(errors::SerializationError::new(to, err)))?,
+                None => data,
+            }
+        } else {
+            match (from, to) {
+                (Encoding::Bincode(..), _) | (_, Encoding::Bincode(..)) => {
+                    return Err(op_error!(errors::MessageError::EncodingError(
+                        errors::EncodingError::UntypedTranscodeNotSupported { from, to }
+                    )));
+                }
+                _ => {
+                    let value: serde_cbor::Value = from.decode(self.data.data())?;
+                    to.encode(value)?
+                }
+            }
+        };
+        let mut metadata = self.metadata;
+        metadata.encoding = to;
+        Ok(Message {
+            metadata,
+            data: MessageBytes(data),
+        })
+    }
+
+    /// Transcodes the message data to the specified encoding by decoding it as the specified type,
+    /// and then re-encoding it. The metadata encoding is updated.
+    /// - this works for any combination of encodings, including Bincode
+    pub fn transcode_as<T>(self, to: Encoding) -> Result<Message<MessageBytes>, Error>
+    where
+        T: fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+    {
+        let mut msg = self.decode::<T>()?;
+        msg.metadata.encoding = to;
+        msg.encode()
+    }
+}
+
+/// Encoded message data
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncodedMessage {
     sender: Address,
     recipient: Address,
@@ -966,7 +2476,18 @@ impl EncodedMessage {
 
     /// return the recipient's address
     pub fn recipient(&self) -> &Address {
-        &self.sender
+        &self.recipient
+    }
+
+    /// swaps the sender and recipient addresses, leaving the encoded message untouched
+    /// - this is a cheap operation, which enables relays to forward messages without having to
+    ///   decode and re-encode the message data
+    pub fn with_addresses(self, sender: Address, recipient: Address) -> EncodedMessage {
+        EncodedMessage {
+            sender,
+            recipient,
+            msg: self.msg,
+        }
     }
 
     /// converts into an OpenEnvelope
@@ -984,6 +2505,32 @@ impl EncodedMessage {
         })
     }
 
+    /// converts into an OpenEnvelope containing [SignedMessageBytes](struct.SignedMessageBytes.html)
+    /// - the message is signed using the sender's private signing key
+    /// - the receiver should use [SealedEnvelope::open_verified_decode()](struct.SealedEnvelope.html#method.open_verified_decode)
+    pub fn signed_open_envelope(self, key: &sign::SecretKey) -> Result<OpenEnvelope, Error> {
+        let msg = MessageBytes(bincode::serialize(&self.msg).map_err(|err| {
+            op_error!(errors::MessageError::EncodedMessageSerializationFailed(
+                self.sender(),
+                errors::ErrorInfo(err.to_string())
+            ))
+        })?);
+        let signed_hash = SignedHash::sign(&msg.hash(), key);
+        let signed_msg =
+            bincode::serialize(&SignedMessageBytes { msg, signed_hash }).map_err(|err| {
+                op_error!(errors::MessageError::EncodingError(
+                    errors::EncodingError::InvalidSealedSignedMessage(ErrorMessage(
+                        err.to_string()
+                    ))
+                ))
+            })?;
+        Ok(OpenEnvelope {
+            sender: self.sender,
+            recipient: self.recipient,
+            msg: MessageBytes(signed_msg),
+        })
+    }
+
     /// converts the MessageBytes data to the specified type, based on the message metatdata
     pub fn decode<T>(self) -> Result<(Addresses, Message<T>), Error>
     where
@@ -1047,7 +2594,245 @@ impl Addresses {
     }
 }
 
+/// A batch of messages that is transferred as a single message, e.g., to pack several replies into
+/// a single nng reply in order to amortize round trips.
+///
+/// The batch is framed using length prefixes:
+///
+/// `| u32 BE message count | (u32 BE length | bincode EncodedMessage)* |`
+///
+/// The whole encoded batch must fit within [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html), and may
+/// contain at most [DEFAULT_MAX_BATCH_FRAMES](constant.DEFAULT_MAX_BATCH_FRAMES.html) messages.
+#[derive(Debug, Clone, Default)]
+pub struct MessageBatch {
+    msgs: Vec<EncodedMessage>,
+    encoded_size: usize,
+}
+
+impl MessageBatch {
+    /// size of the message count header and of each message length prefix
+    const LEN_PREFIX_SIZE: usize = framing::LEN_PREFIX_SIZE;
+
+    /// constructor
+    pub fn new() -> MessageBatch {
+        MessageBatch {
+            msgs: Vec::new(),
+            encoded_size: MessageBatch::LEN_PREFIX_SIZE,
+        }
+    }
+
+    /// Appends the message to the batch
+    ///
+    /// ## Errors
+    /// - [MessageError::MessageBatchTooLarge](errors/enum.MessageError.html#variant.MessageBatchTooLarge)
+    ///   if adding the message would make the encoded batch exceed [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html).
+    ///   The message is not added to the batch.
+    /// - [MessageError::MessageBatchTooManyFrames](errors/enum.MessageError.html#variant.MessageBatchTooManyFrames)
+    ///   if the batch already contains [DEFAULT_MAX_BATCH_FRAMES](constant.DEFAULT_MAX_BATCH_FRAMES.html)
+    ///   messages
+    pub fn push(&mut self, msg: EncodedMessage) -> Result<(), Error> {
+        if self.msgs.len() >= DEFAULT_MAX_BATCH_FRAMES {
+            return Err(op_error!(errors::MessageError::MessageBatchTooManyFrames {
+                count: self.msgs.len() + 1,
+                max: DEFAULT_MAX_BATCH_FRAMES
+            }));
+        }
+        let msg_size = bincode::serialized_size(&msg).map_err(|err| {
+            op_error!(errors::MessageError::EncodingError(
+                errors::EncodingError::InvalidMessageBatch(ErrorMessage(err.to_string()))
+            ))
+        })? as usize;
+        let encoded_size = self.encoded_size + MessageBatch::LEN_PREFIX_SIZE + msg_size;
+        if encoded_size > MAX_MSG_SIZE {
+            return Err(op_error!(errors::MessageError::MessageBatchTooLarge {
+                size: encoded_size,
+                max: MAX_MSG_SIZE
+            }));
+        }
+        self.msgs.push(msg);
+        self.encoded_size = encoded_size;
+        Ok(())
+    }
+
+    /// Returns an iterator over the messages in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = &EncodedMessage> {
+        self.msgs.iter()
+    }
+
+    /// number of messages in the batch
+    pub fn len(&self) -> usize {
+        self.msgs.len()
+    }
+
+    /// returns true if the batch contains no messages
+    pub fn is_empty(&self) -> bool {
+        self.msgs.is_empty()
+    }
+
+    /// size of the encoded batch in bytes
+    pub fn encoded_size(&self) -> usize {
+        self.encoded_size
+    }
+
+    /// encodes the batch and writes it to the io stream
+    pub fn encode<W: ?Sized>(&self, wr: &mut W) -> Result<(), Error>
+    where
+        W: io::Write,
+    {
+        fn encoding_error<E: fmt::Display>(err: E) -> Error {
+            op_error!(errors::MessageError::EncodingError(
+                errors::EncodingError::InvalidMessageBatch(ErrorMessage(err.to_string()))
+            ))
+        }
+
+        wr.write_all(&(self.msgs.len() as u32).to_be_bytes())
+            .map_err(encoding_error)?;
+        for msg in self.msgs.iter() {
+            let bytes = bincode::serialize(msg).map_err(encoding_error)?;
+            framing::write_framed(wr, &bytes).map_err(encoding_error)?;
+        }
+        Ok(())
+    }
+
+    /// decodes the io stream to construct a new MessageBatch
+    /// - the batch is rejected as soon as the bytes read exceed [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html)
+    /// - the batch is rejected if it declares more than [DEFAULT_MAX_BATCH_FRAMES](constant.DEFAULT_MAX_BATCH_FRAMES.html)
+    ///   frames - see [decode_with_max_frames()](#method.decode_with_max_frames)
+    pub fn decode<R>(read: R) -> Result<MessageBatch, Error>
+    where
+        R: io::Read,
+    {
+        MessageBatch::decode_with_max_frames(read, DEFAULT_MAX_BATCH_FRAMES)
+    }
+
+    /// decodes the io stream to construct a new MessageBatch
+    /// - the declared frame count is checked against max_frames before any frames are read, i.e.,
+    ///   a batch header that claims an excessive number of frames is rejected up front
+    ///
+    /// ## Errors
+    /// - [MessageError::MessageBatchTooManyFrames](errors/enum.MessageError.html#variant.MessageBatchTooManyFrames)
+    ///   if the batch declares more than max_frames frames
+    /// - [MessageError::MessageBatchTooLarge](errors/enum.MessageError.html#variant.MessageBatchTooLarge)
+    ///   if the frames exceed [MAX_MSG_SIZE](constant.MAX_MSG_SIZE.html)
+    pub fn decode_with_max_frames<R>(read: R, max_frames: usize) -> Result<MessageBatch, Error>
+    where
+        R: io::Read,
+    {
+        fn decoding_error<E: fmt::Display>(err: E) -> Error {
+            op_error!(errors::MessageError::DecodingError(
+                errors::DecodingError::InvalidMessageBatch(ErrorMessage(err.to_string()))
+            ))
+        }
+
+        fn read_len<R: io::Read>(read: &mut R) -> Result<usize, Error> {
+            framing::read_frame_len(read)
+                .map_err(decoding_error)?
+                .ok_or_else(|| decoding_error("missing frame"))
+        }
+
+        let mut read = read;
+        let count = read_len(&mut read)?;
+        if count > max_frames {
+            return Err(op_error!(errors::MessageError::MessageBatchTooManyFrames {
+                count,
+                max: max_frames
+            }));
+        }
+        let mut batch = MessageBatch {
+            msgs: Vec::with_capacity(count),
+            encoded_size: MessageBatch::LEN_PREFIX_SIZE,
+        };
+        for _ in 0..count {
+            let len = read_len(&mut read)?;
+            let encoded_size = batch.encoded_size + MessageBatch::LEN_PREFIX_SIZE + len;
+            if encoded_size > MAX_MSG_SIZE {
+                return Err(op_error!(errors::MessageError::MessageBatchTooLarge {
+                    size: encoded_size,
+                    max: MAX_MSG_SIZE
+                }));
+            }
+            let frame = framing::read_frame(&mut read, len).map_err(decoding_error)?;
+            batch
+                .msgs
+                .push(bincode::deserialize(&frame).map_err(decoding_error)?);
+            batch.encoded_size = encoded_size;
+        }
+        Ok(batch)
+    }
+
+    /// Converts an nng:Message into a MessageBatch
+    /// - delegates to `TryFrom<nng::Message>`
+    pub fn try_from_nng_message(msg: nng::Message) -> Result<MessageBatch, Error> {
+        MessageBatch::try_from(msg)
+    }
+
+    /// Converts itself into an nng:Message
+    /// - delegates to `TryFrom<MessageBatch> for nng::Message`
+    pub fn try_into_nng_message(self) -> Result<nng::Message, Error> {
+        nng::Message::try_from(self)
+    }
+}
+
+/// Converts an nng:Message into a MessageBatch
+impl TryFrom<nng::Message> for MessageBatch {
+    type Error = Error;
+
+    fn try_from(msg: nng::Message) -> Result<MessageBatch, Error> {
+        MessageBatch::decode(&**msg)
+    }
+}
+
+/// Converts the MessageBatch into an nng:Message
+impl TryFrom<MessageBatch> for nng::Message {
+    type Error = Error;
+
+    fn try_from(batch: MessageBatch) -> Result<nng::Message, Error> {
+        let mut bytes = Vec::with_capacity(batch.encoded_size);
+        batch.encode(&mut bytes)?;
+        let mut msg = nng::Message::with_capacity(bytes.len()).map_err(|err| {
+            op_error!(errors::NngMessageError::from(ErrorMessage(format!("Failed to create an empty message with a pre-allocated body buffer (capacity = {}): {}", bytes.len(), err))))
+        })?;
+        msg.push_back(&bytes).map_err(|err| {
+            op_error!(errors::NngMessageError::from(ErrorMessage(format!(
+                "Failed to append data to the back of the message body: {}",
+                err
+            ))))
+        })?;
+        Ok(msg)
+    }
+}
+
+impl IntoIterator for MessageBatch {
+    type Item = EncodedMessage;
+    type IntoIter = std::vec::IntoIter<EncodedMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.msgs.into_iter()
+    }
+}
+
 /// Each new client connection is assigned a new SessionId
+///
+/// A SessionId cannot be implicitly created from a ULID, which prevents ids from other domains from
+/// being used as a SessionId:
+/// ```compile_fail
+/// use oysterpack_core::message::SessionId;
+/// use oysterpack_uid::ULID;
+///
+/// let session_id: SessionId = ULID::generate().into();
+/// ```
+///
+/// ```compile_fail
+/// use oysterpack_core::message::{InstanceId, SessionId};
+///
+/// let session_id = SessionId::from(InstanceId::generate().ulid());
+/// ```
+///
+/// ```compile_fail
+/// use oysterpack_core::message::{MessageType, SessionId};
+///
+/// let msg_type: MessageType = SessionId::generate();
+/// ```
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct SessionId(ULID);
 
@@ -1057,16 +2842,41 @@ impl SessionId {
         SessionId(ULID::generate())
     }
 
+    /// generates a new SessionId that is greater than all session ids that were previously
+    /// generated via this method within the process
+    /// - see [InstanceId::generate_monotonic()](struct.InstanceId.html#method.generate_monotonic)
+    pub fn generate_monotonic() -> SessionId {
+        SessionId(generate_monotonic_ulid())
+    }
+
     /// session ULID
     pub fn ulid(&self) -> ULID {
         self.0
     }
-}
 
-impl From<ULID> for SessionId {
-    fn from(ulid: ULID) -> SessionId {
+    /// Converts the ULID into a SessionId.
+    ///
+    /// The caller is responsible for ensuring that the ULID is in fact a SessionId, e.g., it was
+    /// obtained via [ulid()](#method.ulid) and persisted. The conversion is deliberately explicit,
+    /// which makes it easy to spot at the call site and audit.
+    pub fn from_ulid_unchecked(ulid: ULID) -> SessionId {
         SessionId(ulid)
     }
+
+    /// Strict deserialization, which rejects session ids whose ULID timestamp is implausibly far
+    /// from the current time - see [ULID::deserialize_strict()](../../oysterpack_uid/ulid/struct.ULID.html#method.deserialize_strict)
+    /// - opt in via `#[serde(deserialize_with = "SessionId::deserialize_strict")]`
+    pub fn deserialize_strict<'de, D>(deserializer: D) -> Result<SessionId, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let session_id = <SessionId as serde::Deserialize>::deserialize(deserializer)?;
+        session_id
+            .0
+            .validate_timestamp(oysterpack_uid::DEFAULT_TIMESTAMP_TOLERANCE)
+            .map_err(serde::de::Error::custom)?;
+        Ok(session_id)
+    }
 }
 
 impl fmt::Display for SessionId {
@@ -1250,8 +3060,51 @@ mod test {
         });
     }
 
+    #[test]
+    fn address_bytes() {
+        let (pub_key, _) = box_::gen_keypair();
+        let address = Address::from(pub_key);
+        let bytes = address.as_bytes();
+        assert_eq!(&bytes[..], &address.public_key().0[..]);
+        let address_2 = Address::from_bytes(bytes);
+        assert_eq!(address_2, address);
+        assert_eq!(address_2.to_string(), address.to_string());
+    }
+
+    #[test]
+    fn address_from_str() {
+        use super::{base58, errors};
+        use std::str::FromStr;
+
+        for _ in 0..10 {
+            let (pub_key, _) = box_::gen_keypair();
+            let address = Address::from(pub_key);
+            assert_eq!(Address::from_str(&address.to_string()).unwrap(), address);
+            let address_2: Address = address.to_string().parse().unwrap();
+            assert_eq!(address_2, address);
+        }
+
+        // invalid base58, i.e., '0' is not in the base58 alphabet
+        match Address::from_str("0OIl") {
+            Err(err) => assert_eq!(err.id(), base58::DecodeError::ERR_ID),
+            Ok(address) => panic!("should have failed to parse: {}", address),
+        }
+
+        // valid base58 that decodes to the wrong number of bytes
+        let (pub_key, _) = box_::gen_keypair();
+        for len in vec![0, 1, Address::LEN - 1, Address::LEN + 1] {
+            let bytes: Vec<u8> = pub_key.0.iter().cycle().take(len).cloned().collect();
+            match Address::from_str(&base58::encode(&bytes)) {
+                Err(err) => assert_eq!(err.id(), errors::InvalidAddressLength::ERROR_ID),
+                Ok(address) => panic!("should have failed to parse: {}", address),
+            }
+        }
+    }
+
     #[test]
     fn sealed_envelope_nng_conversions() {
+        use std::convert::TryFrom;
+
         let (client_pub_key, client_priv_key) = box_::gen_keypair();
         let (server_pub_key, server_priv_key) = box_::gen_keypair();
         let (client_addr, server_addr) =
@@ -1262,10 +3115,35 @@ mod test {
 
         let open_envelope = OpenEnvelope::new(client_pub_key.into(), server_pub_key.into(), msg);
         let sealed_envelope = open_envelope.seal(&sealing_key);
+        // the TryFrom conversions round trip
+        let nng_msg = nng::Message::try_from(sealed_envelope.clone()).unwrap();
+        let open_envelope = SealedEnvelope::try_from(nng_msg)
+            .unwrap()
+            .open(&opening_key)
+            .unwrap();
+        assert_eq!(open_envelope.msg(), msg);
         let nng_msg = sealed_envelope.try_into_nng_message().unwrap();
+        // the nng message body is framed
+        assert_eq!(nng_msg[..4], [0xFF, b'O', b'P', SealedEnvelope::FRAME_VERSION]);
         let sealed_envelope = SealedEnvelope::try_from_nng_message(nng_msg).unwrap();
         let open_envelope = sealed_envelope.open(&opening_key).unwrap();
         assert_eq!(open_envelope.msg(), msg);
+
+        // unframed, i.e., version 0, nng messages are still supported
+        let mut nng_msg = nng::Message::new().unwrap();
+        nng_msg.push_back(&UNFRAMED_SEALED_ENVELOPE).unwrap();
+        let sealed_envelope = SealedEnvelope::try_from_nng_message(nng_msg).unwrap();
+        assert_eq!(sealed_envelope.msg(), &[4, 5, 6]);
+
+        // the frame magic is authoritative, i.e., a frame that does not span the whole message is
+        // rejected instead of being decoded as an unframed envelope
+        let mut frame = vec![0xFF, b'O', b'P', 2, 0];
+        frame.extend_from_slice(&(UNFRAMED_SEALED_ENVELOPE.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&UNFRAMED_SEALED_ENVELOPE);
+        frame.push(0);
+        let mut nng_msg = nng::Message::new().unwrap();
+        nng_msg.push_back(&frame).unwrap();
+        assert!(SealedEnvelope::try_from_nng_message(nng_msg).is_err());
     }
 
     #[test]
@@ -1431,6 +3309,33 @@ mod test {
         server.join().unwrap();
     }
 
+    #[test]
+    fn message_hasher() {
+        run_test("message_hasher", || {
+            let data: Vec<u8> = (0..(1024 * 64)).map(|i| (i % 256) as u8).collect();
+            // WHEN: the data is hashed incrementally in chunks
+            let mut hasher = super::MessageHasher::new();
+            for chunk in data.chunks(1000) {
+                hasher.update(chunk);
+            }
+            // THEN: the digest matches the one-shot hash of the concatenated bytes
+            assert_eq!(hasher.finalize(), MessageBytes::from(data.clone()).hash());
+
+            // GIVEN: a SealedEnvelope
+            let (client_pub_key, client_priv_key) = box_::gen_keypair();
+            let (server_pub_key, _) = box_::gen_keypair();
+            let sealing_key = Address::from(server_pub_key).precompute_sealing_key(&client_priv_key);
+            let sealed_envelope =
+                OpenEnvelope::new(client_pub_key.into(), server_pub_key.into(), &data)
+                    .seal(&sealing_key);
+            // WHEN: the SealedEnvelope is encoded to a stream
+            let mut buf: Vec<u8> = Vec::new();
+            let digest = sealed_envelope.encode_hashed(&mut buf).unwrap();
+            // THEN: the digest matches the hash of the encoded bytes
+            assert_eq!(digest, hash::hash(&buf));
+        });
+    }
+
     #[test]
     fn sealed_envelope_encoding_decoding() {
         let (client_pub_key, client_priv_key) = box_::gen_keypair();
@@ -1526,7 +3431,7 @@ mod test {
                 super::MessageTypeId(1867384532653698871582487715619812439);
             let metadata = super::Metadata::new(
                 MESSAGE_TYPE.message_type(),
-                super::Encoding::Bincode(None),
+                super::Encoding::Bincode(None, super::BincodeOptions::default()),
                 Some(super::Deadline::ProcessingTimeoutMillis(100)),
             );
 
@@ -1547,6 +3452,9 @@ mod test {
 
     #[test]
     fn encoded_message() {
+        use super::{errors, MAX_MSG_SIZE};
+        use oysterpack_errors::IsError;
+
         let (client_pub_key, client_priv_key) = box_::gen_keypair();
         let (server_pub_key, server_priv_key) = box_::gen_keypair();
 
@@ -1563,7 +3471,7 @@ mod test {
                 super::MessageTypeId(1867384532653698871582487715619812439);
             let metadata = super::Metadata::new(
                 MESSAGE_TYPE.message_type(),
-                super::Encoding::Bincode(None),
+                super::Encoding::Bincode(None, super::BincodeOptions::default()),
                 Some(super::Deadline::ProcessingTimeoutMillis(100)),
             );
 
@@ -1606,6 +3514,77 @@ mod test {
             assert_eq!(encoded_message.metadata(), encoded_message_2.metadata());
             assert_eq!(encoded_message.data(), encoded_message_2.data());
         });
+
+        run_test("encoded_message_with_addresses", || {
+            // GIVEN: an encoded message
+            let (relay_pub_key, _) = box_::gen_keypair();
+            let relay_addr = Address::from(relay_pub_key);
+            let encoded_message = OpenEnvelope::new(
+                client_addr,
+                server_addr,
+                &bincode::serialize(&msg).unwrap(),
+            )
+            .encoded_message()
+            .unwrap();
+            assert_eq!(*encoded_message.sender(), client_addr);
+            assert_eq!(*encoded_message.recipient(), server_addr);
+
+            // WHEN: the addresses are swapped
+            let relayed_message = encoded_message.clone().with_addresses(relay_addr, client_addr);
+            // THEN: the addresses are changed
+            assert_eq!(*relayed_message.sender(), relay_addr);
+            assert_eq!(*relayed_message.recipient(), client_addr);
+            // AND: the encoded message is untouched
+            assert_eq!(relayed_message.metadata(), encoded_message.metadata());
+            assert_eq!(relayed_message.data(), encoded_message.data());
+            // AND: the message still decodes to the original payload
+            let (addresses, foo_msg) = relayed_message.decode::<Foo>().unwrap();
+            assert_eq!(*addresses.sender(), relay_addr);
+            assert_eq!(*addresses.recipient(), client_addr);
+            assert_eq!(*foo_msg.data(), Foo("FOO".to_string()));
+        });
+
+        run_test("encoded_message_size_limit", || {
+            // GIVEN: an inner frame whose data length prefix declares a huge length
+            let mut msg_bytes = bincode::serialize(&msg).unwrap();
+            let data_len = msg.data().data().len();
+            let len_prefix_start = msg_bytes.len() - data_len - 8;
+            msg_bytes[len_prefix_start..len_prefix_start + 8]
+                .copy_from_slice(&(u64::max_value() / 2).to_le_bytes());
+            let open_envelope = OpenEnvelope::new(client_addr, server_addr, &msg_bytes);
+            // THEN: the bounded error is returned rather than attempting to allocate
+            let err = open_envelope.clone().encoded_message().unwrap_err();
+            assert_eq!(
+                err.id(),
+                errors::MessageError::MessageDataTooLarge {
+                    from: &client_addr,
+                    limit: MAX_MSG_SIZE
+                }
+                .error_id()
+            );
+            info!("{}", err);
+
+            // GIVEN: a well formed inner frame
+            let msg_bytes = bincode::serialize(&msg).unwrap();
+            let open_envelope = OpenEnvelope::new(client_addr, server_addr, &msg_bytes);
+            // THEN: it is rejected if it exceeds the specified limit
+            let err = open_envelope
+                .clone()
+                .encoded_message_with_limit(msg_bytes.len() - 1)
+                .unwrap_err();
+            assert_eq!(
+                err.id(),
+                errors::MessageError::MessageDataTooLarge {
+                    from: &client_addr,
+                    limit: msg_bytes.len() - 1
+                }
+                .error_id()
+            );
+            // AND: it is parsed if it fits within the limit
+            assert!(open_envelope
+                .encoded_message_with_limit(msg_bytes.len())
+                .is_ok());
+        });
     }
 
     #[test]
@@ -1626,7 +3605,7 @@ mod test {
                 super::MessageTypeId(1867384532653698871582487715619812439);
             let metadata = super::Metadata::new(
                 MESSAGE_TYPE.message_type(),
-                super::Encoding::Bincode(None),
+                super::Encoding::Bincode(None, super::BincodeOptions::default()),
                 None,
             );
 
@@ -1691,7 +3670,7 @@ mod test {
         run_test("bincode_compressed_encodings", || {
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::Bincode(None),
+                super::Encoding::Bincode(None, super::BincodeOptions::default()),
                 None,
             );
             let msg = super::Message::new(metadata, foo.clone());
@@ -1702,7 +3681,10 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::Bincode(Some(super::Compression::Deflate)),
+                super::Encoding::Bincode(
+                    Some(super::Compression::Deflate(super::CompressionLevel::Fast)),
+                    super::BincodeOptions::default(),
+                ),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1713,7 +3695,10 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::Bincode(Some(super::Compression::Gzip)),
+                super::Encoding::Bincode(
+                    Some(super::Compression::Gzip(super::CompressionLevel::Fast)),
+                    super::BincodeOptions::default(),
+                ),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1724,7 +3709,10 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::Bincode(Some(super::Compression::Zlib)),
+                super::Encoding::Bincode(
+                    Some(super::Compression::Zlib(super::CompressionLevel::Fast)),
+                    super::BincodeOptions::default(),
+                ),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1735,7 +3723,10 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::Bincode(Some(super::Compression::Snappy)),
+                super::Encoding::Bincode(
+                    Some(super::Compression::Snappy),
+                    super::BincodeOptions::default(),
+                ),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1746,7 +3737,10 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::Bincode(Some(super::Compression::Lz4)),
+                super::Encoding::Bincode(
+                    Some(super::Compression::Lz4(super::CompressionLevel::Fast)),
+                    super::BincodeOptions::default(),
+                ),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1757,6 +3751,144 @@ mod test {
         });
     }
 
+    #[test]
+    fn session_id_from_ulid_unchecked() {
+        use super::SessionId;
+
+        // GIVEN: a SessionId that was persisted as a ULID
+        let session_id = SessionId::generate();
+        let ulid = session_id.ulid();
+        // WHEN: it is explicitly converted back into a SessionId
+        // THEN: it matches the original SessionId
+        assert_eq!(SessionId::from_ulid_unchecked(ulid), session_id);
+    }
+
+    #[test]
+    fn generate_monotonic() {
+        use super::{InstanceId, SessionId};
+        use std::thread;
+
+        // WHEN: ids are generated in a tight loop, i.e., many ids share the same millisecond
+        let instance_ids: Vec<InstanceId> =
+            (0..10_000).map(|_| InstanceId::generate_monotonic()).collect();
+        // THEN: the ids are strictly monotonic
+        for ids in instance_ids.windows(2) {
+            assert!(ids[0] < ids[1], "{} >= {}", ids[0], ids[1]);
+        }
+        let session_ids: Vec<SessionId> =
+            (0..10_000).map(|_| SessionId::generate_monotonic()).collect();
+        for ids in session_ids.windows(2) {
+            assert!(ids[0] < ids[1]);
+        }
+
+        // WHEN: ids are generated concurrently
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(|| {
+                    (0..1000)
+                        .map(|_| InstanceId::generate_monotonic())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let thread_instance_ids: Vec<Vec<InstanceId>> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        // THEN: the ids are strictly monotonic within each thread
+        for instance_ids in thread_instance_ids.iter() {
+            for ids in instance_ids.windows(2) {
+                assert!(ids[0] < ids[1]);
+            }
+        }
+        // THEN: the ids are unique across threads
+        let mut instance_ids: Vec<InstanceId> =
+            thread_instance_ids.into_iter().flatten().collect();
+        let count = instance_ids.len();
+        instance_ids.sort();
+        instance_ids.dedup();
+        assert_eq!(instance_ids.len(), count);
+    }
+
+    #[test]
+    fn message_transcode() {
+        use super::{
+            errors, BincodeOptions, Compression, CompressionLevel, Encoding, IsMessage, Message,
+            Metadata,
+        };
+        use oysterpack_errors::IsError;
+
+        #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+        struct Order {
+            customer_id: u64,
+            product_code: String,
+            quantity: Option<u32>,
+        }
+        impl IsMessage for Order {
+            const MESSAGE_TYPE_ID: super::MessageTypeId =
+                super::MessageTypeId(1867384532653698871582487715619812439);
+        }
+
+        let order = Order {
+            customer_id: 1234,
+            product_code: "SKU-42".to_string(),
+            quantity: Some(3),
+        };
+
+        // GIVEN: a Bincode+Snappy encoded message
+        let bincode_snappy =
+            Encoding::Bincode(Some(Compression::Snappy), BincodeOptions::default());
+        let metadata = Metadata::new(Order::MESSAGE_TYPE_ID.message_type(), bincode_snappy, None);
+        let msg = Message::new(metadata, order.clone()).encode().unwrap();
+
+        // WHEN: it is transcoded to JSON without specifying the type
+        match msg.clone().transcode(Encoding::JSON(None)) {
+            // THEN: transcoding fails because Bincode is not self-describing
+            Err(err) => {
+                info!("{}", err);
+                assert_eq!(
+                    err.id(),
+                    errors::MessageError::EncodingError(
+                        errors::EncodingError::UntypedTranscodeNotSupported {
+                            from: bincode_snappy,
+                            to: Encoding::JSON(None)
+                        }
+                    )
+                    .error_id()
+                );
+            }
+            Ok(_) => panic!("Bincode should not be transcoded without specifying the type"),
+        }
+
+        // WHEN: it is transcoded to JSON as the original type
+        let json_msg = msg.clone().transcode_as::<Order>(Encoding::JSON(None)).unwrap();
+        // THEN: the metadata encoding is updated
+        assert_eq!(json_msg.metadata().encoding(), Encoding::JSON(None));
+        assert_eq!(
+            json_msg.metadata().instance_id(),
+            msg.metadata().instance_id()
+        );
+        // AND: it decodes as the original type
+        assert_eq!(*json_msg.clone().decode::<Order>().unwrap().data(), order);
+
+        // WHEN: the JSON message is transcoded to CBOR+Lz4 without specifying the type
+        let cbor_encoding = Encoding::CBOR(Some(Compression::Lz4(CompressionLevel::Fast)));
+        let cbor_msg = json_msg.transcode(cbor_encoding).unwrap();
+        // THEN: it decodes as the original type
+        assert_eq!(cbor_msg.metadata().encoding(), cbor_encoding);
+        assert_eq!(*cbor_msg.decode::<Order>().unwrap().data(), order);
+
+        // WHEN: only the compression is changed for the Bincode message
+        let bincode_deflate = Encoding::Bincode(
+            Some(Compression::Deflate(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        );
+        let bincode_msg = msg.transcode(bincode_deflate).unwrap();
+        // THEN: it decodes as the original type
+        assert_eq!(bincode_msg.metadata().encoding(), bincode_deflate);
+        assert_eq!(*bincode_msg.decode::<Order>().unwrap().data(), order);
+    }
+
     #[test]
     fn json_compressed_encodings() {
         let (client_pub_key, client_priv_key) = box_::gen_keypair();
@@ -1788,7 +3920,9 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::JSON(Some(super::Compression::Deflate)),
+                super::Encoding::JSON(Some(super::Compression::Deflate(
+                    super::CompressionLevel::Fast,
+                ))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1799,7 +3933,9 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::JSON(Some(super::Compression::Gzip)),
+                super::Encoding::JSON(Some(super::Compression::Gzip(
+                    super::CompressionLevel::Fast,
+                ))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1810,7 +3946,9 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::JSON(Some(super::Compression::Zlib)),
+                super::Encoding::JSON(Some(super::Compression::Zlib(
+                    super::CompressionLevel::Fast,
+                ))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1832,7 +3970,7 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::JSON(Some(super::Compression::Lz4)),
+                super::Encoding::JSON(Some(super::Compression::Lz4(super::CompressionLevel::Fast))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1874,7 +4012,9 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::CBOR(Some(super::Compression::Deflate)),
+                super::Encoding::CBOR(Some(super::Compression::Deflate(
+                    super::CompressionLevel::Fast,
+                ))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1885,7 +4025,9 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::CBOR(Some(super::Compression::Gzip)),
+                super::Encoding::CBOR(Some(super::Compression::Gzip(
+                    super::CompressionLevel::Fast,
+                ))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1896,7 +4038,9 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::CBOR(Some(super::Compression::Zlib)),
+                super::Encoding::CBOR(Some(super::Compression::Zlib(
+                    super::CompressionLevel::Fast,
+                ))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1918,7 +4062,7 @@ mod test {
 
             let metadata = super::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                super::Encoding::CBOR(Some(super::Compression::Lz4)),
+                super::Encoding::CBOR(Some(super::Compression::Lz4(super::CompressionLevel::Fast))),
                 None,
             );
             let msg = super::Message::new(metadata.clone(), foo.clone());
@@ -1929,6 +4073,154 @@ mod test {
         });
     }
 
+    #[test]
+    fn sealed_envelope_open_verified_decode() {
+        let (client_pub_key, client_priv_key) = box_::gen_keypair();
+        let (server_pub_key, server_priv_key) = box_::gen_keypair();
+        let (client_sign_pub_key, client_sign_priv_key) = sign::gen_keypair();
+        let (client_addr, server_addr) =
+            (Address::from(client_pub_key), Address::from(server_pub_key));
+        let opening_key = client_addr.precompute_opening_key(&server_priv_key);
+        let sealing_key = server_addr.precompute_sealing_key(&client_priv_key);
+
+        use super::IsMessage;
+        #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+        struct Foo(String);
+        impl IsMessage for Foo {
+            const MESSAGE_TYPE_ID: super::MessageTypeId =
+                super::MessageTypeId(1867384532653698871582487715619812439);
+        }
+        let foo = Foo("hello".to_string());
+        let encoded_message = |deadline: Option<super::Deadline>| {
+            let metadata = super::Metadata::new(
+                Foo::MESSAGE_TYPE_ID.message_type(),
+                super::Encoding::Bincode(None, super::BincodeOptions::default()),
+                deadline,
+            );
+            super::Message::new(metadata, foo.clone())
+                .encoded_message(client_addr, server_addr)
+                .unwrap()
+        };
+
+        run_test("sealed_envelope_open_verified_decode", || {
+            // GIVEN: a signed message
+            let sealed_envelope = encoded_message(None)
+                .signed_open_envelope(&client_sign_priv_key)
+                .unwrap()
+                .seal(&sealing_key);
+            // THEN: it is verified and decoded
+            let (addresses, msg) = sealed_envelope
+                .open_verified_decode::<Foo>(&opening_key, &client_sign_pub_key)
+                .unwrap();
+            assert_eq!(*msg.data(), foo);
+            assert_eq!(*addresses.sender(), client_addr);
+            assert_eq!(*addresses.recipient(), server_addr);
+
+            // GIVEN: a signed message whose payload was tampered with
+            let open_envelope = encoded_message(None)
+                .signed_open_envelope(&client_sign_priv_key)
+                .unwrap();
+            let mut signed_msg: super::SignedMessageBytes =
+                bincode::deserialize(open_envelope.msg()).unwrap();
+            let last = signed_msg.msg.0.len() - 1;
+            signed_msg.msg.0[last] ^= 0xFF;
+            let sealed_envelope = OpenEnvelope::new(
+                client_addr,
+                server_addr,
+                &bincode::serialize(&signed_msg).unwrap(),
+            )
+            .seal(&sealing_key);
+            // THEN: verification fails
+            let err = sealed_envelope
+                .open_verified_decode::<Foo>(&opening_key, &client_sign_pub_key)
+                .unwrap_err();
+            info!("tampered message error: {}", err);
+
+            // GIVEN: a message that was signed by someone else
+            let (_, other_sign_priv_key) = sign::gen_keypair();
+            let sealed_envelope = encoded_message(None)
+                .signed_open_envelope(&other_sign_priv_key)
+                .unwrap()
+                .seal(&sealing_key);
+            // THEN: verification fails
+            assert!(sealed_envelope
+                .open_verified_decode::<Foo>(&opening_key, &client_sign_pub_key)
+                .is_err());
+
+            // GIVEN: a signed message whose deadline has expired
+            let sealed_envelope = encoded_message(Some(super::Deadline::MessageTimeoutMillis(0)))
+                .signed_open_envelope(&client_sign_priv_key)
+                .unwrap()
+                .seal(&sealing_key);
+            // THEN: it is rejected
+            assert!(sealed_envelope
+                .open_verified_decode::<Foo>(&opening_key, &client_sign_pub_key)
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn metadata_attributes() {
+        use super::IsMessage;
+        use oysterpack_events::AttributeId;
+        #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+        struct Foo(String);
+        impl IsMessage for Foo {
+            const MESSAGE_TYPE_ID: super::MessageTypeId =
+                super::MessageTypeId(1867384532653698871582487715619812439);
+        }
+        const TENANT_ID: AttributeId = AttributeId(1879901704532744215140385278926389190);
+        const TRACE_ID: AttributeId = AttributeId(1879906207021620167614319595669966309);
+
+        run_test("metadata_attributes", || {
+            let (tenant_id, trace_id) = (ULID::generate(), ULID::generate());
+            // GIVEN: metadata with 2 attributes attached
+            let metadata = super::Metadata::new(
+                Foo::MESSAGE_TYPE_ID.message_type(),
+                super::Encoding::Bincode(None, super::BincodeOptions::default()),
+                None,
+            )
+            .with_attribute(TENANT_ID, tenant_id)
+            .unwrap()
+            .with_attribute(TRACE_ID, trace_id)
+            .unwrap();
+            assert_eq!(metadata.attributes().count(), 2);
+
+            // WHEN: a message is encoded and decoded via an envelope
+            let (client_pub_key, _) = box_::gen_keypair();
+            let (server_pub_key, _) = box_::gen_keypair();
+            let msg = super::Message::new(metadata, Foo("foo".to_string()))
+                .encoded_message(client_pub_key.into(), server_pub_key.into())
+                .unwrap()
+                .open_envelope()
+                .unwrap()
+                .encoded_message()
+                .unwrap();
+            // THEN: the attributes are preserved
+            let decoded_metadata = msg.metadata();
+            assert_eq!(decoded_metadata.attribute(TENANT_ID), Some(tenant_id));
+            assert_eq!(decoded_metadata.attribute(TRACE_ID), Some(trace_id));
+            assert_eq!(decoded_metadata, metadata);
+
+            // WHEN: an attribute is attached using an existing AttributeId
+            let trace_id = ULID::generate();
+            let metadata = metadata.with_attribute(TRACE_ID, trace_id).unwrap();
+            // THEN: the attribute value is replaced
+            assert_eq!(metadata.attribute(TRACE_ID), Some(trace_id));
+            assert_eq!(metadata.attributes().count(), 2);
+
+            // WHEN: more than MAX_ATTRIBUTES are attached
+            let metadata = (0..(super::Metadata::MAX_ATTRIBUTES - 2)).fold(metadata, |md, i| {
+                md.with_attribute(AttributeId(i as u128), ULID::generate())
+                    .unwrap()
+            });
+            // THEN: an error is returned
+            assert!(metadata
+                .with_attribute(AttributeId(u128::max_value()), ULID::generate())
+                .is_err());
+        });
+    }
+
     #[test]
     fn deadline() {
         let start = chrono::Utc::now();
@@ -1947,5 +4239,894 @@ mod test {
             .checked_sub_signed(chrono::Duration::milliseconds(200))
             .unwrap();
         assert_eq!(deadline.duration(start), chrono::Duration::zero());
+        // THEN: the message deadline has expired because the wall clock is past the deadline
+        assert!(deadline.is_expired(start));
+        assert_eq!(deadline.remaining_millis(start), 0);
+
+        // THEN: the message deadline has not expired when the wall clock is before the deadline
+        let deadline = super::Deadline::MessageTimeoutMillis(60 * 1000);
+        let start = chrono::Utc::now()
+            .checked_sub_signed(chrono::Duration::milliseconds(1))
+            .unwrap();
+        assert!(!deadline.is_expired(start));
+        let remaining_millis = deadline.remaining_millis(start);
+        assert!(remaining_millis > 0 && remaining_millis < 60 * 1000);
+
+        // THEN: the processing deadline never expires based on the wall clock, i.e., even if the
+        // processing start time is far in the past
+        let deadline = super::Deadline::ProcessingTimeoutMillis(100);
+        let start = chrono::Utc::now()
+            .checked_sub_signed(chrono::Duration::days(1))
+            .unwrap();
+        assert!(!deadline.is_expired(start));
+        assert_eq!(deadline.remaining_millis(start), 100);
+        // THEN: a zero processing deadline is always expired
+        assert!(super::Deadline::ProcessingTimeoutMillis(0).is_expired(start));
+    }
+
+    #[test]
+    fn deadline_with_mock_clock() {
+        use super::clock::MockClock;
+
+        let start = chrono::Utc::now();
+        let clock = MockClock::new(start);
+        let deadline = super::Deadline::MessageTimeoutMillis(100);
+
+        // GIVEN: the clock is 1 ms past the starting time
+        clock.advance(chrono::Duration::milliseconds(1));
+        // THEN: the remaining time is exact
+        assert_eq!(
+            deadline.duration_with_clock(start, &clock),
+            chrono::Duration::milliseconds(99)
+        );
+        assert_eq!(deadline.remaining_millis_with_clock(start, &clock), 99);
+        assert!(!deadline.is_expired_with_clock(start, &clock));
+
+        // WHEN: the clock is advanced to just before the deadline
+        clock.advance(chrono::Duration::milliseconds(98));
+        // THEN: the deadline has not expired
+        assert_eq!(
+            deadline.duration_with_clock(start, &clock),
+            chrono::Duration::milliseconds(1)
+        );
+        assert!(!deadline.is_expired_with_clock(start, &clock));
+
+        // WHEN: the clock is advanced to the deadline
+        clock.advance(chrono::Duration::milliseconds(1));
+        // THEN: the deadline has expired
+        assert_eq!(
+            deadline.duration_with_clock(start, &clock),
+            chrono::Duration::zero()
+        );
+        assert!(deadline.is_expired_with_clock(start, &clock));
+        assert_eq!(deadline.remaining_millis_with_clock(start, &clock), 0);
+
+        // THEN: processing timeouts are not relative to the clock
+        let deadline = super::Deadline::ProcessingTimeoutMillis(100);
+        assert_eq!(
+            deadline.duration_with_clock(start, &clock),
+            chrono::Duration::milliseconds(100)
+        );
+    }
+
+    #[test]
+    fn open_envelope_try_seal() {
+        use oysterpack_errors::IsError;
+
+        let (client_pub_key, client_priv_key) = box_::gen_keypair();
+        let (server_pub_key, _) = box_::gen_keypair();
+        let (sender, recipient): (Address, Address) =
+            (client_pub_key.into(), server_pub_key.into());
+        let sealing_key = recipient.precompute_sealing_key(&client_priv_key);
+        let encoded_len = |sealed_envelope: &SealedEnvelope| {
+            let mut bytes = Vec::new();
+            sealed_envelope.encode(&mut bytes).unwrap();
+            assert_eq!(sealed_envelope.encoded_len().unwrap(), bytes.len());
+            bytes.len()
+        };
+        // the encryption and framing overhead
+        let overhead = encoded_len(&OpenEnvelope::new(sender, recipient, &[]).seal(&sealing_key));
+
+        // WHEN: the sealed envelope size is just under the max message size
+        let msg = vec![0_u8; super::MAX_MSG_SIZE - overhead - 1];
+        let sealed_envelope = OpenEnvelope::new(sender, recipient, &msg)
+            .try_seal(&sealing_key)
+            .unwrap();
+        // THEN: the envelope is sealed
+        assert_eq!(encoded_len(&sealed_envelope), super::MAX_MSG_SIZE - 1);
+
+        // WHEN: the sealed envelope size is exactly the max message size
+        let msg = vec![0_u8; super::MAX_MSG_SIZE - overhead];
+        // THEN: the envelope is sealed
+        assert!(OpenEnvelope::new(sender, recipient, &msg)
+            .try_seal(&sealing_key)
+            .is_ok());
+
+        // WHEN: the sealed envelope size is just over the max message size
+        let msg = vec![0_u8; super::MAX_MSG_SIZE - overhead + 1];
+        let err = OpenEnvelope::new(sender, recipient, &msg)
+            .try_seal(&sealing_key)
+            .unwrap_err();
+        // THEN: the envelope is rejected
+        info!("{}", err);
+        assert_eq!(
+            err.id(),
+            super::errors::MessageError::MessageTooLarge {
+                from: &sender,
+                size: super::MAX_MSG_SIZE + 1,
+                max: super::MAX_MSG_SIZE
+            }
+            .error_id()
+        );
+        // THEN: the infallible seal does not check the size
+        let sealed_envelope = OpenEnvelope::new(sender, recipient, &msg).seal(&sealing_key);
+        assert_eq!(encoded_len(&sealed_envelope), super::MAX_MSG_SIZE + 1);
+    }
+
+    #[test]
+    fn open_envelope_try_new() {
+        use oysterpack_errors::IsError;
+        run_test("open_envelope_try_new", || {
+            let (client_pub_key, _) = box_::gen_keypair();
+            let (server_pub_key, _) = box_::gen_keypair();
+            let (sender, recipient): (Address, Address) =
+                (client_pub_key.into(), server_pub_key.into());
+
+            // WHEN: the envelope is valid
+            let envelope = OpenEnvelope::try_new(sender, recipient, b"data").unwrap();
+            // THEN: the envelope is created
+            assert_eq!(*envelope.sender(), sender);
+            assert_eq!(*envelope.recipient(), recipient);
+
+            // WHEN: the envelope is self addressed
+            let err = OpenEnvelope::try_new(sender, sender, b"data").unwrap_err();
+            // THEN: the envelope is rejected
+            info!("{}", err);
+            assert_eq!(
+                err.id(),
+                super::errors::MessageError::SelfAddressed(&sender).error_id()
+            );
+
+            // WHEN: the message exceeds the max message size
+            let msg = vec![0_u8; super::MAX_MSG_SIZE + 1];
+            let err = OpenEnvelope::try_new(sender, recipient, &msg).unwrap_err();
+            // THEN: the envelope is rejected
+            info!("{}", err);
+            assert_eq!(
+                err.id(),
+                super::errors::MessageError::MessageTooLarge {
+                    from: &sender,
+                    size: msg.len(),
+                    max: super::MAX_MSG_SIZE
+                }
+                .error_id()
+            );
+            // AND: the permissive constructor still accepts self addressed envelopes
+            let envelope = OpenEnvelope::new(sender, sender, b"data");
+            assert_eq!(envelope.sender(), envelope.recipient());
+        });
+    }
+
+    #[test]
+    fn message_type_registry() {
+        run_test("message_type_registry", || {
+            const FOO: super::MessageTypeId =
+                super::MessageTypeId(1879936212890753659402947454436096704);
+            const BAR: super::MessageTypeId =
+                super::MessageTypeId(1879938057629836889066817892734994168);
+
+            // GIVEN: 2 message types registered with names
+            let registry = super::MessageTypeRegistry::default();
+            registry.register(FOO, Some("Foo"));
+            registry.register(BAR, None);
+            // THEN: they are listed
+            let types = registry.registered_types();
+            assert_eq!(types.len(), 2);
+            assert!(types.contains(&(FOO.message_type(), Some("Foo".to_string()))));
+            assert!(types.contains(&(BAR.message_type(), None)));
+
+            // WHEN: a type is re-registered with a name
+            registry.register(BAR, Some("Bar"));
+            // THEN: the name is updated
+            assert!(registry
+                .registered_types()
+                .contains(&(BAR.message_type(), Some("Bar".to_string()))));
+
+            // WHEN: a type is unregistered
+            assert!(registry.unregister(FOO));
+            // THEN: it is no longer listed
+            assert!(!registry.is_registered(FOO));
+            assert_eq!(registry.registered_types().len(), 1);
+
+            // THEN: the global registry is accessible
+            super::message_type_registry().register(FOO, Some("Foo"));
+            assert!(super::message_type_registry().is_registered(FOO));
+        });
+    }
+
+    #[test]
+    fn message_batch() {
+        use super::{BincodeOptions, Encoding, Message, MessageBatch, MessageTypeId, Metadata};
+        use oysterpack_errors::IsError;
+        use std::{convert::TryFrom, thread};
+
+        const MESSAGE_TYPE: MessageTypeId = MessageTypeId(1879999477166124603499135613529874466);
+        let (client_pub_key, _) = box_::gen_keypair();
+        let (server_pub_key, _) = box_::gen_keypair();
+        let (client_addr, server_addr) =
+            (Address::from(client_pub_key), Address::from(server_pub_key));
+
+        // GIVEN: a batch containing 3 messages
+        let mut batch = MessageBatch::new();
+        for i in 0..3_u64 {
+            let metadata = Metadata::new(
+                MESSAGE_TYPE.message_type(),
+                Encoding::Bincode(None, BincodeOptions::default()),
+                None,
+            );
+            let msg = Message::new(metadata, i)
+                .encoded_message(server_addr, client_addr)
+                .unwrap();
+            batch.push(msg).unwrap();
+        }
+        assert_eq!(batch.len(), 3);
+        let mut bytes = Vec::new();
+        batch.encode(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), batch.encoded_size());
+
+        // GIVEN: an echo server
+        let url = format!("inproc://{}", ULID::generate());
+        let mut server = nng::Socket::new(nng::Protocol::Rep0).unwrap();
+        server.listen(&url).unwrap();
+        let server = thread::spawn(move || {
+            let msg = server.recv().unwrap();
+            server.send(msg).unwrap();
+        });
+        let mut client = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        client.dial(&url).unwrap();
+
+        // WHEN: the batch is sent through the echo server
+        client.send(nng::Message::try_from(batch).unwrap()).unwrap();
+        let reply = client.recv().unwrap();
+        server.join().unwrap();
+
+        // THEN: all 3 messages are unpacked in order
+        let batch = MessageBatch::try_from(reply).unwrap();
+        let values: Vec<u64> = batch
+            .into_iter()
+            .map(|msg| msg.decode::<u64>().unwrap().1.data().clone())
+            .collect();
+        assert_eq!(values, vec![0, 1, 2]);
+
+        // GIVEN: a message that would make the batch exceed MAX_MSG_SIZE
+        let mut batch = MessageBatch::new();
+        let metadata = Metadata::new(
+            MESSAGE_TYPE.message_type(),
+            Encoding::Bincode(None, BincodeOptions::default()),
+            None,
+        );
+        let msg = Message::new(metadata, vec![0_u8; super::MAX_MSG_SIZE])
+            .encoded_message(server_addr, client_addr)
+            .unwrap();
+        // THEN: the message is rejected
+        let err = batch.push(msg).unwrap_err();
+        assert_eq!(
+            err.id(),
+            super::errors::MessageError::MessageBatchTooLarge {
+                size: 0,
+                max: super::MAX_MSG_SIZE
+            }
+            .error_id()
+        );
+        assert!(batch.is_empty());
+
+        // GIVEN: an encoded batch whose frame lengths exceed MAX_MSG_SIZE
+        let mut bytes = 1_u32.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&(super::MAX_MSG_SIZE as u32).to_be_bytes());
+        // THEN: decoding fails before the frame is read
+        assert!(MessageBatch::decode(&bytes[..]).is_err());
+
+        // GIVEN: a batch header that claims an excessive frame count
+        let bytes = u32::max_value().to_be_bytes().to_vec();
+        // WHEN: the batch is decoded
+        let err = MessageBatch::decode(&bytes[..]).unwrap_err();
+        // THEN: decoding fails with a bounded error, before any frames are allocated
+        info!("{}", err);
+        assert_eq!(
+            err.id(),
+            super::errors::MessageError::MessageBatchTooManyFrames {
+                count: u32::max_value() as usize,
+                max: super::DEFAULT_MAX_BATCH_FRAMES
+            }
+            .error_id()
+        );
+        // AND: the max frame count is configurable
+        let mut batch = MessageBatch::new();
+        for i in 0..3_u64 {
+            let metadata = Metadata::new(
+                MESSAGE_TYPE.message_type(),
+                Encoding::Bincode(None, BincodeOptions::default()),
+                None,
+            );
+            let msg = Message::new(metadata, i)
+                .encoded_message(server_addr, client_addr)
+                .unwrap();
+            batch.push(msg).unwrap();
+        }
+        let mut bytes = Vec::new();
+        batch.encode(&mut bytes).unwrap();
+        assert!(MessageBatch::decode_with_max_frames(&bytes[..], 2).is_err());
+        assert_eq!(
+            MessageBatch::decode_with_max_frames(&bytes[..], 3)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn compression_level() {
+        use super::{
+            BincodeOptions, Compression, CompressionLevel, Encoding, Metadata, MessageTypeId,
+        };
+
+        // GIVEN: a repetitive payload
+        let data: Vec<u8> = (0..5000)
+            .map(|i| {
+                format!(
+                    "{{\"id\":{},\"name\":\"name-{}\",\"group\":{}}}\n",
+                    i % 97,
+                    i % 13,
+                    i % 7
+                )
+            })
+            .collect::<String>()
+            .into_bytes();
+        let compressions: Vec<fn(CompressionLevel) -> Compression> = vec![
+            Compression::Deflate,
+            Compression::Zlib,
+            Compression::Gzip,
+            Compression::Lz4,
+        ];
+        for compression in compressions {
+            let fast = compression(CompressionLevel::Fast).compress(&data).unwrap();
+            let best = compression(CompressionLevel::Best).compress(&data).unwrap();
+            info!(
+                "{:?}: fast = {}, best = {}",
+                compression(CompressionLevel::Best),
+                fast.len(),
+                best.len()
+            );
+            // THEN: Best produces a smaller output than Fast for a repetitive payload
+            assert!(best.len() < fast.len());
+            // THEN: decompression does not depend on the compression level
+            for compressed in vec![fast, best] {
+                let decompressed = compression(CompressionLevel::Default)
+                    .decompress(&compressed)
+                    .unwrap();
+                assert_eq!(decompressed, data);
+            }
+        }
+
+        // THEN: the compression level round trips through serde as part of the Metadata
+        let metadata = Metadata::new(
+            MessageTypeId(1880058380013195449526106075850415952).message_type(),
+            Encoding::Bincode(
+                Some(Compression::Gzip(CompressionLevel::Best)),
+                BincodeOptions::default(),
+            ),
+            None,
+        );
+        let bytes = bincode::serialize(&metadata).unwrap();
+        let metadata: Metadata = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(
+            metadata.encoding().compression(),
+            Some(Compression::Gzip(CompressionLevel::Best))
+        );
+    }
+
+    #[test]
+    fn encoding_streaming() {
+        use super::{errors, BincodeOptions, Compression, CompressionLevel, Encoding};
+
+        #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+        struct Foo {
+            id: u64,
+            names: Vec<String>,
+        }
+
+        let foo = Foo {
+            id: 1,
+            names: (0..1000).map(|i| format!("name-{}", i)).collect(),
+        };
+
+        // THEN: the streamed output is byte identical to the buffered output for uncompressed data
+        for encoding in vec![
+            Encoding::Bincode(None, BincodeOptions::default()),
+            Encoding::CBOR(None),
+            Encoding::JSON(None),
+        ] {
+            let mut streamed = Vec::new();
+            encoding.encode_into(&foo, &mut streamed).unwrap();
+            assert_eq!(streamed, encoding.encode(&foo).unwrap());
+            let decoded: Foo = encoding.decode_from(&streamed[..]).unwrap();
+            assert_eq!(decoded, foo);
+        }
+
+        // THEN: streamed and buffered data is interchangeable for compressed data
+        for compression in vec![
+            Compression::Deflate(CompressionLevel::Fast),
+            Compression::Zlib(CompressionLevel::Default),
+            Compression::Gzip(CompressionLevel::Best),
+            Compression::Snappy,
+            Compression::Lz4(CompressionLevel::Fast),
+        ] {
+            for encoding in vec![
+                Encoding::Bincode(Some(compression), BincodeOptions::default()),
+                Encoding::CBOR(Some(compression)),
+                Encoding::JSON(Some(compression)),
+            ] {
+                let mut streamed = Vec::new();
+                encoding.encode_into(&foo, &mut streamed).unwrap();
+                let decoded: Foo = encoding.decode(&streamed).unwrap();
+                assert_eq!(decoded, foo);
+
+                let buffered = encoding.encode(&foo).unwrap();
+                let decoded: Foo = encoding.decode_from(&buffered[..]).unwrap();
+                assert_eq!(decoded, foo);
+            }
+        }
+
+        // WHEN: the compressed data is corrupt
+        let encoding = Encoding::Bincode(
+            Some(Compression::Deflate(CompressionLevel::Fast)),
+            BincodeOptions::default(),
+        );
+        match encoding.decode_from::<Foo, _>(&[0xFF_u8; 16][..]) {
+            // THEN: a DecompressionError is returned
+            Err(err) => assert_eq!(err.id(), errors::DecompressionError::ERROR_ID),
+            Ok(foo) => panic!("corrupt data should have failed to decode: {:?}", foo),
+        }
+    }
+
+    #[test]
+    fn decompress_guarded() {
+        use super::{Compression, CompressionLevel};
+        use std::io;
+
+        // GIVEN: a payload that compresses extremely well, i.e., a decompression bomb
+        const PAYLOAD_SIZE: usize = 10 * 1024 * 1024;
+        let payload = vec![0_u8; PAYLOAD_SIZE];
+        for compression in vec![
+            Compression::Deflate(CompressionLevel::Fast),
+            Compression::Zlib(CompressionLevel::Fast),
+            Compression::Gzip(CompressionLevel::Fast),
+            Compression::Snappy,
+            Compression::Lz4(CompressionLevel::Fast),
+        ] {
+            let compressed = compression.compress(&payload).unwrap();
+            let max_ratio = 10;
+            assert!(PAYLOAD_SIZE > compressed.len() * max_ratio);
+
+            // WHEN: it is decompressed with a max ratio that the payload exceeds
+            let err = compression
+                .decompress_guarded(&compressed, max_ratio, PAYLOAD_SIZE * 2)
+                .unwrap_err();
+            // THEN: decompression is rejected
+            info!("{:?}: {}", compression, err);
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("ratio"));
+
+            // WHEN: it is decompressed with a max size that the payload exceeds
+            let err = compression
+                .decompress_guarded(&compressed, usize::max_value(), PAYLOAD_SIZE / 2)
+                .unwrap_err();
+            // THEN: decompression is rejected
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("max size"));
+
+            // WHEN: it is decompressed within both limits
+            let decompressed = compression
+                .decompress_guarded(&compressed, PAYLOAD_SIZE, PAYLOAD_SIZE)
+                .unwrap();
+            // THEN: the payload is returned
+            assert_eq!(decompressed.len(), PAYLOAD_SIZE);
+            assert!(decompressed == payload);
+        }
+    }
+
+    #[test]
+    fn compression_dictionary() {
+        use super::{
+            compression_dictionary_registry, Compression, CompressionLevel, DictionaryId, Encoding,
+        };
+
+        #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+        struct Order {
+            customer_id: u64,
+            product_code: String,
+            quantity: u32,
+            shipping_priority: String,
+        }
+
+        let order = Order {
+            customer_id: 1234,
+            product_code: "SKU-42".to_string(),
+            quantity: 3,
+            shipping_priority: "EXPRESS".to_string(),
+        };
+
+        // GIVEN: a dictionary that was trained on the Order JSON structure
+        let dictionary_id = DictionaryId(1879994067100902471922993454833305102);
+        let dictionary = br#"{"customer_id":,"product_code":"SKU-","quantity":,"shipping_priority":"EXPRESS"STANDARD"}"#;
+        compression_dictionary_registry()
+            .register(dictionary_id, dictionary)
+            .unwrap();
+
+        let json = serde_json::to_vec(&order).unwrap();
+        let compressed = Compression::Deflate(CompressionLevel::Fast).compress(&json).unwrap();
+        for compression in vec![
+            Compression::DeflateDictionary(dictionary_id),
+            Compression::ZstdDictionary(dictionary_id),
+        ] {
+            // WHEN: a small message is compressed with and without the dictionary
+            let dictionary_compressed = compression.compress(&json).unwrap();
+            info!(
+                "json: {}, deflate: {}, {:?}: {}",
+                json.len(),
+                compressed.len(),
+                compression,
+                dictionary_compressed.len()
+            );
+            // THEN: the dictionary compressed message is smaller
+            assert!(dictionary_compressed.len() < compressed.len());
+            // AND: it round trips
+            assert_eq!(compression.decompress(&dictionary_compressed).unwrap(), json);
+            assert_eq!(
+                compression
+                    .decompress_guarded(&dictionary_compressed, 100, 1024)
+                    .unwrap(),
+                json
+            );
+
+            // WHEN: the dictionary is configured via the Encoding
+            let encoding = Encoding::JSON(Some(compression));
+            let bytes = encoding.encode(&order).unwrap();
+            // THEN: the DictionaryId carried in the Encoding is used to decode the message
+            let encoding: Encoding =
+                bincode::deserialize(&bincode::serialize(&encoding).unwrap()).unwrap();
+            assert_eq!(encoding.decode::<Order>(&bytes).unwrap(), order);
+            // AND: the message can be streamed through the codec
+            let mut streamed = Vec::new();
+            encoding.encode_into(&order, &mut streamed).unwrap();
+            assert_eq!(encoding.decode_from::<Order, _>(&streamed[..]).unwrap(), order);
+
+            // WHEN: the data was compressed with another dictionary
+            let other_dictionary_id = DictionaryId(1880062522005880230998023058901408844);
+            compression_dictionary_registry()
+                .register(other_dictionary_id, br#"{"shipping_priority":"STANDARD"}"#)
+                .unwrap();
+            let other_compression = match compression {
+                Compression::DeflateDictionary(_) => {
+                    Compression::DeflateDictionary(other_dictionary_id)
+                }
+                _ => Compression::ZstdDictionary(other_dictionary_id),
+            };
+            // THEN: the data does not decompress to the original message
+            assert!(other_compression
+                .decompress(&dictionary_compressed)
+                .map(|data| data != json)
+                .unwrap_or(true));
+            assert!(compression_dictionary_registry().unregister(other_dictionary_id));
+        }
+
+        // WHEN: the dictionary is not registered
+        let dictionary_compressed = Compression::ZstdDictionary(dictionary_id)
+            .compress(&json)
+            .unwrap();
+        assert!(compression_dictionary_registry().unregister(dictionary_id));
+        // THEN: decompression fails
+        assert!(Compression::ZstdDictionary(dictionary_id)
+            .decompress(&dictionary_compressed)
+            .is_err());
+        assert!(Compression::DeflateDictionary(dictionary_id)
+            .decompress(&compressed)
+            .is_err());
+    }
+
+    /// SealedEnvelope bytes that were encoded using the bincode layout that predates the key
+    /// exchange scheme, i.e., `sender | recipient | nonce | msg`
+    /// - sender = `[1; 32]`, recipient = `[2; 32]`, nonce = `[3; 24]`, msg = `[4, 5, 6]`
+    const UNFRAMED_SEALED_ENVELOPE: [u8; 123] = [
+        0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x20, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02,
+        0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02,
+        0x02, 0x02, 0x02, 0x02, 0x02, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x03,
+        0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03,
+        0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x04, 0x05, 0x06,
+    ];
+
+    #[test]
+    fn sealed_envelope_framed_decode_partial_reads() {
+        use super::SealedEnvelope;
+        use std::io::Read;
+
+        /// returns the data one byte at a time
+        struct OneByteReader<'a>(&'a [u8]);
+
+        impl Read for OneByteReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() || buf.is_empty() {
+                    return Ok(0);
+                }
+                buf[0] = self.0[0];
+                self.0 = &self.0[1..];
+                Ok(1)
+            }
+        }
+
+        run_test("sealed_envelope_framed_decode_partial_reads", || {
+            // GIVEN: a version 2 frame
+            let mut frame = vec![0xFF, b'O', b'P', 2, 0];
+            frame.extend_from_slice(&(UNFRAMED_SEALED_ENVELOPE.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&UNFRAMED_SEALED_ENVELOPE);
+            // WHEN: it is decoded from a reader that returns one byte at a time
+            let decoded = SealedEnvelope::decode(OneByteReader(&frame)).unwrap();
+            // THEN: the SealedEnvelope is decoded
+            assert_eq!(decoded.sender().as_bytes(), [1; 32]);
+            assert_eq!(decoded.recipient().as_bytes(), [2; 32]);
+            assert_eq!(decoded.msg(), &[4, 5, 6]);
+
+            // GIVEN: an unframed, i.e., version 0, SealedEnvelope
+            // WHEN: it is decoded from a reader that returns one byte at a time
+            let decoded =
+                SealedEnvelope::decode_unframed(OneByteReader(&UNFRAMED_SEALED_ENVELOPE)).unwrap();
+            // THEN: the SealedEnvelope is decoded via the legacy path
+            assert_eq!(decoded.msg(), &[4, 5, 6]);
+            // AND: the stream decoder falls back to the legacy path
+            let decoded = SealedEnvelope::decode(OneByteReader(&UNFRAMED_SEALED_ENVELOPE)).unwrap();
+            assert_eq!(decoded.msg(), &[4, 5, 6]);
+
+            // GIVEN: a truncated frame
+            // THEN: decoding fails for every truncation
+            for len in 0..frame.len() {
+                assert!(SealedEnvelope::decode(OneByteReader(&frame[..len])).is_err());
+            }
+        });
+    }
+
+    #[test]
+    fn sealed_envelope_pre_scheme_layout_decode() {
+        use super::{key_exchange::KeyExchangeScheme, SealedEnvelope};
+
+        fn check(envelope: SealedEnvelope) {
+            assert_eq!(envelope.scheme(), KeyExchangeScheme::SODIUM_BOX);
+            assert_eq!(envelope.sender().as_bytes(), [1; 32]);
+            assert_eq!(envelope.recipient().as_bytes(), [2; 32]);
+            assert_eq!(envelope.nonce().0, [3; 24]);
+            assert_eq!(envelope.msg(), &[4, 5, 6]);
+        }
+
+        run_test("sealed_envelope_pre_scheme_layout_decode", || {
+            // GIVEN: an unframed, i.e., version 0, envelope
+            // THEN: it is decoded as a SodiumBox envelope
+            check(SealedEnvelope::decode_unframed(&UNFRAMED_SEALED_ENVELOPE[..]).unwrap());
+            // AND: the plain decode detects the missing frame header and decodes it the same way
+            check(SealedEnvelope::decode(&UNFRAMED_SEALED_ENVELOPE[..]).unwrap());
+
+            // GIVEN: a version 1 frame, which does not carry the scheme
+            let mut frame = vec![0xFF, b'O', b'P', 1];
+            frame.extend_from_slice(&(UNFRAMED_SEALED_ENVELOPE.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&UNFRAMED_SEALED_ENVELOPE);
+            // THEN: it is decoded as a SodiumBox envelope
+            check(SealedEnvelope::decode(&frame[..]).unwrap());
+
+            // WHEN: the envelope is re-encoded
+            let envelope = SealedEnvelope::decode(&frame[..]).unwrap();
+            let mut frame = Vec::new();
+            envelope.encode(&mut frame).unwrap();
+            // THEN: it is encoded as a version 2 frame that carries the scheme in the header
+            assert_eq!(frame[..5], [0xFF, b'O', b'P', 2, KeyExchangeScheme::SODIUM_BOX.0]);
+            assert_eq!(frame.len(), envelope.encoded_len().unwrap());
+            // AND: the bincode payload layout is unchanged
+            assert_eq!(frame[9..], UNFRAMED_SEALED_ENVELOPE[..]);
+            check(SealedEnvelope::decode(&frame[..]).unwrap());
+
+            // GIVEN: an unsupported frame version
+            frame[3] = 3;
+            // THEN: decoding fails
+            assert!(SealedEnvelope::decode(&frame[..]).is_err());
+        });
+    }
+
+    #[test]
+    fn message_encode_on_thread_pool() {
+        use super::{BincodeOptions, Compression, Encoding, Message, Metadata, MessageTypeId};
+        use futures::Future;
+
+        run_test("message_encode_on_thread_pool", || {
+            const MSG_TYPE: MessageTypeId =
+                MessageTypeId(1867384532653698871582487715619812439);
+            // GIVEN: a message with a large compressed payload
+            let data: Vec<String> = (0..10_000).map(|i| format!("data-{}", i)).collect();
+            let msg = Message::new(
+                Metadata::new(
+                    MSG_TYPE.message_type(),
+                    Encoding::Bincode(Some(Compression::Snappy), BincodeOptions::default()),
+                    None,
+                ),
+                data,
+            );
+            let pool = tokio_threadpool::ThreadPool::new();
+
+            // WHEN: the message is encoded on the thread pool
+            let encoded_async = msg.clone().encode_on(&pool).wait().unwrap();
+            // THEN: the output matches the synchronous encoding
+            let encoded_sync = msg.encode().unwrap();
+            assert_eq!(encoded_async.data().data(), encoded_sync.data().data());
+            assert_eq!(encoded_async.metadata(), encoded_sync.metadata());
+        });
+    }
+
+    #[test]
+    fn message_type_id_macro() {
+        use super::MessageTypeId;
+
+        run_test("message_type_id_macro", || {
+            const MESSAGE_TYPE_ID: MessageTypeId = message_type_id!("01D882HDAHNQSE2MXX05WVM06G");
+            assert_eq!(
+                MESSAGE_TYPE_ID,
+                MessageTypeId(1879939206585297817630700153261916368)
+            );
+            assert_eq!(
+                MESSAGE_TYPE_ID.ulid().to_string(),
+                "01D882HDAHNQSE2MXX05WVM06G"
+            );
+            // ULID strings are case insensitive
+            assert_eq!(
+                message_type_id!("01d882hdahnqse2mxx05wvm06g"),
+                MESSAGE_TYPE_ID
+            );
+        });
+    }
+
+    #[test]
+    fn address_from_seed() {
+        use super::Address;
+        use sodiumoxide::crypto::box_;
+
+        run_test("address_from_seed", || {
+            // GIVEN: the same seed
+            let seed = [1_u8; box_::SEEDBYTES];
+            // WHEN: addresses are derived from the seed
+            let (address_1, private_key_1) = Address::from_seed(&seed);
+            let (address_2, private_key_2) = Address::from_seed(&seed);
+            // THEN: the same address and private-key are derived
+            assert_eq!(address_1, address_2);
+            assert_eq!(private_key_1, private_key_2);
+            // AND: the private-key pairs with the address
+            assert_eq!(private_key_1.public_key(), *address_1.public_key());
+
+            // WHEN: an address is derived from a different seed
+            let (address_3, _) = Address::from_seed(&[2_u8; box_::SEEDBYTES]);
+            // THEN: the addresses differ
+            assert_ne!(address_1, address_3);
+        });
+    }
+
+    #[test]
+    fn bincode_options() {
+        use super::{
+            Address, BincodeOptions, Compression, Encoding, Endian, IntEncoding, Message, Metadata,
+            MessageTypeId,
+        };
+        use sodiumoxide::crypto::box_;
+
+        #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+        struct Data {
+            id: u64,
+            bytes: Vec<u8>,
+        }
+
+        const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1880060105329373756446102937948728668);
+
+        let data = Data {
+            id: 1,
+            bytes: vec![1; 1024],
+        };
+
+        // GIVEN: the default options
+        // THEN: the options match the default bincode config
+        let bytes = Encoding::Bincode(None, BincodeOptions::default())
+            .encode(&data)
+            .unwrap();
+        assert_eq!(bytes, bincode::serialize(&data).unwrap());
+
+        for compression in vec![None, Some(Compression::Snappy)] {
+            let encoding = Encoding::Bincode(compression, BincodeOptions::default());
+            let bytes = encoding.encode(&data).unwrap();
+
+            // WHEN: the data is decoded with a byte limit that the data exceeds
+            let limited = Encoding::Bincode(compression, BincodeOptions::default().set_limit(512));
+            // THEN: decoding fails
+            match limited.decode::<Data>(&bytes) {
+                Err(err) => info!("{}", err),
+                Ok(_) => panic!("decoding should have failed because the data exceeds the limit"),
+            }
+            // WHEN: the data is decoded within the byte limit
+            let limited = Encoding::Bincode(compression, BincodeOptions::default().set_limit(2048));
+            // THEN: decoding succeeds
+            assert_eq!(limited.decode::<Data>(&bytes).unwrap(), data);
+
+            // WHEN: the data is encoded big endian
+            let big_endian =
+                Encoding::Bincode(compression, BincodeOptions::default().set_endian(Endian::Big));
+            let big_endian_bytes = big_endian.encode(&data).unwrap();
+            // THEN: the encoded bytes differ from the default little endian encoding
+            assert_ne!(big_endian_bytes, bytes);
+            // AND: the data round trips using the same options
+            assert_eq!(big_endian.decode::<Data>(&big_endian_bytes).unwrap(), data);
+
+            // WHEN: the data is encoded using varint int encoding
+            let varint = Encoding::Bincode(
+                compression,
+                BincodeOptions::default().set_int_encoding(IntEncoding::Varint),
+            );
+            let varint_bytes = varint.encode(&data).unwrap();
+            // THEN: the encoded bytes differ from the default fixint encoding
+            assert_ne!(varint_bytes, bytes);
+            // AND: the data round trips using the same options
+            assert_eq!(varint.decode::<Data>(&varint_bytes).unwrap(), data);
+            // AND: the streaming encoding matches
+            let mut buf = Vec::new();
+            varint.encode_into(&data, &mut buf).unwrap();
+            assert_eq!(buf, varint_bytes);
+            assert_eq!(varint.decode_from::<Data, _>(&buf[..]).unwrap(), data);
+        }
+
+        // GIVEN: a message encoded using varint int encoding
+        let varint = Encoding::Bincode(
+            None,
+            BincodeOptions::default().set_int_encoding(IntEncoding::Varint),
+        );
+        // THEN: small ints are encoded using fewer bytes
+        assert!(
+            varint.encode(&data).unwrap().len()
+                < Encoding::Bincode(None, BincodeOptions::default())
+                    .encode(&data)
+                    .unwrap()
+                    .len()
+        );
+        let (client_pub_key, client_priv_key) = box_::gen_keypair();
+        let (server_pub_key, server_priv_key) = box_::gen_keypair();
+        let (client_addr, server_addr) =
+            (Address::from(client_pub_key), Address::from(server_pub_key));
+        let sealing_key = server_addr.precompute_sealing_key(&client_priv_key);
+        let opening_key = client_addr.precompute_opening_key(&server_priv_key);
+        let msg = Message::new(
+            Metadata::new(MESSAGE_TYPE_ID.message_type(), varint, None),
+            data.clone(),
+        );
+        // WHEN: the message is sealed and then opened
+        let sealed_envelope = msg
+            .encoded_message(client_addr, server_addr)
+            .unwrap()
+            .open_envelope()
+            .unwrap()
+            .seal(&sealing_key);
+        let (_, msg) = sealed_envelope
+            .open(&opening_key)
+            .unwrap()
+            .encoded_message()
+            .unwrap()
+            .decode::<Data>()
+            .unwrap();
+        // THEN: the options are carried by the message metadata
+        assert_eq!(msg.metadata().encoding(), varint);
+        // AND: the message data round trips
+        assert_eq!(*msg.data(), data);
     }
 }
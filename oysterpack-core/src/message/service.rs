@@ -22,6 +22,7 @@ use futures::prelude::*;
 use oysterpack_errors::Error;
 use std::collections::HashMap;
 use std::fmt;
+use std::time::Instant;
 
 // TODO: provide integration with https://docs.rs/async-bincode/0.4.9/async_bincode
 // TODO: schedule a periodic job to clear precomputed keys that have not been used in a while
@@ -39,12 +40,14 @@ use std::fmt;
 /// Messaging actor service
 /// - is a sync actor because it needs to perform CPU bound load for cryptography and compression
 /// - the service is assigned a public-key based address
+/// - requests can be rate limited per sender address - see [RateLimiter](struct.RateLimiter.html)
 pub struct MessageService {
     address: message::Address,
     private_key: box_::SecretKey,
     // sender -> precomputed key
     precomputed_keys: HashMap<message::Address, box_::PrecomputedKey>,
     message_handlers: HashMap<message::MessageType, actix::Recipient<Request>>,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl MessageService {
@@ -55,8 +58,144 @@ impl MessageService {
             private_key,
             precomputed_keys: HashMap::new(),
             message_handlers: HashMap::new(),
+            rate_limiter: None,
         }
     }
+
+    /// Enables per sender rate limiting
+    /// - the rate limiter is consulted after the envelope is opened
+    pub fn set_rate_limiter(mut self, rate_limiter: RateLimiter) -> MessageService {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+}
+
+/// Token bucket rate limit settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    rate: f64,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// constructor
+    /// - rate: number of tokens that are refilled per second
+    /// - burst: max number of tokens in the bucket, i.e., the max number of requests that can be
+    ///   submitted back to back
+    pub fn new(rate: f64, burst: u32) -> RateLimit {
+        RateLimit { rate, burst }
+    }
+
+    /// number of tokens that are refilled per second
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// max number of tokens in the bucket
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit, now: Instant) -> TokenBucket {
+        TokenBucket {
+            tokens: f64::from(limit.burst),
+            last_refill: now,
+        }
+    }
+
+    fn try_acquire(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        if now > self.last_refill {
+            let elapsed = now.duration_since(self.last_refill);
+            let elapsed_secs =
+                elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1_000_000_000.0;
+            self.tokens = (self.tokens + elapsed_secs * limit.rate).min(f64::from(limit.burst));
+            self.last_refill = now;
+        }
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Per sender Address request rate limiter, based on the token bucket algorithm.
+/// - the default RateLimit applies to all senders, unless overridden for a specific sender
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    default_limit: RateLimit,
+    overrides: HashMap<message::Address, RateLimit>,
+    buckets: HashMap<message::Address, TokenBucket>,
+    rate_limited_count: u64,
+}
+
+impl RateLimiter {
+    /// constructor
+    pub fn new(default_limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            default_limit,
+            overrides: HashMap::new(),
+            buckets: HashMap::new(),
+            rate_limited_count: 0,
+        }
+    }
+
+    /// Overrides the RateLimit for the specified sender
+    pub fn set_limit(&mut self, sender: message::Address, limit: RateLimit) {
+        self.overrides.insert(sender, limit);
+        self.buckets.remove(&sender);
+    }
+
+    /// Removes the sender's RateLimit override
+    pub fn remove_limit(&mut self, sender: &message::Address) -> Option<RateLimit> {
+        self.buckets.remove(sender);
+        self.overrides.remove(sender)
+    }
+
+    /// Returns the RateLimit that applies to the specified sender
+    pub fn limit(&self, sender: &message::Address) -> RateLimit {
+        self.overrides
+            .get(sender)
+            .cloned()
+            .unwrap_or(self.default_limit)
+    }
+
+    /// Returns true if the sender's request is allowed, i.e., a token was acquired
+    pub fn try_acquire(&mut self, sender: &message::Address) -> bool {
+        self.try_acquire_at(sender, Instant::now())
+    }
+
+    fn try_acquire_at(&mut self, sender: &message::Address, now: Instant) -> bool {
+        let limit = self.limit(sender);
+        let acquired = self
+            .buckets
+            .entry(*sender)
+            .or_insert_with(|| TokenBucket::new(&limit, now))
+            .try_acquire(&limit, now);
+        if !acquired {
+            self.rate_limited_count += 1;
+        }
+        acquired
+    }
+
+    /// Frees the sender's token bucket, e.g., when the client disconnects
+    pub fn clear(&mut self, sender: &message::Address) {
+        self.buckets.remove(sender);
+    }
+
+    /// Total number of requests that have been rate limited
+    pub fn rate_limited_count(&self) -> u64 {
+        self.rate_limited_count
+    }
 }
 
 impl fmt::Debug for MessageService {
@@ -125,6 +264,27 @@ impl actix::Message for GetRegisteredMessageTypes {
     type Result = Vec<message::MessageType>;
 }
 
+/// Get the total number of requests that have been rate limited
+#[derive(Debug, Copy, Clone)]
+pub struct GetRateLimitedCount;
+
+impl actix::Message for GetRateLimitedCount {
+    type Result = u64;
+}
+
+impl actix::Handler<GetRateLimitedCount> for MessageService {
+    type Result = actix::MessageResult<GetRateLimitedCount>;
+
+    fn handle(&mut self, _: GetRateLimitedCount, _: &mut Self::Context) -> Self::Result {
+        actix::MessageResult(
+            self.rate_limiter
+                .as_ref()
+                .map(RateLimiter::rate_limited_count)
+                .unwrap_or(0),
+        )
+    }
+}
+
 impl actix::Handler<GetRegisteredMessageTypes> for MessageService {
     type Result = actix::MessageResult<GetRegisteredMessageTypes>;
 
@@ -149,6 +309,9 @@ impl actix::Handler<ClientDisconnect> for MessageService {
 
     fn handle(&mut self, msg: ClientDisconnect, _: &mut Self::Context) -> Self::Result {
         self.precomputed_keys.remove(&msg.0);
+        if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+            rate_limiter.clear(&msg.0);
+        }
         actix::MessageResult(())
     }
 }
@@ -227,9 +390,18 @@ impl actix::Handler<SealedEnvelopeRequest> for MessageService {
         }
 
         let sender = *req.0.sender();
+        let rate_limiter = &mut self.rate_limiter;
         let result = req
             .0
             .open(&key)
+            .and_then(|open_envelope| {
+                if let Some(rate_limiter) = rate_limiter {
+                    if !rate_limiter.try_acquire(&sender) {
+                        return Err(op_error!(errors::RateLimited::new(&sender)));
+                    }
+                }
+                Ok(open_envelope)
+            })
             .and_then(|open_envelope| open_envelope.encoded_message())
             .and_then(|encoded_message| {
                 let message_type = encoded_message.metadata().message_type();
@@ -356,6 +528,40 @@ pub mod errors {
             )
         }
     }
+
+    /// RateLimited
+    #[derive(Debug)]
+    pub struct RateLimited<'a> {
+        sender: &'a message::Address,
+    }
+
+    impl RateLimited<'_> {
+        /// Error Id(01D87MQYSDT0CFQDEC0BA21BSD)
+        pub const ERROR_ID: Id = Id(1879921718781972786537911000584204077);
+        /// Level::Alert because a sender that keeps exceeding its rate limit may be abusive
+        pub const ERROR_LEVEL: Level = Level::Alert;
+
+        /// constructor
+        pub fn new(sender: &message::Address) -> RateLimited {
+            RateLimited { sender }
+        }
+    }
+
+    impl IsError for RateLimited<'_> {
+        fn error_id(&self) -> Id {
+            Self::ERROR_ID
+        }
+
+        fn error_level(&self) -> Level {
+            Self::ERROR_LEVEL
+        }
+    }
+
+    impl fmt::Display for RateLimited<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}: request rate limit exceeded", self.sender)
+        }
+    }
 }
 
 #[allow(warnings)]
@@ -406,7 +612,10 @@ mod tests {
         let sealed_envelope = {
             let metadata = crate::message::Metadata::new(
                 Foo::MESSAGE_TYPE_ID.message_type(),
-                crate::message::Encoding::Bincode(Some(crate::message::Compression::Snappy)),
+                crate::message::Encoding::Bincode(
+                    Some(crate::message::Compression::Snappy),
+                    crate::message::BincodeOptions::default(),
+                ),
                 Some(crate::message::Deadline::ProcessingTimeoutMillis(10)),
             );
             let msg = crate::message::Message::new(
@@ -474,4 +683,48 @@ mod tests {
             }),
         );
     }
+
+    #[test]
+    fn rate_limiter() {
+        use super::{RateLimit, RateLimiter};
+        use crate::message::Address;
+        use std::time::{Duration, Instant};
+
+        // GIVEN: a rate limiter with a burst of 3 and a refill rate of 10 tokens per second
+        let mut rate_limiter = RateLimiter::new(RateLimit::new(10.0, 3));
+        let abusive_sender: Address = box_::gen_keypair().0.into();
+        let well_behaved_sender: Address = box_::gen_keypair().0.into();
+        let now = Instant::now();
+
+        // WHEN: the abusive sender exceeds the burst
+        for _ in 0..3 {
+            assert!(rate_limiter.try_acquire_at(&abusive_sender, now));
+        }
+        // THEN: the sender is rate limited
+        assert!(!rate_limiter.try_acquire_at(&abusive_sender, now));
+        assert_eq!(rate_limiter.rate_limited_count(), 1);
+        // AND: the well behaved sender is unaffected
+        assert!(rate_limiter.try_acquire_at(&well_behaved_sender, now));
+
+        // WHEN: time passes
+        let later = now + Duration::from_millis(200);
+        // THEN: tokens are refilled - 200 ms at 10 tokens per second = 2 tokens
+        assert!(rate_limiter.try_acquire_at(&abusive_sender, later));
+        assert!(rate_limiter.try_acquire_at(&abusive_sender, later));
+        assert!(!rate_limiter.try_acquire_at(&abusive_sender, later));
+        // AND: the bucket never refills beyond the burst
+        let much_later = now + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(rate_limiter.try_acquire_at(&abusive_sender, much_later));
+        }
+        assert!(!rate_limiter.try_acquire_at(&abusive_sender, much_later));
+
+        // WHEN: the sender's limit is overridden
+        rate_limiter.set_limit(abusive_sender, RateLimit::new(1.0, 1));
+        // THEN: the override is applied
+        assert_eq!(rate_limiter.limit(&abusive_sender).burst(), 1);
+        assert!(rate_limiter.try_acquire_at(&abusive_sender, much_later));
+        assert!(!rate_limiter.try_acquire_at(&abusive_sender, much_later));
+        assert_eq!(rate_limiter.limit(&well_behaved_sender).burst(), 3);
+    }
 }
@@ -23,7 +23,7 @@ use crate::security::Address;
 use oysterpack_errors::{op_error, Error, ErrorMessage};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sodiumoxide::crypto::box_;
-use std::fmt;
+use std::{convert::TryFrom, fmt};
 
 /// A sealed envelope is secured via public-key authenticated encryption. It contains a private message
 /// that is encrypted using the recipient's public-key and the sender's private-key. If the recipient
@@ -52,14 +52,14 @@ impl SealedEnvelope {
         }
     }
 
-    // TODO: implement TryFrom when it bocomes stable
     /// Converts an nng:Message into a SealedEnvelope.
+    /// - see the `TryFrom<nng::Message>` impl
     pub fn try_from_nng_message(msg: &nng::Message) -> Result<SealedEnvelope, Error> {
         try_from_nng_message(msg)
     }
 
-    // TODO: implement TryInto when it becomes stable
     /// Converts itself into an nng:Message
+    /// - see the `TryFrom<SealedEnvelope>` impl for nng::Message
     pub fn try_into_nng_message(self) -> Result<nng::Message, Error> {
         try_into_nng_message(&self)
     }
@@ -94,6 +94,22 @@ impl SealedEnvelope {
     }
 }
 
+impl TryFrom<nng::Message> for SealedEnvelope {
+    type Error = Error;
+
+    fn try_from(msg: nng::Message) -> Result<SealedEnvelope, Error> {
+        SealedEnvelope::try_from_nng_message(&msg)
+    }
+}
+
+impl TryFrom<SealedEnvelope> for nng::Message {
+    type Error = Error;
+
+    fn try_from(sealed_envelope: SealedEnvelope) -> Result<nng::Message, Error> {
+        sealed_envelope.try_into_nng_message()
+    }
+}
+
 impl fmt::Display for SealedEnvelope {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -529,6 +545,44 @@ mod test {
         assert_eq!(envelope_2.msg(), envelope.msg());
     }
 
+    #[test]
+    fn sealed_envelope_nng_try_from_conversions() {
+        use std::convert::{TryFrom, TryInto};
+
+        sodiumoxide::init().unwrap();
+        let data: &[u8] = b"cryptocurrency is the future";
+
+        let (client_public_key, client_private_key) = sodiumoxide::crypto::box_::gen_keypair();
+        let (server_public_key, server_private_key) = sodiumoxide::crypto::box_::gen_keypair();
+
+        let client_addr = Address::from(client_public_key);
+        let server_addr = Address::from(server_public_key);
+
+        let envelope = Envelope::bytes_message(client_addr, server_addr, data);
+        let sealed_envelope = envelope
+            .clone()
+            .seal(&server_addr.precompute_key(&client_private_key));
+
+        // SealedEnvelope -> nng::Message
+        let nng_msg: nng::Message = sealed_envelope.clone().try_into().unwrap();
+        // nng::Message -> SealedEnvelope
+        let sealed_envelope_2 = SealedEnvelope::try_from(nng_msg).unwrap();
+        let envelope_2 = sealed_envelope_2
+            .open(&client_addr.precompute_key(&server_private_key))
+            .unwrap();
+        assert_eq!(envelope_2.sender(), envelope.sender());
+        assert_eq!(envelope_2.recipient(), envelope.recipient());
+        assert_eq!(envelope_2.msg(), envelope.msg());
+
+        // the same conversions, using the TryFrom and TryInto forms that were not used above
+        let nng_msg = nng::Message::try_from(sealed_envelope).unwrap();
+        let sealed_envelope_3: SealedEnvelope = nng_msg.try_into().unwrap();
+        let envelope_3 = sealed_envelope_3
+            .open(&client_addr.precompute_key(&server_private_key))
+            .unwrap();
+        assert_eq!(envelope_3.msg(), envelope.msg());
+    }
+
     #[test]
     fn envelope_try_into_bytes_message() {
         #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -16,7 +16,11 @@
 
 //! Provides support for a request/reply RPC-like services.
 
-use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::{
+    fmt,
+    marker::PhantomData,
+    panic::{RefUnwindSafe, UnwindSafe},
+};
 
 pub mod client;
 pub mod server;
@@ -41,3 +45,69 @@ where
     /// processes the request message and returns a reply message
     fn process(&mut self, req: Req) -> Rep;
 }
+
+/// MessageProcessorFactory that adapts a closure, which means simple request handlers do not require
+/// a factory struct and a processor struct to be defined.
+/// - `F` is invoked once per MessageProcessor instance, i.e., each aio context gets its own
+///   processor closure, which may hold its own mutable state
+///
+/// ## Example
+/// ```rust,ignore
+/// let factory = FnProcessorFactory::new(|| |req: nng::Message| req);
+/// let server = Server::builder(ListenerSettings::new(url), factory).spawn()?;
+/// ```
+pub struct FnProcessorFactory<F, P> {
+    f: F,
+    _processor: PhantomData<fn() -> P>,
+}
+
+impl<F, P> FnProcessorFactory<F, P>
+where
+    F: Fn() -> P + Send + Sync + 'static,
+{
+    /// constructor
+    pub fn new(f: F) -> FnProcessorFactory<F, P> {
+        FnProcessorFactory {
+            f,
+            _processor: PhantomData,
+        }
+    }
+}
+
+impl<F, P> fmt::Debug for FnProcessorFactory<F, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("FnProcessorFactory")
+    }
+}
+
+impl<F, P, Req, Rep> MessageProcessorFactory<FnProcessor<P>, Req, Rep> for FnProcessorFactory<F, P>
+where
+    Req: Send + 'static,
+    Rep: Send + 'static,
+    F: Fn() -> P + Send + Sync + 'static,
+    P: FnMut(Req) -> Rep + Send + Sync + RefUnwindSafe + UnwindSafe + 'static,
+{
+    fn new(&self) -> FnProcessor<P> {
+        FnProcessor((self.f)())
+    }
+}
+
+/// MessageProcessor that delegates to the wrapped closure - see [FnProcessorFactory](struct.FnProcessorFactory.html)
+pub struct FnProcessor<P>(P);
+
+impl<P> fmt::Debug for FnProcessor<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("FnProcessor")
+    }
+}
+
+impl<P, Req, Rep> MessageProcessor<Req, Rep> for FnProcessor<P>
+where
+    Req: Send + 'static,
+    Rep: Send + 'static,
+    P: FnMut(Req) -> Rep + Send + Sync + RefUnwindSafe + UnwindSafe + 'static,
+{
+    fn process(&mut self, req: Req) -> Rep {
+        (self.0)(req)
+    }
+}
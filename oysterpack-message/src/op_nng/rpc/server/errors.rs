@@ -134,3 +134,73 @@ impl From<nng::Error> for ListenerSetOptError {
         ListenerSetOptError(err)
     }
 }
+
+/// The request payload exceeded the server's max payload size, and was not processed.
+/// - when the request is rejected, the error is sent back as the reply - see
+///   [PayloadTooLarge::from_reply()](struct.PayloadTooLarge.html#method.from_reply)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PayloadTooLarge {
+    size: usize,
+    max: usize,
+}
+
+impl PayloadTooLarge {
+    /// Error Id
+    pub const ERROR_ID: oysterpack_errors::Id =
+        oysterpack_errors::Id(1879941969121857758310036357687176090);
+    /// Level::Error
+    pub const ERROR_LEVEL: oysterpack_errors::Level = oysterpack_errors::Level::Error;
+
+    /// constructor
+    pub fn new(size: usize, max: usize) -> PayloadTooLarge {
+        PayloadTooLarge { size, max }
+    }
+
+    /// the request payload size
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// the max payload size that the server accepts
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Encodes the error as a reply message: (ERROR_ID, PayloadTooLarge) bincode encoded
+    /// - the reply size is fixed, regardless of the request size
+    pub fn to_reply(&self) -> Result<nng::Message, nng::Error> {
+        let bytes = bincode::serialize(&(Self::ERROR_ID.0, self))
+            .expect("PayloadTooLarge bincode serialization should never fail");
+        let mut msg = nng::Message::with_capacity(bytes.len())?;
+        msg.push_back(&bytes)?;
+        Ok(msg)
+    }
+
+    /// Decodes the reply message - returns None if the reply is not a PayloadTooLarge error reply
+    pub fn from_reply(reply: &nng::Message) -> Option<PayloadTooLarge> {
+        match bincode::deserialize::<(u128, PayloadTooLarge)>(&*reply.body()) {
+            Ok((id, err)) if id == Self::ERROR_ID.0 => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl IsError for PayloadTooLarge {
+    fn error_id(&self) -> oysterpack_errors::Id {
+        Self::ERROR_ID
+    }
+
+    fn error_level(&self) -> oysterpack_errors::Level {
+        Self::ERROR_LEVEL
+    }
+}
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Request payload size ({}) exceeds the max payload size ({})",
+            self.size, self.max
+        )
+    }
+}
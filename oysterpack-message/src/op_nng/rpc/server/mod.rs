@@ -33,8 +33,12 @@ use std::{
     fmt,
     marker::PhantomData,
     num::NonZeroUsize,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
 pub mod errors;
@@ -43,16 +47,34 @@ pub mod errors;
 #[cfg(test)]
 mod tests;
 
+/// Default amount of time that the server will wait for in-flight replies to be sent when the server
+/// is stopped
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Default max number of attempts to initiate an aio receive operation before the aio context is
+/// recreated
+pub const DEFAULT_MAX_RECV_ATTEMPTS: usize = 3;
+
 /// nng RPC server
 /// - if MessageProcessor(s) panic, then the aio context that contains the MessageProcessor will terminate
 ///   - each aio context represents a logical request handler thread. When all aio contexts terminate,
 ///     then the server will no longer be able to serve requests
 ///   - MessageProcessor(s) should never panic - that is either a bug, resource issue, or configuration
 ///     issue (which may cause the resource issue)
+/// - when the server is stopped, requests that are being processed are allowed to complete and their
+///   replies are sent before the aio contexts are closed, bounded by the shutdown grace period
+///   - see [Builder::shutdown_grace_period()](struct.Builder.html#method.shutdown_grace_period)
+/// - nng errors on an aio context are logged and counted, and the aio context attempts to recover
+///   instead of panicking, i.e., a transient nng error does not take down the aio context
+///   - failed aio receive operations are retried, and if the retries are exhausted, then the aio
+///     context is recreated - see [Builder::max_recv_attempts()](struct.Builder.html#method.max_recv_attempts)
+///   - errors are counted via [aio_error_count()](struct.Server.html#method.aio_error_count)
 pub struct Server {
     stop_trigger: crossbeam::channel::Sender<()>,
     running: Arc<Mutex<bool>>,
     join_handle: Option<thread::JoinHandle<()>>,
+    aio_error_count: Arc<AtomicUsize>,
+    aio_context_recreate_count: Arc<AtomicUsize>,
 }
 
 impl Server {
@@ -73,12 +95,13 @@ impl Server {
     /// ## Errors
     /// - SocketCreateError - when the socket fails to be created
     /// - AioCreateError - when an aio context fails to be created
-    /// - AioReceiveError - when the initial aio context receive operations fail
     fn spawn<Factory, Processor>(
         listener_settings: ListenerSettings,
         message_processor_factory: &Factory,
         socket_settings: Option<SocketSettings>,
         thread_config: Option<ThreadConfig>,
+        shutdown_grace_period: Duration,
+        aio_recovery_settings: AioRecoverySettings,
     ) -> Result<Server, Error>
     where
         Factory: MessageProcessorFactory<Processor, nng::Message, nng::Message>,
@@ -88,7 +111,11 @@ impl Server {
             socket: &nng::Socket,
             message_processor_factory: &Factory,
             aio_context_count: usize,
-        ) -> Result<Vec<(nng::Aio, nng::Context)>, Error>
+            max_payload_size: Option<usize>,
+            in_flight: &Arc<AtomicUsize>,
+            stopping: &Arc<AtomicBool>,
+            aio_recovery: &AioRecovery,
+        ) -> Result<Vec<(nng::Aio, Arc<Mutex<nng::Context>>)>, Error>
         where
             Factory: MessageProcessorFactory<Processor, nng::Message, nng::Message>,
             Processor: MessageProcessor<nng::Message, nng::Message>,
@@ -99,65 +126,127 @@ impl Server {
             2. the errors are reported on a channel
             3. the errors are reported via an nng client - pub/sub
             */
+            // - `in_flight` tracks the number of requests that have been received, but whose replies
+            //   have not yet been sent
+            // - once the server is `stopping`, no new receive operations are initiated
+            // - requests that exceed `max_payload_size` are not dispatched to the MessageProcessor -
+            //   a PayloadTooLarge error reply is sent instead
+            // - nng errors are logged and counted, and the receive operation is restarted via
+            //   `aio_recovery`, which may recreate the context
+            #[allow(clippy::too_many_arguments)]
             fn handle_aio_event<T>(
                 aio: &nng::Aio,
-                ctx: &nng::Context,
+                ctx: &mut nng::Context,
                 state: &mut AioState,
                 message_processor: &mut T,
+                max_payload_size: Option<usize>,
+                in_flight: &AtomicUsize,
+                stopping: &AtomicBool,
+                aio_recovery: &AioRecovery,
             ) where
                 T: MessageProcessor<nng::Message, nng::Message>,
             {
+                let restart_recv = |ctx: &mut nng::Context| {
+                    if !stopping.load(Ordering::SeqCst) {
+                        aio_recovery.recv(aio, ctx);
+                    }
+                    AioState::Recv
+                };
+
+                let result = match aio.result() {
+                    Some(result) => result,
+                    None => {
+                        debug!("aio event has no result: {:?}", *state);
+                        return;
+                    }
+                };
                 let new_state = match *state {
-                    AioState::Recv => match aio.result().unwrap() {
+                    AioState::Recv => match result {
                         Ok(_) => match aio.get_msg() {
                             Some(req) => {
-                                let rep = message_processor.process(req);
-                                match ctx.send(&aio, rep) {
-                                    Ok(_) => AioState::Send,
-                                    Err((_rep, err)) => {
-                                        error!("failed to send reply: {}", err);
-                                        aio.cancel();
-                                        ctx.recv(&aio).expect("aio.recv() failed");
-                                        AioState::Recv
+                                in_flight.fetch_add(1, Ordering::SeqCst);
+                                let rep = match max_payload_size {
+                                    Some(max) if req.body().len() > max => {
+                                        let err = errors::PayloadTooLarge::new(req.body().len(), max);
+                                        warn!("request was rejected: {}", err);
+                                        err.to_reply()
+                                    }
+                                    _ => Ok(message_processor.process(req)),
+                                };
+                                match rep {
+                                    Ok(rep) => match ctx.send(&aio, rep) {
+                                        Ok(_) => AioState::Send,
+                                        Err((_rep, err)) => {
+                                            in_flight.fetch_sub(1, Ordering::SeqCst);
+                                            aio_recovery.error(format_args!(
+                                                "failed to send reply: {}",
+                                                err
+                                            ));
+                                            aio.cancel();
+                                            restart_recv(ctx)
+                                        }
+                                    },
+                                    Err(err) => {
+                                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                                        aio_recovery.error(format_args!(
+                                            "failed to create error reply: {}",
+                                            err
+                                        ));
+                                        restart_recv(ctx)
                                     }
                                 }
                             }
                             None => {
                                 debug!("No message was found ... initiating aio.recv()");
-                                ctx.recv(&aio).expect("aio.recv() failed");
-                                AioState::Recv
+                                restart_recv(ctx)
                             }
                         },
-                        Err(err) => {
-                            match err.kind() {
-                                nng::ErrorKind::Closed => debug!("server aio context is closed"),
-                                _ => error!("aio receive error: {}", err),
+                        Err(err) => match err.kind() {
+                            nng::ErrorKind::Closed => {
+                                debug!("server aio context is closed");
+                                AioState::Recv
                             }
-
-                            AioState::Recv
-                        }
+                            _ => {
+                                aio_recovery.error(format_args!("aio receive error: {}", err));
+                                restart_recv(ctx)
+                            }
+                        },
                     },
                     AioState::Send => {
-                        if let Err(err) = aio.result().unwrap() {
-                            error!("aio send error: {}", err)
+                        if let Err(err) = result {
+                            aio_recovery.error(format_args!("aio send error: {}", err));
                         }
-                        ctx.recv(aio).unwrap();
-                        AioState::Recv
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        restart_recv(ctx)
                     }
                 };
 
                 *state = new_state;
             }
 
-            let aio_contexts: Vec<(nng::Aio, nng::Context)> = (0..aio_context_count)
+            let aio_contexts: Vec<(nng::Aio, Arc<Mutex<nng::Context>>)> = (0..aio_context_count)
                 .map(|_| {
                     let mut state = AioState::Recv;
                     let mut message_processor = message_processor_factory.new();
 
-                    let ctx: nng::Context = new_aio_context(socket)?;
+                    // the context is shared with the aio callback, which may recreate it
+                    let ctx = Arc::new(Mutex::new(new_aio_context(socket)?));
                     let callback_context = ctx.clone();
+                    let in_flight = in_flight.clone();
+                    let stopping = stopping.clone();
+                    let aio_recovery = aio_recovery.clone();
                     let aio = nng::Aio::with_callback(move |aio| {
-                        handle_aio_event(aio, &callback_context, &mut state, &mut message_processor)
+                        let mut ctx = callback_context.lock().unwrap();
+                        handle_aio_event(
+                            aio,
+                            &mut ctx,
+                            &mut state,
+                            &mut message_processor,
+                            max_payload_size,
+                            &in_flight,
+                            &stopping,
+                            &aio_recovery,
+                        )
                     })
                     .map_err(|err| op_error!(errors::AioCreateError::from(err)))?;
 
@@ -177,11 +266,12 @@ impl Server {
             }
         }
 
-        fn start_aio_contexts(aio_contexts: &[(nng::Aio, nng::Context)]) {
+        fn start_aio_contexts(
+            aio_contexts: &[(nng::Aio, Arc<Mutex<nng::Context>>)],
+            aio_recovery: &AioRecovery,
+        ) {
             for (a, c) in aio_contexts {
-                c.recv(a)
-                    .map_err(|err| op_error!(errors::AioReceiveError::from(err)))
-                    .unwrap();
+                aio_recovery.recv(a, &mut c.lock().unwrap());
             }
             debug!("aio context receive operations have been initiated");
         }
@@ -190,15 +280,24 @@ impl Server {
         /***** function logic ******/
         /***************************/
 
-        let socket = create_socket(socket_settings)?;
+        let socket = Arc::new(create_socket(socket_settings)?);
 
         // used to send a stop signal to the server
         let (stop_sender, stop_receiver) = crossbeam::channel::bounded(0);
 
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let stopping = Arc::new(AtomicBool::new(false));
+        let aio_recovery = AioRecovery::new(socket.clone(), aio_recovery_settings);
+        let aio_error_count = aio_recovery.error_count.clone();
+        let aio_context_recreate_count = aio_recovery.context_recreate_count.clone();
         let aio_contexts = create_aio_contexts(
             &socket,
             message_processor_factory,
             listener_settings.aio_context_count,
+            listener_settings.max_payload_size,
+            &in_flight,
+            &stopping,
+            &aio_recovery,
         )?;
 
         #[allow(clippy::mutex_atomic)]
@@ -216,7 +315,7 @@ impl Server {
                 let _listener = listener_settings.start_listener(&socket).unwrap();
                 debug!("socket listener has been started");
 
-                start_aio_contexts(&aio_contexts);
+                start_aio_contexts(&aio_contexts, &aio_recovery);
                 {
                     let mut running = running_ref.0.lock().unwrap();
                     *running = true;
@@ -224,8 +323,22 @@ impl Server {
 
                 // block until stop signal is received
                 let _ = stop_receiver.recv();
-                // when the thread exits, the socket listener and aio contexts will will be closed
                 debug!("stopping server");
+                // give in-flight requests a chance to send their replies before the aio contexts are closed
+                stopping.store(true, Ordering::SeqCst);
+                let deadline = Instant::now() + shutdown_grace_period;
+                while in_flight.load(Ordering::SeqCst) > 0 {
+                    if Instant::now() >= deadline {
+                        warn!(
+                            "shutdown grace period ({:?}) expired with {} in-flight replies",
+                            shutdown_grace_period,
+                            in_flight.load(Ordering::SeqCst)
+                        );
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+                // when the thread exits, the socket listener and aio contexts will will be closed
             })
             .expect("failed to spawn server thread");
 
@@ -233,6 +346,8 @@ impl Server {
             stop_trigger: stop_sender,
             running,
             join_handle: Some(join_handle),
+            aio_error_count,
+            aio_context_recreate_count,
         })
     }
 
@@ -261,6 +376,17 @@ impl Server {
     pub fn running(&self) -> bool {
         *self.running.lock().unwrap()
     }
+
+    /// Number of nng errors that have occurred on the server's aio contexts
+    pub fn aio_error_count(&self) -> usize {
+        self.aio_error_count.load(Ordering::SeqCst)
+    }
+
+    /// Number of times an aio context was recreated because it failed to initiate a receive
+    /// operation
+    pub fn aio_context_recreate_count(&self) -> usize {
+        self.aio_context_recreate_count.load(Ordering::SeqCst)
+    }
 }
 
 impl fmt::Debug for Server {
@@ -283,6 +409,107 @@ impl StopSignal {
 #[derive(Debug)]
 struct Running(Arc<Mutex<bool>>);
 
+#[derive(Debug, Copy, Clone)]
+struct AioRecoverySettings {
+    max_recv_attempts: usize,
+    // number of aio receive operations that will be failed, which is used to inject faults in tests
+    #[cfg(test)]
+    recv_faults: usize,
+}
+
+/// Recovers aio contexts from nng errors, and counts the errors
+#[derive(Clone)]
+struct AioRecovery {
+    socket: Arc<nng::Socket>,
+    max_recv_attempts: usize,
+    error_count: Arc<AtomicUsize>,
+    context_recreate_count: Arc<AtomicUsize>,
+    #[cfg(test)]
+    recv_faults: Arc<AtomicUsize>,
+}
+
+impl AioRecovery {
+    fn new(socket: Arc<nng::Socket>, settings: AioRecoverySettings) -> AioRecovery {
+        AioRecovery {
+            socket,
+            max_recv_attempts: settings.max_recv_attempts,
+            error_count: Arc::new(AtomicUsize::new(0)),
+            context_recreate_count: Arc::new(AtomicUsize::new(0)),
+            #[cfg(test)]
+            recv_faults: Arc::new(AtomicUsize::new(settings.recv_faults)),
+        }
+    }
+
+    /// logs and counts the error
+    fn error(&self, msg: fmt::Arguments) {
+        self.error_count.fetch_add(1, Ordering::SeqCst);
+        error!("{}", msg);
+    }
+
+    /// Initiates an aio receive operation
+    /// - if all attempts fail on the current context, then the context is recreated and the receive
+    ///   operation is attempted on the new context
+    /// - returns false if the aio context could not be recovered, i.e., it will no longer receive
+    ///   requests
+    fn recv(&self, aio: &nng::Aio, ctx: &mut nng::Context) -> bool {
+        if self.try_recv(aio, ctx) {
+            return true;
+        }
+        match new_aio_context(&self.socket) {
+            Ok(new_ctx) => {
+                self.context_recreate_count.fetch_add(1, Ordering::SeqCst);
+                warn!("aio context has been recreated");
+                *ctx = new_ctx;
+                if self.try_recv(aio, ctx) {
+                    return true;
+                }
+            }
+            Err(err) => self.error(format_args!("{}", err)),
+        }
+        error!("aio context could not be recovered - it will no longer receive requests");
+        false
+    }
+
+    fn try_recv(&self, aio: &nng::Aio, ctx: &nng::Context) -> bool {
+        for attempt in 1..=self.max_recv_attempts {
+            match self.start_recv(aio, ctx) {
+                Ok(_) => return true,
+                Err(err) => {
+                    self.error(format_args!(
+                        "attempt {} of {}: {}",
+                        attempt,
+                        self.max_recv_attempts,
+                        op_error!(errors::AioReceiveError::from(err))
+                    ));
+                    if attempt < self.max_recv_attempts {
+                        thread::sleep(Duration::from_millis(attempt as u64));
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    #[cfg(not(test))]
+    fn start_recv(&self, aio: &nng::Aio, ctx: &nng::Context) -> Result<(), nng::Error> {
+        ctx.recv(aio)
+    }
+
+    #[cfg(test)]
+    fn start_recv(&self, aio: &nng::Aio, ctx: &nng::Context) -> Result<(), nng::Error> {
+        let faults = self.recv_faults.load(Ordering::SeqCst);
+        if faults > 0
+            && self
+                .recv_faults
+                .compare_and_swap(faults, faults - 1, Ordering::SeqCst)
+                == faults
+        {
+            return Err(nng::Error::from(nng::ErrorKind::TryAgain));
+        }
+        ctx.recv(aio)
+    }
+}
+
 impl Drop for Running {
     fn drop(&mut self) {
         *self.0.lock().unwrap() = false;
@@ -300,6 +527,8 @@ where
     message_processor_factory: Option<Factory>,
     socket_settings: Option<SocketSettings>,
     thread_config: Option<ThreadConfig>,
+    shutdown_grace_period: Duration,
+    aio_recovery_settings: AioRecoverySettings,
     _processor_phantom_data: PhantomData<Processor>,
 }
 
@@ -318,6 +547,12 @@ where
             message_processor_factory: Some(message_processor_factory),
             socket_settings: None,
             thread_config: None,
+            shutdown_grace_period: DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            aio_recovery_settings: AioRecoverySettings {
+                max_recv_attempts: DEFAULT_MAX_RECV_ATTEMPTS,
+                #[cfg(test)]
+                recv_faults: 0,
+            },
             _processor_phantom_data: PhantomData,
         }
     }
@@ -336,6 +571,31 @@ where
         builder
     }
 
+    /// Max amount of time to wait for in-flight replies to be sent when the server is stopped
+    /// - default = [DEFAULT_SHUTDOWN_GRACE_PERIOD](constant.DEFAULT_SHUTDOWN_GRACE_PERIOD.html)
+    pub fn shutdown_grace_period(self, grace_period: Duration) -> Builder<Factory, Processor> {
+        let mut builder = self;
+        builder.shutdown_grace_period = grace_period;
+        builder
+    }
+
+    /// Max number of attempts to initiate an aio receive operation before the aio context is
+    /// recreated
+    /// - default = [DEFAULT_MAX_RECV_ATTEMPTS](constant.DEFAULT_MAX_RECV_ATTEMPTS.html)
+    pub fn max_recv_attempts(self, attempts: NonZeroUsize) -> Builder<Factory, Processor> {
+        let mut builder = self;
+        builder.aio_recovery_settings.max_recv_attempts = attempts.get();
+        builder
+    }
+
+    /// the specified number of aio receive operations will fail
+    #[cfg(test)]
+    fn recv_faults(self, count: usize) -> Builder<Factory, Processor> {
+        let mut builder = self;
+        builder.aio_recovery_settings.recv_faults = count;
+        builder
+    }
+
     /// Spawns a new server instance in a background thread
     ///
     /// ## Panics
@@ -346,6 +606,8 @@ where
             &builder.message_processor_factory.take().unwrap(),
             builder.socket_settings.take(),
             builder.thread_config.take(),
+            builder.shutdown_grace_period,
+            builder.aio_recovery_settings,
         )
     }
 }
@@ -368,6 +630,7 @@ pub struct ListenerSettings {
     keep_alive: Option<bool>,
     non_blocking: bool,
     aio_context_count: usize,
+    max_payload_size: Option<usize>,
 }
 
 impl ListenerSettings {
@@ -380,6 +643,7 @@ impl ListenerSettings {
             keep_alive: None,
             non_blocking: false,
             aio_context_count: 1,
+            max_payload_size: None,
         }
     }
 
@@ -435,6 +699,15 @@ impl ListenerSettings {
         self.aio_context_count
     }
 
+    /// The max request payload size that will be dispatched to the MessageProcessor.
+    ///
+    /// Requests that exceed the max payload size are rejected with a
+    /// [PayloadTooLarge](errors/struct.PayloadTooLarge.html) error reply. Unlike `recv_max_size`,
+    /// which silently discards messages at the transport level, the client is notified.
+    pub fn max_payload_size(&self) -> Option<usize> {
+        self.max_payload_size
+    }
+
     /// The maximum message size that the will be accepted from a remote peer.
     ///
     /// If a peer attempts to send a message larger than this, then the message will be discarded.
@@ -505,4 +778,11 @@ impl ListenerSettings {
         settings.aio_context_count = count.get();
         settings
     }
+
+    /// Sets the max request payload size that will be dispatched to the MessageProcessor
+    pub fn set_max_payload_size(self, max_payload_size: usize) -> Self {
+        let mut settings = self;
+        settings.max_payload_size = Some(max_payload_size);
+        settings
+    }
 }
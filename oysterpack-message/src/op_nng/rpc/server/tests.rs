@@ -19,7 +19,10 @@ use oysterpack_uid::ULID;
 use std::{
     iter::Iterator,
     num::NonZeroUsize,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     thread,
     time::{Duration, Instant},
 };
@@ -193,6 +196,108 @@ fn rpc_server() {
     server.join().unwrap();
 }
 
+/// when the server is stopped, replies for requests that are in-flight should still be sent
+#[test]
+fn rpc_server_graceful_stop() {
+    oysterpack_log::init(log_config(), oysterpack_log::StderrLogger);
+
+    let url = Arc::new(format!("inproc://{}", ULID::generate()));
+    let server = Server::builder(super::ListenerSettings::new(&*url.as_str()), TestProcessor)
+        .shutdown_grace_period(Duration::from_secs(2))
+        .spawn()
+        .unwrap();
+    while !server.running() {
+        thread::yield_now();
+    }
+
+    // GIVEN: a request is being processed
+    const SLEEP_TIME: u32 = 200;
+    let client_thread_handle = {
+        let url = url.clone();
+        thread::spawn(move || send_sleep_request(&*url.as_str(), SLEEP_TIME))
+    };
+    // give the client a chance to send the request
+    thread::sleep_ms(50);
+
+    // WHEN: the server is stopped while the request is in-flight
+    server.stop();
+    server.join().unwrap();
+
+    // THEN: the client still receives the reply
+    let duration = client_thread_handle.join().unwrap().unwrap();
+    assert!(duration >= Duration::from_millis(u64::from(SLEEP_TIME)));
+}
+
+/// requests that exceed the max payload size should be rejected with an error reply, without
+/// invoking the MessageProcessor
+#[test]
+fn rpc_server_max_payload_size() {
+    oysterpack_log::init(log_config(), oysterpack_log::StderrLogger);
+
+    #[derive(Debug, Clone, Default)]
+    struct CountingProcessor(Arc<AtomicUsize>);
+
+    impl MessageProcessorFactory<CountingProcessor, nng::Message, nng::Message> for CountingProcessor {
+        fn new(&self) -> CountingProcessor {
+            self.clone()
+        }
+    }
+
+    impl MessageProcessor<nng::Message, nng::Message> for CountingProcessor {
+        fn process(&mut self, req: nng::Message) -> nng::Message {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            req
+        }
+    }
+
+    const MAX_PAYLOAD_SIZE: usize = 1024;
+    let url = format!("inproc://{}", ULID::generate());
+    let processor = CountingProcessor::default();
+    let process_count = processor.0.clone();
+    let server = Server::builder(
+        super::ListenerSettings::new(url.as_str()).set_max_payload_size(MAX_PAYLOAD_SIZE),
+        processor,
+    )
+    .spawn()
+    .unwrap();
+
+    let mut socket = Socket::new(nng::Protocol::Req0).unwrap();
+    socket
+        .set_opt::<nng::options::RecvTimeout>(Some(Duration::from_secs(2)))
+        .unwrap();
+    let dialer = match nng::DialerOptions::new(&socket, url.as_str())
+        .unwrap()
+        .start(true)
+    {
+        Ok(dialer) => dialer,
+        Err((_, err)) => panic!(err),
+    };
+
+    // GIVEN: a request that is within the max payload size
+    let mut req = nng::Message::with_capacity(MAX_PAYLOAD_SIZE).unwrap();
+    req.push_back(&vec![1_u8; MAX_PAYLOAD_SIZE]).unwrap();
+    socket.send(req).unwrap();
+    // THEN: the request is processed
+    let rep = socket.recv().unwrap();
+    assert!(errors::PayloadTooLarge::from_reply(&rep).is_none());
+    assert_eq!(process_count.load(Ordering::SeqCst), 1);
+
+    // GIVEN: a request that exceeds the max payload size
+    let mut req = nng::Message::with_capacity(MAX_PAYLOAD_SIZE * 10).unwrap();
+    req.push_back(&vec![1_u8; MAX_PAYLOAD_SIZE * 10]).unwrap();
+    socket.send(req).unwrap();
+    // THEN: an error reply is returned
+    let rep = socket.recv().unwrap();
+    let err = errors::PayloadTooLarge::from_reply(&rep).unwrap();
+    assert_eq!(err.size(), MAX_PAYLOAD_SIZE * 10);
+    assert_eq!(err.max(), MAX_PAYLOAD_SIZE);
+    // AND: the MessageProcessor was not invoked
+    assert_eq!(process_count.load(Ordering::SeqCst), 1);
+
+    server.stop();
+    server.join().unwrap();
+}
+
 /// when all aio contexts are busy, client requests will be blocked waiting for an aio context to
 /// free up
 #[test]
@@ -211,7 +316,9 @@ fn rpc_server_all_contexts_busy() {
     let listener_settings =
         super::ListenerSettings::new(&*url.as_str()).set_aio_count(NonZeroUsize::new(2).unwrap());
 
-    let server = super::Server::spawn(listener_settings, &TestProcessor, None, None).unwrap();
+    let server = Server::builder(listener_settings, TestProcessor)
+        .spawn()
+        .unwrap();
 
     // wait for the client background request completes
     client_thread_handle.join();
@@ -283,6 +390,55 @@ fn rpc_server_builder() {
     server.join().unwrap();
 }
 
+/// simple servers can be spawned using a closure based MessageProcessorFactory
+#[test]
+fn rpc_server_fn_processor_factory() {
+    oysterpack_log::init(log_config(), oysterpack_log::StderrLogger);
+
+    let url = format!("inproc://{}", ULID::generate());
+    let process_count = Arc::new(AtomicUsize::new(0));
+    // GIVEN: a server whose MessageProcessor is an echo closure
+    let factory = {
+        let process_count = process_count.clone();
+        crate::op_nng::rpc::FnProcessorFactory::new(move || {
+            let process_count = process_count.clone();
+            move |req: nng::Message| {
+                process_count.fetch_add(1, Ordering::SeqCst);
+                req
+            }
+        })
+    };
+    let server = Server::builder(super::ListenerSettings::new(url.as_str()), factory)
+        .spawn()
+        .unwrap();
+
+    let mut socket = Socket::new(nng::Protocol::Req0).unwrap();
+    socket
+        .set_opt::<nng::options::RecvTimeout>(Some(Duration::from_secs(2)))
+        .unwrap();
+    let dialer = match nng::DialerOptions::new(&socket, url.as_str())
+        .unwrap()
+        .start(true)
+    {
+        Ok(dialer) => dialer,
+        Err((_, err)) => panic!(err),
+    };
+
+    for i in 0..10_u8 {
+        // WHEN: a request is sent
+        let mut req = nng::Message::with_capacity(1).unwrap();
+        req.push_back(&[i]).unwrap();
+        socket.send(req).unwrap();
+        // THEN: the request is echoed back
+        let rep = socket.recv().unwrap();
+        assert_eq!(&*rep.body(), &[i]);
+    }
+    assert_eq!(process_count.load(Ordering::SeqCst), 10);
+
+    server.stop();
+    server.join().unwrap();
+}
+
 /// when a message processor panics, the aio context is terminated - this means that over time,
 /// the server will become unresponsive, i.e., when all aio contexts have terminated
 #[test]
@@ -347,3 +503,48 @@ fn message_processor_panics() {
     );
     server.join().unwrap();
 }
+
+/// recoverable aio context errors should not take down the server
+#[test]
+fn rpc_server_recovers_from_aio_context_errors() {
+    oysterpack_log::init(log_config(), oysterpack_log::StderrLogger);
+
+    let url = format!("inproc://{}", ULID::generate());
+    let listener_settings =
+        super::ListenerSettings::new(url.as_str()).set_aio_count(NonZeroUsize::new(2).unwrap());
+
+    // GIVEN: the first 3 aio receive operations fail, and the aio context is recreated after 2
+    // consecutive failures
+    let server = Server::builder(listener_settings, TestProcessor)
+        .max_recv_attempts(NonZeroUsize::new(2).unwrap())
+        .recv_faults(3)
+        .spawn()
+        .unwrap();
+    while !server.running() {
+        thread::yield_now();
+    }
+
+    // THEN: the server continues to serve requests
+    for _ in 0..10 {
+        send_sleep_request_with_recv_timeout(url.as_str(), 0, Duration::from_secs(2)).unwrap();
+    }
+    // AND: the errors were counted
+    assert_eq!(server.aio_error_count(), 3);
+    // AND: the failing aio context was recreated
+    assert_eq!(server.aio_context_recreate_count(), 1);
+
+    // WHEN: a request is sent while the other aio context is busy
+    let client_thread_handle = {
+        let url = url.clone();
+        thread::spawn(move || send_sleep_request(url.as_str(), 200))
+    };
+    thread::sleep_ms(50);
+    // THEN: the recovered aio context serves the request
+    let duration =
+        send_sleep_request_with_recv_timeout(url.as_str(), 0, Duration::from_secs(2)).unwrap();
+    assert!(duration < Duration::from_millis(150));
+    client_thread_handle.join().unwrap().unwrap();
+
+    server.stop();
+    server.join().unwrap();
+}
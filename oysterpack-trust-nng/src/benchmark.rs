@@ -0,0 +1,446 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides a benchmark harness for measuring the throughput and latency of a request/reply server
+//! and client combo over a configured nng transport.
+//!
+//! [run()](fn.run.html) works as follows:
+//! 1. an echo server is spawned using the configured [ListenerConfig](../reqrep/server/struct.ListenerConfig.html)
+//! 2. a client is registered using the configured [DialerConfig](../reqrep/client/struct.DialerConfig.html)
+//! 3. the requests are evenly split across `concurrency` tasks, where each task sends its requests
+//!    sequentially and records the request/reply round trip latency
+//! 4. the client is unregistered and the server is stopped, even if the benchmark failed
+//!
+//! ## Notes
+//! - each benchmark run uses a newly generated ReqRepId
+//! - the load is driven on the global executor, i.e., `run()` blocks the calling thread until the
+//!   benchmark is complete, and must not be called from within an executor task
+
+use crate::reqrep::{
+    client::{self, Client, DialerConfig},
+    server::{self, ListenerConfig, ServerHandle},
+};
+use failure::Fail;
+use futures::{
+    future::{join_all, FutureExt},
+    task::SpawnExt,
+};
+use oysterpack_log::*;
+use oysterpack_trust::{
+    concurrent::{
+        execution::{global_executor, Executor},
+        messaging::reqrep::{self, *},
+    },
+    metrics,
+};
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+/// Runs the benchmark
+/// - `request_count` is the total number of requests that are sent
+/// - `concurrency` is the number of tasks that concurrently send requests
+///
+/// All resources that are spawned by the benchmark are cleaned up before returning.
+pub fn run(
+    config: BenchmarkConfig,
+    request_count: NonZeroUsize,
+    concurrency: NonZeroUsize,
+) -> Result<BenchResult, BenchmarkError> {
+    let reqrep_id = ReqRepId::generate();
+    let mut executor = global_executor();
+
+    let server_handle = start_server(reqrep_id, &config, executor.clone())?;
+    let result = if server_handle.ping() {
+        match start_client(reqrep_id, &config, executor.clone()) {
+            Ok(client) => {
+                let result = drive_load(
+                    client,
+                    config.message_size,
+                    request_count.get(),
+                    concurrency.get(),
+                    &mut executor,
+                );
+                client::unregister_client(reqrep_id);
+                result
+            }
+            Err(err) => Err(err),
+        }
+    } else {
+        Err(BenchmarkError::ServerNotResponding)
+    };
+    executor.run(server_handle.close());
+
+    let (elapsed, stats) = result?;
+    let result = BenchResult::new(reqrep_id, concurrency.get(), elapsed, stats);
+    debug!("{:?}", result);
+    Ok(result)
+}
+
+fn start_server(
+    reqrep_id: ReqRepId,
+    config: &BenchmarkConfig,
+    executor: Executor,
+) -> Result<ServerHandle, BenchmarkError> {
+    let reqrep = ReqRepConfig::new(reqrep_id, timer_buckets())
+        .start_service(EchoService, executor.clone())
+        .map_err(|err| BenchmarkError::ExecutorSpawnError {
+            is_executor_shutdown: err.is_shutdown(),
+        })?;
+    server::spawn(None, config.listener_config.clone(), reqrep, executor)
+        .map_err(BenchmarkError::ServerSpawnFailed)
+}
+
+fn start_client(
+    reqrep_id: ReqRepId,
+    config: &BenchmarkConfig,
+    executor: Executor,
+) -> Result<Client, BenchmarkError> {
+    client::register_client(
+        ReqRepConfig::new(reqrep_id, timer_buckets()),
+        None,
+        config.dialer_config.clone(),
+        executor,
+    )
+    .map_err(|err| BenchmarkError::ClientRegistrationFailed(err.to_string()))
+}
+
+/// Returns how long it took to complete all requests, along with the per task request stats
+fn drive_load(
+    client: Client,
+    message_size: usize,
+    request_count: usize,
+    concurrency: usize,
+    executor: &mut Executor,
+) -> Result<(Duration, Vec<RequestStats>), BenchmarkError> {
+    // fail fast if request messages cannot be created
+    new_message(message_size)?;
+
+    let start = Instant::now();
+    let mut handles = Vec::with_capacity(concurrency);
+    for i in 0..concurrency {
+        // the remainder is spread across the first tasks
+        let count = request_count / concurrency + (i < request_count % concurrency) as usize;
+        if count == 0 {
+            break;
+        }
+        let task = send_requests(client.clone(), message_size, count);
+        let handle = executor.spawn_with_handle(task).map_err(|err| {
+            BenchmarkError::ExecutorSpawnError {
+                is_executor_shutdown: err.is_shutdown(),
+            }
+        })?;
+        handles.push(handle);
+    }
+    let stats = executor.run(join_all(handles));
+    let elapsed = start.elapsed();
+    let stats = stats.into_iter().collect::<Result<Vec<_>, _>>()?;
+    Ok((elapsed, stats))
+}
+
+/// Sends the requests sequentially, recording the latency for each successful request
+async fn send_requests(
+    mut client: Client,
+    message_size: usize,
+    count: usize,
+) -> Result<RequestStats, BenchmarkError> {
+    let mut stats = RequestStats {
+        latencies: Vec::with_capacity(count),
+        failed_request_count: 0,
+    };
+    for _ in 0..count {
+        let req = new_message(message_size)?;
+        let start = Instant::now();
+        match await!(client.send_recv(req)) {
+            Ok(Ok(_)) => stats.latencies.push(start.elapsed()),
+            Ok(Err(err)) => {
+                debug!("benchmark request failed: {}", err);
+                stats.failed_request_count += 1;
+            }
+            Err(err) => {
+                debug!("benchmark request channel failed: {}", err);
+                stats.failed_request_count += 1;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+fn new_message(size: usize) -> Result<nng::Message, BenchmarkError> {
+    let mut msg =
+        nng::Message::with_capacity(size).map_err(BenchmarkError::MessageCreateFailure)?;
+    msg.push_back(&vec![0; size])
+        .map_err(BenchmarkError::MessageCreateFailure)?;
+    Ok(msg)
+}
+
+fn timer_buckets() -> Vec<f64> {
+    metrics::exponential_timer_buckets(
+        Duration::from_micros(10),
+        2.0,
+        NonZeroUsize::new(20).unwrap(),
+    )
+    .unwrap()
+}
+
+struct RequestStats {
+    latencies: Vec<Duration>,
+    failed_request_count: usize,
+}
+
+struct EchoService;
+
+impl Processor<nng::Message, nng::Message> for EchoService {
+    fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+        async move { req }.boxed()
+    }
+}
+
+/// Benchmark configuration
+#[derive(Debug, Clone)]
+pub struct BenchmarkConfig {
+    listener_config: ListenerConfig,
+    dialer_config: DialerConfig,
+    message_size: usize,
+}
+
+impl BenchmarkConfig {
+    /// default request message size in bytes
+    pub const DEFAULT_MESSAGE_SIZE: usize = 64;
+
+    /// constructor
+    /// - the server listens on the URL and the client dials the URL, using default settings
+    /// - message_size = [DEFAULT_MESSAGE_SIZE](struct.BenchmarkConfig.html#associatedconstant.DEFAULT_MESSAGE_SIZE)
+    pub fn new(url: url::Url) -> Self {
+        Self {
+            listener_config: ListenerConfig::new(url.clone()),
+            dialer_config: DialerConfig::new(url),
+            message_size: Self::DEFAULT_MESSAGE_SIZE,
+        }
+    }
+
+    /// Server listener config
+    pub fn listener_config(&self) -> &ListenerConfig {
+        &self.listener_config
+    }
+
+    /// Client dialer config
+    pub fn dialer_config(&self) -> &DialerConfig {
+        &self.dialer_config
+    }
+
+    /// Request message size in bytes - the server echoes the request back as the reply
+    pub fn message_size(&self) -> usize {
+        self.message_size
+    }
+
+    /// Sets the server listener config
+    pub fn set_listener_config(mut self, listener_config: ListenerConfig) -> Self {
+        self.listener_config = listener_config;
+        self
+    }
+
+    /// Sets the client dialer config
+    pub fn set_dialer_config(mut self, dialer_config: DialerConfig) -> Self {
+        self.dialer_config = dialer_config;
+        self
+    }
+
+    /// Sets the request message size in bytes
+    pub fn set_message_size(mut self, message_size: usize) -> Self {
+        self.message_size = message_size;
+        self
+    }
+}
+
+/// Benchmark results
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    reqrep_id: ReqRepId,
+    concurrency: usize,
+    request_count: usize,
+    failed_request_count: usize,
+    elapsed: Duration,
+    latency_percentiles: Vec<(f64, Duration)>,
+}
+
+impl BenchResult {
+    /// The latency percentiles that are reported
+    pub const PERCENTILES: [f64; 5] = [0.5, 0.9, 0.95, 0.99, 1.0];
+
+    fn new(
+        reqrep_id: ReqRepId,
+        concurrency: usize,
+        elapsed: Duration,
+        stats: Vec<RequestStats>,
+    ) -> Self {
+        let failed_request_count = stats.iter().map(|stats| stats.failed_request_count).sum();
+        let mut latencies: Vec<Duration> = stats
+            .into_iter()
+            .flat_map(|stats| stats.latencies.into_iter())
+            .collect();
+        latencies.sort();
+        let latency_percentiles = if latencies.is_empty() {
+            vec![]
+        } else {
+            Self::PERCENTILES
+                .iter()
+                .map(|p| {
+                    // nearest rank
+                    let rank = (p * latencies.len() as f64).ceil() as usize;
+                    (*p, latencies[rank.max(1).min(latencies.len()) - 1])
+                })
+                .collect()
+        };
+        Self {
+            reqrep_id,
+            concurrency,
+            request_count: latencies.len() + failed_request_count,
+            failed_request_count,
+            elapsed,
+            latency_percentiles,
+        }
+    }
+
+    /// The ReqRepId that was used by the benchmark server and client
+    pub fn reqrep_id(&self) -> ReqRepId {
+        self.reqrep_id
+    }
+
+    /// Number of tasks that concurrently sent requests
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Total number of requests that were sent
+    pub fn request_count(&self) -> usize {
+        self.request_count
+    }
+
+    /// Number of requests that failed
+    pub fn failed_request_count(&self) -> usize {
+        self.failed_request_count
+    }
+
+    /// How long it took to complete all requests
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Number of successful requests per second
+    pub fn throughput(&self) -> f64 {
+        let secs = metrics::duration_as_secs_f64(self.elapsed);
+        if secs == 0.0 {
+            return 0.0;
+        }
+        (self.request_count - self.failed_request_count) as f64 / secs
+    }
+
+    /// Successful request latency (percentile, latency) pairs for the
+    /// [PERCENTILES](struct.BenchResult.html#associatedconstant.PERCENTILES)
+    /// - percentiles are computed using the nearest rank method
+    /// - empty if no requests succeeded
+    pub fn latency_percentiles(&self) -> &[(f64, Duration)] {
+        &self.latency_percentiles
+    }
+
+    /// Returns the latency for the specified percentile
+    /// - returns None if the percentile is not one of the reported [PERCENTILES](struct.BenchResult.html#associatedconstant.PERCENTILES)
+    pub fn latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        self.latency_percentiles
+            .iter()
+            .find(|(p, _)| *p == percentile)
+            .map(|(_, latency)| *latency)
+    }
+}
+
+/// Benchmark errors
+#[derive(Debug, Fail)]
+pub enum BenchmarkError {
+    /// Failed to spawn the server
+    #[fail(display = "Failed to spawn the server: {}", _0)]
+    ServerSpawnFailed(#[cause] server::SpawnError),
+    /// The server did not respond to a ping after it was spawned
+    #[fail(display = "The server did not respond to a ping after it was spawned")]
+    ServerNotResponding,
+    /// Failed to register the client
+    #[fail(display = "Failed to register the client: {}", _0)]
+    ClientRegistrationFailed(String),
+    /// Failed to create a request message
+    #[fail(display = "Failed to create a request message: {}", _0)]
+    MessageCreateFailure(#[cause] nng::Error),
+    /// An error that occurred during spawning.
+    #[fail(
+        display = "Spawning Future failed: executor shutdown = {}",
+        is_executor_shutdown
+    )]
+    ExecutorSpawnError {
+        /// whether spawning failed because the executor is shut down
+        is_executor_shutdown: bool,
+    },
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use oysterpack_uid::ULID;
+
+    #[test]
+    fn inproc_benchmark() {
+        configure_logging();
+
+        // GIVEN: an inproc benchmark config
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let config = BenchmarkConfig::new(url).set_message_size(128);
+
+        // WHEN: a tiny benchmark is run
+        let result = run(
+            config,
+            NonZeroUsize::new(100).unwrap(),
+            NonZeroUsize::new(4).unwrap(),
+        )
+        .unwrap();
+        info!("{:?}", result);
+
+        // THEN: all requests succeeded
+        assert_eq!(result.request_count(), 100);
+        assert_eq!(result.failed_request_count(), 0);
+        assert_eq!(result.concurrency(), 4);
+        // AND: the throughput is positive
+        assert!(result.throughput() > 0.0);
+        // AND: the latency percentiles are populated
+        assert_eq!(
+            result.latency_percentiles().len(),
+            BenchResult::PERCENTILES.len()
+        );
+        let latencies: Vec<Duration> = result
+            .latency_percentiles()
+            .iter()
+            .map(|(_, latency)| *latency)
+            .collect();
+        assert!(latencies.iter().all(|latency| *latency > Duration::from_nanos(0)));
+        assert!(latencies.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(result.latency_percentile(0.5).is_some());
+        assert!(result.latency_percentile(0.42).is_none());
+
+        // AND: the server and client were cleaned up
+        assert!(ServerHandle::get_by_reqrep_id(result.reqrep_id()).is_empty());
+        assert!(client::client(result.reqrep_id()).is_none());
+    }
+}
@@ -17,13 +17,128 @@
 //! common nng configuration, i.e., common to all nng messaging protocols
 
 use failure::Fail;
+use lazy_static::lazy_static;
 use nng::options::Options;
+use oysterpack_log::*;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use std::{
+    fs,
     num::{NonZeroU16, NonZeroUsize},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
     time::Duration,
 };
 
+/// Default max number of Aio contexts that can be created per socket.
+///
+/// Each Aio context consumes file descriptors. Thus, the default is based on the typical default
+/// soft limit for open file descriptors per process.
+pub const DEFAULT_MAX_AIO_CONTEXTS: usize = 1024;
+
+static MAX_AIO_CONTEXTS: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_AIO_CONTEXTS);
+
+/// Returns the max number of Aio contexts that can be created per socket
+/// - this is used to validate the parallelism configured for servers and clients
+/// - default = [DEFAULT_MAX_AIO_CONTEXTS](constant.DEFAULT_MAX_AIO_CONTEXTS.html)
+pub fn max_aio_contexts() -> usize {
+    MAX_AIO_CONTEXTS.load(Ordering::Relaxed)
+}
+
+/// Sets the max number of Aio contexts that can be created per socket.
+/// - this should be set based on the system's open file descriptor limit
+pub fn set_max_aio_contexts(max: NonZeroUsize) {
+    MAX_AIO_CONTEXTS.store(max.get(), Ordering::Relaxed);
+}
+
+/// Returns the CPU quota that the process is limited to as a number of CPUs, e.g., 1.5, or None if
+/// the process is not limited
+pub type CpuQuotaSource = fn() -> Option<f64>;
+
+lazy_static! {
+    static ref CPU_QUOTA_SOURCE: RwLock<CpuQuotaSource> = RwLock::new(cgroup_cpu_quota);
+}
+
+/// Returns the number of CPUs that the process can effectively use, which is used as the default
+/// parallelism for [ListenerConfig](../reqrep/server/struct.ListenerConfig.html) and
+/// [DialerConfig](../reqrep/client/struct.DialerConfig.html).
+/// - in containers, the CPU quota can be much lower than the number of host CPUs. Sizing the
+///   parallelism on the host CPUs over-provisions, and the extra work is throttled by the quota.
+/// - the CPU quota is rounded up, and is capped by the number of available CPUs
+/// - if no CPU quota is detected, then the number of available CPUs is returned
+/// - the CPU quota is read from the cgroup by default - see [set_cpu_quota_source()](fn.set_cpu_quota_source.html)
+pub fn effective_parallelism() -> usize {
+    let cpu_quota = *CPU_QUOTA_SOURCE.read();
+    effective_parallelism_with(cpu_quota)
+}
+
+/// Computes the effective parallelism using the specified CPU quota source - see [effective_parallelism()](fn.effective_parallelism.html)
+pub fn effective_parallelism_with(cpu_quota: CpuQuotaSource) -> usize {
+    let cpus = num_cpus::get();
+    match cpu_quota() {
+        Some(quota) if quota > 0.0 => cpus.min(quota.ceil() as usize).max(1),
+        _ => cpus,
+    }
+}
+
+/// Sets the source that is used to detect the process CPU quota.
+/// - default = [cgroup_cpu_quota()](fn.cgroup_cpu_quota.html)
+pub fn set_cpu_quota_source(cpu_quota: CpuQuotaSource) {
+    *CPU_QUOTA_SOURCE.write() = cpu_quota;
+}
+
+/// Reads the CPU quota from the cgroup CPU controller
+/// - cgroup v2: `/sys/fs/cgroup/cpu.max`
+/// - cgroup v1: `/sys/fs/cgroup/cpu/cpu.cfs_quota_us` and `/sys/fs/cgroup/cpu/cpu.cfs_period_us`
+///
+/// Returns None if the CPU quota is not set, or the cgroup files cannot be read, e.g., on non-Linux
+/// platforms.
+pub fn cgroup_cpu_quota() -> Option<f64> {
+    if let Ok(cpu_max) = fs::read_to_string("/sys/fs/cgroup/cpu.max") {
+        return parse_cgroup_v2_cpu_max(&cpu_max);
+    }
+    let quota = fs::read_to_string("/sys/fs/cgroup/cpu/cpu.cfs_quota_us").ok()?;
+    let period = fs::read_to_string("/sys/fs/cgroup/cpu/cpu.cfs_period_us").ok()?;
+    parse_cgroup_v1_cpu_quota(&quota, &period)
+}
+
+/// parses the `cpu.max` contents, which has the format `$MAX $PERIOD`, where $MAX is "max" when
+/// the quota is not set
+fn parse_cgroup_v2_cpu_max(cpu_max: &str) -> Option<f64> {
+    let mut fields = cpu_max.split_whitespace();
+    let quota = fields.next()?.parse::<f64>().ok()?;
+    let period = fields.next()?.parse::<f64>().ok()?;
+    cpu_quota(quota, period)
+}
+
+/// a quota of -1 means the quota is not set
+fn parse_cgroup_v1_cpu_quota(quota: &str, period: &str) -> Option<f64> {
+    let quota = quota.trim().parse::<f64>().ok()?;
+    let period = period.trim().parse::<f64>().ok()?;
+    cpu_quota(quota, period)
+}
+
+fn cpu_quota(quota: f64, period: f64) -> Option<f64> {
+    if quota > 0.0 && period > 0.0 {
+        Some(quota / period)
+    } else {
+        None
+    }
+}
+
+/// Current schema version of persisted
+/// [ListenerConfig](../reqrep/server/struct.ListenerConfig.html) and
+/// [DialerConfig](../reqrep/client/struct.DialerConfig.html) settings.
+/// - binary formats that are not self-describing, e.g., bincode, must persist the configs via
+///   [VersionedListenerConfig](../reqrep/server/enum.VersionedListenerConfig.html) and
+///   [VersionedDialerConfig](../reqrep/client/enum.VersionedDialerConfig.html), which tag the
+///   schema version
+/// - version 0 is the schema that was persisted before versioning was introduced
+pub const CONFIG_VERSION: u32 = 1;
+
 /// Socket config
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Default)]
 pub struct SocketConfig {
@@ -36,6 +151,9 @@ pub struct SocketConfig {
     socket_name: Option<String>,
     tcp_no_delay: Option<bool>,
     tcp_keep_alive: Option<bool>,
+    aio_send_timeout: Option<Duration>,
+    aio_recv_timeout: Option<Duration>,
+    linger: Option<Duration>,
 }
 
 impl SocketConfig {
@@ -173,10 +291,19 @@ impl SocketConfig {
         self.recv_buffer_size.map(NonZeroU16::get)
     }
 
+    /// maximum allowed setting for receive buffer size
+    pub const MAX_RECV_BUFFER_SIZE: u16 = 8192;
+
     /// configures the depth of the socket's receive buffer as a number of messages.
+    /// - if the size is greater than 8192, then it will be set to 8192
     pub fn set_recv_buffer_size(self, size: NonZeroU16) -> SocketConfig {
         let mut this = self;
-        this.recv_buffer_size = Some(size);
+        if size.get() > SocketConfig::MAX_RECV_BUFFER_SIZE {
+            this.recv_buffer_size =
+                Some(NonZeroU16::new(SocketConfig::MAX_RECV_BUFFER_SIZE).unwrap());
+        } else {
+            this.recv_buffer_size = Some(size);
+        }
         this
     }
 
@@ -232,6 +359,37 @@ impl SocketConfig {
         this
     }
 
+    /// Default timeout that is applied to each Aio send operation.
+    ///
+    /// This is a safety net that prevents a single stuck operation from pinning an Aio Context
+    /// indefinitely. Per request deadlines take precedence on the client side.
+    pub fn aio_send_timeout(&self) -> Option<Duration> {
+        self.aio_send_timeout
+    }
+
+    /// configures the default Aio send timeout
+    pub fn set_aio_send_timeout(self, timeout: Duration) -> SocketConfig {
+        let mut this = self;
+        this.aio_send_timeout = Some(timeout);
+        this
+    }
+
+    /// Default timeout that is applied to each Aio recv operation.
+    ///
+    /// On the client, this bounds how long to wait for the reply. On the server, a recv that times
+    /// out simply means no request arrived within the timeout, and the Aio Context goes back to
+    /// receiving. Per request deadlines take precedence on the client side.
+    pub fn aio_recv_timeout(&self) -> Option<Duration> {
+        self.aio_recv_timeout
+    }
+
+    /// configures the default Aio recv timeout
+    pub fn set_aio_recv_timeout(self, timeout: Duration) -> SocketConfig {
+        let mut this = self;
+        this.aio_recv_timeout = Some(timeout);
+        this
+    }
+
     /// The maximum number of "hops" a message may traverse.
     ///
     /// The intention here is to prevent forwarding loops in device chains. Note that not all protocols
@@ -257,6 +415,100 @@ impl SocketConfig {
         this.max_ttl = Some(ttl);
         this
     }
+
+    /// The max amount of time to wait for the socket to close on shutdown.
+    ///
+    /// Closing a socket may block while nng tries to flush pending data to a peer. If the socket
+    /// does not close within the linger period, then the close is left to complete in the
+    /// background on a shared socket closer thread and shutdown proceeds, i.e., a stuck peer cannot
+    /// hang shutdown. If not set, then
+    /// the close is waited on without a bound.
+    ///
+    /// A zero linger means the close is never waited on.
+    ///
+    /// NOTE: nng does not flush the socket send buffers when the socket is closed, i.e., messages
+    /// that are still queued on the socket, e.g., replies that have not yet been delivered to the
+    /// peer, are dropped. The linger only bounds how long shutdown waits for the close itself.
+    pub fn linger(&self) -> Option<Duration> {
+        self.linger
+    }
+
+    /// configures the socket linger
+    pub fn set_linger(self, linger: Duration) -> SocketConfig {
+        let mut this = self;
+        this.linger = Some(linger);
+        this
+    }
+}
+
+/// Number of threads that are shared to close sockets in the background
+const SOCKET_CLOSER_THREADS: usize = 4;
+/// Max number of socket closes that can be queued for the socket closer threads
+const SOCKET_CLOSER_QUEUE_SIZE: usize = 256;
+
+/// socket close request, which is notified when the socket is closed
+type SocketClose = (nng::Socket, mpsc::Sender<()>);
+
+lazy_static! {
+    /// Sockets that are closed with a linger are closed on a fixed pool of shared threads, i.e.,
+    /// sockets that are stuck closing do not leak a thread each.
+    static ref SOCKET_CLOSER: Mutex<mpsc::SyncSender<SocketClose>> = {
+        let (tx, rx) = mpsc::sync_channel::<SocketClose>(SOCKET_CLOSER_QUEUE_SIZE);
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..SOCKET_CLOSER_THREADS {
+            let rx = rx.clone();
+            thread::Builder::new()
+                .name(format!("oysterpack-nng-socket-closer-{}", i))
+                .spawn(move || loop {
+                    let close = rx.lock().recv();
+                    match close {
+                        Ok((socket, closed_tx)) => {
+                            socket.close();
+                            let _ = closed_tx.send(());
+                        }
+                        Err(_) => return,
+                    }
+                })
+                .expect("failed to spawn socket closer thread");
+        }
+        Mutex::new(tx)
+    };
+}
+
+/// Closes the socket, waiting at most for the linger period for the close to complete
+/// - see [SocketConfig::linger()](struct.SocketConfig.html#method.linger)
+/// - returns false if the linger period elapsed before the socket was closed
+/// - closes that are bounded by a linger are run on a fixed pool of shared socket closer threads.
+///   If the closer queue is full, i.e., the closer threads are all stuck, then the socket is closed
+///   on the calling thread.
+pub(crate) fn close_socket(socket: nng::Socket, linger: Option<Duration>) -> bool {
+    let linger = match linger {
+        None => {
+            socket.close();
+            return true;
+        }
+        Some(linger) => linger,
+    };
+    let (closed_tx, closed_rx) = mpsc::channel::<()>();
+    let queued = SOCKET_CLOSER.lock().try_send((socket, closed_tx));
+    if let Err(err) = queued {
+        let (socket, _) = match err {
+            mpsc::TrySendError::Full(close) | mpsc::TrySendError::Disconnected(close) => close,
+        };
+        warn!("Socket closer queue is full - the socket is being closed on the calling thread");
+        socket.close();
+        return true;
+    }
+    match closed_rx.recv_timeout(linger) {
+        Ok(_) => true,
+        Err(_) => {
+            warn!(
+                "Socket was not closed within the linger period ({:?}) - the close will complete in the background",
+                linger
+            );
+            false
+        }
+    }
 }
 
 /// Socket config related errors
@@ -302,3 +554,137 @@ pub enum SocketConfigError {
     #[fail(display = "Failed to set the ResendTime Socket option: {}", _0)]
     ResendTime(#[cause] nng::Error),
 }
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use std::time::Instant;
+
+    #[test]
+    fn socket_config_buffer_sizes() {
+        configure_logging();
+
+        // GIVEN: a SocketConfig with recv and send buffer sizes
+        let config = SocketConfig::default()
+            .set_recv_buffer_size(NonZeroU16::new(64).unwrap())
+            .set_send_buffer_size(NonZeroU16::new(128).unwrap());
+        // WHEN: the config is applied to a socket
+        let socket = config
+            .apply(nng::Socket::new(nng::Protocol::Rep0).unwrap())
+            .unwrap();
+        // THEN: the options are set on the socket
+        assert_eq!(socket.get_opt::<nng::options::RecvBufferSize>().unwrap(), 64);
+        assert_eq!(socket.get_opt::<nng::options::SendBufferSize>().unwrap(), 128);
+
+        // WHEN: the buffer sizes exceed the max
+        let config = SocketConfig::default()
+            .set_recv_buffer_size(NonZeroU16::new(u16::max_value()).unwrap())
+            .set_send_buffer_size(NonZeroU16::new(u16::max_value()).unwrap());
+        // THEN: they are capped
+        assert_eq!(
+            config.recv_buffer_size(),
+            Some(SocketConfig::MAX_RECV_BUFFER_SIZE)
+        );
+        assert_eq!(
+            config.send_buffer_size(),
+            Some(SocketConfig::MAX_SEND_BUFFER_SIZE)
+        );
+        let socket = config
+            .apply(nng::Socket::new(nng::Protocol::Rep0).unwrap())
+            .unwrap();
+        assert_eq!(
+            socket.get_opt::<nng::options::RecvBufferSize>().unwrap(),
+            i32::from(SocketConfig::MAX_RECV_BUFFER_SIZE)
+        );
+        assert_eq!(
+            socket.get_opt::<nng::options::SendBufferSize>().unwrap(),
+            i32::from(SocketConfig::MAX_SEND_BUFFER_SIZE)
+        );
+    }
+
+    #[test]
+    fn socket_close_linger() {
+        configure_logging();
+
+        let url = format!("inproc://{}", oysterpack_uid::ULID::generate());
+        // GIVEN: a peer that does not drain its messages while the socket is being closed
+        let peer = nng::Socket::new(nng::Protocol::Pull0).unwrap();
+        peer.set_opt::<nng::options::RecvBufferSize>(1).unwrap();
+        peer.set_opt::<nng::options::RecvTimeout>(Some(Duration::from_millis(50))).unwrap();
+        peer.listen(url.as_str()).unwrap();
+        // AND: a socket with pending messages queued for the peer
+        const LINGER: Duration = Duration::from_millis(100);
+        let config = SocketConfig::default()
+            .set_send_buffer_size(NonZeroU16::new(8).unwrap())
+            .set_send_timeout(Duration::from_millis(10))
+            .set_linger(LINGER);
+        assert_eq!(config.linger(), Some(LINGER));
+        let mut socket = config
+            .apply(nng::Socket::new(nng::Protocol::Push0).unwrap())
+            .unwrap();
+        socket.dial(url.as_str()).unwrap();
+        let mut sent = 0;
+        while socket.send(nng::Message::new().unwrap()).is_ok() {
+            sent += 1;
+        }
+        // the peer buffer is full, i.e., messages are pending in the socket send buffer
+        assert!(sent > 1);
+
+        // WHEN: the socket is closed
+        let start = Instant::now();
+        let closed = close_socket(socket, config.linger());
+        // THEN: the close completes within the linger bound
+        let elapsed = start.elapsed();
+        assert!(closed);
+        assert!(elapsed < LINGER + Duration::from_millis(50), "{:?}", elapsed);
+        // AND: the messages that were delivered to the peer before the close are received
+        let mut received = 0;
+        while peer.recv().is_ok() {
+            received += 1;
+        }
+        assert!(received >= 1);
+        // AND: the messages that were still pending on the closed socket are dropped
+        assert!(received < sent, "received = {}, sent = {}", received, sent);
+
+        // GIVEN: a zero linger
+        let socket = nng::Socket::new(nng::Protocol::Push0).unwrap();
+        socket.dial(url.as_str()).unwrap();
+        // WHEN: the socket is closed
+        let start = Instant::now();
+        close_socket(socket, Some(Duration::from_millis(0)));
+        // THEN: the close is not waited on
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn effective_parallelism_honors_cpu_quota() {
+        configure_logging();
+
+        // the CPU quota source is global, and thus is not changed by tests, i.e., the quota is
+        // injected via effective_parallelism_with()
+
+        // GIVEN: a simulated 2 CPU quota
+        // THEN: the effective parallelism is based on the quota, and not on the host CPU count
+        assert_eq!(effective_parallelism_with(|| Some(2.0)), num_cpus::get().min(2));
+        // AND: the default listener and dialer parallelism is based on the effective parallelism
+        let url = url::Url::parse("inproc://effective_parallelism").unwrap();
+        let listener_config = crate::reqrep::server::ListenerConfig::new(url.clone());
+        let dialer_config = crate::reqrep::client::DialerConfig::new(url);
+        assert_eq!(listener_config.parallelism(), effective_parallelism() + 1);
+        assert_eq!(dialer_config.parallelism(), effective_parallelism());
+
+        // a fractional quota is rounded up
+        assert_eq!(effective_parallelism_with(|| Some(0.5)), 1);
+        // when no quota is detected, then the number of available CPUs is used
+        assert_eq!(effective_parallelism_with(|| None), num_cpus::get());
+
+        // cgroup v2
+        assert_eq!(parse_cgroup_v2_cpu_max("200000 100000\n"), Some(2.0));
+        assert_eq!(parse_cgroup_v2_cpu_max("max 100000\n"), None);
+        // cgroup v1
+        assert_eq!(parse_cgroup_v1_cpu_quota("150000\n", "100000\n"), Some(1.5));
+        assert_eq!(parse_cgroup_v1_cpu_quota("-1\n", "100000\n"), None);
+    }
+}
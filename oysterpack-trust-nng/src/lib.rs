@@ -32,8 +32,10 @@
 #[macro_use]
 extern crate pretty_assertions;
 
+pub mod benchmark;
 pub mod config;
 pub mod reqrep;
+pub mod util;
 
 #[cfg(test)]
 fn log_config() -> oysterpack_log::LogConfig {
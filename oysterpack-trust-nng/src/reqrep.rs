@@ -16,6 +16,231 @@
 
 //! Provides support for the request/reply messaging protocol.
 //! - the service client interface is defined by [Client](client/type.Client.html)
+//! - typed services are plugged into the server via [typed::SealedEnvelopeProcessor](typed/struct.SealedEnvelopeProcessor.html)
+//! - per request stage timings are tracked via [context::RequestContext](context/struct.RequestContext.html)
+//! - a sample of requests can be logged via [request_log::RequestLogger](request_log/struct.RequestLogger.html)
+//! - concurrent identical client requests are deduplicated via [coalesce::Coalescer](coalesce/struct.Coalescer.html)
+//! - multiple client requests can be kept in flight via [pipeline::Pipeline](pipeline/struct.Pipeline.html)
+//! - application code can be decoupled from how the service is reached via [transport::Transport](transport/trait.Transport.html)
+//! - a set of servers and clients can be deployed from config via [topology::Topology](topology/struct.Topology.html)
+//! - inbound messages can be persisted before they are processed, and replayed on restart, via [journal::Journal](journal/struct.Journal.html)
+//!
+//! ## Registry Size Guard
+//! Servers and clients are tracked in global registries. If callers forget to stop servers or
+//! unregister clients, then the registries will grow without bound. To make such leaks visible, a
+//! soft cap is applied to each registry - see [set_registry_soft_cap()](fn.set_registry_soft_cap.html).
+//! - when a registry size exceeds the soft cap, a warning is logged and the
+//!   [REGISTRY_SOFT_CAP_EXCEEDED_COUNT_METRIC_ID](constant.REGISTRY_SOFT_CAP_EXCEEDED_COUNT_METRIC_ID.html)
+//!   counter is incremented
+//! - registration does not fail when the soft cap is exceeded
+//! - [registry_stats()](fn.registry_stats.html) returns the current registry sizes
+
+use lazy_static::lazy_static;
+use oysterpack_log::*;
+use oysterpack_trust::metrics;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 pub mod client;
+pub mod coalesce;
+pub mod context;
+pub mod journal;
+pub mod pipeline;
+pub mod request_log;
 pub mod server;
+pub mod topology;
+pub mod transport;
+pub mod typed;
+
+lazy_static! {
+    static ref REGISTRY_SOFT_CAP_EXCEEDED_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        REGISTRY_SOFT_CAP_EXCEEDED_COUNT_METRIC_ID,
+        "Number of times a registration was made while the registry size exceeded the soft cap",
+        &[REGISTRY_LABEL_ID],
+        None
+    ).unwrap();
+}
+
+/// Default registry soft cap
+pub const DEFAULT_REGISTRY_SOFT_CAP: usize = 1024;
+
+static REGISTRY_SOFT_CAP: AtomicUsize = AtomicUsize::new(DEFAULT_REGISTRY_SOFT_CAP);
+
+/// IntCounterVec MetricId which is used to track the number of registrations that were made while
+/// the registry exceeded its soft cap: `M01D86X7G072R1KFZBJCMHRRJD1`
+pub const REGISTRY_SOFT_CAP_EXCEEDED_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1879891910686433900891700795829668257);
+
+/// Metric LabelId which is used to store the [Registry](enum.Registry.html) name: `L01D86Y7PJEK85FJ90EGWMNKGAN`
+pub const REGISTRY_LABEL_ID: metrics::LabelId =
+    metrics::LabelId(1879893186470101613089643428291658069);
+
+/// Returns the soft cap that is applied to each of the global registries
+/// - default = [DEFAULT_REGISTRY_SOFT_CAP](constant.DEFAULT_REGISTRY_SOFT_CAP.html)
+pub fn registry_soft_cap() -> usize {
+    REGISTRY_SOFT_CAP.load(Ordering::Relaxed)
+}
+
+/// Sets the soft cap that is applied to each of the global registries
+pub fn set_registry_soft_cap(cap: usize) {
+    REGISTRY_SOFT_CAP.store(cap, Ordering::Relaxed);
+}
+
+/// Returns the number of times a registration was made while the registry size exceeded the soft cap
+pub fn registry_soft_cap_exceeded_count(registry: Registry) -> u64 {
+    REGISTRY_SOFT_CAP_EXCEEDED_COUNT
+        .with_label_values(&[registry.name()])
+        .get() as u64
+}
+
+/// Returns the current global registry sizes
+pub fn registry_stats() -> RegistryStats {
+    RegistryStats {
+        server_handle_count: server::server_handle_count(),
+        client_count: client::client_count(),
+        client_context_count: client::client_context_count(),
+    }
+}
+
+/// Checks the registry size against the soft cap.
+/// - if the soft cap is exceeded, then a warning is logged and the metric counter is incremented
+pub(crate) fn check_registry_size(registry: Registry, size: usize) {
+    let cap = registry_soft_cap();
+    if size > cap {
+        REGISTRY_SOFT_CAP_EXCEEDED_COUNT
+            .with_label_values(&[registry.name()])
+            .inc();
+        warn!(
+            "{} registry size ({}) exceeds the soft cap ({}) - check for servers that were not stopped or clients that were not unregistered",
+            registry, size, cap
+        );
+    }
+}
+
+/// Global registries
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Registry {
+    /// [ServerHandle](server/struct.ServerHandle.html) registry
+    ServerHandles,
+    /// [Client](client/type.Client.html) registry
+    Clients,
+    /// nng client context registry, i.e., the backend resources for the registered clients
+    ClientContexts,
+}
+
+impl Registry {
+    /// Registry name, which is used as the metric label value
+    pub fn name(self) -> &'static str {
+        match self {
+            Registry::ServerHandles => "server_handles",
+            Registry::Clients => "clients",
+            Registry::ClientContexts => "client_contexts",
+        }
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Global registry sizes
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RegistryStats {
+    server_handle_count: usize,
+    client_count: usize,
+    client_context_count: usize,
+}
+
+impl RegistryStats {
+    /// Number of registered [ServerHandle(s)](server/struct.ServerHandle.html)
+    pub fn server_handle_count(&self) -> usize {
+        self.server_handle_count
+    }
+
+    /// Number of registered [Client(s)](client/type.Client.html)
+    pub fn client_count(&self) -> usize {
+        self.client_count
+    }
+
+    /// Number of registered nng client contexts
+    pub fn client_context_count(&self) -> usize {
+        self.client_context_count
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use oysterpack_trust::concurrent::{
+        execution::global_executor,
+        messaging::reqrep::{ReqRepConfig, ReqRepId},
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn registry_soft_cap_exceeded() {
+        configure_logging();
+
+        // the soft cap is global, and thus is not changed by tests, i.e., registry sizes are
+        // checked against the default soft cap
+        let soft_cap = registry_soft_cap();
+        assert_eq!(soft_cap, DEFAULT_REGISTRY_SOFT_CAP);
+        let clients_warning_count = registry_soft_cap_exceeded_count(Registry::Clients);
+        let client_contexts_warning_count =
+            registry_soft_cap_exceeded_count(Registry::ClientContexts);
+
+        // WHEN: the registry sizes are at the soft cap
+        check_registry_size(Registry::Clients, soft_cap);
+        check_registry_size(Registry::ClientContexts, soft_cap);
+        // THEN: the warning counters are not incremented
+        assert_eq!(
+            registry_soft_cap_exceeded_count(Registry::Clients),
+            clients_warning_count
+        );
+        assert_eq!(
+            registry_soft_cap_exceeded_count(Registry::ClientContexts),
+            client_contexts_warning_count
+        );
+
+        // WHEN: the registry sizes exceed the soft cap
+        check_registry_size(Registry::Clients, soft_cap + 1);
+        check_registry_size(Registry::ClientContexts, soft_cap + 1);
+        // THEN: the warning counters are incremented
+        assert_eq!(
+            registry_soft_cap_exceeded_count(Registry::Clients),
+            clients_warning_count + 1
+        );
+        assert_eq!(
+            registry_soft_cap_exceeded_count(Registry::ClientContexts),
+            client_contexts_warning_count + 1
+        );
+
+        // WHEN: a client is registered
+        let reqrep_id = ReqRepId::generate();
+        let url = url::Url::parse(&format!("inproc://{}", reqrep_id)).unwrap();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_nanos(50), Duration::from_nanos(100)])
+                .unwrap();
+        let result = client::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets),
+            None,
+            client::DialerConfig::new(url),
+            global_executor(),
+        );
+        assert!(result.is_ok());
+        // THEN: the registry stats reflect the registered client
+        let stats = registry_stats();
+        info!("{:?}", stats);
+        assert!(stats.client_count() >= 1);
+        assert!(stats.client_context_count() >= 1);
+
+        assert!(client::unregister_client(reqrep_id).is_some());
+    }
+}
@@ -16,15 +16,33 @@
 
 //! Provides an ReqRep [Client](type.Client.html) application interface for nng clients.
 //! - [register_client](fn.register_client.html) is used to register clients in a global registry
+//! - [get_or_register_client](fn.get_or_register_client.html) is the idempotent version, which reports
+//!   whether the client was newly created
 //! - [client](fn.client.html) is used to lookup Clients by ReqRepId
+//! - [drain](fn.drain.html) is used to wait for in-flight requests to complete before shutting down
+//!   the client
+//! - [warm_up](fn.warm_up.html) is used to establish the client connection, and optionally round-trip
+//!   a ping request, before the first real request is sent
+//! - [connection_events](fn.connection_events.html) is used to be notified when the client connection
+//!   drops and is re-established, e.g., to renegotiate session state
+//! - [set_socket_overrides](fn.set_socket_overrides.html) is used to tune the socket resend and
+//!   reconnect settings per ReqRepId, which are applied when the client is registered
+//! - [client_balanced](fn.client_balanced.html) is used to pick a healthy Client among the clients
+//!   that are registered for the same logical service, e.g., clients that are connected to
+//!   different backends - see [add_balanced_client](fn.add_balanced_client.html)
 //!
 //! - The client is fully async and supports parallelism. The level of parallelism is configured via
 //!   [DialerConfig::parallelism()](struct.DialerConfig.html#method.parallelism).
 //! - When all [Client(s)](type.Client.html) are unregistered and all references fall out of scope, then
 //!   the backend ReqRep service will stop which will:
+//!   - stop accepting new requests, and wait up to the [destroy grace period](struct.DialerConfig.html#method.destroy_grace_period)
+//!     for in-flight requests to complete - the wait is async, i.e., it does not block the
+//!     Executor thread
 //!   - unregister its context
 //!   - close the nng::Dialer and nng:Socket resources
 //!   - close the Aio event loop channels, which will trigger the Aio event loop tasks to exit
+//!   - requests that are aborted because the client is shutting down fail with
+//!     [RequestError::ClientShuttingDown](enum.RequestError.html#variant.ClientShuttingDown)
 //!
 //! ## Design
 //! The server is designed internally to be async and non-blocking leveraging nng's async capabilities.
@@ -39,26 +57,44 @@
 //! tasks. If all Aio context tasks are busy, then requests will wait asynchronously in a non-blocking
 //! manner for an Aio context task.
 
+use super::server;
 use crate::config::{self, SocketConfigError};
 use failure::Fail;
 use futures::{
     channel::{mpsc, oneshot},
-    future::FutureExt,
+    future::{self, FutureExt},
     sink::SinkExt,
-    stream::StreamExt,
+    stream::{Stream, StreamExt},
     task::SpawnExt,
 };
 use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use nng::options::Options;
 use oysterpack_log::*;
-use oysterpack_trust::concurrent::{
-    execution::Executor,
-    messaging::reqrep::{self, ReqRep, ReqRepId},
+use oysterpack_trust::{
+    concurrent::{
+        execution::{self, Executor},
+        messaging::{
+            errors::ChannelError,
+            reqrep::{self, ReqRep, ReqRepId},
+        },
+        timer,
+    },
+    metrics,
 };
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::{fmt, num::NonZeroUsize, panic::AssertUnwindSafe, sync::Arc, time::Duration};
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 lazy_static! {
      /// Global Client contexts
@@ -66,13 +102,52 @@ lazy_static! {
 
     /// Global ReqRep nng client registry
     static ref CLIENTS: RwLock<HashMap<ReqRepId, Client>> = RwLock::new(HashMap::new());
+
+    /// Per ReqRepId socket overrides that are applied when the client is registered
+    static ref SOCKET_OVERRIDES: RwLock<HashMap<ReqRepId, SocketOverrides>> = RwLock::new(HashMap::new());
+
+    /// logical service id -> member client ReqRepId(s) that the balancer selects from
+    static ref BALANCED_CLIENTS: RwLock<HashMap<ReqRepId, Vec<ReqRepId>>> = RwLock::new(HashMap::new());
+
+    /// the metric is incremented each time an Aio Context is recreated after consecutive request failures
+    static ref CONTEXT_RECREATE_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        CONTEXT_RECREATE_COUNT_METRIC_ID,
+        "Number of times a client Aio Context was recreated after consecutive request failures",
+        &[server::REQREP_LABEL_ID],
+        None
+    ).unwrap();
+}
+
+/// IntCounterVec MetricId which is used to track the number of times a client Aio Context was recreated
+/// by ReqRepId: `M01D882AXGHAFJNN8S8B2WK1570`
+pub const CONTEXT_RECREATE_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1879938949325458141141059900005192928);
+
+/// Returns the number of times the client's Aio Contexts have been recreated after consecutive request failures
+pub fn context_recreate_count(reqrep_id: ReqRepId) -> u64 {
+    CONTEXT_RECREATE_COUNT
+        .with_label_values(&[reqrep_id.to_string().as_str()])
+        .get() as u64
 }
 
+/// The number of most recent request outcomes that the client success rate is computed over
+pub const HEALTH_WINDOW_SIZE: usize = 100;
+
+/// The min number of recent request outcomes required before the success rate is used to judge
+/// the client's health
+pub const HEALTH_MIN_SAMPLES: usize = 10;
+
+/// Clients whose recent success rate is below the min success rate are considered unhealthy
+pub const HEALTH_MIN_SUCCESS_RATE: f64 = 0.5;
+
 /// Client type alias
 pub type Client = ReqRep<nng::Message, Result<nng::Message, RequestError>>;
 
 /// The client's ReqRepId is used as the registry key. Thus, if a Client is already registered with
 /// the same ReqRepId, then a [ClientRegistrationError::ClientAlreadyRegistered] error is returned.
+///
+/// If [SocketOverrides](struct.SocketOverrides.html) are registered for the ReqRepId, then they
+/// take precedence over the specified SocketConfig settings.
 pub fn register_client(
     reqrep_service_config: reqrep::ReqRepConfig,
     socket_config: Option<SocketConfig>,
@@ -85,6 +160,10 @@ pub fn register_client(
             reqrep_service_config.reqrep_id(),
         ));
     }
+    let socket_config = match socket_overrides(reqrep_service_config.reqrep_id()) {
+        Some(overrides) => Some(socket_config.unwrap_or_default().apply_overrides(overrides)),
+        None => socket_config,
+    };
     let nng_client = NngClient::new(
         reqrep_service_config.reqrep_id(),
         socket_config,
@@ -100,9 +179,195 @@ pub fn register_client(
             ))
         })?;
     let _ = clients.insert(reqrep.id(), reqrep.clone());
+    super::check_registry_size(super::Registry::Clients, clients.len());
     Ok(reqrep)
 }
 
+/// Idempotent version of [register_client](fn.register_client.html).
+///
+/// If a Client is already registered for the ReqRepId, then the registered Client is returned along
+/// with [Registration::Existing](enum.Registration.html). In that case, the specified configs and
+/// Executor are not used - the caller can use the returned Registration to decide whether to clean
+/// up resources that it allocated for the client, e.g., an Executor.
+pub fn get_or_register_client(
+    reqrep_service_config: reqrep::ReqRepConfig,
+    socket_config: Option<SocketConfig>,
+    dialer_config: DialerConfig,
+    executor: Executor,
+) -> Result<(Client, Registration), ClientRegistrationError> {
+    if let Some(client) = client(reqrep_service_config.reqrep_id()) {
+        return Ok((client, Registration::Existing));
+    }
+    match register_client(reqrep_service_config, socket_config, dialer_config, executor) {
+        Ok(client) => Ok((client, Registration::Created)),
+        // another thread may have registered the client after the lookup above
+        Err(ClientRegistrationError::ClientAlreadyRegistered(reqrep_id)) => client(reqrep_id)
+            .map(|client| (client, Registration::Existing))
+            .ok_or_else(|| ClientRegistrationError::ClientAlreadyRegistered(reqrep_id)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Indicates whether a Client registration created a new Client or returned an existing one
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Registration {
+    /// A new Client was created and registered
+    Created,
+    /// A Client was already registered, and was returned
+    Existing,
+}
+
+/// Registers socket overrides for the ReqRepId, which are applied when a client is registered for
+/// the ReqRepId
+/// - this enables each logical service to be tuned independently, without having to pass a
+///   SocketConfig at each call site that registers the client
+/// - clients that are already registered are not affected
+/// - returns the previously registered overrides
+pub fn set_socket_overrides(
+    reqrep_id: ReqRepId,
+    overrides: SocketOverrides,
+) -> Option<SocketOverrides> {
+    SOCKET_OVERRIDES.write().insert(reqrep_id, overrides)
+}
+
+/// Returns the socket overrides that are registered for the ReqRepId
+pub fn socket_overrides(reqrep_id: ReqRepId) -> Option<SocketOverrides> {
+    SOCKET_OVERRIDES.read().get(&reqrep_id).cloned()
+}
+
+/// Removes the socket overrides that are registered for the ReqRepId
+pub fn remove_socket_overrides(reqrep_id: ReqRepId) -> Option<SocketOverrides> {
+    SOCKET_OVERRIDES.write().remove(&reqrep_id)
+}
+
+/// Adds the client to the logical service's balancer group - see [client_balanced()](fn.client_balanced.html)
+/// - the client does not need to be registered yet, i.e., unregistered members are skipped by the
+///   balancer
+/// - returns false if the client is already a member
+pub fn add_balanced_client(logical_id: ReqRepId, reqrep_id: ReqRepId) -> bool {
+    let mut balanced_clients = BALANCED_CLIENTS.write();
+    let members = balanced_clients.entry(logical_id).or_insert_with(Vec::new);
+    if members.contains(&reqrep_id) {
+        return false;
+    }
+    members.push(reqrep_id);
+    true
+}
+
+/// Removes the client from the logical service's balancer group
+/// - returns false if the client was not a member
+pub fn remove_balanced_client(logical_id: ReqRepId, reqrep_id: ReqRepId) -> bool {
+    let mut balanced_clients = BALANCED_CLIENTS.write();
+    let removed = match balanced_clients.get_mut(&logical_id) {
+        Some(members) => {
+            let len = members.len();
+            members.retain(|member| *member != reqrep_id);
+            members.len() < len
+        }
+        None => false,
+    };
+    if balanced_clients
+        .get(&logical_id)
+        .map_or(false, |members| members.is_empty())
+    {
+        balanced_clients.remove(&logical_id);
+    }
+    removed
+}
+
+/// Returns the ReqRepId(s) of the clients that are members of the logical service's balancer group
+pub fn balanced_client_ids(logical_id: ReqRepId) -> Vec<ReqRepId> {
+    BALANCED_CLIENTS
+        .read()
+        .get(&logical_id)
+        .cloned()
+        .unwrap_or_else(Vec::new)
+}
+
+/// Selects a healthy Client among the registered members of the logical service's balancer group
+/// - clients that are marked unhealthy are skipped until their cool down elapses - see [mark_unhealthy()](fn.mark_unhealthy.html)
+/// - clients whose recent success rate is below [HEALTH_MIN_SUCCESS_RATE](constant.HEALTH_MIN_SUCCESS_RATE.html)
+///   are skipped, once at least [HEALTH_MIN_SAMPLES](constant.HEALTH_MIN_SAMPLES.html) outcomes have been recorded
+/// - clients that are draining are skipped
+/// - among the healthy clients, connected clients are preferred, followed by the highest success
+///   rate, and then the fewest in-flight requests
+/// - returns None if no healthy client is registered
+pub fn client_balanced(logical_id: ReqRepId) -> Option<Client> {
+    let members = balanced_client_ids(logical_id);
+    // the locks are acquired in the same order as register_client()
+    let clients = CLIENTS.read();
+    let client_contexts = CLIENT_CONTEXTS.read();
+    members
+        .iter()
+        .filter(|reqrep_id| clients.contains_key(reqrep_id))
+        .filter_map(|reqrep_id| client_contexts.get(reqrep_id))
+        .filter(|ctx| !ctx.draining.load(Ordering::SeqCst) && ctx.health.is_healthy())
+        .max_by(|ctx1, ctx2| {
+            let connected =
+                |ctx: &NngClientContext| ctx.connection_count.load(Ordering::SeqCst) > 0;
+            connected(ctx1)
+                .cmp(&connected(ctx2))
+                .then_with(|| {
+                    ctx1.health
+                        .success_rate()
+                        .partial_cmp(&ctx2.health.success_rate())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .then_with(|| {
+                    ctx2.in_flight
+                        .load(Ordering::SeqCst)
+                        .cmp(&ctx1.in_flight.load(Ordering::SeqCst))
+                })
+        })
+        .map(|ctx| clients[&ctx.id].clone())
+}
+
+/// Marks the registered client as unhealthy, i.e., trips its circuit breaker, which makes the
+/// balancer skip the client until the cool down elapses
+/// - returns false if the client is not registered
+pub fn mark_unhealthy(reqrep_id: ReqRepId, cool_down: Duration) -> bool {
+    match CLIENT_CONTEXTS.read().get(&reqrep_id) {
+        Some(ctx) => {
+            *ctx.health.unhealthy_until.lock() = Some(Instant::now() + cool_down);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Marks the registered client as healthy, i.e., resets its circuit breaker and clears its recent
+/// request outcomes
+/// - returns false if the client is not registered
+pub fn mark_healthy(reqrep_id: ReqRepId) -> bool {
+    match CLIENT_CONTEXTS.read().get(&reqrep_id) {
+        Some(ctx) => {
+            *ctx.health.unhealthy_until.lock() = None;
+            ctx.health.outcomes.lock().clear();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns true if the registered client is healthy - see [client_balanced()](fn.client_balanced.html)
+/// - returns None if the client is not registered
+pub fn is_healthy(reqrep_id: ReqRepId) -> Option<bool> {
+    CLIENT_CONTEXTS
+        .read()
+        .get(&reqrep_id)
+        .map(|ctx| ctx.health.is_healthy())
+}
+
+/// Returns the registered client's success rate over its [HEALTH_WINDOW_SIZE](constant.HEALTH_WINDOW_SIZE.html)
+/// most recent requests
+/// - if no requests have completed yet, then the success rate is 1.0
+pub fn success_rate(reqrep_id: ReqRepId) -> Option<f64> {
+    CLIENT_CONTEXTS
+        .read()
+        .get(&reqrep_id)
+        .map(|ctx| ctx.health.success_rate())
+}
+
 /// Unregisters the client from the global registry
 pub fn unregister_client(reqrep_id: ReqRepId) -> Option<Client> {
     let mut clients = CLIENTS.write();
@@ -119,6 +384,127 @@ pub fn registered_client_ids() -> Vec<ReqRepId> {
     CLIENTS.read().keys().cloned().collect()
 }
 
+/// Drains the client, i.e., stops accepting new requests and waits until all in-flight requests
+/// have completed or the timeout elapses.
+/// - once the client is draining, new requests will fail with [RequestError::ClientDraining](enum.RequestError.html#variant.ClientDraining)
+/// - this complements [unregister_client()](fn.unregister_client.html), i.e., drain the client
+///   before unregistering it to ensure in-flight requests are not dropped
+/// - if the client is not registered, then there is nothing to drain
+///
+/// ## Notes
+/// The current thread is blocked while waiting for the in-flight requests to complete.
+pub fn drain(reqrep_id: ReqRepId, timeout: Duration) -> Result<(), DrainTimeout> {
+    let in_flight = {
+        let client_contexts = CLIENT_CONTEXTS.read();
+        match client_contexts.get(&reqrep_id) {
+            Some(ctx) => {
+                ctx.draining.store(true, Ordering::SeqCst);
+                ctx.in_flight.clone()
+            }
+            None => return Ok(()),
+        }
+    };
+    let start = Instant::now();
+    while in_flight.load(Ordering::SeqCst) > 0 {
+        if start.elapsed() >= timeout {
+            return Err(DrainTimeout {
+                reqrep_id,
+                in_flight: in_flight.load(Ordering::SeqCst),
+            });
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    Ok(())
+}
+
+/// Returns the number of in-flight requests for the registered client
+pub fn in_flight_request_count(reqrep_id: ReqRepId) -> Option<usize> {
+    CLIENT_CONTEXTS
+        .read()
+        .get(&reqrep_id)
+        .map(|ctx| ctx.in_flight.load(Ordering::SeqCst))
+}
+
+/// Warms up the client, i.e., waits until the client's dialer has established a connection to the
+/// server, and then optionally round-trips the specified ping request. This moves the cold dial cost
+/// off of the first real request.
+/// - the timeout is applied to the connection wait and the ping round trip combined
+/// - the ping request is application specific - it must be a request that the server will reply to,
+///   and its reply is discarded
+/// - the dialer connection can be established eagerly when the client is registered via
+///   [DialerConfig::set_pre_dial()](struct.DialerConfig.html#method.set_pre_dial)
+///
+/// ## Notes
+/// The current thread is blocked while warming up the client.
+pub fn warm_up(
+    reqrep_id: ReqRepId,
+    ping: Option<nng::Message>,
+    timeout: Duration,
+) -> Result<(), WarmUpError> {
+    let connection_count = CLIENT_CONTEXTS
+        .read()
+        .get(&reqrep_id)
+        .map(|ctx| ctx.connection_count.clone())
+        .ok_or(WarmUpError::ClientNotRegistered(reqrep_id))?;
+    let start = Instant::now();
+    while connection_count.load(Ordering::SeqCst) == 0 {
+        if start.elapsed() >= timeout {
+            return Err(WarmUpError::ConnectTimeout(reqrep_id));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    if let Some(ping) = ping {
+        let mut client = client(reqrep_id).ok_or(WarmUpError::ClientNotRegistered(reqrep_id))?;
+        let deadline = start + timeout;
+        let reply = execution::global_executor()
+            .run(async move { await!(client.send_recv_with_deadline(ping, deadline)) })
+            .map_err(WarmUpError::PingChannelError)?;
+        reply.map_err(WarmUpError::PingFailed)?;
+    }
+    Ok(())
+}
+
+/// Returns the number of connections that the registered client's dialer currently has established
+pub fn connection_count(reqrep_id: ReqRepId) -> Option<usize> {
+    CLIENT_CONTEXTS
+        .read()
+        .get(&reqrep_id)
+        .map(|ctx| ctx.connection_count.load(Ordering::SeqCst))
+}
+
+/// Subscribes to the registered client's [ConnectionEvent(s)](enum.ConnectionEvent.html), which are
+/// fed by the dialer's pipe events, e.g., to react to reconnects by renegotiating a session key.
+/// - only events that occur after subscribing are received
+/// - the stream ends when the client's backend service is stopped
+/// - returns None if the client is not registered
+pub fn connection_events(reqrep_id: ReqRepId) -> Option<impl Stream<Item = ConnectionEvent>> {
+    CLIENT_CONTEXTS.read().get(&reqrep_id).map(|ctx| {
+        let (tx, rx) = mpsc::unbounded();
+        ctx.connection_event_subscribers.lock().push(tx);
+        rx
+    })
+}
+
+/// Client connection events
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ConnectionEvent {
+    /// a connection to the server was established
+    Connected,
+    /// the connection to the server was dropped
+    Disconnected,
+}
+
+/// Returns the number of registered clients
+pub(crate) fn client_count() -> usize {
+    CLIENTS.read().len()
+}
+
+/// Returns the number of registered client contexts
+pub(crate) fn client_context_count() -> usize {
+    CLIENT_CONTEXTS.read().len()
+}
+
 /// The context that is required by the NngClient's backend service.
 #[derive(Clone)]
 struct NngClientContext {
@@ -126,14 +512,89 @@ struct NngClientContext {
     socket: Option<nng::Socket>,
     dialer: Option<nng::Dialer>,
     aio_context_pool_return: mpsc::Sender<mpsc::Sender<Request>>,
+    in_flight: Arc<AtomicUsize>,
+    draining: Arc<AtomicBool>,
+    connection_count: Arc<AtomicUsize>,
+    connection_event_subscribers: ConnectionEventSubscribers,
+    health: Arc<ClientHealth>,
+}
+
+/// Tracks the recent request outcomes and the circuit breaker state, which the balancer uses to
+/// judge the client's health
+#[derive(Debug, Default)]
+struct ClientHealth {
+    // true = success
+    outcomes: Mutex<std::collections::VecDeque<bool>>,
+    unhealthy_until: Mutex<Option<Instant>>,
 }
 
+impl ClientHealth {
+    fn record(&self, success: bool) {
+        let mut outcomes = self.outcomes.lock();
+        if outcomes.len() == HEALTH_WINDOW_SIZE {
+            outcomes.pop_front();
+        }
+        outcomes.push_back(success);
+    }
+
+    fn success_rate(&self) -> f64 {
+        let outcomes = self.outcomes.lock();
+        if outcomes.is_empty() {
+            return 1.0;
+        }
+        outcomes.iter().filter(|success| **success).count() as f64 / outcomes.len() as f64
+    }
+
+    fn is_healthy(&self) -> bool {
+        {
+            let mut unhealthy_until = self.unhealthy_until.lock();
+            match *unhealthy_until {
+                Some(until) if Instant::now() < until => return false,
+                // the cool down has elapsed - the circuit is closed again
+                Some(_) => *unhealthy_until = None,
+                None => (),
+            }
+        }
+        self.outcomes.lock().len() < HEALTH_MIN_SAMPLES
+            || self.success_rate() >= HEALTH_MIN_SUCCESS_RATE
+    }
+}
+
+type ConnectionEventSubscribers = Arc<Mutex<Vec<mpsc::UnboundedSender<ConnectionEvent>>>>;
+
 /// nng client
 #[derive(Clone)]
 struct NngClient {
     id: ReqRepId,
     borrow: mpsc::Sender<oneshot::Sender<mpsc::Sender<Request>>>,
     request_sender_pool_task_stop_tx: mpsc::Sender<()>,
+    in_flight: Arc<AtomicUsize>,
+    in_flight_idle: InFlightIdle,
+    draining: Arc<AtomicBool>,
+    shutting_down: Arc<AtomicBool>,
+    destroy_grace_period: Duration,
+    linger: Option<Duration>,
+    health: Arc<ClientHealth>,
+    executor: Executor,
+}
+
+/// Notified when the in-flight request count drops to zero
+type InFlightIdle = Arc<Mutex<Option<oneshot::Sender<()>>>>;
+
+/// Decrements the in-flight request count when dropped
+struct InFlightRequest {
+    in_flight: Arc<AtomicUsize>,
+    in_flight_idle: InFlightIdle,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            if let Some(idle) = self.in_flight_idle.lock().take() {
+                let _ = idle.send(());
+            }
+        }
+    }
 }
 
 impl NngClient {
@@ -150,45 +611,87 @@ impl NngClient {
         mut executor: Executor,
     ) -> Result<Self, NngClientError> {
         let mut nng_client_executor = executor.clone();
+        // used to drain the in-flight requests when the client is destroyed
+        let drain_executor = executor.clone();
         let parallelism = dialer_config.parallelism();
+        let max_consecutive_failures = dialer_config.max_consecutive_context_failures();
+        let destroy_grace_period = dialer_config.destroy_grace_period();
+        let (aio_send_timeout, aio_recv_timeout) = socket_config
+            .as_ref()
+            .and_then(SocketConfig::socket_config)
+            .map_or((None, None), |config| {
+                (config.aio_send_timeout(), config.aio_recv_timeout())
+            });
+        let linger = socket_config
+            .as_ref()
+            .and_then(SocketConfig::socket_config)
+            .and_then(config::SocketConfig::linger);
+        let reqrep_id_label = id.to_string();
         let (aio_context_pool_return, aio_context_pool_borrow) =
             mpsc::channel::<mpsc::Sender<Request>>(parallelism);
 
-        let create_context = move || {
-            let socket = SocketConfig::create_socket(socket_config)
-                .map_err(NngClientError::SocketCreateFailure)?;
-            let dialer = dialer_config
-                .start_dialer(&socket)
-                .map_err(NngClientError::DialerStartError)?;
-
-            Ok(NngClientContext {
-                id,
-                socket: Some(socket),
-                dialer: Some(dialer),
-                aio_context_pool_return,
-            })
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let draining = Arc::new(AtomicBool::new(false));
+        let health = Arc::new(ClientHealth::default());
+
+        let create_context = {
+            let in_flight = in_flight.clone();
+            let draining = draining.clone();
+            let health = health.clone();
+            move || {
+                let socket = SocketConfig::create_socket(socket_config)
+                    .map_err(NngClientError::SocketCreateFailure)?;
+                // tracks the dialer connections - used to know when the client is connected
+                let connection_count = Arc::new(AtomicUsize::new(0));
+                let connection_event_subscribers: ConnectionEventSubscribers =
+                    Arc::new(Mutex::new(Vec::new()));
+                {
+                    let connection_count = connection_count.clone();
+                    let connection_event_subscribers = connection_event_subscribers.clone();
+                    socket
+                        .pipe_notify(move |_pipe, event| {
+                            let connection_event = match event {
+                                nng::PipeEvent::AddPost => {
+                                    connection_count.fetch_add(1, Ordering::SeqCst);
+                                    ConnectionEvent::Connected
+                                }
+                                nng::PipeEvent::RemovePost => {
+                                    connection_count.fetch_sub(1, Ordering::SeqCst);
+                                    ConnectionEvent::Disconnected
+                                }
+                                _ => return,
+                            };
+                            // subscribers that have dropped their stream are removed
+                            connection_event_subscribers.lock().retain(|subscriber| {
+                                subscriber.unbounded_send(connection_event).is_ok()
+                            });
+                        })
+                        .map_err(NngClientError::PipeNotifyFailed)?;
+                }
+                let dialer = dialer_config
+                    .start_dialer(&socket)
+                    .map_err(NngClientError::DialerStartError)?;
+
+                Ok(NngClientContext {
+                    id,
+                    socket: Some(socket),
+                    dialer: Some(dialer),
+                    aio_context_pool_return,
+                    in_flight,
+                    draining,
+                    connection_count,
+                    connection_event_subscribers,
+                    health,
+                })
+            }
         };
 
         let mut start_workers = move |ctx: &NngClientContext| {
             for i in 0..parallelism {
-                // used to notify the workers when an Aio event has occurred, i.e., the Aio callback has been invoked
-                let (aio_tx, mut aio_rx) = futures::channel::mpsc::unbounded::<()>();
-                let aio_tx = AssertUnwindSafe(aio_tx);
-                let context = nng::Context::new(ctx.socket.as_ref().unwrap())
-                    .map_err(NngClientError::NngContextCreateFailed)?;
-                let callback_ctx = context.clone();
-                let aio = nng::Aio::with_callback(move |_aio| {
-                    if let Err(err) = aio_tx.unbounded_send(()) {
-                        // means the channel has been disconnected because the worker Future task has completed
-                        // the server is either being stopped, or the worker has crashed
-                        // TODO: we need a way to know if the server is being shutdown
-                        warn!("Failed to nofify worker of Aio event. This means the worker is not running. The Aio Context will be closed: {}", err);
-                        // TODO: will cloning the Context work ? Context::close() cannot be invoked from the callback because it consumes the Context
-                        //       and rust won't allow it because the Context is being referenced by the FnMut closure
-                        callback_ctx.clone().close();
-                        // TODO: send an alert - if the worker crashed, i.e., panicked, then it may need to be restarted
-                    }
-                }).map_err(NngClientError::NngAioCreateFailed)?;
+                let socket = ctx.socket.as_ref().unwrap().clone();
+                let (mut context, mut aio, mut aio_rx) = new_aio_context(&socket)?;
+                let context_recreate_count =
+                    CONTEXT_RECREATE_COUNT.with_label_values(&[reqrep_id_label.as_str()]);
 
                 let (req_tx, mut req_rx) = futures::channel::mpsc::channel::<Request>(1);
                 let mut aio_context_pool_return = ctx.aio_context_pool_return.clone();
@@ -201,9 +704,15 @@ impl NngClient {
                 }
                 executor.spawn(async move {
                     debug!("[{}-{}] NngClient Aio Context task is running", id, i);
+                    let mut consecutive_failures = 0;
                     while let Some(mut req) = await!(req_rx.next()) {
                         debug!("[{}-{}] NngClient: processing request", id, i);
+                        let mut failed = false;
                         if let Some(msg) = req.msg.take() {
+                            // the request timeout takes precedence over the default Aio timeouts
+                            if let Err(err) = aio.set_timeout(req.timeout.or(aio_send_timeout)) {
+                                warn!("[{}-{}] Aio::set_timeout() failed: {}", id, i, err);
+                            }
                             // send the request
                             match context.send(&aio, msg) {
                                 Ok(_) => {
@@ -213,7 +722,9 @@ impl NngClient {
                                     }
                                     match aio.result().unwrap() {
                                         Ok(_) => {
-                                            // TODO: set a timeout - see Aio::set_timeout()
+                                            if let Err(err) = aio.set_timeout(req.timeout.or(aio_recv_timeout)) {
+                                                warn!("[{}-{}] Aio::set_timeout() failed: {}", id, i, err);
+                                            }
                                             // receive the reply
                                             match context.recv(&aio) {
                                                 Ok(_) => {
@@ -224,8 +735,13 @@ impl NngClient {
                                                     match aio.result().unwrap() {
                                                         Ok(_) => {
                                                             match aio.get_msg() {
-                                                                Some(reply) => {
-                                                                    let _ = req.reply_chan.send(Ok(reply));
+                                                                Some(reply) => match server::WireError::decode_reply(&reply) {
+                                                                    Some(err) => {
+                                                                        let _ = req.reply_chan.send(Err(RequestError::Service(err)));
+                                                                    },
+                                                                    None => {
+                                                                        let _ = req.reply_chan.send(Ok(reply));
+                                                                    }
                                                                 },
                                                                 None => {
                                                                     let _ = req.reply_chan.send(Err(RequestError::NoReplyMessage));
@@ -235,29 +751,55 @@ impl NngClient {
                                                         Err(err) => {
                                                             let _ = req.reply_chan.send(Err(RequestError::RecvFailed(err)));
                                                             aio.cancel();
+                                                            failed = true;
                                                         }
                                                     }
                                                 },
                                                 Err(err) => {
                                                     let _ = req.reply_chan.send(Err(RequestError::RecvFailed(err)));
                                                     aio.cancel();
+                                                    failed = true;
                                                 }
                                             }
                                         },
                                         Err(err) => {
                                             let _ = req.reply_chan.send(Err(RequestError::SendFailed(err)));
                                             aio.cancel();
+                                            failed = true;
                                         }
                                     }
                                 },
                                 Err((_msg, err)) =>  {
                                     let _ = req.reply_chan.send(Err(RequestError::SendFailed(err)));
                                     aio.cancel();
+                                    failed = true;
                                 }
                             }
                         } else {
                             let _ = req.reply_chan.send(Err(RequestError::InvalidRequest("BUG: Request was received with no nng::Message".to_string())));
                         }
+                        if failed {
+                            consecutive_failures += 1;
+                            // a Context that keeps failing is presumed to be dead - replace it with a new one
+                            // - the dialer is owned by the socket and will redial on its own, thus only
+                            //   the Context and Aio need to be recreated
+                            if consecutive_failures >= max_consecutive_failures {
+                                warn!("[{}-{}] NngClient Aio Context failed {} consecutive requests - it will be recreated", id, i, consecutive_failures);
+                                match new_aio_context(&socket) {
+                                    Ok((new_context, new_aio, new_aio_rx)) => {
+                                        let old_context = std::mem::replace(&mut context, new_context);
+                                        aio = new_aio;
+                                        aio_rx = new_aio_rx;
+                                        old_context.close();
+                                        context_recreate_count.inc();
+                                        consecutive_failures = 0;
+                                    }
+                                    Err(err) => error!("[{}-{}] Failed to recreate NngClient Aio Context: {}", id, i, err)
+                                }
+                            }
+                        } else {
+                            consecutive_failures = 0;
+                        }
                         // add a request Sender back to the pool, indicating the worker is now available
                         if let Err(err) = await!(aio_context_pool_return.send(req_tx.clone())) {
                             error!("[{}-{}] Failed to return request sender back to the pool: {}",id, i, err)
@@ -320,69 +862,223 @@ impl NngClient {
         {
             let mut clients = CLIENT_CONTEXTS.write();
             clients.insert(ctx.id, Arc::new(ctx));
+            super::check_registry_size(super::Registry::ClientContexts, clients.len());
         }
 
         Ok(Self {
             id,
             borrow: borrow_tx,
             request_sender_pool_task_stop_tx,
+            in_flight,
+            in_flight_idle: Arc::new(Mutex::new(None)),
+            draining,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            destroy_grace_period,
+            linger,
+            health,
+            executor: drain_executor,
         })
     }
 }
 
+/// Creates a new Context on the socket along with an Aio that is bound to it.
+/// - the returned Receiver is notified each time the Aio callback is invoked
+fn new_aio_context(
+    socket: &nng::Socket,
+) -> Result<(nng::Context, nng::Aio, mpsc::UnboundedReceiver<()>), NngClientError> {
+    // used to notify the workers when an Aio event has occurred, i.e., the Aio callback has been invoked
+    let (aio_tx, aio_rx) = mpsc::unbounded::<()>();
+    let aio_tx = AssertUnwindSafe(aio_tx);
+    let context = nng::Context::new(socket).map_err(NngClientError::NngContextCreateFailed)?;
+    let callback_ctx = context.clone();
+    let aio = nng::Aio::with_callback(move |_aio| {
+        if let Err(err) = aio_tx.unbounded_send(()) {
+            // means the channel has been disconnected because the worker Future task has completed
+            // the server is either being stopped, or the worker has crashed
+            // TODO: we need a way to know if the server is being shutdown
+            warn!("Failed to nofify worker of Aio event. This means the worker is not running. The Aio Context will be closed: {}", err);
+            // TODO: will cloning the Context work ? Context::close() cannot be invoked from the callback because it consumes the Context
+            //       and rust won't allow it because the Context is being referenced by the FnMut closure
+            callback_ctx.clone().close();
+            // TODO: send an alert - if the worker crashed, i.e., panicked, then it may need to be restarted
+        }
+    })
+    .map_err(NngClientError::NngAioCreateFailed)?;
+    Ok((context, aio, aio_rx))
+}
+
 impl fmt::Debug for NngClient {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "NngClient({})", self.id)
     }
 }
 
-impl reqrep::Processor<nng::Message, Result<nng::Message, RequestError>> for NngClient {
-    fn process(
+impl NngClient {
+    /// Sends the request to an Aio Context worker
+    /// - timeout - if specified, then it overrides the default Aio send and recv timeouts
+    fn send_request(
         &mut self,
         req: nng::Message,
+        timeout: Option<Duration>,
     ) -> reqrep::FutureReply<Result<nng::Message, RequestError>> {
-        let mut borrow = self.borrow.clone();
+        // the request is counted as in-flight before checking if the client is draining - this ensures
+        // that drain() will see the request
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let in_flight = InFlightRequest {
+            in_flight: self.in_flight.clone(),
+            in_flight_idle: self.in_flight_idle.clone(),
+        };
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return async move {
+                drop(in_flight);
+                Err(RequestError::ClientShuttingDown)
+            }
+                .boxed();
+        }
+        if self.draining.load(Ordering::SeqCst) {
+            return async move {
+                drop(in_flight);
+                Err(RequestError::ClientDraining)
+            }
+                .boxed();
+        }
+
+        let borrow = self.borrow.clone();
+        let shutting_down = self.shutting_down.clone();
+        let health = self.health.clone();
 
         async move {
-            let (borrow_tx, borrow_rx) = oneshot::channel();
-            if await!(borrow.send(borrow_tx)).is_err() {
-                return Err(RequestError::AioContextPoolChannelDisconnected);
+            let _in_flight = in_flight;
+            match await!(Self::send_to_aio_context(borrow, req, timeout)) {
+                // the request was aborted because the client was force closed
+                Err(ref err) if err.is_aborted() && shutting_down.load(Ordering::SeqCst) => {
+                    Err(RequestError::ClientShuttingDown)
+                }
+                result => {
+                    health.record(result.is_ok());
+                    result
+                }
             }
+        }
+            .boxed()
+    }
 
-            let (tx, rx) = oneshot::channel();
-            let request = Request {
-                msg: Some(req),
-                reply_chan: tx,
-            };
-
-            match await!(borrow_rx) {
-                Ok(ref mut sender) => match await!(sender.send(request)) {
-                    Ok(_) => match await!(rx) {
-                        Ok(result) => result,
-                        Err(_) => Err(RequestError::ReplyChannelClosed),
-                    },
-                    Err(err) => Err(RequestError::AioContextChannelDisconnected(err)),
+    /// Borrows an Aio Context worker from the pool, and sends it the request
+    async fn send_to_aio_context(
+        mut borrow: mpsc::Sender<oneshot::Sender<mpsc::Sender<Request>>>,
+        req: nng::Message,
+        timeout: Option<Duration>,
+    ) -> Result<nng::Message, RequestError> {
+        let (borrow_tx, borrow_rx) = oneshot::channel();
+        if await!(borrow.send(borrow_tx)).is_err() {
+            return Err(RequestError::AioContextPoolChannelDisconnected);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let request = Request {
+            msg: Some(req),
+            reply_chan: tx,
+            timeout,
+        };
+
+        match await!(borrow_rx) {
+            Ok(ref mut sender) => match await!(sender.send(request)) {
+                Ok(_) => match await!(rx) {
+                    Ok(result) => result,
+                    Err(_) => Err(RequestError::ReplyChannelClosed),
                 },
-                Err(_) => Err(RequestError::AioContextPoolChannelDisconnected),
-            }
+                Err(err) => Err(RequestError::AioContextChannelDisconnected(err)),
+            },
+            Err(_) => Err(RequestError::AioContextPoolChannelDisconnected),
         }
-            .boxed()
+    }
+}
+
+impl reqrep::Processor<nng::Message, Result<nng::Message, RequestError>> for NngClient {
+    fn process(
+        &mut self,
+        req: nng::Message,
+    ) -> reqrep::FutureReply<Result<nng::Message, RequestError>> {
+        self.send_request(req, None)
+    }
+
+    /// the time remaining until the deadline is used as the Aio send and recv timeout
+    fn process_with_deadline(
+        &mut self,
+        req: nng::Message,
+        deadline: reqrep::DeadlineSignal,
+    ) -> reqrep::FutureReply<Result<nng::Message, RequestError>> {
+        let deadline = deadline.deadline();
+        let now = Instant::now();
+        let timeout = if deadline > now {
+            deadline - now
+        } else {
+            Duration::from_millis(0)
+        };
+        self.send_request(req, Some(timeout))
     }
 
+    /// new requests are rejected, and in-flight requests are given up to the destroy grace period
+    /// to complete before the client is force closed
+    /// - the in-flight requests are drained async, i.e., the Executor thread is not blocked
     fn destroy(&mut self) {
         debug!("NngClient({}) is being destroyed ...", self.id);
+        self.shutting_down.store(true, Ordering::SeqCst);
+        // the idle signal is registered before checking the in-flight count, i.e., requests that
+        // complete after the check will fire it
+        let (idle_tx, idle_rx) = oneshot::channel();
+        *self.in_flight_idle.lock() = Some(idle_tx);
+        if self.in_flight.load(Ordering::SeqCst) == 0 {
+            self.close();
+            return;
+        }
+        let grace_period_expired = timer::delay_for(self.destroy_grace_period);
+        let mut client = self.clone();
+        let spawn_result = self.executor.spawn(
+            async move {
+                let _ = await!(future::select(idle_rx, grace_period_expired));
+                client.close();
+            },
+        );
+        if let Err(err) = spawn_result {
+            error!(
+                "NngClient({}): failed to spawn the drain task - the client will be closed: {:?}",
+                self.id, err
+            );
+            self.close();
+        }
+    }
+}
+
+impl NngClient {
+    /// Closes the nng resources - the in-flight requests that remain are aborted
+    fn close(&mut self) {
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        if in_flight > 0 {
+            warn!(
+                "NngClient({}): destroy grace period ({:?}) elapsed - {} in-flight requests will be aborted",
+                self.id, self.destroy_grace_period, in_flight
+            );
+        }
         let mut client_contexts = CLIENT_CONTEXTS.write();
-        if let Some(mut context) = client_contexts.remove(&self.id) {
-            let context = Arc::get_mut(&mut context).unwrap();
-            context.dialer.take().unwrap().close();
-            debug!("NngClient({}): closed nng::Dialer", self.id);
-            context.socket.take().unwrap().close();
-            debug!("NngClient({}): closed nng::Socket ", self.id);
-            // shutdown the Sender<Request> pool task
-            self.borrow.close_channel();
-            self.request_sender_pool_task_stop_tx.close_channel();
-            debug!("NngClient({}): closed channels", self.id);
+        // the client may have been registered again while this instance was being drained
+        let registered = client_contexts
+            .get(&self.id)
+            .map_or(false, |context| Arc::ptr_eq(&context.in_flight, &self.in_flight));
+        if registered {
+            if let Some(mut context) = client_contexts.remove(&self.id) {
+                let context = Arc::get_mut(&mut context).unwrap();
+                context.dialer.take().unwrap().close();
+                debug!("NngClient({}): closed nng::Dialer", self.id);
+                config::close_socket(context.socket.take().unwrap(), self.linger);
+                debug!("NngClient({}): closed nng::Socket ", self.id);
+            }
         }
+        drop(client_contexts);
+        // shutdown the Sender<Request> pool task
+        self.borrow.close_channel();
+        self.request_sender_pool_task_stop_tx.close_channel();
+        debug!("NngClient({}): closed channels", self.id);
         debug!("NngClient({}) is destroyed", self.id);
     }
 }
@@ -428,6 +1124,26 @@ pub enum NngClientError {
         _0
     )]
     ReqRepServiceStartFailed(bool),
+    /// Failed to register the socket pipe notify callback
+    #[fail(display = "Failed to register the socket pipe notify callback: {}", _0)]
+    PipeNotifyFailed(#[cause] nng::Error),
+}
+
+/// Client warm up related errors
+#[derive(Debug, Fail, Clone)]
+pub enum WarmUpError {
+    /// The client is not registered
+    #[fail(display = "Client is not registered: {}", _0)]
+    ClientNotRegistered(ReqRepId),
+    /// The client did not connect to the server before the warm up timed out
+    #[fail(display = "Client did not connect before the warm up timed out: {}", _0)]
+    ConnectTimeout(ReqRepId),
+    /// The ping request failed
+    #[fail(display = "Ping request failed: {}", _0)]
+    PingFailed(#[cause] RequestError),
+    /// The ping request could not be sent to the client, or the reply channel was disconnected
+    #[fail(display = "Ping request channel error: {}", _0)]
+    PingChannelError(#[cause] ChannelError),
 }
 
 /// Request related errors
@@ -455,15 +1171,66 @@ pub enum RequestError {
     /// No reply message
     #[fail(display = "BUG: No reply message was found - this should never happen")]
     NoReplyMessage,
+    /// The client is draining and is no longer accepting new requests
+    #[fail(display = "The client is draining and is no longer accepting new requests")]
+    ClientDraining,
+    /// The client is shutting down - the request was either rejected, or was aborted because it did
+    /// not complete within the destroy grace period
+    #[fail(display = "The client is shutting down")]
+    ClientShuttingDown,
+    /// The service replied with an error, i.e., either a [WireError](../server/struct.WireError.html)
+    /// or a [ServiceError](../server/struct.ServiceError.html), which is mapped to a WireError
+    #[fail(display = "Service error: {}", _0)]
+    Service(server::WireError),
+}
+
+impl RequestError {
+    /// returns true if the error is caused by the client's Aio resources being closed out from
+    /// under the request
+    fn is_aborted(&self) -> bool {
+        match self {
+            RequestError::AioContextPoolChannelDisconnected
+            | RequestError::AioContextChannelDisconnected(_)
+            | RequestError::ReplyChannelClosed => true,
+            RequestError::SendFailed(err) | RequestError::RecvFailed(err) => {
+                *err == nng::Error::Closed
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The client drain timed out before all in-flight requests completed
+#[derive(Debug, Fail, Clone)]
+#[fail(
+    display = "Client drain timed out: ReqRepId({}) in-flight request count = {}",
+    reqrep_id, in_flight
+)]
+pub struct DrainTimeout {
+    reqrep_id: ReqRepId,
+    in_flight: usize,
+}
+
+impl DrainTimeout {
+    /// ReqRepId for the client that was being drained
+    pub fn reqrep_id(&self) -> ReqRepId {
+        self.reqrep_id
+    }
+
+    /// Number of in-flight requests at the time the drain timed out
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
 }
 
 struct Request {
     msg: Option<nng::Message>,
     reply_chan: oneshot::Sender<Result<nng::Message, RequestError>>,
+    timeout: Option<Duration>,
 }
 
 /// Socket Settings
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SocketConfig {
     reconnect_min_time: Option<Duration>,
     reconnect_max_time: Option<Duration>,
@@ -572,11 +1339,121 @@ impl SocketConfig {
         this.socket_config = Some(config);
         this
     }
+
+    /// the override settings take precedence
+    fn apply_overrides(self, overrides: SocketOverrides) -> Self {
+        let mut this = self;
+        this.reconnect_min_time = overrides.reconnect_min_time.or(this.reconnect_min_time);
+        this.reconnect_max_time = overrides.reconnect_max_time.or(this.reconnect_max_time);
+        this.resend_time = overrides.resend_time.or(this.resend_time);
+        this
+    }
 }
 
-/// Dialer Settings
+/// Per ReqRepId socket settings, which override the SocketConfig settings when the client is
+/// registered
+/// - see [set_socket_overrides()](fn.set_socket_overrides.html)
+/// - only the settings that are specified are overridden
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SocketOverrides {
+    reconnect_min_time: Option<Duration>,
+    reconnect_max_time: Option<Duration>,
+    resend_time: Option<Duration>,
+}
+
+impl SocketOverrides {
+    /// See [SocketConfig::resend_time()](struct.SocketConfig.html#method.resend_time)
+    pub fn resend_time(&self) -> Option<Duration> {
+        self.resend_time
+    }
+
+    /// See [SocketConfig::reconnect_min_time()](struct.SocketConfig.html#method.reconnect_min_time)
+    pub fn reconnect_min_time(&self) -> Option<Duration> {
+        self.reconnect_min_time
+    }
+
+    /// See [SocketConfig::reconnect_max_time()](struct.SocketConfig.html#method.reconnect_max_time)
+    pub fn reconnect_max_time(&self) -> Option<Duration> {
+        self.reconnect_max_time
+    }
+
+    /// Overrides the amount of time to wait before sending a new request.
+    pub fn set_resend_time(self, resend_time: Duration) -> Self {
+        let mut this = self;
+        this.resend_time = Some(resend_time);
+        this
+    }
+
+    /// Overrides the minimum amount of time to wait before attempting to establish a connection
+    /// after a previous attempt has failed.
+    pub fn set_reconnect_min_time(self, reconnect_min_time: Duration) -> Self {
+        let mut this = self;
+        this.reconnect_min_time = Some(reconnect_min_time);
+        this
+    }
+
+    /// Overrides the maximum amount of time to wait before attempting to establish a connection
+    /// after a previous attempt has failed.
+    pub fn set_reconnect_max_time(self, reconnect_max_time: Duration) -> Self {
+        let mut this = self;
+        this.reconnect_max_time = Some(reconnect_max_time);
+        this
+    }
+}
+
+/// Versioned [DialerConfig](struct.DialerConfig.html), which is used to persist the config using
+/// binary formats that are not self-describing, e.g., bincode.
+/// - missing fields cannot be defaulted by binary formats, thus each schema version is a variant.
+///   New schema versions are appended as new variants.
+/// - self-describing formats, e.g., JSON, can persist the DialerConfig directly because fields that
+///   were added since are defaulted when missing
+/// - configs that were persisted before versioning was introduced deserialize as
+///   [DialerConfigV0](struct.DialerConfigV0.html)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DialerConfig {
+pub enum VersionedDialerConfig {
+    /// schema that was persisted before versioning was introduced
+    V0(DialerConfigV0),
+    /// current schema - see [CONFIG_VERSION](../../config/constant.CONFIG_VERSION.html)
+    V1(DialerConfig),
+}
+
+impl VersionedDialerConfig {
+    /// Returns the schema version
+    pub fn version(&self) -> u32 {
+        match self {
+            VersionedDialerConfig::V0(_) => 0,
+            VersionedDialerConfig::V1(_) => config::CONFIG_VERSION,
+        }
+    }
+
+    /// Upgrades the config to the current schema version
+    /// - fields that were added since the persisted schema version are set to their defaults
+    pub fn migrate(self) -> DialerConfig {
+        match self {
+            VersionedDialerConfig::V0(config) => config.into(),
+            VersionedDialerConfig::V1(config) => config,
+        }
+    }
+}
+
+impl From<DialerConfig> for VersionedDialerConfig {
+    fn from(config: DialerConfig) -> VersionedDialerConfig {
+        VersionedDialerConfig::V1(config)
+    }
+}
+
+impl From<DialerConfigV0> for VersionedDialerConfig {
+    fn from(config: DialerConfigV0) -> VersionedDialerConfig {
+        VersionedDialerConfig::V0(config)
+    }
+}
+
+/// DialerConfig schema version 0, i.e., the schema that was persisted before versioning was
+/// introduced
+/// - the fields must match the released schema exactly, i.e., fields that were added since are
+///   defaulted when the config is migrated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialerConfigV0 {
     #[serde(with = "url_serde")]
     url: url::Url,
     parallelism: usize,
@@ -587,30 +1464,97 @@ pub struct DialerConfig {
     reconnect_max_time: Option<Duration>,
 }
 
-impl DialerConfig {
-    /// constructor
-    /// - parallelism = 1
-    pub fn new(url: url::Url) -> DialerConfig {
+impl From<DialerConfigV0> for DialerConfig {
+    fn from(config: DialerConfigV0) -> DialerConfig {
         DialerConfig {
-            url,
-            recv_max_size: None,
-            no_delay: None,
-            keep_alive: None,
-            parallelism: 1,
-            reconnect_min_time: None,
-            reconnect_max_time: None,
+            url: config.url,
+            parallelism: config.parallelism,
+            recv_max_size: config.recv_max_size,
+            no_delay: config.no_delay,
+            keep_alive: config.keep_alive,
+            reconnect_min_time: config.reconnect_min_time,
+            reconnect_max_time: config.reconnect_max_time,
+            max_consecutive_context_failures:
+                DialerConfig::default_max_consecutive_context_failures(),
+            pre_dial: false,
+            destroy_grace_period: DialerConfig::DEFAULT_DESTROY_GRACE_PERIOD,
         }
     }
+}
 
-    /// Start a socket dialer.
-    ///
-    /// Connection attempt is made asynchronously.
-    /// Furthermore, if the connection was closed for a synchronously dialed connection, the dialer
-    /// will still attempt to redial asynchronously.
+/// Dialer Settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialerConfig {
+    #[serde(with = "url_serde")]
+    url: url::Url,
+    parallelism: usize,
+    recv_max_size: Option<usize>,
+    no_delay: Option<bool>,
+    keep_alive: Option<bool>,
+    reconnect_min_time: Option<Duration>,
+    reconnect_max_time: Option<Duration>,
+    #[serde(default = "DialerConfig::default_max_consecutive_context_failures")]
+    max_consecutive_context_failures: NonZeroUsize,
+    #[serde(default)]
+    pre_dial: bool,
+    #[serde(default = "DialerConfig::default_destroy_grace_period")]
+    destroy_grace_period: Duration,
+}
+
+impl DialerConfig {
+    /// default max number of consecutive failed requests on an Aio Context before it is recreated
+    pub const DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES: usize = 3;
+
+    /// default max amount of time that the client waits for in-flight requests to complete when it
+    /// is being destroyed
+    pub const DEFAULT_DESTROY_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+    /// constructor
+    /// - parallelism = [effective parallelism](../../config/fn.effective_parallelism.html)
+    /// - max_consecutive_context_failures = [DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES](struct.DialerConfig.html#associatedconstant.DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES)
+    /// - destroy_grace_period = [DEFAULT_DESTROY_GRACE_PERIOD](struct.DialerConfig.html#associatedconstant.DEFAULT_DESTROY_GRACE_PERIOD)
+    pub fn new(url: url::Url) -> DialerConfig {
+        DialerConfig {
+            url,
+            recv_max_size: None,
+            no_delay: None,
+            keep_alive: None,
+            parallelism: config::effective_parallelism(),
+            reconnect_min_time: None,
+            reconnect_max_time: None,
+            max_consecutive_context_failures: Self::default_max_consecutive_context_failures(),
+            pre_dial: false,
+            destroy_grace_period: Self::DEFAULT_DESTROY_GRACE_PERIOD,
+        }
+    }
+
+    fn default_max_consecutive_context_failures() -> NonZeroUsize {
+        NonZeroUsize::new(Self::DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES)
+            .expect("DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES must not be zero")
+    }
+
+    fn default_destroy_grace_period() -> Duration {
+        Self::DEFAULT_DESTROY_GRACE_PERIOD
+    }
+
+    /// Start a socket dialer.
+    ///
+    /// Connection attempt is made asynchronously, unless [pre_dial](struct.DialerConfig.html#method.pre_dial)
+    /// is enabled, in which case the connection is established before the dialer is returned.
+    /// Furthermore, if the connection was closed for a synchronously dialed connection, the dialer
+    /// will still attempt to redial asynchronously.
     ///
     /// The returned handle controls the life of the dialer. If it is dropped, the dialer is shut down
     /// and no more messages will be received on it.
     pub fn start_dialer(self, socket: &nng::Socket) -> Result<nng::Dialer, DialerConfigError> {
+        let max_parallelism = config::max_aio_contexts();
+        if self.parallelism > max_parallelism {
+            return Err(DialerConfigError::ParallelismTooHigh {
+                parallelism: self.parallelism,
+                max: max_parallelism,
+            });
+        }
+
         let dialer_options = nng::DialerOptions::new(socket, self.url.as_str())
             .map_err(DialerConfigError::DialerOptionsCreateFailed)?;
 
@@ -641,7 +1585,7 @@ impl DialerConfig {
             .map_err(DialerConfigError::ReconnectMaxTime)?;
 
         dialer_options
-            .start(true)
+            .start(!self.pre_dial)
             .map_err(|(_options, err)| DialerConfigError::DialerStartError(err))
     }
 
@@ -652,7 +1596,7 @@ impl DialerConfig {
 
     /// Max number of async IO operations that can be performed concurrently, which corresponds to the number
     /// of socket contexts that will be created.
-    /// - default = 1
+    /// - default = [effective parallelism](../../config/fn.effective_parallelism.html)
     pub fn parallelism(&self) -> usize {
         self.parallelism
     }
@@ -707,6 +1651,31 @@ impl DialerConfig {
         self.reconnect_max_time
     }
 
+    /// Max number of consecutive failed requests on an Aio Context, i.e., send or recv failures, before
+    /// the Context and its Aio are closed and recreated.
+    /// - default = [DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES](struct.DialerConfig.html#associatedconstant.DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES)
+    /// - the value is never 0, i.e., configs that specify 0 are rejected when they are deserialized
+    pub fn max_consecutive_context_failures(&self) -> usize {
+        self.max_consecutive_context_failures.get()
+    }
+
+    /// If true, then the dialer connection is established synchronously when the dialer is started,
+    /// i.e., the client is connected by the time it is registered. If the server is not reachable,
+    /// then the client registration will fail.
+    /// - default = false
+    pub fn pre_dial(&self) -> bool {
+        self.pre_dial
+    }
+
+    /// Max amount of time that the client waits for in-flight requests to complete when it is being
+    /// destroyed, after which the client is force closed and the remaining in-flight requests fail
+    /// with [RequestError::ClientShuttingDown](enum.RequestError.html#variant.ClientShuttingDown)
+    /// - new requests are rejected as soon as the client starts shutting down
+    /// - default = [DEFAULT_DESTROY_GRACE_PERIOD](struct.DialerConfig.html#associatedconstant.DEFAULT_DESTROY_GRACE_PERIOD)
+    pub fn destroy_grace_period(&self) -> Duration {
+        self.destroy_grace_period
+    }
+
     /// Sets the maximum message size that the will be accepted from a remote peer.
     pub fn set_recv_max_size(self, recv_max_size: usize) -> Self {
         let mut settings = self;
@@ -729,6 +1698,7 @@ impl DialerConfig {
     }
 
     /// set the max capacity of concurrent async requests
+    /// - the count is validated when the dialer is started against [max_aio_contexts()](../../config/fn.max_aio_contexts.html)
     pub fn set_parallelism(self, count: NonZeroUsize) -> Self {
         let mut settings = self;
         settings.parallelism = count.get();
@@ -750,6 +1720,28 @@ impl DialerConfig {
         this.reconnect_max_time = Some(reconnect_max_time);
         this
     }
+
+    /// Sets the max number of consecutive failed requests on an Aio Context before it is recreated
+    pub fn set_max_consecutive_context_failures(self, max: NonZeroUsize) -> Self {
+        let mut this = self;
+        this.max_consecutive_context_failures = max;
+        this
+    }
+
+    /// Enables the dialer connection to be established synchronously when the dialer is started
+    pub fn set_pre_dial(self, pre_dial: bool) -> Self {
+        let mut this = self;
+        this.pre_dial = pre_dial;
+        this
+    }
+
+    /// Sets the max amount of time that the client waits for in-flight requests to complete when it
+    /// is being destroyed
+    pub fn set_destroy_grace_period(self, destroy_grace_period: Duration) -> Self {
+        let mut this = self;
+        this.destroy_grace_period = destroy_grace_period;
+        this
+    }
 }
 
 /// Dialer config related errors
@@ -776,6 +1768,17 @@ pub enum DialerConfigError {
     /// Failed to start Dialer
     #[fail(display = "Failed to start Dialer: {}", _0)]
     DialerStartError(#[cause] nng::Error),
+    /// The configured parallelism exceeds the max number of Aio contexts per socket
+    #[fail(
+        display = "Parallelism ({}) exceeds the max number of Aio contexts per socket ({})",
+        parallelism, max
+    )]
+    ParallelismTooHigh {
+        /// configured parallelism
+        parallelism: usize,
+        /// max number of Aio contexts per socket - see [max_aio_contexts()](../../config/fn.max_aio_contexts.html)
+        max: usize,
+    },
 }
 
 #[allow(warnings)]
@@ -798,7 +1801,10 @@ mod tests {
     };
     use oysterpack_uid::ULID;
     use oysterpack_uid::*;
-    use std::{thread, time::Duration};
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
 
     struct EchoService;
     impl Processor<nng::Message, nng::Message> for EchoService {
@@ -990,8 +1996,11 @@ mod tests {
         let reqrep_id = ReqRepId::generate();
         let url = url::Url::parse(&format!("inproc://{}", reqrep_id)).unwrap();
 
-        // WHEN: the client is started
-        let (mut client, client_executor_id) = start_client(reqrep_id, url.clone());
+        // WHEN: the client is started with a single Aio Context
+        let (mut client, client_executor_id) = start_client_with_dialer_config(
+            reqrep_id,
+            DialerConfig::new(url.clone()).set_parallelism(NonZeroUsize::new(1).unwrap()),
+        );
         // THEN: we expect the Client to have 3 tasks running = 1 Aio worker + 1 ReqRep backend service task + 1 request sender pool task
         let expected_task_count = 3;
         let executor = execution::executor(client_executor_id).unwrap();
@@ -1005,6 +2014,531 @@ mod tests {
         assert_eq!(executor.task_active_count(), expected_task_count);
     }
 
+    #[test]
+    fn drain_client() {
+        configure_logging();
+        let mut executor = execution::ExecutorBuilder::new(ExecutorId::generate())
+            .register()
+            .unwrap();
+
+        struct SlowEchoService;
+        impl Processor<nng::Message, nng::Message> for SlowEchoService {
+            fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+                async move {
+                    thread::sleep(Duration::from_millis(50));
+                    req
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a slow server is running
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let server_reqrep = ReqRepConfig::new(reqrep_id, timer_buckets)
+            .start_service(SlowEchoService, global_executor())
+            .unwrap();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            server_reqrep,
+            global_executor(),
+        )
+        .unwrap();
+        assert!(server_handle.ping());
+
+        // GIVEN: the client has sent several slow requests
+        let (client, _) = start_client(reqrep_id, url.clone());
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let mut client = client.clone();
+                executor
+                    .spawn_with_handle(
+                        async move { await!(client.send_recv(nng::Message::new().unwrap())) },
+                    )
+                    .unwrap()
+            })
+            .collect();
+        while super::in_flight_request_count(reqrep_id).unwrap() == 0 {
+            thread::yield_now();
+        }
+
+        // WHEN: the client is drained
+        super::drain(reqrep_id, Duration::from_secs(5)).unwrap();
+        // THEN: there are no in-flight requests
+        assert_eq!(super::in_flight_request_count(reqrep_id), Some(0));
+        // AND: the in-flight request completed successfully, and requests that were not yet in-flight
+        // were rejected
+        let replies = executor.run(
+            async move {
+                let mut replies = Vec::new();
+                for handle in handles {
+                    replies.push(await!(handle).unwrap());
+                }
+                replies
+            },
+        );
+        assert!(replies.iter().any(|reply| reply.is_ok()));
+        assert!(replies.iter().all(|reply| match reply {
+            Ok(_) | Err(RequestError::ClientDraining) => true,
+            _ => false,
+        }));
+
+        // WHEN: a new request is sent after the client has been drained
+        let mut client = client.clone();
+        let reply = executor.run(async move { await!(client.send_recv(nng::Message::new().unwrap())) });
+        // THEN: the request is rejected
+        match reply.unwrap() {
+            Err(RequestError::ClientDraining) => (),
+            other => panic!("expected RequestError::ClientDraining, but got: {:?}", other),
+        }
+
+        assert!(super::unregister_client(reqrep_id).is_some());
+    }
+
+    #[test]
+    fn destroy_client_with_in_flight_requests() {
+        use futures::future::RemoteHandle;
+        configure_logging();
+        let mut executor = execution::ExecutorBuilder::new(ExecutorId::generate())
+            .register()
+            .unwrap();
+
+        struct SlowEchoService;
+        impl Processor<nng::Message, nng::Message> for SlowEchoService {
+            fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+                async move {
+                    thread::sleep(Duration::from_millis(200));
+                    req
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a slow server is running
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let server_reqrep = ReqRepConfig::new(reqrep_id, timer_buckets)
+            .start_service(SlowEchoService, global_executor())
+            .unwrap();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            server_reqrep,
+            global_executor(),
+        )
+        .unwrap();
+        assert!(server_handle.ping());
+
+        fn send_slow_request(
+            executor: &mut Executor,
+            reqrep_id: ReqRepId,
+            url: url::Url,
+            grace_period: Duration,
+        ) -> (NngClient, RemoteHandle<Result<nng::Message, RequestError>>) {
+            let dialer_config = DialerConfig::new(url).set_destroy_grace_period(grace_period);
+            let client = NngClient::new(reqrep_id, None, dialer_config, executor.clone()).unwrap();
+            let mut request_client = client.clone();
+            let handle = executor
+                .spawn_with_handle(
+                    async move { await!(request_client.process(nng::Message::new().unwrap())) },
+                )
+                .unwrap();
+            while client.in_flight.load(Ordering::SeqCst) == 0 {
+                thread::yield_now();
+            }
+            (client, handle)
+        }
+
+        // GIVEN: a client with a destroy grace period that is shorter than the slow request
+        let (mut client, handle) =
+            send_slow_request(&mut executor, reqrep_id, url.clone(), Duration::from_millis(20));
+        // WHEN: the client is destroyed while the request is in-flight
+        client.destroy();
+        // THEN: the in-flight request is aborted with a clean shutdown error
+        match executor.run(handle) {
+            Err(RequestError::ClientShuttingDown) => (),
+            other => panic!("expected RequestError::ClientShuttingDown, but got: {:?}", other),
+        }
+        // AND: new requests are rejected
+        match executor.run(client.process(nng::Message::new().unwrap())) {
+            Err(RequestError::ClientShuttingDown) => (),
+            other => panic!("expected RequestError::ClientShuttingDown, but got: {:?}", other),
+        }
+        // wait for the server to catch up with the aborted request
+        thread::sleep(Duration::from_millis(250));
+
+        // GIVEN: a client with a destroy grace period that is longer than the slow request
+        let (mut client, handle) =
+            send_slow_request(&mut executor, reqrep_id, url.clone(), Duration::from_secs(5));
+        // WHEN: the client is destroyed while the request is in-flight
+        let start = Instant::now();
+        client.destroy();
+        // THEN: destroy does not block waiting for the in-flight request
+        assert!(start.elapsed() < Duration::from_millis(100));
+        // AND: the in-flight request completes successfully
+        assert!(executor.run(handle).is_ok());
+        assert_eq!(client.in_flight.load(Ordering::SeqCst), 0);
+        // AND: the client is closed once the in-flight request completed, i.e., without waiting for
+        //      the grace period to elapse
+        let start = Instant::now();
+        while CLIENT_CONTEXTS.read().contains_key(&reqrep_id) {
+            assert!(start.elapsed() < Duration::from_secs(1));
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn socket_overrides_per_reqrep_id() {
+        configure_logging();
+
+        // GIVEN: 2 services, each with different socket overrides registered
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let (reqrep_id_1, reqrep_id_2) = (ReqRepId::generate(), ReqRepId::generate());
+        let overrides_1 = SocketOverrides::default().set_resend_time(Duration::from_secs(10));
+        let overrides_2 = SocketOverrides::default()
+            .set_resend_time(Duration::from_secs(20))
+            .set_reconnect_min_time(Duration::from_millis(50))
+            .set_reconnect_max_time(Duration::from_millis(500));
+        assert!(super::set_socket_overrides(reqrep_id_1, overrides_1).is_none());
+        assert!(super::set_socket_overrides(reqrep_id_2, overrides_2).is_none());
+        assert_eq!(super::socket_overrides(reqrep_id_1), Some(overrides_1));
+
+        // WHEN: the clients are registered using the same SocketConfig
+        let socket_config =
+            || SocketConfig::default().set_reconnect_min_time(Duration::from_millis(100));
+        let timer_buckets = metrics::timer_buckets(vec![Duration::from_millis(1)]).unwrap();
+        let client_1 = super::register_client(
+            ReqRepConfig::new(reqrep_id_1, timer_buckets.clone()),
+            Some(socket_config()),
+            DialerConfig::new(url.clone()),
+            global_executor(),
+        )
+        .unwrap();
+        let client_2 = super::register_client(
+            ReqRepConfig::new(reqrep_id_2, timer_buckets),
+            Some(socket_config()),
+            DialerConfig::new(url.clone()),
+            global_executor(),
+        )
+        .unwrap();
+
+        // THEN: each socket carries its configured values
+        {
+            let client_contexts = CLIENT_CONTEXTS.read();
+            let socket = |reqrep_id| client_contexts[&reqrep_id].socket.as_ref().unwrap();
+            let socket_1 = socket(reqrep_id_1);
+            assert_eq!(
+                socket_1
+                    .get_opt::<nng::options::protocol::reqrep::ResendTime>()
+                    .unwrap(),
+                Some(Duration::from_secs(10))
+            );
+            // AND: settings that are not overridden use the SocketConfig setting
+            assert_eq!(
+                socket_1.get_opt::<nng::options::ReconnectMinTime>().unwrap(),
+                Some(Duration::from_millis(100))
+            );
+            let socket_2 = socket(reqrep_id_2);
+            assert_eq!(
+                socket_2
+                    .get_opt::<nng::options::protocol::reqrep::ResendTime>()
+                    .unwrap(),
+                Some(Duration::from_secs(20))
+            );
+            assert_eq!(
+                socket_2.get_opt::<nng::options::ReconnectMinTime>().unwrap(),
+                Some(Duration::from_millis(50))
+            );
+            assert_eq!(
+                socket_2.get_opt::<nng::options::ReconnectMaxTime>().unwrap(),
+                Some(Duration::from_millis(500))
+            );
+        }
+
+        // WHEN: the overrides are removed
+        // THEN: the removed overrides are returned
+        assert_eq!(super::remove_socket_overrides(reqrep_id_1), Some(overrides_1));
+        assert_eq!(super::remove_socket_overrides(reqrep_id_2), Some(overrides_2));
+        assert!(super::socket_overrides(reqrep_id_1).is_none());
+
+        assert!(super::unregister_client(reqrep_id_1).is_some());
+        assert!(super::unregister_client(reqrep_id_2).is_some());
+    }
+
+    #[test]
+    fn client_balanced() {
+        configure_logging();
+
+        // GIVEN: 2 clients that are registered for the same logical service
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let logical_id = ReqRepId::generate();
+        let (reqrep_id_1, reqrep_id_2) = (ReqRepId::generate(), ReqRepId::generate());
+        let timer_buckets = metrics::timer_buckets(vec![Duration::from_millis(1)]).unwrap();
+        for reqrep_id in vec![reqrep_id_1, reqrep_id_2] {
+            super::register_client(
+                ReqRepConfig::new(reqrep_id, timer_buckets.clone()),
+                None,
+                DialerConfig::new(url.clone()),
+                global_executor(),
+            )
+            .unwrap();
+            assert!(super::add_balanced_client(logical_id, reqrep_id));
+        }
+        assert!(!super::add_balanced_client(logical_id, reqrep_id_1));
+        assert_eq!(super::balanced_client_ids(logical_id).len(), 2);
+        assert_eq!(super::is_healthy(reqrep_id_1), Some(true));
+
+        // WHEN: client #1 is marked unhealthy
+        assert!(super::mark_unhealthy(reqrep_id_1, Duration::from_secs(60)));
+        assert_eq!(super::is_healthy(reqrep_id_1), Some(false));
+        // THEN: the balancer routes to the healthy client #2
+        for _ in 0..10 {
+            assert_eq!(super::client_balanced(logical_id).unwrap().id(), reqrep_id_2);
+        }
+
+        // WHEN: both clients are unhealthy
+        assert!(super::mark_unhealthy(reqrep_id_2, Duration::from_secs(60)));
+        // THEN: there is no client to route to
+        assert!(super::client_balanced(logical_id).is_none());
+
+        // WHEN: client #1 is marked healthy again
+        assert!(super::mark_healthy(reqrep_id_1));
+        // THEN: the balancer routes to client #1
+        assert_eq!(super::client_balanced(logical_id).unwrap().id(), reqrep_id_1);
+
+        // WHEN: client #1's recent requests have mostly failed
+        assert!(super::mark_healthy(reqrep_id_2));
+        {
+            let client_contexts = CLIENT_CONTEXTS.read();
+            for _ in 0..HEALTH_MIN_SAMPLES {
+                client_contexts[&reqrep_id_1].health.record(false);
+                client_contexts[&reqrep_id_2].health.record(true);
+            }
+        }
+        assert_eq!(super::success_rate(reqrep_id_1), Some(0.0));
+        // THEN: client #1 is skipped because its success rate is too low
+        assert_eq!(super::client_balanced(logical_id).unwrap().id(), reqrep_id_2);
+
+        // WHEN: client #2 is unregistered
+        assert!(super::unregister_client(reqrep_id_2).is_some());
+        assert!(super::remove_balanced_client(logical_id, reqrep_id_2));
+        assert!(!super::remove_balanced_client(logical_id, reqrep_id_2));
+        // THEN: no healthy client is left
+        assert!(super::client_balanced(logical_id).is_none());
+
+        assert!(super::remove_balanced_client(logical_id, reqrep_id_1));
+        assert!(super::balanced_client_ids(logical_id).is_empty());
+        assert!(super::unregister_client(reqrep_id_1).is_some());
+    }
+
+    #[test]
+    fn context_recreated_after_consecutive_failures() {
+        configure_logging();
+        let mut executor = execution::ExecutorBuilder::new(ExecutorId::generate())
+            .register()
+            .unwrap();
+
+        // the first SLOW_REQUEST_COUNT requests are slower than the client's recv timeout
+        const SLOW_REQUEST_COUNT: usize = 2;
+        struct FlakyEchoService(Arc<AtomicUsize>);
+        impl Processor<nng::Message, nng::Message> for FlakyEchoService {
+            fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+                let request_count = self.0.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if request_count < SLOW_REQUEST_COUNT {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    req
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a server that is slow to reply to the first requests
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let server_reqrep = ReqRepConfig::new(reqrep_id, timer_buckets.clone())
+            .start_service(
+                FlakyEchoService(Arc::new(AtomicUsize::new(0))),
+                global_executor(),
+            )
+            .unwrap();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            server_reqrep,
+            global_executor(),
+        )
+        .unwrap();
+        assert!(server_handle.ping());
+
+        // GIVEN: a client with a recv timeout that is shorter than the slow replies, and whose
+        // Aio Context is recreated after 2 consecutive failures
+        let client = super::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets),
+            Some(super::SocketConfig::default().set_socket_config(
+                SocketConfig::default().set_recv_timeout(Duration::from_millis(20)),
+            )),
+            DialerConfig::new(url.clone())
+                .set_parallelism(NonZeroUsize::new(1).unwrap())
+                .set_max_consecutive_context_failures(
+                    NonZeroUsize::new(SLOW_REQUEST_COUNT).unwrap(),
+                ),
+            executor.clone(),
+        )
+        .unwrap();
+
+        // WHEN: consecutive requests fail
+        for _ in 0..SLOW_REQUEST_COUNT {
+            let mut client = client.clone();
+            let reply =
+                executor.run(async move { await!(client.send_recv(nng::Message::new().unwrap())) });
+            match reply.unwrap() {
+                Err(RequestError::RecvFailed(_)) => (),
+                other => panic!("expected RequestError::RecvFailed, but got: {:?}", other),
+            }
+        }
+        // THEN: the Aio Context is recreated
+        assert_eq!(super::context_recreate_count(reqrep_id), 1);
+
+        // WHEN: the server has caught up with the slow requests
+        thread::sleep(Duration::from_millis(250));
+        // THEN: the recreated Aio Context successfully serves requests
+        let mut client = client.clone();
+        let reply =
+            executor.run(async move { await!(client.send_recv(nng::Message::new().unwrap())) });
+        assert!(reply.unwrap().is_ok());
+        assert_eq!(super::context_recreate_count(reqrep_id), 1);
+
+        assert!(super::unregister_client(reqrep_id).is_some());
+    }
+
+    #[test]
+    fn aio_recv_timeout() {
+        configure_logging();
+        let mut executor = global_executor();
+        let reqrep_id = ReqRepId::generate();
+        let url = url::Url::parse(&format!("inproc://{}", reqrep_id)).unwrap();
+        let timer_buckets = metrics::timer_buckets(vec![Duration::from_millis(100)]).unwrap();
+
+        // GIVEN: a peer that never replies
+        let peer = nng::Socket::new(nng::Protocol::Rep0).unwrap();
+        peer.listen(url.as_str()).unwrap();
+
+        // AND: a client that is configured with a default Aio recv timeout
+        const AIO_RECV_TIMEOUT: Duration = Duration::from_millis(50);
+        let client = super::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets),
+            Some(super::SocketConfig::default().set_socket_config(
+                SocketConfig::default().set_aio_recv_timeout(AIO_RECV_TIMEOUT),
+            )),
+            DialerConfig::new(url.clone()),
+            executor.clone(),
+        )
+        .unwrap();
+
+        // WHEN: the client sends a request
+        let start = Instant::now();
+        let mut client = client.clone();
+        let reply =
+            executor.run(async move { await!(client.send_recv(nng::Message::new().unwrap())) });
+        // THEN: the Aio recv operation times out at the configured default
+        match reply.unwrap() {
+            Err(RequestError::RecvFailed(nng::Error::TimedOut)) => (),
+            other => panic!("expected RequestError::RecvFailed(TimedOut), but got: {:?}", other),
+        }
+        let elapsed = start.elapsed();
+        info!("request timed out after {:?}", elapsed);
+        assert!(elapsed >= AIO_RECV_TIMEOUT);
+        assert!(elapsed < Duration::from_secs(1));
+
+        assert!(super::unregister_client(reqrep_id).is_some());
+    }
+
+    #[test]
+    fn start_dialer_with_parallelism_too_high() {
+        configure_logging();
+
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let socket = super::SocketConfig::create_socket(None).unwrap();
+        // GIVEN: a DialerConfig with an absurd parallelism
+        let parallelism = crate::config::max_aio_contexts() * 1000;
+        let dialer_config =
+            DialerConfig::new(url).set_parallelism(NonZeroUsize::new(parallelism).unwrap());
+        // WHEN: the dialer is started
+        match dialer_config.start_dialer(&socket) {
+            // THEN: the dialer fails to start with a clear error
+            Err(DialerConfigError::ParallelismTooHigh {
+                parallelism: p,
+                max,
+            }) => {
+                assert_eq!(p, parallelism);
+                assert_eq!(max, crate::config::max_aio_contexts());
+            }
+            Err(err) => panic!("expected DialerConfigError::ParallelismTooHigh, but got: {}", err),
+            Ok(_) => panic!("expected DialerConfigError::ParallelismTooHigh"),
+        }
+    }
+
+    #[test]
+    fn get_or_register_client() {
+        configure_logging();
+
+        let reqrep_id = ReqRepId::generate();
+        let url = url::Url::parse(&format!("inproc://{}", reqrep_id)).unwrap();
+        let timer_buckets = || {
+            metrics::timer_buckets(vec![Duration::from_nanos(50), Duration::from_nanos(100)])
+                .unwrap()
+        };
+
+        // WHEN: the client is registered for the first time
+        let (client, registration) = super::get_or_register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets()),
+            None,
+            DialerConfig::new(url.clone()),
+            global_executor(),
+        )
+        .unwrap();
+        // THEN: the client is created
+        assert_eq!(registration, Registration::Created);
+        assert_eq!(client.id(), reqrep_id);
+        assert!(super::client(reqrep_id).is_some());
+
+        // WHEN: the client is registered again using the same ReqRepId
+        let (client, registration) = super::get_or_register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets()),
+            None,
+            DialerConfig::new(url.clone()),
+            global_executor(),
+        )
+        .unwrap();
+        // THEN: the existing client is returned
+        assert_eq!(registration, Registration::Existing);
+        assert_eq!(client.id(), reqrep_id);
+        // AND: register_client fails because the client is already registered
+        match super::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets()),
+            None,
+            DialerConfig::new(url.clone()),
+            global_executor(),
+        ) {
+            Err(ClientRegistrationError::ClientAlreadyRegistered(id)) => assert_eq!(id, reqrep_id),
+            other => panic!("expected ClientAlreadyRegistered, but got: {:?}", other),
+        }
+
+        assert!(super::unregister_client(reqrep_id).is_some());
+    }
+
     #[test]
     fn dialer_config_reconnect_time_min_max() {
         configure_logging();
@@ -1092,4 +2626,312 @@ mod tests {
             .unwrap();
         info!("reply = {:?}", reply.unwrap());
     }
+
+    #[test]
+    fn dialer_config_version_0_migration() {
+        // GIVEN: a DialerConfig that was persisted before versioning was introduced
+        let json = r#"{
+            "url": "tcp://127.0.0.1:5964",
+            "parallelism": 4,
+            "recv_max_size": null,
+            "no_delay": null,
+            "keep_alive": null,
+            "reconnect_min_time": null,
+            "reconnect_max_time": null
+        }"#;
+
+        // WHEN: it is deserialized
+        let dialer_config: DialerConfig = serde_json::from_str(json).unwrap();
+        // THEN: the fields that were added since are defaulted
+        assert_eq!(
+            dialer_config.max_consecutive_context_failures(),
+            DialerConfig::DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES
+        );
+        assert_eq!(dialer_config.parallelism(), 4);
+        assert!(!dialer_config.pre_dial());
+
+        // GIVEN: a DialerConfig with max_consecutive_context_failures = 0
+        let json = r#"{
+            "url": "tcp://127.0.0.1:5964",
+            "parallelism": 4,
+            "recv_max_size": null,
+            "no_delay": null,
+            "keep_alive": null,
+            "reconnect_min_time": null,
+            "reconnect_max_time": null,
+            "max_consecutive_context_failures": 0
+        }"#;
+        // THEN: it is rejected when it is deserialized
+        assert!(serde_json::from_str::<DialerConfig>(json).is_err());
+    }
+
+    #[test]
+    fn dialer_config_version_0_bincode_migration() {
+        // GIVEN: DialerConfig bincode bytes that were persisted before versioning was introduced
+        let bytes: &[u8] = &[
+            // url = "tcp://127.0.0.1:5964"
+            20, 0, 0, 0, 0, 0, 0, 0, b't', b'c', b'p', b':', b'/', b'/', b'1', b'2', b'7', b'.',
+            b'0', b'.', b'0', b'.', b'1', b':', b'5', b'9', b'6', b'4',
+            // parallelism = 4
+            4, 0, 0, 0, 0, 0, 0, 0,
+            // recv_max_size = Some(1024)
+            1, 0, 4, 0, 0, 0, 0, 0, 0,
+            // no_delay = Some(true)
+            1, 1,
+            // keep_alive = None
+            0,
+            // reconnect_min_time = Some(1 sec)
+            1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            // reconnect_max_time = None
+            0,
+        ];
+
+        // WHEN: the bytes are deserialized using the version 0 schema
+        let config_v0 = bincode::deserialize::<DialerConfigV0>(bytes).unwrap();
+        // THEN: the version 0 schema consumes all of the bytes, i.e., it matches the released
+        // layout
+        assert_eq!(bincode::serialize(&config_v0).unwrap(), bytes);
+        let versioned_config = VersionedDialerConfig::from(config_v0);
+        assert_eq!(versioned_config.version(), 0);
+        // AND: the config is migrated
+        let dialer_config = versioned_config.migrate();
+        // THEN: the persisted settings are loaded
+        assert_eq!(dialer_config.url().as_str(), "tcp://127.0.0.1:5964");
+        assert_eq!(dialer_config.parallelism(), 4);
+        assert_eq!(dialer_config.recv_max_size(), Some(1024));
+        assert_eq!(dialer_config.no_delay(), Some(true));
+        assert_eq!(dialer_config.keep_alive(), None);
+        assert_eq!(dialer_config.reconnect_min_time(), Some(Duration::from_secs(1)));
+        assert_eq!(dialer_config.reconnect_max_time(), None);
+        // AND: the fields that were added since are defaulted
+        assert_eq!(
+            dialer_config.max_consecutive_context_failures(),
+            DialerConfig::DEFAULT_MAX_CONSECUTIVE_CONTEXT_FAILURES
+        );
+        assert!(!dialer_config.pre_dial());
+        assert_eq!(
+            dialer_config.destroy_grace_period(),
+            DialerConfig::DEFAULT_DESTROY_GRACE_PERIOD
+        );
+
+        // GIVEN: the version 0 config is wrapped in the versioned envelope
+        let mut versioned_bytes = 0_u32.to_le_bytes().to_vec();
+        versioned_bytes.extend_from_slice(bytes);
+        // THEN: it loads as version 0
+        let versioned_config: VersionedDialerConfig =
+            bincode::deserialize(&versioned_bytes).unwrap();
+        assert_eq!(versioned_config.version(), 0);
+        assert_eq!(versioned_config.migrate().recv_max_size(), Some(1024));
+
+        // WHEN: the current config is persisted via the versioned envelope
+        let dialer_config = dialer_config.set_pre_dial(true);
+        let bytes = bincode::serialize(&VersionedDialerConfig::from(dialer_config)).unwrap();
+        // THEN: it round trips as the current version
+        let versioned_config: VersionedDialerConfig = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(versioned_config.version(), config::CONFIG_VERSION);
+        let dialer_config = versioned_config.migrate();
+        assert!(dialer_config.pre_dial());
+        assert_eq!(dialer_config.recv_max_size(), Some(1024));
+    }
+
+    #[test]
+    fn nng_client_warm_up() {
+        configure_logging();
+        let mut executor = execution::global_executor();
+
+        // GIVEN: the server is running
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let server_reqrep = start_server();
+        let reqrep_id = server_reqrep.id();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            server_reqrep,
+            execution::ExecutorBuilder::new(ExecutorId::generate())
+                .register()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(server_handle.ping());
+
+        // GIVEN: the client is registered with pre_dial enabled
+        let (mut client, _client_executor_id) = start_client_with_dialer_config(
+            reqrep_id,
+            DialerConfig::new(url.clone()).set_pre_dial(true),
+        );
+        // THEN: the client is connected as soon as it is registered
+        assert_eq!(super::connection_count(reqrep_id), Some(1));
+
+        // WHEN: the client is warmed up with a ping request
+        super::warm_up(
+            reqrep_id,
+            Some(nng::Message::new().unwrap()),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        // THEN: the first request latency is comparable to subsequent requests
+        const REQUEST_COUNT: usize = 100;
+        let latencies: Vec<Duration> = executor.run(
+            async move {
+                let mut latencies = Vec::with_capacity(REQUEST_COUNT);
+                for _ in 0..REQUEST_COUNT {
+                    let start = Instant::now();
+                    await!(client.send_recv(nng::Message::new().unwrap()))
+                        .unwrap()
+                        .unwrap();
+                    latencies.push(start.elapsed());
+                }
+                latencies
+            },
+        );
+        let first = latencies[0];
+        let max_subsequent = latencies.iter().skip(1).max().cloned().unwrap();
+        info!(
+            "first request latency = {:?}, max subsequent request latency = {:?}",
+            first, max_subsequent
+        );
+        assert!(first <= max_subsequent * 2 + Duration::from_millis(1));
+
+        // WHEN: warming up a client that is not registered
+        match super::warm_up(ReqRepId::generate(), None, Duration::from_millis(10)) {
+            // THEN: the warm up fails
+            Err(WarmUpError::ClientNotRegistered(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let _ = super::unregister_client(reqrep_id);
+    }
+
+    #[test]
+    fn nng_client_service_wire_error() {
+        configure_logging();
+        let mut executor = execution::global_executor();
+
+        const INVALID_ORDER: (oysterpack_errors::Id, oysterpack_errors::Level) = (
+            oysterpack_errors::Id(1880005529042231405173013421125435805),
+            oysterpack_errors::Level::Error,
+        );
+
+        struct OrderService;
+        impl Processor<nng::Message, nng::Message> for OrderService {
+            fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+                async move {
+                    let err = oysterpack_errors::op_error!(INVALID_ORDER, "quantity must be > 0");
+                    server::WireError::from(&err).encode().unwrap()
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a server whose service fails with a known error
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let service = ReqRepConfig::new(reqrep_id, timer_buckets)
+            .start_service(OrderService, global_executor())
+            .unwrap();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            service,
+            global_executor(),
+        )
+        .unwrap();
+
+        // GIVEN: a client that connects to the server
+        let (mut client, _client_executor_id) = start_client(reqrep_id, url.clone());
+
+        // WHEN: the client submits a request
+        let reply = executor.run(
+            async move { await!(client.send_recv(nng::Message::new().unwrap())).unwrap() },
+        );
+        // THEN: the client decodes the error reply
+        match reply {
+            Err(RequestError::Service(err)) => {
+                info!("{}", err);
+                // AND: the error id matches
+                assert_eq!(err.error_id(), INVALID_ORDER.0);
+                assert_eq!(err.level(), INVALID_ORDER.1);
+                assert_eq!(err.message(), "quantity must be > 0");
+            }
+            other => panic!("expected RequestError::Service, but got: {:?}", other),
+        }
+
+        // THEN: non WireError messages are not decoded as a WireError
+        assert!(server::WireError::decode(&nng::Message::new().unwrap()).is_none());
+        assert!(server::WireError::decode_reply(&nng::Message::new().unwrap()).is_none());
+
+        // GIVEN: a ServiceError reply
+        let service_error = server::ServiceError::new(reqrep_id, "service failed".to_string());
+        // WHEN: the reply is decoded by the client
+        let err = server::WireError::decode_reply(&service_error.encode().unwrap()).unwrap();
+        // THEN: it is mapped to a WireError
+        assert_eq!(err.error_id(), server::SERVICE_ERROR_ID);
+        assert_eq!(err.level(), oysterpack_errors::Level::Error);
+        assert_eq!(err.reqrep_id(), Some(reqrep_id));
+        assert_eq!(err.message(), "service failed");
+
+        let _ = super::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn nng_client_connection_events() {
+        configure_logging();
+        let mut executor = execution::global_executor();
+        let server_executor = || {
+            execution::ExecutorBuilder::new(ExecutorId::generate())
+                .register()
+                .unwrap()
+        };
+
+        // GIVEN: the server is running
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let server_reqrep = start_server();
+        let reqrep_id = server_reqrep.id();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            server_reqrep.clone(),
+            server_executor(),
+        )
+        .unwrap();
+        assert!(server_handle.ping());
+
+        // GIVEN: a connected client that is subscribed to connection events
+        let (_client, _client_executor_id) = start_client_with_dialer_config(
+            reqrep_id,
+            DialerConfig::new(url.clone()).set_pre_dial(true),
+        );
+        assert_eq!(super::connection_count(reqrep_id), Some(1));
+        let mut connection_events = super::connection_events(reqrep_id).unwrap();
+
+        // WHEN: the server is bounced
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            server_reqrep,
+            server_executor(),
+        )
+        .unwrap();
+
+        // THEN: a Disconnected event followed by a Connected event is received
+        assert_eq!(executor.run(connection_events.next()), Some(ConnectionEvent::Disconnected));
+        assert_eq!(executor.run(connection_events.next()), Some(ConnectionEvent::Connected));
+        assert_eq!(super::connection_count(reqrep_id), Some(1));
+
+        // WHEN: subscribing to a client that is not registered
+        // THEN: None is returned
+        assert!(super::connection_events(ReqRepId::generate()).is_none());
+
+        let _ = super::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
 }
@@ -0,0 +1,337 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides request coalescing for nng [Client(s)](../client/type.Client.html).
+//!
+//! When many callers send the identical request while the same request is already in flight, e.g.,
+//! after a cache miss, then the [Coalescer](struct.Coalescer.html) lets them share the single backend
+//! call. The first caller sends the request, and the reply is fanned out to all callers that are
+//! waiting on the same request.
+//! - requests are keyed by their [content_hash()](fn.content_hash.html)
+//! - requests are only coalesced when their content is identical - if the content hash collides with
+//!   an in-flight request that has different content, then the request is sent on its own
+//! - requests are coalesced only while they are in flight, i.e., replies are not cached
+
+use super::client::{Client, RequestError};
+use futures::channel::oneshot;
+use hashbrown::HashMap;
+use oysterpack_log::*;
+use oysterpack_trust::concurrent::messaging::{errors::ChannelError, reqrep::ReqRepId};
+use parking_lot::Mutex;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+/// Coalescer reply type
+pub type CoalescedReply = Result<Result<nng::Message, RequestError>, ChannelError>;
+
+/// Returns the hash of the message body, which is used to key in-flight requests
+pub fn content_hash(msg: &nng::Message) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    msg[..].hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Wraps a [Client](../client/type.Client.html) and deduplicates concurrent identical requests.
+///
+/// Coalescer clones share the same in-flight request table, i.e., requests sent via any clone are
+/// coalesced together.
+#[derive(Clone)]
+pub struct Coalescer {
+    client: Client,
+    in_flight: Arc<Mutex<HashMap<u64, InFlightRequest>>>,
+}
+
+/// A request that is in flight along with the callers that are waiting on its reply
+struct InFlightRequest {
+    request: Vec<u8>,
+    waiters: Vec<oneshot::Sender<CoalescedReply>>,
+}
+
+/// Determines how the request is sent
+enum Role {
+    /// sends the request and fans out the reply to the waiters
+    Leader,
+    /// waits for the leader's reply
+    Waiter(oneshot::Receiver<CoalescedReply>),
+    /// the content hash collided with an in-flight request with different content
+    Bypass,
+}
+
+impl Coalescer {
+    /// constructor
+    pub fn new(client: Client) -> Coalescer {
+        Coalescer {
+            client,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the wrapped Client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Returns the client ReqRepId
+    pub fn id(&self) -> ReqRepId {
+        self.client.id()
+    }
+
+    /// Returns the number of distinct requests that are currently in flight
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.lock().len()
+    }
+
+    /// Sends the request and awaits to receive the reply.
+    /// - if an identical request is already in flight, then the in-flight request's reply is shared
+    pub async fn send_recv(&mut self, req: nng::Message) -> CoalescedReply {
+        let key = content_hash(&req);
+        let role = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get_mut(&key) {
+                Some(in_flight_request) => {
+                    if in_flight_request.request[..] == req[..] {
+                        let (tx, rx) = oneshot::channel();
+                        in_flight_request.waiters.push(tx);
+                        Role::Waiter(rx)
+                    } else {
+                        Role::Bypass
+                    }
+                }
+                None => {
+                    in_flight.insert(
+                        key,
+                        InFlightRequest {
+                            request: req[..].to_vec(),
+                            waiters: Vec::new(),
+                        },
+                    );
+                    Role::Leader
+                }
+            }
+        };
+
+        match role {
+            Role::Waiter(rx) => await!(rx)?,
+            Role::Bypass => await!(self.client.send_recv(req)),
+            Role::Leader => {
+                // ensures the in-flight entry is removed even if this future is dropped before the
+                // reply is received - the waiters will then fail with a ChannelError
+                let mut guard = InFlightGuard {
+                    key,
+                    in_flight: self.in_flight.clone(),
+                };
+                let reply = await!(self.client.send_recv(req));
+                let waiters = guard.remove();
+                if !waiters.is_empty() {
+                    debug!(
+                        "ReqRepId({}) coalesced reply is being sent to {} waiters",
+                        self.client.id(),
+                        waiters.len()
+                    );
+                }
+                for waiter in waiters {
+                    let _ = waiter.send(copy_reply(&reply));
+                }
+                reply
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Coalescer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Coalescer(ReqRepId({}), in_flight_count = {})",
+            self.client.id(),
+            self.in_flight_count()
+        )
+    }
+}
+
+/// Removes the in-flight request entry when dropped
+struct InFlightGuard {
+    key: u64,
+    in_flight: Arc<Mutex<HashMap<u64, InFlightRequest>>>,
+}
+
+impl InFlightGuard {
+    /// removes the in-flight request and returns its waiters
+    fn remove(&mut self) -> Vec<oneshot::Sender<CoalescedReply>> {
+        self.in_flight
+            .lock()
+            .remove(&self.key)
+            .map(|in_flight_request| in_flight_request.waiters)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let _ = self.remove();
+    }
+}
+
+/// nng::Message is not Clone, thus each waiter is sent a copy of the reply message
+fn copy_reply(reply: &CoalescedReply) -> CoalescedReply {
+    match reply {
+        Ok(Ok(msg)) => {
+            let copy = nng::Message::with_capacity(msg.len()).and_then(|mut copy| {
+                copy.push_back(&msg[..])?;
+                Ok(copy)
+            });
+            Ok(copy.map_err(RequestError::RecvFailed))
+        }
+        Ok(Err(err)) => Ok(Err(err.clone())),
+        Err(err) => Err(*err),
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        configure_logging,
+        reqrep::{client, server},
+    };
+    use futures::{future::FutureExt, task::SpawnExt};
+    use oysterpack_trust::{
+        concurrent::{
+            execution::{self, *},
+            messaging::reqrep::{self, *},
+        },
+        metrics,
+    };
+    use oysterpack_uid::ULID;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[derive(Clone)]
+    struct SlowEchoService(Arc<AtomicUsize>);
+
+    impl Processor<nng::Message, nng::Message> for SlowEchoService {
+        fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            async move {
+                // gives the concurrent requests time to queue up behind the in-flight request
+                thread::sleep(Duration::from_millis(200));
+                req
+            }
+                .boxed()
+        }
+    }
+
+    #[test]
+    fn coalescer() {
+        configure_logging();
+        let mut executor = execution::global_executor();
+        let timer_buckets = || {
+            metrics::timer_buckets(vec![Duration::from_nanos(50), Duration::from_nanos(100)])
+                .unwrap()
+        };
+
+        // GIVEN: a server whose backend counts the number of times it is invoked
+        let invocation_count = Arc::new(AtomicUsize::new(0));
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let service = ReqRepConfig::new(reqrep_id, timer_buckets())
+            .start_service(SlowEchoService(invocation_count.clone()), executor.clone())
+            .unwrap();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            service,
+            executor.clone(),
+        )
+        .unwrap();
+        assert!(server_handle.ping());
+
+        // GIVEN: a Coalescer that wraps the client
+        let client = client::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets()),
+            None,
+            client::DialerConfig::new(url.clone()).set_pre_dial(true),
+            execution::ExecutorBuilder::new(ExecutorId::generate())
+                .register()
+                .unwrap(),
+        )
+        .unwrap();
+        let coalescer = Coalescer::new(client);
+
+        // WHEN: 50 identical requests are sent concurrently
+        const REQUEST_COUNT: usize = 50;
+        const REQUEST: &[u8] = b"GET /cached/resource";
+        let mut handles = Vec::with_capacity(REQUEST_COUNT);
+        for _ in 0..REQUEST_COUNT {
+            let mut coalescer = coalescer.clone();
+            let handle = executor
+                .spawn_with_handle(
+                    async move {
+                        let mut req = nng::Message::with_capacity(REQUEST.len()).unwrap();
+                        req.push_back(REQUEST).unwrap();
+                        await!(coalescer.send_recv(req))
+                    },
+                )
+                .unwrap();
+            handles.push(handle);
+        }
+        let replies: Vec<CoalescedReply> = executor.run(
+            async move {
+                let mut replies = Vec::with_capacity(REQUEST_COUNT);
+                for handle in handles {
+                    replies.push(await!(handle));
+                }
+                replies
+            },
+        );
+
+        // THEN: the backend was invoked once
+        assert_eq!(invocation_count.load(Ordering::SeqCst), 1);
+        // AND: all requests received the reply
+        assert_eq!(replies.len(), REQUEST_COUNT);
+        for reply in replies {
+            let reply = reply.unwrap().unwrap();
+            assert_eq!(&reply[..], REQUEST);
+        }
+        // AND: there are no requests in flight
+        assert_eq!(coalescer.in_flight_count(), 0);
+
+        // WHEN: the same request is sent again after the reply was received
+        let mut coalescer_clone = coalescer.clone();
+        let reply = executor.run(
+            async move {
+                let mut req = nng::Message::with_capacity(REQUEST.len()).unwrap();
+                req.push_back(REQUEST).unwrap();
+                await!(coalescer_clone.send_recv(req))
+            },
+        );
+        // THEN: the backend is invoked again, i.e., replies are not cached
+        assert_eq!(&reply.unwrap().unwrap()[..], REQUEST);
+        assert_eq!(invocation_count.load(Ordering::SeqCst), 2);
+
+        let _ = client::unregister_client(reqrep_id);
+        assert!(server_handle.stop_async().unwrap());
+        server_handle.await_shutdown();
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides request timing support for end-to-end latency attribution.
+//!
+//! A [RequestContext](struct.RequestContext.html) is created when a request is received, and records
+//! how long the request spent in each processing [Stage](enum.Stage.html) as it flows through the
+//! message pipeline. When the request is complete, the timings are logged and recorded into the
+//! [REQUEST_STAGE_DURATION_METRIC_ID](constant.REQUEST_STAGE_DURATION_METRIC_ID.html) histogram.
+
+use super::server::REQREP_LABEL_ID;
+use lazy_static::lazy_static;
+use oysterpack_core::message::{InstanceId, SessionId};
+use oysterpack_log::*;
+use oysterpack_trust::{concurrent::messaging::reqrep::ReqRepId, metrics};
+use std::{
+    fmt,
+    time::{Duration, Instant, SystemTime},
+};
+
+lazy_static! {
+    /// request processing durations by stage
+    static ref REQUEST_STAGE_DURATION: prometheus::HistogramVec = metrics::registry().register_histogram_vec(
+        REQUEST_STAGE_DURATION_METRIC_ID,
+        "Request processing stage duration",
+        &[REQREP_LABEL_ID, STAGE_LABEL_ID],
+        metrics::timer_buckets(vec![
+            Duration::from_micros(10),
+            Duration::from_micros(100),
+            Duration::from_millis(1),
+            Duration::from_millis(10),
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+        ]).unwrap(),
+        None
+    ).unwrap();
+}
+
+/// HistogramVec MetricId which is used to track request processing durations by ReqRepId and
+/// [Stage](enum.Stage.html): `M01D88GBZ6XWE3HBCRZ9D0G7M0K`
+pub const REQUEST_STAGE_DURATION_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1879956738152151801426414372378628115);
+
+/// Metric LabelId which is used to store the request processing [Stage](enum.Stage.html): `L01D88H71ZES7QB6QZYDFCYXPZR`
+pub const STAGE_LABEL_ID: metrics::LabelId =
+    metrics::LabelId(1879957811157111720811002609519221752);
+
+/// Request processing stages
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Stage {
+    /// the request is opened and decoded
+    Decode,
+    /// the request is dispatched to the service, i.e., the service is processing the request
+    Dispatch,
+    /// the reply is encoded and sealed
+    Encode,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Decode => f.write_str("Decode"),
+            Stage::Dispatch => f.write_str("Dispatch"),
+            Stage::Encode => f.write_str("Encode"),
+        }
+    }
+}
+
+/// Tracks the timings for a single request as it flows through the message pipeline
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    received_on: SystemTime,
+    start: Instant,
+    instance_id: Option<InstanceId>,
+    session_id: Option<SessionId>,
+    timings: Vec<(Stage, Duration)>,
+}
+
+impl RequestContext {
+    /// constructor - should be invoked when the request is received
+    pub fn new() -> RequestContext {
+        RequestContext {
+            received_on: SystemTime::now(),
+            start: Instant::now(),
+            instance_id: None,
+            session_id: None,
+            timings: Vec::with_capacity(3),
+        }
+    }
+
+    /// Ingress timestamp, i.e., when the request was received
+    pub fn received_on(&self) -> SystemTime {
+        self.received_on
+    }
+
+    /// The request message InstanceId
+    /// - None if the request has not been decoded yet, or failed to be decoded
+    pub fn instance_id(&self) -> Option<InstanceId> {
+        self.instance_id
+    }
+
+    /// Sets the request message InstanceId once the request has been decoded
+    pub fn set_instance_id(&mut self, instance_id: InstanceId) {
+        self.instance_id = Some(instance_id);
+    }
+
+    /// The SessionId that was assigned to the connection the request was received on
+    /// - None if the request was not received via an nng server connection
+    pub fn session_id(&self) -> Option<SessionId> {
+        self.session_id
+    }
+
+    /// Sets the connection SessionId
+    pub fn set_session_id(&mut self, session_id: SessionId) {
+        self.session_id = Some(session_id);
+    }
+
+    /// Returns the time spent in the specified stage
+    pub fn timing(&self, stage: Stage) -> Option<Duration> {
+        self.timings
+            .iter()
+            .find(|(s, _)| *s == stage)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// Returns the recorded stage timings in the order they were recorded
+    pub fn timings(&self) -> &[(Stage, Duration)] {
+        &self.timings
+    }
+
+    /// Time elapsed since the request was received
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Records the time spent in the specified stage
+    pub fn record(&mut self, stage: Stage, duration: Duration) {
+        self.timings.push((stage, duration));
+    }
+
+    /// Runs the function and records its duration against the specified stage
+    pub fn time<F, T>(&mut self, stage: Stage, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    /// Marks the request as complete
+    /// - the stage timings are recorded into the [REQUEST_STAGE_DURATION_METRIC_ID](constant.REQUEST_STAGE_DURATION_METRIC_ID.html) histogram
+    /// - the timing breakdown is logged
+    pub fn complete(&self, reqrep_id: ReqRepId) {
+        let reqrep_id_label = reqrep_id.to_string();
+        for (stage, duration) in self.timings.iter() {
+            REQUEST_STAGE_DURATION
+                .with_label_values(&[reqrep_id_label.as_str(), stage.to_string().as_str()])
+                .observe(metrics::duration_as_secs_f64(*duration));
+        }
+        debug!(
+            "ReqRepId({}) request complete: instance_id = {:?}, session_id = {:?}, total = {:?}, timings = {:?}",
+            reqrep_id,
+            self.instance_id,
+            self.session_id,
+            self.elapsed(),
+            self.timings
+        );
+    }
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        RequestContext::new()
+    }
+}
@@ -0,0 +1,343 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides an append-only message journal.
+//!
+//! Inbound messages are appended to the [Journal](struct.Journal.html) before they are processed,
+//! and acked once processing is complete. On restart, the messages that were never acked can be
+//! replayed via [Journal::replay_unacked()](struct.Journal.html#method.replay_unacked).
+//!
+//! ## Journal Format
+//! The journal file is a sequence of length prefixed frames: `| u32 BE length | bincode entry |`,
+//! i.e., the core [framing](../../../oysterpack_core/message/framing/index.html) format,
+//! where each entry is either an appended message or an ack for a message [InstanceId](../../../oysterpack_core/message/struct.InstanceId.html).
+//! - a truncated frame at the end of the journal, i.e., a write that was interrupted by a crash,
+//!   marks the end of the journal. The message was never acked, but it was also never fully
+//!   persisted, which means it was never processed.
+//! - the journal is never compacted, i.e., it grows until it is removed
+
+use failure::Fail;
+use futures::{channel::mpsc, executor, sink::SinkExt, stream::Stream};
+use hashbrown::HashSet;
+use oysterpack_core::message::{
+    framing::{self, read_framed, write_framed},
+    BincodeOptions, EncodedMessage, Encoding, InstanceId,
+};
+use oysterpack_log::*;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// Max number of replayed messages that are buffered ahead of the replay stream consumer
+pub const REPLAY_BUFFER_SIZE: usize = 64;
+
+/// Journal entry
+#[derive(Debug, Serialize, Deserialize)]
+enum Entry<'a> {
+    Message(Cow<'a, EncodedMessage>),
+    Ack(InstanceId),
+}
+
+/// Append-only message journal
+/// - entries are synced to disk before `append()` and `ack()` return
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Opens the journal at the specified path for appending
+    /// - the journal file is created if it does not exist
+    /// - a truncated frame at the end of the journal is removed, i.e., new entries are appended
+    ///   after the last complete frame
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Journal, JournalError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(JournalError::Io)?;
+        let len = complete_frames_len(&file).map_err(JournalError::Io)?;
+        if len < file.metadata().map_err(JournalError::Io)?.len() {
+            warn!("Removing truncated frame from the end of the journal: {}", path.display());
+            file.set_len(len).map_err(JournalError::Io)?;
+        }
+        Ok(Journal {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Returns the journal file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the message to the journal
+    /// - the message is persisted when this returns, i.e., it is safe to process the message
+    pub fn append(&self, msg: &EncodedMessage) -> Result<(), JournalError> {
+        self.write_entry(&Entry::Message(Cow::Borrowed(msg)))
+    }
+
+    /// Acks the message, i.e., the message will no longer be replayed
+    pub fn ack(&self, instance_id: InstanceId) -> Result<(), JournalError> {
+        self.write_entry(&Entry::Ack(instance_id))
+    }
+
+    /// Replays the messages that have not been acked, in the order they were appended
+    /// - the messages are streamed, i.e., at most [REPLAY_BUFFER_SIZE](constant.REPLAY_BUFFER_SIZE.html)
+    ///   messages are buffered ahead of the consumer
+    /// - the journal file IO is blocking, and is thus run on a dedicated replay thread, i.e., it
+    ///   does not tie up Executor threads. Replay is meant to be run once on startup.
+    /// - only the entries that were in the journal when the replay started are replayed
+    /// - if the journal fails to be read, then the error is logged and the stream ends
+    pub fn replay_unacked(&self) -> impl Stream<Item = EncodedMessage> {
+        let (tx, rx) = mpsc::channel(REPLAY_BUFFER_SIZE);
+        // the journal length is captured under the file lock, i.e., it does not race appends
+        let len = match self.file.lock().metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(err) => {
+                error!("Journal replay failed ({}): {}", self.path.display(), err);
+                return rx;
+            }
+        };
+        let path = self.path.clone();
+        let spawn_result = thread::Builder::new()
+            .name("oysterpack-journal-replay".to_string())
+            .spawn(move || {
+                if let Err(err) = replay_unacked(&path, len, tx) {
+                    error!("Journal replay failed ({}): {}", path.display(), err);
+                }
+            });
+        if let Err(err) = spawn_result {
+            error!("Failed to spawn journal replay thread: {}", err);
+        }
+        rx
+    }
+
+    fn write_entry(&self, entry: &Entry) -> Result<(), JournalError> {
+        let bytes = Encoding::Bincode(None, BincodeOptions::default())
+            .encode(entry)
+            .map_err(|err| JournalError::Encoding(err.to_string()))?;
+        let mut file = self.file.lock();
+        write_framed(&mut *file, &bytes)
+            .and_then(|_| file.sync_data())
+            .map_err(JournalError::Io)
+    }
+}
+
+impl fmt::Debug for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Journal").field("path", &self.path).finish()
+    }
+}
+
+/// Replays the unacked messages in append order, using 2 passes over the journal
+/// - the first pass collects the acked message ids
+/// - the second pass streams the messages that were not acked, i.e., the messages are never all
+///   held in memory
+/// - both passes are bounded by the journal length when the replay started, i.e., entries that are
+///   appended during the replay are not replayed
+/// - the replay stops early if the replay stream is dropped
+fn replay_unacked(
+    path: &Path,
+    len: u64,
+    tx: mpsc::Sender<EncodedMessage>,
+) -> Result<(), JournalError> {
+    let mut acked = HashSet::new();
+    for_each_entry(path, len, |entry| {
+        if let Entry::Ack(instance_id) = entry {
+            acked.insert(instance_id);
+        }
+        true
+    })?;
+    let mut tx = tx;
+    for_each_entry(path, len, |entry| match entry {
+        Entry::Message(ref msg) if acked.contains(&msg.metadata().instance_id()) => true,
+        // blocks while the replay buffer is full, i.e., the stream consumer applies back pressure
+        Entry::Message(msg) => executor::block_on(tx.send(msg.into_owned())).is_ok(),
+        Entry::Ack(_) => true,
+    })
+}
+
+/// Reads the journal entries in order until `len` bytes have been read, or until `f` returns false
+/// - a truncated frame marks the end of the journal
+fn for_each_entry<F>(path: &Path, len: u64, mut f: F) -> Result<(), JournalError>
+where
+    F: FnMut(Entry<'static>) -> bool,
+{
+    let mut read = BufReader::new(File::open(path).map_err(JournalError::Io)?).take(len);
+    loop {
+        let frame = match read_framed(&mut read) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(()),
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                warn!("Journal ends with a truncated frame ({}): {}", path.display(), err);
+                return Ok(());
+            }
+            Err(err) => return Err(JournalError::Io(err)),
+        };
+        let entry: Entry<'static> = Encoding::Bincode(None, BincodeOptions::default())
+            .decode(&frame)
+            .map_err(|err| JournalError::Decoding(err.to_string()))?;
+        if !f(entry) {
+            return Ok(());
+        }
+    }
+}
+
+/// Returns the length of the journal up to the end of the last complete frame
+fn complete_frames_len(file: &File) -> io::Result<u64> {
+    let mut read = BufReader::new(file);
+    let mut len = 0;
+    loop {
+        match read_framed(&mut read) {
+            Ok(Some(frame)) => len += (framing::LEN_PREFIX_SIZE + frame.len()) as u64,
+            Ok(None) => return Ok(len),
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(len),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Journal errors
+#[derive(Debug, Fail)]
+pub enum JournalError {
+    /// Journal file IO failed
+    #[fail(display = "Journal IO failed: {}", _0)]
+    Io(#[cause] io::Error),
+    /// Failed to encode a journal entry
+    #[fail(display = "Failed to encode journal entry: {}", _0)]
+    Encoding(String),
+    /// Failed to decode a journal entry
+    #[fail(display = "Failed to decode journal entry: {}", _0)]
+    Decoding(String),
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use futures::stream::StreamExt;
+    use oysterpack_core::message::{Address, Message, Metadata, MessageTypeId};
+    use oysterpack_trust::concurrent::execution::global_executor;
+    use oysterpack_uid::ULID;
+    use sodiumoxide::crypto::box_;
+    use std::fs;
+
+    fn encoded_message(n: u64) -> EncodedMessage {
+        let (sender, _) = box_::gen_keypair();
+        let (recipient, _) = box_::gen_keypair();
+        let metadata = Metadata::new(
+            MessageTypeId(1880055795426936550206608891715775039).message_type(),
+            Encoding::Bincode(None, BincodeOptions::default()),
+            None,
+        );
+        Message::new(metadata, n)
+            .encoded_message(Address::from(sender), Address::from(recipient))
+            .unwrap()
+    }
+
+    #[test]
+    fn journal_replay_unacked() {
+        configure_logging();
+        let mut executor = global_executor();
+        let path = std::env::temp_dir().join(format!("journal-{}", ULID::generate()));
+
+        // GIVEN: a journal with 3 appended messages, where the second message is acked
+        let msgs: Vec<EncodedMessage> = (1..=3).map(encoded_message).collect();
+        {
+            let journal = Journal::open(&path).unwrap();
+            for msg in msgs.iter() {
+                journal.append(msg).unwrap();
+            }
+            journal.ack(msgs[1].metadata().instance_id()).unwrap();
+        }
+
+        // WHEN: the journal is reopened and replayed
+        let journal = Journal::open(&path).unwrap();
+        let replayed: Vec<EncodedMessage> =
+            executor.run(async move { await!(journal.replay_unacked().collect::<Vec<_>>()) });
+
+        // THEN: the 2 unacked messages are replayed in order
+        let replayed_ids: Vec<InstanceId> = replayed
+            .iter()
+            .map(|msg| msg.metadata().instance_id())
+            .collect();
+        assert_eq!(
+            replayed_ids,
+            vec![
+                msgs[0].metadata().instance_id(),
+                msgs[2].metadata().instance_id()
+            ]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journal_replay_streams_unacked_messages() {
+        configure_logging();
+        let mut executor = global_executor();
+        let path = std::env::temp_dir().join(format!("journal-{}", ULID::generate()));
+
+        // GIVEN: a journal with more messages than the replay buffer can hold, where every other
+        // message is acked
+        let msgs: Vec<EncodedMessage> = (0..(REPLAY_BUFFER_SIZE as u64 * 4))
+            .map(encoded_message)
+            .collect();
+        let journal = Journal::open(&path).unwrap();
+        for (i, msg) in msgs.iter().enumerate() {
+            journal.append(msg).unwrap();
+            if i % 2 == 1 {
+                journal.ack(msg.metadata().instance_id()).unwrap();
+            }
+        }
+
+        // WHEN: the journal is replayed
+        let replay = journal.replay_unacked();
+        // AND: a message is appended after the replay started
+        let late_msg = encoded_message(0);
+        journal.append(&late_msg).unwrap();
+        let replayed: Vec<EncodedMessage> =
+            executor.run(async move { await!(replay.collect::<Vec<_>>()) });
+
+        // THEN: the unacked messages are streamed in order through the bounded replay buffer
+        let replayed_ids: Vec<InstanceId> = replayed
+            .iter()
+            .map(|msg| msg.metadata().instance_id())
+            .collect();
+        let expected_ids: Vec<InstanceId> = msgs
+            .iter()
+            .step_by(2)
+            .map(|msg| msg.metadata().instance_id())
+            .collect();
+        assert_eq!(replayed_ids, expected_ids);
+        // AND: the message that was appended after the replay started is not replayed
+        assert!(!replayed_ids.contains(&late_msg.metadata().instance_id()));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
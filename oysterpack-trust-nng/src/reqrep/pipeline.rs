@@ -0,0 +1,222 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides request pipelining for nng [Client(s)](../client/type.Client.html).
+//!
+//! Sending a batch of requests serially, i.e., awaiting each reply before sending the next request,
+//! pays the full round trip latency per request. On high-latency links, throughput is improved by
+//! keeping multiple requests in flight. The [Pipeline](struct.Pipeline.html) keeps up to `depth`
+//! requests in flight, and returns the replies in request order, i.e., replies are correlated to
+//! requests by order.
+//!
+//! ## Notes
+//! The nng REQ protocol allows only a single outstanding request per socket context. Thus, the
+//! in-flight requests are spread across the client's Aio contexts, and the effective pipeline depth
+//! is bounded by the client's [DialerConfig::parallelism()](../client/struct.DialerConfig.html#method.parallelism).
+//! Requests beyond the client's parallelism wait for an Aio context to become available.
+
+use super::client::{Client, RequestError};
+use futures::{
+    future::FutureExt,
+    stream::{FuturesOrdered, StreamExt},
+};
+use oysterpack_trust::concurrent::messaging::{errors::ChannelError, reqrep::ReqRepId};
+use std::num::NonZeroUsize;
+
+/// Pipelined reply type
+pub type PipelinedReply = Result<Result<nng::Message, RequestError>, ChannelError>;
+
+/// Wraps a [Client](../client/type.Client.html) and keeps multiple requests in flight
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    client: Client,
+    depth: usize,
+}
+
+impl Pipeline {
+    /// constructor
+    /// - depth is the max number of requests that are kept in flight
+    pub fn new(client: Client, depth: NonZeroUsize) -> Pipeline {
+        Pipeline {
+            client,
+            depth: depth.get(),
+        }
+    }
+
+    /// Returns the wrapped Client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Returns the client ReqRepId
+    pub fn id(&self) -> ReqRepId {
+        self.client.id()
+    }
+
+    /// Returns the max number of requests that are kept in flight
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Sends the requests, keeping up to `depth` requests in flight, and returns the replies in
+    /// request order
+    pub async fn send_recv_all(&mut self, reqs: Vec<nng::Message>) -> Vec<PipelinedReply> {
+        let mut replies = Vec::with_capacity(reqs.len());
+        let mut in_flight = FuturesOrdered::new();
+        for req in reqs {
+            if in_flight.len() == self.depth {
+                if let Some(reply) = await!(in_flight.next()) {
+                    replies.push(reply);
+                }
+            }
+            let mut client = self.client.clone();
+            in_flight.push(async move { await!(client.send_recv(req)) }.boxed());
+        }
+        while let Some(reply) = await!(in_flight.next()) {
+            replies.push(reply);
+        }
+        replies
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{configure_logging, reqrep::client};
+    use futures::channel::mpsc;
+    use oysterpack_log::*;
+    use oysterpack_trust::{
+        concurrent::{
+            execution::{self, *},
+            messaging::reqrep::*,
+        },
+        metrics,
+    };
+    use oysterpack_uid::ULID;
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const LATENCY: Duration = Duration::from_millis(20);
+
+    /// Starts an echo server that replies after a fixed latency. Each worker thread services its own
+    /// socket context, i.e., the server can process requests concurrently.
+    fn start_fixed_latency_echo_server(url: &url::Url, worker_count: usize) -> nng::Socket {
+        let socket = nng::Socket::new(nng::Protocol::Rep0).unwrap();
+        for _ in 0..worker_count {
+            let ctx = nng::Context::new(&socket).unwrap();
+            let (aio_tx, mut aio_rx) = mpsc::unbounded::<()>();
+            let aio = nng::Aio::with_callback(move |_aio| {
+                let _ = aio_tx.unbounded_send(());
+            })
+            .unwrap();
+            thread::spawn(move || loop {
+                if ctx.recv(&aio).is_err() {
+                    break;
+                }
+                let _ = futures::executor::block_on(aio_rx.next());
+                if aio.result().unwrap().is_err() {
+                    break;
+                }
+                let msg = aio.get_msg().unwrap();
+                thread::sleep(LATENCY);
+                if ctx.send(&aio, msg).is_err() {
+                    break;
+                }
+                let _ = futures::executor::block_on(aio_rx.next());
+                if aio.result().unwrap().is_err() {
+                    break;
+                }
+            });
+        }
+        socket.listen(url.as_str()).unwrap();
+        socket
+    }
+
+    fn requests(count: usize) -> Vec<nng::Message> {
+        (0..count)
+            .map(|i| {
+                let mut req = nng::Message::with_capacity(8).unwrap();
+                req.push_back(&(i as u64).to_be_bytes()).unwrap();
+                req
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pipeline() {
+        configure_logging();
+        let mut executor = execution::global_executor();
+        const DEPTH: usize = 8;
+        const REQUEST_COUNT: usize = 32;
+
+        // GIVEN: a fixed latency echo server
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let server_socket = start_fixed_latency_echo_server(&url, DEPTH);
+        // AND: a client with enough parallelism to support the pipeline depth
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets = metrics::timer_buckets(vec![LATENCY, LATENCY * 2]).unwrap();
+        let client = client::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets),
+            None,
+            client::DialerConfig::new(url.clone())
+                .set_parallelism(NonZeroUsize::new(DEPTH).unwrap())
+                .set_pre_dial(true),
+            execution::ExecutorBuilder::new(ExecutorId::generate())
+                .register()
+                .unwrap(),
+        )
+        .unwrap();
+
+        // WHEN: the requests are sent serially
+        let serial_elapsed = {
+            let mut client = client.clone();
+            let reqs = requests(REQUEST_COUNT);
+            let start = Instant::now();
+            executor.run(
+                async move {
+                    for req in reqs {
+                        await!(client.send_recv(req)).unwrap().unwrap();
+                    }
+                },
+            );
+            start.elapsed()
+        };
+
+        // WHEN: the requests are pipelined
+        let mut pipeline = Pipeline::new(client, NonZeroUsize::new(DEPTH).unwrap());
+        let reqs = requests(REQUEST_COUNT);
+        let start = Instant::now();
+        let replies = executor.run(async move { await!(pipeline.send_recv_all(reqs)) });
+        let pipelined_elapsed = start.elapsed();
+        info!("serial: {:?}, pipelined: {:?}", serial_elapsed, pipelined_elapsed);
+
+        // THEN: the replies are returned in request order
+        assert_eq!(replies.len(), REQUEST_COUNT);
+        for (i, reply) in replies.into_iter().enumerate() {
+            let reply = reply.unwrap().unwrap();
+            assert_eq!(&reply[..], &(i as u64).to_be_bytes());
+        }
+        // AND: pipelining achieves higher throughput than sending the requests serially
+        assert!(pipelined_elapsed < serial_elapsed);
+        assert!(serial_elapsed >= LATENCY * REQUEST_COUNT as u32);
+
+        let _ = client::unregister_client(reqrep_id);
+        server_socket.close();
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides sampled server-side request logging.
+//!
+//! A [RequestLogger](struct.RequestLogger.html) writes a structured [RequestLogRecord](struct.RequestLogRecord.html)
+//! for each sampled request. The sample rate is configured as 1 in N requests:
+//! - 0 disables request logging
+//! - 1 logs every request
+//! - N logs 1 in N requests
+//!
+//! Sampling is deterministic per request message [InstanceId](../../../oysterpack_core/message/struct.InstanceId.html),
+//! i.e., given the same sample rate, the same request will always be sampled in or out. Thus, events
+//! that are correlated by InstanceId across services will be found in all of the services' request
+//! logs. Requests that are decoded, but then rejected, e.g., because they are stale or they fail
+//! validation, are also sampled by their InstanceId. Requests that failed to be decoded have no
+//! InstanceId - they are sampled by arrival order.
+//!
+//! Records are logged at info level, and can optionally be sent to a sink for further processing.
+
+use super::context::RequestContext;
+use futures::channel::mpsc;
+use oysterpack_core::message::{Address, InstanceId, MessageType, SessionId};
+use oysterpack_log::*;
+use oysterpack_trust::concurrent::messaging::reqrep::ReqRepId;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+/// Logs a sample of requests
+/// - clones share the same sampling state
+#[derive(Debug, Clone)]
+pub struct RequestLogger {
+    sample_rate: u32,
+    // used to sample requests that have no InstanceId
+    unidentified_request_count: Arc<AtomicU64>,
+    sink: Option<mpsc::UnboundedSender<RequestLogRecord>>,
+}
+
+impl RequestLogger {
+    /// constructor
+    /// - `sample_rate` is 1 in N requests, where 0 disables request logging
+    pub fn new(sample_rate: u32) -> RequestLogger {
+        RequestLogger {
+            sample_rate,
+            unidentified_request_count: Arc::new(AtomicU64::new(0)),
+            sink: None,
+        }
+    }
+
+    /// Each logged record is sent to the sink
+    pub fn set_sink(mut self, sink: mpsc::UnboundedSender<RequestLogRecord>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Returns the sample rate, i.e., 1 in N requests are logged
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns true if the request with the specified InstanceId is sampled
+    pub fn is_sampled(&self, instance_id: InstanceId) -> bool {
+        match self.sample_rate {
+            0 => false,
+            1 => true,
+            n => u128::from(instance_id.ulid()) % u128::from(n) == 0,
+        }
+    }
+
+    /// Logs the record if it is sampled
+    /// - returns true if the record was logged
+    pub fn log(&self, record: RequestLogRecord) -> bool {
+        let sampled = match record.instance_id {
+            Some(instance_id) => self.is_sampled(instance_id),
+            None => match self.sample_rate {
+                0 => false,
+                n => {
+                    self.unidentified_request_count
+                        .fetch_add(1, Ordering::Relaxed)
+                        % u64::from(n)
+                        == 0
+                }
+            },
+        };
+        if !sampled {
+            return false;
+        }
+        info!("{}", record);
+        if let Some(sink) = self.sink.as_ref() {
+            if let Err(err) = sink.unbounded_send(record) {
+                warn!("RequestLogger sink is disconnected: {}", err);
+            }
+        }
+        true
+    }
+}
+
+/// Request processing outcome
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RequestOutcome {
+    /// a reply was sent
+    Ok,
+    /// a ServiceError reply was sent
+    Failed(String),
+}
+
+impl fmt::Display for RequestOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestOutcome::Ok => f.write_str("Ok"),
+            RequestOutcome::Failed(err) => write!(f, "Failed({})", err),
+        }
+    }
+}
+
+/// Structured request log record
+#[derive(Debug, Clone)]
+pub struct RequestLogRecord {
+    reqrep_id: ReqRepId,
+    received_on: SystemTime,
+    instance_id: Option<InstanceId>,
+    session_id: Option<SessionId>,
+    sender: Option<Address>,
+    message_type: Option<MessageType>,
+    request_size: usize,
+    elapsed: Duration,
+    outcome: RequestOutcome,
+}
+
+impl RequestLogRecord {
+    /// constructor - the InstanceId, SessionId, and timings are taken from the RequestContext
+    pub fn new(
+        reqrep_id: ReqRepId,
+        ctx: &RequestContext,
+        request_size: usize,
+        outcome: RequestOutcome,
+    ) -> RequestLogRecord {
+        RequestLogRecord {
+            reqrep_id,
+            received_on: ctx.received_on(),
+            instance_id: ctx.instance_id(),
+            session_id: ctx.session_id(),
+            sender: None,
+            message_type: None,
+            request_size,
+            elapsed: ctx.elapsed(),
+            outcome,
+        }
+    }
+
+    /// Sets the request sender
+    pub fn set_sender(mut self, sender: Address) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Sets the request MessageType
+    pub fn set_message_type(mut self, message_type: MessageType) -> Self {
+        self.message_type = Some(message_type);
+        self
+    }
+
+    /// ReqRepId
+    pub fn reqrep_id(&self) -> ReqRepId {
+        self.reqrep_id
+    }
+
+    /// when the request was received
+    pub fn received_on(&self) -> SystemTime {
+        self.received_on
+    }
+
+    /// the request message InstanceId
+    /// - None if the request failed to be decoded
+    pub fn instance_id(&self) -> Option<InstanceId> {
+        self.instance_id
+    }
+
+    /// the connection SessionId
+    pub fn session_id(&self) -> Option<SessionId> {
+        self.session_id
+    }
+
+    /// the request sender
+    /// - None if the request failed to be decoded
+    pub fn sender(&self) -> Option<&Address> {
+        self.sender.as_ref()
+    }
+
+    /// the request MessageType
+    /// - None if the request failed to be decoded
+    pub fn message_type(&self) -> Option<MessageType> {
+        self.message_type
+    }
+
+    /// the request message size in bytes
+    pub fn request_size(&self) -> usize {
+        self.request_size
+    }
+
+    /// the request processing time
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// the request processing outcome
+    pub fn outcome(&self) -> &RequestOutcome {
+        &self.outcome
+    }
+}
+
+impl fmt::Display for RequestLogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let display = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        write!(
+            f,
+            "request_log reqrep_id={} instance_id={} session_id={} sender={} message_type={} request_size={} elapsed={:?} outcome={}",
+            self.reqrep_id,
+            display(self.instance_id.map(|id| id.to_string())),
+            display(self.session_id.map(|id| id.to_string())),
+            display(self.sender.map(|sender| sender.to_string())),
+            display(self.message_type.map(|msg_type| msg_type.to_string())),
+            self.request_size,
+            self.elapsed,
+            self.outcome
+        )
+    }
+}
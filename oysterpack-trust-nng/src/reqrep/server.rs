@@ -36,9 +36,12 @@
 //! with the controller's lifetime. The controller's purpose is handle the server management commands:
 //! - respond to ping requests - which can be used check that the server is running
 //! - listen for a signal to stop the server. Upon receiving the signal the controller will
+//!   - log the [ShutdownReason](enum.ShutdownReason.html)
 //!   - close the nng Listener and Socket
 //!   - unregister the ServerHandle from the global registry
 //!
+//! Server lifecycle events are published via [ServerHandle::events()](struct.ServerHandle.html#method.events).
+//!
 //! ### Server Components and Resources
 //! - nng::Socket
 //! - nng:Listener
@@ -49,6 +52,16 @@
 //! - server controller task
 //! - ServerHandle - reference stored in global registry
 //!
+//! ## Request Streams
+//! [spawn_stream()](fn.spawn_stream.html) exposes the incoming requests as a `Stream` instead of
+//! requiring a [Processor](../../../oysterpack_trust/concurrent/messaging/reqrep/trait.Processor.html)
+//! implementation. Each request is paired with a [ReplyHandle](struct.ReplyHandle.html) that is used
+//! to send back the reply.
+//!
+//! ## Server Groups
+//! [ServerGroup](struct.ServerGroup.html) is used to spawn a set of servers that are managed as a unit.
+//! If any member fails to spawn, then the members that were already started are stopped.
+//!
 //! ## Config
 //! - [SocketConfig](../../config/struct.SocketConfig.html)
 //! - [ListenerConfig](struct.ListenerConfig.html)
@@ -59,32 +72,86 @@
 //! - total number of connections that have been initiated since the server has started - [TOT_CONN_INITIATE_COUNT_METRIC_ID](constant.TOT_CONN_INITIATE_COUNT_METRIC_ID.html)
 //!   - this may be greater that the total number of socket connections - a connection may close before
 //!     being added to the socket
+//! - reply size distribution in bytes - [REPLY_SIZE_METRIC_ID](constant.REPLY_SIZE_METRIC_ID.html)
 //! - the ReqRep service provides the message processing metrics
+//! - [ServerHandle::connections()](struct.ServerHandle.html#method.connections) lists the active
+//!   connections along with the remote peer addresses
+//!
+//! ## Connection Authentication
+//! An [AuthCallback](struct.AuthCallback.html) can be configured via [ListenerConfig::set_auth_callback()](struct.ListenerConfig.html#method.set_auth_callback),
+//! which is invoked when a connection is initiated, i.e., on nng::PipeEvent::AddPre, before the
+//! connection is added to the socket. The callback is given the [PipeInfo](struct.PipeInfo.html),
+//! e.g., the remote peer address, and can veto the connection, in which case the connection is
+//! closed and tracked via the [REJECTED_AUTH_COUNT_METRIC_ID](constant.REJECTED_AUTH_COUNT_METRIC_ID.html)
+//! metric.
+//!
+//! ## Backpressure
+//! When the backend ReqRep service is at capacity, the worker handles the request according to the
+//! configured [Backpressure](enum.Backpressure.html) strategy - see [ListenerConfig::set_backpressure()](struct.ListenerConfig.html#method.set_backpressure)
+//! - `Await` (default) - the worker waits for the service to accept the request, which stops the
+//!   worker from receiving new requests from the socket
+//! - `Reject` - a [ServiceError](struct.ServiceError.html) reply is sent back immediately
+//! - `Shed` - the request is dropped, and the client will time out
+//!
+//! Rejected and shed requests are tracked via the [REJECTED_REQUEST_COUNT_METRIC_ID](constant.REJECTED_REQUEST_COUNT_METRIC_ID.html)
+//! and [SHED_REQUEST_COUNT_METRIC_ID](constant.SHED_REQUEST_COUNT_METRIC_ID.html) metrics.
+//!
+//! ## Error Replies
+//! - [ServiceError](struct.ServiceError.html) replies are sent by the server when the backend
+//!   service fails - see [ListenerConfig::set_reply_on_service_error()](struct.ListenerConfig.html#method.set_reply_on_service_error)
+//! - [WireError](struct.WireError.html) replies are sent by services to report a typed
+//!   [oysterpack_errors::Error](../../../oysterpack_errors/struct.Error.html)
+//!
+//! Clients decode both into a [WireError](struct.WireError.html), i.e., both surface as
+//! [RequestError::Service](../client/enum.RequestError.html#variant.Service). A ServiceError is
+//! mapped to a WireError with the [SERVICE_ERROR_ID](constant.SERVICE_ERROR_ID.html) error id.
+//!
+//! ## Connection Sessions
+//! Each connection is assigned a new [SessionId](../../../oysterpack_core/message/struct.SessionId.html)
+//! when it is added to the socket. The SessionId for a request's connection is looked up via
+//! [connection_session_id()](fn.connection_session_id.html).
 
 use crate::config::{SocketConfig, SocketConfigError};
 use failure::Fail;
 use futures::{future::FutureExt, prelude::*, sink::SinkExt, stream::StreamExt, task::SpawnExt};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
 use nng::options::Options;
+use oysterpack_core::message::SessionId;
+use oysterpack_errors::{Error, Id as ErrorId, Level as ErrorLevel};
 use oysterpack_log::*;
 use oysterpack_trust::{
     concurrent::{
         execution::Executor,
-        messaging::reqrep::{ReqRep, ReqRepId},
+        messaging::{
+            errors::ChannelError,
+            reqrep::{FutureReply, Processor, ReqRep, ReqRepConfig, ReqRepId},
+        },
     },
     metrics,
 };
 use oysterpack_uid::ULID;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use prometheus::core::Metric;
 use serde::{Deserialize, Serialize};
-use std::{fmt, num::NonZeroUsize, panic::AssertUnwindSafe};
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    panic::AssertUnwindSafe,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 lazy_static! {
 
     /// Global ServerHandle registry
     static ref SERVER_HANDLES: RwLock<HashMap<ULID, ServerHandle>> = RwLock::new(HashMap::new());
 
+    /// pipe id -> connection SessionId
+    /// - nng pipe ids are unique across sockets, thus a single global registry is used
+    static ref CONNECTION_SESSIONS: RwLock<HashMap<i32, SessionId>> = RwLock::new(HashMap::new());
+
     /// the metric is incremented on nng::PipeEvent::AddPost and decremented on nng::PipeEvent::RemovePost
     static ref ACTIVE_CONN_COUNT: prometheus::IntGaugeVec = metrics::registry().register_int_gauge_vec(
         ACTIVE_CONN_COUNT_METRIC_ID,
@@ -111,6 +178,59 @@ lazy_static! {
         None
     ).unwrap();
 
+    /// the metric is incremented on nng::PipeEvent::AddPre when the connection is rejected because the
+    /// server is at its max connections cap
+    static ref REJECTED_CONN_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        REJECTED_CONN_COUNT_METRIC_ID,
+        "Total number of connections that were rejected because the server was at its max connections cap",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the metric is incremented on nng::PipeEvent::AddPre when the connection is rejected by the
+    /// AuthCallback
+    static ref REJECTED_AUTH_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        REJECTED_AUTH_COUNT_METRIC_ID,
+        "Total number of connections that were rejected by the connection authentication callback",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the metric is set on nng::PipeEvent::AddPre and nng::PipeEvent::AddPost to the number of
+    /// initiated connections that were not added to the socket, excluding rejected connections
+    static ref CONN_SETUP_FAILURE_COUNT: prometheus::IntGaugeVec = metrics::registry().register_int_gauge_vec(
+        CONN_SETUP_FAILURE_COUNT_METRIC_ID,
+        "Number of connections that were initiated, but were never added to the socket nor rejected, since the server was started",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the metric is incremented when a ServiceError reply is sent back because the backend service
+    /// is at capacity - see Backpressure::Reject
+    static ref REJECTED_REQUEST_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        REJECTED_REQUEST_COUNT_METRIC_ID,
+        "Total number of requests that were rejected because the backend service was at capacity",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the reply size is observed by the worker just before the reply is sent on the Aio Context
+    static ref REPLY_SIZE: prometheus::HistogramVec = metrics::registry().register_histogram_vec(
+        REPLY_SIZE_METRIC_ID,
+        "Reply message size in bytes",
+        &[REQREP_LABEL_ID],
+        REPLY_SIZE_BUCKETS.to_vec(),
+        None
+    ).unwrap();
+
+    /// the metric is incremented when a request is dropped because the backend service is at capacity
+    /// - see Backpressure::Shed
+    static ref SHED_REQUEST_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        SHED_REQUEST_COUNT_METRIC_ID,
+        "Total number of requests that were dropped because the backend service was at capacity",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
 }
 
 /// IntGaugeVec MetricId which is used to track the total number of active socket connections by ReqRepId
@@ -122,6 +242,50 @@ pub const TOT_CONN_COUNT_METRIC_ID: metrics::MetricId =
 /// IntCounterVec MetricId which is used to track the total number of connection that have been initiated by ReqRepId
 pub const TOT_CONN_INITIATE_COUNT_METRIC_ID: metrics::MetricId =
     metrics::MetricId(1873172273925609759145190455058277250);
+/// IntCounterVec MetricId which is used to track the number of connections that were rejected
+/// because the server was at its max connections cap by ReqRepId: `M01D886YS4NA3ASGTH0SJ3GQDQB`
+pub const REJECTED_CONN_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1879944806798317770311126305945138923);
+
+/// IntCounterVec MetricId which is used to track the number of connections that were rejected by
+/// the AuthCallback by ReqRepId: `M01D8AE2YBTF82A8KV2WFWRY5V4`
+pub const REJECTED_AUTH_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880034974913997697125892306823485284);
+
+/// IntGaugeVec MetricId which is used to track the number of connections that were initiated but
+/// never added to the socket by ReqRepId: `M01D89S0QEHFPT9E3EWK1ARPZWH`
+/// - connections that are rejected by the AuthCallback or because of the max connections cap are
+///   tracked separately, i.e., they are not counted as setup failures
+pub const CONN_SETUP_FAILURE_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880008266462844074124147422822891409);
+
+/// IntCounterVec MetricId which is used to track the number of requests that were rejected because
+/// the backend service was at capacity by ReqRepId: `M01D8A74C5QB00SDDTMJ6A1NJGF`
+pub const REJECTED_REQUEST_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880026158069136755042523331121826319);
+
+/// IntCounterVec MetricId which is used to track the number of requests that were dropped because
+/// the backend service was at capacity by ReqRepId: `M01D8A7XZRKB0VF2KDWYRAYTHC3`
+pub const SHED_REQUEST_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880027172672221109021159892437517699);
+
+/// HistogramVec MetricId which is used to track the reply message size distribution in bytes by
+/// ReqRepId: `M01D8AEEYD7HS66M9QJAK11EKKY`
+pub const REPLY_SIZE_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880035450337470215899083667259477630);
+
+/// [REPLY_SIZE_METRIC_ID](constant.REPLY_SIZE_METRIC_ID.html) histogram buckets, in bytes
+pub const REPLY_SIZE_BUCKETS: &[f64] = &[
+    64.0,
+    256.0,
+    1024.0,
+    4096.0,
+    16_384.0,
+    65_536.0,
+    262_144.0,
+    1_048_576.0,
+    4_194_304.0,
+];
 
 /// Metric LabelId which is used to store a ReqRepId
 /// - this is used by the following metrics:
@@ -151,16 +315,40 @@ pub fn spawn(
     service: ReqRep<nng::Message, nng::Message>,
     mut executor: Executor,
 ) -> Result<ServerHandle, SpawnError> {
+    let parallelism = listener_config.parallelism();
+    let max_parallelism = crate::config::max_aio_contexts();
+    if parallelism > max_parallelism {
+        return Err(SpawnError::ParallelismTooHigh {
+            parallelism,
+            max: max_parallelism,
+        });
+    }
+
     let (server_command_tx, mut server_command_rx) = futures::channel::mpsc::channel(1);
 
     let reqrep_id = service.id();
     let url = listener_config.url.clone();
-    let parallelism = listener_config.parallelism();
+    let reply_on_service_error = listener_config.reply_on_service_error();
+    let backpressure = listener_config.backpressure();
+    let max_connections = listener_config.max_connections();
+    let auth_callback = listener_config.auth_callback().cloned();
+    let aio_send_timeout = socket_config
+        .as_ref()
+        .and_then(SocketConfig::aio_send_timeout);
+    let aio_recv_timeout = socket_config
+        .as_ref()
+        .and_then(SocketConfig::aio_recv_timeout);
+    let linger = socket_config.as_ref().and_then(SocketConfig::linger);
     let server_metrics = ServerMetrics::new(reqrep_id);
     let server_handle_id = ULID::generate();
 
+    let connections: Arc<RwLock<Connections>> = Default::default();
+    let shutdown_reason: Arc<RwLock<Option<ShutdownReason>>> = Default::default();
+    let event_subscribers: ServerEventSubscribers = Default::default();
+
     let create_socket = || {
         let server_metrics = server_metrics.clone();
+        let connections = connections.clone();
         let mut socket =
             nng::Socket::new(nng::Protocol::Rep0).map_err(SpawnError::SocketCreateFailure)?;
         socket.set_nonblocking(true);
@@ -169,10 +357,74 @@ pub fn spawn(
                 match event {
                     nng::PipeEvent::AddPost => {
                         server_metrics.active_conn_count.inc();
-                        server_metrics.tot_conn_count.inc();
+                        server_metrics.conn_added();
+                        let remote_address = pipe
+                            .get_opt::<nng::options::RemAddr>()
+                            .ok()
+                            .map(|addr| format!("{:?}", addr));
+                        let session_id = SessionId::generate();
+                        CONNECTION_SESSIONS.write().insert(pipe.id(), session_id);
+                        let mut connections = connections.write();
+                        connections.pending.remove(&pipe.id());
+                        connections.added.insert(
+                            pipe.id(),
+                            ConnectionInfo {
+                                pipe_id: pipe.id(),
+                                session_id,
+                                remote_address,
+                                connected_on: SystemTime::now(),
+                            },
+                        );
+                    }
+                    nng::PipeEvent::RemovePost => {
+                        CONNECTION_SESSIONS.write().remove(&pipe.id());
+                        // rejected connections were never added
+                        let mut connections = connections.write();
+                        connections.pending.remove(&pipe.id());
+                        if connections.added.remove(&pipe.id()).is_some() {
+                            server_metrics.active_conn_count.dec();
+                        }
+                    }
+                    nng::PipeEvent::AddPre => {
+                        server_metrics.conn_initiated(reqrep_id);
+                        if let Some(auth_callback) = auth_callback.as_ref() {
+                            let pipe_info = PipeInfo {
+                                pipe_id: pipe.id(),
+                                remote_address: pipe.get_opt::<nng::options::RemAddr>().ok(),
+                            };
+                            if !auth_callback.authenticate(&pipe_info) {
+                                warn!(
+                                    "ReqRep({}) connection was rejected by the AuthCallback: {:?}",
+                                    reqrep_id, pipe_info
+                                );
+                                server_metrics.conn_rejected_auth();
+                                let _ = pipe.close();
+                                return;
+                            }
+                        }
+                        // the cap check and the reservation are done under the same write lock,
+                        // i.e., concurrent connections cannot both pass the cap check
+                        let rejected_at_cap = {
+                            let mut connections = connections.write();
+                            match max_connections {
+                                Some(max) if connections.len() >= max => Some(max),
+                                _ => {
+                                    connections.pending.insert(pipe.id());
+                                    None
+                                }
+                            }
+                        };
+                        // the pipe is closed outside of the lock because closing the pipe may
+                        // trigger the RemovePost event
+                        if let Some(max_connections) = rejected_at_cap {
+                            warn!(
+                                "ReqRep({}) connection was rejected because the server is at its max connections cap ({}): {:?}",
+                                reqrep_id, max_connections, pipe
+                            );
+                            server_metrics.conn_rejected_max_connections();
+                            let _ = pipe.close();
+                        }
                     }
-                    nng::PipeEvent::RemovePost => server_metrics.active_conn_count.dec(),
-                    nng::PipeEvent::AddPre => server_metrics.tot_conn_initiate_count.inc(),
                     _ => (),
                 }
                 debug!("{:?} {:?}", pipe, event);
@@ -226,6 +478,7 @@ pub fn spawn(
                     }
                 }).map_err(SpawnError::AioCreateWithCallbackFailure)?;
                 let mut service_client = service.clone();
+                let worker_metrics = server_metrics.clone();
                 executor
                     .spawn(
                         async move {
@@ -236,6 +489,9 @@ pub fn spawn(
                                     let mut state = AioState::Recv;
 
                                     let recv = |state: AioState| {
+                                        if let Err(err) = aio.set_timeout(aio_recv_timeout) {
+                                            error!("{:?}: Aio::set_timeout() failed: {}", state, err);
+                                        }
                                         if let Err(err) = ctx.recv(&aio) {
                                             // TODO: trigger alert - async I/O errors need to be investigated
                                             error!("{:?}: Context::recv() failed: {}", state, err);
@@ -244,6 +500,10 @@ pub fn spawn(
                                     };
 
                                     let send = |state: AioState, msg: nng::Message| {
+                                        if let Err(err) = aio.set_timeout(aio_send_timeout) {
+                                            error!("{:?}: Aio::set_timeout() failed: {}", state, err);
+                                        }
+                                        worker_metrics.reply_size.observe(msg.len() as f64);
                                         if let Err((_msg, err)) = ctx.send(&aio, msg) {
                                             // TODO: trigger alert - async I/O errors need to be investigated
                                             error!("{:?}: Context::send() failed: {}", state, err);
@@ -253,15 +513,55 @@ pub fn spawn(
                                         AioState::Send
                                     };
 
-                                    let reqrep_send_recv_failed = |state, err, reqrep_id| {
+                                    let reqrep_send_recv_failed =
+                                        |state, err: ChannelError, reqrep_id| {
                                         error!(
                                             "ReqRep::send_recv() failed: ReqRepId({}) : {}",
                                             reqrep_id, err
                                         );
+                                        if reply_on_service_error {
+                                            // send back a structured error reply to fail fast the client request
+                                            let service_error =
+                                                ServiceError::new(reqrep_id, err.to_string());
+                                            match service_error.encode() {
+                                                Ok(msg) => return send(state, msg),
+                                                Err(err) => error!(
+                                                    "Failed to encode ServiceError reply: {}",
+                                                    err
+                                                ),
+                                            }
+                                        }
+                                        aio.cancel();
+                                        recv(state)
+                                    };
+
+                                    // the backend service is at capacity - only applies to
+                                    // Backpressure::Reject and Backpressure::Shed
+                                    let service_full = |state| {
+                                        if backpressure == Backpressure::Reject {
+                                            worker_metrics.rejected_request_count.inc();
+                                            let service_error = ServiceError::new(
+                                                reqrep_id,
+                                                ChannelError::Full.to_string(),
+                                            );
+                                            match service_error.encode() {
+                                                Ok(msg) => return send(state, msg),
+                                                Err(err) => error!(
+                                                    "Failed to encode ServiceError reply: {}",
+                                                    err
+                                                ),
+                                            }
+                                        } else {
+                                            worker_metrics.shed_request_count.inc();
+                                        }
                                         aio.cancel();
                                         recv(state)
                                     };
 
+                                    // if false, then requests are sent without waiting, and
+                                    // the service being full is handled via service_full()
+                                    let await_service = backpressure == Backpressure::Await;
+
                                     let no_msg_available = |state| {
                                         warn!("{:?} Expected a message to be available", state);
                                         aio.cancel();
@@ -287,9 +587,23 @@ pub fn spawn(
                                             AioState::Recv => match aio.result().unwrap() {
                                                 Ok(_) => match aio.get_msg() {
                                                     Some(msg) => {
-                                                        match await!(service_client.send_recv(msg))
-                                                        {
+                                                        // the ReqRep futures are cancellation safe, i.e., if the worker
+                                                        // is dropped mid-request, the service load remains consistent
+                                                        let reply = if await_service {
+                                                            await!(service_client.send_recv(msg))
+                                                        } else {
+                                                            match service_client.try_send(msg) {
+                                                                Ok(reply_receiver) => {
+                                                                    await!(reply_receiver.recv())
+                                                                }
+                                                                Err(err) => Err(err),
+                                                            }
+                                                        };
+                                                        match reply {
                                                             Ok(reply) => send(state, reply),
+                                                            Err(ChannelError::Full) => {
+                                                                service_full(state)
+                                                            }
                                                             Err(err) => reqrep_send_recv_failed(
                                                                 state,
                                                                 err,
@@ -299,6 +613,8 @@ pub fn spawn(
                                                     }
                                                     None => no_msg_available(state),
                                                 },
+                                                // no request arrived within the Aio recv timeout
+                                                Err(nng::Error::TimedOut) => recv(state),
                                                 Err(err) => handle_aio_error(state, err),
                                             },
                                             AioState::Send => match aio.result().unwrap() {
@@ -331,6 +647,10 @@ pub fn spawn(
                          socket: nng::Socket,
                          listener: nng::Listener,
                          mut executor: Executor| {
+        let shutdown_reason = shutdown_reason.clone();
+        let event_subscribers = event_subscribers.clone();
+        let server_metrics = server_metrics.clone();
+        let connections = connections.clone();
         executor.spawn_with_handle(async move{
             for c in worker_start_chans {
                 if c.send(()).is_err() {
@@ -339,18 +659,41 @@ pub fn spawn(
                 }
             }
             debug!("Server({}) is running ...", reqrep_id);
+            // if all command channels are dropped without a Stop command, then the stop is treated as requested
+            let mut reason = ShutdownReason::Requested;
             while let Some(cmd) = await!(server_command_rx.next()) {
                 match cmd {
                     ServerCommand::Ping(reply_chan) => {
                         let _ = reply_chan.send(());
                     },
-                    ServerCommand::Stop => break
+                    ServerCommand::Stop(stop_reason) => {
+                        reason = stop_reason;
+                        break
+                    }
                 }
             }
-            debug!("Server({}) is shutting down ...", reqrep_id);
+            match reason {
+                ShutdownReason::Fatal(ref err) => error!("Server({}) is shutting down: Fatal: {}", reqrep_id, err),
+                _ => info!("Server({}) is shutting down: {}", reqrep_id, reason)
+            }
+            publish_server_event(&event_subscribers, ServerEvent::Stopping(reason.clone()));
             listener.close();
-            socket.close();
+            crate::config::close_socket(socket, linger);
+            // pipes may not all emit RemovePost before the socket closes - clearing the connections
+            // also ensures that late RemovePost events do not decrement the reset gauge
+            {
+                let mut connections = connections.write();
+                let mut connection_sessions = CONNECTION_SESSIONS.write();
+                for pipe_id in connections.added.keys() {
+                    connection_sessions.remove(pipe_id);
+                }
+                connections.added.clear();
+                connections.pending.clear();
+            }
+            server_metrics.active_conn_count.set(0);
             debug!("Server({}) is shut down", reqrep_id);
+            *shutdown_reason.write() = Some(reason.clone());
+            publish_server_event(&event_subscribers, ServerEvent::Stopped(reason));
             let mut server_handles = SERVER_HANDLES.write();
             server_handles.remove(&server_handle_id);
         }).map_err(|err| SpawnError::ExecutorSpawnError {
@@ -372,20 +715,145 @@ pub fn spawn(
         server_command_channel: Some(server_command_tx),
         executor,
         metrics: server_metrics,
+        connections,
+        shutdown_reason,
+        event_subscribers,
     };
 
     let mut server_handles = SERVER_HANDLES.write();
     server_handles.insert(server_handle.id(), server_handle.clone());
+    super::check_registry_size(super::Registry::ServerHandles, server_handles.len());
 
     Ok(server_handle)
 }
 
+type ServerEventSubscribers = Arc<Mutex<Vec<futures::channel::mpsc::UnboundedSender<ServerEvent>>>>;
+
+/// publishes the event to the subscribers - subscribers that have dropped their stream are removed
+fn publish_server_event(subscribers: &ServerEventSubscribers, event: ServerEvent) {
+    subscribers
+        .lock()
+        .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+}
+
+/// Returns the SessionId of the connection that the request was received on
+/// - each connection is assigned a new SessionId when it is added to the socket
+/// - returns None if the request was not received via a server socket, or the connection has been
+///   closed
+pub fn connection_session_id(req: &nng::Message) -> Option<SessionId> {
+    let pipe = req.pipe()?;
+    CONNECTION_SESSIONS.read().get(&pipe.id()).cloned()
+}
+
+/// Validates the whole config up front before spawning the server - see [spawn()](fn.spawn.html)
+/// - no resources are allocated if the config is invalid
+/// - all configuration problems are reported at once via [SpawnError::InvalidConfig](enum.SpawnError.html#variant.InvalidConfig)
+pub fn spawn_validated(
+    socket_config: Option<SocketConfig>,
+    listener_config: ListenerConfig,
+    service: ReqRep<nng::Message, nng::Message>,
+    executor: Executor,
+) -> Result<ServerHandle, SpawnError> {
+    let problems = listener_config.validate(socket_config.as_ref());
+    if !problems.is_empty() {
+        return Err(SpawnError::InvalidConfig(ConfigProblems(problems)));
+    }
+    spawn(socket_config, listener_config, service, executor)
+}
+
+/// Spawns a server that exposes the incoming requests as a Stream - see [spawn()](fn.spawn.html)
+/// - each request is paired with a [ReplyHandle](struct.ReplyHandle.html), which is used to send back the reply
+/// - the stream is bounded by the listener's parallelism. Thus, if the consumer falls behind, then
+///   backpressure is applied all the way back to the socket
+/// - the ReqRep backend service is started using the specified ReqRepConfig
+pub fn spawn_stream(
+    reqrep_config: ReqRepConfig,
+    socket_config: Option<SocketConfig>,
+    listener_config: ListenerConfig,
+    executor: Executor,
+) -> Result<
+    (
+        ServerHandle,
+        impl Stream<Item = (nng::Message, ReplyHandle)> + Send + Unpin,
+    ),
+    SpawnError,
+> {
+    let (request_tx, request_rx) =
+        futures::channel::mpsc::channel(listener_config.parallelism());
+    let service = reqrep_config
+        .start_service(
+            StreamProcessor {
+                request_sender: request_tx,
+            },
+            executor.clone(),
+        )
+        .map_err(|err| SpawnError::ExecutorSpawnError {
+            is_executor_shutdown: err.is_shutdown(),
+        })?;
+    let server_handle = spawn(socket_config, listener_config, service, executor)?;
+    Ok((server_handle, request_rx))
+}
+
+/// Used to send back the reply for a request that was received via [spawn_stream()](fn.spawn_stream.html)
+/// - if the handle is dropped without replying, then an empty reply message is sent
+#[derive(Debug)]
+pub struct ReplyHandle(futures::channel::oneshot::Sender<nng::Message>);
+
+impl ReplyHandle {
+    /// Sends the reply
+    /// - if the server is no longer waiting on the reply, then the reply is returned
+    pub fn reply(self, rep: nng::Message) -> Result<(), nng::Message> {
+        self.0.send(rep)
+    }
+}
+
+/// Forwards requests to the request stream, and waits for the consumer to reply via the ReplyHandle
+struct StreamProcessor {
+    request_sender: futures::channel::mpsc::Sender<(nng::Message, ReplyHandle)>,
+}
+
+impl Processor<nng::Message, nng::Message> for StreamProcessor {
+    fn process(&mut self, req: nng::Message) -> FutureReply<nng::Message> {
+        let mut request_sender = self.request_sender.clone();
+        async move {
+            let (rep_sender, rep_receiver) = futures::channel::oneshot::channel();
+            if await!(request_sender.send((req, ReplyHandle(rep_sender)))).is_err() {
+                warn!("request stream is disconnected - replying with an empty message");
+                return nng::Message::new().expect("failed to create empty message");
+            }
+            match await!(rep_receiver) {
+                Ok(rep) => rep,
+                Err(_) => {
+                    warn!("ReplyHandle was dropped without replying - replying with an empty message");
+                    nng::Message::new().expect("failed to create empty message")
+                }
+            }
+        }
+            .boxed()
+    }
+}
+
+/// Returns the number of registered ServerHandle(s)
+pub(crate) fn server_handle_count() -> usize {
+    SERVER_HANDLES.read().len()
+}
+
 /// Server handle
 /// - the server handle is globally registered using its ULID as the key
 ///
 ///
 /// ## Stopping the server
 /// - [stop_async()](#method.stop_async) is used to signal the server to stop
+/// - [stop_async_with_reason()](#method.stop_async_with_reason) is used to signal the server to
+///   stop, specifying the [ShutdownReason](enum.ShutdownReason.html)
+/// - [close()](#method.close) signals the server to stop, and returns a future that completes once
+///   the server has shut down and has been unregistered
+///
+/// ## Drop
+/// Dropping a ServerHandle does not stop the server. The server keeps running as long as it is not
+/// signalled to stop, because a ServerHandle clone is held by the global registry - the server can
+/// always be looked up again via [get()](#method.get). Stopping the server via any clone stops the
+/// server for all clones.
 #[derive(Debug, Clone)]
 pub struct ServerHandle {
     id: ULID,
@@ -396,6 +864,9 @@ pub struct ServerHandle {
     server_command_channel: Option<futures::channel::mpsc::Sender<ServerCommand>>,
     executor: Executor,
     metrics: ServerMetrics,
+    connections: Arc<RwLock<Connections>>,
+    shutdown_reason: Arc<RwLock<Option<ShutdownReason>>>,
+    event_subscribers: ServerEventSubscribers,
 }
 
 impl ServerHandle {
@@ -431,6 +902,26 @@ impl ServerHandle {
         &self.metrics
     }
 
+    /// Returns the server's active connections
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.connections.read().added.values().cloned().collect()
+    }
+
+    /// Returns the reason the server was shut down
+    /// - None if the server has not yet shut down
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_reason.read().clone()
+    }
+
+    /// Subscribes to the server's lifecycle [ServerEvent(s)](enum.ServerEvent.html)
+    /// - only events that occur after subscribing are received
+    /// - the stream ends when the server has shut down, and all ServerHandle(s) have been dropped
+    pub fn events(&self) -> impl Stream<Item = ServerEvent> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        self.event_subscribers.lock().push(tx);
+        rx
+    }
+
     /// pings the server to check if it is still alive
     /// - returns true if the server responds to the ping
     ///
@@ -458,15 +949,25 @@ impl ServerHandle {
         }
     }
 
-    /// signals the server to shutdown async
+    /// signals the server to shutdown async, using [ShutdownReason::Requested](enum.ShutdownReason.html#variant.Requested)
     pub fn stop_async(&mut self) -> Result<bool, ServerHandleError> {
+        self.stop_async_with_reason(ShutdownReason::Requested)
+    }
+
+    /// signals the server to shutdown async
+    /// - the reason is logged by the server controller and published as a [ServerEvent](enum.ServerEvent.html)
+    /// - returns false if the server was already signalled to stop
+    pub fn stop_async_with_reason(
+        &mut self,
+        reason: ShutdownReason,
+    ) -> Result<bool, ServerHandleError> {
         if let Some(mut c) = self.server_command_channel.take() {
             self.executor
                 .spawn(
                     async move {
                         // the result can be ignored because if the channel is disconnected then it means the
                         // server has stopped
-                        let _ = await!(c.send(ServerCommand::Stop));
+                        let _ = await!(c.send(ServerCommand::Stop(reason)));
                     },
                 )
                 .map_err(|err| {
@@ -481,6 +982,26 @@ impl ServerHandle {
         Ok(false)
     }
 
+    /// Signals the server to stop and returns a future that completes when the server has shut down.
+    /// - once the future completes, the ServerHandle is removed from the global registry
+    /// - the server is stopped even if other ServerHandle clones exist - after the server is closed,
+    ///   pinging the server via any clone will fail
+    pub fn close(mut self) -> impl Future<Output = ()> {
+        let id = self.id;
+        let server_command_channel = self.server_command_channel.take();
+        let handle = self.handle.take();
+        async move {
+            if let Some(mut c) = server_command_channel {
+                // if the channel is disconnected, then it means the server has already stopped
+                let _ = await!(c.send(ServerCommand::Stop(ShutdownReason::Requested)));
+            }
+            if let Some(handle) = handle {
+                await!(handle);
+            }
+            SERVER_HANDLES.write().remove(&id);
+        }
+    }
+
     /// Block the current thread until the server has shutdown
     ///
     /// ## Notes
@@ -546,13 +1067,164 @@ impl ServerHandle {
 #[fail(display = "ServerHandle error: {}", _0)]
 pub struct ServerHandleError(String);
 
+/// Server group member spec, i.e., the arguments that are used to [spawn](fn.spawn.html) the server.
+#[derive(Debug)]
+pub struct ServerGroupMember {
+    socket_config: Option<SocketConfig>,
+    listener_config: ListenerConfig,
+    service: ReqRep<nng::Message, nng::Message>,
+}
+
+impl ServerGroupMember {
+    /// constructor
+    pub fn new(listener_config: ListenerConfig, service: ReqRep<nng::Message, nng::Message>) -> Self {
+        Self {
+            socket_config: None,
+            listener_config,
+            service,
+        }
+    }
+
+    /// Sets the SocketConfig
+    pub fn set_socket_config(self, socket_config: SocketConfig) -> Self {
+        let mut this = self;
+        this.socket_config = Some(socket_config);
+        this
+    }
+}
+
+/// A group of servers that are supervised together, i.e., they are spawned as a group and are
+/// stopped as a group.
+///
+/// Spawning is all or nothing - if any member fails to spawn, then the members that were already
+/// started are stopped, i.e., rolled back.
+#[derive(Debug)]
+pub struct ServerGroup {
+    servers: Vec<ServerHandle>,
+}
+
+impl ServerGroup {
+    /// Spawns the group members in the specified order.
+    /// - if a member fails to spawn, then the already started members are stopped before returning
+    ///   the SpawnError
+    pub fn spawn(
+        members: Vec<ServerGroupMember>,
+        executor: Executor,
+    ) -> Result<ServerGroup, SpawnError> {
+        let mut servers = Vec::with_capacity(members.len());
+        for member in members {
+            match spawn(
+                member.socket_config,
+                member.listener_config,
+                member.service,
+                executor.clone(),
+            ) {
+                Ok(server) => servers.push(server),
+                Err(err) => {
+                    warn!(
+                        "ServerGroup member failed to spawn - stopping {} started server(s): {}",
+                        servers.len(),
+                        err
+                    );
+                    for mut server in servers {
+                        if let Err(err) = server.stop_async() {
+                            error!("Failed to signal server to stop: {:?} : {}", server.id, err);
+                        }
+                        server.await_shutdown();
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(ServerGroup { servers })
+    }
+
+    /// Returns the group's ServerHandle(s)
+    pub fn servers(&self) -> &[ServerHandle] {
+        &self.servers
+    }
+
+    /// Returns the ServerHandle ULIDs
+    pub fn ids(&self) -> Vec<ULID> {
+        self.servers.iter().map(ServerHandle::id).collect()
+    }
+
+    /// Pings each server in the group
+    /// - returns the results in the same order as the [servers](#method.servers)
+    pub fn ping(&self) -> Vec<(ULID, bool)> {
+        self.servers
+            .iter()
+            .map(|server| (server.id, server.ping()))
+            .collect()
+    }
+
+    /// Returns true if all servers in the group are alive
+    /// - an empty group is considered healthy
+    pub fn health(&self) -> bool {
+        self.servers.iter().all(ServerHandle::ping)
+    }
+
+    /// Returns each server's metrics
+    pub fn metrics(&self) -> Vec<(ULID, ServerMetrics)> {
+        self.servers
+            .iter()
+            .map(|server| (server.id, server.metrics.clone()))
+            .collect()
+    }
+
+    /// Signals all servers to stop, and then waits for them all to shutdown.
+    /// - after the future completes, the group is empty
+    pub async fn stop_all(&mut self) {
+        for server in self.servers.iter_mut() {
+            if let Err(err) = server.stop_async() {
+                error!("Failed to signal server to stop: {:?} : {}", server.id, err);
+            }
+        }
+        for mut server in self.servers.drain(..) {
+            if let Some(handle) = server.handle.take() {
+                await!(handle);
+            }
+        }
+    }
+}
+
 /// Server commands
 #[derive(Debug)]
 pub enum ServerCommand {
     /// Ping the server to check if it is still alive
     Ping(futures::channel::oneshot::Sender<()>),
     /// Signals the server to shutdown
-    Stop,
+    Stop(ShutdownReason),
+}
+
+/// The reason the server was shut down
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ShutdownReason {
+    /// the server was requested to stop, e.g., normal application shutdown
+    Requested,
+    /// the server was stopped for maintenance
+    Maintenance,
+    /// the server was stopped because of an unrecoverable error
+    Fatal(String),
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShutdownReason::Requested => f.write_str("Requested"),
+            ShutdownReason::Maintenance => f.write_str("Maintenance"),
+            ShutdownReason::Fatal(err) => write!(f, "Fatal({})", err),
+        }
+    }
+}
+
+/// Server lifecycle events
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ServerEvent {
+    /// the server controller received the stop signal, and is shutting down the server
+    Stopping(ShutdownReason),
+    /// the server has shut down, i.e., the socket and listener are closed
+    Stopped(ShutdownReason),
 }
 
 /// Errors that could happen while trying to spawn a server
@@ -585,83 +1257,788 @@ pub enum SpawnError {
     /// Failed to apply SocketConfig options
     #[fail(display = "{}", _0)]
     SocketConfigApplyFailed(#[cause] SocketConfigError),
+    /// The configured parallelism exceeds the max number of Aio contexts per socket
+    #[fail(
+        display = "Parallelism ({}) exceeds the max number of Aio contexts per socket ({})",
+        parallelism, max
+    )]
+    ParallelismTooHigh {
+        /// configured parallelism
+        parallelism: usize,
+        /// max number of Aio contexts per socket - see [max_aio_contexts()](../../config/fn.max_aio_contexts.html)
+        max: usize,
+    },
+    /// The config failed validation - see [spawn_validated()](fn.spawn_validated.html)
+    #[fail(display = "Invalid server config: {}", _0)]
+    InvalidConfig(ConfigProblems),
 }
 
-/// Aio state for socket context
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-enum AioState {
-    /// aio receive operation is in progress
-    Recv,
-    /// aio send operation is in progress
-    Send,
-    /// Closed
-    Closed,
+/// Server configuration problem detected by [ListenerConfig::validate()](struct.ListenerConfig.html#method.validate)
+#[derive(Debug, Clone, Eq, PartialEq, Fail)]
+pub enum ConfigProblem {
+    /// The URL scheme is not a transport that is supported by nng
+    #[fail(display = "Unsupported URL scheme: {}", _0)]
+    UnsupportedUrlScheme(String),
+    /// Binding to all interfaces was not explicitly allowed
+    #[fail(display = "Binding to all interfaces is not allowed: {}", _0)]
+    WildcardBindNotAllowed(String),
+    /// Parallelism must be at least 1
+    #[fail(display = "Parallelism must be greater than 0")]
+    ZeroParallelism,
+    /// The configured parallelism exceeds the max number of Aio contexts per socket
+    #[fail(
+        display = "Parallelism ({}) exceeds the max number of Aio contexts per socket ({})",
+        parallelism, max
+    )]
+    ParallelismTooHigh {
+        /// configured parallelism
+        parallelism: usize,
+        /// max number of Aio contexts per socket
+        max: usize,
+    },
+    /// A TCP option was configured for a transport that is not TCP based
+    #[fail(display = "TCP option '{}' is not supported by the '{}' transport", option, scheme)]
+    TcpOptionNotSupported {
+        /// option name
+        option: &'static str,
+        /// URL scheme
+        scheme: String,
+    },
 }
 
-/// Server metrics
-#[derive(Clone)]
-pub struct ServerMetrics {
-    active_conn_count: prometheus::IntGauge,
-    tot_conn_count: prometheus::IntCounter,
-    tot_conn_initiate_count: prometheus::IntCounter,
+/// Aggregates all of the problems found while validating the server config
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConfigProblems(Vec<ConfigProblem>);
+
+impl ConfigProblems {
+    /// returns the config problems
+    pub fn problems(&self) -> &[ConfigProblem] {
+        &self.0
+    }
 }
 
-impl ServerMetrics {
-    fn new(reqrep_id: ReqRepId) -> Self {
-        let reqrep_id_label = reqrep_id.to_string();
-        Self {
-            active_conn_count: ACTIVE_CONN_COUNT.with_label_values(&[reqrep_id_label.as_str()]),
-            tot_conn_count: TOT_CONN_COUNT.with_label_values(&[reqrep_id_label.as_str()]),
-            tot_conn_initiate_count: TOT_CONN_INITIATE_COUNT
-                .with_label_values(&[reqrep_id_label.as_str()]),
-        }
+impl fmt::Display for ConfigProblems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problems: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&problems.join("; "))
     }
+}
 
-    /// Active number of socket connections
-    pub fn active_conn_count(&self) -> usize {
-        self.active_conn_count.get() as usize
+/// Structured error reply that is sent back to the client when the backend ReqRep service fails to
+/// process the request.
+/// - this is opt-in - see [ListenerConfig::set_reply_on_service_error()](struct.ListenerConfig.html#method.set_reply_on_service_error)
+///
+/// ## Wire Format
+/// <pre>
+/// | SERVICE_ERROR_MSG_TYPE_ID (16 bytes BE) | ReqRepId (16 bytes BE) | UTF-8 error message |
+/// </pre>
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ServiceError {
+    reqrep_id: ReqRepId,
+    message: String,
+}
+
+/// Message type id that is used to tag [ServiceError](struct.ServiceError.html) reply messages: `01D87C7502WRT3V2MQD7HK35C2`
+pub const SERVICE_ERROR_MSG_TYPE_ID: u128 = 1879910911823035419614380218817549698;
+
+impl ServiceError {
+    const HEADER_LEN: usize = 32;
+
+    /// constructor
+    pub fn new(reqrep_id: ReqRepId, message: String) -> Self {
+        Self { reqrep_id, message }
     }
 
-    /// Total number of socket connections since the server was started
-    pub fn tot_conn_count(&self) -> usize {
-        self.tot_conn_count.get() as usize
+    /// ReqRepId for the backend service that failed
+    pub fn reqrep_id(&self) -> ReqRepId {
+        self.reqrep_id
     }
 
-    /// Total number of connections that have been initiated, but before being added to the socket, since the server was started.
-    pub fn tot_conn_initiate_count(&self) -> usize {
-        self.tot_conn_initiate_count.get() as usize
+    /// error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// encodes the ServiceError as an nng::Message
+    pub fn encode(&self) -> Result<nng::Message, nng::Error> {
+        let mut msg = nng::Message::with_capacity(Self::HEADER_LEN + self.message.len())?;
+        msg.push_back(&SERVICE_ERROR_MSG_TYPE_ID.to_be_bytes())?;
+        msg.push_back(&self.reqrep_id.0.to_be_bytes())?;
+        msg.push_back(self.message.as_bytes())?;
+        Ok(msg)
+    }
+
+    /// Tries to decode the reply message as a ServiceError.
+    /// - returns None if the message is not a ServiceError message
+    pub fn decode(msg: &nng::Message) -> Option<ServiceError> {
+        let bytes: &[u8] = msg;
+        if bytes.len() < Self::HEADER_LEN {
+            return None;
+        }
+        let mut msg_type_id = [0_u8; 16];
+        msg_type_id.copy_from_slice(&bytes[..16]);
+        if u128::from_be_bytes(msg_type_id) != SERVICE_ERROR_MSG_TYPE_ID {
+            return None;
+        }
+        let mut reqrep_id = [0_u8; 16];
+        reqrep_id.copy_from_slice(&bytes[16..Self::HEADER_LEN]);
+        let message = String::from_utf8_lossy(&bytes[Self::HEADER_LEN..]).to_string();
+        Some(ServiceError {
+            reqrep_id: ReqRepId(u128::from_be_bytes(reqrep_id)),
+            message,
+        })
     }
 }
 
-impl fmt::Debug for ServerMetrics {
+impl From<ServiceError> for WireError {
+    fn from(err: ServiceError) -> Self {
+        WireError {
+            error_id: SERVICE_ERROR_ID.0,
+            level: ErrorLevel::Error,
+            message: err.message,
+            reqrep_id: Some(err.reqrep_id),
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,"ServerMetrics(active_conn_count = {}, tot_conn_count = {}, tot_conn_initiate_count = {})",
-               self.active_conn_count.get(),
-               self.tot_conn_count.get(),
-               self.tot_conn_initiate_count.get()
-        )
+        write!(f, "ReqRepId({}) service error: {}", self.reqrep_id, self.message)
     }
 }
 
-/// Listener configuration.
+/// Compact error reply that carries an [oysterpack_errors::Error](../../../oysterpack_errors/struct.Error.html)
+/// across the wire. Services reply with a WireError to report a typed error, which the client
+/// decodes into [RequestError::Service](../client/enum.RequestError.html#variant.Service).
+/// - the error id is stable, and is what clients should match on
+/// - WireError is the single client side representation for error replies, i.e.,
+///   [ServiceError](struct.ServiceError.html) replies are also decoded into a WireError - see
+///   [decode_reply()](#method.decode_reply)
+///
+/// ## Wire Format
+/// <pre>
+/// | WIRE_ERROR_MSG_TYPE_ID (16 bytes BE) | error id (16 bytes BE) | level (1 byte) | UTF-8 error message |
+/// </pre>
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
-pub struct ListenerConfig {
-    #[serde(with = "url_serde")]
-    url: url::Url,
-    recv_max_size: Option<usize>,
-    no_delay: Option<bool>,
-    keep_alive: Option<bool>,
-    non_blocking: bool,
-    parallelism: usize,
+pub struct WireError {
+    error_id: u128,
+    level: ErrorLevel,
+    message: String,
+    // only known for ServiceError replies - it is not part of the WireError wire format
+    reqrep_id: Option<ReqRepId>,
 }
 
-impl ListenerConfig {
+/// Message type id that is used to tag [WireError](struct.WireError.html) reply messages: `01D89KAJGX7D7NQB8818SWTE06`
+pub const WIRE_ERROR_MSG_TYPE_ID: u128 = 1880001050601919898345562658938107910;
+
+/// Error id for [ServiceError](struct.ServiceError.html) replies that are decoded into a
+/// [WireError](struct.WireError.html), i.e., the backend ReqRep service failed to process the
+/// request: `01D8B3MA28481YTHRQ63AX0N48`
+pub const SERVICE_ERROR_ID: ErrorId = ErrorId(1880062283500917450373312783739540616);
+
+impl WireError {
+    const HEADER_LEN: usize = 33;
+
     /// constructor
-    /// - refer to nng for supported [transports](https://nanomsg.github.io/nng/man/v1.1.0/index.html#_section_7_protocols_and_transports)
-    ///
-    /// ## Default settings
+    pub fn new(error_id: ErrorId, level: ErrorLevel, message: String) -> Self {
+        Self {
+            error_id: error_id.0,
+            level,
+            message,
+            reqrep_id: None,
+        }
+    }
+
+    /// ReqRepId of the backend service that failed, which is only known for errors that were
+    /// decoded from a [ServiceError](struct.ServiceError.html) reply
+    pub fn reqrep_id(&self) -> Option<ReqRepId> {
+        self.reqrep_id
+    }
+
+    /// Error id
+    pub fn error_id(&self) -> ErrorId {
+        ErrorId(self.error_id)
+    }
+
+    /// Error level
+    pub fn level(&self) -> ErrorLevel {
+        self.level
+    }
+
+    /// Error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// encodes the WireError as an nng::Message
+    pub fn encode(&self) -> Result<nng::Message, nng::Error> {
+        let mut msg = nng::Message::with_capacity(Self::HEADER_LEN + self.message.len())?;
+        msg.push_back(&WIRE_ERROR_MSG_TYPE_ID.to_be_bytes())?;
+        msg.push_back(&self.error_id.to_be_bytes())?;
+        msg.push_back(&[Self::level_to_byte(self.level)])?;
+        msg.push_back(self.message.as_bytes())?;
+        Ok(msg)
+    }
+
+    /// Tries to decode the reply message as a WireError.
+    /// - returns None if the message is not a WireError message
+    pub fn decode(msg: &nng::Message) -> Option<WireError> {
+        let bytes: &[u8] = msg;
+        if bytes.len() < Self::HEADER_LEN {
+            return None;
+        }
+        let mut msg_type_id = [0_u8; 16];
+        msg_type_id.copy_from_slice(&bytes[..16]);
+        if u128::from_be_bytes(msg_type_id) != WIRE_ERROR_MSG_TYPE_ID {
+            return None;
+        }
+        let mut error_id = [0_u8; 16];
+        error_id.copy_from_slice(&bytes[16..32]);
+        let level = Self::byte_to_level(bytes[32])?;
+        let message = String::from_utf8_lossy(&bytes[Self::HEADER_LEN..]).to_string();
+        Some(WireError {
+            error_id: u128::from_be_bytes(error_id),
+            level,
+            message,
+            reqrep_id: None,
+        })
+    }
+
+    /// Tries to decode the reply message as an error reply, i.e., either a WireError or a
+    /// [ServiceError](struct.ServiceError.html), which is mapped to a WireError
+    /// - returns None if the message is not an error reply
+    pub fn decode_reply(msg: &nng::Message) -> Option<WireError> {
+        WireError::decode(msg).or_else(|| ServiceError::decode(msg).map(WireError::from))
+    }
+
+    fn level_to_byte(level: ErrorLevel) -> u8 {
+        match level {
+            ErrorLevel::Emergency => 0,
+            ErrorLevel::Alert => 1,
+            ErrorLevel::Critical => 2,
+            ErrorLevel::Error => 3,
+        }
+    }
+
+    fn byte_to_level(byte: u8) -> Option<ErrorLevel> {
+        match byte {
+            0 => Some(ErrorLevel::Emergency),
+            1 => Some(ErrorLevel::Alert),
+            2 => Some(ErrorLevel::Critical),
+            3 => Some(ErrorLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+impl From<&Error> for WireError {
+    fn from(err: &Error) -> Self {
+        WireError::new(err.id(), err.level(), err.message().to_string())
+    }
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reqrep_id {
+            Some(reqrep_id) => write!(
+                f,
+                "{:?}({}) : ReqRepId({}) : {}",
+                self.level,
+                ErrorId(self.error_id),
+                reqrep_id,
+                self.message
+            ),
+            None => write!(
+                f,
+                "{:?}({}) : {}",
+                self.level,
+                ErrorId(self.error_id),
+                self.message
+            ),
+        }
+    }
+}
+
+/// Aio state for socket context
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum AioState {
+    /// aio receive operation is in progress
+    Recv,
+    /// aio send operation is in progress
+    Send,
+    /// Closed
+    Closed,
+}
+
+/// Socket connections
+/// - a connection is reserved on nng::PipeEvent::AddPre, once it has passed the AuthCallback and
+///   the max connections cap check, and is moved to `added` on nng::PipeEvent::AddPost
+#[derive(Debug, Default)]
+struct Connections {
+    /// connections that have been added to the socket
+    added: HashMap<i32, ConnectionInfo>,
+    /// connections that have been accepted, but have not yet been added to the socket
+    pending: HashSet<i32>,
+}
+
+impl Connections {
+    /// number of connections that count towards the max connections cap
+    fn len(&self) -> usize {
+        self.added.len() + self.pending.len()
+    }
+}
+
+/// Active socket connection info
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    pipe_id: i32,
+    session_id: SessionId,
+    remote_address: Option<String>,
+    connected_on: SystemTime,
+}
+
+impl ConnectionInfo {
+    /// nng pipe id, which identifies the connection
+    pub fn pipe_id(&self) -> i32 {
+        self.pipe_id
+    }
+
+    /// the SessionId that was assigned to the connection when it was added to the socket
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    /// the remote peer's address
+    /// - returns None if the address could not be retrieved from the pipe
+    pub fn remote_address(&self) -> Option<&str> {
+        self.remote_address.as_ref().map(String::as_str)
+    }
+
+    /// when the connection was added to the socket
+    pub fn connected_on(&self) -> SystemTime {
+        self.connected_on
+    }
+}
+
+/// Server metrics
+///
+/// ## Connection Setup Failures
+/// Connections that are initiated, i.e., nng::PipeEvent::AddPre, but are closed before they are
+/// added to the socket, i.e., no matching nng::PipeEvent::AddPost, are counted as connection setup
+/// failures. Connections that the server rejects, i.e., by the AuthCallback or because of the max
+/// connections cap, have their own outcome and are not setup failures - see
+/// [rejected_auth_count()](#method.rejected_auth_count) and [rejected_conn_count()](#method.rejected_conn_count).
+/// A high failure ratio may be a sign of port scanning or an attack. When the ratio of
+/// failures to initiated connections crosses [CONN_SETUP_FAILURE_WARN_RATIO](#associatedconstant.CONN_SETUP_FAILURE_WARN_RATIO),
+/// then a warning is logged - at most once per [CONN_SETUP_FAILURE_WARN_INTERVAL](#associatedconstant.CONN_SETUP_FAILURE_WARN_INTERVAL).
+#[derive(Clone)]
+pub struct ServerMetrics {
+    active_conn_count: prometheus::IntGauge,
+    tot_conn_count: prometheus::IntCounter,
+    tot_conn_initiate_count: prometheus::IntCounter,
+    rejected_conn_count: prometheus::IntCounter,
+    rejected_auth_count: prometheus::IntCounter,
+    conn_setup_failure_count: prometheus::IntGauge,
+    conn_setup_failure_warned_on: Arc<Mutex<Option<Instant>>>,
+    rejected_request_count: prometheus::IntCounter,
+    shed_request_count: prometheus::IntCounter,
+    reply_size: prometheus::Histogram,
+}
+
+impl ServerMetrics {
+    /// connection setup failure ratio that triggers a warning
+    pub const CONN_SETUP_FAILURE_WARN_RATIO: f64 = 0.5;
+    /// min number of initiated connections before the connection setup failure ratio is checked
+    pub const CONN_SETUP_FAILURE_WARN_MIN_INITIATE_COUNT: usize = 10;
+    /// min amount of time between connection setup failure warnings
+    pub const CONN_SETUP_FAILURE_WARN_INTERVAL: Duration = Duration::from_secs(60);
+
+    fn new(reqrep_id: ReqRepId) -> Self {
+        let reqrep_id_label = reqrep_id.to_string();
+        Self {
+            active_conn_count: ACTIVE_CONN_COUNT.with_label_values(&[reqrep_id_label.as_str()]),
+            tot_conn_count: TOT_CONN_COUNT.with_label_values(&[reqrep_id_label.as_str()]),
+            tot_conn_initiate_count: TOT_CONN_INITIATE_COUNT
+                .with_label_values(&[reqrep_id_label.as_str()]),
+            rejected_conn_count: REJECTED_CONN_COUNT
+                .with_label_values(&[reqrep_id_label.as_str()]),
+            rejected_auth_count: REJECTED_AUTH_COUNT
+                .with_label_values(&[reqrep_id_label.as_str()]),
+            conn_setup_failure_count: CONN_SETUP_FAILURE_COUNT
+                .with_label_values(&[reqrep_id_label.as_str()]),
+            conn_setup_failure_warned_on: Arc::new(Mutex::new(None)),
+            rejected_request_count: REJECTED_REQUEST_COUNT
+                .with_label_values(&[reqrep_id_label.as_str()]),
+            shed_request_count: SHED_REQUEST_COUNT.with_label_values(&[reqrep_id_label.as_str()]),
+            reply_size: REPLY_SIZE.with_label_values(&[reqrep_id_label.as_str()]),
+        }
+    }
+
+    /// invoked on nng::PipeEvent::AddPre
+    /// - returns true if a connection setup failure warning was logged
+    fn conn_initiated(&self, reqrep_id: ReqRepId) -> bool {
+        self.tot_conn_initiate_count.inc();
+        // the connection being initiated is not counted as a failure
+        let failures = self.connection_setup_failures().saturating_sub(1);
+        self.conn_setup_failure_count.set(failures as i64);
+
+        let initiated = self.tot_conn_initiate_count();
+        if initiated < Self::CONN_SETUP_FAILURE_WARN_MIN_INITIATE_COUNT {
+            return false;
+        }
+        let ratio = failures as f64 / initiated as f64;
+        if ratio < Self::CONN_SETUP_FAILURE_WARN_RATIO {
+            return false;
+        }
+        let mut warned_on = self.conn_setup_failure_warned_on.lock();
+        match *warned_on {
+            Some(instant) if instant.elapsed() < Self::CONN_SETUP_FAILURE_WARN_INTERVAL => false,
+            _ => {
+                *warned_on = Some(Instant::now());
+                warn!(
+                    "ReqRep({}) connection setup failure ratio is {:.2}: {} of {} initiated connections were never added to the socket",
+                    reqrep_id, ratio, failures, initiated
+                );
+                true
+            }
+        }
+    }
+
+    /// invoked on nng::PipeEvent::AddPost
+    fn conn_added(&self) {
+        self.tot_conn_count.inc();
+        self.conn_setup_failure_count
+            .set(self.connection_setup_failures() as i64);
+    }
+
+    /// invoked on nng::PipeEvent::AddPre when the connection is rejected by the AuthCallback
+    fn conn_rejected_auth(&self) {
+        self.rejected_auth_count.inc();
+        self.conn_setup_failure_count
+            .set(self.connection_setup_failures() as i64);
+    }
+
+    /// invoked on nng::PipeEvent::AddPre when the connection is rejected because the server is at
+    /// its max connections cap
+    fn conn_rejected_max_connections(&self) {
+        self.rejected_conn_count.inc();
+        self.conn_setup_failure_count
+            .set(self.connection_setup_failures() as i64);
+    }
+
+    /// Active number of socket connections
+    pub fn active_conn_count(&self) -> usize {
+        self.active_conn_count.get() as usize
+    }
+
+    /// Total number of socket connections since the server was started
+    pub fn tot_conn_count(&self) -> usize {
+        self.tot_conn_count.get() as usize
+    }
+
+    /// Total number of connections that have been initiated, but before being added to the socket, since the server was started.
+    pub fn tot_conn_initiate_count(&self) -> usize {
+        self.tot_conn_initiate_count.get() as usize
+    }
+
+    /// Total number of connections that were rejected because the server was at its max connections cap
+    pub fn rejected_conn_count(&self) -> usize {
+        self.rejected_conn_count.get() as usize
+    }
+
+    /// Total number of connections that were rejected by the
+    /// [AuthCallback](struct.AuthCallback.html)
+    pub fn rejected_auth_count(&self) -> usize {
+        self.rejected_auth_count.get() as usize
+    }
+
+    /// Number of connections that were initiated, but were never added to the socket nor rejected,
+    /// i.e., the initiated connections that are neither counted by [tot_conn_count()](#method.tot_conn_count),
+    /// [rejected_conn_count()](#method.rejected_conn_count), nor [rejected_auth_count()](#method.rejected_auth_count)
+    /// - connections that are rejected because the server is at its max connections cap, or by the
+    ///   AuthCallback, are excluded
+    /// - a connection that is in the process of being set up is counted until it is added
+    pub fn connection_setup_failures(&self) -> usize {
+        self.tot_conn_initiate_count()
+            .saturating_sub(self.tot_conn_count())
+            .saturating_sub(self.rejected_conn_count())
+            .saturating_sub(self.rejected_auth_count())
+    }
+
+    /// Total number of requests that were rejected because the backend service was at capacity
+    /// - see [Backpressure::Reject](enum.Backpressure.html#variant.Reject)
+    pub fn rejected_request_count(&self) -> usize {
+        self.rejected_request_count.get() as usize
+    }
+
+    /// Total number of requests that were dropped because the backend service was at capacity
+    /// - see [Backpressure::Shed](enum.Backpressure.html#variant.Shed)
+    pub fn shed_request_count(&self) -> usize {
+        self.shed_request_count.get() as usize
+    }
+
+    /// Reply message size distribution in bytes
+    /// - the histogram buckets are defined by [REPLY_SIZE_BUCKETS](constant.REPLY_SIZE_BUCKETS.html)
+    pub fn reply_size(&self) -> prometheus::proto::Histogram {
+        self.reply_size.metric().take_histogram()
+    }
+}
+
+impl fmt::Debug for ServerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"ServerMetrics(active_conn_count = {}, tot_conn_count = {}, tot_conn_initiate_count = {}, rejected_conn_count = {}, rejected_auth_count = {}, connection_setup_failures = {}, rejected_request_count = {}, shed_request_count = {}, reply_count = {})",
+               self.active_conn_count.get(),
+               self.tot_conn_count.get(),
+               self.tot_conn_initiate_count.get(),
+               self.rejected_conn_count.get(),
+               self.rejected_auth_count.get(),
+               self.connection_setup_failures(),
+               self.rejected_request_count.get(),
+               self.shed_request_count.get(),
+               self.reply_size.metric().get_histogram().get_sample_count()
+        )
+    }
+}
+
+/// Versioned [ListenerConfig](struct.ListenerConfig.html), which is used to persist the config
+/// using binary formats that are not self-describing, e.g., bincode.
+/// - missing fields cannot be defaulted by binary formats, thus each schema version is a variant.
+///   New schema versions are appended as new variants.
+/// - self-describing formats, e.g., JSON, can persist the ListenerConfig directly because fields
+///   that were added since are defaulted when missing
+/// - configs that were persisted before versioning was introduced deserialize as
+///   [ListenerConfigV0](struct.ListenerConfigV0.html)
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VersionedListenerConfig {
+    /// schema that was persisted before versioning was introduced
+    V0(ListenerConfigV0),
+    /// current schema - see [CONFIG_VERSION](../../config/constant.CONFIG_VERSION.html)
+    V1(ListenerConfig),
+}
+
+impl VersionedListenerConfig {
+    /// Returns the schema version
+    pub fn version(&self) -> u32 {
+        match self {
+            VersionedListenerConfig::V0(_) => 0,
+            VersionedListenerConfig::V1(_) => crate::config::CONFIG_VERSION,
+        }
+    }
+
+    /// Upgrades the config to the current schema version
+    /// - fields that were added since the persisted schema version are set to their defaults
+    pub fn migrate(self) -> ListenerConfig {
+        match self {
+            VersionedListenerConfig::V0(config) => config.into(),
+            VersionedListenerConfig::V1(config) => config,
+        }
+    }
+}
+
+impl From<ListenerConfig> for VersionedListenerConfig {
+    fn from(config: ListenerConfig) -> VersionedListenerConfig {
+        VersionedListenerConfig::V1(config)
+    }
+}
+
+impl From<ListenerConfigV0> for VersionedListenerConfig {
+    fn from(config: ListenerConfigV0) -> VersionedListenerConfig {
+        VersionedListenerConfig::V0(config)
+    }
+}
+
+/// ListenerConfig schema version 0, i.e., the schema that was persisted before versioning was
+/// introduced
+/// - the fields must match the released schema exactly, i.e., fields that were added since are
+///   defaulted when the config is migrated
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ListenerConfigV0 {
+    #[serde(with = "url_serde")]
+    url: url::Url,
+    recv_max_size: Option<usize>,
+    no_delay: Option<bool>,
+    keep_alive: Option<bool>,
+    non_blocking: bool,
+    parallelism: usize,
+}
+
+impl From<ListenerConfigV0> for ListenerConfig {
+    fn from(config: ListenerConfigV0) -> ListenerConfig {
+        ListenerConfig {
+            url: config.url,
+            recv_max_size: config.recv_max_size,
+            no_delay: config.no_delay,
+            keep_alive: config.keep_alive,
+            non_blocking: config.non_blocking,
+            parallelism: config.parallelism,
+            allow_wildcard_bind: false,
+            reply_on_service_error: false,
+            max_connections: None,
+            backpressure: Backpressure::default(),
+            auth_callback: None,
+        }
+    }
+}
+
+/// Listener configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ListenerConfig {
+    #[serde(with = "url_serde")]
+    url: url::Url,
+    recv_max_size: Option<usize>,
+    no_delay: Option<bool>,
+    keep_alive: Option<bool>,
+    non_blocking: bool,
+    parallelism: usize,
+    #[serde(default)]
+    allow_wildcard_bind: bool,
+    #[serde(default)]
+    reply_on_service_error: bool,
+    #[serde(default)]
+    max_connections: Option<usize>,
+    #[serde(default)]
+    backpressure: Backpressure,
+    #[serde(skip)]
+    auth_callback: Option<AuthCallback>,
+}
+
+/// Connection info that is available when a connection is initiated, i.e., before the connection is
+/// added to the socket
+#[derive(Debug, Clone)]
+pub struct PipeInfo {
+    pipe_id: i32,
+    remote_address: Option<nng::SocketAddr>,
+}
+
+impl PipeInfo {
+    /// nng pipe id, which identifies the connection
+    pub fn pipe_id(&self) -> i32 {
+        self.pipe_id
+    }
+
+    /// the remote peer's address
+    /// - returns None if the address could not be retrieved from the pipe
+    pub fn remote_address(&self) -> Option<&nng::SocketAddr> {
+        self.remote_address.as_ref()
+    }
+
+    /// the remote peer's IP address
+    /// - returns None for transports that are not IP based, e.g., inproc and ipc
+    pub fn remote_ip(&self) -> Option<IpAddr> {
+        match self.remote_address {
+            Some(nng::SocketAddr::Inet(addr)) => Some(IpAddr::V4(*addr.ip())),
+            Some(nng::SocketAddr::Inet6(addr)) => Some(IpAddr::V6(*addr.ip())),
+            _ => None,
+        }
+    }
+}
+
+/// Connection authentication callback, which decides whether a connection is accepted
+/// - the callback is invoked on nng::PipeEvent::AddPre, i.e., before the connection is added to the
+///   socket - returning false closes the connection
+/// - the callback is invoked on nng's pipe notification thread, thus it should not block
+/// - the callback is not serialized as part of the ListenerConfig, i.e., it needs to be set
+///   programmatically
+/// - AuthCallback(s) are equal if they share the same callback instance
+#[derive(Clone)]
+pub struct AuthCallback(Arc<dyn Fn(&PipeInfo) -> bool + Send + Sync>);
+
+impl AuthCallback {
+    /// constructor
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&PipeInfo) -> bool + Send + Sync + 'static,
+    {
+        AuthCallback(Arc::new(callback))
+    }
+
+    /// returns true if the connection is accepted
+    pub fn authenticate(&self, pipe_info: &PipeInfo) -> bool {
+        (self.0)(pipe_info)
+    }
+}
+
+impl fmt::Debug for AuthCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AuthCallback")
+    }
+}
+
+impl PartialEq for AuthCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for AuthCallback {}
+
+/// Determines how a server worker handles a request when the backend ReqRep service is at capacity
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Backpressure {
+    /// The worker waits until the service accepts the request. While waiting, the worker does not
+    /// receive new requests from the socket.
+    Await,
+    /// A [ServiceError](struct.ServiceError.html) reply is sent back immediately
+    Reject,
+    /// The request is dropped, and the client will time out
+    Shed,
+}
+
+impl Default for Backpressure {
+    fn default() -> Self {
+        Backpressure::Await
+    }
+}
+
+/// Constructs a TCP URL that binds to the specified interface address.
+///
+/// Wildcard addresses, i.e., `0.0.0.0` and `::`, bind to all interfaces, which may expose the server
+/// publicly. Thus, wildcard addresses are rejected unless `allow_wildcard` is true.
+pub fn bind_interface(
+    addr: SocketAddr,
+    allow_wildcard: bool,
+) -> Result<url::Url, ListenerConfigError> {
+    if addr.ip().is_unspecified() && !allow_wildcard {
+        return Err(ListenerConfigError::WildcardBindNotAllowed(addr.to_string()));
+    }
+    url::Url::parse(&format!("tcp://{}", addr))
+        .map_err(|err| ListenerConfigError::InvalidUrl(err.to_string()))
+}
+
+/// Returns true if the URL binds to all interfaces, i.e., its host is `*`, `0.0.0.0`, `::`, or empty
+fn is_wildcard_bind(url: &url::Url) -> bool {
+    match url.scheme() {
+        "inproc" | "ipc" => false,
+        _ => match url.host_str() {
+            None | Some("") | Some("*") => true,
+            Some(host) => host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .map(|ip| ip.is_unspecified())
+                .unwrap_or(false),
+        },
+    }
+}
+
+/// URL schemes for the transports that are supported by nng
+const SUPPORTED_URL_SCHEMES: &[&str] = &[
+    "inproc", "ipc", "tcp", "tcp4", "tcp6", "tls+tcp", "tls+tcp4", "tls+tcp6", "ws", "ws4", "ws6",
+    "wss", "wss4", "wss6", "zt",
+];
+
+impl ListenerConfig {
+    /// constructor
+    /// - refer to nng for supported [transports](https://nanomsg.github.io/nng/man/v1.1.0/index.html#_section_7_protocols_and_transports)
+    ///
+    /// ## Default settings
     /// - non_blocking = true
-    /// - parallelism = num of available CPUs + 1
+    /// - parallelism = [effective parallelism](../../config/fn.effective_parallelism.html) + 1
+    /// - allow_wildcard_bind = false
+    /// - reply_on_service_error = false
+    /// - max_connections = None, i.e., unlimited
+    /// - backpressure = Backpressure::Await
+    /// - auth_callback = None, i.e., all connections are accepted
     pub fn new(url: url::Url) -> ListenerConfig {
         ListenerConfig {
             url,
@@ -669,7 +2046,12 @@ impl ListenerConfig {
             no_delay: None,
             keep_alive: None,
             non_blocking: true,
-            parallelism: num_cpus::get() + 1,
+            parallelism: crate::config::effective_parallelism() + 1,
+            allow_wildcard_bind: false,
+            reply_on_service_error: false,
+            max_connections: None,
+            backpressure: Backpressure::Await,
+            auth_callback: None,
         }
     }
 
@@ -686,6 +2068,12 @@ impl ListenerConfig {
         &self,
         socket: &nng::Socket,
     ) -> Result<nng::Listener, ListenerConfigError> {
+        if !self.allow_wildcard_bind && is_wildcard_bind(&self.url) {
+            return Err(ListenerConfigError::WildcardBindNotAllowed(
+                self.url.to_string(),
+            ));
+        }
+
         let options = nng::ListenerOptions::new(socket, self.url().as_str())
             .map_err(ListenerConfigError::ListenerOptionsCreateFailed)?;
 
@@ -712,147 +2100,919 @@ impl ListenerConfig {
             .map_err(|(_options, err)| ListenerConfigError::ListenerStartFailed(err))
     }
 
-    /// the address that the server is listening on
-    pub fn url(&self) -> &url::Url {
-        &self.url
+    /// the address that the server is listening on
+    pub fn url(&self) -> &url::Url {
+        &self.url
+    }
+
+    /// if true, then it binds to the address asynchronously
+    pub fn non_blocking(&self) -> bool {
+        self.non_blocking
+    }
+
+    /// if true, then the listener is allowed to bind to all interfaces, e.g., `tcp://0.0.0.0:5555`
+    /// - default = false
+    pub fn allow_wildcard_bind(&self) -> bool {
+        self.allow_wildcard_bind
+    }
+
+    /// if true, then a [ServiceError](struct.ServiceError.html) reply is sent back to the client when
+    /// the backend ReqRep service fails to process the request. Otherwise, the request is dropped and
+    /// the client will time out.
+    /// - default = false
+    pub fn reply_on_service_error(&self) -> bool {
+        self.reply_on_service_error
+    }
+
+    /// Validates the listener config, along with the socket config, without allocating any resources.
+    /// - returns all problems that were found, i.e., an empty Vec means the config is valid
+    pub fn validate(&self, socket_config: Option<&SocketConfig>) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let scheme = self.url.scheme();
+        if !SUPPORTED_URL_SCHEMES.contains(&scheme) {
+            problems.push(ConfigProblem::UnsupportedUrlScheme(scheme.to_string()));
+        }
+        if !self.allow_wildcard_bind && is_wildcard_bind(&self.url) {
+            problems.push(ConfigProblem::WildcardBindNotAllowed(self.url.to_string()));
+        }
+        let max_parallelism = crate::config::max_aio_contexts();
+        if self.parallelism == 0 {
+            problems.push(ConfigProblem::ZeroParallelism);
+        } else if self.parallelism > max_parallelism {
+            problems.push(ConfigProblem::ParallelismTooHigh {
+                parallelism: self.parallelism,
+                max: max_parallelism,
+            });
+        }
+        if scheme == "inproc" || scheme == "ipc" {
+            let tcp_options = [
+                ("no_delay", self.no_delay.is_some()),
+                ("keep_alive", self.keep_alive.is_some()),
+                (
+                    "tcp_no_delay",
+                    socket_config.map_or(false, |config| config.tcp_no_delay().is_some()),
+                ),
+                (
+                    "tcp_keep_alive",
+                    socket_config.map_or(false, |config| config.tcp_keep_alive().is_some()),
+                ),
+            ];
+            for (option, _) in tcp_options.iter().filter(|(_, is_set)| *is_set) {
+                problems.push(ConfigProblem::TcpOptionNotSupported {
+                    option: *option,
+                    scheme: scheme.to_string(),
+                });
+            }
+        }
+        problems
+    }
+
+    /// Max number of concurrent connections that the server will accept.
+    /// - new connections that would exceed the cap are closed immediately, and tracked via the
+    ///   `REJECTED_CONN_COUNT_METRIC_ID` metric
+    /// - default = None, i.e., unlimited
+    pub fn max_connections(&self) -> Option<usize> {
+        self.max_connections
+    }
+
+    /// How requests are handled when the backend ReqRep service is at capacity
+    /// - default = Backpressure::Await
+    pub fn backpressure(&self) -> Backpressure {
+        self.backpressure
+    }
+
+    /// Connection authentication callback
+    /// - default = None, i.e., all connections are accepted
+    pub fn auth_callback(&self) -> Option<&AuthCallback> {
+        self.auth_callback.as_ref()
+    }
+
+    /// Number of outstanding requests that the server can handle at a given time.
+    ///
+    /// This is *NOT* the number of threads in use, but instead represents outstanding work items.
+    pub fn parallelism(&self) -> usize {
+        self.parallelism
+    }
+
+    /// The maximum message size that the will be accepted from a remote peer.
+    ///
+    /// If a peer attempts to send a message larger than this, then the message will be discarded.
+    /// If the value of this is zero, then no limit on message sizes is enforced. This option exists
+    /// to prevent certain kinds of denial-of-service attacks, where a malicious agent can claim to
+    /// want to send an extraordinarily large message, without sending any data. This option can be
+    /// set for the socket, but may be overridden for on a per-dialer or per-listener basis.
+    pub fn recv_max_size(&self) -> Option<usize> {
+        self.recv_max_size
+    }
+
+    /// When true (the default), messages are sent immediately by the underlying TCP stream without waiting to gather more data.
+    /// When false, Nagle's algorithm is enabled, and the TCP stream may wait briefly in attempt to coalesce messages.
+    ///
+    /// Nagle's algorithm is useful on low-bandwidth connections to reduce overhead, but it comes at a cost to latency.
+    pub fn no_delay(&self) -> Option<bool> {
+        self.no_delay
+    }
+
+    /// Enable the sending of keep-alive messages on the underlying TCP stream.
+    ///
+    /// This option is false by default. When enabled, if no messages are seen for a period of time,
+    /// then a zero length TCP message is sent with the ACK flag set in an attempt to tickle some traffic
+    /// from the peer. If none is still seen (after some platform-specific number of retries and timeouts),
+    /// then the remote peer is presumed dead, and the connection is closed.
+    ///
+    /// This option has two purposes. First, it can be used to detect dead peers on an otherwise quiescent
+    /// network. Second, it can be used to keep connection table entries in NAT and other middleware
+    /// from being expiring due to lack of activity.
+    pub fn keep_alive(&self) -> Option<bool> {
+        self.keep_alive
+    }
+
+    /// Sets the maximum message size that the will be accepted from a remote peer.
+    pub fn set_recv_max_size(mut self, recv_max_size: usize) -> Self {
+        self.recv_max_size = Some(recv_max_size);
+        self
+    }
+
+    /// Sets no delay setting on TCP connection
+    pub fn set_no_delay(mut self, no_delay: bool) -> Self {
+        self.no_delay = Some(no_delay);
+        self
+    }
+
+    /// Sets keep alive setting on TCP connection
+    pub fn set_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// Normally, the act of "binding" to the address indicated by url is done synchronously, including
+    /// any necessary name resolution. As a result, a failure, such as if the address is already in use,
+    /// will be returned immediately. However, if nonblocking is specified then this is done asynchronously;
+    /// furthermore any failure to bind will be periodically reattempted in the background.
+    pub fn set_non_blocking(mut self, non_blocking: bool) -> Self {
+        self.non_blocking = non_blocking;
+        self
+    }
+
+    /// Explicitly opt in to binding to all interfaces, e.g., `tcp://0.0.0.0:5555`. By default, wildcard
+    /// binds are rejected in order to prevent accidental public exposure.
+    pub fn set_allow_wildcard_bind(mut self, allow_wildcard_bind: bool) -> Self {
+        self.allow_wildcard_bind = allow_wildcard_bind;
+        self
+    }
+
+    /// Opt in to sending back a [ServiceError](struct.ServiceError.html) reply when the backend ReqRep
+    /// service fails to process the request
+    pub fn set_reply_on_service_error(mut self, reply_on_service_error: bool) -> Self {
+        self.reply_on_service_error = reply_on_service_error;
+        self
+    }
+
+    /// Caps the number of concurrent connections. When the server is at the cap, new connections are
+    /// closed immediately - existing connections are unaffected.
+    pub fn set_max_connections(mut self, max_connections: NonZeroUsize) -> Self {
+        self.max_connections = Some(max_connections.get());
+        self
+    }
+
+    /// Sets the [Backpressure](enum.Backpressure.html) strategy that is applied when the backend
+    /// ReqRep service is at capacity
+    pub fn set_backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Sets the [AuthCallback](struct.AuthCallback.html), which can veto connections before they
+    /// are added to the socket
+    pub fn set_auth_callback(mut self, auth_callback: AuthCallback) -> Self {
+        self.auth_callback = Some(auth_callback);
+        self
+    }
+
+    /// set the number of async IO operations that can be performed concurrently
+    /// - the count is validated when the server is spawned against [max_aio_contexts()](../../config/fn.max_aio_contexts.html)
+    pub fn set_aio_count(mut self, count: NonZeroUsize) -> Self {
+        self.parallelism = count.get();
+        self
+    }
+}
+
+/// Socket config related errors
+#[derive(Debug, Fail)]
+pub enum ListenerConfigError {
+    /// Failed to create ListenerOpion
+    #[fail(display = "Failed to create ListenerOpions: {}", _0)]
+    ListenerOptionsCreateFailed(#[cause] nng::Error),
+    /// Failed start the Listener
+    #[fail(display = "Failed start the Listener: {}", _0)]
+    ListenerStartFailed(#[cause] nng::Error),
+    ///Failed to set the RecvMaxSize Socket option
+    #[fail(display = "Failed to set the RecvMaxSize Socket option: {}", _0)]
+    RecvMaxSize(#[cause] nng::Error),
+    /// Failed to set the TcpNoDelay Socket option
+    #[fail(display = "Failed to set the TcpNoDelay Socket option: {}", _0)]
+    TcpNoDelay(#[cause] nng::Error),
+    /// Failed to set the TcpKeepAlive Socket option
+    #[fail(display = "Failed to set the TcpKeepAlive Socket option: {}", _0)]
+    TcpKeepAlive(#[cause] nng::Error),
+    /// Binding to all interfaces was not explicitly allowed
+    #[fail(
+        display = "Binding to all interfaces is not allowed - use a specific interface address, or explicitly opt in: {}",
+        _0
+    )]
+    WildcardBindNotAllowed(String),
+    /// Invalid URL
+    #[fail(display = "Invalid URL: {}", _0)]
+    InvalidUrl(String),
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use oysterpack_trust::{
+        concurrent::{
+            execution::{self, *},
+            messaging::reqrep::{self, *},
+        },
+        metrics,
+    };
+    use oysterpack_uid::ULID;
+    use oysterpack_uid::*;
+    use std::thread;
+
+    struct EchoService;
+    impl Processor<nng::Message, nng::Message> for EchoService {
+        fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+            async move { req }.boxed()
+        }
+    }
+
+    const REQREP_ID: ReqRepId = ReqRepId(1871557337320005579010710867531265404);
+
+    fn start_service() -> ReqRep<nng::Message, nng::Message> {
+        let timer_buckets = metrics::timer_buckets(vec![
+            Duration::from_nanos(50),
+            Duration::from_nanos(100),
+            Duration::from_nanos(150),
+            Duration::from_nanos(200),
+        ])
+        .unwrap();
+
+        ReqRepConfig::new(REQREP_ID, timer_buckets)
+            .start_service(EchoService, global_executor().clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn wildcard_bind_not_allowed() {
+        configure_logging();
+
+        // GIVEN: a ListenerConfig that binds to all interfaces without opting in
+        let url = url::Url::parse("tcp://0.0.0.0:5555").unwrap();
+        // WHEN: the server is spawned
+        match super::spawn(
+            None,
+            ListenerConfig::new(url),
+            start_service(),
+            global_executor(),
+        ) {
+            // THEN: the server fails to start
+            Err(SpawnError::ListenerStartFailure(ListenerConfigError::WildcardBindNotAllowed(
+                url,
+            ))) => info!("wildcard bind was rejected: {}", url),
+            Err(err) => panic!(
+                "expected ListenerConfigError::WildcardBindNotAllowed, but got: {}",
+                err
+            ),
+            Ok(_) => panic!("expected ListenerConfigError::WildcardBindNotAllowed"),
+        }
+
+        // THEN: wildcard hosts are detected
+        for url in &["tcp://0.0.0.0:5555", "tcp://*:5555", "tcp://[::]:5555"] {
+            assert!(super::is_wildcard_bind(&url::Url::parse(url).unwrap()));
+        }
+        for url in &["tcp://127.0.0.1:5555", "tcp://[::1]:5555", "inproc://foo"] {
+            assert!(!super::is_wildcard_bind(&url::Url::parse(url).unwrap()));
+        }
+
+        // THEN: bind_interface rejects wildcard addresses unless explicitly allowed
+        let addr: SocketAddr = "0.0.0.0:5555".parse().unwrap();
+        match super::bind_interface(addr, false) {
+            Err(ListenerConfigError::WildcardBindNotAllowed(_)) => (),
+            other => panic!(
+                "expected ListenerConfigError::WildcardBindNotAllowed, but got: {:?}",
+                other
+            ),
+        }
+        assert!(super::bind_interface(addr, true).is_ok());
+        let addr: SocketAddr = "127.0.0.1:5555".parse().unwrap();
+        assert_eq!(
+            super::bind_interface(addr, false).unwrap().as_str(),
+            "tcp://127.0.0.1:5555"
+        );
+    }
+
+    #[test]
+    fn server_group() {
+        configure_logging();
+
+        let start_service = |reqrep_id: ReqRepId| {
+            let timer_buckets = metrics::timer_buckets(vec![
+                Duration::from_nanos(50),
+                Duration::from_nanos(100),
+            ])
+            .unwrap();
+            ReqRepConfig::new(reqrep_id, timer_buckets)
+                .start_service(EchoService, global_executor().clone())
+                .unwrap()
+        };
+        let inproc_url = || url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+
+        // GIVEN: a group of 2 servers
+        let reqrep_id = ReqRepId(ULID::generate().into());
+        let members = vec![
+            ServerGroupMember::new(ListenerConfig::new(inproc_url()), start_service(reqrep_id)),
+            ServerGroupMember::new(ListenerConfig::new(inproc_url()), start_service(reqrep_id)),
+        ];
+        // WHEN: the group is spawned
+        let mut server_group = ServerGroup::spawn(members, global_executor()).unwrap();
+        // THEN: both servers are alive
+        assert_eq!(server_group.servers().len(), 2);
+        assert!(server_group.health());
+        assert!(server_group.ping().iter().all(|(_, alive)| *alive));
+        assert_eq!(server_group.metrics().len(), 2);
+        assert_eq!(ServerHandle::get_by_reqrep_id(reqrep_id).len(), 2);
+
+        // WHEN: the group is stopped
+        let ids = server_group.ids();
+        global_executor().run(server_group.stop_all());
+        // THEN: all servers are unregistered
+        assert!(server_group.servers().is_empty());
+        assert!(ids.iter().all(|id| ServerHandle::get(*id).is_none()));
+
+        // GIVEN: a group where the second member is forced to fail to spawn
+        let reqrep_id = ReqRepId(ULID::generate().into());
+        let members = vec![
+            ServerGroupMember::new(ListenerConfig::new(inproc_url()), start_service(reqrep_id)),
+            ServerGroupMember::new(
+                ListenerConfig::new(url::Url::parse("tcp://0.0.0.0:5555").unwrap()),
+                start_service(reqrep_id),
+            ),
+        ];
+        // WHEN: the group is spawned
+        match ServerGroup::spawn(members, global_executor()) {
+            // THEN: spawning the group fails
+            Err(SpawnError::ListenerStartFailure(ListenerConfigError::WildcardBindNotAllowed(
+                _,
+            ))) => (),
+            Err(err) => panic!(
+                "expected ListenerConfigError::WildcardBindNotAllowed, but got: {}",
+                err
+            ),
+            Ok(_) => panic!("expected ServerGroup spawn to fail"),
+        }
+        // AND: the first server was rolled back
+        assert!(ServerHandle::get_by_reqrep_id(reqrep_id).is_empty());
+    }
+
+    #[test]
+    fn reply_on_service_error() {
+        configure_logging();
+
+        struct PanickingService;
+        impl Processor<nng::Message, nng::Message> for PanickingService {
+            fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+                async move { panic!("BOOM!!!") }.boxed()
+            }
+        }
+
+        // GIVEN: a server whose backend service fails and that is configured to reply on service errors
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let service = ReqRepConfig::new(reqrep_id, timer_buckets)
+            .start_service(PanickingService, global_executor())
+            .unwrap();
+        let mut server_handle = super::spawn(
+            None,
+            ListenerConfig::new(url.clone()).set_reply_on_service_error(true),
+            service,
+            global_executor(),
+        )
+        .unwrap();
+
+        // GIVEN: a client that connects to the server
+        let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        s.set_opt::<nng::options::RecvTimeout>(Some(Duration::from_secs(5)))
+            .unwrap();
+        s.dial(url.as_str()).unwrap();
+
+        // WHEN: the client submits a request
+        s.send(nng::Message::new().unwrap()).unwrap();
+        // THEN: the client receives a ServiceError reply, i.e., the client does not time out
+        let reply = s.recv().unwrap();
+        let service_error = ServiceError::decode(&reply).unwrap();
+        info!("{}", service_error);
+        assert_eq!(service_error.reqrep_id(), reqrep_id);
+
+        // THEN: non ServiceError messages are not decoded as a ServiceError
+        assert!(ServiceError::decode(&nng::Message::new().unwrap()).is_none());
+
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn backpressure_reject() {
+        configure_logging();
+
+        // blocks processing the first request until the gate is opened
+        struct Paused(Option<futures::channel::oneshot::Receiver<()>>);
+        impl Processor<nng::Message, nng::Message> for Paused {
+            fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+                let gate = self.0.take();
+                async move {
+                    if let Some(gate) = gate {
+                        let _ = await!(gate);
+                    }
+                    req
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a paused backend service with a capacity of 2 requests
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let (gate_tx, gate_rx) = futures::channel::oneshot::channel();
+        let service = ReqRepConfig::new(reqrep_id, timer_buckets)
+            .set_chan_buf_size(1)
+            .start_service(Paused(Some(gate_rx)), global_executor())
+            .unwrap();
+        // AND: a server that is configured to reject requests when the service is full
+        let mut server_handle = super::spawn(
+            None,
+            ListenerConfig::new(url.clone())
+                .set_aio_count(NonZeroUsize::new(4).unwrap())
+                .set_backpressure(Backpressure::Reject),
+            service.clone(),
+            global_executor(),
+        )
+        .unwrap();
+
+        let client = || {
+            let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+            s.set_opt::<nng::options::RecvTimeout>(Some(Duration::from_secs(5)))
+                .unwrap();
+            s.dial(url.as_str()).unwrap();
+            s
+        };
+
+        // WHEN: the backend service is filled
+        let clients: Vec<nng::Socket> = (0..2)
+            .map(|i| {
+                let s = client();
+                s.send(nng::Message::new().unwrap()).unwrap();
+                // wait for the request to reach the service
+                for _ in 0..100 {
+                    if service.pending_request_count() > i {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                s
+            })
+            .collect();
+        assert_eq!(service.pending_request_count(), 2);
+
+        // WHEN: another request is sent
+        let s = client();
+        let start = Instant::now();
+        s.send(nng::Message::new().unwrap()).unwrap();
+        // THEN: the client promptly receives a ServiceError reply, i.e., it does not stall
+        let reply = s.recv().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let service_error = ServiceError::decode(&reply).unwrap();
+        info!("{}", service_error);
+        assert_eq!(service_error.reqrep_id(), reqrep_id);
+        assert_eq!(service_error.message(), ChannelError::Full.to_string());
+        // AND: the rejected request is tracked
+        assert_eq!(server_handle.metrics().rejected_request_count(), 1);
+        assert_eq!(server_handle.metrics().shed_request_count(), 0);
+
+        // WHEN: the backend service is resumed
+        gate_tx.send(()).unwrap();
+        // THEN: the accepted requests are processed
+        for s in clients {
+            let reply = s.recv().unwrap();
+            assert!(ServiceError::decode(&reply).is_none());
+        }
+
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn active_conn_count_reset_on_shutdown() {
+        configure_logging();
+
+        // GIVEN: a server with active connections
+        // - a unique ReqRepId is used to isolate the connection metrics from other tests
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let service = ReqRepConfig::new(ReqRepId::generate(), timer_buckets)
+            .start_service(EchoService, global_executor())
+            .unwrap();
+        let mut server_handle =
+            super::spawn(None, ListenerConfig::new(url.clone()), service, global_executor())
+                .unwrap();
+        let clients: Vec<nng::Socket> = (0..2)
+            .map(|_| {
+                let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+                s.dial(url.as_str()).unwrap();
+                s.send(nng::Message::new().unwrap()).unwrap();
+                let _ = s.recv().unwrap();
+                s
+            })
+            .collect();
+        let metrics = server_handle.metrics().clone();
+        assert_eq!(metrics.active_conn_count(), 2);
+        assert_eq!(server_handle.connections().len(), 2);
+
+        // WHEN: the server is stopped while the clients are still connected
+        server_handle.stop_async().unwrap();
+        let connections = server_handle.connections.clone();
+        server_handle.await_shutdown();
+
+        // THEN: the active connection count gauge is reset
+        assert_eq!(metrics.active_conn_count(), 0);
+        assert!(connections.read().is_empty());
+        // AND: it stays reset after the clients disconnect
+        for client in clients {
+            client.close();
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(metrics.active_conn_count(), 0);
+    }
+
+    #[test]
+    fn reply_size_metric() {
+        configure_logging();
+
+        // GIVEN: a server running an echo service
+        // - a unique ReqRepId is used to isolate the reply size metric from other tests
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let service = ReqRepConfig::new(ReqRepId::generate(), timer_buckets)
+            .start_service(EchoService, global_executor())
+            .unwrap();
+        let mut server_handle =
+            super::spawn(None, ListenerConfig::new(url.clone()), service, global_executor())
+                .unwrap();
+        assert_eq!(server_handle.metrics().reply_size().get_sample_count(), 0);
+
+        // WHEN: replies of known sizes are sent back
+        let reply_sizes = [10_usize, 100, 2000];
+        let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        s.dial(url.as_str()).unwrap();
+        for size in reply_sizes.iter() {
+            let mut req = nng::Message::with_capacity(*size).unwrap();
+            req.push_back(&vec![1_u8; *size]).unwrap();
+            s.send(req).unwrap();
+            let reply = s.recv().unwrap();
+            assert_eq!(reply.len(), *size);
+        }
+
+        // THEN: the sample count matches the number of replies
+        let histogram = server_handle.metrics().reply_size();
+        info!("{:?}", histogram);
+        assert_eq!(histogram.get_sample_count(), reply_sizes.len() as u64);
+        assert_eq!(histogram.get_sample_sum(), 2110.0);
+        // AND: the buckets reflect the reply sizes
+        let bucket_upper_bounds: Vec<f64> = histogram
+            .get_bucket()
+            .iter()
+            .map(|bucket| bucket.get_upper_bound())
+            .collect();
+        assert_eq!(bucket_upper_bounds, REPLY_SIZE_BUCKETS.to_vec());
+        let cumulative_count = |upper_bound: f64| {
+            histogram
+                .get_bucket()
+                .iter()
+                .find(|bucket| bucket.get_upper_bound() == upper_bound)
+                .map(|bucket| bucket.get_cumulative_count())
+                .unwrap()
+        };
+        assert_eq!(cumulative_count(64.0), 1);
+        assert_eq!(cumulative_count(256.0), 2);
+        assert_eq!(cumulative_count(1024.0), 2);
+        assert_eq!(cumulative_count(4096.0), 3);
+
+        s.close();
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
     }
 
-    /// if true, then it binds to the address asynchronously
-    pub fn non_blocking(&self) -> bool {
-        self.non_blocking
-    }
+    #[test]
+    fn server_connections() {
+        configure_logging();
 
-    /// Number of outstanding requests that the server can handle at a given time.
-    ///
-    /// This is *NOT* the number of threads in use, but instead represents outstanding work items.
-    pub fn parallelism(&self) -> usize {
-        self.parallelism
-    }
+        // GIVEN: a server listening on TCP
+        let url = url::Url::parse("tcp://127.0.0.1:5961").unwrap();
+        let mut server_handle = super::spawn(
+            None,
+            ListenerConfig::new(url.clone()),
+            start_service(),
+            global_executor(),
+        )
+        .unwrap();
+        assert!(server_handle.connections().is_empty());
+
+        // WHEN: 2 clients connect to the server
+        let clients: Vec<nng::Socket> = (0..2)
+            .map(|_| {
+                let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+                s.dial(url.as_str()).unwrap();
+                s.send(nng::Message::new().unwrap()).unwrap();
+                let _ = s.recv().unwrap();
+                s
+            })
+            .collect();
+
+        // THEN: the server reports 2 connections with distinct remote addresses
+        let connections = server_handle.connections();
+        info!("{:#?}", connections);
+        assert_eq!(connections.len(), 2);
+        assert_ne!(connections[0].pipe_id(), connections[1].pipe_id());
+        assert!(connections[0].remote_address().is_some());
+        assert_ne!(
+            connections[0].remote_address(),
+            connections[1].remote_address()
+        );
+
+        // WHEN: the clients disconnect
+        for client in clients {
+            client.close();
+        }
+        // THEN: the connections are removed
+        for _ in 0..100 {
+            if server_handle.connections().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(server_handle.connections().is_empty());
 
-    /// The maximum message size that the will be accepted from a remote peer.
-    ///
-    /// If a peer attempts to send a message larger than this, then the message will be discarded.
-    /// If the value of this is zero, then no limit on message sizes is enforced. This option exists
-    /// to prevent certain kinds of denial-of-service attacks, where a malicious agent can claim to
-    /// want to send an extraordinarily large message, without sending any data. This option can be
-    /// set for the socket, but may be overridden for on a per-dialer or per-listener basis.
-    pub fn recv_max_size(&self) -> Option<usize> {
-        self.recv_max_size
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
     }
 
-    /// When true (the default), messages are sent immediately by the underlying TCP stream without waiting to gather more data.
-    /// When false, Nagle's algorithm is enabled, and the TCP stream may wait briefly in attempt to coalesce messages.
-    ///
-    /// Nagle's algorithm is useful on low-bandwidth connections to reduce overhead, but it comes at a cost to latency.
-    pub fn no_delay(&self) -> Option<bool> {
-        self.no_delay
-    }
+    #[test]
+    fn server_max_connections() {
+        configure_logging();
 
-    /// Enable the sending of keep-alive messages on the underlying TCP stream.
-    ///
-    /// This option is false by default. When enabled, if no messages are seen for a period of time,
-    /// then a zero length TCP message is sent with the ACK flag set in an attempt to tickle some traffic
-    /// from the peer. If none is still seen (after some platform-specific number of retries and timeouts),
-    /// then the remote peer is presumed dead, and the connection is closed.
-    ///
-    /// This option has two purposes. First, it can be used to detect dead peers on an otherwise quiescent
-    /// network. Second, it can be used to keep connection table entries in NAT and other middleware
-    /// from being expiring due to lack of activity.
-    pub fn keep_alive(&self) -> Option<bool> {
-        self.keep_alive
-    }
+        // GIVEN: a server that is capped at 1 connection
+        let url = url::Url::parse("tcp://127.0.0.1:5962").unwrap();
+        let mut server_handle = super::spawn(
+            None,
+            ListenerConfig::new(url.clone()).set_max_connections(NonZeroUsize::new(1).unwrap()),
+            start_service(),
+            global_executor(),
+        )
+        .unwrap();
 
-    /// Sets the maximum message size that the will be accepted from a remote peer.
-    pub fn set_recv_max_size(mut self, recv_max_size: usize) -> Self {
-        self.recv_max_size = Some(recv_max_size);
-        self
+        // WHEN: the first client connects
+        let mut client_1 = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        client_1.dial(url.as_str()).unwrap();
+        // THEN: the client is served
+        client_1.send(nng::Message::new().unwrap()).unwrap();
+        let _ = client_1.recv().unwrap();
+        assert_eq!(server_handle.connections().len(), 1);
+
+        // WHEN: a second client connects
+        let mut client_2 = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        client_2
+            .set_opt::<nng::options::RecvTimeout>(Some(Duration::from_millis(200)))
+            .unwrap();
+        // the dialer is started non-blocking because the server closes the connection
+        let _dialer = nng::DialerOptions::new(&client_2, url.as_str())
+            .unwrap()
+            .start(true);
+        let _ = client_2.send(nng::Message::new().unwrap());
+        // THEN: the second client is rejected
+        assert!(client_2.recv().is_err());
+        assert!(server_handle.metrics().rejected_conn_count() > 0);
+        assert_eq!(server_handle.connections().len(), 1);
+
+        // THEN: the first client is unaffected
+        client_1.send(nng::Message::new().unwrap()).unwrap();
+        let _ = client_1.recv().unwrap();
+
+        client_1.close();
+        client_2.close();
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
     }
 
-    /// Sets no delay setting on TCP connection
-    pub fn set_no_delay(mut self, no_delay: bool) -> Self {
-        self.no_delay = Some(no_delay);
-        self
-    }
+    #[test]
+    fn server_auth_callback() {
+        configure_logging();
 
-    /// Sets keep alive setting on TCP connection
-    pub fn set_keep_alive(mut self, keep_alive: bool) -> Self {
-        self.keep_alive = Some(keep_alive);
-        self
+        // GIVEN: a server that rejects connections from 127.0.0.2
+        let blocked_ip: IpAddr = "127.0.0.2".parse().unwrap();
+        let url = url::Url::parse("tcp://127.0.0.1:5965").unwrap();
+        let auth_callback = AuthCallback::new(move |pipe_info: &PipeInfo| {
+            pipe_info.remote_ip() != Some(blocked_ip)
+        });
+        let listener_config = ListenerConfig::new(url.clone()).set_auth_callback(auth_callback);
+        assert!(listener_config.auth_callback().is_some());
+        // a unique ReqRepId is used to isolate the connection metrics from other tests
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let service = ReqRepConfig::new(ReqRepId::generate(), timer_buckets)
+            .start_service(EchoService, global_executor())
+            .unwrap();
+        let mut server_handle =
+            super::spawn(None, listener_config, service, global_executor()).unwrap();
+
+        // WHEN: a client connects from 127.0.0.1
+        let mut client_1 = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        client_1.dial(url.as_str()).unwrap();
+        // THEN: the client is served
+        client_1.send(nng::Message::new().unwrap()).unwrap();
+        let _ = client_1.recv().unwrap();
+        assert_eq!(server_handle.connections().len(), 1);
+        assert_eq!(server_handle.metrics().rejected_auth_count(), 0);
+
+        // WHEN: a client connects from the blocked address
+        // - nng dialers select the local address via the `tcp://<local-addr>;<host>:<port>` syntax
+        let mut client_2 = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        client_2
+            .set_opt::<nng::options::RecvTimeout>(Some(Duration::from_millis(200)))
+            .unwrap();
+        // the dialer is started non-blocking because the server closes the connection
+        let _dialer = nng::DialerOptions::new(&client_2, "tcp://127.0.0.2;127.0.0.1:5965")
+            .unwrap()
+            .start(true);
+        let _ = client_2.send(nng::Message::new().unwrap());
+        // THEN: the client cannot complete a request
+        assert!(client_2.recv().is_err());
+        assert!(server_handle.metrics().rejected_auth_count() > 0);
+        assert_eq!(server_handle.connections().len(), 1);
+        // AND: the rejected connections are not counted as connection setup failures
+        assert_eq!(server_handle.metrics().connection_setup_failures(), 0);
+
+        // THEN: the allowed client is unaffected
+        client_1.send(nng::Message::new().unwrap()).unwrap();
+        let _ = client_1.recv().unwrap();
+
+        client_1.close();
+        client_2.close();
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
     }
 
-    /// Normally, the act of "binding" to the address indicated by url is done synchronously, including
-    /// any necessary name resolution. As a result, a failure, such as if the address is already in use,
-    /// will be returned immediately. However, if nonblocking is specified then this is done asynchronously;
-    /// furthermore any failure to bind will be periodically reattempted in the background.
-    pub fn set_non_blocking(mut self, non_blocking: bool) -> Self {
-        self.non_blocking = non_blocking;
-        self
-    }
+    #[test]
+    fn spawn_with_parallelism_too_high() {
+        configure_logging();
 
-    /// set the number of async IO operations that can be performed concurrently
-    pub fn set_aio_count(mut self, count: NonZeroUsize) -> Self {
-        self.parallelism = count.get();
-        self
+        // GIVEN: a ListenerConfig with an absurd parallelism
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let parallelism = crate::config::max_aio_contexts() * 1000;
+        let listener_config =
+            ListenerConfig::new(url).set_aio_count(NonZeroUsize::new(parallelism).unwrap());
+        // WHEN: the server is spawned
+        match super::spawn(None, listener_config, start_service(), global_executor()) {
+            // THEN: spawning fails with a clear error
+            Err(SpawnError::ParallelismTooHigh {
+                parallelism: p,
+                max,
+            }) => {
+                assert_eq!(p, parallelism);
+                assert_eq!(max, crate::config::max_aio_contexts());
+            }
+            Err(err) => panic!("expected SpawnError::ParallelismTooHigh, but got: {}", err),
+            Ok(_) => panic!("expected SpawnError::ParallelismTooHigh"),
+        }
     }
-}
-
-/// Socket config related errors
-#[derive(Debug, Fail)]
-pub enum ListenerConfigError {
-    /// Failed to create ListenerOpion
-    #[fail(display = "Failed to create ListenerOpions: {}", _0)]
-    ListenerOptionsCreateFailed(#[cause] nng::Error),
-    /// Failed start the Listener
-    #[fail(display = "Failed start the Listener: {}", _0)]
-    ListenerStartFailed(#[cause] nng::Error),
-    ///Failed to set the RecvMaxSize Socket option
-    #[fail(display = "Failed to set the RecvMaxSize Socket option: {}", _0)]
-    RecvMaxSize(#[cause] nng::Error),
-    /// Failed to set the TcpNoDelay Socket option
-    #[fail(display = "Failed to set the TcpNoDelay Socket option: {}", _0)]
-    TcpNoDelay(#[cause] nng::Error),
-    /// Failed to set the TcpKeepAlive Socket option
-    #[fail(display = "Failed to set the TcpKeepAlive Socket option: {}", _0)]
-    TcpKeepAlive(#[cause] nng::Error),
-}
 
-#[allow(warnings)]
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::configure_logging;
-    use oysterpack_trust::{
-        concurrent::{
-            execution::{self, *},
-            messaging::reqrep::{self, *},
-        },
-        metrics,
-    };
-    use oysterpack_uid::ULID;
-    use oysterpack_uid::*;
-    use std::{thread, time::Duration};
+    #[test]
+    fn spawn_validated_reports_all_problems() {
+        configure_logging();
 
-    struct EchoService;
-    impl Processor<nng::Message, nng::Message> for EchoService {
-        fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
-            async move { req }.boxed()
+        // GIVEN: an ipc ListenerConfig with TCP options and a parallelism that is too high
+        let url = url::Url::parse(&format!("ipc:///tmp/{}", ULID::generate())).unwrap();
+        let parallelism = crate::config::max_aio_contexts() + 1;
+        let listener_config = ListenerConfig::new(url)
+            .set_no_delay(true)
+            .set_aio_count(NonZeroUsize::new(parallelism).unwrap());
+        // AND: a SocketConfig with a TCP option
+        let socket_config = SocketConfig::default().set_tcp_keep_alive(true);
+
+        // WHEN: the server is spawned
+        match super::spawn_validated(
+            Some(socket_config),
+            listener_config,
+            start_service(),
+            global_executor(),
+        ) {
+            // THEN: spawning fails with an aggregate error that lists each problem
+            Err(SpawnError::InvalidConfig(problems)) => {
+                info!("{}", problems);
+                assert_eq!(
+                    problems.problems(),
+                    &[
+                        ConfigProblem::ParallelismTooHigh {
+                            parallelism,
+                            max: crate::config::max_aio_contexts()
+                        },
+                        ConfigProblem::TcpOptionNotSupported {
+                            option: "no_delay",
+                            scheme: "ipc".to_string()
+                        },
+                        ConfigProblem::TcpOptionNotSupported {
+                            option: "tcp_keep_alive",
+                            scheme: "ipc".to_string()
+                        },
+                    ]
+                );
+            }
+            Err(err) => panic!("expected SpawnError::InvalidConfig, but got: {}", err),
+            Ok(_) => panic!("expected SpawnError::InvalidConfig"),
         }
+
+        // GIVEN: a ListenerConfig with an unsupported scheme that binds to all interfaces
+        let listener_config = ListenerConfig::new(url::Url::parse("udp://0.0.0.0:5965").unwrap());
+        // THEN: each problem is reported
+        assert_eq!(
+            listener_config.validate(None),
+            vec![
+                ConfigProblem::UnsupportedUrlScheme("udp".to_string()),
+                ConfigProblem::WildcardBindNotAllowed("udp://0.0.0.0:5965".to_string()),
+            ]
+        );
     }
 
-    const REQREP_ID: ReqRepId = ReqRepId(1871557337320005579010710867531265404);
+    #[test]
+    fn server_request_stream() {
+        configure_logging();
 
-    fn start_service() -> ReqRep<nng::Message, nng::Message> {
-        let timer_buckets = metrics::timer_buckets(vec![
-            Duration::from_nanos(50),
-            Duration::from_nanos(100),
-            Duration::from_nanos(150),
-            Duration::from_nanos(200),
-        ])
+        // GIVEN: a server whose requests are consumed as a Stream
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let timer_buckets =
+            metrics::timer_buckets(vec![Duration::from_millis(50), Duration::from_millis(100)])
+                .unwrap();
+        let (mut server_handle, mut requests) = super::spawn_stream(
+            ReqRepConfig::new(ReqRepId::generate(), timer_buckets),
+            None,
+            ListenerConfig::new(url.clone()),
+            global_executor(),
+        )
         .unwrap();
+        assert!(server_handle.ping());
+        // AND: the stream consumer echoes back the request via the ReplyHandle
+        global_executor()
+            .spawn(
+                async move {
+                    while let Some((req, reply_handle)) = await!(requests.next()) {
+                        if reply_handle.reply(req).is_err() {
+                            warn!("failed to send reply");
+                        }
+                    }
+                },
+            )
+            .unwrap();
 
-        ReqRepConfig::new(REQREP_ID, timer_buckets)
-            .start_service(EchoService, global_executor().clone())
-            .unwrap()
+        // GIVEN: a client that connects to the server
+        let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        s.set_opt::<nng::options::RecvTimeout>(Some(Duration::from_secs(5)))
+            .unwrap();
+        s.dial(url.as_str()).unwrap();
+
+        for i in 0..10_u8 {
+            // WHEN: the client submits a request
+            let mut req = nng::Message::new().unwrap();
+            req.push_back(&[i]).unwrap();
+            s.send(req).unwrap();
+            // THEN: the client receives the reply that was sent via the ReplyHandle
+            let reply = s.recv().unwrap();
+            assert_eq!(&reply[..], &[i]);
+        }
+
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
     }
 
     #[test]
@@ -1061,17 +3221,14 @@ mod tests {
         let executor = execution::ExecutorBuilder::new(executor_id)
             .register()
             .unwrap();
-        let mut server_handle = super::spawn(
-            None,
-            ListenerConfig::new(url.clone()),
-            start_service(),
-            executor.clone(),
-        )
-        .unwrap();
+        let listener_config = ListenerConfig::new(url.clone());
+        let parallelism = listener_config.parallelism();
+        let mut server_handle =
+            super::spawn(None, listener_config, start_service(), executor.clone()).unwrap();
         assert!(server_handle.ping());
 
         // THEN: we expect the server to have N number of tasks running = 1 Aio worker per logical cpu + 1 controller task + 1 ReqRep backend service task
-        let expected_task_count = num_cpus::get() as u64 + 2;
+        let expected_task_count = parallelism as u64 + 1;
         info!("active task count = {}", executor.task_active_count());
         for _ in 0..10 {
             if executor.task_active_count() == expected_task_count {
@@ -1082,4 +3239,227 @@ mod tests {
         assert_eq!(executor.task_active_count(), expected_task_count);
     }
 
+    #[test]
+    fn listener_config_version_0_migration() {
+        configure_logging();
+
+        // GIVEN: a ListenerConfig that was persisted before versioning was introduced
+        let json = r#"{
+            "url": "tcp://127.0.0.1:5963",
+            "recv_max_size": null,
+            "no_delay": null,
+            "keep_alive": null,
+            "non_blocking": true,
+            "parallelism": 2
+        }"#;
+
+        // WHEN: it is deserialized
+        let config: ListenerConfig = serde_json::from_str(json).unwrap();
+        // THEN: the fields that were added since are defaulted
+        assert!(!config.allow_wildcard_bind());
+        assert!(!config.reply_on_service_error());
+        assert!(config.max_connections().is_none());
+        assert_eq!(config.backpressure(), Backpressure::Await);
+        assert_eq!(config.parallelism(), 2);
+        assert_eq!(
+            config,
+            ListenerConfig::new(config.url().clone()).set_aio_count(NonZeroUsize::new(2).unwrap())
+        );
+    }
+
+    #[test]
+    fn listener_config_version_0_bincode_migration() {
+        configure_logging();
+
+        // GIVEN: ListenerConfig bincode bytes that were persisted before versioning was introduced
+        let bytes: &[u8] = &[
+            // url = "tcp://127.0.0.1:5963"
+            20, 0, 0, 0, 0, 0, 0, 0, b't', b'c', b'p', b':', b'/', b'/', b'1', b'2', b'7', b'.',
+            b'0', b'.', b'0', b'.', b'1', b':', b'5', b'9', b'6', b'3',
+            // recv_max_size = None
+            0,
+            // no_delay = Some(false)
+            1, 0,
+            // keep_alive = None
+            0,
+            // non_blocking = true
+            1,
+            // parallelism = 2
+            2, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        // WHEN: the bytes are deserialized using the version 0 schema
+        let config_v0 = bincode::deserialize::<ListenerConfigV0>(bytes).unwrap();
+        // THEN: the version 0 schema consumes all of the bytes, i.e., it matches the released
+        // layout
+        assert_eq!(bincode::serialize(&config_v0).unwrap(), bytes);
+        let versioned_config = VersionedListenerConfig::from(config_v0);
+        assert_eq!(versioned_config.version(), 0);
+        // AND: the config is migrated
+        let config = versioned_config.migrate();
+        // THEN: the persisted settings are loaded
+        // AND: the fields that were added since are defaulted
+        let url = url::Url::parse("tcp://127.0.0.1:5963").unwrap();
+        assert_eq!(
+            config,
+            ListenerConfig::new(url)
+                .set_no_delay(false)
+                .set_aio_count(NonZeroUsize::new(2).unwrap())
+        );
+
+        // GIVEN: the version 0 config is wrapped in the versioned envelope
+        let mut versioned_bytes = 0_u32.to_le_bytes().to_vec();
+        versioned_bytes.extend_from_slice(bytes);
+        // THEN: it loads as version 0
+        let versioned_config: VersionedListenerConfig =
+            bincode::deserialize(&versioned_bytes).unwrap();
+        assert_eq!(versioned_config.version(), 0);
+        assert_eq!(versioned_config.migrate(), config);
+
+        // WHEN: the current config is persisted via the versioned envelope
+        let config = config.set_backpressure(Backpressure::Reject);
+        let bytes = bincode::serialize(&VersionedListenerConfig::from(config.clone())).unwrap();
+        // THEN: it round trips as the current version
+        let versioned_config: VersionedListenerConfig = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(versioned_config.version(), crate::config::CONFIG_VERSION);
+        assert_eq!(versioned_config.migrate(), config);
+    }
+
+    #[test]
+    fn server_handle_close() {
+        configure_logging();
+
+        // GIVEN: a running server
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let timer_buckets = metrics::timer_buckets(vec![
+            Duration::from_nanos(50),
+            Duration::from_nanos(100),
+        ])
+        .unwrap();
+        let service = ReqRepConfig::new(ReqRepId(ULID::generate().into()), timer_buckets)
+            .start_service(EchoService, global_executor().clone())
+            .unwrap();
+        let server_handle = super::spawn(
+            None,
+            ListenerConfig::new(url.clone()),
+            service,
+            global_executor().clone(),
+        )
+        .unwrap();
+        let server_handle_id = server_handle.id();
+        // AND: other ServerHandle clones exist
+        let server_handle_clone = server_handle.clone();
+        let server_handle_ref = ServerHandle::get(server_handle_id).unwrap();
+        assert!(server_handle_clone.ping());
+
+        // WHEN: the server is closed
+        global_executor().run(server_handle.close());
+        // THEN: the server is stopped for all clones
+        assert!(!server_handle_clone.ping());
+        assert!(!server_handle_ref.ping());
+        // AND: the server is unregistered
+        assert!(SERVER_HANDLES.read().get(&server_handle_id).is_none());
+        assert!(ServerHandle::get(server_handle_id).is_none());
+
+        // WHEN: a clone is closed after the server has already been closed
+        // THEN: the future still completes
+        global_executor().run(server_handle_clone.close());
+    }
+
+    #[test]
+    fn server_metrics_connection_setup_failures() {
+        configure_logging();
+
+        let reqrep_id = ReqRepId::generate();
+        let server_metrics = ServerMetrics::new(reqrep_id);
+        let conn_setup_failure_count = || {
+            CONN_SETUP_FAILURE_COUNT
+                .with_label_values(&[reqrep_id.to_string().as_str()])
+                .get()
+        };
+
+        // GIVEN: connections that are initiated and added
+        for _ in 0..5 {
+            assert!(!server_metrics.conn_initiated(reqrep_id));
+            server_metrics.conn_added();
+        }
+        // THEN: there are no connection setup failures
+        assert_eq!(server_metrics.connection_setup_failures(), 0);
+        assert_eq!(conn_setup_failure_count(), 0);
+
+        // WHEN: connections are initiated without ever being added
+        let mut warnings = 0;
+        for _ in 0..15 {
+            if server_metrics.conn_initiated(reqrep_id) {
+                warnings += 1;
+            }
+        }
+        // THEN: the derived metric reflects the gap
+        assert_eq!(server_metrics.tot_conn_initiate_count(), 20);
+        assert_eq!(server_metrics.tot_conn_count(), 5);
+        assert_eq!(server_metrics.connection_setup_failures(), 15);
+        // AND: the gauge excludes the connection that may still be in the process of being set up
+        assert_eq!(conn_setup_failure_count(), 14);
+        // AND: the warning was throttled, i.e., logged once
+        assert_eq!(warnings, 1);
+
+        // WHEN: a connection is added
+        server_metrics.conn_added();
+        // THEN: the gap narrows
+        assert_eq!(server_metrics.connection_setup_failures(), 14);
+        assert_eq!(conn_setup_failure_count(), 14);
+
+        // WHEN: connections are initiated and rejected
+        server_metrics.conn_initiated(reqrep_id);
+        server_metrics.conn_rejected_auth();
+        server_metrics.conn_initiated(reqrep_id);
+        server_metrics.conn_rejected_max_connections();
+        // THEN: the rejected connections are tracked on their own
+        assert_eq!(server_metrics.rejected_auth_count(), 1);
+        assert_eq!(server_metrics.rejected_conn_count(), 1);
+        // AND: they are not counted as connection setup failures
+        assert_eq!(server_metrics.connection_setup_failures(), 14);
+        assert_eq!(conn_setup_failure_count(), 14);
+    }
+
+    #[test]
+    fn server_shutdown_reason() {
+        configure_logging();
+
+        // GIVEN: a running server
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let timer_buckets = metrics::timer_buckets(vec![
+            Duration::from_nanos(50),
+            Duration::from_nanos(100),
+        ])
+        .unwrap();
+        let service = ReqRepConfig::new(ReqRepId(ULID::generate().into()), timer_buckets)
+            .start_service(EchoService, global_executor().clone())
+            .unwrap();
+        let mut server_handle = super::spawn(
+            None,
+            ListenerConfig::new(url.clone()),
+            service,
+            global_executor().clone(),
+        )
+        .unwrap();
+        let server_handle_clone = server_handle.clone();
+        assert!(server_handle.shutdown_reason().is_none());
+        // AND: a lifecycle event subscriber
+        let mut events = server_handle.events();
+
+        // WHEN: the server is stopped because of a fatal error
+        let reason = ShutdownReason::Fatal("disk is full".to_string());
+        assert!(server_handle.stop_async_with_reason(reason.clone()).unwrap());
+        // AND: signalling the server again is a noop
+        assert!(!server_handle.stop_async_with_reason(ShutdownReason::Maintenance).unwrap());
+        server_handle.await_shutdown();
+
+        // THEN: the Fatal reason appears in the lifecycle events
+        let mut executor = global_executor();
+        assert_eq!(executor.run(events.next()), Some(ServerEvent::Stopping(reason.clone())));
+        assert_eq!(executor.run(events.next()), Some(ServerEvent::Stopped(reason.clone())));
+        // AND: the shutdown reason is available via the ServerHandle
+        assert_eq!(server_handle_clone.shutdown_reason(), Some(reason));
+    }
 }
@@ -0,0 +1,527 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides declarative deployment for a set of servers and clients.
+//!
+//! A [Topology](struct.Topology.html) aggregates the configs that are required to bring up each
+//! server and client, i.e., [ReqRepConfig](../../../oysterpack_trust/concurrent/messaging/reqrep/struct.ReqRepConfig.html),
+//! [ListenerConfig](../server/struct.ListenerConfig.html), [DialerConfig](../client/struct.DialerConfig.html),
+//! and the socket configs. It is serde serializable, which means it can be exported to and loaded
+//! from a config file.
+//!
+//! The server message processors cannot be described by config. They are bound to the servers by
+//! name at runtime via [Topology::set_processor()](struct.Topology.html#method.set_processor).
+//!
+//! [Topology::spawn_all()](struct.Topology.html#method.spawn_all) spawns the servers first, and
+//! then registers the clients. Spawning is all or nothing - if any server or client fails to come
+//! up, then the ones that were already started are stopped. The returned [TopologyHandle](struct.TopologyHandle.html)
+//! provides access to the server handles and clients by name.
+
+use super::{
+    client::{self, Client, ClientRegistrationError, DialerConfig},
+    server::{self, ListenerConfig, ServerHandle, SpawnError},
+};
+use crate::config::SocketConfig;
+use failure::Fail;
+use futures::task::SpawnError as ExecutorSpawnError;
+use hashbrown::{HashMap, HashSet};
+use oysterpack_log::*;
+use oysterpack_trust::concurrent::{
+    execution::Executor,
+    messaging::reqrep::{Processor, ReqRep, ReqRepConfig},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Describes a set of servers and clients that are spawned together
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Topology {
+    #[serde(default)]
+    servers: Vec<ServerSpec>,
+    #[serde(default)]
+    clients: Vec<ClientSpec>,
+    // server name -> processor
+    #[serde(skip)]
+    processors: HashMap<String, ProcessorBinding>,
+}
+
+impl Topology {
+    /// Adds the server to the topology
+    pub fn add_server(mut self, server: ServerSpec) -> Self {
+        self.servers.push(server);
+        self
+    }
+
+    /// Adds the client to the topology
+    pub fn add_client(mut self, client: ClientSpec) -> Self {
+        self.clients.push(client);
+        self
+    }
+
+    /// Binds the message processor to the named server
+    /// - the server's ReqRep backend service is started using the processor when the topology is spawned
+    pub fn set_processor<P>(mut self, server_name: &str, processor: P) -> Self
+    where
+        P: Processor<nng::Message, nng::Message> + Send + 'static,
+    {
+        self.processors.insert(
+            server_name.to_string(),
+            ProcessorBinding(Box::new(move |config: ReqRepConfig, executor| {
+                config.start_service(processor, executor)
+            })),
+        );
+        self
+    }
+
+    /// Returns the server specs
+    pub fn servers(&self) -> &[ServerSpec] {
+        &self.servers
+    }
+
+    /// Returns the client specs
+    pub fn clients(&self) -> &[ClientSpec] {
+        &self.clients
+    }
+
+    /// Spawns each server, and then registers each client
+    /// - the topology is checked before anything is spawned, i.e., names must be unique per kind,
+    ///   and each server must have a processor bound to it
+    /// - if any server or client fails to come up, then the servers that were already spawned are
+    ///   stopped, and the clients that were already registered are unregistered
+    pub fn spawn_all(self, executor: Executor) -> Result<TopologyHandle, TopologyError> {
+        self.check()?;
+        let Topology {
+            servers,
+            clients,
+            mut processors,
+        } = self;
+
+        let mut handle = TopologyHandle {
+            servers: HashMap::with_capacity(servers.len()),
+            clients: HashMap::with_capacity(clients.len()),
+        };
+        for spec in servers {
+            // check() ensures that each server has a processor bound to it
+            let processor = processors.remove(&spec.name).unwrap();
+            let service = match (processor.0)(spec.service_config, executor.clone()) {
+                Ok(service) => service,
+                Err(err) => {
+                    handle.shutdown();
+                    return Err(TopologyError::ServiceStartFailed {
+                        name: spec.name,
+                        is_executor_shutdown: err.is_shutdown(),
+                    });
+                }
+            };
+            match server::spawn(
+                spec.socket_config,
+                spec.listener_config,
+                service,
+                executor.clone(),
+            ) {
+                Ok(server_handle) => {
+                    handle.servers.insert(spec.name, server_handle);
+                }
+                Err(err) => {
+                    handle.shutdown();
+                    return Err(TopologyError::ServerSpawnFailed {
+                        name: spec.name,
+                        err,
+                    });
+                }
+            }
+        }
+        for spec in clients {
+            match client::register_client(
+                spec.reqrep_config,
+                spec.socket_config,
+                spec.dialer_config,
+                executor.clone(),
+            ) {
+                Ok(client) => {
+                    handle.clients.insert(spec.name, client);
+                }
+                Err(err) => {
+                    handle.shutdown();
+                    return Err(TopologyError::ClientRegistrationFailed {
+                        name: spec.name,
+                        err,
+                    });
+                }
+            }
+        }
+        Ok(handle)
+    }
+
+    fn check(&self) -> Result<(), TopologyError> {
+        let mut server_names = HashSet::with_capacity(self.servers.len());
+        for spec in self.servers.iter() {
+            if !server_names.insert(spec.name.as_str()) {
+                return Err(TopologyError::DuplicateServerName(spec.name.clone()));
+            }
+            if !self.processors.contains_key(&spec.name) {
+                return Err(TopologyError::ProcessorNotBound(spec.name.clone()));
+            }
+        }
+        let mut client_names = HashSet::with_capacity(self.clients.len());
+        for spec in self.clients.iter() {
+            if !client_names.insert(spec.name.as_str()) {
+                return Err(TopologyError::DuplicateClientName(spec.name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Starts the server's ReqRep backend service
+struct ProcessorBinding(
+    Box<
+        dyn FnOnce(
+                ReqRepConfig,
+                Executor,
+            ) -> Result<ReqRep<nng::Message, nng::Message>, ExecutorSpawnError>
+            + Send,
+    >,
+);
+
+impl fmt::Debug for ProcessorBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProcessorBinding")
+    }
+}
+
+/// Server spec, i.e., the configs that are used to [spawn](../server/fn.spawn.html) the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerSpec {
+    name: String,
+    service_config: ReqRepConfig,
+    #[serde(default)]
+    socket_config: Option<SocketConfig>,
+    listener_config: ListenerConfig,
+}
+
+impl ServerSpec {
+    /// constructor
+    /// - the service config is used to start the server's ReqRep backend service
+    pub fn new(name: &str, service_config: ReqRepConfig, listener_config: ListenerConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            service_config,
+            socket_config: None,
+            listener_config,
+        }
+    }
+
+    /// Sets the SocketConfig
+    pub fn set_socket_config(mut self, socket_config: SocketConfig) -> Self {
+        self.socket_config = Some(socket_config);
+        self
+    }
+
+    /// Returns the server name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the ReqRep backend service config
+    pub fn service_config(&self) -> &ReqRepConfig {
+        &self.service_config
+    }
+
+    /// Returns the SocketConfig
+    pub fn socket_config(&self) -> Option<&SocketConfig> {
+        self.socket_config.as_ref()
+    }
+
+    /// Returns the ListenerConfig
+    pub fn listener_config(&self) -> &ListenerConfig {
+        &self.listener_config
+    }
+}
+
+/// Client spec, i.e., the configs that are used to [register](../client/fn.register_client.html)
+/// the client
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientSpec {
+    name: String,
+    reqrep_config: ReqRepConfig,
+    #[serde(default)]
+    socket_config: Option<client::SocketConfig>,
+    dialer_config: DialerConfig,
+}
+
+impl ClientSpec {
+    /// constructor
+    pub fn new(name: &str, reqrep_config: ReqRepConfig, dialer_config: DialerConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            reqrep_config,
+            socket_config: None,
+            dialer_config,
+        }
+    }
+
+    /// Sets the client SocketConfig
+    pub fn set_socket_config(mut self, socket_config: client::SocketConfig) -> Self {
+        self.socket_config = Some(socket_config);
+        self
+    }
+
+    /// Returns the client name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the client ReqRepConfig
+    pub fn reqrep_config(&self) -> &ReqRepConfig {
+        &self.reqrep_config
+    }
+
+    /// Returns the client SocketConfig
+    pub fn socket_config(&self) -> Option<&client::SocketConfig> {
+        self.socket_config.as_ref()
+    }
+
+    /// Returns the DialerConfig
+    pub fn dialer_config(&self) -> &DialerConfig {
+        &self.dialer_config
+    }
+}
+
+/// The servers and clients that were brought up by [Topology::spawn_all()](struct.Topology.html#method.spawn_all),
+/// keyed by name
+#[derive(Debug)]
+pub struct TopologyHandle {
+    servers: HashMap<String, ServerHandle>,
+    clients: HashMap<String, Client>,
+}
+
+impl TopologyHandle {
+    /// Returns the named server's handle
+    pub fn server(&self, name: &str) -> Option<&ServerHandle> {
+        self.servers.get(name)
+    }
+
+    /// Returns the named client
+    pub fn client(&self, name: &str) -> Option<Client> {
+        self.clients.get(name).cloned()
+    }
+
+    /// Returns the server names
+    pub fn server_names(&self) -> Vec<&str> {
+        self.servers.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the client names
+    pub fn client_names(&self) -> Vec<&str> {
+        self.clients.keys().map(String::as_str).collect()
+    }
+
+    /// Unregisters the clients, and then stops the servers and waits for them to shutdown
+    ///
+    /// ## Notes
+    /// The current thread is blocked while waiting for the servers to shutdown.
+    pub fn shutdown(mut self) {
+        for (name, client) in self.clients.drain() {
+            if client::unregister_client(client.id()).is_none() {
+                warn!("Topology client was not registered: {}", name);
+            }
+        }
+        for (name, mut server) in self.servers.drain() {
+            if let Err(err) = server.stop_async() {
+                error!("Failed to signal Topology server to stop: {} : {}", name, err);
+            }
+            server.await_shutdown();
+        }
+    }
+}
+
+/// Topology related errors
+#[derive(Debug, Fail)]
+pub enum TopologyError {
+    /// More than one server is configured with the same name
+    #[fail(display = "Duplicate server name: {}", _0)]
+    DuplicateServerName(String),
+    /// More than one client is configured with the same name
+    #[fail(display = "Duplicate client name: {}", _0)]
+    DuplicateClientName(String),
+    /// No processor is bound to the server - see [Topology::set_processor()](struct.Topology.html#method.set_processor)
+    #[fail(display = "No processor is bound to the server: {}", _0)]
+    ProcessorNotBound(String),
+    /// Failed to start the server's ReqRep backend service
+    #[fail(
+        display = "Failed to start the ReqRep service for server ({}): executor is shutdown = {}",
+        name, is_executor_shutdown
+    )]
+    ServiceStartFailed {
+        /// server name
+        name: String,
+        /// whether spawning failed because the executor is shut down
+        is_executor_shutdown: bool,
+    },
+    /// Failed to spawn the server
+    #[fail(display = "Failed to spawn server ({}): {}", name, err)]
+    ServerSpawnFailed {
+        /// server name
+        name: String,
+        /// cause
+        #[cause]
+        err: SpawnError,
+    },
+    /// Failed to register the client
+    #[fail(display = "Failed to register client ({}): {}", name, err)]
+    ClientRegistrationFailed {
+        /// client name
+        name: String,
+        /// cause
+        #[cause]
+        err: ClientRegistrationError,
+    },
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use futures::future::FutureExt;
+    use oysterpack_trust::concurrent::{
+        execution::global_executor,
+        messaging::reqrep::{FutureReply, ReqRepId},
+    };
+    use oysterpack_uid::ULID;
+
+    struct EchoService;
+
+    impl Processor<nng::Message, nng::Message> for EchoService {
+        fn process(&mut self, req: nng::Message) -> FutureReply<nng::Message> {
+            async move { req }.boxed()
+        }
+    }
+
+    #[test]
+    fn spawn_topology_from_json() {
+        configure_logging();
+
+        // GIVEN: a topology with one server and one client over inproc that is loaded from JSON
+        let url = format!("inproc://{}", ULID::generate());
+        let (server_reqrep_id, client_reqrep_id) = (
+            serde_json::to_string(&ReqRepId::generate()).unwrap(),
+            serde_json::to_string(&ReqRepId::generate()).unwrap(),
+        );
+        let json = format!(
+            r#"{{
+            "servers": [{{
+                "name": "echo-server",
+                "service_config": {{
+                    "reqrep_id": {},
+                    "chan_buf_size": 1,
+                    "metric_timer_buckets": [0.001, 0.01]
+                }},
+                "listener_config": {{
+                    "url": "{}",
+                    "recv_max_size": null,
+                    "no_delay": null,
+                    "keep_alive": null,
+                    "non_blocking": true,
+                    "parallelism": 2
+                }}
+            }}],
+            "clients": [{{
+                "name": "echo-client",
+                "reqrep_config": {{
+                    "reqrep_id": {},
+                    "chan_buf_size": 1,
+                    "metric_timer_buckets": [0.001, 0.01]
+                }},
+                "dialer_config": {{
+                    "url": "{}",
+                    "parallelism": 2,
+                    "recv_max_size": null,
+                    "no_delay": null,
+                    "keep_alive": null,
+                    "reconnect_min_time": null,
+                    "reconnect_max_time": null
+                }}
+            }}]
+        }}"#,
+            server_reqrep_id, url, client_reqrep_id, url
+        );
+        let topology: Topology = serde_json::from_str(&json).unwrap();
+        assert_eq!(topology.servers().len(), 1);
+        assert_eq!(topology.clients().len(), 1);
+        // AND: the topology can be exported
+        let exported = serde_json::to_string(&topology).unwrap();
+        let topology: Topology = serde_json::from_str(&exported).unwrap();
+
+        // WHEN: the topology is spawned without binding a processor to the server
+        match Topology::default()
+            .add_server(topology.servers()[0].clone())
+            .spawn_all(global_executor())
+        {
+            // THEN: nothing is spawned
+            Err(TopologyError::ProcessorNotBound(name)) => assert_eq!(name, "echo-server"),
+            other => panic!("expected ProcessorNotBound, but was: {:?}", other),
+        }
+
+        // WHEN: the topology is spawned
+        let handle = topology
+            .set_processor("echo-server", EchoService)
+            .spawn_all(global_executor())
+            .unwrap();
+        assert!(handle.server("echo-server").unwrap().ping());
+
+        // THEN: a request round-trips through the client and server
+        let mut client = handle.client("echo-client").unwrap();
+        let mut req = nng::Message::new().unwrap();
+        req.push_back(b"ping").unwrap();
+        let reply = global_executor()
+            .run(async move { await!(client.send_recv(req)) })
+            .unwrap()
+            .unwrap();
+        assert_eq!(&*reply, b"ping");
+
+        // WHEN: the topology is torn down
+        let client_id = handle.client("echo-client").unwrap().id();
+        handle.shutdown();
+        // THEN: the client is unregistered
+        assert!(client::client(client_id).is_none());
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        configure_logging();
+
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let client_spec = || {
+            ClientSpec::new(
+                "client",
+                ReqRepConfig::new(ReqRepId::generate(), vec![0.001]),
+                DialerConfig::new(url.clone()),
+            )
+        };
+        // GIVEN: a topology with 2 clients that have the same name
+        let topology = Topology::default()
+            .add_client(client_spec())
+            .add_client(client_spec());
+        // WHEN: it is spawned
+        match topology.spawn_all(global_executor()) {
+            // THEN: it is rejected
+            Err(TopologyError::DuplicateClientName(name)) => assert_eq!(name, "client"),
+            other => panic!("expected DuplicateClientName, but was: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides a [Transport](trait.Transport.html) abstraction for sending a request and awaiting its
+//! reply, which decouples application code from how the backend service is reached.
+//! - [InProcessTransport](struct.InProcessTransport.html) sends requests directly to the service's
+//!   [ReqRep](../../../oysterpack_trust/concurrent/messaging/reqrep/struct.ReqRep.html)
+//! - [NngTransport](struct.NngTransport.html) sends requests over nng via the [Client](../client/type.Client.html)
+//!
+//! Application code depends on `Transport`, and the implementation is selected by configuration via
+//! [TransportKind](enum.TransportKind.html) - see [transport()](fn.transport.html). In-process
+//! services are made available by registering them via [register_in_process_service()](fn.register_in_process_service.html).
+
+use super::client::{self, Client, RequestError};
+use failure::Fail;
+use futures::future::FutureExt;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use oysterpack_trust::concurrent::messaging::{
+    errors::ChannelError,
+    reqrep::{FutureReply, ReqRep, ReqRepId},
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+lazy_static! {
+    /// Global in-process service registry
+    static ref IN_PROCESS_SERVICES: RwLock<HashMap<ReqRepId, ReqRep<nng::Message, nng::Message>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Sends a request and awaits its reply
+pub trait Transport: fmt::Debug + Send {
+    /// ReqRepId for the backend service
+    fn id(&self) -> ReqRepId;
+
+    /// Sends the request and returns a future for the reply
+    fn send_recv(
+        &mut self,
+        req: nng::Message,
+    ) -> FutureReply<Result<nng::Message, TransportError>>;
+}
+
+/// Transport implementations
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum TransportKind {
+    /// [InProcessTransport](struct.InProcessTransport.html)
+    InProcess,
+    /// [NngTransport](struct.NngTransport.html)
+    Nng,
+}
+
+/// Returns the Transport for the specified kind
+/// - TransportKind::InProcess requires the service to be registered via [register_in_process_service()](fn.register_in_process_service.html)
+/// - TransportKind::Nng requires the client to be registered via [client::register_client()](../client/fn.register_client.html)
+/// - returns None if no service or client is registered for the ReqRepId
+pub fn transport(reqrep_id: ReqRepId, kind: TransportKind) -> Option<Box<dyn Transport>> {
+    match kind {
+        TransportKind::InProcess => IN_PROCESS_SERVICES
+            .read()
+            .get(&reqrep_id)
+            .cloned()
+            .map(|service| Box::new(InProcessTransport::new(service)) as Box<dyn Transport>),
+        TransportKind::Nng => client::client(reqrep_id)
+            .map(|client| Box::new(NngTransport::new(client)) as Box<dyn Transport>),
+    }
+}
+
+/// Registers the service for in-process transport.
+/// - if a service is already registered for the same ReqRepId, then it is replaced and returned
+pub fn register_in_process_service(
+    service: ReqRep<nng::Message, nng::Message>,
+) -> Option<ReqRep<nng::Message, nng::Message>> {
+    IN_PROCESS_SERVICES.write().insert(service.id(), service)
+}
+
+/// Unregisters the in-process service
+pub fn unregister_in_process_service(
+    reqrep_id: ReqRepId,
+) -> Option<ReqRep<nng::Message, nng::Message>> {
+    IN_PROCESS_SERVICES.write().remove(&reqrep_id)
+}
+
+/// Sends requests directly to the service's ReqRep, i.e., bypassing nng
+#[derive(Debug, Clone)]
+pub struct InProcessTransport(ReqRep<nng::Message, nng::Message>);
+
+impl InProcessTransport {
+    /// constructor
+    pub fn new(service: ReqRep<nng::Message, nng::Message>) -> InProcessTransport {
+        InProcessTransport(service)
+    }
+}
+
+impl Transport for InProcessTransport {
+    fn id(&self) -> ReqRepId {
+        self.0.id()
+    }
+
+    fn send_recv(
+        &mut self,
+        req: nng::Message,
+    ) -> FutureReply<Result<nng::Message, TransportError>> {
+        let mut service = self.0.clone();
+        async move { await!(service.send_recv(req)).map_err(TransportError::Channel) }.boxed()
+    }
+}
+
+/// Sends requests over nng via the Client
+#[derive(Debug, Clone)]
+pub struct NngTransport(Client);
+
+impl NngTransport {
+    /// constructor
+    pub fn new(client: Client) -> NngTransport {
+        NngTransport(client)
+    }
+}
+
+impl Transport for NngTransport {
+    fn id(&self) -> ReqRepId {
+        self.0.id()
+    }
+
+    fn send_recv(
+        &mut self,
+        req: nng::Message,
+    ) -> FutureReply<Result<nng::Message, TransportError>> {
+        let mut client = self.0.clone();
+        async move {
+            match await!(client.send_recv(req)) {
+                Ok(reply) => reply.map_err(TransportError::Request),
+                Err(err) => Err(TransportError::Channel(err)),
+            }
+        }
+            .boxed()
+    }
+}
+
+/// Transport errors
+#[derive(Debug, Fail, Clone)]
+pub enum TransportError {
+    /// The request could not be sent, or the reply channel was disconnected
+    #[fail(display = "Transport channel error: {}", _0)]
+    Channel(#[cause] ChannelError),
+    /// The nng request failed
+    #[fail(display = "Transport request failed: {}", _0)]
+    Request(#[cause] RequestError),
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{configure_logging, reqrep::server};
+    use oysterpack_trust::{
+        concurrent::{
+            execution::{self, *},
+            messaging::reqrep::{self, *},
+        },
+        metrics,
+    };
+    use oysterpack_uid::ULID;
+    use std::time::Duration;
+
+    /// replies with the request bytes reversed
+    struct ReverseService;
+    impl Processor<nng::Message, nng::Message> for ReverseService {
+        fn process(&mut self, req: nng::Message) -> reqrep::FutureReply<nng::Message> {
+            async move {
+                let mut bytes = req[..].to_vec();
+                bytes.reverse();
+                let mut rep = nng::Message::with_capacity(bytes.len()).unwrap();
+                rep.push_back(&bytes).unwrap();
+                rep
+            }
+                .boxed()
+        }
+    }
+
+    /// application logic that only depends on the Transport
+    async fn reverse_words(
+        mut transport: Box<dyn Transport>,
+        words: Vec<&'static str>,
+    ) -> Vec<String> {
+        let mut replies = Vec::with_capacity(words.len());
+        for word in words {
+            let mut req = nng::Message::with_capacity(word.len()).unwrap();
+            req.push_back(word.as_bytes()).unwrap();
+            let rep = await!(transport.send_recv(req)).unwrap();
+            replies.push(String::from_utf8(rep[..].to_vec()).unwrap());
+        }
+        replies
+    }
+
+    #[test]
+    fn transport() {
+        configure_logging();
+        let mut executor = execution::global_executor();
+        let timer_buckets =
+            || metrics::timer_buckets(vec![Duration::from_millis(50)]).unwrap();
+        let words = vec!["alpha", "bravo", "charlie"];
+
+        // GIVEN: the service is registered for in-process transport
+        let reqrep_id = ReqRepId::generate();
+        let service = ReqRepConfig::new(reqrep_id, timer_buckets())
+            .start_service(ReverseService, executor.clone())
+            .unwrap();
+        assert!(register_in_process_service(service.clone()).is_none());
+        // AND: the service is served over nng
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let mut server_handle = server::spawn(
+            None,
+            server::ListenerConfig::new(url.clone()),
+            service,
+            executor.clone(),
+        )
+        .unwrap();
+        client::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets()),
+            None,
+            client::DialerConfig::new(url.clone()),
+            execution::ExecutorBuilder::new(ExecutorId::generate())
+                .register()
+                .unwrap(),
+        )
+        .unwrap();
+
+        // WHEN: the same application logic is run against both transports
+        let in_process = super::transport(reqrep_id, TransportKind::InProcess).unwrap();
+        let nng = super::transport(reqrep_id, TransportKind::Nng).unwrap();
+        assert_eq!(in_process.id(), reqrep_id);
+        assert_eq!(nng.id(), reqrep_id);
+        let in_process_replies = executor.run(reverse_words(in_process, words.clone()));
+        let nng_replies = executor.run(reverse_words(nng, words.clone()));
+
+        // THEN: the results are identical
+        assert_eq!(in_process_replies, vec!["ahpla", "ovarb", "eilrahc"]);
+        assert_eq!(in_process_replies, nng_replies);
+
+        // WHEN: the service and client are unregistered
+        assert!(unregister_in_process_service(reqrep_id).is_some());
+        let _ = client::unregister_client(reqrep_id);
+        // THEN: the transports are no longer available
+        assert!(super::transport(reqrep_id, TransportKind::InProcess).is_none());
+        assert!(super::transport(reqrep_id, TransportKind::Nng).is_none());
+
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+}
@@ -0,0 +1,2586 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides support for implementing services using typed request and reply messages.
+//!
+//! Services implement [TypedProcessor](trait.TypedProcessor.html), and are plugged into the nng
+//! server via [SealedEnvelopeProcessor](struct.SealedEnvelopeProcessor.html), which takes care of
+//! the message plumbing:
+//!
+//! <pre>
+//! nng::Message -> SealedEnvelope -> open -> decode -> TypedProcessor
+//! nng::Message <- SealedEnvelope <- seal <- encode <- TypedProcessor
+//! </pre>
+//!
+//! If the request fails to be opened or decoded, or the reply fails to be encoded, then a
+//! [ServiceError](../server/struct.ServiceError.html) reply is returned, which the
+//! [TypedClient](struct.TypedClient.html) surfaces as [TypedRequestError::Service](enum.TypedRequestError.html#variant.Service).
+//!
+//! Each request is tracked via a [RequestContext](../context/struct.RequestContext.html), which
+//! records the decode, dispatch, and encode timings.
+//!
+//! ## Session Binding
+//! Each server connection is assigned a SessionId. Replies are stamped with the connection SessionId,
+//! and requests can be bound to it via [SealedEnvelopeProcessor::set_validate_session_id()](struct.SealedEnvelopeProcessor.html#method.set_validate_session_id),
+//! which rejects requests whose message SessionId does not match the connection's.
+//!
+//! ## Handshake
+//! The client runs the connection
+//! [handshake](../../../oysterpack_core/message/handshake/index.html) via
+//! [TypedClient::handshake()](struct.TypedClient.html#method.handshake), which the
+//! SealedEnvelopeProcessor answers without dispatching to the TypedProcessor:
+//! - the client proves that it controls its signing key
+//! - the session encoding is negotiated from the client's and the server's encoding preferences -
+//!   see [TypedClient::set_encodings()](struct.TypedClient.html#method.set_encodings) and
+//!   [SealedEnvelopeProcessor::set_encodings()](struct.SealedEnvelopeProcessor.html#method.set_encodings)
+//! - the negotiated encoding is stored on the session, and is used to encode the session's requests
+//!   and replies instead of the configured Encoding
+//!
+//! ## Accounting
+//! An [Accounting](struct.Accounting.html) hook can be configured, which computes the processing cost
+//! for each request based on flat rates per message type, per message byte, and per unit of connection
+//! time. The cost is recorded into the [PROCESSING_COST_METRIC_ID](constant.PROCESSING_COST_METRIC_ID.html)
+//! CounterVec, labeled by the sender Address and MessageType.
+//!
+//! ## Stale Messages
+//! Requests are not processed if they have been stuck in transit for too long, even if they do not
+//! specify a [Deadline](../../../oysterpack_core/message/enum.Deadline.html). This also guards against
+//! replays of old captured traffic. The max message age is configured via
+//! [SealedEnvelopeProcessor::set_max_message_age()](struct.SealedEnvelopeProcessor.html#method.set_max_message_age).
+//! Stale messages are rejected with a ServiceError reply, and are counted by the
+//! [STALE_MSG_COUNT_METRIC_ID](constant.STALE_MSG_COUNT_METRIC_ID.html) metric.
+//!
+//! ## Request Deadlines
+//! The request [Deadline](../../../oysterpack_core/message/enum.Deadline.html) is carried in the sealed
+//! message Metadata, which enables the server to stop working on requests that the client has given up on:
+//! - requests whose deadline has already expired when they arrive are rejected before they are dispatched
+//! - otherwise, the TypedProcessor is raced against the remaining time. If the deadline expires first,
+//!   then the work is abandoned, i.e., the processing future is dropped, and a ServiceError reply is
+//!   returned right away, which frees up the server's Aio Context. Abandoned requests are counted by the
+//!   [ABANDONED_REQUEST_COUNT_METRIC_ID](constant.ABANDONED_REQUEST_COUNT_METRIC_ID.html) metric.
+//!
+//! ## Decode Failures
+//! Requests that fail to be decoded are rejected with a ServiceError reply, and are counted by the
+//! [DECODE_FAILURE_COUNT_METRIC_ID](constant.DECODE_FAILURE_COUNT_METRIC_ID.html) metric, which is
+//! labeled by [DecodeFailure](enum.DecodeFailure.html) kind. A spike in decode failures usually
+//! points to a misbehaving or malicious peer. A callback can be registered to alert on failures via
+//! [SealedEnvelopeProcessor::set_decode_failure_callback()](struct.SealedEnvelopeProcessor.html#method.set_decode_failure_callback).
+//!
+//! ## Request Logging
+//! A sample of requests can be logged via [SealedEnvelopeProcessor::set_request_logger()](struct.SealedEnvelopeProcessor.html#method.set_request_logger)
+//! - see [RequestLogger](../request_log/struct.RequestLogger.html)
+//!
+//! ## Typed Client
+//! [TypedClient](struct.TypedClient.html) is the client side counterpart, which seals typed requests,
+//! sends them via an nng [Client](../client/type.Client.html), and opens the typed replies.
+//!
+//! ## Signing Policy
+//! Signing messages is not free, thus a [SigningPolicy](struct.SigningPolicy.html) decides per
+//! MessageType whether a message must be signed, i.e., whether a [SignedHash](../../../oysterpack_core/message/struct.SignedHash.html)
+//! is attached before the message is sealed - see [Signing](enum.Signing.html).
+//! - the client signs outbound requests according to [TypedClient::set_signing_policy()](struct.TypedClient.html#method.set_signing_policy)
+//! - the server mirrors the policy via [SealedEnvelopeProcessor::set_signing_policy()](struct.SealedEnvelopeProcessor.html#method.set_signing_policy),
+//!   and rejects unsigned requests that are required to be signed. Signed requests are always
+//!   verified against the sender's signing public-key - see [SealedEnvelopeProcessor::add_signing_key()](struct.SealedEnvelopeProcessor.html#method.add_signing_key)
+//!
+//! ## Compression Policy
+//! Message types compress differently, e.g., already compressed blobs should not be compressed
+//! again. A [CompressionPolicy](struct.CompressionPolicy.html) maps MessageType(s) to the
+//! compression that is applied when the message is encoded, overriding the compression of the
+//! configured Encoding.
+//! - the client applies the policy to requests - see [TypedClient::set_compression_policy()](struct.TypedClient.html#method.set_compression_policy)
+//! - the server applies the policy to replies - see [SealedEnvelopeProcessor::set_compression_policy()](struct.SealedEnvelopeProcessor.html#method.set_compression_policy)
+//! - the receiver decodes the message according to the message's Metadata, thus the policies do not
+//!   need to match
+//!
+//! ## Validation
+//! A [Validator](trait.Validator.html) can be configured via [SealedEnvelopeProcessor::set_validator()](struct.SealedEnvelopeProcessor.html#method.set_validator),
+//! which checks the request message after it has been decoded, and before it is dispatched to the
+//! TypedProcessor. Invalid requests are never seen by the TypedProcessor - the client receives a
+//! ServiceError reply carrying the [ValidationError](struct.ValidationError.html).
+//! - by default, requests are not validated - see [NoopValidator](struct.NoopValidator.html)
+//!
+//! ## Tracing
+//! If the `tracing` feature is enabled, then requests are instrumented using [tracing](https://crates.io/crates/tracing)
+//! spans, which nest across the nng hop:
+//! - `typed_send` wraps [TypedClient::send_recv()](struct.TypedClient.html#method.send_recv). The
+//!   span id is injected into the request Metadata via the [TRACE_PARENT_ATTR_ID](constant.TRACE_PARENT_ATTR_ID.html)
+//!   attribute.
+//! - `typed_process` wraps the request processing. The span id that is extracted from the request
+//!   Metadata is used as the span's parent.
+//! - both spans carry the `reqrep_id` field and the `msg_id` field, which is the request message
+//!   [InstanceId](../../../oysterpack_core/message/struct.InstanceId.html)
+//!
+//! Span ids are only meaningful to the subscriber that issued them, i.e., the server's subscriber
+//! must be able to resolve the client's span ids, e.g., a distributed tracing subscriber.
+
+use crate::util::BoundedTimedCache;
+use super::{
+    client::{Client, RequestError},
+    context::{RequestContext, Stage},
+    request_log::{RequestLogRecord, RequestLogger, RequestOutcome},
+    server::{self, ServiceError, WireError, REQREP_LABEL_ID},
+};
+use failure::Fail;
+use futures::{
+    channel::mpsc,
+    future::{self, Either, FutureExt},
+};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use oysterpack_core::message::{
+    errors::DecompressionError,
+    handshake::{
+        Connect, ConnectAccepted, ConnectChallenge, ConnectChallengeResponse, ServerHandshake,
+    },
+    Address, Compression, Deadline, EncodedMessage, Encoding, IsMessage, Message, MessageType,
+    Metadata, SealedEnvelope, SessionId,
+};
+use oysterpack_events::AttributeId;
+use oysterpack_log::*;
+#[cfg(feature = "tracing")]
+use oysterpack_trust::{concurrent::messaging::reqrep::Instrumented, tracing};
+use oysterpack_trust::{
+    concurrent::messaging::{
+        errors::ChannelError,
+        reqrep::{DeadlineSignal, FutureReply, Processor, ReqRepId},
+    },
+    metrics,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sodiumoxide::crypto::{box_, sign};
+use std::{
+    fmt,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+lazy_static! {
+    /// the metric is incremented by the processing cost of each request
+    static ref PROCESSING_COST: prometheus::CounterVec = metrics::registry().register_counter_vec(
+        PROCESSING_COST_METRIC_ID,
+        "Message processing cost",
+        &[SENDER_LABEL_ID, MESSAGE_TYPE_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the metric is incremented each time a request is rejected because it is older than the max message age
+    static ref STALE_MSG_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        STALE_MSG_COUNT_METRIC_ID,
+        "Number of stale request messages that were rejected",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the metric is incremented each time request processing is abandoned because the request deadline expired
+    static ref ABANDONED_REQUEST_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        ABANDONED_REQUEST_COUNT_METRIC_ID,
+        "Number of requests whose processing was abandoned because the deadline expired",
+        &[REQREP_LABEL_ID],
+        None
+    ).unwrap();
+
+    /// the metric is incremented each time a request message fails to be decoded
+    static ref DECODE_FAILURE_COUNT: prometheus::IntCounterVec = metrics::registry().register_int_counter_vec(
+        DECODE_FAILURE_COUNT_METRIC_ID,
+        "Number of request messages that failed to be decoded",
+        &[REQREP_LABEL_ID, DECODE_FAILURE_LABEL_ID],
+        None
+    ).unwrap();
+}
+
+/// CounterVec MetricId which is used to track the message processing cost by sender Address and
+/// MessageType: `M01D88AQZQTHR4M8HD1F72Y97G0`
+pub const PROCESSING_COST_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1879949608271150663962635938346343936);
+
+/// Metric LabelId which is used to store the sender Address: `L01D88D8BV3C5FGRC80JRPK8C64`
+pub const SENDER_LABEL_ID: metrics::LabelId =
+    metrics::LabelId(1879952792379657992907468582186922180);
+
+/// Metric LabelId which is used to store the MessageType: `L01D88G9S5YN4ZM2Z3ACQPR4MAW`
+pub const MESSAGE_TYPE_LABEL_ID: metrics::LabelId =
+    metrics::LabelId(1879956651458597128775297470398878044);
+
+/// IntCounterVec MetricId which is used to track the number of stale request messages that were
+/// rejected by ReqRepId: `M01D8A3CKC8F7BWBWCSK1TPS4W5`
+pub const STALE_MSG_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880021413297791459579661976712549253);
+
+/// IntCounterVec MetricId which is used to track the number of requests that were dispatched, but
+/// whose processing was abandoned because the request deadline expired: `M01D8AJ14FVRHNW0DM20EWXQ6W7`
+pub const ABANDONED_REQUEST_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880039973872179119366711438728731527);
+
+/// IntCounterVec MetricId which is used to track the number of request messages that failed to be
+/// decoded by ReqRepId and [DecodeFailure](enum.DecodeFailure.html): `M01D8ATXC0SGV4BF6D228PP3S1Q`
+pub const DECODE_FAILURE_COUNT_METRIC_ID: metrics::MetricId =
+    metrics::MetricId(1880051233591783142336084518732162103);
+
+/// Metric LabelId which is used to store the [DecodeFailure](enum.DecodeFailure.html) kind:
+/// `L01D8AXMFEJR0DQ4G3QQ30JAGBV`
+pub const DECODE_FAILURE_LABEL_ID: metrics::LabelId =
+    metrics::LabelId(1880054684264079618113171991206117755);
+
+/// Metadata AttributeId which is used to propagate the client's `typed_send` tracing span id to the
+/// server: `01D8B6GA5ECNAW8EJFCNECAE7F`
+/// - the u64 span id is stored as the attribute ULID value
+/// - the attribute is only attached if the `tracing` feature is enabled
+pub const TRACE_PARENT_ATTR_ID: AttributeId = AttributeId(1880065928120021448362941840995203311);
+
+/// Default max number of senders whose connection time is tracked by [Accounting](struct.Accounting.html)
+pub const DEFAULT_ACCOUNTING_SENDER_CAPACITY: usize = 1024 * 8;
+
+/// Default time that a sender's connection time is tracked by [Accounting](struct.Accounting.html)
+/// after the sender was last charged
+pub const DEFAULT_ACCOUNTING_SENDER_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Default clock skew tolerance that is applied to the max message age
+pub const DEFAULT_CLOCK_SKEW_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// Default max number of precomputed sender keys that are cached
+pub const DEFAULT_KEY_CACHE_CAPACITY: usize = 1024;
+
+/// Default time to live for cached precomputed sender keys
+pub const DEFAULT_KEY_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Returns the number of stale request messages that were rejected
+pub fn stale_msg_count(reqrep_id: ReqRepId) -> u64 {
+    STALE_MSG_COUNT
+        .with_label_values(&[reqrep_id.to_string().as_str()])
+        .get() as u64
+}
+
+/// Returns the number of requests whose processing was abandoned because the request deadline expired
+pub fn abandoned_request_count(reqrep_id: ReqRepId) -> u64 {
+    ABANDONED_REQUEST_COUNT
+        .with_label_values(&[reqrep_id.to_string().as_str()])
+        .get() as u64
+}
+
+/// Returns the number of request messages that failed to be decoded for the specified failure kind
+pub fn decode_failure_count(reqrep_id: ReqRepId, failure: DecodeFailure) -> u64 {
+    DECODE_FAILURE_COUNT
+        .with_label_values(&[reqrep_id.to_string().as_str(), failure.as_str()])
+        .get() as u64
+}
+
+/// Request message decode failure kinds
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DecodeFailure {
+    /// the message is not a well formed SealedEnvelope, or the opened envelope does not contain a
+    /// well formed EncodedMessage
+    Framing,
+    /// the envelope failed to be opened, i.e., decrypted, or the message signature failed to be
+    /// verified
+    Auth,
+    /// the message data failed to be decompressed
+    Decompression,
+    /// the message data failed to be deserialized into the request type
+    Deserialization,
+}
+
+impl DecodeFailure {
+    /// Returns the metric label value
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DecodeFailure::Framing => "framing",
+            DecodeFailure::Auth => "auth",
+            DecodeFailure::Decompression => "decompression",
+            DecodeFailure::Deserialization => "deserialization",
+        }
+    }
+}
+
+impl fmt::Display for DecodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the total processing cost that has been recorded for the sender and message type
+pub fn processing_cost(sender: &Address, message_type: MessageType) -> f64 {
+    PROCESSING_COST
+        .with_label_values(&[sender.to_string().as_str(), message_type.to_string().as_str()])
+        .get()
+}
+
+/// Computes the processing cost for each request, which is recorded into the
+/// [PROCESSING_COST_METRIC_ID](constant.PROCESSING_COST_METRIC_ID.html) metric
+/// - cost = message type rate + (byte rate * message size) + (connection time rate * connection time)
+/// - connection time is charged incrementally, i.e., each request is charged for the connection time
+///   that has elapsed since the sender's previous request was charged
+/// - rates default to 0, i.e., no cost
+/// - the connection time tracking state is bounded - see [set_sender_cache()](#method.set_sender_cache)
+/// - Accounting clones share the connection time tracking state
+#[derive(Debug, Clone)]
+pub struct Accounting {
+    message_type_rates: HashMap<MessageType, f64>,
+    byte_rate: f64,
+    connection_time_rate: f64,
+    // sender -> when the sender was last charged
+    last_charged: BoundedTimedCache<Address, Instant>,
+}
+
+impl Default for Accounting {
+    fn default() -> Self {
+        Self {
+            message_type_rates: HashMap::new(),
+            byte_rate: 0.0,
+            connection_time_rate: 0.0,
+            last_charged: BoundedTimedCache::new(
+                NonZeroUsize::new(DEFAULT_ACCOUNTING_SENDER_CAPACITY).unwrap(),
+                DEFAULT_ACCOUNTING_SENDER_TTL,
+            ),
+        }
+    }
+}
+
+impl Accounting {
+    /// Sets the flat rate that is charged for each message of the specified type
+    pub fn set_message_type_rate(mut self, message_type: MessageType, rate: f64) -> Self {
+        self.message_type_rates.insert(message_type, rate);
+        self
+    }
+
+    /// Sets the flat rate that is charged per message byte
+    pub fn set_byte_rate(mut self, rate: f64) -> Self {
+        self.byte_rate = rate;
+        self
+    }
+
+    /// Sets the flat rate that is charged per second of connection time
+    pub fn set_connection_time_rate(mut self, rate: f64) -> Self {
+        self.connection_time_rate = rate;
+        self
+    }
+
+    /// Configures the cache that tracks when each sender was last charged, which bounds the memory
+    /// used no matter how many distinct senders are seen
+    /// - senders that are evicted, or that have not been charged within the ttl, are treated as new
+    ///   senders, i.e., their next request is not charged any connection time
+    /// - default capacity = [DEFAULT_ACCOUNTING_SENDER_CAPACITY](constant.DEFAULT_ACCOUNTING_SENDER_CAPACITY.html)
+    /// - default ttl = [DEFAULT_ACCOUNTING_SENDER_TTL](constant.DEFAULT_ACCOUNTING_SENDER_TTL.html)
+    pub fn set_sender_cache(mut self, capacity: NonZeroUsize, ttl: Duration) -> Self {
+        self.last_charged = BoundedTimedCache::new(capacity, ttl);
+        self
+    }
+
+    /// Returns the flat rate that is charged for each message of the specified type
+    pub fn message_type_rate(&self, message_type: MessageType) -> f64 {
+        self.message_type_rates
+            .get(&message_type)
+            .cloned()
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the flat rate that is charged per message byte
+    pub fn byte_rate(&self) -> f64 {
+        self.byte_rate
+    }
+
+    /// Returns the flat rate that is charged per second of connection time
+    pub fn connection_time_rate(&self) -> f64 {
+        self.connection_time_rate
+    }
+
+    /// Computes the processing cost
+    pub fn cost(
+        &self,
+        message_type: MessageType,
+        msg_size: usize,
+        connection_time: Duration,
+    ) -> f64 {
+        let connection_secs = connection_time.as_secs() as f64
+            + f64::from(connection_time.subsec_nanos()) / 1_000_000_000.0;
+        self.message_type_rate(message_type)
+            + self.byte_rate * msg_size as f64
+            + self.connection_time_rate * connection_secs
+    }
+
+    /// Computes the processing cost for the sender's request and records it
+    fn charge(&self, sender: &Address, message_type: MessageType, msg_size: usize) -> f64 {
+        let now = Instant::now();
+        let connection_time = self
+            .last_charged
+            .insert(*sender, now)
+            .map(|last_charged| now.duration_since(last_charged))
+            .unwrap_or_else(|| Duration::from_millis(0));
+        let cost = self.cost(message_type, msg_size, connection_time);
+        PROCESSING_COST
+            .with_label_values(&[sender.to_string().as_str(), message_type.to_string().as_str()])
+            .inc_by(cost);
+        cost
+    }
+
+    /// Frees the sender's connection time tracking state, e.g., when the client disconnects
+    pub fn clear(&self, sender: &Address) {
+        self.last_charged.remove(sender);
+    }
+}
+
+/// Typed request/reply message processor
+/// - the `init()` and `destroy()` are lifecycle hooks, which by default are noop
+pub trait TypedProcessor<Req, Rep>
+where
+    Req: IsMessage + fmt::Debug + Clone + Send + Serialize + DeserializeOwned + 'static,
+    Rep: IsMessage + fmt::Debug + Clone + Send + Serialize + 'static,
+{
+    /// request / reply processing
+    fn process(&mut self, req: Req) -> FutureReply<Rep>;
+
+    /// request / reply processing, which provides access to the [RequestContext](../context/struct.RequestContext.html),
+    /// e.g., to retrieve the connection SessionId
+    /// - the default implementation delegates to `process()`
+    fn process_with_context(&mut self, req: Req, _ctx: &RequestContext) -> FutureReply<Rep> {
+        self.process(req)
+    }
+
+    /// Invoked before any messages have been sent
+    fn init(&mut self) {}
+
+    /// Invoked when the message processor service is being shutdown
+    fn destroy(&mut self) {}
+}
+
+/// Validates request messages before they are processed
+/// - closures of type `Fn(&Message<T>) -> Result<(), ValidationError>` are Validators
+pub trait Validator<T>: Send {
+    /// returns a ValidationError if the message is invalid
+    fn validate(&self, msg: &Message<T>) -> Result<(), ValidationError>;
+}
+
+impl<T, F> Validator<T> for F
+where
+    F: Fn(&Message<T>) -> Result<(), ValidationError> + Send,
+{
+    fn validate(&self, msg: &Message<T>) -> Result<(), ValidationError> {
+        self(msg)
+    }
+}
+
+/// Default Validator, which accepts all messages
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopValidator;
+
+impl<T> Validator<T> for NoopValidator {
+    fn validate(&self, _msg: &Message<T>) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+/// Request message validation error
+#[derive(Debug, Fail, Clone, Eq, PartialEq)]
+#[fail(display = "Validation failed: {}", _0)]
+pub struct ValidationError(String);
+
+impl ValidationError {
+    /// constructor
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        ValidationError(message.into())
+    }
+
+    /// Returns the error message
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Adapts a [TypedProcessor](trait.TypedProcessor.html) to a `Processor<nng::Message, nng::Message>`
+/// - requests are expected to be bincode encoded [SealedEnvelope(s)](../../../oysterpack_core/message/struct.SealedEnvelope.html)
+///   addressed to the service
+/// - replies are sealed and addressed to the request sender, using the configured Encoding
+pub struct SealedEnvelopeProcessor<P, Req, Rep> {
+    reqrep_id: ReqRepId,
+    processor: P,
+    address: Address,
+    private_key: box_::SecretKey,
+    encoding: Encoding,
+    encodings: Vec<Encoding>,
+    // sender -> precomputed key
+    precomputed_keys: BoundedTimedCache<Address, box_::PrecomputedKey>,
+    // connection SessionId -> handshake that is awaiting the client's challenge response
+    handshakes: BoundedTimedCache<SessionId, ServerHandshake>,
+    // connection SessionId -> negotiated encoding
+    session_encodings: BoundedTimedCache<SessionId, Encoding>,
+    accounting: Option<Accounting>,
+    request_context_sink: Option<mpsc::UnboundedSender<RequestContext>>,
+    validate_session_id: bool,
+    request_logger: Option<RequestLogger>,
+    max_message_age: Option<Duration>,
+    clock_skew_tolerance: Duration,
+    signing_policy: SigningPolicy,
+    // sender -> signing public-key
+    signing_keys: HashMap<Address, sign::PublicKey>,
+    validator: Box<dyn Validator<Req>>,
+    compression_policy: CompressionPolicy,
+    decode_failure_callback: Option<Box<dyn Fn(DecodeFailure, &str) + Send>>,
+    _msg_types: PhantomData<fn(Req) -> Rep>,
+}
+
+impl<P, Req, Rep> SealedEnvelopeProcessor<P, Req, Rep>
+where
+    P: TypedProcessor<Req, Rep>,
+    Req: IsMessage + fmt::Debug + Clone + Send + Serialize + DeserializeOwned + 'static,
+    Rep: IsMessage + fmt::Debug + Clone + Send + Serialize + 'static,
+{
+    /// constructor
+    /// - reqrep_id is used to tag ServiceError replies
+    /// - address and private_key are the service's keys
+    /// - encoding is used to encode the reply messages, unless an encoding was negotiated for the
+    ///   session via the handshake
+    pub fn new(
+        reqrep_id: ReqRepId,
+        processor: P,
+        address: Address,
+        private_key: box_::SecretKey,
+        encoding: Encoding,
+    ) -> Self {
+        let cache_capacity = NonZeroUsize::new(DEFAULT_KEY_CACHE_CAPACITY).unwrap();
+        Self {
+            reqrep_id,
+            processor,
+            address,
+            private_key,
+            encoding,
+            encodings: vec![encoding],
+            precomputed_keys: BoundedTimedCache::new(cache_capacity, DEFAULT_KEY_CACHE_TTL),
+            handshakes: BoundedTimedCache::new(cache_capacity, DEFAULT_KEY_CACHE_TTL),
+            session_encodings: BoundedTimedCache::new(cache_capacity, DEFAULT_KEY_CACHE_TTL),
+            accounting: None,
+            request_context_sink: None,
+            validate_session_id: false,
+            request_logger: None,
+            max_message_age: None,
+            clock_skew_tolerance: DEFAULT_CLOCK_SKEW_TOLERANCE,
+            signing_policy: SigningPolicy::default(),
+            signing_keys: HashMap::new(),
+            validator: Box::new(NoopValidator),
+            compression_policy: CompressionPolicy::default(),
+            decode_failure_callback: None,
+            _msg_types: PhantomData,
+        }
+    }
+
+    /// Unsigned requests are rejected if the signing policy requires the request message to be signed
+    /// - the policy should mirror the client's policy - see [TypedClient::set_signing_policy()](struct.TypedClient.html#method.set_signing_policy)
+    /// - default = [Signing::Never](enum.Signing.html#variant.Never), i.e., signatures are optional
+    pub fn set_signing_policy(mut self, signing_policy: SigningPolicy) -> Self {
+        self.signing_policy = signing_policy;
+        self
+    }
+
+    /// Registers the sender's signing public-key, which is used to verify signed requests
+    /// - signed requests from senders whose signing key is unknown are rejected
+    pub fn add_signing_key(mut self, sender: Address, signing_key: sign::PublicKey) -> Self {
+        self.signing_keys.insert(sender, signing_key);
+        self
+    }
+
+    /// Returns the signing policy
+    pub fn signing_policy(&self) -> &SigningPolicy {
+        &self.signing_policy
+    }
+
+    /// Enables processing cost accounting - see [Accounting](struct.Accounting.html)
+    pub fn set_accounting(mut self, accounting: Accounting) -> Self {
+        self.accounting = Some(accounting);
+        self
+    }
+
+    /// Each completed RequestContext is sent to the sink, e.g., for latency attribution
+    pub fn set_request_context_sink(
+        mut self,
+        sink: mpsc::UnboundedSender<RequestContext>,
+    ) -> Self {
+        self.request_context_sink = Some(sink);
+        self
+    }
+
+    /// If enabled, then requests are rejected if the message [Metadata::session_id()](../../../oysterpack_core/message/struct.Metadata.html#method.session_id)
+    /// does not match the SessionId that was assigned to the request's connection, i.e., spoofed
+    /// session ids are rejected
+    /// - replies are stamped with the connection SessionId, which is how clients learn their session
+    /// - default = false
+    pub fn set_validate_session_id(mut self, validate_session_id: bool) -> Self {
+        self.validate_session_id = validate_session_id;
+        self
+    }
+
+    /// Enables sampled request logging - see [RequestLogger](../request_log/struct.RequestLogger.html)
+    pub fn set_request_logger(mut self, request_logger: RequestLogger) -> Self {
+        self.request_logger = Some(request_logger);
+        self
+    }
+
+    /// Requests whose [Metadata::timestamp()](../../../oysterpack_core/message/struct.Metadata.html#method.timestamp)
+    /// is older than the max message age are rejected as stale, independent of the message Deadline
+    /// - the clock skew tolerance is added to the max message age - see [set_clock_skew_tolerance()](struct.SealedEnvelopeProcessor.html#method.set_clock_skew_tolerance)
+    /// - default = None, i.e., message age is not checked
+    pub fn set_max_message_age(mut self, max_message_age: Duration) -> Self {
+        self.max_message_age = Some(max_message_age);
+        self
+    }
+
+    /// Tolerance for clock skew between the sender and the server, which is applied when checking
+    /// the max message age
+    /// - default = [DEFAULT_CLOCK_SKEW_TOLERANCE](constant.DEFAULT_CLOCK_SKEW_TOLERANCE.html)
+    pub fn set_clock_skew_tolerance(mut self, clock_skew_tolerance: Duration) -> Self {
+        self.clock_skew_tolerance = clock_skew_tolerance;
+        self
+    }
+
+    /// Requests are validated after they are decoded, and before they are dispatched to the
+    /// TypedProcessor. Requests that fail validation are rejected with a ServiceError reply.
+    /// - default = [NoopValidator](struct.NoopValidator.html)
+    pub fn set_validator<V>(mut self, validator: V) -> Self
+    where
+        V: Validator<Req> + 'static,
+    {
+        self.validator = Box::new(validator);
+        self
+    }
+
+    /// Reply messages are encoded using the compression that the policy maps to the reply
+    /// MessageType
+    /// - message types that are not configured by the policy use the configured Encoding as is
+    pub fn set_compression_policy(mut self, compression_policy: CompressionPolicy) -> Self {
+        self.compression_policy = compression_policy;
+        self
+    }
+
+    /// The callback is invoked each time a request message fails to be decoded, after the
+    /// [DECODE_FAILURE_COUNT_METRIC_ID](constant.DECODE_FAILURE_COUNT_METRIC_ID.html) metric is
+    /// incremented, e.g., to alert on a misbehaving or malicious peer
+    /// - the callback is invoked inline, and thus should not block
+    pub fn set_decode_failure_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(DecodeFailure, &str) + Send + 'static,
+    {
+        self.decode_failure_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Configures the cache for the keys that are precomputed per sender, which bounds the memory
+    /// used by the service no matter how many distinct senders it sees
+    /// - default capacity = [DEFAULT_KEY_CACHE_CAPACITY](constant.DEFAULT_KEY_CACHE_CAPACITY.html)
+    /// - default ttl = [DEFAULT_KEY_CACHE_TTL](constant.DEFAULT_KEY_CACHE_TTL.html)
+    pub fn set_key_cache(mut self, capacity: NonZeroUsize, ttl: Duration) -> Self {
+        self.precomputed_keys = BoundedTimedCache::new(capacity, ttl);
+        self
+    }
+
+    /// Returns the max message age
+    pub fn max_message_age(&self) -> Option<Duration> {
+        self.max_message_age
+    }
+
+    /// Returns the clock skew tolerance
+    pub fn clock_skew_tolerance(&self) -> Duration {
+        self.clock_skew_tolerance
+    }
+
+    /// Returns the service address
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Returns the Encoding that is used for reply messages, unless an encoding was negotiated for
+    /// the session
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Sets the encodings the service supports, in priority order, which are negotiated with the
+    /// client's encoding preferences during the handshake
+    /// - default = the configured Encoding
+    pub fn set_encodings(mut self, encodings: Vec<Encoding>) -> Self {
+        self.encodings = encodings;
+        self
+    }
+
+    /// Returns the encodings the service supports, in priority order
+    pub fn encodings(&self) -> &[Encoding] {
+        &self.encodings
+    }
+
+    /// Returns the encoding that was negotiated for the session via the handshake
+    pub fn session_encoding(&self, session_id: SessionId) -> Option<Encoding> {
+        self.session_encodings.get(&session_id)
+    }
+
+    /// opens the request envelope
+    /// - returns the precomputed key, the sender address, whether the message was signed, and the
+    ///   encoded message
+    fn open(
+        &mut self,
+        req: &nng::Message,
+    ) -> Result<(box_::PrecomputedKey, Address, bool, EncodedMessage), String> {
+        let bytes: &[u8] = req;
+        let sealed_envelope = SealedEnvelope::decode_transport_message(bytes)
+            .map_err(|err| self.decode_failed(DecodeFailure::Framing, err.to_string()))?;
+        if *sealed_envelope.recipient() != self.address {
+            return Err(format!(
+                "message was not addressed to this service: {}",
+                sealed_envelope.recipient()
+            ));
+        }
+        let sender = *sealed_envelope.sender();
+        let key = {
+            let private_key = &self.private_key;
+            self.precomputed_keys
+                .get_or_insert_with(sender, || box_::precompute(sender.public_key(), private_key))
+        };
+        let open_envelope = sealed_envelope
+            .open(&key)
+            .map_err(|err| self.decode_failed(DecodeFailure::Auth, err.to_string()))?;
+        let signed = open_envelope.is_signed();
+        let encoded_message = if signed {
+            let signing_key = self.signing_keys.get(&sender).ok_or_else(|| {
+                self.decode_failed(
+                    DecodeFailure::Auth,
+                    format!("signing key is unknown for sender: {}", sender),
+                )
+            })?;
+            // the signed message cannot be trusted if it fails to be verified
+            open_envelope
+                .verified_encoded_message(signing_key)
+                .map_err(|err| self.decode_failed(DecodeFailure::Auth, err.to_string()))?
+        } else {
+            open_envelope
+                .encoded_message()
+                .map_err(|err| self.decode_failed(DecodeFailure::Framing, err.to_string()))?
+        };
+        Ok((key, sender, signed, encoded_message))
+    }
+
+    /// decodes the message data
+    fn decode<T>(&self, encoded_message: EncodedMessage) -> Result<Message<T>, String>
+    where
+        T: fmt::Debug + Clone + Serialize + DeserializeOwned,
+    {
+        let (_, msg) = encoded_message.decode::<T>().map_err(|err| {
+            let failure = if err.id() == DecompressionError::ERROR_ID {
+                DecodeFailure::Decompression
+            } else {
+                DecodeFailure::Deserialization
+            };
+            self.decode_failed(failure, err.to_string())
+        })?;
+        Ok(msg)
+    }
+
+    /// runs the server side of the connection handshake for the connection session
+    /// - a `Connect` request is answered with a `ConnectChallenge`
+    /// - a `ConnectChallengeResponse` is answered with `ConnectAccepted`, and the negotiated
+    ///   encoding is stored on the session
+    fn handshake(
+        &mut self,
+        key: &box_::PrecomputedKey,
+        sender: Address,
+        encoded_message: EncodedMessage,
+        session_id: Option<SessionId>,
+    ) -> Result<nng::Message, String> {
+        let session_id = session_id.ok_or_else(|| "connection session id is unknown".to_string())?;
+        let req_metadata = encoded_message.metadata();
+        if req_metadata.message_type() == Connect::MESSAGE_TYPE_ID.message_type() {
+            let connect = self.decode::<Connect>(encoded_message)?;
+            let (handshake, challenge) =
+                ServerHandshake::new(sender, connect.data(), &self.encodings)
+                    .map_err(|err| err.to_string())?;
+            self.handshakes
+                .insert(session_id, handshake.set_session_id(session_id));
+            self.seal_handshake_reply(challenge, &req_metadata, session_id, sender, key)
+        } else {
+            let response = self.decode::<ConnectChallengeResponse>(encoded_message)?;
+            let handshake = self
+                .handshakes
+                .remove(&session_id)
+                .filter(|handshake| *handshake.client() == sender)
+                .ok_or_else(|| format!("handshake was not started by sender: {}", sender))?;
+            let connect_accepted = handshake
+                .verify(response.data())
+                .map_err(|err| err.to_string())?;
+            self.session_encodings
+                .insert(session_id, connect_accepted.encoding());
+            self.seal_handshake_reply(connect_accepted, &req_metadata, session_id, sender, key)
+        }
+    }
+
+    fn seal_handshake_reply<T>(
+        &self,
+        reply: T,
+        req_metadata: &Metadata,
+        session_id: SessionId,
+        recipient: Address,
+        key: &box_::PrecomputedKey,
+    ) -> Result<nng::Message, String>
+    where
+        T: IsMessage + fmt::Debug + Clone + Serialize,
+    {
+        let metadata = Metadata::new(T::MESSAGE_TYPE_ID.message_type(), self.encoding, None)
+            .correlate(req_metadata.instance_id())
+            .set_session_id(session_id);
+        seal_reply(Message::new(metadata, reply), self.address, recipient, key)
+    }
+
+    /// checks whether the decoded request is admitted, i.e., requests that are decoded can still be
+    /// rejected, e.g., because they are stale or they fail validation
+    fn admit(
+        &self,
+        msg: &Message<Req>,
+        sender: &Address,
+        signed: bool,
+        session_id: Option<SessionId>,
+        request_size: usize,
+    ) -> Result<(), String> {
+        let msg_type = msg.metadata().message_type();
+        if msg_type != Req::MESSAGE_TYPE_ID.message_type() {
+            return Err(format!("unsupported message type: {}", msg_type));
+        }
+        if !signed && self.signing_policy.requires_signature(msg.metadata()) {
+            return Err(format!("message must be signed: {}", msg_type));
+        }
+        if let Some(max_message_age) = self.max_message_age {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_millis() as i64)
+                .unwrap_or(0);
+            let age = now - msg.metadata().timestamp().timestamp_millis();
+            if age > (max_message_age + self.clock_skew_tolerance).as_millis() as i64 {
+                STALE_MSG_COUNT
+                    .with_label_values(&[self.reqrep_id.to_string().as_str()])
+                    .inc();
+                return Err(format!(
+                    "stale message: age = {} ms, max message age = {:?}",
+                    age, max_message_age
+                ));
+            }
+        }
+        if let Some(deadline) = msg.metadata().deadline() {
+            if remaining_time(deadline, msg.metadata()) == Duration::from_millis(0) {
+                return Err(format!("request deadline has expired: {:?}", deadline));
+            }
+        }
+        if self.validate_session_id {
+            match session_id {
+                Some(session_id) if session_id == msg.metadata().session_id() => (),
+                Some(session_id) => {
+                    return Err(format!(
+                        "session id mismatch: message session id ({}) does not match the connection session id ({})",
+                        msg.metadata().session_id(),
+                        session_id
+                    ));
+                }
+                None => return Err("connection session id is unknown".to_string()),
+            }
+        }
+        self.validator
+            .validate(msg)
+            .map_err(|err| err.to_string())?;
+        if let Some(accounting) = self.accounting.as_ref() {
+            accounting.charge(sender, msg_type, request_size);
+        }
+        Ok(())
+    }
+
+    /// records the decode failure, and returns the error message
+    fn decode_failed(&self, failure: DecodeFailure, err: String) -> String {
+        DECODE_FAILURE_COUNT
+            .with_label_values(&[self.reqrep_id.to_string().as_str(), failure.as_str()])
+            .inc();
+        if let Some(callback) = self.decode_failure_callback.as_ref() {
+            callback(failure, &err);
+        }
+        err
+    }
+}
+
+/// Returns the span that wraps the request processing, which is nested within the client's
+/// `typed_send` span, if the request metadata carries its span id
+#[cfg(feature = "tracing")]
+fn process_span(reqrep_id: ReqRepId, metadata: &Metadata) -> tracing::Span {
+    let parent = metadata
+        .attribute(TRACE_PARENT_ATTR_ID)
+        .map(|span_id| u128::from(span_id) as u64)
+        // span ids are non-zero
+        .filter(|span_id| *span_id != 0)
+        .map(tracing::span::Id::from_u64);
+    match parent {
+        Some(parent) => tracing::info_span!(
+            parent: parent,
+            "typed_process",
+            reqrep_id = %reqrep_id,
+            msg_id = %metadata.instance_id()
+        ),
+        None => tracing::info_span!(
+            "typed_process",
+            reqrep_id = %reqrep_id,
+            msg_id = %metadata.instance_id()
+        ),
+    }
+}
+
+/// returns true if the message is sent by the client during the connection handshake
+fn is_handshake_message(message_type: MessageType) -> bool {
+    message_type == Connect::MESSAGE_TYPE_ID.message_type()
+        || message_type == ConnectChallengeResponse::MESSAGE_TYPE_ID.message_type()
+}
+
+/// returns the time remaining until the request deadline expires
+fn remaining_time(deadline: Deadline, metadata: &Metadata) -> Duration {
+    deadline
+        .duration(metadata.timestamp())
+        .to_std()
+        .unwrap_or_default()
+}
+
+fn service_error(reqrep_id: ReqRepId, err: String) -> nng::Message {
+    warn!("ReqRepId({}) : {}", reqrep_id, err);
+    ServiceError::new(reqrep_id, err)
+        .encode()
+        .unwrap_or_else(|err| {
+            error!("Failed to encode ServiceError reply: {}", err);
+            nng::Message::new().unwrap()
+        })
+}
+
+fn complete(
+    reqrep_id: ReqRepId,
+    ctx: RequestContext,
+    request_context_sink: Option<mpsc::UnboundedSender<RequestContext>>,
+) {
+    ctx.complete(reqrep_id);
+    if let Some(sink) = request_context_sink {
+        if let Err(err) = sink.unbounded_send(ctx) {
+            warn!("ReqRepId({}) RequestContext sink is disconnected: {}", reqrep_id, err);
+        }
+    }
+}
+
+fn seal_reply<Rep>(
+    reply: Message<Rep>,
+    sender: Address,
+    recipient: Address,
+    key: &box_::PrecomputedKey,
+) -> Result<nng::Message, String>
+where
+    Rep: fmt::Debug + Clone + Serialize,
+{
+    let sealed_envelope = reply
+        .encoded_message(sender, recipient)
+        .and_then(EncodedMessage::open_envelope)
+        .map(|open_envelope| open_envelope.seal(key))
+        .map_err(|err| err.to_string())?;
+    let mut bytes = Vec::new();
+    sealed_envelope
+        .encode(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    let mut msg = nng::Message::with_capacity(bytes.len()).map_err(|err| err.to_string())?;
+    msg.push_back(&bytes).map_err(|err| err.to_string())?;
+    Ok(msg)
+}
+
+impl<P, Req, Rep> Processor<nng::Message, nng::Message> for SealedEnvelopeProcessor<P, Req, Rep>
+where
+    P: TypedProcessor<Req, Rep>,
+    Req: IsMessage + fmt::Debug + Clone + Send + Serialize + DeserializeOwned + 'static,
+    Rep: IsMessage + fmt::Debug + Clone + Send + Serialize + 'static,
+{
+    #[cfg_attr(not(feature = "tracing"), allow(clippy::let_and_return))]
+    fn process(&mut self, req: nng::Message) -> FutureReply<nng::Message> {
+        let reqrep_id = self.reqrep_id;
+        let request_context_sink = self.request_context_sink.clone();
+        let request_logger = self.request_logger.clone();
+        let request_size = req.len();
+        let mut ctx = RequestContext::new();
+        let session_id = server::connection_session_id(&req);
+        if let Some(session_id) = session_id {
+            ctx.set_session_id(session_id);
+        }
+        let decode_start = Instant::now();
+        let opened = self.open(&req);
+        if let Ok((key, sender, _, encoded_message)) = opened.as_ref() {
+            // handshake messages are not dispatched to the TypedProcessor
+            if is_handshake_message(encoded_message.metadata().message_type()) {
+                let reply = self
+                    .handshake(key, *sender, encoded_message.clone(), session_id)
+                    .unwrap_or_else(|err| service_error(reqrep_id, err));
+                return async move { reply }.boxed();
+            }
+        }
+        let decoded = opened
+            .and_then(|(key, sender, signed, encoded_message)| {
+                let msg = self.decode::<Req>(encoded_message)?;
+                Ok((key, sender, signed, msg))
+            })
+            .map(|(key, sender, signed, msg)| {
+                // decoded requests are identified by their InstanceId, even if they are rejected
+                ctx.set_instance_id(msg.metadata().instance_id());
+                let admitted = self.admit(&msg, &sender, signed, session_id, request_size);
+                (key, sender, msg, admitted)
+            });
+        ctx.record(Stage::Decode, decode_start.elapsed());
+        let decoded = match decoded {
+            Ok((key, sender, msg, Ok(()))) => Ok((key, sender, msg)),
+            Ok((_, sender, msg, Err(err))) => {
+                Err((err, Some((sender, msg.metadata().message_type()))))
+            }
+            Err(err) => Err((err, None)),
+        };
+        match decoded {
+            Ok((key, sender, msg)) => {
+                #[cfg(feature = "tracing")]
+                let span = process_span(reqrep_id, msg.metadata());
+                #[cfg(feature = "tracing")]
+                let _enter = span.enter();
+                let rep_msg_type = Rep::MESSAGE_TYPE_ID.message_type();
+                let encoding = session_id
+                    .and_then(|session_id| self.session_encoding(session_id))
+                    .unwrap_or(self.encoding);
+                let encoding = self.compression_policy.encoding(rep_msg_type, encoding);
+                let mut metadata = Metadata::new(rep_msg_type, encoding, None)
+                    .correlate(msg.metadata().instance_id());
+                if let Some(session_id) = session_id {
+                    metadata = metadata.set_session_id(session_id);
+                }
+                let address = self.address;
+                let deadline = msg.metadata().deadline().map(|deadline| {
+                    let remaining_time = remaining_time(deadline, msg.metadata());
+                    (deadline, DeadlineSignal::new(Instant::now() + remaining_time))
+                });
+                let dispatch_start = Instant::now();
+                let reply = self
+                    .processor
+                    .process_with_context(msg.data().clone(), &ctx);
+                let reply = async move {
+                    // if the deadline expires first, then dropping the reply future abandons the work
+                    let reply = match deadline {
+                        Some((deadline, signal)) => {
+                            match await!(future::select(reply, signal.expired().boxed())) {
+                                Either::Left((reply, _)) => Ok(reply),
+                                Either::Right(_) => Err(deadline),
+                            }
+                        }
+                        None => Ok(await!(reply)),
+                    };
+                    ctx.record(Stage::Dispatch, dispatch_start.elapsed());
+                    let (reply, outcome) = match reply {
+                        Ok(reply) => {
+                            let reply = Message::new(metadata, reply);
+                            match ctx
+                                .time(Stage::Encode, || seal_reply(reply, address, sender, &key))
+                            {
+                                Ok(reply) => (reply, RequestOutcome::Ok),
+                                Err(err) => (
+                                    service_error(reqrep_id, err.clone()),
+                                    RequestOutcome::Failed(err),
+                                ),
+                            }
+                        }
+                        Err(deadline) => {
+                            ABANDONED_REQUEST_COUNT
+                                .with_label_values(&[reqrep_id.to_string().as_str()])
+                                .inc();
+                            let err = format!("request deadline has expired: {:?}", deadline);
+                            (service_error(reqrep_id, err.clone()), RequestOutcome::Failed(err))
+                        }
+                    };
+                    if let Some(request_logger) = request_logger {
+                        request_logger.log(
+                            RequestLogRecord::new(reqrep_id, &ctx, request_size, outcome)
+                                .set_sender(sender)
+                                .set_message_type(Req::MESSAGE_TYPE_ID.message_type()),
+                        );
+                    }
+                    complete(reqrep_id, ctx, request_context_sink);
+                    reply
+                }
+                    .boxed();
+                #[cfg(feature = "tracing")]
+                let reply = Instrumented::new(reply, span.clone()).boxed();
+                reply
+            }
+            Err((err, rejected)) => {
+                let reply = service_error(reqrep_id, err.clone());
+                if let Some(request_logger) = request_logger {
+                    let mut record = RequestLogRecord::new(
+                        reqrep_id,
+                        &ctx,
+                        request_size,
+                        RequestOutcome::Failed(err),
+                    );
+                    // the request was decoded, but rejected
+                    if let Some((sender, message_type)) = rejected {
+                        record = record.set_sender(sender).set_message_type(message_type);
+                    }
+                    request_logger.log(record);
+                }
+                complete(reqrep_id, ctx, request_context_sink);
+                async move { reply }.boxed()
+            }
+        }
+    }
+
+    fn init(&mut self) {
+        self.processor.init()
+    }
+
+    fn destroy(&mut self) {
+        self.processor.destroy()
+    }
+}
+
+impl<P, Req, Rep> fmt::Debug for SealedEnvelopeProcessor<P, Req, Rep> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SealedEnvelopeProcessor(ReqRepId({}), address: {}, encoding: {})",
+            self.reqrep_id, self.address, self.encoding
+        )
+    }
+}
+
+/// Decides whether a message must be signed
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Signing {
+    /// messages are always signed
+    Always,
+    /// messages are not signed
+    Never,
+    /// messages are signed if they specify a [Deadline](../../../oysterpack_core/message/enum.Deadline.html)
+    IfDeadlineSet,
+}
+
+impl Default for Signing {
+    fn default() -> Self {
+        Signing::Never
+    }
+}
+
+/// Message signing policy, which is keyed by MessageType
+/// - message types that are not configured use the default [Signing](enum.Signing.html)
+#[derive(Debug, Clone, Default)]
+pub struct SigningPolicy {
+    default: Signing,
+    message_types: HashMap<MessageType, Signing>,
+}
+
+impl SigningPolicy {
+    /// Sets the Signing that is applied to message types that are not explicitly configured
+    /// - default = [Signing::Never](enum.Signing.html#variant.Never)
+    pub fn set_default(mut self, signing: Signing) -> Self {
+        self.default = signing;
+        self
+    }
+
+    /// Sets the Signing for the specified message type
+    pub fn set_message_type_signing(mut self, message_type: MessageType, signing: Signing) -> Self {
+        self.message_types.insert(message_type, signing);
+        self
+    }
+
+    /// Returns the Signing for the specified message type
+    pub fn signing(&self, message_type: MessageType) -> Signing {
+        self.message_types
+            .get(&message_type)
+            .cloned()
+            .unwrap_or(self.default)
+    }
+
+    /// Returns true if the message must be signed
+    pub fn requires_signature(&self, metadata: &Metadata) -> bool {
+        match self.signing(metadata.message_type()) {
+            Signing::Always => true,
+            Signing::Never => false,
+            Signing::IfDeadlineSet => metadata.deadline().is_some(),
+        }
+    }
+}
+
+/// Message compression policy, which is keyed by MessageType
+/// - message types that are not configured use the compression of the configured Encoding
+/// - pre-compressed message types should be mapped to None, i.e., no compression
+#[derive(Debug, Clone, Default)]
+pub struct CompressionPolicy {
+    message_types: HashMap<MessageType, Option<Compression>>,
+}
+
+impl CompressionPolicy {
+    /// Sets the compression for the specified message type
+    pub fn set_message_type_compression(
+        mut self,
+        message_type: MessageType,
+        compression: Option<Compression>,
+    ) -> Self {
+        self.message_types.insert(message_type, compression);
+        self
+    }
+
+    /// Returns the compression for the specified message type
+    /// - returns None if the message type is not configured
+    pub fn compression(&self, message_type: MessageType) -> Option<Option<Compression>> {
+        self.message_types.get(&message_type).cloned()
+    }
+
+    /// Returns the Encoding to use for the specified message type, i.e., the default Encoding's
+    /// compression is overridden if the message type is configured
+    pub fn encoding(&self, message_type: MessageType, default: Encoding) -> Encoding {
+        match self.compression(message_type) {
+            Some(compression) => default.with_compression(compression),
+            None => default,
+        }
+    }
+}
+
+/// Sends typed requests to a service that is plugged in via a [SealedEnvelopeProcessor](struct.SealedEnvelopeProcessor.html)
+/// - requests are sealed and addressed to the service, using the negotiated Encoding once the
+///   [handshake](#method.handshake) has completed, and the configured Encoding until then
+/// - requests are signed according to the [SigningPolicy](struct.SigningPolicy.html)
+/// - requests are compressed according to the [CompressionPolicy](struct.CompressionPolicy.html)
+pub struct TypedClient<Req, Rep> {
+    client: Client,
+    address: Address,
+    service_address: Address,
+    key: box_::PrecomputedKey,
+    encoding: Encoding,
+    encodings: Vec<Encoding>,
+    session: Option<ConnectAccepted>,
+    signing_policy: SigningPolicy,
+    signing_key: Option<sign::SecretKey>,
+    compression_policy: CompressionPolicy,
+    _msg_types: PhantomData<fn(Req) -> Rep>,
+}
+
+impl<Req, Rep> TypedClient<Req, Rep>
+where
+    Req: IsMessage + fmt::Debug + Clone + Send + Serialize + 'static,
+    Rep: IsMessage + fmt::Debug + Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// constructor
+    /// - address and private_key are the client's keys
+    /// - service_address is the service's address
+    /// - encoding is used to encode the request messages, until an encoding is negotiated via the
+    ///   handshake
+    pub fn new(
+        client: Client,
+        address: Address,
+        private_key: &box_::SecretKey,
+        service_address: Address,
+        encoding: Encoding,
+    ) -> Self {
+        Self {
+            client,
+            address,
+            service_address,
+            key: service_address.precompute_sealing_key(private_key),
+            encoding,
+            encodings: vec![encoding],
+            session: None,
+            signing_policy: SigningPolicy::default(),
+            signing_key: None,
+            compression_policy: CompressionPolicy::default(),
+            _msg_types: PhantomData,
+        }
+    }
+
+    /// Sets the policy that decides which requests are signed
+    /// - a signing key is required for requests that must be signed - see [set_signing_key()](#method.set_signing_key)
+    pub fn set_signing_policy(mut self, signing_policy: SigningPolicy) -> Self {
+        self.signing_policy = signing_policy;
+        self
+    }
+
+    /// Sets the private key that is used to sign requests
+    pub fn set_signing_key(mut self, signing_key: sign::SecretKey) -> Self {
+        self.signing_key = Some(signing_key);
+        self
+    }
+
+    /// Returns the signing policy
+    pub fn signing_policy(&self) -> &SigningPolicy {
+        &self.signing_policy
+    }
+
+    /// Request messages are encoded using the compression that the policy maps to the request
+    /// MessageType
+    /// - message types that are not configured by the policy use the configured Encoding as is
+    pub fn set_compression_policy(mut self, compression_policy: CompressionPolicy) -> Self {
+        self.compression_policy = compression_policy;
+        self
+    }
+
+    /// Returns the client address
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Returns the service address
+    pub fn service_address(&self) -> &Address {
+        &self.service_address
+    }
+
+    /// Returns the client ReqRepId
+    pub fn id(&self) -> ReqRepId {
+        self.client.id()
+    }
+
+    /// Sets the encodings the client supports, in priority order, which are offered to the service
+    /// during the handshake
+    /// - default = the configured Encoding
+    pub fn set_encodings(mut self, encodings: Vec<Encoding>) -> Self {
+        self.encodings = encodings;
+        self
+    }
+
+    /// Returns the encodings the client supports, in priority order
+    pub fn encodings(&self) -> &[Encoding] {
+        &self.encodings
+    }
+
+    /// Returns the Encoding that is used for request messages, i.e., the negotiated encoding once
+    /// the handshake has completed
+    pub fn encoding(&self) -> Encoding {
+        self.session
+            .as_ref()
+            .map(ConnectAccepted::encoding)
+            .unwrap_or(self.encoding)
+    }
+
+    /// Returns the SessionId that the service assigned via the handshake
+    pub fn session_id(&self) -> Option<SessionId> {
+        self.session.as_ref().map(ConnectAccepted::session_id)
+    }
+
+    /// Runs the connection [handshake](../../../oysterpack_core/message/handshake/index.html) with
+    /// the service
+    /// - the client proves that it controls its signing key, which must be configured - see
+    ///   [set_signing_key()](#method.set_signing_key)
+    /// - the service picks the client's most preferred encoding that it supports - see
+    ///   [set_encodings()](#method.set_encodings)
+    /// - subsequent requests are encoded using the negotiated encoding, and are stamped with the
+    ///   SessionId that the service assigned to the connection
+    pub async fn handshake(&mut self) -> Result<SessionId, TypedRequestError> {
+        let signing_key = self.signing_key.clone().ok_or_else(|| {
+            TypedRequestError::InvalidRequest(
+                "the handshake requires a signing key, but no signing key is configured"
+                    .to_string(),
+            )
+        })?;
+        // the ed25519 secret key is the seed followed by the public key
+        let signing_public_key = sign::PublicKey::from_slice(
+            &signing_key.0[sign::SECRETKEYBYTES - sign::PUBLICKEYBYTES..],
+        )
+        .unwrap();
+        let connect = Connect::new(signing_public_key, self.encodings.clone());
+        let challenge: Message<ConnectChallenge> = {
+            let req = self.seal(connect, self.handshake_metadata::<Connect>(), false)?;
+            let reply = await!(self.client.send_recv(req));
+            self.open(reply)?
+        };
+        let response = challenge.data().respond(&signing_key);
+        let connect_accepted: Message<ConnectAccepted> = {
+            let metadata = self.handshake_metadata::<ConnectChallengeResponse>();
+            let req = self.seal(response, metadata, false)?;
+            let reply = await!(self.client.send_recv(req));
+            self.open(reply)?
+        };
+        let session_id = connect_accepted.data().session_id();
+        self.session = Some(connect_accepted.data().clone());
+        Ok(session_id)
+    }
+
+    /// handshake messages are encoded using the configured Encoding
+    fn handshake_metadata<T: IsMessage>(&self) -> Metadata {
+        Metadata::new(T::MESSAGE_TYPE_ID.message_type(), self.encoding, None)
+    }
+
+    /// Sends the request and awaits the reply
+    pub async fn send_recv(
+        &mut self,
+        req: Req,
+        deadline: Option<Deadline>,
+    ) -> Result<Message<Rep>, TypedRequestError> {
+        let req_msg_type = Req::MESSAGE_TYPE_ID.message_type();
+        let encoding = self.compression_policy.encoding(req_msg_type, self.encoding());
+        let mut metadata = Metadata::new(req_msg_type, encoding, deadline);
+        if let Some(session_id) = self.session_id() {
+            metadata = metadata.set_session_id(session_id);
+        }
+        #[cfg(feature = "tracing")]
+        let (span, metadata) = {
+            let span = tracing::info_span!(
+                "typed_send",
+                reqrep_id = %self.client.id(),
+                msg_id = %metadata.instance_id()
+            );
+            let metadata = match span.id() {
+                Some(span_id) => metadata
+                    .with_attribute(
+                        TRACE_PARENT_ATTR_ID,
+                        oysterpack_uid::ULID::from(u128::from(span_id.into_u64())),
+                    )
+                    .map_err(|err| TypedRequestError::InvalidRequest(err.to_string()))?,
+                None => metadata,
+            };
+            (span, metadata)
+        };
+        let sign = self.signing_policy.requires_signature(&metadata);
+        let req = self.seal(req, metadata, sign)?;
+        let reply = self.client.send_recv(req);
+        #[cfg(feature = "tracing")]
+        let reply = Instrumented::new(reply.boxed(), span);
+        let reply = await!(reply);
+        self.open(reply)
+    }
+
+    /// opens the sealed reply
+    fn open<T>(
+        &self,
+        reply: Result<Result<nng::Message, RequestError>, ChannelError>,
+    ) -> Result<Message<T>, TypedRequestError>
+    where
+        T: fmt::Debug + Clone + Serialize + DeserializeOwned,
+    {
+        let reply = reply
+            .map_err(TypedRequestError::Channel)?
+            .map_err(|err| match err {
+                RequestError::Service(err) => TypedRequestError::Service(err),
+                err => TypedRequestError::Request(err),
+            })?;
+        let bytes: &[u8] = &reply;
+        let (_, reply) = SealedEnvelope::decode_transport_message(bytes)
+            .and_then(|sealed_envelope| sealed_envelope.open(&self.key))
+            .and_then(|open_envelope| open_envelope.encoded_message())
+            .and_then(EncodedMessage::decode::<T>)
+            .map_err(|err| TypedRequestError::InvalidReply(err.to_string()))?;
+        Ok(reply)
+    }
+
+    /// seals the request, which is signed if `sign` is true
+    fn seal<T>(
+        &self,
+        req: T,
+        metadata: Metadata,
+        sign: bool,
+    ) -> Result<nng::Message, TypedRequestError>
+    where
+        T: fmt::Debug + Clone + Serialize,
+    {
+        let encoded_message = Message::new(metadata, req)
+            .encoded_message(self.address, self.service_address)
+            .map_err(|err| TypedRequestError::InvalidRequest(err.to_string()))?;
+        let open_envelope = if sign {
+            let signing_key = self.signing_key.as_ref().ok_or_else(|| {
+                TypedRequestError::InvalidRequest(
+                    "the request must be signed, but no signing key is configured".to_string(),
+                )
+            })?;
+            encoded_message.signed_open_envelope(signing_key)
+        } else {
+            encoded_message.open_envelope()
+        };
+        let sealed_envelope = open_envelope
+            .map(|open_envelope| open_envelope.seal(&self.key))
+            .map_err(|err| TypedRequestError::InvalidRequest(err.to_string()))?;
+        let mut bytes = Vec::new();
+        sealed_envelope
+            .encode(&mut bytes)
+            .map_err(|err| TypedRequestError::InvalidRequest(err.to_string()))?;
+        let mut msg = nng::Message::with_capacity(bytes.len())
+            .map_err(|err| TypedRequestError::InvalidRequest(err.to_string()))?;
+        msg.push_back(&bytes)
+            .map_err(|err| TypedRequestError::InvalidRequest(err.to_string()))?;
+        Ok(msg)
+    }
+}
+
+impl<Req, Rep> fmt::Debug for TypedClient<Req, Rep> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TypedClient(ReqRepId({}), address: {}, service_address: {}, encoding: {})",
+            self.client.id(),
+            self.address,
+            self.service_address,
+            self.encoding()
+        )
+    }
+}
+
+/// TypedClient request errors
+#[derive(Debug, Fail, Clone)]
+pub enum TypedRequestError {
+    /// The request failed to be encoded, signed, or sealed
+    #[fail(display = "Invalid request: {}", _0)]
+    InvalidRequest(String),
+    /// The request could not be sent, or the reply channel was disconnected
+    #[fail(display = "Channel error: {}", _0)]
+    Channel(#[cause] ChannelError),
+    /// The nng request failed
+    #[fail(display = "Request failed: {}", _0)]
+    Request(#[cause] RequestError),
+    /// The service replied with an error, i.e., either a [WireError](../server/struct.WireError.html)
+    /// or a [ServiceError](../server/struct.ServiceError.html), which is mapped to a WireError
+    #[fail(display = "Service error: {}", _0)]
+    Service(WireError),
+    /// The reply failed to be opened or decoded
+    #[fail(display = "Invalid reply: {}", _0)]
+    InvalidReply(String),
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use crate::reqrep::{
+        client::{self, DialerConfig},
+        server::{self, ListenerConfig, ServerHandle},
+    };
+    use futures::stream::StreamExt;
+    use oysterpack_core::message::{
+        Addresses, BincodeOptions, CompressionLevel, InstanceId, MessageBytes, MessageTypeId,
+        OpenEnvelope,
+    };
+    use oysterpack_trust::{
+        concurrent::{
+            execution::{global_executor, ExecutorBuilder, ExecutorId},
+            messaging::reqrep::ReqRepConfig,
+        },
+        metrics,
+    };
+    use oysterpack_uid::ULID;
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Add(u64, u64);
+
+    impl IsMessage for Add {
+        const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1879934736469244706319820902636257104);
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Sum(u64);
+
+    impl IsMessage for Sum {
+        const MESSAGE_TYPE_ID: MessageTypeId = MessageTypeId(1879934930448097947402776341185321762);
+    }
+
+    struct Adder;
+
+    impl TypedProcessor<Add, Sum> for Adder {
+        fn process(&mut self, req: Add) -> FutureReply<Sum> {
+            async move { Sum(req.0 + req.1) }.boxed()
+        }
+    }
+
+    /// Server and client keys, which are used to seal requests from the client to the server
+    struct Fixture {
+        server_pub_key: box_::PublicKey,
+        server_priv_key: box_::SecretKey,
+        server_address: Address,
+        client_priv_key: box_::SecretKey,
+        client_address: Address,
+        client_key: box_::PrecomputedKey,
+    }
+
+    impl Fixture {
+        fn new() -> Fixture {
+            let (server_pub_key, server_priv_key) = box_::gen_keypair();
+            let (client_pub_key, client_priv_key) = box_::gen_keypair();
+            Fixture {
+                server_address: server_pub_key.into(),
+                client_address: client_pub_key.into(),
+                client_key: box_::precompute(&server_pub_key, &client_priv_key),
+                server_pub_key,
+                server_priv_key,
+                client_priv_key,
+            }
+        }
+
+        /// returns a processor for the server address, which encodes replies using
+        /// `Encoding::Bincode(None, BincodeOptions::default())`
+        fn processor<P>(
+            &self,
+            reqrep_id: ReqRepId,
+            processor: P,
+        ) -> SealedEnvelopeProcessor<P, Add, Sum>
+        where
+            P: TypedProcessor<Add, Sum>,
+        {
+            SealedEnvelopeProcessor::new(
+                reqrep_id,
+                processor,
+                self.server_address,
+                self.server_priv_key.clone(),
+                Encoding::Bincode(None, BincodeOptions::default()),
+            )
+        }
+
+        /// returns a TypedClient that sends requests from the client to the server
+        fn typed_client(&self, client: Client) -> TypedClient<Add, Sum> {
+            TypedClient::new(
+                client,
+                self.client_address,
+                &self.client_priv_key,
+                self.server_address,
+                Encoding::Bincode(None, BincodeOptions::default()),
+            )
+        }
+
+        /// seals the request from the client to the server
+        fn seal(&self, metadata: Metadata, req: Add) -> nng::Message {
+            nng_message(
+                &Message::new(metadata, req)
+                    .encoded_message(self.client_address, self.server_address)
+                    .unwrap()
+                    .open_envelope()
+                    .unwrap()
+                    .seal(&self.client_key),
+            )
+        }
+
+        /// seals the request using the default Add metadata
+        fn seal_add(&self, req: Add) -> nng::Message {
+            self.seal(add_metadata(), req)
+        }
+
+        /// opens the message that was sealed using the client key
+        fn open<T>(&self, msg: &nng::Message) -> (Addresses, Message<T>)
+        where
+            T: fmt::Debug + Clone + Serialize + DeserializeOwned,
+        {
+            let bytes: &[u8] = msg;
+            SealedEnvelope::decode(bytes)
+                .unwrap()
+                .open(&self.client_key)
+                .unwrap()
+                .encoded_message()
+                .unwrap()
+                .decode::<T>()
+                .unwrap()
+        }
+    }
+
+    /// Add request metadata using `Encoding::Bincode(None, BincodeOptions::default())`
+    fn add_metadata() -> Metadata {
+        Metadata::new(
+            Add::MESSAGE_TYPE_ID.message_type(),
+            Encoding::Bincode(None, BincodeOptions::default()),
+            None,
+        )
+    }
+
+    fn nng_message(sealed_envelope: &SealedEnvelope) -> nng::Message {
+        let mut bytes = Vec::new();
+        sealed_envelope.encode(&mut bytes).unwrap();
+        let mut msg = nng::Message::with_capacity(bytes.len()).unwrap();
+        msg.push_back(&bytes).unwrap();
+        msg
+    }
+
+    fn timer_buckets() -> Vec<f64> {
+        metrics::timer_buckets(vec![Duration::from_millis(1), Duration::from_millis(10)]).unwrap()
+    }
+
+    /// starts the service, and serves it on a unique inproc url
+    fn spawn_service<P>(
+        reqrep_id: ReqRepId,
+        processor: SealedEnvelopeProcessor<P, Add, Sum>,
+    ) -> (url::Url, ServerHandle)
+    where
+        P: TypedProcessor<Add, Sum> + Send + 'static,
+    {
+        let service = ReqRepConfig::new(reqrep_id, timer_buckets())
+            .start_service(processor, global_executor())
+            .unwrap();
+        let url = url::Url::parse(&format!("inproc://{}", ULID::generate())).unwrap();
+        let server_handle =
+            server::spawn(None, ListenerConfig::new(url.clone()), service, global_executor())
+                .unwrap();
+        (url, server_handle)
+    }
+
+    /// registers an nng client that dials the service
+    fn register_client(reqrep_id: ReqRepId, url: &url::Url) -> Client {
+        client::register_client(
+            ReqRepConfig::new(reqrep_id, timer_buckets()),
+            None,
+            DialerConfig::new(url.clone()).set_pre_dial(true),
+            ExecutorBuilder::new(ExecutorId::generate())
+                .register()
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn typed_adder_service() {
+        configure_logging();
+
+        // GIVEN: a typed adder service running behind an nng server
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let (url, mut server_handle) =
+            spawn_service(reqrep_id, fixture.processor(reqrep_id, Adder));
+
+        // GIVEN: a raw nng client
+        let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        s.dial(url.as_str()).unwrap();
+
+        // WHEN: the client sends a sealed Add request
+        let metadata = add_metadata();
+        let request_instance_id = metadata.instance_id();
+        s.send(fixture.seal(metadata, Add(1, 2))).unwrap();
+
+        // THEN: the client receives a sealed Sum reply
+        let (addresses, reply) = fixture.open::<Sum>(&s.recv().unwrap());
+        assert_eq!(reply.data().0, 3);
+        assert_eq!(*addresses.sender(), fixture.server_address);
+        assert_eq!(*addresses.recipient(), fixture.client_address);
+        assert_eq!(reply.metadata().correlation_id(), Some(request_instance_id));
+
+        // WHEN: the client sends a message that is not a SealedEnvelope
+        s.send(nng::Message::new().unwrap()).unwrap();
+        // THEN: the client receives a ServiceError reply
+        let reply = s.recv().unwrap();
+        let service_error = ServiceError::decode(&reply).unwrap();
+        assert_eq!(service_error.reqrep_id(), reqrep_id);
+
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn request_context_timings() {
+        configure_logging();
+
+        // GIVEN: an adder service that sends the completed RequestContext to a sink
+        let fixture = Fixture::new();
+        let (sink, mut request_contexts) = mpsc::unbounded();
+        let mut processor = fixture
+            .processor(ReqRepId::generate(), Adder)
+            .set_request_context_sink(sink);
+
+        // WHEN: a request is processed
+        let metadata = add_metadata();
+        let request_instance_id = metadata.instance_id();
+        let mut executor = global_executor();
+        let _ = executor.run(processor.process(fixture.seal(metadata, Add(1, 2))));
+
+        // THEN: the RequestContext records non-zero decode and dispatch durations
+        let ctx = executor.run(request_contexts.next()).unwrap();
+        info!("{:?}", ctx);
+        assert_eq!(ctx.instance_id(), Some(request_instance_id));
+        assert!(ctx.timing(Stage::Decode).unwrap() > Duration::from_millis(0));
+        assert!(ctx.timing(Stage::Dispatch).unwrap() > Duration::from_millis(0));
+        assert!(ctx.timing(Stage::Encode).is_some());
+        // AND: the stages are recorded in processing order
+        let stages: Vec<Stage> = ctx.timings().iter().map(|(stage, _)| *stage).collect();
+        assert_eq!(stages, vec![Stage::Decode, Stage::Dispatch, Stage::Encode]);
+    }
+
+    #[test]
+    fn accounting() {
+        configure_logging();
+
+        // GIVEN: an adder service configured with accounting
+        const ADD_RATE: f64 = 10.0;
+        const BYTE_RATE: f64 = 0.5;
+        let fixture = Fixture::new();
+        let accounting = Accounting::default()
+            .set_message_type_rate(Add::MESSAGE_TYPE_ID.message_type(), ADD_RATE)
+            .set_byte_rate(BYTE_RATE)
+            .set_connection_time_rate(1.0);
+        let mut processor = fixture
+            .processor(ReqRepId::generate(), Adder)
+            .set_accounting(accounting.clone());
+
+        // WHEN: the client's first request is processed
+        let req = fixture.seal_add(Add(1, 2));
+        let request_size = req.len();
+        let _ = global_executor().run(processor.process(req));
+
+        // THEN: the recorded cost is the message type rate plus the byte cost, i.e., there is no
+        // connection time on the first request
+        let expected_cost = ADD_RATE + BYTE_RATE * request_size as f64;
+        assert_eq!(
+            processing_cost(&fixture.client_address, Add::MESSAGE_TYPE_ID.message_type()),
+            expected_cost
+        );
+
+        // THEN: connection time is charged per second
+        assert_eq!(
+            accounting.cost(Add::MESSAGE_TYPE_ID.message_type(), 10, Duration::from_millis(1500)),
+            ADD_RATE + BYTE_RATE * 10.0 + 1.5
+        );
+        // AND: message types without a configured rate are not charged a flat rate
+        assert_eq!(
+            accounting.cost(Sum::MESSAGE_TYPE_ID.message_type(), 0, Duration::from_millis(0)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn accounting_sender_cache_is_bounded() {
+        configure_logging();
+
+        // GIVEN: accounting that tracks at most 2 senders
+        let accounting = Accounting::default()
+            .set_connection_time_rate(1.0)
+            .set_sender_cache(NonZeroUsize::new(2).unwrap(), Duration::from_secs(60));
+        let senders: Vec<Address> = (0..3).map(|_| box_::gen_keypair().0.into()).collect();
+        let msg_type = Add::MESSAGE_TYPE_ID.message_type();
+
+        // WHEN: more senders are charged than the cache capacity
+        for sender in senders.iter() {
+            assert_eq!(accounting.charge(sender, msg_type, 0), 0.0);
+        }
+        // THEN: the tracking state is bounded
+        assert_eq!(accounting.last_charged.len(), 2);
+        // AND: the least recently charged sender was evicted, i.e., it is charged as a new sender
+        assert!(!accounting.last_charged.contains_key(&senders[0]));
+        assert!(accounting.last_charged.contains_key(&senders[2]));
+
+        // WHEN: a sender's tracking state is cleared
+        accounting.clear(&senders[2]);
+        // THEN: it is no longer tracked
+        assert!(!accounting.last_charged.contains_key(&senders[2]));
+    }
+
+    #[test]
+    fn session_id_binding() {
+        configure_logging();
+
+        // GIVEN: an adder service that validates message session ids
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let (url, mut server_handle) = spawn_service(
+            reqrep_id,
+            fixture
+                .processor(reqrep_id, Adder)
+                .set_validate_session_id(true),
+        );
+
+        // GIVEN: a connected client
+        let mut s = nng::Socket::new(nng::Protocol::Req0).unwrap();
+        s.dial(url.as_str()).unwrap();
+        while server_handle.connections().is_empty() {
+            std::thread::yield_now();
+        }
+        let connection_session_id = server_handle.connections()[0].session_id();
+
+        let sealed_request = |session_id: SessionId| {
+            fixture.seal(add_metadata().set_session_id(session_id), Add(1, 2))
+        };
+
+        // WHEN: the client sends a request with a spoofed session id
+        s.send(sealed_request(SessionId::generate())).unwrap();
+        // THEN: the request is rejected
+        let reply = s.recv().unwrap();
+        let service_error = ServiceError::decode(&reply).unwrap();
+        info!("{}", service_error);
+        assert_eq!(service_error.reqrep_id(), reqrep_id);
+
+        // WHEN: the client sends a request with the connection session id
+        s.send(sealed_request(connection_session_id)).unwrap();
+        // THEN: the request is accepted
+        let reply = s.recv().unwrap();
+        assert!(ServiceError::decode(&reply).is_none());
+        let (_, reply) = fixture.open::<Sum>(&reply);
+        assert_eq!(reply.data().0, 3);
+        // AND: the reply is stamped with the connection session id
+        assert_eq!(reply.metadata().session_id(), connection_session_id);
+
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn handshake_encoding_negotiation() {
+        configure_logging();
+
+        // GIVEN: an adder service that prefers Bincode, with CBOR as its second choice
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let (url, mut server_handle) = spawn_service(
+            reqrep_id,
+            fixture
+                .processor(reqrep_id, Adder)
+                .set_encodings(vec![
+                    Encoding::Bincode(None, BincodeOptions::default()),
+                    Encoding::CBOR(None),
+                ])
+                .set_validate_session_id(true),
+        );
+        // AND: a client that prefers CBOR
+        let (_, signing_key) = sign::gen_keypair();
+        let mut typed_client = fixture
+            .typed_client(register_client(reqrep_id, &url))
+            .set_signing_key(signing_key)
+            .set_encodings(vec![Encoding::CBOR(None), Encoding::JSON(None)]);
+        assert_eq!(
+            typed_client.encoding(),
+            Encoding::Bincode(None, BincodeOptions::default())
+        );
+        let mut executor = global_executor();
+
+        // WHEN: the client runs the handshake
+        let session_id = executor.run(typed_client.handshake()).unwrap();
+        // THEN: both converge on CBOR
+        assert_eq!(typed_client.encoding(), Encoding::CBOR(None));
+        // AND: the client is assigned the connection session
+        assert_eq!(typed_client.session_id(), Some(session_id));
+        assert_eq!(server_handle.connections()[0].session_id(), session_id);
+
+        // WHEN: the client sends a request
+        let reply = executor
+            .run(typed_client.send_recv(Add(1, 2), None))
+            .unwrap();
+        // THEN: the request is bound to the session
+        assert_eq!(reply.data().0, 3);
+        assert_eq!(reply.metadata().session_id(), session_id);
+        // AND: the service encodes the reply using the negotiated encoding
+        assert_eq!(reply.metadata().encoding(), Encoding::CBOR(None));
+
+        let _ = client::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn request_logger_sampling() {
+        configure_logging();
+
+        const REQUEST_COUNT: usize = 20;
+        let fixture = Fixture::new();
+        let mut executor = global_executor();
+
+        // GIVEN: an adder service that logs 1 in 1 requests
+        let (sink, mut records) = mpsc::unbounded();
+        let reqrep_id = ReqRepId::generate();
+        let mut processor = fixture
+            .processor(reqrep_id, Adder)
+            .set_validator(|msg: &Message<Add>| {
+                if msg.data().0 > 100 {
+                    Err(ValidationError::new(format!(
+                        "operand is out of range: {:?}",
+                        msg.data()
+                    )))
+                } else {
+                    Ok(())
+                }
+            })
+            .set_request_logger(RequestLogger::new(1).set_sink(sink));
+        // WHEN: requests are processed
+        for _ in 0..REQUEST_COUNT {
+            let _ = executor.run(processor.process(fixture.seal_add(Add(1, 2))));
+        }
+        // AND: a request that is decoded, but fails validation is processed
+        let _ = executor.run(processor.process(fixture.seal_add(Add(101, 2))));
+        // AND: a request that fails to be decoded is processed
+        let _ = executor.run(processor.process(nng::Message::new().unwrap()));
+        drop(processor);
+        // THEN: every request is logged
+        let records: Vec<RequestLogRecord> = executor.run(records.collect());
+        assert_eq!(records.len(), REQUEST_COUNT + 2);
+        for record in records.iter().take(REQUEST_COUNT) {
+            assert_eq!(record.reqrep_id(), reqrep_id);
+            assert!(record.instance_id().is_some());
+            assert_eq!(*record.sender().unwrap(), fixture.client_address);
+            assert_eq!(record.message_type(), Some(Add::MESSAGE_TYPE_ID.message_type()));
+            assert_eq!(*record.outcome(), RequestOutcome::Ok);
+        }
+        // AND: the rejected request is identified by its InstanceId
+        let rejected_record = &records[REQUEST_COUNT];
+        assert!(rejected_record.instance_id().is_some());
+        assert_eq!(*rejected_record.sender().unwrap(), fixture.client_address);
+        assert_eq!(
+            rejected_record.message_type(),
+            Some(Add::MESSAGE_TYPE_ID.message_type())
+        );
+        match rejected_record.outcome() {
+            RequestOutcome::Failed(_) => (),
+            outcome => panic!("expected failed outcome: {}", outcome),
+        }
+        // AND: the request that failed to be decoded has no InstanceId
+        let failed_record = records.last().unwrap();
+        assert!(failed_record.instance_id().is_none());
+        assert!(failed_record.sender().is_none());
+        match failed_record.outcome() {
+            RequestOutcome::Failed(_) => (),
+            outcome => panic!("expected failed outcome: {}", outcome),
+        }
+
+        // GIVEN: an adder service that logs 0 requests
+        let (sink, mut records) = mpsc::unbounded();
+        let mut processor = fixture
+            .processor(reqrep_id, Adder)
+            .set_request_logger(RequestLogger::new(0).set_sink(sink));
+        // WHEN: requests are processed
+        for _ in 0..REQUEST_COUNT {
+            let _ = executor.run(processor.process(fixture.seal_add(Add(1, 2))));
+        }
+        let _ = executor.run(processor.process(nng::Message::new().unwrap()));
+        drop(processor);
+        // THEN: no requests are logged
+        let records: Vec<RequestLogRecord> = executor.run(records.collect());
+        assert!(records.is_empty());
+
+        // THEN: sampling is deterministic per InstanceId, i.e., 1 in 4 samples the instance ids
+        // whose ULID is a multiple of 4
+        let instance_id = |ulid: u128| -> InstanceId {
+            bincode::deserialize(&bincode::serialize(&ULID::from(ulid)).unwrap()).unwrap()
+        };
+        let request_logger = RequestLogger::new(4);
+        for (ulid, sampled) in &[
+            (1_880_061_423_432_010_194_210_818_343_648_584_280_u128, true),
+            (1_880_065_676_157_912_164_987_123_786_193_187_487_u128, false),
+            (1_880_060_508_735_870_454_371_078_759_584_379_245_u128, false),
+            (1_880_061_574_159_344_502_120_753_863_639_322_236_u128, true),
+        ] {
+            assert_eq!(request_logger.is_sampled(instance_id(*ulid)), *sampled);
+        }
+    }
+
+    #[test]
+    fn stale_message() {
+        configure_logging();
+
+        // GIVEN: an adder service with a max message age of 1 minute and a clock skew tolerance of 1 sec
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let mut processor = fixture
+            .processor(reqrep_id, Adder)
+            .set_max_message_age(Duration::from_secs(60))
+            .set_clock_skew_tolerance(Duration::from_secs(1));
+        let mut executor = global_executor();
+
+        // WHEN: a request with a current timestamp is processed
+        let reply = executor.run(processor.process(fixture.seal_add(Add(1, 2))));
+        // THEN: the request is processed
+        assert!(ServiceError::decode(&reply).is_none());
+        assert_eq!(stale_msg_count(reqrep_id), 0);
+
+        // WHEN: a request with a timestamp that is 5 minutes old is processed
+        let metadata = backdate(add_metadata(), Duration::from_secs(5 * 60));
+        let reply = executor.run(processor.process(fixture.seal(metadata, Add(1, 2))));
+        // THEN: the request is dropped as stale
+        let service_error = ServiceError::decode(&reply).unwrap();
+        info!("{}", service_error);
+        assert_eq!(stale_msg_count(reqrep_id), 1);
+    }
+
+    #[test]
+    fn compression_policy() {
+        configure_logging();
+
+        let (add_msg_type, sum_msg_type) = (
+            Add::MESSAGE_TYPE_ID.message_type(),
+            Sum::MESSAGE_TYPE_ID.message_type(),
+        );
+        // GIVEN: a policy that compresses Add messages, and does not compress Sum messages
+        let compression_policy = CompressionPolicy::default()
+            .set_message_type_compression(
+                add_msg_type,
+                Some(Compression::Deflate(CompressionLevel::Fast)),
+            )
+            .set_message_type_compression(sum_msg_type, None);
+        let default_encoding =
+            Encoding::Bincode(Some(Compression::Snappy), BincodeOptions::default());
+        assert_eq!(
+            compression_policy.encoding(add_msg_type, default_encoding),
+            Encoding::Bincode(
+                Some(Compression::Deflate(CompressionLevel::Fast)),
+                BincodeOptions::default()
+            )
+        );
+        assert_eq!(
+            compression_policy.encoding(sum_msg_type, default_encoding),
+            Encoding::Bincode(None, BincodeOptions::default())
+        );
+        // AND: message types that are not configured use the default encoding
+        let unconfigured_msg_type = MessageTypeId(ULID::generate().into()).message_type();
+        assert!(compression_policy
+            .compression(unconfigured_msg_type)
+            .is_none());
+        assert_eq!(
+            compression_policy.encoding(unconfigured_msg_type, default_encoding),
+            default_encoding
+        );
+
+        // GIVEN: an adder service that applies the policy to its replies
+        let fixture = Fixture::new();
+        let mut processor = SealedEnvelopeProcessor::new(
+            ReqRepId::generate(),
+            Adder,
+            fixture.server_address,
+            fixture.server_priv_key.clone(),
+            default_encoding,
+        )
+        .set_compression_policy(compression_policy.clone());
+
+        // WHEN: the client encodes the Add request according to the policy
+        let metadata = Metadata::new(
+            add_msg_type,
+            compression_policy.encoding(
+                add_msg_type,
+                Encoding::Bincode(None, BincodeOptions::default()),
+            ),
+            None,
+        );
+        let req = fixture.seal(metadata, Add(1, 2));
+        // THEN: the request body is compressed
+        let (_, request) = fixture.open::<Add>(&req);
+        assert_eq!(
+            request.metadata().encoding().compression(),
+            Some(Compression::Deflate(CompressionLevel::Fast))
+        );
+
+        // WHEN: the request is processed
+        let reply = global_executor().run(processor.process(req));
+        assert!(ServiceError::decode(&reply).is_none());
+        // THEN: the Sum reply body is not compressed, even though the service encoding compresses
+        let (_, reply) = fixture.open::<Sum>(&reply);
+        assert_eq!(
+            reply.metadata().encoding(),
+            Encoding::Bincode(None, BincodeOptions::default())
+        );
+        // AND: both messages round trip
+        assert_eq!(request.data().0, 1);
+        assert_eq!(request.data().1, 2);
+        assert_eq!(reply.data().0, 3);
+    }
+
+    #[test]
+    fn signing_policy() {
+        configure_logging();
+
+        let add_msg_type = Add::MESSAGE_TYPE_ID.message_type();
+        let signing_policy = SigningPolicy::default()
+            .set_default(Signing::IfDeadlineSet)
+            .set_message_type_signing(add_msg_type, Signing::Always);
+        assert_eq!(signing_policy.signing(add_msg_type), Signing::Always);
+        assert_eq!(
+            signing_policy.signing(Sum::MESSAGE_TYPE_ID.message_type()),
+            Signing::IfDeadlineSet
+        );
+
+        // GIVEN: an adder service that requires Add requests to be signed
+        let fixture = Fixture::new();
+        let (signing_pub_key, signing_priv_key) = sign::gen_keypair();
+        let reqrep_id = ReqRepId::generate();
+        let (url, mut server_handle) = spawn_service(
+            reqrep_id,
+            fixture
+                .processor(reqrep_id, Adder)
+                .set_signing_policy(signing_policy.clone())
+                .add_signing_key(fixture.client_address, signing_pub_key),
+        );
+        let nng_client = register_client(reqrep_id, &url);
+        let typed_client = || fixture.typed_client(nng_client.clone());
+        let mut executor = global_executor();
+
+        // WHEN: the client is configured with the same signing policy
+        let mut signing_client = typed_client()
+            .set_signing_policy(signing_policy.clone())
+            .set_signing_key(signing_priv_key);
+        let reply = executor.run(async move { await!(signing_client.send_recv(Add(1, 2), None)) });
+        // THEN: the signed request is accepted
+        assert_eq!(reply.unwrap().data().0, 3);
+
+        // WHEN: the client does not sign Add requests
+        let mut unsigned_client = typed_client();
+        let reply = executor.run(async move { await!(unsigned_client.send_recv(Add(1, 2), None)) });
+        // THEN: the unsigned request is rejected
+        match reply {
+            Err(TypedRequestError::Service(service_error)) => {
+                info!("{}", service_error);
+                assert_eq!(service_error.reqrep_id(), Some(reqrep_id));
+            }
+            other => panic!("expected ServiceError, but was: {:?}", other),
+        }
+
+        // WHEN: the client signs the request using a key that the service does not know
+        let mut spoofing_client = typed_client()
+            .set_signing_policy(signing_policy.clone())
+            .set_signing_key(sign::gen_keypair().1);
+        let reply = executor.run(async move { await!(spoofing_client.send_recv(Add(1, 2), None)) });
+        // THEN: the request is rejected
+        match reply {
+            Err(TypedRequestError::Service(_)) => (),
+            other => panic!("expected ServiceError, but was: {:?}", other),
+        }
+
+        // WHEN: the request must be signed, but the client has no signing key
+        let mut client_without_key = typed_client().set_signing_policy(signing_policy);
+        let reply =
+            executor.run(async move { await!(client_without_key.send_recv(Add(1, 2), None)) });
+        // THEN: the request is not sent
+        match reply {
+            Err(TypedRequestError::InvalidRequest(_)) => (),
+            other => panic!("expected InvalidRequest, but was: {:?}", other),
+        }
+
+        let _ = client::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn validator() {
+        configure_logging();
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        /// counts the number of requests that were processed
+        struct CountingAdder(Arc<AtomicUsize>);
+
+        impl TypedProcessor<Add, Sum> for CountingAdder {
+            fn process(&mut self, req: Add) -> FutureReply<Sum> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                async move { Sum(req.0 + req.1) }.boxed()
+            }
+        }
+
+        // GIVEN: an adder service that only accepts operands that are <= 100
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let process_count = Arc::new(AtomicUsize::new(0));
+        let (url, mut server_handle) = spawn_service(
+            reqrep_id,
+            fixture
+                .processor(reqrep_id, CountingAdder(process_count.clone()))
+                .set_validator(|msg: &Message<Add>| {
+                    let Add(a, b) = msg.data();
+                    if *a > 100 || *b > 100 {
+                        Err(ValidationError::new(format!(
+                            "operand is out of range: {:?}",
+                            msg.data()
+                        )))
+                    } else {
+                        Ok(())
+                    }
+                }),
+        );
+        let mut typed_client = fixture.typed_client(register_client(reqrep_id, &url));
+        let mut executor = global_executor();
+
+        // WHEN: a valid request is sent
+        let reply = executor.run(typed_client.send_recv(Add(1, 2), None));
+        // THEN: the request is processed
+        assert_eq!(reply.unwrap().data().0, 3);
+        assert_eq!(process_count.load(Ordering::SeqCst), 1);
+
+        // WHEN: a request with an operand that is out of range is sent
+        let reply = executor.run(typed_client.send_recv(Add(1, 101), None));
+        // THEN: the client receives the validation error
+        match reply {
+            Err(TypedRequestError::Service(service_error)) => {
+                info!("{}", service_error);
+                assert_eq!(service_error.reqrep_id(), Some(reqrep_id));
+                assert!(service_error
+                    .message()
+                    .contains("Validation failed: operand is out of range"));
+            }
+            other => panic!("expected ServiceError, but was: {:?}", other),
+        }
+        // AND: the TypedProcessor is not invoked
+        assert_eq!(process_count.load(Ordering::SeqCst), 1);
+
+        let _ = client::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn request_deadline() {
+        configure_logging();
+        use futures::channel::oneshot;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        // adder that never completes the work on its own, i.e., it can only be abandoned
+        struct StuckAdder {
+            started_count: Arc<AtomicUsize>,
+            work_signals: Vec<oneshot::Sender<()>>,
+        }
+
+        impl TypedProcessor<Add, Sum> for StuckAdder {
+            fn process(&mut self, req: Add) -> FutureReply<Sum> {
+                self.started_count.fetch_add(1, Ordering::SeqCst);
+                let (work_tx, work_rx) = oneshot::channel();
+                self.work_signals.push(work_tx);
+                async move {
+                    let _ = await!(work_rx);
+                    Sum(req.0 + req.1)
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a typed adder service that gets stuck processing requests
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let started_count = Arc::new(AtomicUsize::new(0));
+        let stuck_adder = StuckAdder {
+            started_count: started_count.clone(),
+            work_signals: Vec::new(),
+        };
+        let (url, mut server_handle) =
+            spawn_service(reqrep_id, fixture.processor(reqrep_id, stuck_adder));
+        let mut typed_client = fixture.typed_client(register_client(reqrep_id, &url));
+        let mut executor = global_executor();
+
+        // WHEN: the client sends a request with a short deadline
+        let start = Instant::now();
+        let deadline = Deadline::ProcessingTimeoutMillis(50);
+        let reply = executor.run(typed_client.send_recv(Add(1, 2), Some(deadline)));
+        // THEN: the service abandons the work when the deadline expires, and replies with a ServiceError
+        match reply {
+            Err(TypedRequestError::Service(service_error)) => {
+                info!("{}", service_error);
+                assert_eq!(service_error.reqrep_id(), Some(reqrep_id));
+            }
+            other => panic!("expected ServiceError, but was: {:?}", other),
+        }
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        // AND: the work was started, but abandoned
+        assert_eq!(started_count.load(Ordering::SeqCst), 1);
+        assert_eq!(abandoned_request_count(reqrep_id), 1);
+
+        // WHEN: the request deadline has already expired when the request arrives
+        let deadline = Deadline::MessageTimeoutMillis(0);
+        let reply = executor.run(typed_client.send_recv(Add(1, 2), Some(deadline)));
+        // THEN: the request is rejected
+        match reply {
+            Err(TypedRequestError::Service(_)) => (),
+            other => panic!("expected ServiceError, but was: {:?}", other),
+        }
+        // AND: the work is skipped
+        assert_eq!(started_count.load(Ordering::SeqCst), 1);
+        assert_eq!(abandoned_request_count(reqrep_id), 1);
+
+        let _ = client::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    #[test]
+    fn decode_failures() {
+        configure_logging();
+
+        // GIVEN: an adder service that reports decode failures via a callback
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let (failure_tx, failure_rx) = std::sync::mpsc::channel();
+        let mut processor = fixture
+            .processor(reqrep_id, Adder)
+            .set_decode_failure_callback(move |failure, err: &str| {
+                info!("decode failure: {} : {}", failure, err);
+                let _ = failure_tx.send(failure);
+            });
+
+        let seal = |msg_bytes: &[u8], key: &box_::PrecomputedKey| {
+            nng_message(
+                &OpenEnvelope::new(fixture.client_address, fixture.server_address, msg_bytes)
+                    .seal(key),
+            )
+        };
+        // the message data is taken as is, i.e., it is not encoded using the metadata Encoding
+        let encoded_message = |encoding: Encoding, data: Vec<u8>| {
+            let metadata = Metadata::new(Add::MESSAGE_TYPE_ID.message_type(), encoding, None);
+            Encoding::Bincode(None, BincodeOptions::default())
+                .encode(Message::new(metadata, MessageBytes::from(data)))
+                .unwrap()
+        };
+
+        let mut executor = global_executor();
+        let mut check_decode_failure = |req: nng::Message, failure: DecodeFailure| {
+            // THEN: the request is rejected with a ServiceError reply
+            let reply = executor.run(processor.process(req));
+            assert!(ServiceError::decode(&reply).is_some());
+            // AND: the labeled decode failure counter is incremented
+            assert_eq!(decode_failure_count(reqrep_id, failure), 1);
+            // AND: the callback is invoked
+            assert_eq!(failure_rx.try_recv().unwrap(), failure);
+        };
+
+        // WHEN: the request is not a SealedEnvelope
+        check_decode_failure(nng::Message::new().unwrap(), DecodeFailure::Framing);
+        // WHEN: the request is sealed using the wrong key
+        let wrong_key = box_::precompute(&fixture.server_pub_key, &box_::gen_keypair().1);
+        let msg_bytes = encoded_message(
+            Encoding::Bincode(None, BincodeOptions::default()),
+            vec![1, 2, 3],
+        );
+        check_decode_failure(seal(&msg_bytes, &wrong_key), DecodeFailure::Auth);
+        // WHEN: the message data is not valid compressed data
+        let msg_bytes = encoded_message(
+            Encoding::Bincode(
+                Some(Compression::Deflate(CompressionLevel::Fast)),
+                BincodeOptions::default(),
+            ),
+            vec![0xFF; 16],
+        );
+        check_decode_failure(
+            seal(&msg_bytes, &fixture.client_key),
+            DecodeFailure::Decompression,
+        );
+        // WHEN: the message data is not a valid request
+        let msg_bytes = encoded_message(
+            Encoding::Bincode(None, BincodeOptions::default()),
+            vec![1, 2, 3],
+        );
+        check_decode_failure(
+            seal(&msg_bytes, &fixture.client_key),
+            DecodeFailure::Deserialization,
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn typed_tracing_spans_nest_across_nng_hop() {
+        configure_logging();
+        use parking_lot::Mutex;
+        use std::sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        };
+        use tracing::{field, span, Event, Subscriber};
+
+        #[derive(Debug, Clone)]
+        struct SpanRecord {
+            id: u64,
+            name: &'static str,
+            parent: Option<u64>,
+            fields: HashMap<&'static str, String>,
+        }
+
+        impl field::Visit for SpanRecord {
+            fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+                self.fields.insert(field.name(), format!("{:?}", value));
+            }
+        }
+
+        /// records the spans that are created
+        struct SpanRecorder {
+            next_id: AtomicU64,
+            spans: Arc<Mutex<Vec<SpanRecord>>>,
+        }
+
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _: &tracing::Metadata) -> bool {
+                true
+            }
+
+            fn new_span(&self, attrs: &span::Attributes) -> span::Id {
+                let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+                let mut span = SpanRecord {
+                    id,
+                    name: attrs.metadata().name(),
+                    parent: attrs.parent().map(span::Id::into_u64),
+                    fields: HashMap::new(),
+                };
+                attrs.record(&mut span);
+                self.spans.lock().push(span);
+                span::Id::from_u64(id)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, _: &Event) {}
+
+            fn enter(&self, _: &span::Id) {}
+
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        // GIVEN: a global subscriber, because requests are processed on the executor threads
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::set_global_default(SpanRecorder {
+            next_id: AtomicU64::new(1),
+            spans: spans.clone(),
+        })
+        .unwrap();
+
+        // GIVEN: a typed adder service running behind an nng server
+        let fixture = Fixture::new();
+        let reqrep_id = ReqRepId::generate();
+        let (url, mut server_handle) =
+            spawn_service(reqrep_id, fixture.processor(reqrep_id, Adder));
+        // GIVEN: a TypedClient that is connected to the service
+        let mut typed_client = fixture.typed_client(register_client(reqrep_id, &url));
+
+        // WHEN: a request is sent
+        let mut executor = global_executor();
+        let reply = executor
+            .run(typed_client.send_recv(Add(1, 2), None))
+            .unwrap();
+        assert_eq!(reply.data().0, 3);
+        let msg_id = reply.metadata().correlation_id().unwrap().to_string();
+
+        // THEN: the client and server spans carry the request message id
+        let spans = spans.lock().clone();
+        let span = |name: &str| {
+            spans
+                .iter()
+                .find(|span| span.name == name && span.fields.get("msg_id") == Some(&msg_id))
+                .cloned()
+                .unwrap_or_else(|| panic!("{} span was not found", name))
+        };
+        let send_span = span("typed_send");
+        let process_span = span("typed_process");
+        assert_eq!(send_span.fields.get("reqrep_id"), Some(&reqrep_id.to_string()));
+        assert_eq!(process_span.fields.get("reqrep_id"), Some(&reqrep_id.to_string()));
+        // AND: the server span is nested within the client span, i.e., the span id was propagated
+        //      via the request Metadata
+        assert_eq!(process_span.parent, Some(send_span.id));
+
+        let _ = client::unregister_client(reqrep_id);
+        server_handle.stop_async().unwrap();
+        server_handle.await_shutdown();
+    }
+
+    /// The message timestamp is derived from the InstanceId ULID, which cannot be set directly. Thus,
+    /// the InstanceId is swapped out in the bincode encoded metadata with a backdated ULID.
+    fn backdate(metadata: Metadata, age: Duration) -> Metadata {
+        let encoding = Encoding::Bincode(None, BincodeOptions::default());
+        let mut bytes = encoding.encode(&metadata).unwrap();
+        let instance_id: u128 = metadata.instance_id().ulid().into();
+        // the ULID timestamp is stored in the most significant 48 bits, with millisecond granularity
+        let backdated_instance_id = instance_id - ((age.as_millis() as u128) << 80);
+        let instance_id = instance_id.to_le_bytes();
+        let offset = bytes
+            .windows(instance_id.len())
+            .position(|window| window == instance_id)
+            .unwrap();
+        bytes[offset..offset + instance_id.len()]
+            .copy_from_slice(&backdated_instance_id.to_le_bytes());
+        let backdated: Metadata = encoding.decode(&bytes).unwrap();
+        assert!(backdated.timestamp() < metadata.timestamp());
+        backdated
+    }
+}
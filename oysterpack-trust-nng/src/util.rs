@@ -0,0 +1,374 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Common utilities that are shared across the crate.
+//!
+//! ## BoundedTimedCache
+//! [BoundedTimedCache](struct.BoundedTimedCache.html) is a thread-safe map that is bounded in both
+//! size and time, which is the building block for features that need to remember recently seen
+//! keys, e.g., ULID or content hash keyed replay guards, deduplication, and key caches.
+//! - when the cache is at capacity, the least recently used entry is evicted
+//! - entries expire once their time to live has elapsed since they were inserted
+//! - expired entries are removed lazily, i.e., when they are accessed, or via [purge_expired()](struct.BoundedTimedCache.html#method.purge_expired)
+
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Thread-safe map with LRU eviction and TTL expiry
+///
+/// BoundedTimedCache clones share the same entries.
+pub struct BoundedTimedCache<K, V> {
+    capacity: usize,
+    ttl: Duration,
+    inner: Arc<Mutex<Entries<K, V>>>,
+}
+
+struct Entries<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    // access tick -> key, i.e., the first entry is the least recently used
+    lru: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+struct Entry<V> {
+    value: V,
+    inserted_on: Instant,
+    tick: u64,
+}
+
+impl<K, V> Entries<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn touch(&mut self, key: &K) {
+        let tick = self.next_tick();
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.lru.remove(&entry.tick);
+            entry.tick = tick;
+            self.lru.insert(tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Option<Entry<V>> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.tick);
+        Some(entry)
+    }
+
+    fn evict_lru(&mut self) {
+        let tick = match self.lru.keys().next() {
+            Some(tick) => *tick,
+            None => return,
+        };
+        if let Some(key) = self.lru.remove(&tick) {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+impl<K, V> BoundedTimedCache<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    /// constructor
+    /// - capacity is the max number of entries
+    /// - ttl is how long an entry lives after it is inserted
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            capacity: capacity.get(),
+            ttl,
+            inner: Arc::new(Mutex::new(Entries {
+                entries: HashMap::with_capacity(capacity.get()),
+                lru: BTreeMap::new(),
+                tick: 0,
+            })),
+        }
+    }
+
+    /// Max number of entries
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// How long an entry lives after it is inserted
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Number of entries, which may include expired entries that have not yet been purged
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    /// Returns true if the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().entries.is_empty()
+    }
+
+    /// Returns the value, and marks the entry as the most recently used
+    /// - returns None if the entry does not exist or has expired
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.inner.lock();
+        let expired = match inner.entries.get(key) {
+            Some(entry) => self.is_expired(entry),
+            None => return None,
+        };
+        if expired {
+            inner.remove(key);
+            return None;
+        }
+        inner.touch(key);
+        inner.entries.get(key).map(|entry| entry.value.clone())
+    }
+
+    /// Returns true if the cache contains an unexpired entry for the key
+    /// - the entry is not marked as used
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner
+            .lock()
+            .entries
+            .get(key)
+            .map(|entry| !self.is_expired(entry))
+            .unwrap_or(false)
+    }
+
+    /// Inserts the entry, and returns the previous unexpired value
+    /// - if the cache is at capacity, then the least recently used entry is evicted
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut inner = self.inner.lock();
+        let prev = inner
+            .remove(&key)
+            .filter(|entry| !self.is_expired(entry))
+            .map(|entry| entry.value);
+        while inner.entries.len() >= self.capacity {
+            inner.evict_lru();
+        }
+        let tick = inner.next_tick();
+        inner.lru.insert(tick, key.clone());
+        inner.entries.insert(
+            key,
+            Entry {
+                value,
+                inserted_on: Instant::now(),
+                tick,
+            },
+        );
+        prev
+    }
+
+    /// Returns the cached value, or else inserts the value returned by `f`
+    /// - the cache is not locked while `f` is invoked, thus concurrent callers may each invoke `f`
+    ///   for the same key, in which case the last insert wins
+    pub fn get_or_insert_with<F>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = f();
+        self.insert(key, value.clone());
+        value
+    }
+
+    /// Removes the entry, and returns its value if it was not expired
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.inner
+            .lock()
+            .remove(key)
+            .filter(|entry| !self.is_expired(entry))
+            .map(|entry| entry.value)
+    }
+
+    /// Removes all expired entries, and returns the number of entries that were removed
+    pub fn purge_expired(&self) -> usize {
+        let mut inner = self.inner.lock();
+        let expired: Vec<K> = inner
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.is_expired(entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired.iter() {
+            inner.remove(key);
+        }
+        expired.len()
+    }
+
+    /// Removes all entries
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.entries.clear();
+        inner.lru.clear();
+    }
+
+    fn is_expired(&self, entry: &Entry<V>) -> bool {
+        entry.inserted_on.elapsed() >= self.ttl
+    }
+}
+
+impl<K, V> Clone for BoundedTimedCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            ttl: self.ttl,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for BoundedTimedCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BoundedTimedCache(capacity: {}, ttl: {:?}, len: {})",
+            self.capacity,
+            self.ttl,
+            self.inner.lock().entries.len()
+        )
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configure_logging;
+    use oysterpack_uid::ULID;
+    use std::thread;
+
+    #[test]
+    fn capacity_eviction() {
+        configure_logging();
+
+        // GIVEN: a cache with a capacity of 2
+        let cache =
+            BoundedTimedCache::new(NonZeroUsize::new(2).unwrap(), Duration::from_secs(60));
+        let (a, b, c) = (ULID::generate(), ULID::generate(), ULID::generate());
+        assert!(cache.insert(a, 1).is_none());
+        assert!(cache.insert(b, 2).is_none());
+        assert_eq!(cache.len(), 2);
+
+        // WHEN: `a` is used, and then `c` is inserted
+        assert_eq!(cache.get(&a), Some(1));
+        assert!(cache.insert(c, 3).is_none());
+        // THEN: the least recently used entry, i.e., `b`, is evicted
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains_key(&b));
+        assert_eq!(cache.get(&a), Some(1));
+        assert_eq!(cache.get(&c), Some(3));
+
+        // WHEN: an existing entry is replaced
+        // THEN: the previous value is returned, and nothing is evicted
+        assert_eq!(cache.insert(a, 10), Some(1));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&a), Some(10));
+        assert_eq!(cache.get(&c), Some(3));
+
+        // WHEN: entries are removed
+        assert_eq!(cache.remove(&a), Some(10));
+        assert!(cache.remove(&a).is_none());
+        cache.clear();
+        // THEN: the cache is empty
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn ttl_expiry() {
+        configure_logging();
+
+        // GIVEN: a cache with a TTL of 50 ms
+        let cache =
+            BoundedTimedCache::new(NonZeroUsize::new(10).unwrap(), Duration::from_millis(50));
+        let (a, b) = (ULID::generate(), ULID::generate());
+        cache.insert(a, "a");
+        cache.insert(b, "b");
+        assert_eq!(cache.get(&a), Some("a"));
+
+        // WHEN: the TTL elapses
+        thread::sleep(Duration::from_millis(100));
+        // THEN: the entries are expired, even though `a` was recently used
+        assert!(cache.get(&a).is_none());
+        assert!(!cache.contains_key(&b));
+        // AND: expired entries are removed lazily
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.purge_expired(), 1);
+        assert!(cache.is_empty());
+
+        // WHEN: an expired entry is replaced
+        cache.insert(a, "a");
+        thread::sleep(Duration::from_millis(100));
+        // THEN: the expired value is not returned
+        assert!(cache.insert(a, "A").is_none());
+        // AND: get_or_insert_with() only invokes the function for missing entries
+        assert_eq!(cache.get_or_insert_with(a, || "X"), "A");
+        assert_eq!(cache.get_or_insert_with(b, || "B"), "B");
+    }
+
+    #[test]
+    fn concurrent_access() {
+        configure_logging();
+        const THREAD_COUNT: usize = 8;
+        const KEY_COUNT: u128 = 1000;
+
+        // GIVEN: a cache that is shared across threads
+        let cache =
+            BoundedTimedCache::new(NonZeroUsize::new(100).unwrap(), Duration::from_secs(60));
+
+        // WHEN: the threads concurrently insert, get, and remove entries for overlapping keys
+        let handles: Vec<_> = (0..THREAD_COUNT)
+            .map(|i| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for key in 0..KEY_COUNT {
+                        cache.insert(key, i);
+                        if let Some(value) = cache.get(&key) {
+                            assert!(value < THREAD_COUNT);
+                        }
+                        if key % 10 == 0 {
+                            cache.remove(&key);
+                        }
+                        assert!(cache.len() <= cache.capacity());
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // THEN: the cache is bounded by its capacity
+        assert!(cache.len() <= cache.capacity());
+        // AND: the most recently inserted key was retained
+        assert!(cache.contains_key(&(KEY_COUNT - 1)));
+        let inner = cache.inner.lock();
+        assert_eq!(inner.entries.len(), inner.lru.len());
+    }
+}
@@ -18,3 +18,4 @@
 
 pub mod execution;
 pub mod messaging;
+pub mod timer;
@@ -28,6 +28,8 @@
 //! ## Executor Features
 //! - *[01D3W2RTE80P64E1W1TD61KGBN]* A [global Executor](global_executor) will be automatically provided by the Executor registry
 //! - *[01D3YVY445KA4YF5KYMHHQK2TP]* Executors are configured to catch unwinding panics for spawned futures
+//! - *[01D8A6W7JP4DKJSFXRGW56PPS3]* Executor liveness can be probed via [Executor::is_live()](struct.Executor.html#method.is_live),
+//!   i.e., to detect a thread pool that is wedged, e.g., all of its threads are blocked
 //!
 //! ## Metrics Features
 //! - *[01D3W3G8A7H32MVG3WYBER6J13]* Spawned tasks are tracked via metrics
@@ -65,7 +67,7 @@ use failure::Fail;
 use futures::{
     executor::{ThreadPool, ThreadPoolBuilder},
     future::{Future, FutureExt, FutureObj},
-    task::{Spawn, SpawnError},
+    task::{Spawn, SpawnError, SpawnExt},
 };
 use hashbrown::HashMap;
 use lazy_static::lazy_static;
@@ -74,7 +76,13 @@ use oysterpack_uid::macros::ulid;
 use parking_lot::RwLock;
 use prometheus::core::Collector;
 use serde::{Deserialize, Serialize};
-use std::{fmt, io, iter::ExactSizeIterator, num::NonZeroUsize};
+use std::{
+    fmt, io,
+    iter::ExactSizeIterator,
+    num::NonZeroUsize,
+    sync::mpsc,
+    time::Duration,
+};
 
 pub mod metrics;
 
@@ -304,6 +312,23 @@ impl Executor {
         self.threadpool.run(f)
     }
 
+    /// Returns true if the thread pool is making progress, i.e., a trivial task that is spawned on
+    /// the thread pool completes within the specified timeout.
+    /// - the calling thread is blocked for up to the timeout
+    /// - the probe task is counted as a spawned task
+    /// - if the probe times out, then the probe task remains queued and will run once a thread frees up
+    pub fn is_live(&self, timeout: Duration) -> bool {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let probe = async move {
+            let _ = tx.send(());
+        };
+        if let Err(err) = self.clone().spawn(probe) {
+            warn!("Executor({}) liveness probe could not be spawned: {}", self.id, err);
+            return false;
+        }
+        rx.recv_timeout(timeout).is_ok()
+    }
+
     /// returns the number of spawned tasks
     /// - tasks that are run, i.e., via [Executor::run()](struct.Executor.html#method.run), are not counted
     pub fn task_spawned_count(&self) -> u64 {
@@ -749,6 +774,50 @@ mod tests {
         assert_eq!(config.pool_size().unwrap(), 64);
     }
 
+    #[test]
+    fn executor_is_live() {
+        configure_logging();
+        const POOL_SIZE: usize = 2;
+        let mut executor = ExecutorBuilder::new(ExecutorId::generate())
+            .set_pool_size(NonZeroUsize::new(POOL_SIZE).unwrap())
+            .register()
+            .unwrap();
+
+        // GIVEN: a healthy executor
+        // THEN: it reports live
+        assert!(executor.is_live(Duration::from_secs(5)));
+
+        // GIVEN: all of the executor threads are blocked
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let release_rx = std::sync::Arc::new(parking_lot::Mutex::new(release_rx));
+        let (blocked_tx, blocked_rx) = std::sync::mpsc::channel();
+        for _ in 0..POOL_SIZE {
+            let release_rx = release_rx.clone();
+            let blocked_tx = blocked_tx.clone();
+            executor
+                .spawn(
+                    async move {
+                        blocked_tx.send(()).unwrap();
+                        // blocks the executor thread until the sender is dropped
+                        let _ = release_rx.lock().recv();
+                    },
+                )
+                .unwrap();
+        }
+        for _ in 0..POOL_SIZE {
+            blocked_rx.recv().unwrap();
+        }
+        // THEN: the executor reports not live within the timeout
+        let start = std::time::Instant::now();
+        assert!(!executor.is_live(Duration::from_millis(100)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // WHEN: the threads are released
+        drop(release_tx);
+        // THEN: the executor reports live again
+        assert!(executor.is_live(Duration::from_secs(5)));
+    }
+
     // the panic is bubbled up to the current thread when awaiting on a task that panics
     #[test]
     #[should_panic]
@@ -28,6 +28,12 @@ pub enum ChannelError {
     /// Receiver channel is disconnected
     #[fail(display = "Receiver channel is disconnected")]
     ReceiverDisconnected,
+    /// The request deadline had already expired when the request was sent
+    #[fail(display = "Request deadline has expired")]
+    DeadlineExpired,
+    /// The channel is full, i.e., the request could not be sent without waiting
+    #[fail(display = "Channel is full")]
+    Full,
 }
 
 impl From<channel::mpsc::SendError> for ChannelError {
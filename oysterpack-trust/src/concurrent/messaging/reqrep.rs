@@ -39,6 +39,10 @@
 //! - *[01D4RV5JQPQHXQNNJR8740J39J]* Sending request is coupled with receiving reply
 //!   - [ReqRep::send_recv()](struct.ReqRep.html#method.send_recv)
 //! - *[01D4RW7WRVBBGTBZEQCXMFN51V]* The ReqRep client can be shared by cloning it
+//! - Requests can be sent with a deadline via [ReqRep::send_with_deadline()](struct.ReqRep.html#method.send_with_deadline)
+//!   - if the deadline has already expired, then the request fails immediately with `ChannelError::DeadlineExpired`
+//! - [ReqRep::try_send()](struct.ReqRep.html#method.try_send) sends the request without waiting, i.e.,
+//!   it fails immediately with `ChannelError::Full` when the backend service is at capacity
 //!
 //! ## Service Features
 //! - *[01D4Z9P9VVHP7NC4MWV6JQ5XBM]* Backend service processing is executed async
@@ -51,6 +55,18 @@
 //! - *[01D585SEWBEKBBR0ZY3C5GR7A6]* Processor is notified via [Processor::panicked()](trait.Processor.html#method.panicked) if a panic occurred while processing the request.
 //!   - The default implementation simply cascades the panic, which terminates the ReqRep service
 //! - *[01D4RWGKRYAJCQ4Q5SD3Z6WG6P]* When all ReqRep client references fall out of scope, then the backend service will automatically shutdown
+//! - Requests sent with a deadline are dispatched via [Processor::process_with_deadline()](trait.Processor.html#method.process_with_deadline)
+//!   - the [DeadlineSignal](struct.DeadlineSignal.html) fires when the deadline expires, which the Processor can race
+//!     against in order to stop wasting resources on work that is no longer needed
+//! - Messages that cannot be delivered can be routed to a [DeadLetterSink](type.DeadLetterSink.html) for auditing and reprocessing
+//!   - see [ReqRepConfig::start_service_with_dead_letter_sink()](struct.ReqRepConfig.html#method.start_service_with_dead_letter_sink)
+//!   - replies whose ReplyReceiver was dropped
+//!   - requests whose deadline expired, either before they were sent or before the backend service
+//!     started processing them
+//!     - requests that expire while queued are not processed, i.e., the client's ReplyReceiver
+//!       fails with `ChannelError::ReceiverDisconnected`
+//!   - requests that were rejected by [ReqRep::try_send()](struct.ReqRep.html#method.try_send)
+//!     because the backend service was at capacity
 //!
 //! ## Config Features
 //! - *[01D4RVW8XQCSZKNQEBGWKG57S5]* Each request / reply service is assigned a [ReqRepId](struct.ReqRepId.html)
@@ -62,12 +78,36 @@
 //! - *[01D4V1PZ43Z5P7XGED38V6DXHA]* [TimerBuckets](../../../metrics/struct.TimerBuckets.html) are configurable per ReqRep
 //!   - TimerBuckets are used to configure a histogram metric used to time message processing in the backend service.
 //!   - TimerBuckets are not a one size fits all, and need to be tailored to the performance requirements for the backend Processor.
+//!   - default TimerBuckets can be registered per ReqRepId via [ReqRepConfig::register_default_buckets()](struct.ReqRepConfig.html#method.register_default_buckets).
+//!     They are used when the ReqRepConfig does not specify explicit TimerBuckets.
+//!     - if no default TimerBuckets are registered for the ReqRepId, then the global default TimerBuckets are used
+//!
+//! ## Saturation
+//! [ReqRep::saturation()](struct.ReqRep.html#method.saturation) reports how saturated the backend
+//! service is as a ratio between 0.0 and 1.0, which can be used to drive autoscaling. It combines the
+//! request backlog, i.e., requests that are queued or being processed relative to the channel capacity,
+//! with the service's recent utilization, i.e., the fraction of recent time spent processing requests.
+//!
+//! ## Tracing
+//! If the `tracing` feature is enabled, then requests are instrumented using [tracing](https://crates.io/crates/tracing) spans:
+//! - `reqrep_send` is created when the request is sent, and is nested within the sender's current span
+//! - `reqrep_process` wraps the backend processing of the request, and is nested within the
+//!   request's `reqrep_send` span, i.e., spans that are created by the Processor nest across the
+//!   channel boundary
+//! - both spans carry the `reqrep_id` field. The ReqRep channel does not know the request's message
+//!   identity, i.e., message ids are recorded by the layer that owns them, e.g., the nng typed
+//!   client and processor spans carry the message's InstanceId
+//! - futures can be instrumented via [Instrumented](struct.Instrumented.html)
+//!
+//! The default build is not instrumented.
 //!
 //! ## Metric Features
 //! - *[01D52CH5BJQM4D903VN1MJ10CC]* The number of requests sent per ReqRepId is tracked
 //! - *[01D4ZHRS7RV42RXN1R83Q8QDPA]* The number of running ReqRep service backend instances are tracked
 //! - *[01D4ZS3J72KG380GFW4GMQKCFH]* Message processing timer metrics are collected
 //! - *[01D59WRTHWQRPC8DYMN76RJ5X0]* Backend Processor panics are tracked
+//! - Service metrics for transient services can be unregistered when the last service instance exits
+//!   - see [ReqRepConfig::set_unregister_metrics_on_idle()](struct.ReqRepConfig.html#method.set_unregister_metrics_on_idle)
 //! - *[01D59X5KJ7Q72C2F2FP2VYVGS1]* ReqRep related metric descriptors can be easily retrieved
 //! - *[01D59X5KJ7Q72C2F2FP2VYVGS1]* ReqRep related metrics can be easily gathered
 //!
@@ -104,32 +144,57 @@
 //! });
 //! ```
 
-use crate::concurrent::{execution::Executor, messaging::errors::ChannelError};
+use crate::concurrent::{execution::Executor, messaging::errors::ChannelError, timer};
+use failure::Fail;
 use futures::{
     channel,
+    future,
     prelude::*,
     task::{SpawnError, SpawnExt},
 };
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
 use maplit::hashmap;
 use oysterpack_log::*;
 use oysterpack_uid::macros::ulid;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use std::{
     any::Any,
     fmt::{self, Debug},
+    num::NonZeroUsize,
     panic::AssertUnwindSafe,
     pin::Pin,
-    time::Instant,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
+#[cfg(feature = "tracing")]
+use futures::task::{Poll, Waker};
 
 pub mod metrics;
 
+lazy_static! {
+    /// Default timer buckets registry
+    static ref DEFAULT_TIMER_BUCKETS: RwLock<HashMap<ReqRepId, Vec<f64>>> = RwLock::new(HashMap::new());
+
+    /// Global default timer buckets, which are used when no default timer buckets are registered for the ReqRepId
+    /// - 12 buckets starting at 1 ms and growing exponentially by a factor of 2, i.e., 1 ms - 2.048 sec
+    static ref GLOBAL_DEFAULT_TIMER_BUCKETS: RwLock<Vec<f64>> = RwLock::new(
+        crate::metrics::exponential_timer_buckets(Duration::from_millis(1), 2.0, NonZeroUsize::new(12).unwrap()).unwrap()
+    );
+}
+
 /// ReqRep is used to configure and start a ReqRep service
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReqRepConfig {
     reqrep_id: ReqRepId,
     chan_buf_size: usize,
-    metric_timer_buckets: Vec<f64>,
+    metric_timer_buckets: Option<Vec<f64>>,
+    #[serde(default)]
+    unregister_metrics_on_idle: bool,
 }
 
 impl ReqRepConfig {
@@ -144,8 +209,17 @@ impl ReqRepConfig {
     }
 
     /// Returns timer histogram buckets used to configure the Histogram timer metric
-    pub fn metric_timer_buckets(&self) -> &[f64] {
-        &self.metric_timer_buckets
+    /// - if None, then the default timer buckets registered for the ReqRepId are used - see
+    ///   [default_buckets_for()](#method.default_buckets_for)
+    pub fn metric_timer_buckets(&self) -> Option<&[f64]> {
+        self.metric_timer_buckets.as_ref().map(Vec::as_slice)
+    }
+
+    /// If true, then the service metrics for the ReqRepId are unregistered when the last service
+    /// instance exits
+    /// - default = false, i.e., the metrics are retained across service restarts
+    pub fn unregister_metrics_on_idle(&self) -> bool {
+        self.unregister_metrics_on_idle
     }
 
     /// constructor
@@ -155,10 +229,49 @@ impl ReqRepConfig {
         Self {
             reqrep_id,
             chan_buf_size: 0,
-            metric_timer_buckets,
+            metric_timer_buckets: Some(metric_timer_buckets),
+            unregister_metrics_on_idle: false,
+        }
+    }
+
+    /// constructor
+    /// - the timer buckets are resolved when the service is started via [default_buckets_for()](#method.default_buckets_for)
+    pub fn with_default_buckets(reqrep_id: ReqRepId) -> Self {
+        Self {
+            reqrep_id,
+            chan_buf_size: 0,
+            metric_timer_buckets: None,
+            unregister_metrics_on_idle: false,
         }
     }
 
+    /// Registers the default timer buckets for the specified ReqRepId
+    /// - returns the previously registered timer buckets
+    pub fn register_default_buckets(reqrep_id: ReqRepId, buckets: Vec<f64>) -> Option<Vec<f64>> {
+        DEFAULT_TIMER_BUCKETS.write().insert(reqrep_id, buckets)
+    }
+
+    /// Unregisters the default timer buckets for the specified ReqRepId
+    pub fn unregister_default_buckets(reqrep_id: ReqRepId) -> Option<Vec<f64>> {
+        DEFAULT_TIMER_BUCKETS.write().remove(&reqrep_id)
+    }
+
+    /// Sets the global default timer buckets, which are used when no default timer buckets are
+    /// registered for a ReqRepId
+    pub fn set_global_default_buckets(buckets: Vec<f64>) {
+        *GLOBAL_DEFAULT_TIMER_BUCKETS.write() = buckets;
+    }
+
+    /// Returns the default timer buckets registered for the ReqRepId. If none are registered, then
+    /// the global default timer buckets are returned.
+    pub fn default_buckets_for(reqrep_id: ReqRepId) -> Vec<f64> {
+        DEFAULT_TIMER_BUCKETS
+            .read()
+            .get(&reqrep_id)
+            .cloned()
+            .unwrap_or_else(|| GLOBAL_DEFAULT_TIMER_BUCKETS.read().clone())
+    }
+
     /// sets the channel buffer size
     ///
     /// The channel's capacity is equal to buffer + num-senders. In other words, each sender gets a
@@ -169,6 +282,16 @@ impl ReqRepConfig {
         self
     }
 
+    /// Enables unregistering the service metrics for the ReqRepId when the last service instance
+    /// exits, which prevents metrics from leaking for transient services
+    pub fn set_unregister_metrics_on_idle(
+        mut self,
+        unregister_metrics_on_idle: bool,
+    ) -> ReqRepConfig {
+        self.unregister_metrics_on_idle = unregister_metrics_on_idle;
+        self
+    }
+
     /// Starts the backend service message processor and returns the frontend ReqRep client, which
     /// communicates with the backend service via a channel.
     pub fn start_service<Req, Rep, Service>(
@@ -181,12 +304,46 @@ impl ReqRepConfig {
         Rep: Debug + Send + 'static,
         Service: Processor<Req, Rep> + Send + 'static,
     {
+        let metric_timer_buckets = self
+            .metric_timer_buckets
+            .unwrap_or_else(|| ReqRepConfig::default_buckets_for(self.reqrep_id));
+        ReqRep::start_service(
+            self.reqrep_id,
+            self.chan_buf_size,
+            processor,
+            executor,
+            metric_timer_buckets,
+            self.unregister_metrics_on_idle,
+            None,
+        )
+    }
+
+    /// Starts the backend service message processor, routing requests and replies that cannot be
+    /// delivered to the dead letter sink - see [start_service()](#method.start_service)
+    /// - the sink is shared by the backend service and the ReqRep client, i.e., requests that are
+    ///   rejected client side are also routed to the sink
+    pub fn start_service_with_dead_letter_sink<Req, Rep, Service>(
+        self,
+        processor: Service,
+        executor: Executor,
+        dead_letter_sink: DeadLetterSink<Req, Rep>,
+    ) -> Result<ReqRep<Req, Rep>, SpawnError>
+    where
+        Req: Debug + Send + 'static,
+        Rep: Debug + Send + 'static,
+        Service: Processor<Req, Rep> + Send + 'static,
+    {
+        let metric_timer_buckets = self
+            .metric_timer_buckets
+            .unwrap_or_else(|| ReqRepConfig::default_buckets_for(self.reqrep_id));
         ReqRep::start_service(
             self.reqrep_id,
             self.chan_buf_size,
             processor,
             executor,
-            self.metric_timer_buckets,
+            metric_timer_buckets,
+            self.unregister_metrics_on_idle,
+            Some(dead_letter_sink),
         )
     }
 }
@@ -202,6 +359,8 @@ where
     request_sender: channel::mpsc::Sender<ReqRepMessage<Req, Rep>>,
     reqrep_id: ReqRepId,
     request_send_counter: prometheus::IntCounter,
+    service_load: Arc<ServiceLoad>,
+    dead_letter_sink: Option<DeadLetterSink<Req, Rep>>,
 }
 
 impl<Req, Rep> ReqRep<Req, Rep>
@@ -221,21 +380,161 @@ where
         let msg = ReqRepMessage {
             req: Some(req),
             rep_sender,
+            deadline: None,
+            #[cfg(feature = "tracing")]
+            span: self.send_span(),
         };
-        await!(self.request_sender.send(msg))?;
+        await!(self.send_msg(msg))?;
         self.request_send_counter.inc();
         Ok(ReplyReceiver {
             receiver: rep_receiver,
         })
     }
 
+    /// Send the request async with a deadline, which is propagated to the backend Processor
+    /// - if the deadline has already expired, then `ChannelError::DeadlineExpired` is returned
+    ///   and the request is not sent, i.e., it is routed to the dead letter sink
+    pub async fn send_with_deadline(
+        &mut self,
+        req: Req,
+        deadline: Instant,
+    ) -> Result<ReplyReceiver<Rep>, ChannelError> {
+        if deadline <= Instant::now() {
+            send_dead_letter(
+                self.dead_letter_sink.as_ref(),
+                self.reqrep_id,
+                DeadLetterReason::DeadlineExceeded,
+                Undeliverable::Request(req),
+            );
+            return Err(ChannelError::DeadlineExpired);
+        }
+        let (rep_sender, rep_receiver) = channel::oneshot::channel::<Rep>();
+        let msg = ReqRepMessage {
+            req: Some(req),
+            rep_sender,
+            deadline: Some(deadline),
+            #[cfg(feature = "tracing")]
+            span: self.send_span(),
+        };
+        await!(self.send_msg(msg))?;
+        self.request_send_counter.inc();
+        Ok(ReplyReceiver {
+            receiver: rep_receiver,
+        })
+    }
+
+    /// Tries to send the request without waiting
+    /// - if the backend service is at capacity, i.e., the number of pending requests has reached
+    ///   the channel buffer size + 1, then `ChannelError::Full` is returned and the request is not
+    ///   sent, i.e., it is routed to the dead letter sink
+    pub fn try_send(&mut self, req: Req) -> Result<ReplyReceiver<Rep>, ChannelError> {
+        let (rep_sender, rep_receiver) = channel::oneshot::channel::<Rep>();
+        let msg = ReqRepMessage {
+            req: Some(req),
+            rep_sender,
+            deadline: None,
+            #[cfg(feature = "tracing")]
+            span: self.send_span(),
+        };
+        // the slot is reserved before checking the capacity, i.e., concurrent senders cannot overshoot
+        let pending = self.service_load.pending.fetch_add(1, Ordering::SeqCst);
+        if pending >= self.service_load.capacity {
+            self.service_load.pending.fetch_sub(1, Ordering::SeqCst);
+            self.throttled(msg);
+            return Err(ChannelError::Full);
+        }
+        if let Err(err) = self.request_sender.try_send(msg) {
+            self.service_load.pending.fetch_sub(1, Ordering::SeqCst);
+            if err.is_full() {
+                self.throttled(err.into_inner());
+                return Err(ChannelError::Full);
+            }
+            return Err(ChannelError::SenderDisconnected);
+        }
+        self.request_send_counter.inc();
+        Ok(ReplyReceiver {
+            receiver: rep_receiver,
+        })
+    }
+
+    /// Routes the request that was rejected because the backend service is at capacity to the dead
+    /// letter sink
+    fn throttled(&self, mut msg: ReqRepMessage<Req, Rep>) {
+        if let Some(req) = msg.take_request() {
+            send_dead_letter(
+                self.dead_letter_sink.as_ref(),
+                self.reqrep_id,
+                DeadLetterReason::Throttled,
+                Undeliverable::Request(req),
+            );
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn send_span(&self) -> tracing::Span {
+        tracing::info_span!("reqrep_send", reqrep_id = %self.reqrep_id)
+    }
+
+    /// Sends the message to the backend service
+    /// - the request is counted as pending before it is sent, i.e., before the service can receive it
+    /// - cancellation safe: if the future is dropped while waiting for channel capacity, then the
+    ///   message is dropped and the pending count is released. Once the message is handed off to
+    ///   the channel, the future completes without awaiting again, i.e., a message that was handed
+    ///   off is always accounted for by the backend service
+    async fn send_msg(&mut self, msg: ReqRepMessage<Req, Rep>) -> Result<(), ChannelError> {
+        let pending = PendingRequest::new(self.service_load.clone());
+        let request_sender = &mut self.request_sender;
+        await!(future::poll_fn(|waker| request_sender.poll_ready(waker)))?;
+        self.request_sender.start_send(msg)?;
+        pending.handed_off();
+        Ok(())
+    }
+
+    /// Returns the number of requests that have been sent, but have not yet been processed by the
+    /// backend service, i.e., requests that are queued or are being processed
+    pub fn pending_request_count(&self) -> usize {
+        self.service_load.pending.load(Ordering::SeqCst)
+    }
+
+    /// Returns how saturated the backend service is, as a ratio between 0.0 and 1.0
+    /// - the saturation is the max of the backlog ratio and the service's recent utilization
+    /// - backlog ratio = pending requests / (channel buffer size + 1), where the +1 accounts for the
+    ///   request being processed - a channel buffer size of 0 is treated as 1
+    /// - utilization = fraction of the last second that the service spent processing requests
+    pub fn saturation(&self) -> f64 {
+        self.service_load
+            .backlog_ratio()
+            .max(self.service_load.utilization())
+    }
+
+    /// Returns true if the [saturation()](#method.saturation) is at or above the threshold
+    pub fn is_saturated(&self, threshold: f64) -> bool {
+        self.saturation() >= threshold
+    }
+
     /// Send the request and await to receive a reply
+    /// - the returned future is cancellation safe, i.e., it can be dropped at any point:
+    ///   - if the request has not yet been sent, then it is dropped and the pending request count
+    ///     is released
+    ///   - if the request has been sent, then the reply channel is released and the backend service
+    ///     still processes the request - the reply is treated as undeliverable
     pub async fn send_recv(&mut self, req: Req) -> Result<Rep, ChannelError> {
         let receiver = await!(self.send(req))?;
         let rep = await!(receiver.recv())?;
         Ok(rep)
     }
 
+    /// Send the request with a deadline and await to receive a reply
+    pub async fn send_recv_with_deadline(
+        &mut self,
+        req: Req,
+        deadline: Instant,
+    ) -> Result<Rep, ChannelError> {
+        let receiver = await!(self.send_with_deadline(req, deadline))?;
+        let rep = await!(receiver.recv())?;
+        Ok(rep)
+    }
+
     /// constructor
     ///
     /// ## Notes
@@ -245,6 +544,7 @@ where
     fn new(
         reqrep_id: ReqRepId,
         chan_buf_size: usize,
+        dead_letter_sink: Option<DeadLetterSink<Req, Rep>>,
     ) -> (
         ReqRep<Req, Rep>,
         channel::mpsc::Receiver<ReqRepMessage<Req, Rep>>,
@@ -256,6 +556,8 @@ where
                 request_sender,
                 request_send_counter: metrics::REQREP_SEND_COUNTER
                     .with_label_values(&[reqrep_id.to_string().as_str()]),
+                service_load: Arc::new(ServiceLoad::new(chan_buf_size)),
+                dead_letter_sink,
             },
             request_receiver,
         )
@@ -276,6 +578,10 @@ where
     /// - chan_buf_size: usize - the channel buffer size used to send requests to the backend service message processor
     /// - executor: Executor - used to spawn the backend service message processor
     /// - metric_timer_buckets - used to configure Histogram timer metric
+    /// - unregister_metrics_on_idle - if true, then the service metrics are unregistered when the last
+    ///   service instance exits
+    /// - dead_letter_sink - if specified, then requests and replies that cannot be delivered are
+    ///   sent to the sink
     ///
     /// ## Service Metrics
     /// - Processor timer (Histogram)
@@ -290,6 +596,8 @@ where
         processor: Service,
         mut executor: Executor,
         metric_timer_buckets: Vec<f64>,
+        unregister_metrics_on_idle: bool,
+        dead_letter_sink: Option<DeadLetterSink<Req, Rep>>,
     ) -> Result<ReqRep<Req, Rep>, SpawnError>
     where
         Service: Processor<Req, Rep> + Send + 'static,
@@ -324,9 +632,11 @@ where
                 .clone()
         };
 
-        let (reqrep, mut req_receiver) = ReqRep::<Req, Rep>::new(reqrep_id, chan_buf_size);
+        let (reqrep, mut req_receiver) =
+            ReqRep::<Req, Rep>::new(reqrep_id, chan_buf_size, dead_letter_sink.clone());
         let reqrep_service_metrics = reqrep_service_metrics();
         let service_count = reqrep_service_metrics.service_count.clone();
+        let service_load = reqrep.service_load.clone();
 
         let mut processor = AssertUnwindSafe(processor);
         let service = async move {
@@ -339,18 +649,60 @@ where
                 request_count += 1;
                 let req = msg.take_request().unwrap();
 
+                // requests that expired while queued are not processed
+                if msg.deadline.map_or(false, |deadline| deadline <= Instant::now()) {
+                    service_load.processing_completed(Instant::now());
+                    send_dead_letter(
+                        dead_letter_sink.as_ref(),
+                        reqrep_id,
+                        DeadLetterReason::DeadlineExceeded,
+                        Undeliverable::Request(req),
+                    );
+                    continue;
+                }
+
                 // time the request processing
                 let start = Instant::now();
-                let process_future = processor.process(req);
+                service_load.processing_started(start);
+                let process_future = {
+                    #[cfg(feature = "tracing")]
+                    let span = tracing::info_span!(
+                        parent: &msg.span,
+                        "reqrep_process",
+                        reqrep_id = %reqrep_id
+                    );
+                    #[cfg(feature = "tracing")]
+                    let _enter = span.enter();
+                    let process_future = match msg.deadline {
+                        Some(deadline) => {
+                            processor.process_with_deadline(req, DeadlineSignal::new(deadline))
+                        }
+                        None => processor.process(req),
+                    };
+                    #[cfg(feature = "tracing")]
+                    let process_future = Instrumented {
+                        future: process_future,
+                        span: span.clone(),
+                    };
+                    process_future
+                };
                 let process_future = AssertUnwindSafe(process_future);
                 let rep = await!(process_future.catch_unwind());
                 let elapsed = start.elapsed();
+                service_load.processing_completed(start + elapsed);
 
                 match rep {
                     Ok(rep) => {
                         // send back the reply
-                        // we don't care if the client reply channel is disconnected
-                        let _ = msg.reply(rep);
+                        // if the client reply channel is disconnected, then the reply is routed to the dead letter sink
+                        if let Err(rep) = msg.try_reply(rep) {
+                            send_dead_letter(
+                                dead_letter_sink.as_ref(),
+                                reqrep_id,
+                                DeadLetterReason::ReplyReceiverDropped,
+                                Undeliverable::Reply(rep),
+                            );
+                        }
 
                         // record the timing metric
                         reqrep_service_metrics
@@ -376,6 +728,9 @@ where
             async move {
                 let _ = await!(service.catch_unwind());
                 service_count.dec();
+                if unregister_metrics_on_idle && service_count.get() == 0 {
+                    metrics::unregister_service_metrics(reqrep_id);
+                }
             },
         )?;
         Ok(reqrep)
@@ -391,10 +746,137 @@ where
         f.debug_struct("ReqRep")
             .field("reqrep_id", &self.reqrep_id)
             .field("request_send_count", &self.request_send_counter.get())
+            .field("pending_request_count", &self.pending_request_count())
             .finish()
     }
 }
 
+/// Tracks the backend service load, which is used to compute the service saturation
+#[derive(Debug)]
+struct ServiceLoad {
+    // channel buffer size + 1 for the request being processed
+    capacity: usize,
+    pending: AtomicUsize,
+    busy_time: Mutex<BusyTime>,
+}
+
+impl ServiceLoad {
+    /// utilization is measured over a sliding window
+    const UTILIZATION_WINDOW: Duration = Duration::from_secs(1);
+
+    fn new(chan_buf_size: usize) -> ServiceLoad {
+        ServiceLoad {
+            capacity: chan_buf_size.max(1) + 1,
+            pending: AtomicUsize::new(0),
+            busy_time: Mutex::new(BusyTime {
+                window_start: Instant::now(),
+                busy: Duration::from_millis(0),
+                busy_since: None,
+                prev_window_utilization: 0.0,
+            }),
+        }
+    }
+
+    fn backlog_ratio(&self) -> f64 {
+        (self.pending.load(Ordering::SeqCst) as f64 / self.capacity as f64).min(1.0)
+    }
+
+    fn processing_started(&self, now: Instant) {
+        let mut busy_time = self.busy_time.lock();
+        busy_time.roll(now);
+        busy_time.busy_since = Some(now);
+    }
+
+    fn processing_completed(&self, now: Instant) {
+        self.pending.fetch_sub(1, Ordering::SeqCst);
+        let mut busy_time = self.busy_time.lock();
+        busy_time.roll(now);
+        if let Some(busy_since) = busy_time.busy_since.take() {
+            busy_time.busy += now - busy_since.max(busy_time.window_start);
+        }
+    }
+
+    fn utilization(&self) -> f64 {
+        let now = Instant::now();
+        let mut busy_time = self.busy_time.lock();
+        busy_time.roll(now);
+        let busy = busy_time.busy
+            + busy_time
+                .busy_since
+                .map(|busy_since| now - busy_since.max(busy_time.window_start))
+                .unwrap_or_else(|| Duration::from_millis(0));
+        // the previous window is weighted by how much of it still overlaps the sliding window
+        let window = crate::metrics::duration_as_secs_f64(Self::UTILIZATION_WINDOW);
+        let elapsed = crate::metrics::duration_as_secs_f64(now - busy_time.window_start);
+        let utilization = (busy_time.prev_window_utilization * (window - elapsed).max(0.0)
+            + crate::metrics::duration_as_secs_f64(busy))
+            / window;
+        utilization.min(1.0)
+    }
+}
+
+/// Reserves a pending request slot, which is released when dropped unless the request was handed
+/// off to the backend service, i.e., the backend service releases it once the request is processed
+struct PendingRequest(Option<Arc<ServiceLoad>>);
+
+impl PendingRequest {
+    fn new(service_load: Arc<ServiceLoad>) -> PendingRequest {
+        service_load.pending.fetch_add(1, Ordering::SeqCst);
+        PendingRequest(Some(service_load))
+    }
+
+    fn handed_off(mut self) {
+        self.0.take();
+    }
+}
+
+impl Drop for PendingRequest {
+    fn drop(&mut self) {
+        if let Some(service_load) = self.0.take() {
+            service_load.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Tracks the time the service spent processing requests within the current utilization window
+#[derive(Debug)]
+struct BusyTime {
+    window_start: Instant,
+    busy: Duration,
+    // set while a request is being processed
+    busy_since: Option<Instant>,
+    prev_window_utilization: f64,
+}
+
+impl BusyTime {
+    /// starts a new window if the current window has elapsed
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now - self.window_start;
+        if elapsed < ServiceLoad::UTILIZATION_WINDOW {
+            return;
+        }
+        let mut busy = self.busy;
+        if let Some(busy_since) = self.busy_since {
+            busy += now - busy_since.max(self.window_start);
+            self.busy_since = Some(now);
+        }
+        self.prev_window_utilization = if elapsed < ServiceLoad::UTILIZATION_WINDOW * 2 {
+            (crate::metrics::duration_as_secs_f64(busy)
+                / crate::metrics::duration_as_secs_f64(elapsed))
+            .min(1.0)
+        } else {
+            // the service was idle for at least a full window, unless it is still busy
+            if self.busy_since.is_some() {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        self.window_start = now;
+        self.busy = Duration::from_millis(0);
+    }
+}
+
 /// ReqRep service metrics
 #[derive(Clone)]
 struct ReqRepServiceMetrics {
@@ -412,6 +894,45 @@ where
 {
     req: Option<Req>,
     rep_sender: channel::oneshot::Sender<Rep>,
+    deadline: Option<Instant>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// Enters the span each time the future is polled, i.e., spans that are created while the future
+/// is being polled are nested within the span
+#[cfg(feature = "tracing")]
+pub struct Instrumented<F> {
+    future: F,
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl<F: Future + Unpin> Instrumented<F> {
+    /// constructor
+    pub fn new(future: F, span: tracing::Span) -> Self {
+        Self { future, span }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<F> fmt::Debug for Instrumented<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Instrumented")
+            .field("span", &self.span)
+            .finish()
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<F: Future + Unpin> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, waker: &Waker) -> Poll<F::Output> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        Pin::new(&mut this.future).poll(waker)
+    }
 }
 
 impl<Req, Rep> ReqRepMessage<Req, Rep>
@@ -429,12 +950,117 @@ where
 
     /// Send the reply
     fn reply(self, rep: Rep) -> Result<(), ChannelError> {
-        self.rep_sender
-            .send(rep)
-            .map_err(|_| ChannelError::SenderDisconnected)
+        self.try_reply(rep).map_err(|_| ChannelError::SenderDisconnected)
+    }
+
+    /// Send the reply - if the reply cannot be delivered, then it is returned
+    fn try_reply(self, rep: Rep) -> Result<(), Rep> {
+        self.rep_sender.send(rep)
     }
 }
 
+/// Receives requests and replies that could not be delivered - see [DeadLetter](struct.DeadLetter.html)
+pub type DeadLetterSink<Req, Rep> = channel::mpsc::UnboundedSender<DeadLetter<Req, Rep>>;
+
+/// Sends the dead letter to the sink, if one is configured
+/// - if the sink is disconnected, then the dead letter is logged and dropped
+fn send_dead_letter<Req, Rep>(
+    dead_letter_sink: Option<&DeadLetterSink<Req, Rep>>,
+    reqrep_id: ReqRepId,
+    reason: DeadLetterReason,
+    msg: Undeliverable<Req, Rep>,
+) where
+    Req: Debug + Send + 'static,
+    Rep: Debug + Send + 'static,
+{
+    if let Some(dead_letter_sink) = dead_letter_sink {
+        let dead_letter = DeadLetter {
+            reqrep_id,
+            reason,
+            msg,
+        };
+        if let Err(err) = dead_letter_sink.unbounded_send(dead_letter) {
+            warn!(
+                "ReqRepId({}) dead letter sink is disconnected: {}",
+                reqrep_id, err
+            );
+        }
+    }
+}
+
+/// A request or reply that could not be delivered
+#[derive(Debug)]
+pub struct DeadLetter<Req, Rep>
+where
+    Req: Debug + Send + 'static,
+    Rep: Debug + Send + 'static,
+{
+    reqrep_id: ReqRepId,
+    reason: DeadLetterReason,
+    msg: Undeliverable<Req, Rep>,
+}
+
+impl<Req, Rep> DeadLetter<Req, Rep>
+where
+    Req: Debug + Send + 'static,
+    Rep: Debug + Send + 'static,
+{
+    /// The ReqRep service the message was addressed to or produced by
+    pub fn reqrep_id(&self) -> ReqRepId {
+        self.reqrep_id
+    }
+
+    /// Why the message could not be delivered
+    pub fn reason(&self) -> DeadLetterReason {
+        self.reason
+    }
+
+    /// Returns the message that could not be delivered
+    pub fn msg(&self) -> &Undeliverable<Req, Rep> {
+        &self.msg
+    }
+
+    /// Consumes the dead letter, returning the request - None is returned for replies
+    pub fn into_request(self) -> Option<Req> {
+        match self.msg {
+            Undeliverable::Request(req) => Some(req),
+            Undeliverable::Reply(_) => None,
+        }
+    }
+
+    /// Consumes the dead letter, returning the reply - None is returned for requests
+    pub fn into_reply(self) -> Option<Rep> {
+        match self.msg {
+            Undeliverable::Reply(rep) => Some(rep),
+            Undeliverable::Request(_) => None,
+        }
+    }
+}
+
+/// The message that could not be delivered
+#[derive(Debug)]
+pub enum Undeliverable<Req, Rep>
+where
+    Req: Debug + Send + 'static,
+    Rep: Debug + Send + 'static,
+{
+    /// The request was rejected, i.e., it was never processed
+    Request(Req),
+    /// The reply could not be delivered to the client
+    Reply(Rep),
+}
+
+/// Reason the message could not be delivered
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DeadLetterReason {
+    /// The client's ReplyReceiver was dropped before the reply was sent
+    ReplyReceiverDropped,
+    /// The request deadline expired before the request was processed
+    DeadlineExceeded,
+    /// The request was rejected because the backend service was at capacity
+    Throttled,
+}
+
 /// Each request/reply API is uniquely identified by an ID.
 ///
 /// ## Use Cases
@@ -484,6 +1110,15 @@ where
     ///     trait are currently not supported, but are planned to be supported.
     fn process(&mut self, req: Req) -> FutureReply<Rep>; // TODO: change to an async method when async methods become supported on traits
 
+    /// request / reply processing for requests that were sent with a deadline
+    /// - the DeadlineSignal future completes when the deadline expires. The Processor can race the
+    ///   request processing against the signal in order to abort work when the deadline expires.
+    /// - the default implementation ignores the deadline and delegates to `process()`
+    fn process_with_deadline(&mut self, req: Req, deadline: DeadlineSignal) -> FutureReply<Rep> {
+        let _ = deadline;
+        self.process(req)
+    }
+
     /// Invoked before any messages have been sent
     fn init(&mut self) {}
 
@@ -499,6 +1134,45 @@ where
     }
 }
 
+/// Signals when a request deadline has expired - see [Processor::process_with_deadline()](trait.Processor.html#method.process_with_deadline)
+/// - the signal is driven by the shared [timer](../../timer/index.html), i.e., no thread is
+///   dedicated to the signal
+#[derive(Debug)]
+pub struct DeadlineSignal {
+    delay: timer::Delay,
+}
+
+impl DeadlineSignal {
+    /// constructor
+    /// - if the deadline has already expired, then the signal fires immediately
+    pub fn new(deadline: Instant) -> DeadlineSignal {
+        DeadlineSignal {
+            delay: timer::delay(deadline),
+        }
+    }
+
+    /// Returns the request deadline
+    pub fn deadline(&self) -> Instant {
+        self.delay.deadline()
+    }
+
+    /// Returns true if the deadline has expired
+    pub fn is_expired(&self) -> bool {
+        self.delay.is_expired()
+    }
+
+    /// Completes when the deadline expires
+    pub async fn expired(self) {
+        await!(self.delay)
+    }
+}
+
+/// Deadline exceeded error - can be used by Processor(s) to reply that the request was aborted
+/// because its deadline expired
+#[derive(Fail, Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[fail(display = "Request deadline exceeded")]
+pub struct DeadlineExceeded;
+
 /// Panic error type
 pub type PanicError = Box<dyn Any + Send + 'static>;
 
@@ -519,7 +1193,10 @@ mod tests {
         task::{Spawn, SpawnExt},
     };
     use oysterpack_log::*;
-    use std::{thread, time::Duration};
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
 
     #[test]
     fn req_rep() {
@@ -571,7 +1248,8 @@ mod tests {
 
         // GIVEN: a ReqRep client
         let mut req_rep =
-            ReqRep::start_service(REQREP_ID, 1, Inc, executor.clone(), timer_buckets).unwrap();
+            ReqRep::start_service(REQREP_ID, 1, Inc, executor.clone(), timer_buckets, false, None)
+                .unwrap();
 
         let task = async {
             // WHEN: a request is sent async
@@ -646,6 +1324,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn req_rep_config_default_buckets() {
+        configure_logging();
+        let mut executor = global_executor();
+
+        struct Inc;
+
+        impl Processor<usize, usize> for Inc {
+            fn process(&mut self, req: usize) -> reqrep::FutureReply<usize> {
+                async move { req + 1 }.boxed()
+            }
+        }
+
+        // GIVEN: default timer buckets are registered for the ReqRepId
+        let reqrep_id = ReqRepId::generate();
+        let timer_buckets = crate::metrics::timer_buckets(vec![
+            Duration::from_millis(5),
+            Duration::from_millis(10),
+            Duration::from_millis(15),
+        ])
+        .unwrap();
+        assert!(ReqRepConfig::register_default_buckets(reqrep_id, timer_buckets.clone()).is_none());
+        assert_eq!(ReqRepConfig::default_buckets_for(reqrep_id), timer_buckets);
+        // AND: other ReqRepId(s) fallback to the global default timer buckets
+        assert_eq!(
+            ReqRepConfig::default_buckets_for(ReqRepId::generate()),
+            *GLOBAL_DEFAULT_TIMER_BUCKETS.read()
+        );
+
+        // WHEN: the service is started without explicit timer buckets
+        let config = ReqRepConfig::with_default_buckets(reqrep_id);
+        assert!(config.metric_timer_buckets().is_none());
+        let mut client = config.start_service(Inc, executor.clone()).unwrap();
+        let n = executor.run(async { await!(client.send_recv(1)).unwrap() });
+        assert_eq!(n, 2);
+
+        // THEN: the histogram timer metric uses the registered timer buckets
+        let histogram = metrics::histogram_timer_metric(reqrep_id).unwrap();
+        let bucket_upper_bounds: Vec<f64> = histogram
+            .get_bucket()
+            .iter()
+            .map(|bucket| bucket.get_upper_bound())
+            .collect();
+        assert_eq!(bucket_upper_bounds, timer_buckets);
+
+        assert_eq!(
+            ReqRepConfig::unregister_default_buckets(reqrep_id),
+            Some(timer_buckets)
+        );
+    }
+
     #[test]
     fn req_rep_with_disconnected_receiver() {
         configure_logging();
@@ -714,4 +1443,524 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    fn req_rep_deadline() {
+        configure_logging();
+
+        // processor that never completes the work on its own, i.e., it can only be cancelled by the deadline
+        struct LongRunning;
+        impl Processor<usize, Result<usize, DeadlineExceeded>> for LongRunning {
+            fn process(&mut self, req: usize) -> FutureReply<Result<usize, DeadlineExceeded>> {
+                async move { Ok(req) }.boxed()
+            }
+
+            fn process_with_deadline(
+                &mut self,
+                req: usize,
+                deadline: DeadlineSignal,
+            ) -> FutureReply<Result<usize, DeadlineExceeded>> {
+                async move {
+                    let (_work_tx, work_rx) = oneshot::channel::<usize>();
+                    match await!(futures::future::select(work_rx, deadline.expired().boxed())) {
+                        futures::future::Either::Left((Ok(rep), _)) => Ok(rep),
+                        _ => Err(DeadlineExceeded),
+                    }
+                }
+                    .boxed()
+            }
+        }
+
+        let mut executor = global_executor();
+        let mut client = ReqRepConfig::new(ReqRepId::generate(), vec![0.001, 0.01, 0.1])
+            .start_service(LongRunning, executor.clone())
+            .unwrap();
+
+        // WHEN: a request is sent without a deadline
+        let rep = executor.run(
+            async {
+                let mut client = client.clone();
+                await!(client.send_recv(1))
+            },
+        );
+        // THEN: the request is processed normally
+        assert_eq!(rep.unwrap(), Ok(1));
+
+        // WHEN: the request is sent with a deadline, but the processor is still working when the deadline expires
+        let start = Instant::now();
+        let rep = executor.run(
+            async {
+                let mut client = client.clone();
+                await!(client.send_recv_with_deadline(1, Instant::now() + Duration::from_millis(50)))
+            },
+        );
+        // THEN: the processor is cancelled and replies with DeadlineExceeded
+        assert_eq!(rep.unwrap(), Err(DeadlineExceeded));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // WHEN: the request is sent with a deadline that has already expired
+        let rep = executor.run(
+            async {
+                let mut client = client.clone();
+                await!(client.send_with_deadline(1, Instant::now()))
+            },
+        );
+        // THEN: the request fails immediately
+        match rep {
+            Err(ChannelError::DeadlineExpired) => (),
+            other => panic!("expected ChannelError::DeadlineExpired, but got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn req_rep_dead_letter_sink() {
+        configure_logging();
+
+        struct SlowInc;
+        impl Processor<usize, usize> for SlowInc {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                async move {
+                    thread::sleep(Duration::from_millis(50));
+                    req + 1
+                }
+                    .boxed()
+            }
+        }
+
+        let mut executor = global_executor();
+        let reqrep_id = ReqRepId::generate();
+        let (dead_letter_tx, mut dead_letter_rx) = futures::channel::mpsc::unbounded();
+        // GIVEN: a ReqRep service configured with a dead letter sink
+        let client = ReqRepConfig::new(reqrep_id, vec![0.001, 0.01, 0.1])
+            .start_service_with_dead_letter_sink(SlowInc, executor.clone(), dead_letter_tx)
+            .unwrap();
+
+        // WHEN: the ReplyReceiver is dropped before the reply is sent
+        let mut sender = client.clone();
+        let reply_receiver = executor.run(async move { await!(sender.send(1)) }).unwrap();
+        drop(reply_receiver);
+
+        // THEN: the undelivered reply is routed to the dead letter sink
+        let dead_letter = executor
+            .run(async move { await!(dead_letter_rx.next()) })
+            .unwrap();
+        assert_eq!(dead_letter.reqrep_id(), reqrep_id);
+        assert_eq!(dead_letter.reason(), DeadLetterReason::ReplyReceiverDropped);
+        assert_eq!(dead_letter.into_reply(), Some(2));
+    }
+
+    #[test]
+    fn req_rep_dead_letter_sink_rejected_requests() {
+        configure_logging();
+
+        // blocks processing the first request until the gate is opened
+        struct Paused(Option<oneshot::Receiver<()>>);
+        impl Processor<usize, usize> for Paused {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                let gate = self.0.take();
+                async move {
+                    if let Some(gate) = gate {
+                        let _ = await!(gate);
+                    }
+                    req + 1
+                }
+                    .boxed()
+            }
+        }
+
+        let mut executor = global_executor();
+        let reqrep_id = ReqRepId::generate();
+        let (dead_letter_tx, mut dead_letter_rx) = futures::channel::mpsc::unbounded();
+        // GIVEN: a paused service with a capacity of 2 that is configured with a dead letter sink
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let mut client = ReqRepConfig::new(reqrep_id, vec![0.001, 0.01, 0.1])
+            .set_chan_buf_size(1)
+            .start_service_with_dead_letter_sink(
+                Paused(Some(gate_rx)),
+                executor.clone(),
+                dead_letter_tx,
+            )
+            .unwrap();
+
+        // WHEN: the first request blocks the service and a request is queued with a short deadline
+        let first = client.try_send(1).unwrap();
+        let mut sender = client.clone();
+        let expiring = executor
+            .run(async move {
+                await!(sender.send_with_deadline(2, Instant::now() + Duration::from_millis(10)))
+            })
+            .unwrap();
+        // AND: the service is at capacity when another request is sent
+        match client.try_send(3) {
+            Err(ChannelError::Full) => (),
+            other => panic!("expected ChannelError::Full, but got: {:?}", other),
+        }
+        // THEN: the throttled request is routed to the dead letter sink
+        let dead_letter = executor
+            .run(async { await!(dead_letter_rx.next()) })
+            .unwrap();
+        assert_eq!(dead_letter.reqrep_id(), reqrep_id);
+        assert_eq!(dead_letter.reason(), DeadLetterReason::Throttled);
+        assert_eq!(dead_letter.into_request(), Some(3));
+
+        // WHEN: the service is resumed after the queued request's deadline expired
+        thread::sleep(Duration::from_millis(20));
+        gate_tx.send(()).unwrap();
+        assert_eq!(executor.run(async move { await!(first.recv()) }).unwrap(), 2);
+        // THEN: the expired request is not processed and is routed to the dead letter sink
+        let dead_letter = executor
+            .run(async { await!(dead_letter_rx.next()) })
+            .unwrap();
+        assert_eq!(dead_letter.reason(), DeadLetterReason::DeadlineExceeded);
+        assert_eq!(dead_letter.into_request(), Some(2));
+        match executor.run(async move { await!(expiring.recv()) }) {
+            Err(ChannelError::ReceiverDisconnected) => (),
+            other => panic!("expected ChannelError::ReceiverDisconnected, but got: {:?}", other),
+        }
+        assert_eq!(client.pending_request_count(), 0);
+
+        // WHEN: a request is sent with a deadline that has already expired
+        let mut sender = client.clone();
+        let rep = executor.run(async move { await!(sender.send_with_deadline(4, Instant::now())) });
+        assert!(rep.is_err());
+        // THEN: it is routed to the dead letter sink
+        let dead_letter = executor
+            .run(async { await!(dead_letter_rx.next()) })
+            .unwrap();
+        assert_eq!(dead_letter.reason(), DeadLetterReason::DeadlineExceeded);
+        assert_eq!(dead_letter.into_request(), Some(4));
+    }
+
+    #[test]
+    fn req_rep_unregister_metrics_on_idle() {
+        configure_logging();
+
+        struct Inc;
+        impl Processor<usize, usize> for Inc {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                async move { req + 1 }.boxed()
+            }
+        }
+
+        let mut executor = global_executor();
+        let reqrep_id = ReqRepId::generate();
+
+        // GIVEN: a transient service that is configured to unregister its metrics when idle
+        let mut client = ReqRepConfig::new(reqrep_id, vec![0.001, 0.01, 0.1])
+            .set_unregister_metrics_on_idle(true)
+            .start_service(Inc, executor.clone())
+            .unwrap();
+        let rep = executor
+            .run(async move { await!(client.send_recv(1)) })
+            .unwrap();
+        assert_eq!(rep, 2);
+        // THEN: the service timer metric is registered
+        assert!(metrics::histogram_timer_metric(reqrep_id).is_some());
+
+        // WHEN: the last service instance exits, i.e., all clients are dropped
+        // THEN: the timer metric is unregistered
+        let start = Instant::now();
+        while metrics::histogram_timer_metric(reqrep_id).is_some() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "timer metric was not unregistered"
+            );
+            thread::yield_now();
+        }
+        // AND: the service instance count gauge returns to zero
+        assert_eq!(metrics::service_instance_count(reqrep_id), 0);
+        assert_eq!(
+            metrics::REQ_REP_SERVICE_INSTANCE_COUNT
+                .with_label_values(&[reqrep_id.to_string().as_str()])
+                .get(),
+            0
+        );
+    }
+
+    #[test]
+    fn req_rep_saturation() {
+        configure_logging();
+
+        // blocks processing the first request until the gate is opened
+        struct Paused(Option<oneshot::Receiver<()>>);
+        impl Processor<usize, usize> for Paused {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                let gate = self.0.take();
+                async move {
+                    if let Some(gate) = gate {
+                        let _ = await!(gate);
+                    }
+                    req + 1
+                }
+                    .boxed()
+            }
+        }
+
+        let mut executor = global_executor();
+        const CHAN_BUF_SIZE: usize = 4;
+
+        // GIVEN: an idle service
+        let (_gate_tx, gate_rx) = oneshot::channel();
+        let idle = ReqRepConfig::new(ReqRepId::generate(), vec![0.001, 0.01, 0.1])
+            .set_chan_buf_size(CHAN_BUF_SIZE)
+            .start_service(Paused(Some(gate_rx)), executor.clone())
+            .unwrap();
+        // THEN: the service reports no saturation
+        assert_eq!(idle.pending_request_count(), 0);
+        assert!(idle.saturation() < 0.01, "{}", idle.saturation());
+        assert!(!idle.is_saturated(0.8));
+
+        // GIVEN: a paused service
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let mut client = ReqRepConfig::new(ReqRepId::generate(), vec![0.001, 0.01, 0.1])
+            .set_chan_buf_size(CHAN_BUF_SIZE)
+            .start_service(Paused(Some(gate_rx)), executor.clone())
+            .unwrap();
+        // WHEN: the channel is filled, i.e., 1 request is being processed and the rest are queued
+        let mut reply_receivers = Vec::new();
+        for i in 0..=CHAN_BUF_SIZE {
+            let mut sender = client.clone();
+            reply_receivers.push(executor.run(async move { await!(sender.send(i)) }).unwrap());
+        }
+        // THEN: the service reports that it is saturated
+        assert_eq!(client.pending_request_count(), CHAN_BUF_SIZE + 1);
+        assert!(client.saturation() > 0.99, "{}", client.saturation());
+        assert!(client.is_saturated(0.8));
+
+        // WHEN: the service is resumed
+        gate_tx.send(()).unwrap();
+        for (i, reply_receiver) in reply_receivers.into_iter().enumerate() {
+            let rep = executor.run(async move { await!(reply_receiver.recv()) });
+            assert_eq!(rep.unwrap(), i + 1);
+        }
+        // THEN: the backlog is drained
+        assert_eq!(client.pending_request_count(), 0);
+    }
+
+    #[test]
+    fn req_rep_try_send() {
+        configure_logging();
+
+        // blocks processing the first request until the gate is opened
+        struct Paused(Option<oneshot::Receiver<()>>);
+        impl Processor<usize, usize> for Paused {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                let gate = self.0.take();
+                async move {
+                    if let Some(gate) = gate {
+                        let _ = await!(gate);
+                    }
+                    req + 1
+                }
+                    .boxed()
+            }
+        }
+
+        // GIVEN: a paused service with a capacity of 2, i.e., 1 being processed and 1 queued
+        let mut executor = global_executor();
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let mut client = ReqRepConfig::new(ReqRepId::generate(), vec![0.001, 0.01, 0.1])
+            .set_chan_buf_size(1)
+            .start_service(Paused(Some(gate_rx)), executor.clone())
+            .unwrap();
+
+        // WHEN: requests are sent until the service is full
+        let reply_receivers: Vec<ReplyReceiver<usize>> =
+            (0..2).map(|i| client.try_send(i).unwrap()).collect();
+        // THEN: the next request fails immediately
+        match client.try_send(2) {
+            Err(ChannelError::Full) => (),
+            other => panic!("expected ChannelError::Full, but got: {:?}", other),
+        }
+        assert_eq!(client.pending_request_count(), 2);
+
+        // WHEN: the service is resumed
+        gate_tx.send(()).unwrap();
+        for (i, reply_receiver) in reply_receivers.into_iter().enumerate() {
+            let rep = executor.run(async move { await!(reply_receiver.recv()) });
+            assert_eq!(rep.unwrap(), i + 1);
+        }
+        // THEN: requests can be sent again
+        let reply_receiver = client.try_send(10).unwrap();
+        let rep = executor.run(async move { await!(reply_receiver.recv()) });
+        assert_eq!(rep.unwrap(), 11);
+    }
+
+    #[test]
+    fn req_rep_send_recv_cancellation() {
+        configure_logging();
+
+        // blocks processing the first request until the gate is opened
+        struct Paused(Option<oneshot::Receiver<()>>);
+        impl Processor<usize, usize> for Paused {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                let gate = self.0.take();
+                async move {
+                    if let Some(gate) = gate {
+                        let _ = await!(gate);
+                    }
+                    req + 1
+                }
+                    .boxed()
+            }
+        }
+
+        // polls the future once, and then drops it
+        fn poll_once_and_drop<F: Future>(executor: &mut Executor, f: F) {
+            let mut f = Box::pin(f);
+            executor.run(future::poll_fn(move |waker| {
+                assert!(f.as_mut().poll(waker).is_pending());
+                futures::task::Poll::Ready(())
+            }));
+        }
+
+        // GIVEN: a paused service with a channel buffer size of 0
+        let mut executor = global_executor();
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let mut client = ReqRepConfig::new(ReqRepId::generate(), vec![0.001, 0.01, 0.1])
+            .start_service(Paused(Some(gate_rx)), executor.clone())
+            .unwrap();
+        let reply_receiver = {
+            let mut client = client.clone();
+            executor.run(async move { await!(client.send(0)) }).unwrap()
+        };
+        assert_eq!(client.pending_request_count(), 1);
+
+        // WHEN: the send_recv future is dropped while awaiting the reply
+        poll_once_and_drop(&mut executor, client.send_recv(1));
+        // THEN: the request is still pending, i.e., it was handed off to the service
+        assert_eq!(client.pending_request_count(), 2);
+
+        // WHEN: the send_recv future is dropped while waiting for channel capacity
+        // - the client's channel slot is occupied by the previous request
+        poll_once_and_drop(&mut executor, client.send_recv(2));
+        // THEN: the pending request count is released
+        assert_eq!(client.pending_request_count(), 2);
+
+        // WHEN: the service is resumed
+        gate_tx.send(()).unwrap();
+        let rep = executor.run(async move { await!(reply_receiver.recv()) });
+        assert_eq!(rep.unwrap(), 1);
+        // THEN: the backlog is drained, including the request whose reply channel was dropped
+        let wait_until = Instant::now() + Duration::from_secs(5);
+        while client.pending_request_count() > 0 && Instant::now() < wait_until {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(client.pending_request_count(), 0);
+        // AND: the client can still be used
+        let rep = executor.run(async move { await!(client.send_recv(3)) });
+        assert_eq!(rep.unwrap(), 4);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn req_rep_tracing_spans() {
+        use std::sync::atomic::AtomicU64;
+        use tracing::{field, span, Event, Metadata, Subscriber};
+
+        configure_logging();
+
+        #[derive(Debug, Clone)]
+        struct SpanRecord {
+            id: u64,
+            name: &'static str,
+            parent: Option<u64>,
+            fields: HashMap<&'static str, String>,
+        }
+
+        impl field::Visit for SpanRecord {
+            fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+                self.fields.insert(field.name(), format!("{:?}", value));
+            }
+        }
+
+        /// records the spans that are created
+        struct SpanRecorder {
+            next_id: AtomicU64,
+            spans: Arc<Mutex<Vec<SpanRecord>>>,
+        }
+
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn new_span(&self, attrs: &span::Attributes) -> span::Id {
+                let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+                let mut span = SpanRecord {
+                    id,
+                    name: attrs.metadata().name(),
+                    parent: attrs.parent().map(span::Id::into_u64),
+                    fields: HashMap::new(),
+                };
+                attrs.record(&mut span);
+                self.spans.lock().push(span);
+                span::Id::from_u64(id)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, _: &Event) {}
+
+            fn enter(&self, _: &span::Id) {}
+
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        // GIVEN: a global subscriber, because requests are processed on the executor threads
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::set_global_default(SpanRecorder {
+            next_id: AtomicU64::new(1),
+            spans: spans.clone(),
+        })
+        .unwrap();
+
+        struct Inc;
+        impl Processor<usize, usize> for Inc {
+            fn process(&mut self, req: usize) -> FutureReply<usize> {
+                async move { req + 1 }.boxed()
+            }
+        }
+
+        // GIVEN: a ReqRep service
+        let mut executor = global_executor();
+        let reqrep_id = ReqRepId::generate();
+        let mut client = ReqRepConfig::new(reqrep_id, vec![0.001, 0.01, 0.1])
+            .start_service(Inc, executor.clone())
+            .unwrap();
+
+        // WHEN: requests are sent
+        const REQUEST_COUNT: usize = 3;
+        for i in 0..REQUEST_COUNT {
+            let mut client = client.clone();
+            let rep = executor.run(
+                async move {
+                    let rep_receiver = await!(client.send(i)).unwrap();
+                    await!(rep_receiver.recv()).unwrap()
+                },
+            );
+            assert_eq!(rep, i + 1);
+        }
+
+        // THEN: a send span and a process span are emitted per request with the ReqRepId field
+        let reqrep_id = reqrep_id.to_string();
+        let spans: Vec<SpanRecord> = spans
+            .lock()
+            .iter()
+            .filter(|span| span.fields.get("reqrep_id") == Some(&reqrep_id))
+            .cloned()
+            .collect();
+        let send_spans: Vec<&SpanRecord> =
+            spans.iter().filter(|span| span.name == "reqrep_send").collect();
+        let process_spans: Vec<&SpanRecord> =
+            spans.iter().filter(|span| span.name == "reqrep_process").collect();
+        assert_eq!(send_spans.len(), REQUEST_COUNT);
+        assert_eq!(process_spans.len(), REQUEST_COUNT);
+        // AND: each process span is nested within its request's send span
+        for (send_span, process_span) in send_spans.iter().zip(process_spans.iter()) {
+            assert_eq!(process_span.parent, Some(send_span.id));
+        }
+    }
 }
@@ -18,6 +18,7 @@
 
 use super::{ReqRepId, ReqRepServiceMetrics};
 use hashbrown::HashMap;
+use oysterpack_log::*;
 use oysterpack_uid::ULID;
 use parking_lot::RwLock;
 
@@ -90,6 +91,25 @@ pub fn metric_ids() -> Vec<crate::metrics::MetricId> {
     ]
 }
 
+/// Unregisters the service metrics for the ReqRepId, if no service instances are running
+/// - the entry is re-checked while holding the lock, in case a new service instance was started
+pub(super) fn unregister_service_metrics(reqrep_id: ReqRepId) {
+    let mut reqrep_metrics = REQ_REP_METRICS.write();
+    let idle = reqrep_metrics
+        .get(&reqrep_id)
+        .map_or(false, |m| m.service_count.get() == 0);
+    if idle {
+        if let Some(m) = reqrep_metrics.remove(&reqrep_id) {
+            if let Err(err) = crate::metrics::registry().unregister(m.timer) {
+                warn!(
+                    "ReqRepId({}) failed to unregister the timer metric: {}",
+                    reqrep_id, err
+                );
+            }
+        }
+    }
+}
+
 /// return the ReqRep backend service count
 pub fn service_instance_count(reqrep_id: ReqRepId) -> u64 {
     let reqrep_metrics = REQ_REP_METRICS.read();
@@ -0,0 +1,246 @@
+/*
+ * Copyright 2019 OysterPack Inc.
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Provides async delays that are driven by a single shared timer thread.
+//!
+//! - a [Delay](struct.Delay.html) is a Future that completes when its deadline is reached
+//! - the timer thread sleeps until the next deadline, i.e., it does not poll
+//! - delays are kept in a min-heap ordered by deadline. Delays that are dropped before they fire are
+//!   removed lazily, i.e., when their deadline is reached or when the heap is pruned.
+
+use futures::{
+    channel::oneshot,
+    prelude::*,
+    task::{Poll, Waker},
+};
+use lazy_static::lazy_static;
+use parking_lot::{Condvar, Mutex};
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    pin::Pin,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+lazy_static! {
+    static ref TIMER: Timer = Timer::start();
+}
+
+/// Returns a Future that completes when the deadline is reached
+/// - if the deadline has already been reached, then the Future completes immediately
+pub fn delay(deadline: Instant) -> Delay {
+    let (sender, receiver) = oneshot::channel();
+    if deadline <= Instant::now() {
+        let _ = sender.send(());
+    } else {
+        TIMER.schedule(TimerEntry { deadline, sender });
+    }
+    Delay { deadline, receiver }
+}
+
+/// Returns a Future that completes after the specified duration
+pub fn delay_for(duration: Duration) -> Delay {
+    delay(Instant::now() + duration)
+}
+
+/// Future that completes when its deadline is reached - see [delay()](fn.delay.html)
+#[derive(Debug)]
+pub struct Delay {
+    deadline: Instant,
+    receiver: oneshot::Receiver<()>,
+}
+
+impl Delay {
+    /// Returns the deadline
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns true if the deadline has been reached
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, waker: &Waker) -> Poll<()> {
+        // the sender is only dropped without firing if the timer thread is gone, in which case the
+        // delay completes in order to not hang the task
+        Pin::new(&mut self.receiver).poll(waker).map(|_| ())
+    }
+}
+
+struct TimerEntry {
+    deadline: Instant,
+    sender: oneshot::Sender<()>,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &TimerEntry) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &TimerEntry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    /// reversed, i.e., the BinaryHeap max-heap is used as a min-heap
+    fn cmp(&self, other: &TimerEntry) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+struct TimerEntries {
+    heap: BinaryHeap<TimerEntry>,
+    /// when the heap grows to this size, the delays that were dropped are pruned
+    prune_threshold: usize,
+}
+
+struct Timer {
+    entries: Arc<(Mutex<TimerEntries>, Condvar)>,
+}
+
+impl Timer {
+    /// min heap size before dropped delays are pruned
+    const MIN_PRUNE_THRESHOLD: usize = 1024;
+
+    fn start() -> Timer {
+        let entries = Arc::new((
+            Mutex::new(TimerEntries {
+                heap: BinaryHeap::new(),
+                prune_threshold: Timer::MIN_PRUNE_THRESHOLD,
+            }),
+            Condvar::new(),
+        ));
+        let timer_entries = entries.clone();
+        thread::Builder::new()
+            .name("oysterpack-timer".to_string())
+            .spawn(move || Timer::run(&timer_entries))
+            .expect("failed to spawn timer thread");
+        Timer { entries }
+    }
+
+    fn schedule(&self, entry: TimerEntry) {
+        let (entries, condvar) = &*self.entries;
+        let mut entries = entries.lock();
+        if entries.heap.len() >= entries.prune_threshold {
+            let heap: BinaryHeap<TimerEntry> = entries
+                .heap
+                .drain()
+                .filter(|entry| !entry.sender.is_canceled())
+                .collect();
+            entries.prune_threshold = std::cmp::max(Timer::MIN_PRUNE_THRESHOLD, heap.len() * 2);
+            entries.heap = heap;
+        }
+        let next_deadline = entries.heap.peek().map(|next| next.deadline);
+        let deadline = entry.deadline;
+        entries.heap.push(entry);
+        // the timer thread only needs to be woken up if it is sleeping past the new deadline
+        if next_deadline.map_or(true, |next_deadline| deadline < next_deadline) {
+            condvar.notify_one();
+        }
+    }
+
+    fn run(entries: &(Mutex<TimerEntries>, Condvar)) {
+        let (entries, condvar) = entries;
+        let mut entries = entries.lock();
+        loop {
+            let now = Instant::now();
+            while entries
+                .heap
+                .peek()
+                .map_or(false, |next| next.deadline <= now)
+            {
+                let entry = entries.heap.pop().unwrap();
+                let _ = entry.sender.send(());
+            }
+            match entries.heap.peek().map(|next| next.deadline) {
+                Some(deadline) => {
+                    condvar.wait_until(&mut entries, deadline);
+                }
+                None => condvar.wait(&mut entries),
+            }
+        }
+    }
+}
+
+#[allow(warnings)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::execution::global_executor;
+    use crate::configure_logging;
+    use futures::future;
+
+    #[test]
+    fn delays_fire_at_their_deadlines() {
+        configure_logging();
+        let mut executor = global_executor();
+
+        // GIVEN: delays that are scheduled out of order
+        let start = Instant::now();
+        let delays = vec![
+            delay_for(Duration::from_millis(30)),
+            delay_for(Duration::from_millis(10)),
+            delay_for(Duration::from_millis(20)),
+        ];
+        // WHEN: the delays are awaited
+        let elapsed: Vec<Duration> = executor.run(future::join_all(
+            delays
+                .into_iter()
+                .map(|delay| delay.map(move |_| start.elapsed())),
+        ));
+        // THEN: each delay fires once its deadline is reached
+        assert!(elapsed[0] >= Duration::from_millis(30));
+        assert!(elapsed[1] >= Duration::from_millis(10));
+        assert!(elapsed[2] >= Duration::from_millis(20));
+
+        // GIVEN: a deadline that has already been reached
+        let delay = delay(Instant::now());
+        // THEN: the delay fires immediately
+        assert!(delay.is_expired());
+        executor.run(delay);
+    }
+
+    #[test]
+    fn dropped_delays_do_not_block_later_delays() {
+        configure_logging();
+        let mut executor = global_executor();
+
+        // GIVEN: delays that are dropped before they fire
+        for _ in 0..(Timer::MIN_PRUNE_THRESHOLD * 2) {
+            drop(delay_for(Duration::from_secs(60)));
+        }
+        // WHEN: a short delay is scheduled
+        let start = Instant::now();
+        executor.run(delay_for(Duration::from_millis(10)));
+        // THEN: it fires when its deadline is reached
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        assert!(start.elapsed() < Duration::from_secs(60));
+        // AND: the dropped delays were pruned
+        assert!(TIMER.entries.0.lock().heap.len() <= Timer::MIN_PRUNE_THRESHOLD * 2);
+    }
+}
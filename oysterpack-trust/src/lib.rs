@@ -41,6 +41,11 @@ extern crate pretty_assertions;
 pub mod concurrent;
 pub mod metrics;
 
+/// re-exported to enable downstream crates to instrument ReqRep services using the same `tracing`
+/// version - see [reqrep](concurrent/messaging/reqrep/index.html#tracing)
+#[cfg(feature = "tracing")]
+pub use tracing;
+
 #[cfg(test)]
 fn log_config() -> oysterpack_log::LogConfig {
     oysterpack_log::config::LogConfigBuilder::new(oysterpack_log::Level::Info)
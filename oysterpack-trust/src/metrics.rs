@@ -88,7 +88,24 @@
 //!   - histogram builders
 //!     - [HistogramBuilder](struct.HistogramBuilder.html)
 //!     - [HistogramVecBuilder](struct.HistogramVecBuilder.html)
+//! - Metrics can be registered in bulk from a declarative list of [MetricDescriptor(s)](struct.MetricDescriptor.html)
+//!   - [MetricRegistry::register_all()](struct.MetricRegistry.html#method.register_all)
+//!   - registration is atomic, i.e., if any metric fails to register, then none are registered
 //! - *[01D3M9X86BSYWW3132JQHWA3AT]* Text encoding metrics in a prometheus compatible format
+//! - Metrics are self documenting, i.e., MetricId(s) and LabelId(s) can be mapped to human readable
+//!   names, descriptions, and units
+//!   - [describe()](fn.describe.html) returns the [MetricDoc(s)](struct.MetricDoc.html) for the
+//!     registered metrics
+//!   - the metric description is populated from the help when the metric is registered
+//!   - names and units are provided via [MetricRegistry::document_metric()](struct.MetricRegistry.html#method.document_metric),
+//!     [MetricRegistry::document_label()](struct.MetricRegistry.html#method.document_label), or
+//!     the [MetricDescriptor](struct.MetricDescriptor.html)
+//!   - the text encoding includes the docs as `# NAME`, `# UNIT`, and `# LABEL` comments
+//! - Approximate quantiles, e.g., p50/p95/p99, can be computed from histogram buckets
+//!   - [histogram_quantiles()](fn.histogram_quantiles.html)
+//!   - [MetricRegistry::histogram_quantiles()](struct.MetricRegistry.html#method.histogram_quantiles)
+//! - SLA breaches can be detected by monitoring a histogram quantile against a threshold
+//!   - [SlaMonitor](struct.SlaMonitor.html)
 //! - *[01D3XX3ZBB7VW0GGRA60PMFC1M]* Time conversion functions to report timings in seconds as f64
 //!   - in prometheus, it is a common practice to report timer metrics in secs
 //!     - [nanos_as_secs_f64](fn.nanos_as_secs_f64.html)
@@ -105,6 +122,8 @@
 //! - because they are more efficient
 //! - IntCounter, IntCounterVec, IntGauge, IntGaugeVec
 
+use crate::concurrent::{execution::Executor, timer};
+use futures::task::{SpawnError, SpawnExt};
 use lazy_static::lazy_static;
 use oysterpack_log::*;
 use oysterpack_uid::{ulid_u128_into_string, ULID};
@@ -120,15 +139,33 @@ use std::{
     iter::Iterator,
     num::NonZeroUsize,
     str::FromStr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
 lazy_static! {
     /// Global metrics registry
     static ref METRIC_REGISTRY: MetricRegistry = MetricRegistry::default();
+
+    /// the metric is incremented each time an [SlaMonitor](struct.SlaMonitor.html) detects a breach
+    static ref SLA_BREACH_COUNT: prometheus::IntCounterVec = registry().register_int_counter_vec(
+        SLA_BREACH_COUNT_METRIC_ID,
+        "Number of SLA breaches that were detected",
+        &[SLA_METRIC_ID_LABEL_ID],
+        None
+    ).unwrap();
 }
 
+/// IntCounterVec MetricId which is used to count SLA breaches per monitored histogram MetricId:
+/// `M01D89ET2XJRJVBDDVH7M87VQE2`
+pub const SLA_BREACH_COUNT_METRIC_ID: MetricId = MetricId(1879995326857442070474060549012184514);
+
+/// Metric LabelId which is used to store the monitored histogram MetricId: `L01D89FH08VKGAZYPSV5580757R`
+pub const SLA_METRIC_ID_LABEL_ID: LabelId = LabelId(1879996234703721228187821737542653176);
+
 /// Metric Desc ID
 pub type DescId = u64;
 
@@ -789,6 +826,177 @@ fn new_histogram_vec<S: BuildHasher, Help: AsRef<str>>(
     prometheus::HistogramVec::new(opts, &label_names)
 }
 
+/// Metric types supported by [MetricDescriptor](struct.MetricDescriptor.html)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MetricType {
+    /// prometheus::IntCounter
+    IntCounter,
+    /// prometheus::Counter
+    Counter,
+    /// prometheus::IntCounterVec
+    IntCounterVec,
+    /// prometheus::CounterVec
+    CounterVec,
+    /// prometheus::IntGauge
+    IntGauge,
+    /// prometheus::Gauge
+    Gauge,
+    /// prometheus::IntGaugeVec
+    IntGaugeVec,
+    /// prometheus::GaugeVec
+    GaugeVec,
+    /// prometheus::Histogram
+    Histogram,
+    /// prometheus::HistogramVec
+    HistogramVec,
+}
+
+/// Declarative metric descriptor, which is used to register metrics in bulk - see
+/// [MetricRegistry::register_all()](struct.MetricRegistry.html#method.register_all)
+/// - variable labels are required for the vector metric types, and are ignored otherwise
+/// - buckets are required for the histogram metric types, and are ignored otherwise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricDescriptor {
+    metric_type: MetricType,
+    metric_id: MetricId,
+    help: String,
+    label_ids: Vec<LabelId>,
+    buckets: Vec<f64>,
+    const_labels: Option<HashMap<LabelId, String>>,
+    name: Option<String>,
+    unit: Option<String>,
+}
+
+impl MetricDescriptor {
+    /// constructor
+    pub fn new<Help: AsRef<str>>(
+        metric_type: MetricType,
+        metric_id: MetricId,
+        help: Help,
+    ) -> Self {
+        Self {
+            metric_type,
+            metric_id,
+            help: help.as_ref().to_string(),
+            label_ids: Vec::new(),
+            buckets: Vec::new(),
+            const_labels: None,
+            name: None,
+            unit: None,
+        }
+    }
+
+    /// set the human readable metric name, which is used to document the metric
+    pub fn with_name<Name: AsRef<str>>(mut self, name: Name) -> Self {
+        self.name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// set the metric unit, e.g., seconds, which is used to document the metric
+    pub fn with_unit<Unit: AsRef<str>>(mut self, unit: Unit) -> Self {
+        self.unit = Some(unit.as_ref().to_string());
+        self
+    }
+
+    /// set the variable labels
+    pub fn with_label_ids(mut self, label_ids: &[LabelId]) -> Self {
+        self.label_ids = label_ids.to_vec();
+        self
+    }
+
+    /// set the histogram buckets
+    pub fn with_buckets(mut self, buckets: Vec<f64>) -> Self {
+        self.buckets = buckets;
+        self
+    }
+
+    /// add a constant label
+    pub fn with_label<Value: AsRef<str>>(mut self, id: LabelId, value: Value) -> Self {
+        let mut const_labels = self.const_labels.take().unwrap_or_else(HashMap::new);
+        const_labels.insert(id, value.as_ref().to_string());
+        self.const_labels = Some(const_labels);
+        self
+    }
+
+    /// metric type
+    pub fn metric_type(&self) -> MetricType {
+        self.metric_type
+    }
+
+    /// MetricId
+    pub fn metric_id(&self) -> MetricId {
+        self.metric_id
+    }
+
+    /// returns the metric documentation
+    fn doc(&self) -> MetricDoc {
+        let mut doc = MetricDoc::new(self.metric_id).with_description(self.help.as_str());
+        if let Some(name) = self.name.as_ref() {
+            doc = doc.with_name(name);
+        }
+        if let Some(unit) = self.unit.as_ref() {
+            doc = doc.with_unit(unit);
+        }
+        doc
+    }
+
+    /// constructs the metric
+    fn build(&self) -> prometheus::Result<ArcCollector> {
+        let help = self.help.as_str();
+        let const_labels = self.const_labels.clone();
+        let collector = match self.metric_type {
+            MetricType::IntCounter => {
+                ArcCollector::new(new_int_counter(self.metric_id, help, const_labels)?)
+            }
+            MetricType::Counter => {
+                ArcCollector::new(new_counter(self.metric_id, help, const_labels)?)
+            }
+            MetricType::IntCounterVec => ArcCollector::new(new_int_counter_vec(
+                self.metric_id,
+                help,
+                &self.label_ids,
+                const_labels,
+            )?),
+            MetricType::CounterVec => ArcCollector::new(new_counter_vec(
+                self.metric_id,
+                help,
+                &self.label_ids,
+                const_labels,
+            )?),
+            MetricType::IntGauge => {
+                ArcCollector::new(new_int_gauge(self.metric_id, help, const_labels)?)
+            }
+            MetricType::Gauge => ArcCollector::new(new_gauge(self.metric_id, help, const_labels)?),
+            MetricType::IntGaugeVec => ArcCollector::new(new_int_gauge_vec(
+                self.metric_id,
+                help,
+                &self.label_ids,
+                const_labels,
+            )?),
+            MetricType::GaugeVec => ArcCollector::new(new_gauge_vec(
+                self.metric_id,
+                help,
+                &self.label_ids,
+                const_labels,
+            )?),
+            MetricType::Histogram => ArcCollector::new(new_histogram(
+                self.metric_id,
+                help,
+                self.buckets.clone(),
+                const_labels,
+            )?),
+            MetricType::HistogramVec => ArcCollector::new(new_histogram_vec(
+                self.metric_id,
+                help,
+                &self.label_ids,
+                self.buckets.clone(),
+                const_labels,
+            )?),
+        };
+        Ok(collector)
+    }
+}
+
 /// Tries to parse the descriptor name into a MetricId.
 /// - expected format: `M{ULID}`, e.g, `M01D3SF3R0DTBTVRKC9PFHQEEM9`
 /// - returns None, if the descriptor name is not a valid MetricId
@@ -804,6 +1012,8 @@ pub fn parse_desc_metric_id(desc: &prometheus::core::Desc) -> Option<MetricId> {
 pub struct MetricRegistry {
     registry: prometheus::Registry,
     metric_collectors: RwLock<Vec<ArcCollector>>,
+    metric_docs: RwLock<HashMap<MetricId, MetricDoc>>,
+    label_docs: RwLock<HashMap<LabelId, LabelDoc>>,
 }
 
 impl MetricRegistry {
@@ -930,9 +1140,90 @@ impl MetricRegistry {
         let collector = ArcCollector::new(collector);
         self.registry.register(Box::new(collector.clone()))?;
         metric_collectors.push(collector.clone());
+        self.record_docs(&collector);
         Ok(collector)
     }
 
+    /// Populates the metric docs from the collector's descriptors
+    /// - the description is set from the descriptor help, unless it was already documented
+    /// - descriptors that are not named using a MetricId are skipped
+    fn record_docs(&self, collector: &ArcCollector) {
+        let mut metric_docs = self.metric_docs.write();
+        for desc in collector.desc() {
+            if let Some(metric_id) = parse_desc_metric_id(desc) {
+                let doc = metric_docs
+                    .entry(metric_id)
+                    .or_insert_with(|| MetricDoc::new(metric_id));
+                if doc.description.is_empty() {
+                    doc.description = desc.help.clone();
+                }
+                doc.labels = desc
+                    .const_label_pairs
+                    .iter()
+                    .map(prometheus::proto::LabelPair::get_name)
+                    .chain(desc.variable_labels.iter().map(String::as_str))
+                    .filter_map(|label| label.parse().ok())
+                    .map(LabelDoc::new)
+                    .collect();
+            }
+        }
+    }
+
+    /// Documents the metric, i.e., maps the MetricId to a human readable name, description, and unit
+    /// - the metric can be documented before or after it is registered
+    /// - fields that are not set on the doc are retained from the existing doc
+    pub fn document_metric(&self, doc: MetricDoc) {
+        let mut metric_docs = self.metric_docs.write();
+        match metric_docs.get_mut(&doc.metric_id) {
+            Some(existing) => {
+                if doc.name.is_some() {
+                    existing.name = doc.name;
+                }
+                if !doc.description.is_empty() {
+                    existing.description = doc.description;
+                }
+                if doc.unit.is_some() {
+                    existing.unit = doc.unit;
+                }
+            }
+            None => {
+                metric_docs.insert(doc.metric_id, doc);
+            }
+        }
+    }
+
+    /// Documents the label, i.e., maps the LabelId to a human readable name and description
+    pub fn document_label(&self, doc: LabelDoc) {
+        self.label_docs.write().insert(doc.label_id, doc);
+    }
+
+    /// Returns the docs for the registered metrics, sorted by MetricId
+    /// - label docs are resolved from the documented labels
+    pub fn describe(&self) -> Vec<MetricDoc> {
+        let metric_ids: HashSet<MetricId> = self
+            .descs()
+            .iter()
+            .filter_map(parse_desc_metric_id)
+            .collect();
+        let metric_docs = self.metric_docs.read();
+        let label_docs = self.label_docs.read();
+        let mut docs: Vec<MetricDoc> = metric_docs
+            .values()
+            .filter(|doc| metric_ids.contains(&doc.metric_id))
+            .cloned()
+            .map(|mut doc| {
+                for label in doc.labels.iter_mut() {
+                    if let Some(label_doc) = label_docs.get(&label.label_id) {
+                        *label = label_doc.clone();
+                    }
+                }
+                doc
+            })
+            .collect();
+        docs.sort_by_key(|doc| doc.metric_id);
+        docs
+    }
+
     /// Collects descriptors for registered metrics
     pub fn descs(&self) -> Vec<prometheus::core::Desc> {
         let metric_collectors = self.metric_collectors.read();
@@ -1192,6 +1483,61 @@ impl MetricRegistry {
         Ok(metric)
     }
 
+    /// Registers the metrics in bulk.
+    ///
+    /// Registration is atomic - if any metric fails to be constructed or registered, then the
+    /// metrics that were already registered as part of this batch are unregistered, and the error
+    /// is returned.
+    pub fn register_all(
+        &self,
+        descriptors: &[MetricDescriptor],
+    ) -> prometheus::Result<Vec<ArcCollector>> {
+        // all metrics are constructed up front, i.e., descriptor validation errors are caught before
+        // any metrics are registered
+        let collectors = descriptors
+            .iter()
+            .map(MetricDescriptor::build)
+            .collect::<prometheus::Result<Vec<_>>>()?;
+
+        let mut registered = Vec::with_capacity(collectors.len());
+        for collector in collectors {
+            match self.register(collector) {
+                Ok(collector) => registered.push(collector),
+                Err(err) => {
+                    for collector in registered {
+                        if let Err(err) = self.unregister(collector) {
+                            warn!("Failed to roll back metric registration: {}", err);
+                        }
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        for descriptor in descriptors {
+            self.document_metric(descriptor.doc());
+        }
+        Ok(registered)
+    }
+
+    /// Unregisters the metrics Collector.
+    /// - collectors are matched on their descriptor IDs
+    /// - returns an error if the collector is not registered
+    pub fn unregister(
+        &self,
+        collector: impl prometheus::core::Collector + 'static,
+    ) -> prometheus::Result<()> {
+        let mut metric_collectors = self.metric_collectors.write();
+        let desc_ids: HashSet<DescId> = collector.desc().iter().map(|desc| desc.id).collect();
+        self.registry.unregister(Box::new(collector))?;
+        metric_collectors.retain(|registered| {
+            !registered
+                .desc()
+                .iter()
+                .any(|desc| desc_ids.contains(&desc.id))
+        });
+        Ok(())
+    }
+
     fn check_help<Help: AsRef<str>>(help: Help) -> Result<String, prometheus::Error> {
         let help = help.as_ref().trim();
         if help.is_empty() {
@@ -1304,10 +1650,28 @@ impl MetricRegistry {
     }
 
     /// Text encodes a snapshot of the current metrics
+    /// - documented metrics are preceded by `# NAME`, `# UNIT`, and `# LABEL` comments, which are
+    ///   ignored by prometheus
     pub fn text_encode_metrics<W: Write>(&self, writer: &mut W) -> prometheus::Result<()> {
         let metric_families = self.registry.gather();
         let encoder = prometheus::TextEncoder::new();
-        encoder.encode(&metric_families, writer)
+        let docs: HashMap<MetricId, MetricDoc> = self
+            .describe()
+            .into_iter()
+            .map(|doc| (doc.metric_id, doc))
+            .collect();
+        for metric_family in metric_families {
+            let doc = metric_family
+                .get_name()
+                .parse::<MetricId>()
+                .ok()
+                .and_then(|metric_id| docs.get(&metric_id));
+            if let Some(doc) = doc {
+                doc.text_encode(writer)?;
+            }
+            encoder.encode(&[metric_family], writer)?;
+        }
+        Ok(())
     }
 
     /// gathers metrics from all registered metric collectors
@@ -1485,6 +1849,70 @@ impl MetricRegistry {
         self.gather_for_desc_names(&metric_names)
     }
 
+    /// Computes approximate quantiles for the histogram registered with the specified MetricId.
+    /// - returns a `(quantile, value)` pair for each of the specified quantiles
+    /// - for histogram vectors, the buckets are aggregated across all label values
+    /// - returns None if no histogram is registered for the MetricId or if no observations have been
+    ///   recorded
+    ///
+    /// See [histogram_quantiles()](fn.histogram_quantiles.html) for how the values are computed.
+    pub fn histogram_quantiles(
+        &self,
+        metric_id: MetricId,
+        quantiles: &[f64],
+    ) -> Option<Vec<(f64, f64)>> {
+        let buckets = self.histogram_buckets(metric_id)?;
+        if buckets.last().map_or(true, |(_, count)| *count == 0) {
+            return None;
+        }
+        Some(
+            quantiles
+                .iter()
+                .map(|q| (*q, bucket_quantile(*q, &buckets)))
+                .collect(),
+        )
+    }
+
+    /// Returns the histogram's (upper bound, cumulative count) buckets sorted by upper bound, where
+    /// the last bucket is the `+Inf` bucket
+    /// - for histogram vectors, the buckets are aggregated across all label values
+    /// - returns None if no histogram is registered for the MetricId
+    fn histogram_buckets(&self, metric_id: MetricId) -> Option<Vec<(f64, u64)>> {
+        let mfs = self.gather_for_metric_ids(&[metric_id]);
+        let histograms = mfs
+            .iter()
+            .filter(|mf| mf.get_field_type() == prometheus::proto::MetricType::HISTOGRAM)
+            .flat_map(|mf| mf.get_metric().iter())
+            .map(prometheus::proto::Metric::get_histogram)
+            .collect::<Vec<_>>();
+        if histograms.is_empty() {
+            return None;
+        }
+
+        // (upper bound, cumulative count)
+        let mut buckets: Vec<(f64, u64)> = vec![];
+        let mut sample_count = 0;
+        for histogram in histograms {
+            sample_count += histogram.get_sample_count();
+            for bucket in histogram.get_bucket() {
+                let upper_bound = bucket.get_upper_bound();
+                match buckets.iter_mut().find(|(bound, _)| *bound == upper_bound) {
+                    Some((_, count)) => *count += bucket.get_cumulative_count(),
+                    None => buckets.push((upper_bound, bucket.get_cumulative_count())),
+                }
+            }
+        }
+        buckets.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        // the +Inf bucket is implied by the sample count
+        if buckets
+            .last()
+            .map_or(true, |(bound, _)| *bound != std::f64::INFINITY)
+        {
+            buckets.push((std::f64::INFINITY, sample_count));
+        }
+        Some(buckets)
+    }
+
     /// Gathers process related metrics
     pub fn gather_process_metrics(&self) -> ProcessMetrics {
         let collectors = self.metric_collectors.read();
@@ -1496,6 +1924,8 @@ impl MetricRegistry {
         let registry = Self {
             registry: prometheus::Registry::new(),
             metric_collectors: RwLock::new(Vec::new()),
+            metric_docs: RwLock::new(HashMap::new()),
+            label_docs: RwLock::new(HashMap::new()),
         };
 
         registry
@@ -1512,6 +1942,149 @@ impl fmt::Debug for MetricRegistry {
     }
 }
 
+/// Returns the docs for the metrics that are registered with the global registry
+pub fn describe() -> Vec<MetricDoc> {
+    registry().describe()
+}
+
+/// Human readable metric documentation, which maps the opaque MetricId to a name, description, and unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDoc {
+    metric_id: MetricId,
+    name: Option<String>,
+    description: String,
+    unit: Option<String>,
+    labels: Vec<LabelDoc>,
+}
+
+impl MetricDoc {
+    /// constructor
+    pub fn new(metric_id: MetricId) -> Self {
+        Self {
+            metric_id,
+            name: None,
+            description: String::new(),
+            unit: None,
+            labels: Vec::new(),
+        }
+    }
+
+    /// set the human readable metric name
+    pub fn with_name<Name: AsRef<str>>(mut self, name: Name) -> Self {
+        self.name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// set the metric description
+    pub fn with_description<Description: AsRef<str>>(mut self, description: Description) -> Self {
+        self.description = description.as_ref().to_string();
+        self
+    }
+
+    /// set the metric unit
+    pub fn with_unit<Unit: AsRef<str>>(mut self, unit: Unit) -> Self {
+        self.unit = Some(unit.as_ref().to_string());
+        self
+    }
+
+    /// MetricId
+    pub fn metric_id(&self) -> MetricId {
+        self.metric_id
+    }
+
+    /// human readable metric name
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    /// metric description, which defaults to the metric help
+    pub fn description(&self) -> &str {
+        self.description.as_str()
+    }
+
+    /// metric unit
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_ref().map(String::as_str)
+    }
+
+    /// the metric's constant and variable labels
+    pub fn labels(&self) -> &[LabelDoc] {
+        &self.labels
+    }
+
+    /// writes the doc as prometheus text format comments
+    fn text_encode<W: Write>(&self, writer: &mut W) -> prometheus::Result<()> {
+        if let Some(name) = self.name.as_ref() {
+            writeln!(writer, "# NAME {} {}", self.metric_id, escape_doc(name))?;
+        }
+        if let Some(unit) = self.unit.as_ref() {
+            writeln!(writer, "# UNIT {} {}", self.metric_id, escape_doc(unit))?;
+        }
+        for label in self.labels.iter().filter(|label| label.name.is_some()) {
+            write!(writer, "# LABEL {} {}", self.metric_id, label.label_id)?;
+            if let Some(name) = label.name.as_ref() {
+                write!(writer, " {}", escape_doc(name))?;
+            }
+            if let Some(description) = label.description.as_ref() {
+                write!(writer, " - {}", escape_doc(description))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Human readable label documentation, which maps the opaque LabelId to a name and description
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelDoc {
+    label_id: LabelId,
+    name: Option<String>,
+    description: Option<String>,
+}
+
+impl LabelDoc {
+    /// constructor
+    pub fn new(label_id: LabelId) -> Self {
+        Self {
+            label_id,
+            name: None,
+            description: None,
+        }
+    }
+
+    /// set the human readable label name
+    pub fn with_name<Name: AsRef<str>>(mut self, name: Name) -> Self {
+        self.name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// set the label description
+    pub fn with_description<Description: AsRef<str>>(mut self, description: Description) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+
+    /// LabelId
+    pub fn label_id(&self) -> LabelId {
+        self.label_id
+    }
+
+    /// human readable label name
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    /// label description
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(String::as_str)
+    }
+}
+
+/// doc comments must be single line
+fn escape_doc(doc: &str) -> String {
+    doc.replace('\\', r"\\").replace('\n', r"\n")
+}
+
 /// Label Id
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct LabelId(pub u128);
@@ -1828,6 +2401,255 @@ pub fn timer_buckets(durations: Vec<Duration>) -> Result<Vec<f64>, prometheus::E
         .collect::<Vec<_>>())
 }
 
+/// Computes approximate quantiles for the histogram registered with the specified MetricId within
+/// the global registry.
+/// - the quantile value is linearly interpolated within the bucket that the quantile falls into,
+///   which is the same approach that prometheus' `histogram_quantile()` uses
+/// - the lower bound of the first bucket is assumed to be 0, unless its upper bound is negative
+/// - if the quantile falls into the `+Inf` bucket, then the upper bound of the highest finite bucket
+///   is returned
+/// - quantiles < 0 map to `-Inf` and quantiles > 1 map to `+Inf`
+/// - returns None if no histogram is registered for the MetricId or if no observations have been
+///   recorded
+pub fn histogram_quantiles(metric_id: MetricId, quantiles: &[f64]) -> Option<Vec<(f64, f64)>> {
+    registry().histogram_quantiles(metric_id, quantiles)
+}
+
+/// Returns the number of SLA breaches that have been detected for the histogram MetricId
+pub fn sla_breach_count(metric_id: MetricId) -> u64 {
+    SLA_BREACH_COUNT
+        .with_label_values(&[metric_id.name().as_str()])
+        .get() as u64
+}
+
+/// Detects SLA breaches by periodically sampling a histogram, e.g., a message processing timer.
+///
+/// Each time the histogram is sampled, the configured quantile is computed over the observations that
+/// were recorded since the previous sample, i.e., the sampling interval is the SLA window. If the
+/// quantile value exceeds the threshold, then:
+/// - the [SLA_BREACH_COUNT_METRIC_ID](constant.SLA_BREACH_COUNT_METRIC_ID.html) counter is incremented
+/// - the breach callback is invoked, if one is configured
+///
+/// The histogram is sampled by a task running on the specified [Executor](../concurrent/execution/struct.Executor.html),
+/// which sleeps between samples using a [timer::delay_for()](../concurrent/timer/fn.delay_for.html),
+/// i.e., no thread is dedicated to the monitor.
+pub struct SlaMonitor {
+    metric_id: MetricId,
+    quantile: f64,
+    threshold: f64,
+    interval: Duration,
+    on_breach: Option<Box<dyn FnMut(SlaBreach) + Send>>,
+}
+
+impl SlaMonitor {
+    /// constructor
+    /// - quantile, e.g., 0.95 for p95
+    /// - threshold is the max allowed quantile value. Timer histograms record their values in secs,
+    ///   see [duration_as_secs_f64](fn.duration_as_secs_f64.html)
+    /// - interval is how often the histogram is sampled
+    pub fn new(metric_id: MetricId, quantile: f64, threshold: f64, interval: Duration) -> Self {
+        Self {
+            metric_id,
+            quantile,
+            threshold,
+            interval,
+            on_breach: None,
+        }
+    }
+
+    /// Sets the callback that is invoked when a breach is detected
+    pub fn on_breach<F>(mut self, on_breach: F) -> Self
+    where
+        F: FnMut(SlaBreach) + Send + 'static,
+    {
+        self.on_breach = Some(Box::new(on_breach));
+        self
+    }
+
+    /// Starts monitoring the histogram
+    /// - the monitor runs until the returned handle is stopped or dropped
+    pub fn start(self, mut executor: Executor) -> Result<SlaMonitorHandle, SpawnError> {
+        let SlaMonitor {
+            metric_id,
+            quantile,
+            threshold,
+            interval,
+            mut on_breach,
+        } = self;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let monitor_stopped = stopped.clone();
+        let mut prev_buckets = registry().histogram_buckets(metric_id);
+        executor.spawn(async move {
+            let breach_count = SLA_BREACH_COUNT.with_label_values(&[metric_id.name().as_str()]);
+            loop {
+                await!(timer::delay_for(interval));
+                if monitor_stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let buckets = registry().histogram_buckets(metric_id);
+                if let Some(buckets) = buckets.as_ref() {
+                    let window = match prev_buckets.as_ref() {
+                        Some(prev_buckets) => window_buckets(prev_buckets, buckets),
+                        None => buckets.clone(),
+                    };
+                    if window.last().map_or(false, |(_, count)| *count > 0) {
+                        let value = bucket_quantile(quantile, &window);
+                        if value > threshold {
+                            breach_count.inc();
+                            let breach = SlaBreach {
+                                metric_id,
+                                quantile,
+                                threshold,
+                                value,
+                            };
+                            warn!("{:?}", breach);
+                            if let Some(on_breach) = on_breach.as_mut() {
+                                on_breach(breach);
+                            }
+                        }
+                    }
+                }
+                prev_buckets = buckets;
+            }
+            debug!("SlaMonitor({}) is stopped", metric_id);
+        })?;
+        Ok(SlaMonitorHandle { metric_id, stopped })
+    }
+}
+
+impl fmt::Debug for SlaMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SlaMonitor(metric_id: {}, quantile: {}, threshold: {}, interval: {:?})",
+            self.metric_id, self.quantile, self.threshold, self.interval
+        )
+    }
+}
+
+/// Returns the buckets for the observations that were recorded between the 2 samples
+/// - if the bucket layout changed, then the current buckets are returned
+fn window_buckets(prev: &[(f64, u64)], current: &[(f64, u64)]) -> Vec<(f64, u64)> {
+    if prev.len() != current.len()
+        || prev
+            .iter()
+            .zip(current.iter())
+            .any(|((prev_bound, _), (bound, _))| prev_bound != bound)
+    {
+        return current.to_vec();
+    }
+    prev.iter()
+        .zip(current.iter())
+        .map(|((_, prev_count), (bound, count))| (*bound, count.saturating_sub(*prev_count)))
+        .collect()
+}
+
+/// SLA breach that was detected by an [SlaMonitor](struct.SlaMonitor.html)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlaBreach {
+    metric_id: MetricId,
+    quantile: f64,
+    threshold: f64,
+    value: f64,
+}
+
+impl SlaBreach {
+    /// the monitored histogram MetricId
+    pub fn metric_id(&self) -> MetricId {
+        self.metric_id
+    }
+
+    /// the monitored quantile
+    pub fn quantile(&self) -> f64 {
+        self.quantile
+    }
+
+    /// the SLA threshold
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// the quantile value that exceeded the threshold
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+/// SlaMonitor handle, which is used to stop the monitor
+/// - the monitor is stopped when the handle is dropped
+#[derive(Debug)]
+pub struct SlaMonitorHandle {
+    metric_id: MetricId,
+    stopped: Arc<AtomicBool>,
+}
+
+impl SlaMonitorHandle {
+    /// the monitored histogram MetricId
+    pub fn metric_id(&self) -> MetricId {
+        self.metric_id
+    }
+
+    /// Stops the monitor
+    /// - the monitor task exits when it wakes up for its next sample
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if the monitor has been signalled to stop
+    pub fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for SlaMonitorHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// - `buckets` are (upper bound, cumulative count) pairs sorted by upper bound, where the last bucket
+///   is the `+Inf` bucket
+fn bucket_quantile(q: f64, buckets: &[(f64, u64)]) -> f64 {
+    if q.is_nan() {
+        return std::f64::NAN;
+    }
+    if q < 0.0 {
+        return std::f64::NEG_INFINITY;
+    }
+    if q > 1.0 {
+        return std::f64::INFINITY;
+    }
+    let sample_count = buckets.last().map_or(0, |(_, count)| *count);
+    if buckets.len() < 2 || sample_count == 0 {
+        return std::f64::NAN;
+    }
+
+    let rank = q * sample_count as f64;
+    let i = buckets
+        .iter()
+        .position(|(_, count)| *count as f64 >= rank)
+        .unwrap_or(buckets.len() - 1);
+    if i == buckets.len() - 1 {
+        // the quantile falls into the +Inf bucket
+        return buckets[buckets.len() - 2].0;
+    }
+
+    let (upper_bound, count) = buckets[i];
+    let (lower_bound, prev_count) = if i == 0 {
+        if upper_bound <= 0.0 {
+            return upper_bound;
+        }
+        (0.0, 0)
+    } else {
+        buckets[i - 1]
+    };
+    let bucket_count = count - prev_count;
+    if bucket_count == 0 {
+        return lower_bound;
+    }
+    lower_bound + (upper_bound - lower_bound) * ((rank - prev_count as f64) / bucket_count as f64)
+}
+
 #[allow(warnings)]
 #[cfg(test)]
 mod tests;
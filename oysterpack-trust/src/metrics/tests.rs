@@ -1139,3 +1139,260 @@ fn timer_buckets() {
     println!("timer_buckets(vec![])-> {:?}", result);
     assert!(result.is_err());
 }
+
+#[test]
+fn histogram_quantiles() {
+    configure_logging();
+
+    let metric_id = MetricId::generate();
+    let histogram = registry()
+        .register_histogram(
+            metric_id,
+            "histogram quantiles",
+            vec![1.0, 2.0, 3.0, 4.0, 5.0],
+            None,
+        )
+        .unwrap();
+
+    // empty histograms do not report quantiles
+    assert!(super::histogram_quantiles(metric_id, &[0.5]).is_none());
+    // unregistered metrics do not report quantiles
+    assert!(super::histogram_quantiles(MetricId::generate(), &[0.5]).is_none());
+
+    // GIVEN: a known distribution, i.e., 10 observations per bucket
+    for i in 0..50 {
+        histogram.observe(f64::from(i % 5) + 0.5);
+    }
+    // WHEN: quantiles are computed
+    let quantiles = super::histogram_quantiles(metric_id, &[0.5, 0.95, 0.99]).unwrap();
+    info!("quantiles: {:?}", quantiles);
+    // THEN: p50 falls within the (2.0, 3.0] bucket
+    let (q, p50) = quantiles[0];
+    assert_eq!(q, 0.5);
+    assert!(p50 > 2.0 && p50 <= 3.0, "p50 = {}", p50);
+    // THEN: p95 and p99 fall within the (4.0, 5.0] bucket
+    let (_, p95) = quantiles[1];
+    assert!(p95 > 4.0 && p95 <= 5.0, "p95 = {}", p95);
+    let (_, p99) = quantiles[2];
+    assert!(p99 > 4.0 && p99 <= 5.0, "p99 = {}", p99);
+
+    // GIVEN: observations that fall into the +Inf bucket
+    for _ in 0..1000 {
+        histogram.observe(100.0);
+    }
+    // THEN: quantiles that fall into the +Inf bucket report the highest finite bucket upper bound
+    let quantiles = super::histogram_quantiles(metric_id, &[0.99]).unwrap();
+    assert_eq!(quantiles[0].1, 5.0);
+}
+
+#[test]
+fn register_all() {
+    configure_logging();
+
+    // GIVEN: a mixed batch of metric descriptors
+    let label_id = LabelId::generate();
+    let descriptors = vec![
+        MetricDescriptor::new(MetricType::IntCounter, MetricId::generate(), "int counter"),
+        MetricDescriptor::new(MetricType::GaugeVec, MetricId::generate(), "gauge vec")
+            .with_label_ids(&[label_id]),
+        MetricDescriptor::new(MetricType::Histogram, MetricId::generate(), "histogram")
+            .with_buckets(vec![0.1, 0.5, 1.0])
+            .with_label(label_id, "const"),
+    ];
+    let metric_ids: Vec<MetricId> = descriptors.iter().map(MetricDescriptor::metric_id).collect();
+
+    // WHEN: the metrics are registered in bulk
+    let collectors = registry().register_all(&descriptors).unwrap();
+    // THEN: all of the metrics are registered
+    assert_eq!(collectors.len(), 3);
+    for metric_id in metric_ids.iter() {
+        assert_eq!(registry().descs_for_metric_id(*metric_id).len(), 1);
+    }
+
+    // GIVEN: a batch where the last metric conflicts with an already registered metric
+    let new_metric_id = MetricId::generate();
+    let descriptors = vec![
+        MetricDescriptor::new(MetricType::IntGauge, new_metric_id, "int gauge"),
+        MetricDescriptor::new(MetricType::IntCounter, metric_ids[0], "int counter"),
+    ];
+    // WHEN: the metrics are registered in bulk
+    let result = registry().register_all(&descriptors);
+    // THEN: registration fails
+    assert!(result.is_err());
+    // AND: the metrics that were registered as part of the batch are rolled back
+    assert!(registry().descs_for_metric_id(new_metric_id).is_empty());
+    // AND: the batch can be registered once the conflict is removed
+    let collectors = registry().register_all(&descriptors[..1]).unwrap();
+    assert_eq!(collectors.len(), 1);
+    assert_eq!(registry().descs_for_metric_id(new_metric_id).len(), 1);
+
+    // GIVEN: a batch with an invalid descriptor, i.e., a vector metric without variable labels
+    let descriptors = vec![
+        MetricDescriptor::new(MetricType::IntGauge, MetricId::generate(), "int gauge"),
+        MetricDescriptor::new(MetricType::IntCounterVec, MetricId::generate(), "int counter vec"),
+    ];
+    // THEN: none of the metrics are registered
+    assert!(registry().register_all(&descriptors).is_err());
+    assert!(registry()
+        .descs_for_metric_id(descriptors[0].metric_id())
+        .is_empty());
+}
+
+#[test]
+fn sla_monitor() {
+    use crate::concurrent::{
+        execution::global_executor,
+        messaging::reqrep::{FutureReply, Processor, ReqRepConfig, ReqRepId},
+    };
+    use futures::{channel::mpsc, future::FutureExt, stream::StreamExt};
+    use std::time::Instant;
+
+    configure_logging();
+
+    // GIVEN: a processor that records its processing time and is too slow to meet the SLA
+    let metric_id = MetricId::generate();
+    let timer = registry()
+        .register_histogram(
+            metric_id,
+            "sla monitor timer",
+            timer_buckets(vec![
+                Duration::from_millis(1),
+                Duration::from_millis(5),
+                Duration::from_millis(10),
+                Duration::from_millis(50),
+            ])
+            .unwrap(),
+            None,
+        )
+        .unwrap();
+
+    struct SlowProcessor(prometheus::Histogram);
+    impl Processor<(), ()> for SlowProcessor {
+        fn process(&mut self, _req: ()) -> FutureReply<()> {
+            let start = Instant::now();
+            thread::sleep(Duration::from_millis(20));
+            self.0.observe(duration_as_secs_f64(start.elapsed()));
+            async {}.boxed()
+        }
+    }
+
+    // GIVEN: an SlaMonitor that requires p95 <= 5 ms
+    let (breach_tx, mut breach_rx) = mpsc::unbounded();
+    let monitor = SlaMonitor::new(
+        metric_id,
+        0.95,
+        duration_as_secs_f64(Duration::from_millis(5)),
+        Duration::from_millis(50),
+    )
+    .on_breach(move |breach| {
+        let _ = breach_tx.unbounded_send(breach);
+    })
+    .start(global_executor())
+    .unwrap();
+    assert_eq!(sla_breach_count(metric_id), 0);
+
+    // WHEN: requests are processed
+    let mut executor = global_executor();
+    let mut client = ReqRepConfig::new(ReqRepId::generate(), vec![0.01, 0.1])
+        .start_service(SlowProcessor(timer), executor.clone())
+        .unwrap();
+    executor.run(async move {
+        for _ in 0..5 {
+            await!(client.send_recv(())).unwrap();
+        }
+    });
+
+    // THEN: the breach is detected
+    let breach = executor.run(breach_rx.next()).unwrap();
+    info!("{:?}", breach);
+    assert_eq!(breach.metric_id(), metric_id);
+    assert!(breach.value() > breach.threshold());
+    // AND: the breach counter is incremented
+    assert!(sla_breach_count(metric_id) > 0);
+
+    // WHEN: the monitor is stopped
+    monitor.stop();
+    assert!(monitor.stopped());
+}
+
+#[test]
+fn metric_docs() {
+    configure_logging();
+
+    let registry = MetricRegistry::default();
+    let metric_id = MetricId::generate();
+    let label_id = LabelId::generate();
+
+    // GIVEN: a label that is documented
+    registry.document_label(
+        LabelDoc::new(label_id)
+            .with_name("endpoint")
+            .with_description("service endpoint"),
+    );
+    // WHEN: a metric is registered with a description
+    let timer = registry
+        .register_histogram_vec(
+            metric_id,
+            "Request processing time",
+            &[label_id],
+            vec![0.01, 0.1],
+            None,
+        )
+        .unwrap();
+    timer.with_label_values(&["ping"]).observe(0.05);
+    // AND: the metric is documented with a name and unit
+    registry.document_metric(
+        MetricDoc::new(metric_id)
+            .with_name("request latency")
+            .with_unit("seconds"),
+    );
+
+    // THEN: the metric appears in describe()
+    let docs = registry.describe();
+    info!("{:#?}", docs);
+    let doc = docs
+        .iter()
+        .find(|doc| doc.metric_id() == metric_id)
+        .unwrap();
+    // AND: the description is populated from the help
+    assert_eq!(doc.description(), "Request processing time");
+    assert_eq!(doc.name(), Some("request latency"));
+    assert_eq!(doc.unit(), Some("seconds"));
+    // AND: the label doc is resolved
+    assert_eq!(doc.labels().len(), 1);
+    assert_eq!(doc.labels()[0].label_id(), label_id);
+    assert_eq!(doc.labels()[0].name(), Some("endpoint"));
+    assert_eq!(doc.labels()[0].description(), Some("service endpoint"));
+
+    // THEN: the docs appear in the text output
+    let mut text = Vec::new();
+    registry.text_encode_metrics(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    info!("{}", text);
+    assert!(text.contains(&format!("# NAME {} request latency", metric_id)));
+    assert!(text.contains(&format!("# UNIT {} seconds", metric_id)));
+    assert!(text.contains(&format!(
+        "# LABEL {} {} endpoint - service endpoint",
+        metric_id, label_id
+    )));
+    assert!(text.contains(&format!("# HELP {} Request processing time", metric_id)));
+
+    // WHEN: the metric is declared with a name and unit via a MetricDescriptor
+    let metric_id = MetricId::generate();
+    registry
+        .register_all(&[
+            MetricDescriptor::new(MetricType::IntCounter, metric_id, "Requests received")
+                .with_name("requests")
+                .with_unit("requests"),
+        ])
+        .unwrap();
+    // THEN: it is documented when it is registered
+    let doc = registry
+        .describe()
+        .into_iter()
+        .find(|doc| doc.metric_id() == metric_id)
+        .unwrap();
+    assert_eq!(doc.name(), Some("requests"));
+    assert_eq!(doc.unit(), Some("requests"));
+    assert_eq!(doc.description(), "Requests received");
+}
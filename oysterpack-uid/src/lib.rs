@@ -66,6 +66,8 @@
 //! let domain_ulid = FOO_EVENT_ID.as_domain_ulid();
 //! ```
 
+// const_ulid_u128() reads the ULID str bytes in a const fn
+#![feature(const_str_as_bytes, const_slice_len)]
 #![deny(missing_docs, missing_debug_implementations)]
 #![doc(html_root_url = "https://docs.rs/oysterpack_uid/0.2.3")]
 
@@ -79,7 +81,8 @@ pub mod macros {
 pub mod ulid;
 
 pub use crate::ulid::{
-    ulid_str, ulid_str_into_u128, ulid_u128, ulid_u128_into_string, DecodingError, ULID,
+    const_ulid_u128, ulid_str, ulid_str_into_u128, ulid_u128, ulid_u128_into_string,
+    DecodingError, TimestampError, DEFAULT_TIMESTAMP_TOLERANCE, ULID,
 };
 
 pub use crate::ulid::domain::{Domain, DomainId, DomainULID, HasDomain};
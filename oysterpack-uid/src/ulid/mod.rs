@@ -24,10 +24,14 @@ use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::{fmt, str::FromStr};
+use std::{fmt, str::FromStr, time::Duration};
 
 pub(crate) mod domain;
 
+/// Default tolerance that is applied by [ULID::deserialize_strict()](struct.ULID.html#method.deserialize_strict)
+/// - 1 day
+pub const DEFAULT_TIMESTAMP_TOLERANCE: Duration = Duration::from_secs(60 * 60 * 24);
+
 /// Returns a new ULID encoded as a String.
 pub fn ulid_str() -> String {
     rusty_ulid::generate_ulid_string()
@@ -50,6 +54,105 @@ pub fn ulid_u128_into_string(ulid: u128) -> String {
     rusty_ulid::Ulid::from(ulid).to_string()
 }
 
+/// Crockford base32 decoding table, which is indexed by the ASCII character
+/// - characters that are not part of the ULID alphabet decode to 0xFF
+const CROCKFORD_DECODING: [u8; 256] = [
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11, 0xFF, 0x12, 0x13, 0xFF, 0x14, 0x15, 0xFF,
+    0x16, 0x17, 0x18, 0x19, 0x1A, 0xFF, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11, 0xFF, 0x12, 0x13, 0xFF, 0x14, 0x15, 0xFF,
+    0x16, 0x17, 0x18, 0x19, 0x1A, 0xFF, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+/// Converts a ULID string representation into u128 at compile time, e.g., to define ULID constants
+/// using the human readable ULID string
+/// - ULID strings are case insensitive
+/// - when used to initialize a constant, an invalid ULID string fails compilation, i.e., const
+///   evaluation fails
+/// - invalid ULID strings panic at runtime - use [ulid_str_into_u128()](fn.ulid_str_into_u128.html)
+///   to handle untrusted input
+///
+/// ```rust
+/// # use oysterpack_uid::*;
+/// const ID: u128 = const_ulid_u128("01D882HDAHNQSE2MXX05WVM06G");
+/// assert_eq!(ID, ulid_str_into_u128("01D882HDAHNQSE2MXX05WVM06G").unwrap());
+/// ```
+///
+/// The following only differ from the above by the ULID string. They are required to fail const
+/// evaluation (E0080), i.e., they do not pass by failing to compile for any other reason.
+///
+/// ```rust,compile_fail,E0080
+/// # use oysterpack_uid::*;
+/// // 'U' is not part of the ULID alphabet
+/// const ID: u128 = const_ulid_u128("01D882HDAHNQSE2MXX05WVM06U");
+/// assert_eq!(ID, ulid_str_into_u128("01D882HDAHNQSE2MXX05WVM06G").unwrap());
+/// ```
+///
+/// ```rust,compile_fail,E0080
+/// # use oysterpack_uid::*;
+/// // ULID strings are 26 characters
+/// const ID: u128 = const_ulid_u128("01D882HDAHNQSE2MXX05WVM06");
+/// assert_eq!(ID, ulid_str_into_u128("01D882HDAHNQSE2MXX05WVM06G").unwrap());
+/// ```
+pub const fn const_ulid_u128(ulid: &str) -> u128 {
+    let bytes = ulid.as_bytes();
+    // indexing the single element array with 1 fails const evaluation, i.e., it is used as a
+    // compile time assert
+    [()][(bytes.len() != 26) as usize];
+    let digits_or = digit(bytes, 0) | digit(bytes, 1) | digit(bytes, 2) | digit(bytes, 3)
+        | digit(bytes, 4) | digit(bytes, 5) | digit(bytes, 6) | digit(bytes, 7) | digit(bytes, 8)
+        | digit(bytes, 9) | digit(bytes, 10) | digit(bytes, 11) | digit(bytes, 12)
+        | digit(bytes, 13) | digit(bytes, 14) | digit(bytes, 15) | digit(bytes, 16)
+        | digit(bytes, 17) | digit(bytes, 18) | digit(bytes, 19) | digit(bytes, 20)
+        | digit(bytes, 21) | digit(bytes, 22) | digit(bytes, 23) | digit(bytes, 24)
+        | digit(bytes, 25);
+    // invalid characters decode to 0xFF, and the first character must fit into the 3 most
+    // significant bits
+    [()][(((digits_or & 0xE0) != 0) | (digit(bytes, 0) > 7)) as usize];
+    (digit(bytes, 0) as u128) << 125
+        | (digit(bytes, 1) as u128) << 120
+        | (digit(bytes, 2) as u128) << 115
+        | (digit(bytes, 3) as u128) << 110
+        | (digit(bytes, 4) as u128) << 105
+        | (digit(bytes, 5) as u128) << 100
+        | (digit(bytes, 6) as u128) << 95
+        | (digit(bytes, 7) as u128) << 90
+        | (digit(bytes, 8) as u128) << 85
+        | (digit(bytes, 9) as u128) << 80
+        | (digit(bytes, 10) as u128) << 75
+        | (digit(bytes, 11) as u128) << 70
+        | (digit(bytes, 12) as u128) << 65
+        | (digit(bytes, 13) as u128) << 60
+        | (digit(bytes, 14) as u128) << 55
+        | (digit(bytes, 15) as u128) << 50
+        | (digit(bytes, 16) as u128) << 45
+        | (digit(bytes, 17) as u128) << 40
+        | (digit(bytes, 18) as u128) << 35
+        | (digit(bytes, 19) as u128) << 30
+        | (digit(bytes, 20) as u128) << 25
+        | (digit(bytes, 21) as u128) << 20
+        | (digit(bytes, 22) as u128) << 15
+        | (digit(bytes, 23) as u128) << 10
+        | (digit(bytes, 24) as u128) << 5
+        | digit(bytes, 25) as u128
+}
+
+/// decodes the Crockford base32 character at the specified index
+const fn digit(bytes: &[u8], index: usize) -> u8 {
+    CROCKFORD_DECODING[bytes[index] as usize]
+}
+
 /// Provides the core ULID functionality.
 ///
 /// ```rust
@@ -111,6 +214,43 @@ impl ULID {
         Ok(ULID::from(ulid))
     }
 
+    /// Checks that the ULID's embedded timestamp is within the tolerance of the current time, in
+    /// either direction. Malformed or adversarial ULIDs can carry timestamps that are far in the
+    /// future or in the past, which would corrupt time range based logic.
+    pub fn validate_timestamp(&self, tolerance: Duration) -> Result<(), TimestampError> {
+        let now = Utc::now();
+        let datetime = self.datetime();
+        let drift = if datetime > now {
+            datetime.signed_duration_since(now)
+        } else {
+            now.signed_duration_since(datetime)
+        };
+        match chrono::Duration::from_std(tolerance) {
+            Ok(max_drift) if drift > max_drift => Err(TimestampError {
+                datetime,
+                now,
+                tolerance,
+            }),
+            // if the tolerance is out of range, then it is effectively unbounded
+            _ => Ok(()),
+        }
+    }
+
+    /// Strict deserialization, which rejects ULIDs whose embedded timestamp is not within the
+    /// [DEFAULT_TIMESTAMP_TOLERANCE](constant.DEFAULT_TIMESTAMP_TOLERANCE.html) of the current time
+    /// - see [validate_timestamp()](#method.validate_timestamp)
+    /// - the default Deserialize implementation is permissive, i.e., the timestamp is not checked.
+    ///   The strict path is opted into via `#[serde(deserialize_with = "ULID::deserialize_strict")]`
+    pub fn deserialize_strict<'de, D>(deserializer: D) -> Result<ULID, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ulid = ULID::deserialize(deserializer)?;
+        ulid.validate_timestamp(DEFAULT_TIMESTAMP_TOLERANCE)
+            .map_err(de::Error::custom)?;
+        Ok(ulid)
+    }
+
     /// Returns a new ULID with the random part incremented by one.
     /// Overflowing the random part generates a new ULID, i.e., with a new timestamp portion.
     ///
@@ -254,6 +394,35 @@ pub enum DecodingError {
     DataTypeOverflow,
 }
 
+/// The ULID's embedded timestamp is implausibly far from the current time - see [ULID::validate_timestamp()](struct.ULID.html#method.validate_timestamp)
+#[derive(Debug, Clone, Fail)]
+#[fail(
+    display = "ULID timestamp ({}) is more than {:?} from the current time ({})",
+    datetime, tolerance, now
+)]
+pub struct TimestampError {
+    datetime: DateTime<Utc>,
+    now: DateTime<Utc>,
+    tolerance: Duration,
+}
+
+impl TimestampError {
+    /// The ULID's embedded timestamp
+    pub fn datetime(&self) -> DateTime<Utc> {
+        self.datetime
+    }
+
+    /// When the timestamp was checked
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    /// The tolerance that was exceeded
+    pub fn tolerance(&self) -> Duration {
+        self.tolerance
+    }
+}
+
 impl From<rusty_ulid::crockford::DecodingError> for DecodingError {
     fn from(err: rusty_ulid::crockford::DecodingError) -> Self {
         match err {
@@ -466,4 +635,51 @@ mod tests {
         assert_eq!(ulid1, ulid2);
     }
 
+    #[test]
+    fn validate_timestamp() {
+        use chrono::TimeZone;
+
+        #[derive(Debug, Deserialize)]
+        struct Strict(#[serde(deserialize_with = "ULID::deserialize_strict")] ULID);
+
+        let ulid = ULID::generate();
+        assert!(ulid.validate_timestamp(Duration::from_secs(1)).is_ok());
+        let json = serde_json::to_string(&ulid).unwrap();
+        let strict: Strict = serde_json::from_str(&json).unwrap();
+        assert_eq!(strict.0, ulid);
+
+        // the ULID timestamp is stored in the most significant 48 bits, with millisecond granularity
+        let year_3000 = Utc.ymd(3000, 1, 1).and_hms(0, 0, 0);
+        let ulid = ULID::from((year_3000.timestamp_millis() as u128) << 80);
+        assert_eq!(ulid.datetime(), year_3000);
+        let err = ulid
+            .validate_timestamp(DEFAULT_TIMESTAMP_TOLERANCE)
+            .unwrap_err();
+        println!("{}", err);
+        assert_eq!(err.datetime(), year_3000);
+        assert_eq!(err.tolerance(), DEFAULT_TIMESTAMP_TOLERANCE);
+
+        let json = serde_json::to_string(&ulid).unwrap();
+        // the default deserialization is permissive
+        let permissive: ULID = serde_json::from_str(&json).unwrap();
+        assert_eq!(permissive, ulid);
+        // the strict path rejects the ULID
+        let err = serde_json::from_str::<Strict>(&json).unwrap_err();
+        println!("{}", err);
+    }
+
+    #[test]
+    fn const_ulid_u128_round_trip() {
+        const ID: u128 = const_ulid_u128("01D882HDAHNQSE2MXX05WVM06G");
+        assert_eq!(ID, 1879939206585297817630700153261916368);
+
+        for _ in 0..1000 {
+            let ulid = ULID::generate();
+            let ulid_str = ulid.to_string();
+            assert_eq!(const_ulid_u128(&ulid_str), u128::from(ulid));
+            // ULID strings are case insensitive
+            assert_eq!(const_ulid_u128(&ulid_str.to_lowercase()), u128::from(ulid));
+        }
+        assert_eq!(const_ulid_u128("7ZZZZZZZZZZZZZZZZZZZZZZZZZ"), u128::max_value());
+    }
 }